@@ -9,14 +9,19 @@ import re
 import math
 import importlib
 from collections import OrderedDict
-from .predicates import And, Predicate, FieldPredicate  # noqa
+from .predicates import And, Predicate, FieldPredicate, TypePredicate  # noqa
+from . import registers
 
 # The typing module is only required by mypy, and we don't use these imports
 # outside type comments.
 try:
-    from typing import Tuple, Union, Any, Iterable, Sequence  # noqa
+    from typing import Tuple, Union, Any, Iterable, Sequence, Dict, List  # noqa
     MaybeBoundInst = Union['Instruction', 'BoundInstruction']
     AnyPredicate = Union['Predicate', 'FieldPredicate']
+    OperandConstraint = Union[
+            'registers.RegClass', 'registers.Register', 'registers.Stack',
+            'registers.EarlyClobber', int]
+    ConstraintSeq = Union[OperandConstraint, Tuple[OperandConstraint, ...]]
 except ImportError:
     pass
 
@@ -354,15 +359,46 @@ class ImmediateKind(OperandKind):
 
     :param default_member: The default member name of this kind the
                            `InstructionData` data structure.
+    :param values: Dictionary mapping the textual IL representation of
+                   enumerated values to the corresponding Rust enum
+                   variants. Only used for enumerated immediate kinds.
     """
 
-    def __init__(self, name, doc, default_member='imm', rust_type=None):
+    def __init__(
+            self, name, doc,
+            default_member='imm', rust_type=None, values=None):
         super(ImmediateKind, self).__init__(
                 name, doc, default_member, rust_type)
+        self.values = values
 
     def __repr__(self):
         return 'ImmediateKind({})'.format(self.name)
 
+    def __getattr__(self, value):
+        # type: (str) -> Any
+        """
+        Enumerated immediate kinds can be accessed with dot syntax to produce
+        an `ast.Enumerator` node:
+
+        >>> from .immediates import intcc
+        >>> intcc.ult
+        Enumerator(intcc.ult)
+        """
+        if value.startswith('__') or not self.__dict__.get('values'):
+            raise AttributeError(value)
+        if value not in self.values:
+            raise AssertionError(
+                    'No such {} enumerator: {}'.format(self.name, value))
+        from .ast import Enumerator
+        return Enumerator(self, value)
+
+    def rust_enumerator(self, value):
+        # type: (str) -> str
+        """
+        Get the Rust expression for one of the enumerated values.
+        """
+        return '{}::{}'.format(self.rust_type, self.values[value])
+
 
 # Instances of entity reference operand types are provided in the
 # `cretonne.entities` module.
@@ -1054,6 +1090,7 @@ class TargetISA(object):
         self.settings = None
         self.instruction_groups = instruction_groups
         self.cpumodes = list()
+        self.regbanks = list()
 
     def finish(self):
         """
@@ -1067,6 +1104,7 @@ class TargetISA(object):
         """
         self._collect_encoding_recipes()
         self._collect_predicates()
+        self._collect_regclasses()
         return self
 
     def _collect_encoding_recipes(self):
@@ -1110,6 +1148,25 @@ class TargetISA(object):
                 if enc.isap:
                     self.settings.number_predicate(enc.isap)
 
+    def _collect_regclasses(self):
+        """
+        Assign register unit numbers to all register banks, and collect and
+        number all register classes.
+
+        Register banks are laid out in the order they were defined, and the
+        register classes are placed in `self.regclasses` in numerical order.
+        """
+        self.regclasses = list()
+        unit = 0
+        for bank in self.regbanks:
+            bank.first_unit = unit
+            unit += bank.units
+            for rc in bank.classes:
+                rc.index = len(self.regclasses)
+                self.regclasses.append(rc)
+        assert unit <= registers.MAX_UNITS, \
+            "Too many register units in {}".format(self.name)
+
 
 class CPUMode(object):
     """
@@ -1126,6 +1183,8 @@ class CPUMode(object):
         self.name = name
         self.isa = isa
         self.encodings = []
+        # Map ValueType -> XFormGroup for `legalize_type()`.
+        self.type_legalize = OrderedDict()  # type: Dict[ValueType, Any]
         isa.cpumodes.append(self)
 
     def __str__(self):
@@ -1140,6 +1199,24 @@ class CPUMode(object):
         """
         self.encodings.append(Encoding(self, *args, **kwargs))
 
+    def legalize_type(self, **kwargs):
+        # type: (**Any) -> None
+        """
+        Configure the legalization action for instructions whose controlling
+        type variable is supported by this CPU mode, but whose opcode has no
+        encoding for that type.
+
+        By default, such instructions are expanded. Keyword arguments map type
+        names to the `XFormGroup` that should be applied instead:
+
+            RV32.legalize_type(i8=widen, i16=widen)
+
+        Instructions with a controlling type variable that isn't supported by
+        the CPU mode at all are narrowed.
+        """
+        for name, xgrp in kwargs.items():
+            self.type_legalize[ValueType.by_name(name)] = xgrp
+
 
 class EncRecipe(object):
     """
@@ -1148,22 +1225,116 @@ class EncRecipe(object):
     Many different instructions can be encoded by the same recipe, but they
     must all have the same instruction format.
 
+    The `ins` and `outs` arguments are tuples specifying the register
+    allocation constraints for the value operands and results respectively. The
+    possible constraints for an operand are:
+
+    - A `RegClass` specifying the set of allowed registers.
+    - A `Register` specifying a fixed-register operand.
+    - An integer indicating that this result is tied to a value operand, so
+      they must use the same register.
+    - A `Stack` specifying a value in a stack slot.
+    - An `EarlyClobber` result register that can't share a register with any
+      of the value operands.
+
+    The number of constraints in `ins` must match the number of value operands
+    in the instruction format. A single constraint doesn't need to be wrapped
+    in a tuple.
+
+    The `emit` argument is a snippet of Rust code that emits the binary
+    machine code for an instruction using this recipe. It is pasted into a
+    function where these local variables are available:
+
+    - `func` and `inst`, the `Function` and `Inst` being emitted.
+    - `sink`, the `CodeSink` receiving the machine code.
+    - `bits`, the encoding bits of the instruction.
+    - `opcode`, `ty`, and the members of the instruction format, like `imm`.
+    - `in_reg0`, `in_reg1`, ... for value operands with register constraints.
+    - `in_ss0`, ... for value operands with stack constraints.
+    - `out_reg0`, `out_ss0`, ... for the results.
+
+    Branch recipes also specify a `branch_range` as a tuple `(origin, bits)`.
+    The branch displacement is measured in bytes from `origin` bytes into the
+    instruction to the destination EBB, and it must fit in a signed `bits`-bit
+    integer.
+
     :param name: Short mnemonic name for this recipe.
     :param format: All encoded instructions must have this
             :py:class:`InstructionFormat`.
+    :param size: Number of bytes in the binary encoded instruction.
+    :param ins: Tuple of register constraints for value operands.
+    :param outs: Tuple of register constraints for results.
+    :param instp: Instruction predicate.
+    :param isap: ISA predicate.
+    :param branch_range: `(origin, bits)` range for branches.
+    :param emit: Rust code for binary emission.
     """
 
-    def __init__(self, name, format, instp=None, isap=None):
+    def __init__(
+            self, name, format, size, ins, outs,
+            instp=None, isap=None, branch_range=None, emit=None):
+        # type: (str, InstructionFormat, int, ConstraintSeq, ConstraintSeq, AnyPredicate, AnyPredicate, Tuple[int, int], str) -> None # noqa
         self.name = name
         self.format = format
+        assert size >= 0
+        self.size = size
+        self.branch_range = branch_range
         self.instp = instp
         self.isap = isap
+        self.emit = emit
         if instp:
             assert instp.predicate_context() == format
 
+        self.ins = self._verify_constraints(ins)
+        assert len(self.ins) == len(format.value_operands), \
+            "{} needs {} input constraints".format(
+                    name, len(format.value_operands))
+        self.outs = self._verify_constraints(outs)
+        for c in self.outs:
+            if isinstance(c, int):
+                assert c >= 0 and c < len(self.ins), \
+                    "Bad tied operand {} in {}".format(c, name)
+                assert not isinstance(self.ins[c], registers.Stack), \
+                    "Can't tie to stack operand in {}".format(name)
+        for c in self.ins:
+            assert not isinstance(c, registers.EarlyClobber), \
+                "Only results can be early clobbers in {}".format(name)
+
     def __str__(self):
         return self.name
 
+    def _verify_constraints(self, seq):
+        # type: (ConstraintSeq) -> Tuple[OperandConstraint, ...]
+        if not isinstance(seq, tuple):
+            seq = (seq,)
+        for c in seq:
+            if isinstance(c, int):
+                # An integer constraint is bound to a value operand.
+                # Check that it is in range.
+                assert c >= 0 and c < len(self.format.value_operands)
+            else:
+                assert (isinstance(c, registers.RegClass) or
+                        isinstance(c, registers.Register) or
+                        isinstance(c, registers.Stack) or
+                        isinstance(c, registers.EarlyClobber))
+        return seq
+
+    def ties(self):
+        # type: () -> Tuple[Dict[int, int], Dict[int, int]]
+        """
+        Return two dictionaries representing the tied operands.
+
+        The first maps input number to tied output number, the second maps
+        output number to tied input number.
+        """
+        i2o = dict()  # type: Dict[int, int]
+        o2i = dict()  # type: Dict[int, int]
+        for o, i in enumerate(self.outs):
+            if isinstance(i, int):
+                i2o[i] = o
+                o2i[o] = i
+        return (i2o, o2i)
+
 
 class Encoding(object):
     """
@@ -1192,8 +1363,9 @@ class Encoding(object):
                     self.inst.format, recipe.format))
         self.recipe = recipe
         self.encbits = encbits
-        # Combine recipe predicates with the manually specified ones.
-        self.instp = And.combine(recipe.instp, instp)
+        # Combine recipe predicates with the manually specified ones, and check
+        # the types of operands with a secondary type variable.
+        self.instp = And.combine(recipe.instp, instp, *self.type_predicates())
         self.isap = And.combine(recipe.isap, isap)
 
     def __str__(self):
@@ -1208,6 +1380,27 @@ class Encoding(object):
         else:
             return None
 
+    def type_predicates(self):
+        # type: () -> List[TypePredicate]
+        """
+        Get a list of type predicates checking the value operands that are
+        typed by secondary type variables.
+
+        The encoding tables are indexed by the controlling type variable, so
+        the other type variables must be checked by the instruction predicate.
+        """
+        preds = list()  # type: List[TypePredicate]
+        if len(self.typevars) < 2:
+            return preds
+        iform = self.inst.format
+        for opnum, opidx in enumerate(iform.value_operands):
+            tv = self.inst.ins[opidx].typ
+            if tv in self.inst.other_typevars:
+                idx = self.inst.other_typevars.index(tv)
+                preds.append(
+                        TypePredicate(iform, opnum, self.typevars[1 + idx]))
+        return preds
+
 
 # Import the fixed instruction formats now so they can be added to the
 # registry.
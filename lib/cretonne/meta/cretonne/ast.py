@@ -5,10 +5,10 @@ This module defines classes that can be used to create abstract syntax trees
 for patern matching an rewriting of cretonne instructions.
 """
 from __future__ import absolute_import
-from . import Instruction, BoundInstruction
+from . import Instruction, BoundInstruction, ImmediateKind  # noqa
 
 try:
-    from typing import Union, Tuple  # noqa
+    from typing import Union, Tuple, Any  # noqa
 except ImportError:
     pass
 
@@ -197,3 +197,82 @@ class Apply(Expr):
         # type: () -> Tuple[Tuple[Var, ...], Apply]
         """Split into a defs tuple and an Apply expr."""
         return ((), self)
+
+    def rust_builder(self):
+        # type: () -> str
+        """
+        Return a Rust `InstBuilder` method call for instantiating this
+        instruction application.
+
+        >>> from .base import iadd, icmp
+        >>> from .immediates import intcc
+        >>> iadd('x', 'y').rust_builder()
+        'iadd(x, y)'
+        >>> icmp(intcc.ult, 'a', 'x').rust_builder()
+        'icmp(IntCC::UnsignedLessThan, a, x)'
+        """
+        args = [rust_expr(a) for a in self.args]
+        # Do we need to pass an explicit type argument?
+        if self.inst.is_polymorphic and not self.inst.use_typevar_operand:
+            if not self.typevars:
+                raise AssertionError(
+                        '{} needs an explicit controlling type: {}.i32(...)'
+                        .format(self, self.inst.name))
+            args.insert(0, self.typevars[0].rust_name())
+        method = self.inst.name
+        if method == 'return':
+            # Avoid Rust keywords by appending '_'.
+            method += '_'
+        return '{}({})'.format(method, ', '.join(args))
+
+
+class Enumerator(Expr):
+    """
+    A value of an enumerated immediate operand.
+
+    Some immediate operand kinds like `intcc` and `floatcc` have an enumerated
+    range of values corresponding to a Rust enum type. An `Enumerator` object
+    is an AST leaf node representing one of the values.
+
+    `Enumerator` nodes are not usually created directly. They are created by
+    using the dot syntax on immediate kinds: `intcc.ult`.
+
+    :param kind: The enumerated `ImmediateKind` containing the value.
+    :param value: The textual IL representation of the value.
+    """
+
+    def __init__(self, kind, value):
+        # type: (ImmediateKind, str) -> None
+        self.kind = kind
+        self.value = value
+
+    def __str__(self):
+        # type: () -> str
+        return '{}.{}'.format(self.kind, self.value)
+
+    def __repr__(self):
+        # type: () -> str
+        return 'Enumerator({})'.format(self)
+
+    def rust_expression(self):
+        # type: () -> str
+        """Get the Rust expression representing this enumerated value."""
+        return self.kind.rust_enumerator(self.value)
+
+
+def rust_expr(arg):
+    # type: (Any) -> str
+    """
+    Get the Rust expression for an argument in an `Apply` node.
+
+    Variables become references to the Rust local of the same name, and
+    integer immediates are emitted as literals.
+    """
+    if isinstance(arg, Enumerator):
+        return arg.rust_expression()
+    elif isinstance(arg, int) and not isinstance(arg, bool):
+        return str(arg)
+    elif isinstance(arg, (Var, str)):
+        return str(arg)
+    else:
+        raise AssertionError('No Rust expression for {!r}'.format(arg))
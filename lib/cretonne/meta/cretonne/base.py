@@ -9,13 +9,14 @@ from . import Operand, Instruction, InstructionGroup, variable_args
 from .typevar import TypeVar
 from .types import i8, f32, f64, b1
 from .immediates import imm64, uimm8, ieee32, ieee64, immvector, intcc, floatcc
+from .immediates import offset32, memflags, uimm32, atomicop, ordering
 from . import entities
 
 instructions = InstructionGroup("base", "Shared base instruction set")
 
 Int = TypeVar('Int', 'A scalar or vector integer type', ints=True, simd=True)
 iB = TypeVar('iB', 'A scalar integer type', ints=True)
-iPtr = TypeVar('iB', 'An integer address type', ints=(32, 64))
+iPtr = TypeVar('iPtr', 'An integer address type', ints=(32, 64))
 Testable = TypeVar(
         'Testable', 'A scalar boolean or integer type',
         ints=True, bools=True)
@@ -25,6 +26,9 @@ TxN = TypeVar(
 Any = TypeVar(
         'Any', 'Any integer, float, or boolean scalar or vector type',
         ints=True, floats=True, bools=True, scalars=True, simd=True)
+Mem = TypeVar(
+        'Mem', 'Any type that can be stored in memory',
+        ints=True, floats=True, simd=True)
 
 #
 # Control flow
@@ -61,6 +65,30 @@ brnz = Instruction(
         """,
         ins=(c, EBB, args), is_branch=True)
 
+Cond = Operand('Cond', intcc)
+x = Operand('x', iB)
+y = Operand('y', iB)
+
+br_icmp = Instruction(
+        'br_icmp', r"""
+        Compare scalar integers and branch.
+
+        Compare ``x`` and ``y`` in the same way as the :inst:`icmp` instruction
+        and take the branch if the condition is true::
+
+            br_icmp ugt, v1, v2, ebb4(v5, v6)
+
+        is semantically equivalent to::
+
+            v10 = icmp ugt, v1, v2
+            brnz v10, ebb4(v5, v6)
+
+        Some RISC architectures like MIPS and RISC-V provide instructions that
+        implement all or some of the condition codes. The instruction can also
+        be used to represent *macro-op fusion* on architectures like Intel's.
+        """,
+        ins=(Cond, x, y, EBB, args), is_branch=True)
+
 x = Operand('x', iB, doc='index into jump table')
 JT = Operand('JT', entities.jump_table)
 br_table = Instruction(
@@ -237,6 +265,298 @@ fill = Instruction(
         """,
         ins=x, outs=a)
 
+SPOffset = Operand('Offset', imm64, 'Offset from current stack pointer')
+
+adjust_sp_imm = Instruction(
+        'adjust_sp_imm', r"""
+        Adds ``Offset`` to the stack pointer register.
+
+        This instruction is used to allocate and deallocate the stack frame in
+        function prologues and epilogues. It is inserted after register
+        allocation, when the size of the stack frame is known.
+        """,
+        ins=SPOffset)
+
+
+#
+# Memory operations
+#
+
+Flags = Operand('Flags', memflags)
+p = Operand('p', iPtr, doc='Base address')
+Offset = Operand('Offset', offset32, 'Byte offset from base address')
+x = Operand('x', Mem, doc='Value to be stored')
+a = Operand('a', Mem, doc='Value loaded')
+
+load = Instruction(
+        'load', r"""
+        Load from memory at ``p + Offset``.
+
+        This is a polymorphic instruction that can load any value type which
+        has a memory representation.
+        """,
+        ins=(Flags, p, Offset), outs=a)
+
+store = Instruction(
+        'store', r"""
+        Store ``x`` to memory at ``p + Offset``.
+
+        This is a polymorphic instruction that can store any value type with a
+        memory representation.
+        """,
+        ins=(Flags, x, p, Offset))
+
+iExt8 = TypeVar(
+        'iExt8', 'An integer type with more than 8 bits',
+        ints=(16, 64))
+x = Operand('x', iExt8)
+a = Operand('a', iExt8)
+
+uload8 = Instruction(
+        'uload8', r"""
+        Load 8 bits from memory at ``p + Offset`` and zero-extend.
+
+        This is equivalent to ``load.i8`` followed by ``uextend``.
+        """,
+        ins=(Flags, p, Offset), outs=a)
+
+sload8 = Instruction(
+        'sload8', r"""
+        Load 8 bits from memory at ``p + Offset`` and sign-extend.
+
+        This is equivalent to ``load.i8`` followed by ``sextend``.
+        """,
+        ins=(Flags, p, Offset), outs=a)
+
+istore8 = Instruction(
+        'istore8', r"""
+        Store the low 8 bits of ``x`` to memory at ``p + Offset``.
+
+        This is equivalent to ``ireduce.i8`` followed by ``store.i8``.
+        """,
+        ins=(Flags, x, p, Offset))
+
+iExt16 = TypeVar(
+        'iExt16', 'An integer type with more than 16 bits',
+        ints=(32, 64))
+x = Operand('x', iExt16)
+a = Operand('a', iExt16)
+
+uload16 = Instruction(
+        'uload16', r"""
+        Load 16 bits from memory at ``p + Offset`` and zero-extend.
+
+        This is equivalent to ``load.i16`` followed by ``uextend``.
+        """,
+        ins=(Flags, p, Offset), outs=a)
+
+sload16 = Instruction(
+        'sload16', r"""
+        Load 16 bits from memory at ``p + Offset`` and sign-extend.
+
+        This is equivalent to ``load.i16`` followed by ``sextend``.
+        """,
+        ins=(Flags, p, Offset), outs=a)
+
+istore16 = Instruction(
+        'istore16', r"""
+        Store the low 16 bits of ``x`` to memory at ``p + Offset``.
+
+        This is equivalent to ``ireduce.i16`` followed by ``store.i16``.
+        """,
+        ins=(Flags, x, p, Offset))
+
+iExt32 = TypeVar(
+        'iExt32', 'An integer type with more than 32 bits',
+        ints=(64, 64))
+x = Operand('x', iExt32)
+a = Operand('a', iExt32)
+
+uload32 = Instruction(
+        'uload32', r"""
+        Load 32 bits from memory at ``p + Offset`` and zero-extend.
+
+        This is equivalent to ``load.i32`` followed by ``uextend``.
+        """,
+        ins=(Flags, p, Offset), outs=a)
+
+sload32 = Instruction(
+        'sload32', r"""
+        Load 32 bits from memory at ``p + Offset`` and sign-extend.
+
+        This is equivalent to ``load.i32`` followed by ``sextend``.
+        """,
+        ins=(Flags, p, Offset), outs=a)
+
+istore32 = Instruction(
+        'istore32', r"""
+        Store the low 32 bits of ``x`` to memory at ``p + Offset``.
+
+        This is equivalent to ``ireduce.i32`` followed by ``store.i32``.
+        """,
+        ins=(Flags, x, p, Offset))
+
+#
+# Atomic operations.
+#
+
+iAtomic = TypeVar(
+        'iAtomic', 'An integer type that supports atomic memory accesses',
+        ints=(8, 64))
+
+Op = Operand('Op', atomicop)
+Ordering = Operand('Ordering', ordering, 'Memory ordering constraint')
+p = Operand('p', iPtr, doc='Address of the memory location')
+x = Operand('x', iAtomic, doc='Value operand')
+a = Operand('a', iAtomic, doc='Previous value in memory')
+
+atomic_rmw = Instruction(
+        'atomic_rmw', r"""
+        Atomically read-modify-write the memory at ``p``.
+
+        Load the value at ``p``, combine it with ``x`` according to ``Op``, and
+        store the result back to ``p`` as a single atomic operation. The
+        previous value in memory is returned.
+
+        The address ``p`` must be aligned to the size of ``x``.
+        """,
+        ins=(Op, Ordering, p, x), outs=a)
+
+e = Operand('e', iAtomic, doc='Expected value in memory')
+r = Operand('r', iAtomic, doc='Replacement value')
+
+atomic_cas = Instruction(
+        'atomic_cas', r"""
+        Atomic compare-and-swap.
+
+        If the value in memory at ``p`` is equal to ``e``, replace it with
+        ``r``. This is performed as a single atomic operation. The previous
+        value in memory is always returned, so the exchange succeeded if and
+        only if ``a == e``.
+
+        The ordering constraint applies to both the load and the store. When
+        the comparison fails, only the load is performed.
+        """,
+        ins=(Ordering, p, e, r), outs=a)
+
+a = Operand('a', iAtomic, doc='Value loaded')
+
+atomic_load = Instruction(
+        'atomic_load', r"""
+        Atomically load from memory at ``p``.
+
+        The ordering constraint must be one of ``relaxed``, ``acquire``, or
+        ``seq_cst``.
+        """,
+        ins=(Ordering, p), outs=a)
+
+x = Operand('x', iAtomic, doc='Value to be stored')
+
+atomic_store = Instruction(
+        'atomic_store', r"""
+        Atomically store ``x`` to memory at ``p``.
+
+        The ordering constraint must be one of ``relaxed``, ``release``, or
+        ``seq_cst``.
+        """,
+        ins=(Ordering, x, p))
+
+fence = Instruction(
+        'fence', r"""
+        A memory fence.
+
+        Order the memory accesses before and after the fence according to
+        ``Ordering``. A ``relaxed`` fence is not allowed.
+        """,
+        ins=Ordering)
+
+#
+# Stack slots.
+#
+
+SS = Operand('SS', entities.stack_slot)
+Offset = Operand('Offset', offset32, 'In-bounds offset into stack slot')
+x = Operand('x', Mem, doc='Value to be stored')
+a = Operand('a', Mem, doc='Value loaded')
+addr = Operand('addr', iPtr)
+
+stack_load = Instruction(
+        'stack_load', r"""
+        Load a value from a stack slot at the constant offset.
+
+        This is a polymorphic instruction that can load any value type which
+        has a memory representation.
+
+        The offset is an immediate constant, not an SSA value. The memory
+        access cannot go out of bounds, i.e. ``sizeof(a) + Offset <=
+        sizeof(SS)``.
+        """,
+        ins=(SS, Offset), outs=a)
+
+stack_store = Instruction(
+        'stack_store', r"""
+        Store a value to a stack slot at a constant offset.
+
+        This is a polymorphic instruction that can store any value type with a
+        memory representation.
+
+        The offset is an immediate constant, not an SSA value. The memory
+        access cannot go out of bounds, i.e. ``sizeof(a) + Offset <=
+        sizeof(SS)``.
+        """,
+        ins=(x, SS, Offset))
+
+stack_addr = Instruction(
+        'stack_addr', r"""
+        Get the address of a stack slot.
+
+        Compute the absolute address of a byte in a stack slot. The offset must
+        refer to a byte inside the stack slot: ``0 <= Offset < sizeof(SS)``.
+        """,
+        ins=(SS, Offset), outs=addr)
+
+#
+# Global variables and heaps.
+#
+
+GV = Operand('GV', entities.global_var)
+
+global_addr = Instruction(
+        'global_addr', r"""
+        Compute the address of global variable GV.
+        """,
+        ins=GV, outs=addr)
+
+# A specialized form of global_addr instructions that only handles
+# symbolic names.
+globalsym_addr = Instruction(
+        'globalsym_addr', r"""
+        Compute the address of global variable GV, which is a symbolic name.
+
+        The address is materialized by the code generator, typically with a
+        relocation.
+        """,
+        ins=GV, outs=addr)
+
+HeapOffset = TypeVar('HeapOffset', 'An unsigned heap offset', ints=(32, 64))
+H = Operand('H', entities.heap)
+p = Operand('p', HeapOffset)
+Size = Operand('Size', uimm32, 'Size in bytes')
+
+heap_addr = Instruction(
+        'heap_addr', r"""
+        Bounds check and compute absolute address of heap memory.
+
+        Verify that the offset range ``p .. p + Size - 1`` is in bounds for the
+        heap H, and generate an absolute address that is safe to dereference.
+
+        1. If ``p + Size`` is not greater than the heap bound, return an
+           absolute address corresponding to a byte offset of ``p`` from the
+           heap's base address.
+        2. If ``p + Size`` is greater than the heap bound, generate a trap.
+        """,
+        ins=(H, p, Size), outs=addr)
+
 
 #
 # Vector operations
@@ -1003,9 +1323,6 @@ nearest = Instruction(
 # Conversions
 #
 
-Mem = TypeVar(
-        'Mem', 'Any type that can be stored in memory',
-        ints=True, floats=True, simd=True)
 MemTo = TypeVar(
         'MemTo', 'Any type that can be stored in memory',
         ints=True, floats=True, simd=True)
@@ -1210,4 +1527,38 @@ iconcat_lohi = Instruction(
         """,
         ins=(lo, hi), outs=a)
 
+x = Operand('x', TxN, doc='Vector to split')
+lo = Operand('lo', TxN.half_vector(), doc='Low-numbered lanes of `x`')
+hi = Operand('hi', TxN.half_vector(), doc='High-numbered lanes of `x`')
+
+vsplit = Instruction(
+        'vsplit', r"""
+        Split a vector into two halves.
+
+        Split the vector `x` into two separate values, each containing half of
+        the lanes from ``x``. The result may be two scalars if ``x`` only had
+        two lanes.
+        """,
+        ins=x, outs=(lo, hi))
+
+Any128 = TypeVar(
+        'Any128', 'Any scalar or vector type with at most 128 lanes',
+        ints=True, floats=True, bools=True, scalars=True, simd=(1, 128))
+x = Operand('x', Any128, doc='Low-numbered lanes')
+y = Operand('y', Any128, doc='High-numbered lanes')
+a = Operand('a', Any128.double_vector(), doc='Concatenation of `x` and `y`')
+
+vconcat = Instruction(
+        'vconcat', r"""
+        Vector concatenation.
+
+        Return a vector formed by concatenating ``x`` and ``y``. The resulting
+        vector type has twice as many lanes as each of the inputs. The lanes of
+        ``x`` appear as the low-numbered lanes, and the lanes of ``y`` become
+        the high-numbered lanes of ``a``.
+
+        It is possible to form a vector by concatenating two scalars.
+        """,
+        ins=(x, y), outs=a)
+
 instructions.close()
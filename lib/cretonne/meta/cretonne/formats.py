@@ -8,7 +8,9 @@ in this module.
 from __future__ import absolute_import
 from . import InstructionFormat, value, variable_args
 from .immediates import imm64, uimm8, ieee32, ieee64, immvector, intcc, floatcc
-from .entities import ebb, sig_ref, func_ref, jump_table
+from .immediates import offset32, memflags, uimm32, atomicop, ordering
+from .entities import ebb, sig_ref, func_ref, jump_table, stack_slot
+from .entities import global_var, heap
 
 Nullary = InstructionFormat()
 
@@ -41,8 +43,29 @@ ExtractLane = InstructionFormat(value, ('lane', uimm8))
 IntCompare = InstructionFormat(intcc, value, value)
 FloatCompare = InstructionFormat(floatcc, value, value)
 
+Load = InstructionFormat(memflags, value, offset32)
+Store = InstructionFormat(memflags, value, value, offset32)
+
+# Atomic memory operations are controlled by the type of the stored value,
+# not by the address operand.
+AtomicRmw = InstructionFormat(atomicop, ordering, value, value,
+                              typevar_operand=3)
+AtomicCas = InstructionFormat(ordering, value, value, value,
+                              typevar_operand=2)
+AtomicLoad = InstructionFormat(ordering, value)
+AtomicStore = InstructionFormat(ordering, value, value)
+Fence = InstructionFormat(ordering)
+
+StackLoad = InstructionFormat(stack_slot, offset32)
+StackStore = InstructionFormat(value, stack_slot, offset32)
+
+UnaryGlobalVar = InstructionFormat(global_var)
+HeapAddr = InstructionFormat(heap, value, uimm32)
+
 Jump = InstructionFormat(ebb, variable_args, boxed_storage=True)
 Branch = InstructionFormat(value, ebb, variable_args, boxed_storage=True)
+BranchIcmp = InstructionFormat(
+        intcc, value, value, ebb, variable_args, boxed_storage=True)
 BranchTable = InstructionFormat(value, jump_table)
 
 Call = InstructionFormat(
@@ -17,6 +17,21 @@ imm64 = ImmediateKind('imm64', 'A 64-bit immediate integer.')
 #: immediate bit counts on shift instructions.
 uimm8 = ImmediateKind('uimm8', 'An 8-bit immediate unsigned integer.')
 
+#: An unsigned 32-bit immediate integer operand.
+#:
+#: This is used to represent the size of a memory access in the `heap_addr`
+#: instruction.
+uimm32 = ImmediateKind('uimm32', 'A 32-bit immediate unsigned integer.')
+
+#: A 32-bit immediate signed offset.
+#:
+#: This is used to represent an immediate address offset in load/store
+#: instructions.
+offset32 = ImmediateKind(
+        'offset32',
+        'A 32-bit immediate signed offset.',
+        default_member='offset')
+
 #: A 32-bit immediate floating point operand.
 #:
 #: IEEE 754-2008 binary32 interchange format.
@@ -40,7 +55,19 @@ immvector = ImmediateKind(
 intcc = ImmediateKind(
         'intcc',
         'An integer comparison condition code.',
-        default_member='cond', rust_type='IntCC')
+        default_member='cond', rust_type='IntCC',
+        values={
+            'eq':  'Equal',
+            'ne':  'NotEqual',
+            'sge': 'SignedGreaterThanOrEqual',
+            'sgt': 'SignedGreaterThan',
+            'sle': 'SignedLessThanOrEqual',
+            'slt': 'SignedLessThan',
+            'uge': 'UnsignedGreaterThanOrEqual',
+            'ugt': 'UnsignedGreaterThan',
+            'ule': 'UnsignedLessThanOrEqual',
+            'ult': 'UnsignedLessThan',
+        })
 
 #: A condition code for comparing floating point values.
 #:
@@ -49,4 +76,62 @@ intcc = ImmediateKind(
 floatcc = ImmediateKind(
         'floatcc',
         'A floating point comparison condition code.',
-        default_member='cond', rust_type='FloatCC')
+        default_member='cond', rust_type='FloatCC',
+        values={
+            'ord': 'Ordered',
+            'uno': 'Unordered',
+            'eq':  'Equal',
+            'ne':  'NotEqual',
+            'one': 'OrderedNotEqual',
+            'ueq': 'UnorderedOrEqual',
+            'lt':  'LessThan',
+            'le':  'LessThanOrEqual',
+            'gt':  'GreaterThan',
+            'ge':  'GreaterThanOrEqual',
+            'ult': 'UnorderedOrLessThan',
+            'ule': 'UnorderedOrLessThanOrEqual',
+            'ugt': 'UnorderedOrGreaterThan',
+            'uge': 'UnorderedOrGreaterThanOrEqual',
+        })
+
+#: Flags for memory operations like :cton:inst:`load` and :cton:inst:`store`.
+memflags = ImmediateKind(
+        'memflags',
+        'Memory operation flags',
+        default_member='flags', rust_type='MemFlags')
+
+#: The operation performed by an atomic read-modify-write instruction.
+#:
+#: This enumerated operand kind is used for the :cton:inst:`atomic_rmw`
+#: instruction and corresponds to the `atomics::AtomicRmwOp` Rust type.
+atomicop = ImmediateKind(
+        'atomicop',
+        'An atomic read-modify-write operation.',
+        default_member='op', rust_type='AtomicRmwOp',
+        values={
+            'add':  'Add',
+            'and':  'And',
+            'or':   'Or',
+            'xor':  'Xor',
+            'xchg': 'Xchg',
+            'smin': 'Smin',
+            'smax': 'Smax',
+            'umin': 'Umin',
+            'umax': 'Umax',
+        })
+
+#: A memory ordering constraint for atomic memory operations.
+#:
+#: This enumerated operand kind corresponds to the `atomics::AtomicOrdering`
+#: Rust type.
+ordering = ImmediateKind(
+        'ordering',
+        'A memory ordering constraint.',
+        default_member='ordering', rust_type='AtomicOrdering',
+        values={
+            'relaxed': 'Relaxed',
+            'acquire': 'Acquire',
+            'release': 'Release',
+            'acq_rel': 'AcqRel',
+            'seq_cst': 'SeqCst',
+        })
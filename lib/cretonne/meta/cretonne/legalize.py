@@ -7,10 +7,16 @@ patterns that describe how base instructions can be transformed to other base
 instructions that are legal.
 """
 from __future__ import absolute_import
-from .base import iadd, iadd_cout, iadd_cin, iadd_carry
-from .base import isub, isub_bin, isub_bout, isub_borrow
-from .base import bor, isplit_lohi, iconcat_lohi
-from .base import icmp
+from .base import iadd, iadd_imm, iadd_cout, iadd_cin, iadd_carry
+from .base import isub, isub_imm, isub_bin, isub_bout, isub_borrow
+from .base import imul, imul_imm, udiv, sdiv, urem, srem
+from .base import band, bor, bxor, bnot, band_imm, bor_imm, bxor_imm
+from .base import ishl, ushr, sshr, ishl_imm, ushr_imm, sshr_imm
+from .base import iconst, icmp, select, brnz, br_icmp, trapz, trapnz
+from .base import isplit_lohi, iconcat_lohi, uextend, sextend, ireduce
+from .base import load, store, uload8, uload16, istore8, istore16
+from .immediates import intcc
+from .types import i8, i16
 from .ast import Var
 from .xform import Rtl, XFormGroup
 
@@ -24,6 +30,15 @@ narrow = XFormGroup('narrow', """
         operations are expressed in terms of smaller integer types.
         """)
 
+widen = XFormGroup('widen', """
+        Legalize instructions by widening.
+
+        The transformations in the 'widen' group work by expressing
+        instructions in terms of larger types. Small integer operations are
+        expressed in terms of `i32` operations, extending the inputs and
+        truncating the result.
+        """)
+
 expand = XFormGroup('expand', """
         Legalize instructions by expansion.
 
@@ -32,7 +47,10 @@ expand = XFormGroup('expand', """
         """)
 
 x = Var('x')
+x1 = Var('x1')
 y = Var('y')
+y1 = Var('y1')
+y2 = Var('y2')
 a = Var('a')
 a1 = Var('a1')
 a2 = Var('a2')
@@ -50,6 +68,13 @@ yl = Var('yl')
 yh = Var('yh')
 al = Var('al')
 ah = Var('ah')
+cc = Var('cc')
+imm = Var('imm')
+flags = Var('flags')
+ptr = Var('ptr')
+offset = Var('offset')
+ebb = Var('ebb')
+args = Var('args')
 
 narrow.legalize(
         a << iadd(x, y),
@@ -71,41 +96,198 @@ narrow.legalize(
             a << iconcat_lohi(al, ah)
         ))
 
+# Equality comparisons and conditional traps combine the two halves.
+narrow.legalize(
+        a << icmp(intcc.eq, x, y),
+        Rtl(
+            (xl, xh) << isplit_lohi(x),
+            (yl, yh) << isplit_lohi(y),
+            b1 << icmp(intcc.eq, xl, yl),
+            b2 << icmp(intcc.eq, xh, yh),
+            a << band(b1, b2)
+        ))
+
+narrow.legalize(
+        a << icmp(intcc.ne, x, y),
+        Rtl(
+            (xl, xh) << isplit_lohi(x),
+            (yl, yh) << isplit_lohi(y),
+            b1 << icmp(intcc.ne, xl, yl),
+            b2 << icmp(intcc.ne, xh, yh),
+            a << bor(b1, b2)
+        ))
+
+for trap in [trapz, trapnz]:
+    narrow.legalize(
+            trap(x),
+            Rtl(
+                (xl, xh) << isplit_lohi(x),
+                a << bor(xl, xh),
+                trap(a)
+            ))
+
+# Widen small integer operations to `i32`.
+for int_ty in [i8, i16]:
+    # The high bits of the extended inputs don't affect the low bits of the
+    # result for these operations.
+    for binop in [iadd, isub, imul, band, bor, bxor]:
+        widen.legalize(
+                a << binop.bind(int_ty)(x, y),
+                Rtl(
+                    x1 << uextend.i32(x),
+                    y1 << uextend.i32(y),
+                    a1 << binop(x1, y1),
+                    a << ireduce.bind(int_ty)(a1)
+                ))
+
+    for binop in [iadd_imm, imul_imm, band_imm, bor_imm, bxor_imm]:
+        widen.legalize(
+                a << binop.bind(int_ty)(x, imm),
+                Rtl(
+                    x1 << uextend.i32(x),
+                    a1 << binop(x1, imm),
+                    a << ireduce.bind(int_ty)(a1)
+                ))
+
+    widen.legalize(
+            a << isub_imm.bind(int_ty)(imm, y),
+            Rtl(
+                y1 << uextend.i32(y),
+                a1 << isub_imm(imm, y1),
+                a << ireduce.bind(int_ty)(a1)
+            ))
+
+    widen.legalize(
+            a << bnot.bind(int_ty)(x),
+            Rtl(
+                x1 << uextend.i32(x),
+                a1 << bnot(x1),
+                a << ireduce.bind(int_ty)(a1)
+            ))
+
+    widen.legalize(
+            a << iconst.bind(int_ty)(imm),
+            Rtl(
+                a1 << iconst.i32(imm),
+                a << ireduce.bind(int_ty)(a1)
+            ))
+
+    # Division and remainder depend on the high bits, so the inputs must be
+    # extended according to the signedness of the operation.
+    for binop, extend in [
+            (udiv, uextend), (urem, uextend),
+            (sdiv, sextend), (srem, sextend)]:
+        widen.legalize(
+                a << binop.bind(int_ty)(x, y),
+                Rtl(
+                    x1 << extend.i32(x),
+                    y1 << extend.i32(y),
+                    a1 << binop(x1, y1),
+                    a << ireduce.bind(int_ty)(a1)
+                ))
+
+    # Right shifts move the high bits of `x` into the result, so `x` must be
+    # extended to match the shift. The shift amount is masked to the size of
+    # the narrow type.
+    for shift, extend in [
+            (ishl, uextend), (ushr, uextend), (sshr, sextend)]:
+        widen.legalize(
+                a << shift.bind(int_ty)(x, y),
+                Rtl(
+                    x1 << extend.i32(x),
+                    y1 << band_imm(y, int_ty.bits - 1),
+                    a1 << shift(x1, y1),
+                    a << ireduce.bind(int_ty)(a1)
+                ))
+
+    # Immediate shifts are turned into dynamic shifts which are then widened
+    # as above.
+    for shift_imm, shift in [
+            (ishl_imm, ishl), (ushr_imm, ushr), (sshr_imm, sshr)]:
+        widen.legalize(
+                a << shift_imm.bind(int_ty)(x, imm),
+                Rtl(
+                    y1 << iconst.i32(imm),
+                    a << shift(x, y1)
+                ))
+
+    # Sign extension preserves both the signed and the unsigned ordering of
+    # the inputs, so it works for all condition codes.
+    widen.legalize(
+            a << icmp.bind(int_ty)(cc, x, y),
+            Rtl(
+                x1 << sextend.i32(x),
+                y1 << sextend.i32(y),
+                a << icmp(cc, x1, y1)
+            ))
+
+    widen.legalize(
+            br_icmp.bind(int_ty)(cc, x, y, ebb, args),
+            Rtl(
+                x1 << sextend.i32(x),
+                y1 << sextend.i32(y),
+                br_icmp(cc, x1, y1, ebb, args)
+            ))
+
+# Small integers are loaded with an extending load and stored with a
+# truncating store.
+for int_ty, uload, istore in [(i8, uload8, istore8), (i16, uload16, istore16)]:
+    widen.legalize(
+            a << load.bind(int_ty)(flags, ptr, offset),
+            Rtl(
+                a1 << uload.i32(flags, ptr, offset),
+                a << ireduce.bind(int_ty)(a1)
+            ))
+
+    widen.legalize(
+            store.bind(int_ty)(flags, x, ptr, offset),
+            Rtl(
+                x1 << uextend.i32(x),
+                istore(flags, x1, ptr, offset)
+            ))
+
 # Expand integer operations with carry for RISC architectures that don't have
 # the flags.
 expand.legalize(
         (a, c) << iadd_cout(x, y),
         Rtl(
             a << iadd(x, y),
-            c << icmp('ult', a, x)
+            c << icmp(intcc.ult, a, x)
         ))
 
 expand.legalize(
         (a, b) << isub_bout(x, y),
         Rtl(
             a << isub(x, y),
-            b << icmp('ugt', a, x)
+            b << icmp(intcc.ugt, a, x)
         ))
 
+# The carry and borrow flags are `b1` values, so they can't be used directly
+# in integer arithmetic. Select between the adjusted and unadjusted results
+# instead.
 expand.legalize(
         a << iadd_cin(x, y, c),
         Rtl(
             a1 << iadd(x, y),
-            a << iadd(a1, c)
+            a2 << iadd_imm(a1, 1),
+            a << select(c, a2, a1)
         ))
 
 expand.legalize(
         a << isub_bin(x, y, b),
         Rtl(
             a1 << isub(x, y),
-            a << isub(a1, b)
+            a2 << iadd_imm(a1, -1),
+            a << select(b, a2, a1)
         ))
 
 expand.legalize(
         (a, c) << iadd_carry(x, y, c_in),
         Rtl(
             (a1, c1) << iadd_cout(x, y),
-            (a, c2) << iadd_cout(a1, c_in),
+            a2 << iadd_imm(a1, 1),
+            a << select(c_in, a2, a1),
+            c2 << icmp(intcc.ult, a, a1),
             c << bor(c1, c2)
         ))
 
@@ -113,6 +295,36 @@ expand.legalize(
         (a, b) << isub_borrow(x, y, b_in),
         Rtl(
             (a1, b1) << isub_bout(x, y),
-            (a, b2) << isub_bout(a1, b_in),
+            a2 << iadd_imm(a1, -1),
+            a << select(b_in, a2, a1),
+            b2 << icmp(intcc.ugt, a, a1),
             b << bor(b1, b2)
         ))
+
+# Comparisons can be rewritten by swapping the operands. This is used for ISAs
+# like RISC-V that only implement half of the condition codes directly.
+for cond,       swapped in [
+        (intcc.sgt, intcc.slt),
+        (intcc.sle, intcc.sge),
+        (intcc.ugt, intcc.ult),
+        (intcc.ule, intcc.uge)
+        ]:
+    expand.legalize(
+            a << icmp(cond, x, y),
+            Rtl(
+                a << icmp(swapped, y, x)
+            ))
+
+    expand.legalize(
+            br_icmp(cond, x, y, ebb, args),
+            Rtl(
+                br_icmp(swapped, y, x, ebb, args)
+            ))
+
+# Expand the fused compare and branch for ISAs that don't have it.
+expand.legalize(
+        br_icmp(cc, x, y, ebb, args),
+        Rtl(
+            a << icmp(cc, x, y),
+            brnz(a, ebb, args)
+        ))
@@ -9,7 +9,8 @@ function determine the kind of predicate:
   has been configured, the value of all ISA predicates is known.
 
 - An *Instruction predicate* is evaluated on an instruction instance, so it can
-  inspect all the immediate fields and type variables of the instruction.
+  inspect all the immediate fields and type variables of the instruction, as
+  well as the types of its value operands.
   Instruction predicates can be evaluatd before register allocation, so they
   can not depend on specific register assignments to the value operands or
   outputs.
@@ -186,6 +187,12 @@ class FieldPredicate(object):
     def predicate_leafs(self, leafs):
         leafs.add(self)
 
+    def field_name(self):
+        """
+        Get the name of the `InstructionData` member being tested.
+        """
+        return self.field.name
+
     def rust_predicate(self, prec):
         """
         Return a string of Rust code that evaluates this predicate.
@@ -238,3 +245,96 @@ class IsUnsignedInt(FieldPredicate):
         self.scale = scale
         assert width >= 0 and width <= 64
         assert scale >= 0 and scale < width
+
+
+class IsLogicalImm(FieldPredicate):
+    """
+    Instruction predicate that checks if an immediate instruction format field
+    can be encoded as an ARM64 logical immediate when truncated to `width`
+    bits.
+
+    :param field: `FormatField` to be checked.
+    :param width: Number of bits in the operation, 32 or 64.
+
+    The predicate is true if the low `width` bits of the field consist of a
+    repeating element of 2, 4, 8, 16, 32, or 64 bits, where the element is a
+    rotated run of ones. This excludes all zeros and all ones.
+    """
+
+    def __init__(self, field, width):
+        super(IsLogicalImm, self).__init__(
+                field, 'is_logical_imm', (width,))
+        self.width = width
+        assert width in (32, 64)
+
+
+class IsEqual(FieldPredicate):
+    """
+    Instruction predicate that checks if an immediate instruction format field
+    is equal to a constant value.
+
+    :param field: `FormatField` to be checked.
+    :param value: The constant value to compare against. This can be an
+                  integer or an `Enumerator` like `intcc.slt`.
+    """
+
+    def __init__(self, field, value):
+        if not isinstance(value, int):
+            value = value.rust_expression()
+        super(IsEqual, self).__init__(field, 'is_equal', (value,))
+        self.value = value
+
+
+class TypePredicate(object):
+    """
+    An instruction predicate that checks the type of a value operand.
+
+    The encoding tables are indexed by the controlling type variable only.
+    Type predicates are used to distinguish the encodings of instructions with
+    secondary type variables, like `fcvt_from_sint.f32.i64`.
+
+    :param iform: The `InstructionFormat` of the instruction to be checked.
+    :param opnum: Index of the value operand to check, counting only the
+                  value operands of the format.
+    :param value_type: The required `ValueType` of the operand.
+    """
+
+    def __init__(self, iform, opnum, value_type):
+        assert opnum < len(iform.value_operands)
+        self.iform = iform
+        self.opnum = opnum
+        self.value_type = value_type
+
+    def __str__(self):
+        return 'args[{}]:{}'.format(self.opnum, self.value_type)
+
+    def predicate_context(self):
+        """
+        This predicate can be evaluated in the context of an instruction
+        format.
+        """
+        return self.iform
+
+    def predicate_leafs(self, leafs):
+        leafs.add(self)
+
+    def field_name(self):
+        """
+        Get the name of the `InstructionData` member holding the operand.
+        """
+        if len(self.iform.value_operands) == 1:
+            return 'arg'
+        else:
+            return 'args'
+
+    def rust_predicate(self, prec):
+        """
+        Return a string of Rust code that evaluates this predicate.
+        """
+        arg = self.field_name()
+        if arg == 'args':
+            arg = 'args[{}]'.format(self.opnum)
+        if self.iform.boxed_storage:
+            arg = 'data.' + arg
+        return 'dfg.value_type({}) == {}'.format(
+                arg, self.value_type.rust_name())
@@ -0,0 +1,226 @@
+"""
+Register set definitions
+------------------------
+
+Each ISA defines a separate register set that is used by the register allocator
+and the final binary encoding of machine code.
+
+The CPU registers are first divided into disjoint register banks, represented
+by a `RegBank` instance. Registers in different register banks never interfere
+with each other. A typical CPU will have a general purpose and a floating point
+register bank.
+
+A register bank consists of a number of *register units* which are the smallest
+indivisible units of allocation and interference. A register unit doesn't
+necessarily correspond to a particular number of bits in a register, it is
+more like a placeholder that can be used to determine if a register is taken or
+not.
+
+The register allocator works with *register classes* which can allocate one or
+more register units at a time. A register class allocates more than one
+register unit at a time when its registers are composed of smaller allocatable
+units. For example, the ARM double precision floating point registers are
+composed of two single precision registers.
+"""
+from __future__ import absolute_import
+
+try:
+    from typing import Sequence, List  # noqa
+    from cretonne import TargetISA  # noqa
+except ImportError:
+    pass
+
+
+# The number of 32-bit elements in a register unit mask
+MASK_LEN = 3
+
+# The maximum total number of register units supported by a mask.
+MAX_UNITS = 32 * MASK_LEN
+
+
+class RegBank(object):
+    """
+    A register bank belonging to an ISA.
+
+    A register bank controls a set of *register units* disjoint from all the
+    other register banks in the ISA. The register units are numbered uniquely
+    within the target ISA, and the units in a register bank form a contiguous
+    sequence. The banks are laid out in the order they are defined.
+
+    Register units can be given generated names like `r0`, `r1`, ..., or a
+    tuple of special register unit names can be provided.
+
+    :param name: Name of this register bank.
+    :param isa: The target ISA this register bank belongs to.
+    :param doc: Documentation string.
+    :param units: Number of register units.
+    :param prefix: Prefix for generated unit names.
+    :param names: Special names for the first units. May be shorter than
+                  `units`, the remaining units are named using `prefix`.
+    """
+
+    def __init__(self, name, isa, doc, units, prefix='r', names=()):
+        # type: (str, TargetISA, str, int, str, Sequence[str]) -> None
+        self.name = name
+        self.isa = isa
+        self.first_unit = 0
+        self.units = units
+        self.prefix = prefix
+        self.names = names
+        self.classes = list()  # type: List[RegClass]
+
+        assert len(names) <= units
+
+        isa.regbanks.append(self)
+
+    def __repr__(self):
+        # type: () -> str
+        return ('RegBank({}, units={}, first_unit={})'
+                .format(self.name, self.units, self.first_unit))
+
+
+class RegClass(object):
+    """
+    A register class is a subset of register units in a RegBank along with a
+    strategy for allocating registers.
+
+    The *width* parameter determines how many register units are allocated at a
+    time. Usually that is one, but for example the ARM D registers are
+    allocated two units at a time. When multiple units are allocated, it is
+    always a contiguous set of unit numbers.
+
+    :param name: Name of this register class.
+    :param bank: The register bank we're allocating from.
+    :param count: The maximum number of allocations in this register class. By
+                  default, the whole register bank can be allocated.
+    :param width: How many units to allocate at a time.
+    :param start: The first unit to allocate, relative to `bank.first_unit`.
+    """
+
+    def __init__(self, name, bank, count=None, width=1, start=0):
+        # type: (str, RegBank, int, int, int) -> None
+        self.name = name
+        self.index = None  # type: int
+        self.bank = bank
+        self.start = start
+        self.width = width
+
+        assert width > 0
+        assert start >= 0 and start < bank.units
+
+        if count is None:
+            count = bank.units // width
+        self.count = count
+
+        bank.classes.append(self)
+
+    def __str__(self):
+        # type: () -> str
+        return self.name
+
+    def __getitem__(self, idx):
+        # type: (int) -> Register
+        """
+        Get a specific register in the class.
+
+        `GPR[10]` is the register with offset 10 in the `GPR` class.
+        """
+        assert idx >= 0 and idx < self.count, \
+            "No register {} in {}".format(idx, self)
+        return Register(self, idx)
+
+    def mask(self):
+        # type: () -> List[int]
+        """
+        Compute a bit-mask of the register units allocated by this register
+        class.
+
+        Return as a list of 32-bit integers.
+        """
+        mask = [0] * MASK_LEN
+
+        start = self.bank.first_unit + self.start
+        for a in range(self.count):
+            u = start + a * self.width
+            mask[u // 32] |= 1 << (u % 32)
+
+        return mask
+
+
+class Register(object):
+    """
+    A specific register in a register class.
+
+    A register is identified by a register class and an offset into the class.
+    Registers are normally created by indexing a register class: `GPR[10]`.
+
+    Registers can be used as operand constraints in encoding recipes when an
+    instruction requires an operand in a fixed register.
+
+    :param regclass: Register class containing the register.
+    :param offset: Offset of the register in the class, counting allocations
+                   of `regclass.width` units.
+    """
+
+    def __init__(self, regclass, offset):
+        # type: (RegClass, int) -> None
+        self.regclass = regclass
+        self.offset = offset
+
+    def __str__(self):
+        # type: () -> str
+        return '{}[{}]'.format(self.regclass, self.offset)
+
+    def unit(self):
+        # type: () -> int
+        """
+        Get the register unit number of this register.
+
+        Register units are only numbered after the ISA has been finished.
+        """
+        rc = self.regclass
+        return rc.bank.first_unit + rc.start + self.offset * rc.width
+
+
+class Stack(object):
+    """
+    An operand that must be in a stack slot.
+
+    A `Stack` object can be used as an operand constraint in an encoding
+    recipe when an instruction reads or writes the operand directly from
+    memory. The register class indicates which register values can be spilled
+    to the stack slot.
+
+    :param regclass: Register class of values that are spilled to the slot.
+    """
+
+    def __init__(self, regclass):
+        # type: (RegClass) -> None
+        self.regclass = regclass
+
+    def __str__(self):
+        # type: () -> str
+        return 'Stack({})'.format(self.regclass)
+
+
+
+class EarlyClobber(object):
+    """
+    A result that is written before all the operands have been read.
+
+    An `EarlyClobber` object can be used as a result constraint in an encoding
+    recipe that expands to a sequence of machine instructions where the result
+    register is written before the last use of an input operand. The register
+    allocator makes sure that the result doesn't share a register with any of
+    the instruction's value operands.
+
+    :param regclass: Register class of the result.
+    """
+
+    def __init__(self, regclass):
+        # type: (RegClass) -> None
+        self.regclass = regclass
+
+    def __str__(self):
+        # type: () -> str
+        return 'EarlyClobber({})'.format(self.regclass)
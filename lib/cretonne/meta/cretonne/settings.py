@@ -18,6 +18,15 @@ opt_level = EnumSetting(
         """,
         'default', 'best', 'fastest')
 
+enable_verifier = BoolSetting(
+        """
+        Run the Cretonne IL verifier at strategic times during compilation.
+
+        This makes compilation slower but catches many bugs. Disable it to
+        speed up compilation of trusted input.
+        """,
+        default=True)
+
 is_64bit = BoolSetting("Enable 64-bit code generation")
 
 enable_float = BoolSetting(
@@ -62,3 +62,16 @@ class TestTypeVar(TestCase):
         self.assertEqual(str(x3.double_width()), '`DoubleWidth(x3)`')
         with self.assertRaises(AssertionError):
             x3.half_width()
+
+    def test_vector_functions(self):
+        x = TypeVar('x', 'all vectors', ints=True, simd=True)
+        with self.assertRaises(AssertionError):
+            x.half_vector()
+        with self.assertRaises(AssertionError):
+            x.double_vector()
+
+        x2 = TypeVar('x2', 'vectors only', ints=True, scalars=False, simd=True)
+        self.assertEqual(str(x2.half_vector()), '`HalfVector(x2)`')
+
+        x3 = TypeVar('x3', 'up to 128 lanes', ints=True, simd=(1, 128))
+        self.assertEqual(str(x3.double_vector()), '`DoubleVector(x3)`')
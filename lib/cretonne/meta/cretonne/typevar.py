@@ -314,6 +314,26 @@ class TypeVar(object):
 
         return TypeVar(None, None, base=self, derived_func='DoubleWidth')
 
+    def half_vector(self):
+        # type: () -> TypeVar
+        """
+        Return a derived type variable that has half the number of vector lanes
+        as this one, with the same lane type.
+        """
+        ts = self.type_set
+        assert ts.min_lanes > 1, "Can't halve a scalar type"
+        return TypeVar(None, None, base=self, derived_func='HalfVector')
+
+    def double_vector(self):
+        # type: () -> TypeVar
+        """
+        Return a derived type variable that has twice the number of vector
+        lanes as this one, with the same lane type.
+        """
+        ts = self.type_set
+        assert ts.max_lanes < MAX_LANES, "Can't double 256 lanes."
+        return TypeVar(None, None, base=self, derived_func='DoubleVector')
+
     def operand_kind(self):
         # type: () -> OperandKind
         # When a `TypeVar` object is used to describe the type of an `Operand`
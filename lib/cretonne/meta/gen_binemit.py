@@ -0,0 +1,152 @@
+"""
+Generate binary emission code for each ISA.
+
+Every encoding recipe has a snippet of Rust code that emits the machine code
+for instructions using the recipe. We generate a function for each recipe
+which unpacks the instruction operands and the value locations assigned by the
+register allocator, and then runs the recipe's emission code.
+
+The generated `emit_inst()` function dispatches on the recipe number of an
+instruction's encoding.
+"""
+from __future__ import absolute_import
+import re
+from textwrap import dedent
+import srcgen
+from cretonne.registers import RegClass, Register, Stack, EarlyClobber
+
+try:
+    from typing import Sequence  # noqa
+    from cretonne import TargetISA, EncRecipe, OperandConstraint  # noqa
+except ImportError:
+    pass
+
+
+def uses(name, code):
+    # type: (str, str) -> bool
+    """Does the Rust `code` snippet use the identifier `name`?"""
+    return re.search(r'\b{}\b'.format(name), code) is not None
+
+
+def unwrap_locations(prefix, values, constraints, emit, fmt):
+    # type: (str, Sequence[str], Sequence[OperandConstraint], str, srcgen.Formatter) -> None # noqa
+    """
+    Emit `let` bindings for the value locations used by `emit`.
+
+    :param prefix: Either `in` or `out`.
+    :param values: Rust expressions for the values.
+    :param constraints: Operand constraints for the values.
+    """
+    for i, (value, cons) in enumerate(zip(values, constraints)):
+        if isinstance(cons, Stack):
+            name = '{}_ss{}'.format(prefix, i)
+            method = 'unwrap_stack'
+        else:
+            assert isinstance(
+                    cons, (RegClass, Register, EarlyClobber, int))
+            name = '{}_reg{}'.format(prefix, i)
+            method = 'unwrap_reg'
+        if uses(name, emit):
+            fmt.line(
+                    'let {} = func.locations[{}].{}();'
+                    .format(name, value, method))
+
+
+def gen_recipe(recipe, fmt):
+    # type: (EncRecipe, srcgen.Formatter) -> None
+    """
+    Generate the emission function for a single recipe.
+
+    The instruction is matched against the recipe's instruction format, and
+    only the fields and value locations used by the emission code are
+    unpacked.
+    """
+    iform = recipe.format
+    emit = dedent(recipe.emit).strip()
+    nvops = len(iform.value_operands)
+
+    # Fields to unpack from the instruction data.
+    if iform.boxed_storage:
+        fields = ['ref data']
+        args = ['data.arg'] if nvops == 1 else [
+                'data.args[{}]'.format(i) for i in range(nvops)]
+    else:
+        fields = [m for m in iform.members if m]
+        if nvops == 1:
+            fields.append('arg')
+            args = ['arg']
+        elif nvops > 1:
+            fields.append('args')
+            args = ['args[{}]'.format(i) for i in range(nvops)]
+        else:
+            args = []
+    fields = ['opcode', 'ty'] + fields
+
+    fmt.doc_comment('Emit binary machine code for an instruction using the '
+                    '`{}` recipe.'.format(recipe.name))
+    # Recipes like a no-op conversion don't emit any code.
+    sink = 'sink' if uses('sink', emit) else '_sink'
+    with fmt.indented(
+            'fn recipe_{}<CS: CodeSink + ?Sized>'
+            '(func: &Function, inst: Inst, {}: &mut CS) {{'
+            .format(recipe.name.lower(), sink), '}'):
+        # Only unpack the fields that are actually used by the snippet. The
+        # value operands are also needed to look up their locations.
+        needed = set(f for f in fields if uses(f.split()[-1], emit))
+        for i in range(nvops):
+            if (uses('in_reg{}'.format(i), emit) or
+                    uses('in_ss{}'.format(i), emit)):
+                needed.add(fields[-1])
+        pattern = ', '.join([f for f in fields if f in needed] + ['..'])
+        with fmt.indented(
+                'if let InstructionData::{} {{ {} }} = func.dfg[inst] {{'
+                .format(iform.name, pattern), '}'):
+            if uses('bits', emit):
+                fmt.line('let bits = func.encodings[inst].bits();')
+            unwrap_locations('in', args, recipe.ins, emit, fmt)
+            if len(recipe.outs) == 1:
+                results = ['func.dfg.first_result(inst)']
+            else:
+                results = [
+                        'func.dfg.inst_results(inst).nth({}).unwrap()'
+                        .format(i) for i in range(len(recipe.outs))]
+            unwrap_locations('out', results, recipe.outs, emit, fmt)
+            for line in emit.splitlines():
+                fmt.line(line)
+            fmt.line('return;')
+        fmt.line('bad_encoding(func, inst);')
+
+
+def gen_isa(isa, fmt):
+    # type: (TargetISA, srcgen.Formatter) -> None
+    """
+    Generate binary emission code for `isa`.
+    """
+    fmt.doc_comment(
+            'Emit binary machine code for `inst` for the {} ISA.'
+            .format(isa.name))
+    with fmt.indented(
+            'pub fn emit_inst<CS: CodeSink + ?Sized>'
+            '(func: &Function, inst: Inst, sink: &mut CS) {', '}'):
+        if not isa.all_recipes:
+            fmt.line('bad_encoding(func, inst);')
+            return
+        with fmt.indented('match func.encodings[inst].recipe() {', '}'):
+            for recipe in isa.all_recipes:
+                if recipe.emit is not None:
+                    fmt.line('{} => recipe_{}(func, inst, sink),'.format(
+                        recipe.number, recipe.name.lower()))
+            fmt.line('_ => bad_encoding(func, inst),')
+
+    for recipe in isa.all_recipes:
+        if recipe.emit is not None:
+            fmt.line()
+            gen_recipe(recipe, fmt)
+
+
+def generate(isas, out_dir):
+    # type: (Sequence[TargetISA], str) -> None
+    for isa in isas:
+        fmt = srcgen.Formatter()
+        gen_isa(isa, fmt)
+        fmt.update_file('binemit-{}.rs'.format(isa.name), out_dir)
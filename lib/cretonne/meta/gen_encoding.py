@@ -6,8 +6,8 @@ function which determines if a given instruction is legal, and if so, it's
 `Encoding` data which consists of a *recipe* and some *encoding* bits.
 
 The `encode` function doesn't actually generate the binary machine bits. Each
-recipe has a corresponding hand-written function to do that after registers
-are allocated.
+recipe has a snippet of Rust code to do that after registers are allocated.
+See `gen_binemit.py`.
 
 This is the information available to us:
 
@@ -24,6 +24,12 @@ The CPU mode provides the first table. The key is the instruction's controlling
 type variable. If the instruction is not polymorphic, use `VOID` for the type
 variable. The table values are level 2 tables.
 
+If the controlling type variable is missing from the level 1 table, the
+instruction must be narrowed. Each level 1 entry also records the legalization
+action to take when the opcode is missing from the level 2 table. This is
+configured with `CPUMode.legalize_type()` and defaults to expanding the
+instruction.
+
 ## Level 2 table lookup
 
 The level 2 table is keyed by the instruction's opcode. The table values are
@@ -51,6 +57,8 @@ instructions with different types for secondary type variables.
 """
 from __future__ import absolute_import
 import srcgen
+from cretonne import camel_case
+from cretonne.registers import RegClass, Register, Stack, EarlyClobber
 from constant_hash import compute_quadratic
 from unique_table import UniqueSeqTable
 from collections import OrderedDict, defaultdict
@@ -61,7 +69,8 @@ import itertools
 def emit_instp(instp, fmt):
     """
     Emit code for matching an instruction predicate against an
-    `InstructionData` reference called `inst`.
+    `InstructionData` reference called `inst`. The types of value operands are
+    looked up in a `DataFlowGraph` reference called `dfg`.
 
     The generated code is a pattern match that falls through if the instruction
     has an unexpected format. This should lead to a panic.
@@ -75,9 +84,9 @@ def emit_instp(instp, fmt):
         # Collect the leaf predicates
         leafs = set()
         instp.predicate_leafs(leafs)
-        # All the leafs are FieldPredicate instances. Here we just care about
-        # the field names.
-        fields = ', '.join(sorted(set(p.field.name for p in leafs)))
+        # All the leafs are FieldPredicate or TypePredicate instances. Here we
+        # just care about the field names.
+        fields = ', '.join(sorted(set(p.field_name() for p in leafs)))
 
     with fmt.indented('{} => {{'.format(instp.number), '}'):
         with fmt.indented(
@@ -92,8 +101,8 @@ def emit_instps(instps, fmt):
     """
 
     with fmt.indented(
-            'pub fn check_instp(inst: &InstructionData, instp_idx: u16) ' +
-            '-> bool {', '}'):
+            'pub fn check_instp(inst: &InstructionData, instp_idx: u16, ' +
+            'dfg: &DataFlowGraph) -> bool {', '}'):
         with fmt.indented('match instp_idx {', '}'):
             for instp in instps:
                 emit_instp(instp, fmt)
@@ -184,7 +193,7 @@ class EncList(object):
             # This probably never happens, but we can't express more than
             # maxlen encodings per isap.
             while len(group) > maxlen:
-                yield (isap, group[0..maxlen])
+                yield (isap, group[0:maxlen])
                 group = group[maxlen:]
             yield (isap, group)
 
@@ -232,13 +241,24 @@ class Level2Table(object):
     Level 2 table mapping instruction opcodes to `EncList` objects.
 
     :param ty: Controlling type variable of all entries, or `None`.
+    :param legalize: `XFormGroup` to apply to instructions with opcodes
+                     missing from this table, or `None` to expand them.
     """
 
-    def __init__(self, ty):
+    def __init__(self, ty, legalize=None):
         self.ty = ty
+        self.legalize = legalize
         # Maps inst -> EncList
         self.lists = OrderedDict()
 
+    def type_name(self):
+        """Name of the controlling type, `void` for non-polymorphic."""
+        return self.ty.name if self.ty else 'void'
+
+    def type_number(self):
+        """Type number used as a level 1 hash key. `VOID` is 0."""
+        return self.ty.number if self.ty else 0
+
     def __getitem__(self, inst):
         ls = self.lists.get(inst)
         if not ls:
@@ -258,6 +278,11 @@ class Level2Table(object):
         hash_table = compute_quadratic(
                 self.lists.values(),
                 lambda enclist: enclist.inst.number)
+        # An empty level 1 entry is encoded with a 0 `log2len`, so level 2
+        # tables need at least two entries. This only matters for types that
+        # have a legalization action, but no encodings.
+        if len(hash_table) < 2:
+            hash_table.append(None)
 
         self.hash_table_offset = len(level2_hashtables)
         self.hash_table_len = len(hash_table)
@@ -265,7 +290,7 @@ class Level2Table(object):
         level2_doc[self.hash_table_offset].append(
                 '{:06x}: {}, {} entries'.format(
                     self.hash_table_offset,
-                    self.ty.name,
+                    self.type_name(),
                     self.hash_table_len))
         level2_hashtables.extend(hash_table)
 
@@ -273,15 +298,18 @@ class Level2Table(object):
 class Level1Table(object):
     """
     Level 1 table mapping types to `Level2` objects.
+
+    :param cpumode: CPU mode providing the per-type legalization actions.
     """
 
-    def __init__(self):
+    def __init__(self, cpumode):
+        self.cpumode = cpumode
         self.tables = OrderedDict()
 
     def __getitem__(self, ty):
         tbl = self.tables.get(ty)
         if not tbl:
-            tbl = Level2Table(ty)
+            tbl = Level2Table(ty, self.cpumode.type_legalize.get(ty))
             self.tables[ty] = tbl
         return tbl
 
@@ -293,11 +321,15 @@ def make_tables(cpumode):
     """
     Generate tables for `cpumode` as described above.
     """
-    table = Level1Table()
+    table = Level1Table(cpumode)
     for enc in cpumode.encodings:
         ty = enc.ctrl_typevar()
         inst = enc.inst
         table[ty][inst].encodings.append(enc)
+    # Make sure there are level 1 entries for all the types with a custom
+    # legalization action.
+    for ty in cpumode.type_legalize:
+        table[ty]
     return table
 
 
@@ -361,7 +393,7 @@ def emit_level1_hashtable(cpumode, level1, offt, fmt):
     """
     hash_table = compute_quadratic(
             level1.tables.values(),
-            lambda level2: level2.ty.number)
+            lambda level2: level2.type_number())
 
     with fmt.indented(
             'pub static LEVEL1_{}: [Level1Entry<{}>; {}] = ['
@@ -370,18 +402,24 @@ def emit_level1_hashtable(cpumode, level1, offt, fmt):
             if level2:
                 l2l = int(math.log(level2.hash_table_len, 2))
                 assert l2l > 0, "Hash table too small"
+                if level2.legalize:
+                    legalize = camel_case(level2.legalize.name)
+                else:
+                    legalize = 'Expand'
                 fmt.line(
-                        'Level1Entry ' +
-                        '{{ ty: types::{}, log2len: {}, offset: {:#08x} }},'
+                        ('Level1Entry {{ ty: types::{}, log2len: {}, ' +
+                         'legalize: Legalize::{}, offset: {:#08x} }},')
                         .format(
-                            level2.ty.name.upper(),
+                            level2.type_name().upper(),
                             l2l,
+                            legalize,
                             level2.hash_table_offset))
             else:
                 # Empty entry.
                 fmt.line(
                         'Level1Entry ' +
-                        '{ ty: types::VOID, log2len: 0, offset: 0 },')
+                        '{ ty: types::VOID, log2len: 0, ' +
+                        'legalize: Legalize::Narrow, offset: 0 },')
 
 
 def offset_type(length):
@@ -409,6 +447,82 @@ def emit_recipe_names(isa, fmt):
             fmt.line('"{}",'.format(r.name))
 
 
+def emit_recipe_constraints(isa, fmt):
+    """
+    Emit a table of encoding recipe operand constraints keyed by recipe number.
+
+    These are used by the register allocator to pick registers that can be
+    properly encoded.
+    """
+    with fmt.indented(
+            'pub static RECIPE_CONSTRAINTS: [RecipeConstraints; {}] = ['
+            .format(len(isa.all_recipes)), '];'):
+        for r in isa.all_recipes:
+            fmt.comment(r.name)
+            with fmt.indented('RecipeConstraints {', '},'):
+                emit_operand_constraints(r, r.ins, 'ins', fmt)
+                emit_operand_constraints(r, r.outs, 'outs', fmt)
+
+
+def emit_recipe_sizing(isa, fmt):
+    """
+    Emit a table of encoding recipe code size information.
+    """
+    with fmt.indented(
+            'pub static RECIPE_SIZING: [RecipeSizing; {}] = ['
+            .format(len(isa.all_recipes)), '];'):
+        for r in isa.all_recipes:
+            fmt.comment(r.name)
+            with fmt.indented('RecipeSizing {', '},'):
+                fmt.format('bytes: {},', r.size)
+                if r.branch_range:
+                    fmt.format(
+                        'branch_range: '
+                        'Some(BranchRange {{ origin: {}, bits: {} }}),',
+                        *r.branch_range)
+                else:
+                    fmt.line('branch_range: None,')
+
+
+def emit_operand_constraints(recipe, seq, field, fmt):
+    """
+    Emit a struct field initializer for an array of operand constraints.
+    """
+    if len(seq) == 0:
+        fmt.line('{}: &[],'.format(field))
+        return
+    with fmt.indented('{}: &['.format(field), '],'):
+        for cons in seq:
+            with fmt.indented('OperandConstraint {', '},'):
+                if isinstance(cons, RegClass):
+                    fmt.line('kind: ConstraintKind::Reg,')
+                    fmt.line('regclass: {},'.format(cons))
+                elif isinstance(cons, Register):
+                    fmt.line(
+                            'kind: ConstraintKind::FixedReg({}),'
+                            .format(cons.unit()))
+                    fmt.line('regclass: {},'.format(cons.regclass))
+                elif isinstance(cons, int):
+                    # This is a tied output constraint. It should never happen
+                    # for input constraints.
+                    assert field == 'outs'
+                    tied = recipe.ins[cons]
+                    if isinstance(tied, Register):
+                        tied = tied.regclass
+                    fmt.line('kind: ConstraintKind::Tied({}),'.format(cons))
+                    fmt.line('regclass: {},'.format(tied))
+                elif isinstance(cons, Stack):
+                    fmt.line('kind: ConstraintKind::Stack,')
+                    fmt.line('regclass: {},'.format(cons.regclass))
+                elif isinstance(cons, EarlyClobber):
+                    assert field == 'outs'
+                    fmt.line('kind: ConstraintKind::EarlyClobber,')
+                    fmt.line('regclass: {},'.format(cons.regclass))
+                else:
+                    raise AssertionError(
+                            'Unsupported constraint {}'.format(cons))
+
+
 def gen_isa(isa, fmt):
     # First assign numbers to relevant instruction predicates and generate the
     # check_instp() function..
@@ -444,6 +558,8 @@ def gen_isa(isa, fmt):
                 cpumode, level1_tables[cpumode], level1_offt, fmt)
 
     emit_recipe_names(isa, fmt)
+    emit_recipe_constraints(isa, fmt)
+    emit_recipe_sizing(isa, fmt)
 
 
 def generate(isas, out_dir):
@@ -9,27 +9,41 @@ the input instruction.
 """
 from __future__ import absolute_import
 from srcgen import Formatter
+from cretonne import variable_args
 import cretonne.legalize as legalize
-from cretonne.ast import Def, Apply  # noqa
+from cretonne.ast import Def, Var, Enumerator  # noqa
 from cretonne.xform import XForm, XFormGroup  # noqa
 
 try:
-    from typing import Union
-    DefApply = Union[Def, Apply]
+    from typing import Sequence, Set, List  # noqa
 except ImportError:
     pass
 
 
+def wrap_tup(seq):
+    # type: (Sequence[str]) -> str
+    """Format a sequence of Rust patterns or expressions as a tuple."""
+    if len(seq) == 1:
+        return seq[0]
+    else:
+        return '({})'.format(', '.join(seq))
+
+
 def unwrap_inst(iref, node, fmt):
-    # type: (str, DefApply, Formatter) -> None
+    # type: (str, Def, Formatter) -> None
     """
-    Given a `Def` or `Apply` node, emit code that extracts all the instruction
-    fields from `dfg[iref]`.
+    Given a `Def` node, emit code that extracts all the instruction fields from
+    `dfg[iref]`.
+
+    Create local variables named after the `Var` instances in `node`. Other
+    arguments like `Enumerator` constants are checked by the match guard, so
+    they are not bound. Variable argument lists are cloned.
 
-    Create local variables named after the `Var` instances in `node`.
+    If the node defines more than one value, the secondary result values are
+    detached from `iref` and bound to local variables named `src_{var}`.
 
     :param iref: Name of the `Inst` reference to unwrap.
-    :param node: `Def` or `Apply` node providing variable names.
+    :param node: `Def` node providing variable names.
 
     """
     fmt.comment('Unwrap {}'.format(node))
@@ -39,8 +53,13 @@ def unwrap_inst(iref, node, fmt):
 
     # The tuple of locals we're extracting is `expr.args`.
     with fmt.indented(
-            'let ({}) = if let InstructionData::{} {{'
-            .format(', '.join(map(str, expr.args)), iform.name), '};'):
+            'let {} = if let InstructionData::{} {{'
+            .format(
+                wrap_tup([
+                    str(a) if isinstance(a, Var) else '_'
+                    for a in expr.args]),
+                iform.name),
+            '};'):
         if iform.boxed_storage:
             # This format indirects to a largish `data` struct.
             fmt.line('ref data,')
@@ -54,13 +73,16 @@ def unwrap_inst(iref, node, fmt):
             elif nvops > 1:
                 fmt.line('args,')
         fmt.line('..')
-        fmt.outdented_line('} = dfg[inst] {')
+        fmt.outdented_line('}} = dfg[{}] {{'.format(iref))
         # Generate the values for the tuple.
         outs = list()
         prefix = 'data.' if iform.boxed_storage else ''
         for i, m in enumerate(iform.members):
             if m:
-                outs.append(prefix + m)
+                if iform.kinds[i] is variable_args:
+                    outs.append(prefix + m + '.clone()')
+                else:
+                    outs.append(prefix + m)
             else:
                 # This is a value operand.
                 if nvops == 1:
@@ -69,9 +91,41 @@ def unwrap_inst(iref, node, fmt):
                     outs.append(
                             '{}args[{}]'.format(
                                 prefix, iform.value_operands.index(i)))
-        fmt.line('({})'.format(', '.join(outs)))
+        fmt.line(wrap_tup(outs))
         fmt.outdented_line('} else {')
-        fmt.line('unimplemented!("bad instruction format")')
+        fmt.line('unreachable!("bad instruction format")')
+
+    # If the node has multiple results, detach the secondary values so they
+    # can be turned into aliases of the values computed by the destination
+    # pattern.
+    if len(defs) > 1:
+        for d in defs[1:]:
+            fmt.line('let src_{};'.format(d))
+        with fmt.indented('{', '}'):
+            fmt.line(
+                    'let mut vals = dfg.detach_secondary_results({});'
+                    .format(iref))
+            for d in defs[1:]:
+                fmt.line('src_{} = vals.next().unwrap();'.format(d))
+            fmt.line('assert_eq!(vals.next(), None);')
+
+
+def emit_dst_inst(node, builder, used, fmt):
+    # type: (Def, str, Set[Var], Formatter) -> None
+    """
+    Emit code for a single instruction in the destination pattern.
+
+    :param node: `Def` node to emit.
+    :param builder: Rust expression creating the instruction builder to use.
+    :param used: Set of variables that are used after `node`. Other values
+                 defined by `node` are not bound to Rust locals.
+    """
+    call = '{}.{}'.format(builder, node.expr.rust_builder())
+    if any(d in used for d in node.defs):
+        pat = wrap_tup([str(d) if d in used else '_' for d in node.defs])
+        fmt.line('let {} = {};'.format(pat, call))
+    else:
+        fmt.line('{};'.format(call))
 
 
 def gen_xform(xform, fmt):
@@ -84,22 +138,127 @@ def gen_xform(xform, fmt):
     Cursor`.
     `dfg: DataFlowGraph` is available and mutable.
     """
-    unwrap_inst('inst', xform.src.rtl[0], fmt)
+    src = xform.src.rtl[0]
+    dst = xform.dst.rtl
+
+    # Unwrap the source instruction, create local variables for the input
+    # variables.
+    unwrap_inst('inst', src, fmt)
+
+    # The primary result of the source instruction is a direct value that
+    # can't be turned into an alias. The destination instruction defining it
+    # replaces the source instruction in place. If the source instruction has
+    # no results, the last destination instruction replaces it.
+    if src.defs:
+        primary = [
+                i for i, d in enumerate(dst) if src.defs[0] in d.defs]
+        assert len(primary) == 1
+        replace = primary[0]
+        if dst[replace].defs[0] is not src.defs[0]:
+            raise AssertionError(
+                    '{} must be the first value defined by {}'
+                    .format(src.defs[0], dst[replace]))
+    else:
+        replace = len(dst) - 1
+
+    # Compute the set of variables that are used after each destination
+    # instruction. The secondary source results are needed for the final
+    # aliasing.
+    used = set(src.defs[1:])  # type: Set[Var]
+    live_after = list()  # type: List[Set[Var]]
+    for d in reversed(dst):
+        live_after.append(set(used))
+        used.update(a for a in d.expr.args if isinstance(a, Var))
+    live_after.reverse()
+
+    for i, d in enumerate(dst):
+        if i == replace:
+            emit_dst_inst(d, 'dfg.replace(inst)', live_after[i], fmt)
+            if i + 1 < len(dst):
+                # Following instructions must be inserted *after* the
+                # replaced instruction.
+                fmt.line('pos.next_inst();')
+        else:
+            emit_dst_inst(d, 'dfg.ins(pos)', live_after[i], fmt)
+
+    # The detached secondary results become aliases of the values computed
+    # by the destination pattern.
+    for d in src.defs[1:]:
+        fmt.line('dfg.change_to_alias(src_{0}, {0});'.format(d))
+
+
+def type_guard(xform):
+    # type: (XForm) -> List[str]
+    """
+    Return a list of Rust conditions that check the controlling type variable
+    of the instruction to be replaced when the source pattern of `xform` is
+    bound to a concrete type.
+
+    Return an empty list if the pattern applies to all types.
+    """
+    _, expr = xform.src.rtl[0].defs_expr()
+    if not expr.typevars:
+        return []
+    if len(expr.typevars) > 1:
+        raise AssertionError(
+                'Secondary type variables are not supported in {}'
+                .format(expr))
+    return ['dfg[inst].ctrl_typevar(dfg) == {}'.format(
+            expr.typevars[0].rust_name())]
+
+
+def enum_guard(xform):
+    # type: (XForm) -> List[str]
+    """
+    Return a list of Rust conditions that check the enumerated immediate
+    operands of the instruction to be replaced when the source pattern of
+    `xform` specifies them, as in `icmp(intcc.sgt, x, y)`.
+    """
+    _, expr = xform.src.rtl[0].defs_expr()
+    iform = expr.inst.format
+    conds = list()  # type: List[str]
+    for i, arg in enumerate(expr.args):
+        if not isinstance(arg, Enumerator):
+            continue
+        member = iform.members[i]
+        if iform.boxed_storage:
+            pat = 'ref data'
+            field = 'data.' + member
+        else:
+            pat = field = member
+        conds.append(
+                'match dfg[inst] {{ InstructionData::{} {{ {}, .. }} => '
+                '{} == {}, _ => false }}'
+                .format(iform.name, pat, field, arg.rust_expression()))
+    return conds
+
+
+def src_guard(xform):
+    # type: (XForm) -> str
+    """
+    Return a match guard that checks if the instruction to be replaced matches
+    the source pattern of `xform`, beyond its opcode.
+
+    Return an empty string if the pattern applies to all instances of the
+    opcode.
+    """
+    conds = type_guard(xform) + enum_guard(xform)
+    if not conds:
+        return ''
+    return ' if ' + ' && '.join(conds)
 
 
 def gen_xform_group(xgrp, fmt):
     # type: (XFormGroup, Formatter) -> None
-    fmt.doc_comment("""
-        Legalize the instruction pointed to by `pos`.
+    fmt.doc_comment("""Legalize the instruction pointed to by `pos`.
 
-        Return the first instruction in the expansion, and leave `pos` pointing
-        at the last instruction in the expansion.
-        """)
+Return `true` if the instruction was rewritten, or `false` if there are no
+applicable transformations in the `{}` group. When the instruction is
+rewritten, the cursor is left somewhere inside the expansion.""".format(
+        xgrp.name))
     with fmt.indented(
-            'fn ' + xgrp.name +
-            '(pos: &mut Cursor, dfg: &mut DataFlowGraph) -> ' +
-            'Option<Inst> {{',
-            '}'):
+            'fn {}(pos: &mut Cursor, dfg: &mut DataFlowGraph) -> bool {{'
+            .format(xgrp.name), '}'):
         # Gen the instruction to be legalized. The cursor we're passed must be
         # pointing at an instruction.
         fmt.line('let inst = pos.current_inst().expect("need instruction");')
@@ -108,14 +267,24 @@ def gen_xform_group(xgrp, fmt):
             for xform in xgrp.xforms:
                 inst = xform.src.rtl[0].root_inst()
                 with fmt.indented(
-                        'Opcode::{} => {{'.format(inst.camel_name), '}'):
+                        'Opcode::{}{} => {{'.format(
+                            inst.camel_name, src_guard(xform)), '}'):
                     gen_xform(xform, fmt)
+                    fmt.line('true')
             # We'll assume there are uncovered opcodes.
-            fmt.line('_ => None,')
+            fmt.line('_ => false,')
 
 
 def generate(isas, out_dir):
     fmt = Formatter()
-    gen_xform_group(legalize.narrow, fmt)
-    gen_xform_group(legalize.expand, fmt)
+    groups = [legalize.narrow, legalize.widen, legalize.expand]
+    # Target ISAs can define their own transformation groups and select them
+    # with `CPUMode.legalize_type()`.
+    for isa in isas:
+        for cpumode in isa.cpumodes:
+            for xgrp in cpumode.type_legalize.values():
+                if xgrp not in groups:
+                    groups.append(xgrp)
+    for xgrp in groups:
+        gen_xform_group(xgrp, fmt)
     fmt.update_file('legalizer.rs', out_dir)
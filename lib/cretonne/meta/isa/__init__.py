@@ -6,7 +6,7 @@ The :py:mod:`isa` package contains sub-packages for each target instruction set
 architecture supported by Cretonne.
 """
 from __future__ import absolute_import
-from . import riscv
+from . import riscv, intel, arm64, arm32
 from cretonne import TargetISA  # noqa
 
 
@@ -16,4 +16,4 @@ def all_isas():
     Get a list of all the supported target ISAs. Each target ISA is represented
     as a :py:class:`cretonne.TargetISA` instance.
     """
-    return [riscv.isa]
+    return [riscv.isa, intel.isa, arm64.isa, arm32.isa]
@@ -0,0 +1,36 @@
+"""
+ARM32 Target
+------------
+
+ARM32 is the 32-bit ARM architecture, as found in the ARMv7-A application
+processors and the ARMv7-M microcontrollers like the Cortex-M series. There
+are two instruction sets that share the sixteen general purpose registers:
+
+A32
+    The original ARM instruction set with fixed-width 32-bit instructions.
+
+T32
+    The Thumb-2 instruction set which mixes 16-bit and 32-bit instructions.
+    This is the only instruction set supported by the M-profile cores.
+
+Optional features are:
+
+VFP
+    Floating point instructions on single and double precision registers.
+
+NEON
+    Advanced SIMD instructions on 128-bit vectors, and 32 double precision
+    registers instead of 16.
+
+Hardware division
+    The `sdiv` and `udiv` instructions. These are always present in the
+    ARMv7-M profile and in T32 code on ARMv7-R, but they are an optional
+    extension for ARMv7-A.
+
+"""
+from __future__ import absolute_import
+from . import defs
+from . import encodings, settings, registers  # noqa
+
+# Re-export the primary target ISA definition.
+isa = defs.isa.finish()
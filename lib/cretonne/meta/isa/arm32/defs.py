@@ -0,0 +1,15 @@
+"""
+ARM32 definitions.
+
+Commonly used definitions.
+"""
+from __future__ import absolute_import
+from cretonne import TargetISA, CPUMode
+import cretonne.base
+
+isa = TargetISA('arm32', [cretonne.base.instructions])
+
+# The two instruction sets are separate CPU modes. A function is compiled for
+# one of them.
+A32 = CPUMode('A32', isa)
+T32 = CPUMode('T32', isa)
@@ -0,0 +1,431 @@
+"""
+ARM32 Encodings.
+"""
+from __future__ import absolute_import
+from cretonne import base
+from cretonne.formats import Load, Store, FloatCompare
+from cretonne.immediates import floatcc
+from cretonne.types import i8, i16, i32, i64, f32, f64, b1
+from cretonne.legalize import widen, expand
+from cretonne.predicates import IsEqual, IsUnsignedInt
+from .defs import A32, T32
+from .recipes import THUMB, A32 as A32OP, DP, LDST, LDSTH
+from .recipes import VFP, VLDST, VMOV, NEON
+from .recipes import Rrr, Rmov, Rmul, Rdiv, Rsdiv, Rrem, Rshift, Rrotr
+from .recipes import Ishift, Irotl, Rclz, Rctz, Rext, Iaddsub, Ilogic, Ibnot
+from .recipes import Umov, Umovt, Ricmp, Rsel, Ildr, Ildrh, Sstr, Sstrh
+from .recipes import GPsp, GPfi, Iadjsp, Iadjsp2, Bjump, Bz, Bicmp, Bret
+from .recipes import Bcall, Ugsym, Ifence, Rfcmps, Rfcmpd, Udf, Btrap
+from .recipes import TRrr, TRmov, TRreg, TRshift, TRdiv, TRsdiv, TRrem
+from .recipes import TRclz, TRctz, TRext, TIaddsub, TIlogic, TIbnot
+from .recipes import TIshift, TIrotl, TUmov, TUmovt, TRicmp, TRsel
+from .recipes import TIldr, TSstr, TGPsp, TGPfi, TIadjsp, TIadjsp2
+from .recipes import TBjump16, TBjump, TBz16, TBz, TBicmp16, TBicmp, TBret
+from .recipes import TBcall, TUgsym, TIfence, TRfcmps, TRfcmpd
+from .recipes import TUdf, TBtrap16, TBtrap
+from .recipes import TRrr16, TRtied16, TRadd16, TRmov16, TRr16, TIshift16
+from .recipes import TIaddsub16, TUmov16, TIldr16, TSstr16
+from .recipes import Rfs, Rfd, Rffs, Rffd, Rfpromote, Rfdemote, Rifs, Rifd
+from .recipes import Rmovrs, Rmovsr, Fldrs, Fldrd, Fstrs, Fstrd
+from .recipes import FPsps, FPspd, FPfis, FPfid
+from .recipes import Rq, Rqmov, Qld, Qst, QPsp, QPfi
+from .settings import has_hwdiv, use_vfp, use_neon
+
+# The condition codes used by `brz` and `brnz`.
+COND_EQ = 0b0000
+COND_NE = 0b0001
+
+# A32 integer instructions. All the operations are on 32-bit registers, and
+# `i64` values are narrowed into pairs of `i32` values by the legalizer.
+for inst,           opcode in [
+        (base.iadd, 0b0100),
+        (base.isub, 0b0010),
+        (base.band, 0b0000),
+        (base.bor,  0b1100),
+        (base.bxor, 0b0001)
+        ]:
+    A32.enc(inst.i32, Rrr, DP(opcode))
+
+# Logical operations have immediate forms with an 8-bit constant. Other
+# constants are materialized in a register by the legalizer.
+for inst,               opcode in [
+        (base.band_imm, 0b0000),
+        (base.bor_imm,  0b1100),
+        (base.bxor_imm, 0b0001)
+        ]:
+    A32.enc(inst.i32, Ilogic, DP(opcode, imm=1))
+A32.enc(base.iadd_imm.i32, Iaddsub, DP(0b0100, imm=1))
+
+# Register copies are `mov rd, rm`, and `bnot` is `mvn rd, rm`.
+A32.enc(base.copy.i32, Rmov, DP(0b1101))
+A32.enc(base.bnot.i32, Rmov, DP(0b1111))
+
+A32.enc(base.imul.i32, Rmul, A32OP(0b00000000, 0b1001))
+
+# Division and remainder check for the trapping conditions before the
+# division instruction, which produces 0 instead of trapping. Without
+# hardware division, they become library calls.
+A32.enc(base.udiv.i32, Rdiv, A32OP(0b01110011, 0b0001), isap=has_hwdiv)
+A32.enc(base.sdiv.i32, Rsdiv, A32OP(0b01110001, 0b0001), isap=has_hwdiv)
+A32.enc(base.urem.i32, Rrem, A32OP(0b01110011, 0b0001), isap=has_hwdiv)
+A32.enc(base.srem.i32, Rrem, A32OP(0b01110001, 0b0001), isap=has_hwdiv)
+
+# Shifts are `mov` instructions with a shifted register operand. Only the low
+# byte of the shift amount register is used, so any integer type works for
+# the amount.
+for inst,           shtype in [
+        (base.ishl, 0b00),
+        (base.ushr, 0b01),
+        (base.sshr, 0b10)
+        ]:
+    for amt in [i8, i16, i32]:
+        A32.enc(inst.i32.bind(amt), Rshift, A32OP(0b00011010, shtype << 1 | 1))
+for amt in [i8, i16, i32]:
+    A32.enc(base.rotr.i32.bind(amt), Rrotr, A32OP(0b00011010, 0b0111))
+
+for inst,               shtype in [
+        (base.ishl_imm, 0b00),
+        (base.ushr_imm, 0b01),
+        (base.sshr_imm, 0b10),
+        (base.rotr_imm, 0b11)
+        ]:
+    A32.enc(inst.i32, Ishift, A32OP(0b00011010, shtype << 1))
+A32.enc(base.rotl_imm.i32, Irotl, A32OP(0b00011010, 0b0110))
+
+A32.enc(base.clz.i32, Rclz, A32OP(0b00010110, 0b0001))
+A32.enc(base.ctz.i32, Rctz, A32OP(0b00010110, 0b0001))
+
+# Integer constants. The general `movw` + `movt` sequence is listed last, so
+# it is picked before register allocation. Branch relaxation switches to a
+# single instruction when the constant allows it.
+A32.enc(base.iconst.i32, Umov, A32OP(0b00110000))
+A32.enc(base.iconst.i32, Umovt, A32OP(0b00110000))
+
+# All the integer condition codes are supported by conditional execution.
+A32.enc(base.icmp.i32, Ricmp, DP(0b1010, s=1))
+A32.enc(base.select.i32.b1, Rsel, DP(0b1101))
+
+# Integer conversions. The small integer types only appear as the inputs and
+# outputs of these instructions. All other operations on `i8` and `i16` values
+# are widened.
+A32.legalize_type(i8=widen, i16=widen)
+
+for inst in [base.ireduce.i8, base.ireduce.i16]:
+    A32.enc(inst.i32, Rmov, DP(0b1101))
+
+for inst,         ty,  op in [
+        (base.uextend, i8,  0b01101110),
+        (base.uextend, i16, 0b01101111),
+        (base.sextend, i8,  0b01101010),
+        (base.sextend, i16, 0b01101011)
+        ]:
+    A32.enc(inst.i32.bind(ty), Rext, A32OP(op, 0b0111))
+
+# Loads and stores. Words and unsigned bytes have a 12-bit offset, the other
+# sizes have an 8-bit offset.
+A32.enc(base.load.i32.i32, Ildr, LDST(0, 1))
+A32.enc(base.uload8.i32.i32, Ildr, LDST(1, 1))
+A32.enc(base.uload16.i32.i32, Ildrh, LDSTH(1, 0b01))
+A32.enc(base.sload8.i32.i32, Ildrh, LDSTH(1, 0b10))
+A32.enc(base.sload16.i32.i32, Ildrh, LDSTH(1, 0b11))
+A32.enc(base.store.i32.i32, Sstr, LDST(0, 0))
+A32.enc(base.istore8.i32.i32, Sstr, LDST(1, 0))
+A32.enc(base.istore16.i32.i32, Sstrh, LDSTH(0, 0b01))
+
+# Spills and fills use stack pointer relative loads and stores.
+A32.enc(base.spill.i32, GPsp, LDST(0, 0))
+A32.enc(base.fill.i32, GPfi, LDST(0, 1))
+
+# The stack frame is allocated and deallocated by adjusting the stack pointer.
+A32.enc(base.adjust_sp_imm, Iadjsp, DP(0b0100, imm=1))
+A32.enc(base.adjust_sp_imm, Iadjsp2, DP(0b0100, imm=1))
+
+# Control flow.
+A32.enc(base.jump, Bjump, A32OP(0b10100000))
+for ty in [i32, b1]:
+    A32.enc(base.brz.bind(ty), Bz, COND_EQ)
+    A32.enc(base.brnz.bind(ty), Bz, COND_NE)
+A32.enc(base.br_icmp.i32, Bicmp, DP(0b1010, s=1))
+A32.enc(base.x_return, Bret, 0)
+
+# Traps use the permanently undefined instruction. The conditional traps skip
+# over it when the trap condition doesn't hold.
+A32.enc(base.trap, Udf, 0)
+for ty in [i32, b1]:
+    A32.enc(base.trapz.bind(ty), Btrap, COND_NE)
+    A32.enc(base.trapnz.bind(ty), Btrap, COND_EQ)
+
+# Direct calls and symbol addresses need relocations.
+A32.enc(base.call, Bcall, A32OP(0b10110000))
+A32.enc(base.globalsym_addr.i32, Ugsym, A32OP(0b00110000))
+
+# Boolean operations on `b1` values, like the results of comparisons.
+for inst,           opcode in [
+        (base.band, 0b0000),
+        (base.bor,  0b1100),
+        (base.bxor, 0b0001)
+        ]:
+    A32.enc(inst.b1, Rrr, DP(opcode))
+A32.enc(base.bnot.b1, Ibnot, DP(0b0001, imm=1))
+
+# Aligned loads and stores are atomic, but the atomic memory operations would
+# need exclusive load/store loops. They become library calls, and only the
+# memory barrier is encoded.
+A32.enc(base.fence, Ifence, 0)
+
+# T32 integer instructions. Most instructions have a 16-bit form that can only
+# use the low registers `r0`-`r7`, and a 32-bit form that can use all the
+# registers. The 16-bit forms are listed first, so the 32-bit forms are picked
+# before register allocation. Branch relaxation shrinks the instructions when
+# the allocated registers allow it.
+T32.enc(base.iadd.i32, TRrr16, 0x1800)
+T32.enc(base.iadd.i32, TRadd16, 0x4400)
+T32.enc(base.iadd.i32, TRrr, 0xeb00)
+T32.enc(base.isub.i32, TRrr16, 0x1a00)
+T32.enc(base.isub.i32, TRrr, 0xeba0)
+
+for inst,           bits16, bits in [
+        (base.band, 0x4000, 0xea00),
+        (base.bor,  0x4300, 0xea40),
+        (base.bxor, 0x4040, 0xea80)
+        ]:
+    for ty in [i32, b1]:
+        T32.enc(inst.bind(ty), TRtied16, bits16)
+        T32.enc(inst.bind(ty), TRrr, bits)
+
+for inst,               bits in [
+        (base.band_imm, 0xf000),
+        (base.bor_imm,  0xf040),
+        (base.bxor_imm, 0xf080)
+        ]:
+    T32.enc(inst.i32, TIlogic, bits)
+T32.enc(base.iadd_imm.i32, TIaddsub16, 0x3000)
+T32.enc(base.iadd_imm.i32, TIaddsub, 0xf200)
+
+# The 16-bit `mov rd, rm` can use all the registers.
+T32.enc(base.copy.i32, TRmov16, 0x4600)
+T32.enc(base.bnot.i32, TRr16, 0x43c0)
+T32.enc(base.bnot.i32, TRmov, 0xea6f)
+T32.enc(base.bnot.b1, TIbnot, 0xf080)
+
+T32.enc(base.imul.i32, TRtied16, 0x4340)
+T32.enc(base.imul.i32, TRreg, 0xfb00)
+
+T32.enc(base.udiv.i32, TRdiv, 0xfbb0, isap=has_hwdiv)
+T32.enc(base.sdiv.i32, TRsdiv, 0xfb90, isap=has_hwdiv)
+T32.enc(base.urem.i32, TRrem, 0xfbb0, isap=has_hwdiv)
+T32.enc(base.srem.i32, TRrem, 0xfb90, isap=has_hwdiv)
+
+for inst,           bits in [
+        (base.ishl, 0xfa00),
+        (base.ushr, 0xfa20),
+        (base.sshr, 0xfa40)
+        ]:
+    for amt in [i8, i16, i32]:
+        T32.enc(inst.i32.bind(amt), TRshift, bits)
+for amt in [i8, i16, i32]:
+    T32.enc(base.rotr.i32.bind(amt), TRreg, 0xfa60)
+
+# The 16-bit immediate shifts have no rotate form.
+for inst,               bits16, shtype in [
+        (base.ishl_imm, 0x0000, 0b00),
+        (base.ushr_imm, 0x0800, 0b01),
+        (base.sshr_imm, 0x1000, 0b10)
+        ]:
+    T32.enc(inst.i32, TIshift16, bits16)
+    T32.enc(inst.i32, TIshift, shtype)
+T32.enc(base.rotr_imm.i32, TIshift, 0b11)
+T32.enc(base.rotl_imm.i32, TIrotl, 0b11)
+
+T32.enc(base.clz.i32, TRclz, 0xfab0)
+T32.enc(base.ctz.i32, TRctz, 0xfab0)
+
+T32.enc(base.iconst.i32, TUmov16, 0x2000)
+T32.enc(base.iconst.i32, TUmov, 0xf240)
+T32.enc(base.iconst.i32, TUmovt, 0xf240)
+
+T32.enc(base.icmp.i32, TRicmp, 0xebb0)
+T32.enc(base.select.i32.b1, TRsel, 0x4600)
+
+T32.legalize_type(i8=widen, i16=widen)
+
+for inst in [base.ireduce.i8, base.ireduce.i16]:
+    T32.enc(inst.i32, TRmov16, 0x4600)
+
+for inst,         ty,  bits16, bits in [
+        (base.uextend, i8,  0xb2c0, 0xfa5f),
+        (base.uextend, i16, 0xb280, 0xfa1f),
+        (base.sextend, i8,  0xb240, 0xfa4f),
+        (base.sextend, i16, 0xb200, 0xfa0f)
+        ]:
+    T32.enc(inst.i32.bind(ty), TRr16, bits16)
+    T32.enc(inst.i32.bind(ty), TRext, bits)
+
+# The 16-bit loads and stores have an unsigned 5-bit offset scaled by the
+# access size. There is no 16-bit form of the sign-extending loads with an
+# immediate offset.
+for inst,           scale, bits16, bits in [
+        (base.load,    2, 0x6800, 0xf8d0),
+        (base.uload8,  0, 0x7800, 0xf890),
+        (base.uload16, 1, 0x8800, 0xf8b0)
+        ]:
+    T32.enc(inst.i32.i32, TIldr16, bits16,
+            instp=IsUnsignedInt(Load.offset, 5 + scale, scale))
+    T32.enc(inst.i32.i32, TIldr, bits)
+T32.enc(base.sload8.i32.i32, TIldr, 0xf990)
+T32.enc(base.sload16.i32.i32, TIldr, 0xf9b0)
+
+for inst,            scale, bits16, bits in [
+        (base.store,    2, 0x6000, 0xf8c0),
+        (base.istore8,  0, 0x7000, 0xf880),
+        (base.istore16, 1, 0x8000, 0xf8a0)
+        ]:
+    T32.enc(inst.i32.i32, TSstr16, bits16,
+            instp=IsUnsignedInt(Store.offset, 5 + scale, scale))
+    T32.enc(inst.i32.i32, TSstr, bits)
+
+T32.enc(base.spill.i32, TGPsp, 0xf8c0)
+T32.enc(base.fill.i32, TGPfi, 0xf8d0)
+
+T32.enc(base.adjust_sp_imm, TIadjsp, 0xf200)
+T32.enc(base.adjust_sp_imm, TIadjsp2, 0xf200)
+
+T32.enc(base.jump, TBjump16, 0xe000)
+T32.enc(base.jump, TBjump, 0x9000)
+for ty in [i32, b1]:
+    for recipe in [TBz16, TBz]:
+        T32.enc(base.brz.bind(ty), recipe, COND_EQ)
+        T32.enc(base.brnz.bind(ty), recipe, COND_NE)
+for recipe in [TBicmp16, TBicmp]:
+    T32.enc(base.br_icmp.i32, recipe, 0x4280)
+T32.enc(base.x_return, TBret, 0x4770)
+
+T32.enc(base.trap, TUdf, 0)
+for ty in [i32, b1]:
+    for recipe in [TBtrap16, TBtrap]:
+        T32.enc(base.trapz.bind(ty), recipe, COND_NE)
+        T32.enc(base.trapnz.bind(ty), recipe, COND_EQ)
+
+T32.enc(base.call, TBcall, 0xd000)
+T32.enc(base.globalsym_addr.i32, TUgsym, 0xf240)
+
+T32.enc(base.fence, TIfence, 0)
+
+# VFP instructions have the same encodings in both instruction sets. The `sz`
+# bit selects double precision.
+for cpu, thumb, fcmp_recipes in [
+        (A32, 0,     (Rfcmps, Rfcmpd)),
+        (T32, THUMB, (TRfcmps, TRfcmpd))
+        ]:
+    for ty,  sz, rf,  rff,  fldr,  fstr,  fpsp,  fpfi,  rfcmp in [
+            (f32, 0, Rfs, Rffs, Fldrs, Fstrs, FPsps, FPfis, fcmp_recipes[0]),
+            (f64, 1, Rfd, Rffd, Fldrd, Fstrd, FPspd, FPfid, fcmp_recipes[1])
+            ]:
+        for inst,         opc1,   opc2,   op4 in [
+                (base.fadd, 0b0011, 0b0000, 0b0000),
+                (base.fsub, 0b0011, 0b0000, 0b0100),
+                (base.fmul, 0b0010, 0b0000, 0b0000),
+                (base.fdiv, 0b1000, 0b0000, 0b0000)
+                ]:
+            cpu.enc(inst.bind(ty), rf, thumb | VFP(opc1, opc2, op4, sz),
+                    isap=use_vfp)
+
+        for inst,         opc2,   op4 in [
+                (base.copy, 0b0000, 0b0100),
+                (base.fabs, 0b0000, 0b1100),
+                (base.fneg, 0b0001, 0b0100),
+                (base.sqrt, 0b0001, 0b1100)
+                ]:
+            cpu.enc(inst.bind(ty), rff, thumb | VFP(0b1011, opc2, op4, sz),
+                    isap=use_vfp)
+
+        # The flags copied from `vcmp` can express all the condition codes
+        # except `one` and `ueq`, which are expanded by the legalizer.
+        for cond in ['ord', 'uno', 'eq', 'ne', 'lt', 'le', 'gt', 'ge',
+                     'ult', 'ule', 'ugt', 'uge']:
+            cpu.enc(base.fcmp.bind(ty), rfcmp,
+                    thumb | VFP(0b1011, 0b0100, 0b0100, sz),
+                    instp=IsEqual(FloatCompare.cond, getattr(floatcc, cond)),
+                    isap=use_vfp)
+
+        cpu.enc(base.load.bind(ty, i32), fldr, thumb | VLDST(1, sz),
+                isap=use_vfp)
+        cpu.enc(base.store.bind(ty, i32), fstr, thumb | VLDST(0, sz),
+                isap=use_vfp)
+        cpu.enc(base.spill.bind(ty), fpsp, thumb | VLDST(0, sz), isap=use_vfp)
+        cpu.enc(base.fill.bind(ty), fpfi, thumb | VLDST(1, sz), isap=use_vfp)
+
+    # Conversions between single and double precision. The `sz` bit describes
+    # the operand.
+    cpu.enc(base.fpromote.f64.f32, Rfpromote,
+            thumb | VFP(0b1011, 0b0111, 0b1100, 0), isap=use_vfp)
+    cpu.enc(base.fdemote.f32.f64, Rfdemote,
+            thumb | VFP(0b1011, 0b0111, 0b1100, 1), isap=use_vfp)
+
+    # Conversions from integers go through a floating point register. The
+    # conversions to integers would need a temporary floating point register,
+    # so they become library calls.
+    for inst,                   op4 in [
+            (base.fcvt_from_sint, 0b1100),
+            (base.fcvt_from_uint, 0b0100)
+            ]:
+        cpu.enc(inst.f32.i32, Rifs, thumb | VFP(0b1011, 0b1000, op4, 0),
+                isap=use_vfp)
+        cpu.enc(inst.f64.i32, Rifd, thumb | VFP(0b1011, 0b1000, op4, 1),
+                isap=use_vfp)
+
+    cpu.enc(base.bitcast.i32.f32, Rmovrs, thumb | VMOV(1), isap=use_vfp)
+    cpu.enc(base.bitcast.f32.i32, Rmovsr, thumb | VMOV(0), isap=use_vfp)
+
+    # NEON instructions on 128-bit vectors in quad registers. The `size` field
+    # is the log2 of the lane size in bytes. There is no 64-bit lane
+    # multiplication.
+    for ty,     size in [
+            (i8,  0b00),
+            (i16, 0b01),
+            (i32, 0b10),
+            (i64, 0b11)
+            ]:
+        vty = ty.by(128 // ty.bits)
+        cpu.enc(base.iadd.bind(vty), Rq, thumb | NEON(0, size, 0b1000, 0),
+                isap=use_neon)
+        cpu.enc(base.isub.bind(vty), Rq, thumb | NEON(1, size, 0b1000, 0),
+                isap=use_neon)
+        if size != 0b11:
+            cpu.enc(base.imul.bind(vty), Rq,
+                    thumb | NEON(0, size, 0b1001, 1), isap=use_neon)
+
+        # The bitwise operations use the `size` field as an opcode.
+        for inst,           u, opsize in [
+                (base.band, 0, 0b00),
+                (base.bor,  0, 0b10),
+                (base.bxor, 1, 0b00)
+                ]:
+            cpu.enc(inst.bind(vty), Rq, thumb | NEON(u, opsize, 0b0001, 1),
+                    isap=use_neon)
+
+    for inst,           u, size in [
+            (base.fadd, 0, 0b00),
+            (base.fsub, 0, 0b10)
+            ]:
+        cpu.enc(inst.bind(f32.by(4)), Rq, thumb | NEON(u, size, 0b1101, 0),
+                isap=use_neon)
+    cpu.enc(base.fmul.bind(f32.by(4)), Rq, thumb | NEON(1, 0, 0b1101, 1),
+            isap=use_neon)
+
+    # Quad register copies, loads, and stores don't depend on the lane type.
+    # Spills and fills move the two double precision halves separately.
+    for vty in [i8.by(16), i16.by(8), i32.by(4), i64.by(2), f32.by(4)]:
+        cpu.enc(base.copy.bind(vty), Rqmov, thumb | NEON(0, 0b10, 0b0001, 1),
+                isap=use_neon)
+        cpu.enc(base.load.bind(vty, i32), Qld, thumb | 1, isap=use_neon)
+        cpu.enc(base.store.bind(vty, i32), Qst, thumb | 0, isap=use_neon)
+        cpu.enc(base.spill.bind(vty), QPsp, thumb | VLDST(0, 1),
+                isap=use_neon)
+        cpu.enc(base.fill.bind(vty), QPfi, thumb | VLDST(1, 1),
+                isap=use_neon)
+
+    # Floating point values are always supported. When `use_vfp` is off,
+    # floating point operations have no encodings, and they are expanded into
+    # soft-float library calls.
+    cpu.legalize_type(f32=expand, f64=expand)
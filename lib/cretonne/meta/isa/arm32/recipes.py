@@ -0,0 +1,1034 @@
+"""
+ARM32 Encoding recipes.
+
+The encoding recipes defined here correspond to the A32 and T32 instruction
+encodings described in the reference:
+
+    ARM Architecture Reference Manual
+    ARMv7-A and ARMv7-R edition
+
+The recipes for each instruction set are separate, except for the VFP and
+NEON instructions which have the same 32-bit encodings in both. Those recipes
+are shared, and the `THUMB` bit in the encbits selects how the instruction
+words are emitted.
+"""
+from __future__ import absolute_import
+from cretonne import EncRecipe
+from cretonne.formats import Nullary, Unary, UnaryImm, Binary, BinaryImm
+from cretonne.formats import Ternary
+from cretonne.formats import Load, Store, IntCompare, FloatCompare
+from cretonne.formats import UnaryGlobalVar, Call, Jump, Branch
+from cretonne.formats import BranchIcmp, Return, Fence
+from cretonne.predicates import IsSignedInt, IsUnsignedInt, Or
+from cretonne.registers import Stack, EarlyClobber
+from .registers import GPR, GPRL, SPR, DPR, DPRL, QPR
+
+# Encbits bit 15 selects T32 emission in the shared VFP and NEON recipes. The
+# 32-bit instruction words are emitted as two halfwords, most significant
+# first.
+THUMB = 1 << 15
+
+# All A32 instructions are 32 bits wide. Bits 27:20 and 7:4 identify most
+# instructions, and bits 31:28 hold the condition code.
+#
+# Encbits for the A32 integer recipes are
+# `bits[27:20] | (bits[7:4] << 8) | ((cond ^ 0xe) << 12)`, so the condition
+# code is `al` unless a recipe changes it. The register and immediate fields
+# are left clear.
+
+
+def A32(op, op4=0):
+    # type: (int, int) -> int
+    assert op <= 0xff
+    assert op4 <= 0xf
+    return op | (op4 << 8)
+
+
+def DP(opcode, s=0, imm=0):
+    # type: (int, int, int) -> int
+    """
+    Data-processing: `cond 00 I opcode S Rn Rd operand2`. The `I` bit selects
+    a modified immediate second operand instead of a register.
+    """
+    assert opcode <= 0xf and s <= 1 and imm <= 1
+    return A32((imm << 5) | (opcode << 1) | s)
+
+
+def LDST(b, l):
+    # type: (int, int) -> int
+    """
+    Load/store word and unsigned byte (immediate offset):
+    `cond 010 P U B W L Rn Rt imm12`. The `P` and `U` bits are set, and the
+    `U` bit is cleared for negative offsets when the instruction is emitted.
+    """
+    assert b <= 1 and l <= 1
+    return A32(0b01011000 | (b << 2) | l)
+
+
+def LDSTH(l, op2):
+    # type: (int, int) -> int
+    """
+    Extra load/store (immediate offset):
+    `cond 000 P U 1 W L Rn Rt imm4H 1 op2 1 imm4L`. The `op2` field selects
+    halfword, signed byte, or signed halfword accesses.
+    """
+    assert l <= 1 and op2 <= 0b11
+    return A32(0b00011100 | l, 0b1001 | (op2 << 1))
+
+
+# The T32 instruction set has 16-bit instructions and 32-bit instructions
+# that are emitted as two halfwords. The encbits are the first halfword of the
+# instruction with the register and immediate fields clear. Fields of the
+# second halfword are placed by the recipes.
+#
+# The VFP instructions are encoded as the A32 instruction words with the `al`
+# condition code in both instruction sets. The encbits are
+# `opc1 | (opc2 << 4) | (op4 << 8) | (sz << 12)` where `opc1` is bits 23:20,
+# `opc2` is bits 19:16, `op4` is bits 7:4, and `sz` is bit 8 which selects
+# double precision.
+
+
+def VFP(opc1, opc2, op4, sz):
+    # type: (int, int, int, int) -> int
+    """
+    VFP data-processing: `cond 1110 opc1 opc2 Vd 101 sz op4 Vm`. The `D`, `N`,
+    and `M` bits in `opc1` and `op4` are placed with the registers.
+    """
+    assert opc1 <= 0xf and opc2 <= 0xf and op4 <= 0xf and sz <= 1
+    return opc1 | (opc2 << 4) | (op4 << 8) | (sz << 12)
+
+
+def VLDST(l, sz):
+    # type: (int, int) -> int
+    """
+    VFP load/store: `cond 1101 U D 0 L Rn Vd 101 sz imm8`. The offset is
+    scaled by 4.
+    """
+    assert l <= 1 and sz <= 1
+    return l | (sz << 12)
+
+
+def VMOV(op):
+    # type: (int) -> int
+    """
+    Move between a core register and a single precision register:
+    `cond 1110 000 op Vn Rt 1010 N 001 0000`. The `op` bit selects a move to
+    the core register.
+    """
+    assert op <= 1
+    return VFP(op, 0, 0b0001, 0)
+
+
+def NEON(u, size, opc, op):
+    # type: (int, int, int, int) -> int
+    """
+    NEON three registers of the same length on quad registers:
+    `1111 001 U 0 D size Vn Vd opc N 1 M op Vm`. The T32 encoding has the `U`
+    bit at bit 28 instead of bit 24.
+
+    Encbits: `u | (size << 1) | (opc << 3) | (op << 7)`.
+    """
+    assert u <= 1 and size <= 0b11 and opc <= 0xf and op <= 1
+    return u | (size << 1) | (opc << 3) | (op << 7)
+
+
+# A32 recipes.
+
+# Data-processing with register operands, `add rd, rn, rm` and friends.
+Rrr = EncRecipe(
+        'Rrr', Binary, size=4, ins=(GPR, GPR), outs=GPR,
+        emit='put_a32_dp(bits, in_reg0, in_reg1, out_reg0, sink);')
+
+# Data-processing without a first operand. Register copies are
+# `mov rd, rm`, and `bnot` is `mvn rd, rm`.
+Rmov = EncRecipe(
+        'Rmov', Unary, size=4, ins=GPR, outs=GPR,
+        emit='put_a32_dp(bits, 0, in_reg0, out_reg0, sink);')
+
+# Multiplication, `mul rd, rn, rm`.
+Rmul = EncRecipe(
+        'Rmul', Binary, size=4, ins=(GPR, GPR), outs=GPR,
+        emit='put_a32_mul(bits, in_reg0, in_reg1, out_reg0, sink);')
+
+# Division which traps on a zero divisor:
+#
+#     cmp rm, #0
+#     bne 1f
+#     udf #0
+#  1: udiv rd, rn, rm
+#
+# The encbits describe the `udiv` or `sdiv` instruction.
+Rdiv = EncRecipe(
+        'Rdiv', Binary, size=16, ins=(GPR, GPR), outs=GPR,
+        emit='''
+        put_a32_trapz(in_reg1, sink);
+        put_a32_div(bits, in_reg0, in_reg1, out_reg0, sink);
+        ''')
+
+# Signed division which also traps on overflow when `rn` is the smallest
+# integer and `rm` is -1. See `put_a32_trap_sdiv_overflow()`.
+Rsdiv = EncRecipe(
+        'Rsdiv', Binary, size=32, ins=(GPR, GPR), outs=GPR,
+        emit='''
+        put_a32_trapz(in_reg1, sink);
+        put_a32_trap_sdiv_overflow(in_reg0, in_reg1, sink);
+        put_a32_div(bits, in_reg0, in_reg1, out_reg0, sink);
+        ''')
+
+# Remainder, computed from the quotient with `mls rd, rd, rm, rn`. The result
+# register is written before the operands are read for the last time, so it
+# must be distinct from them. The signed remainder doesn't overflow.
+Rrem = EncRecipe(
+        'Rrem', Binary, size=20, ins=(GPR, GPR), outs=EarlyClobber(GPR),
+        emit='''
+        put_a32_trapz(in_reg1, sink);
+        put_a32_div(bits, in_reg0, in_reg1, out_reg0, sink);
+        put_a32_mls(out_reg0, in_reg1, in_reg0, out_reg0, sink);
+        ''')
+
+# Dynamic shifts use the low byte of the shift amount register, so the amount
+# is masked first to get the cton semantics:
+#
+#     and rd, rm, #31
+#     mov rd, rn, lsl rd
+#
+# The result register holds the masked amount, so it must be distinct from
+# the value operand.
+Rshift = EncRecipe(
+        'Rshift', Binary, size=8, ins=(GPR, GPR), outs=EarlyClobber(GPR),
+        emit='''
+        put_a32_dp_imm(A32_AND_IMM, in_reg1, 31, out_reg0, sink);
+        put_a32_shift_reg(bits, in_reg0, out_reg0, out_reg0, sink);
+        ''')
+
+# Rotations are already modulo 32, `mov rd, rn, ror rm`.
+Rrotr = EncRecipe(
+        'Rrotr', Binary, size=4, ins=(GPR, GPR), outs=GPR,
+        emit='put_a32_shift_reg(bits, in_reg0, in_reg1, out_reg0, sink);')
+
+# Immediate shifts, `mov rd, rm, lsl #sh` and friends. The shift amount is
+# masked to 5 bits, and a zero shift is always encoded as `lsl #0`.
+Ishift = EncRecipe(
+        'Ishift', BinaryImm, size=4, ins=GPR, outs=GPR,
+        emit='''
+        put_a32_shift_imm(bits, in_reg0, imm.into(), out_reg0, sink);
+        ''')
+
+# Rotate left, `mov rd, rm, ror #(-sh % 32)`.
+Irotl = EncRecipe(
+        'Irotl', BinaryImm, size=4, ins=GPR, outs=GPR,
+        emit='''
+        let sh: i64 = imm.into();
+        put_a32_shift_imm(bits, in_reg0, sh.wrapping_neg(), out_reg0, sink);
+        ''')
+
+# Count leading zeros, `clz rd, rm`.
+Rclz = EncRecipe(
+        'Rclz', Unary, size=4, ins=GPR, outs=GPR,
+        emit='put_a32_r1(bits, in_reg0, out_reg0, sink);')
+
+# Count trailing zeros, `rbit rd, rm` followed by `clz rd, rd`. The encbits
+# describe the `clz` instruction.
+Rctz = EncRecipe(
+        'Rctz', Unary, size=8, ins=GPR, outs=GPR,
+        emit='''
+        put_a32_r1(A32_RBIT, in_reg0, out_reg0, sink);
+        put_a32_r1(bits, out_reg0, out_reg0, sink);
+        ''')
+
+# Integer extension, `uxtb rd, rm` and friends.
+Rext = EncRecipe(
+        'Rext', Unary, size=4, ins=GPR, outs=GPR,
+        emit='put_a32_ext(bits, in_reg0, out_reg0, sink);')
+
+# Add an immediate, `add rd, rn, #imm` or `sub rd, rn, #-imm`. The emitter
+# finds a modified immediate for the 8-bit magnitude.
+Iaddsub = EncRecipe(
+        'Iaddsub', BinaryImm, size=4, ins=GPR, outs=GPR,
+        instp=IsSignedInt(BinaryImm.imm, 9),
+        emit='put_a32_addsub_imm(bits, in_reg0, imm.into(), out_reg0, sink);')
+
+# Logical operations with an 8-bit immediate, `and rd, rn, #imm` and friends.
+Ilogic = EncRecipe(
+        'Ilogic', BinaryImm, size=4, ins=GPR, outs=GPR,
+        instp=IsUnsignedInt(BinaryImm.imm, 8),
+        emit='put_a32_dp_imm(bits, in_reg0, imm.into(), out_reg0, sink);')
+
+# Boolean negation, `eor rd, rn, #1`. The `b1` values are represented as 0 or
+# 1 in integer registers.
+Ibnot = EncRecipe(
+        'Ibnot', Unary, size=4, ins=GPR, outs=GPR,
+        emit='put_a32_dp_imm(bits, in_reg0, 1, out_reg0, sink);')
+
+# Constants that fit in a single instruction, `movw rd, #imm16` or
+# `mvn rd, #~imm` for small negative constants. The encbits describe the
+# `movw` instruction.
+Umov = EncRecipe(
+        'Umov', UnaryImm, size=4, ins=(), outs=GPR,
+        instp=Or(IsUnsignedInt(UnaryImm.imm, 16),
+                 IsSignedInt(UnaryImm.imm, 9)),
+        emit='put_a32_mov_imm(bits, imm.into(), out_reg0, sink);')
+
+# Arbitrary 32-bit constants, `movw rd, #lo16` followed by `movt rd, #hi16`.
+Umovt = EncRecipe(
+        'Umovt', UnaryImm, size=8, ins=(), outs=GPR,
+        emit='''
+        let imm: i64 = imm.into();
+        put_a32_movw(bits, imm as u32, out_reg0, sink);
+        put_a32_movw(bits | A32_MOVT, imm as u32 >> 16, out_reg0, sink);
+        ''')
+
+# Integer comparison, `cmp rn, rm` followed by two conditional moves:
+#
+#     cmp rn, rm
+#     mov<!cond> rd, #0
+#     mov<cond> rd, #1
+#
+# The encbits describe the `cmp` instruction.
+Ricmp = EncRecipe(
+        'Ricmp', IntCompare, size=12, ins=(GPR, GPR), outs=GPR,
+        emit='''
+        put_a32_dp(bits, in_reg0, in_reg1, 0, sink);
+        put_a32_cset(icc_bits(cond), out_reg0, sink);
+        ''')
+
+# Select, `cmp rc, #0` followed by `movne rd, rn` and `moveq rd, rm`. The
+# encbits describe the `mov` instruction.
+Rsel = EncRecipe(
+        'Rsel', Ternary, size=12, ins=(GPR, GPR, GPR), outs=GPR,
+        emit='''
+        put_a32_dp_imm(A32_CMP_IMM, in_reg0, 0, 0, sink);
+        put_a32_dp(cond_bits(bits, COND_NE), 0, in_reg1, out_reg0, sink);
+        put_a32_dp(cond_bits(bits, COND_EQ), 0, in_reg2, out_reg0, sink);
+        ''')
+
+# Word and byte loads, `ldr rt, [rn, #offset]`. The magnitude of the offset
+# is a 12-bit immediate.
+Ildr = EncRecipe(
+        'Ildr', Load, size=4, ins=GPR, outs=GPR,
+        instp=Or(IsUnsignedInt(Load.offset, 12), IsSignedInt(Load.offset, 12)),
+        emit='put_a32_ldst(bits, in_reg0, offset.into(), out_reg0, sink);')
+
+# Halfword and sign-extending loads, `ldrh rt, [rn, #offset]`. The magnitude
+# of the offset is an 8-bit immediate.
+Ildrh = EncRecipe(
+        'Ildrh', Load, size=4, ins=GPR, outs=GPR,
+        instp=Or(IsUnsignedInt(Load.offset, 8), IsSignedInt(Load.offset, 8)),
+        emit='put_a32_ldsth(bits, in_reg0, offset.into(), out_reg0, sink);')
+
+Sstr = EncRecipe(
+        'Sstr', Store, size=4, ins=(GPR, GPR), outs=(),
+        instp=Or(IsUnsignedInt(Store.offset, 12),
+                 IsSignedInt(Store.offset, 12)),
+        emit='put_a32_ldst(bits, in_reg1, offset.into(), in_reg0, sink);')
+
+Sstrh = EncRecipe(
+        'Sstrh', Store, size=4, ins=(GPR, GPR), outs=(),
+        instp=Or(IsUnsignedInt(Store.offset, 8), IsSignedInt(Store.offset, 8)),
+        emit='put_a32_ldsth(bits, in_reg1, offset.into(), in_reg0, sink);')
+
+# Spill a register to a spill slot, `str rt, [sp, #offset]`. The stack slot
+# offset is relative to the stack pointer on function entry.
+GPsp = EncRecipe(
+        'GPsp', Unary, size=4, ins=GPR, outs=Stack(GPR),
+        emit='''
+        let offset = stack_slot_offset(func, out_ss0);
+        put_a32_ldst(bits, STACK_POINTER, offset, in_reg0, sink);
+        ''')
+
+# Fill a register from a spill slot, `ldr rt, [sp, #offset]`.
+GPfi = EncRecipe(
+        'GPfi', Unary, size=4, ins=Stack(GPR), outs=GPR,
+        emit='''
+        let offset = stack_slot_offset(func, in_ss0);
+        put_a32_ldst(bits, STACK_POINTER, offset, out_reg0, sink);
+        ''')
+
+# Stack pointer adjustment, `add sp, sp, #imm` or `sub sp, sp, #-imm`.
+Iadjsp = EncRecipe(
+        'Iadjsp', UnaryImm, size=4, ins=(), outs=(),
+        instp=IsSignedInt(UnaryImm.imm, 9),
+        emit='''
+        put_a32_addsub_imm(bits, STACK_POINTER, imm.into(), STACK_POINTER,
+                           sink);
+        ''')
+
+# Larger stack pointer adjustments with a 16-bit magnitude, split into two
+# instructions that each adjust by a modified immediate.
+Iadjsp2 = EncRecipe(
+        'Iadjsp2', UnaryImm, size=8, ins=(), outs=(),
+        instp=IsSignedInt(UnaryImm.imm, 16),
+        emit='''
+        let imm: i64 = imm.into();
+        let lo = if imm < 0 { -(-imm & 0xff) } else { imm & 0xff };
+        put_a32_addsub_imm(bits, STACK_POINTER, imm - lo, STACK_POINTER,
+                           sink);
+        put_a32_addsub_imm(bits, STACK_POINTER, lo, STACK_POINTER, sink);
+        ''')
+
+# Unconditional branch, `b offset`. The offset is relative to the address of
+# the instruction plus 8. The range is +/- 32 MiB.
+Bjump = EncRecipe(
+        'Bjump', Jump, size=4, ins=(), outs=(), branch_range=(8, 26),
+        emit='''
+        let dest = func.offsets[data.destination] as i64;
+        let disp = dest - sink.offset() as i64;
+        put_a32_b(bits, disp, sink);
+        ''')
+
+# Compare a register to zero and branch, `cmp rn, #0` followed by
+# `beq offset` or `bne offset`. The encbits are the condition code.
+Bz = EncRecipe(
+        'Bz', Branch, size=8, ins=GPR, outs=(), branch_range=(12, 26),
+        emit='''
+        put_a32_dp_imm(A32_CMP_IMM, in_reg0, 0, 0, sink);
+        let dest = func.offsets[data.destination] as i64;
+        let disp = dest - sink.offset() as i64;
+        put_a32_b(cond_bits(A32_B, bits as u32), disp, sink);
+        ''')
+
+# Compare two registers and branch, `cmp rn, rm` followed by `b<cond> offset`.
+# The encbits describe the `cmp` instruction.
+Bicmp = EncRecipe(
+        'Bicmp', BranchIcmp, size=8, ins=(GPR, GPR), outs=(),
+        branch_range=(12, 26),
+        emit='''
+        put_a32_dp(bits, in_reg0, in_reg1, 0, sink);
+        let dest = func.offsets[data.destination] as i64;
+        let disp = dest - sink.offset() as i64;
+        put_a32_b(cond_bits(A32_B, icc_bits(data.cond)), disp, sink);
+        ''')
+
+# Unconditional trap, `udf #0`.
+Udf = EncRecipe(
+        'Udf', Nullary, size=4, ins=(), outs=(),
+        emit='sink.put4(A32_UDF);')
+
+# Conditional trap, `cmp rn, #0` followed by `beq` or `bne` skipping over
+# `udf #0` unless the trap condition holds. The encbits are the condition code
+# of the skipping branch.
+Btrap = EncRecipe(
+        'Btrap', Unary, size=12, ins=GPR, outs=(),
+        emit='''
+        put_a32_dp_imm(A32_CMP_IMM, in_reg0, 0, 0, sink);
+        put_a32_b(cond_bits(A32_B, bits as u32), 8, sink);
+        sink.put4(A32_UDF);
+        ''')
+
+# Return to the caller, `bx lr`.
+Bret = EncRecipe(
+        'Bret', Return, size=4, ins=(), outs=(),
+        emit='sink.put4(A32_BX_LR);')
+
+# Direct function call, `bl sym`, with an `R_ARM_CALL` relocation. The
+# implicit addend in the instruction is the PC offset of -8.
+Bcall = EncRecipe(
+        'Bcall', Call, size=4, ins=(), outs=(),
+        emit='''
+        sink.reloc_external(RelocKind::Call.into(),
+                            &func.dfg.ext_funcs[data.func_ref].name);
+        put_a32_b(bits, 0, sink);
+        ''')
+
+# Absolute address of a symbol, `movw rd, #:lower16:sym` followed by
+# `movt rd, #:upper16:sym`.
+Ugsym = EncRecipe(
+        'Ugsym', UnaryGlobalVar, size=8, ins=(), outs=GPR,
+        emit='''
+        let name = globalsym_name(func, global_var);
+        sink.reloc_external(RelocKind::MovwAbsNc.into(), name);
+        put_a32_movw(bits, 0, out_reg0, sink);
+        sink.reloc_external(RelocKind::MovtAbs.into(), name);
+        put_a32_movw(bits | A32_MOVT, 0, out_reg0, sink);
+        ''')
+
+# Memory barrier, `dmb ish`.
+Ifence = EncRecipe(
+        'Ifence', Fence, size=4, ins=(), outs=(),
+        emit='sink.put4(A32_DMB_ISH);')
+
+# Floating point comparisons, `vcmp` followed by `vmrs APSR_nzcv, fpscr` to
+# copy the flags, and two conditional moves like `Ricmp`. The encbits describe
+# the `vcmp` instruction.
+Rfcmps = EncRecipe(
+        'Rfcmps', FloatCompare, size=16, ins=(SPR, SPR), outs=GPR,
+        emit='''
+        put_vfp2(bits, in_reg1, in_reg0, sink);
+        sink.put4(VMRS);
+        put_a32_cset(fcc_bits(cond), out_reg0, sink);
+        ''')
+
+Rfcmpd = EncRecipe(
+        'Rfcmpd', FloatCompare, size=16, ins=(DPR, DPR), outs=GPR,
+        emit='''
+        put_vfp2(bits, in_reg1, in_reg0, sink);
+        sink.put4(VMRS);
+        put_a32_cset(fcc_bits(cond), out_reg0, sink);
+        ''')
+
+# T32 recipes.
+
+# Data-processing with register operands, `add.w rd, rn, rm` and friends.
+TRrr = EncRecipe(
+        'TRrr', Binary, size=4, ins=(GPR, GPR), outs=GPR,
+        emit='put_t32_dp(bits, in_reg0, in_reg1, out_reg0, sink);')
+
+# Data-processing without a first operand, `mov.w rd, rm` and
+# `mvn.w rd, rm`. The all-ones `Rn` field is included in the encbits.
+TRmov = EncRecipe(
+        'TRmov', Unary, size=4, ins=GPR, outs=GPR,
+        emit='put_t32_dp(bits, 0, in_reg0, out_reg0, sink);')
+
+# Register instructions with a fixed first nibble in the second halfword,
+# `mul rd, rn, rm` and `ror.w rd, rn, rm`.
+TRreg = EncRecipe(
+        'TRreg', Binary, size=4, ins=(GPR, GPR), outs=GPR,
+        emit='put_t32_reg(bits, in_reg0, 0, in_reg1, out_reg0, sink);')
+
+# Dynamic shifts, masking the shift amount first:
+#
+#     and.w rd, rm, #31
+#     lsl.w rd, rn, rd
+TRshift = EncRecipe(
+        'TRshift', Binary, size=8, ins=(GPR, GPR), outs=EarlyClobber(GPR),
+        emit='''
+        put_t32_dp_imm(T32_AND_IMM, in_reg1, 31, out_reg0, sink);
+        put_t32_reg(bits, in_reg0, 0, out_reg0, out_reg0, sink);
+        ''')
+
+# Division which traps on a zero divisor like `Rdiv`:
+#
+#     cmp.w rm, #0
+#     bne.n 1f
+#     udf #0
+#  1: udiv rd, rn, rm
+TRdiv = EncRecipe(
+        'TRdiv', Binary, size=12, ins=(GPR, GPR), outs=GPR,
+        emit='''
+        put_t32_trapz(in_reg1, sink);
+        put_t32_reg(bits, in_reg0, 0xf, in_reg1, out_reg0, sink);
+        ''')
+
+TRsdiv = EncRecipe(
+        'TRsdiv', Binary, size=26, ins=(GPR, GPR), outs=GPR,
+        emit='''
+        put_t32_trapz(in_reg1, sink);
+        put_t32_trap_sdiv_overflow(in_reg0, in_reg1, sink);
+        put_t32_reg(bits, in_reg0, 0xf, in_reg1, out_reg0, sink);
+        ''')
+
+TRrem = EncRecipe(
+        'TRrem', Binary, size=16, ins=(GPR, GPR), outs=EarlyClobber(GPR),
+        emit='''
+        put_t32_trapz(in_reg1, sink);
+        put_t32_reg(bits, in_reg0, 0xf, in_reg1, out_reg0, sink);
+        put_t32_mls(out_reg0, in_reg1, in_reg0, out_reg0, sink);
+        ''')
+
+# Count leading zeros, `clz rd, rm`. The `Rm` register appears in both
+# halfwords.
+TRclz = EncRecipe(
+        'TRclz', Unary, size=4, ins=GPR, outs=GPR,
+        emit='put_t32_reg(bits, in_reg0, 0x8, in_reg0, out_reg0, sink);')
+
+# Count trailing zeros, `rbit rd, rm` followed by `clz rd, rd`.
+TRctz = EncRecipe(
+        'TRctz', Unary, size=8, ins=GPR, outs=GPR,
+        emit='''
+        put_t32_reg(T32_RBIT, in_reg0, 0xa, in_reg0, out_reg0, sink);
+        put_t32_reg(bits, out_reg0, 0x8, out_reg0, out_reg0, sink);
+        ''')
+
+# Integer extension, `uxtb.w rd, rm` and friends.
+TRext = EncRecipe(
+        'TRext', Unary, size=4, ins=GPR, outs=GPR,
+        emit='put_t32_reg(bits, 0, 0x8, in_reg0, out_reg0, sink);')
+
+# Add a 12-bit immediate, `addw rd, rn, #imm` or `subw rd, rn, #-imm`.
+TIaddsub = EncRecipe(
+        'TIaddsub', BinaryImm, size=4, ins=GPR, outs=GPR,
+        instp=Or(IsUnsignedInt(BinaryImm.imm, 12),
+                 IsSignedInt(BinaryImm.imm, 12)),
+        emit='put_t32_addsub_imm(bits, in_reg0, imm.into(), out_reg0, sink);')
+
+# Logical operations with an 8-bit immediate, `and.w rd, rn, #imm` and
+# friends.
+TIlogic = EncRecipe(
+        'TIlogic', BinaryImm, size=4, ins=GPR, outs=GPR,
+        instp=IsUnsignedInt(BinaryImm.imm, 8),
+        emit='put_t32_dp_imm(bits, in_reg0, imm.into(), out_reg0, sink);')
+
+# Boolean negation, `eor.w rd, rn, #1`.
+TIbnot = EncRecipe(
+        'TIbnot', Unary, size=4, ins=GPR, outs=GPR,
+        emit='put_t32_dp_imm(bits, in_reg0, 1, out_reg0, sink);')
+
+# Immediate shifts, `mov.w rd, rm, lsl #sh` and friends. The encbits are the
+# shift type.
+TIshift = EncRecipe(
+        'TIshift', BinaryImm, size=4, ins=GPR, outs=GPR,
+        emit='''
+        put_t32_shift_imm(bits, in_reg0, imm.into(), out_reg0, sink);
+        ''')
+
+# Rotate left, `mov.w rd, rm, ror #(-sh % 32)`.
+TIrotl = EncRecipe(
+        'TIrotl', BinaryImm, size=4, ins=GPR, outs=GPR,
+        emit='''
+        let sh: i64 = imm.into();
+        put_t32_shift_imm(bits, in_reg0, sh.wrapping_neg(), out_reg0, sink);
+        ''')
+
+# Constants that fit in a single instruction, `movw rd, #imm16` or
+# `mvn rd, #~imm`.
+TUmov = EncRecipe(
+        'TUmov', UnaryImm, size=4, ins=(), outs=GPR,
+        instp=Or(IsUnsignedInt(UnaryImm.imm, 16),
+                 IsSignedInt(UnaryImm.imm, 9)),
+        emit='put_t32_mov_imm(bits, imm.into(), out_reg0, sink);')
+
+# Arbitrary 32-bit constants, `movw rd, #lo16` followed by `movt rd, #hi16`.
+TUmovt = EncRecipe(
+        'TUmovt', UnaryImm, size=8, ins=(), outs=GPR,
+        emit='''
+        let imm: i64 = imm.into();
+        put_t32_movw(bits, imm as u32, out_reg0, sink);
+        put_t32_movw(bits | T32_MOVT, imm as u32 >> 16, out_reg0, sink);
+        ''')
+
+# Integer comparison, `cmp.w rn, rm` followed by an `ite` block. The `Rd`
+# field of `cmp.w` is all ones.
+#
+#     cmp.w rn, rm
+#     ite <cond>
+#     mov<cond>.w rd, #1
+#     mov<!cond>.w rd, #0
+TRicmp = EncRecipe(
+        'TRicmp', IntCompare, size=14, ins=(GPR, GPR), outs=GPR,
+        emit='''
+        put_t32_dp(bits, in_reg0, in_reg1, 0xf, sink);
+        put_t32_cset(icc_bits(cond), out_reg0, sink);
+        ''')
+
+# Select, `cmp.w rc, #0` and `ite ne` followed by 16-bit moves that can use
+# all the registers.
+TRsel = EncRecipe(
+        'TRsel', Ternary, size=10, ins=(GPR, GPR, GPR), outs=GPR,
+        emit='''
+        put_t32_dp_imm(T32_CMP_IMM, in_reg0, 0, 0xf, sink);
+        sink.put2(ite_bits(COND_NE));
+        put_t16_hireg(bits, in_reg1, out_reg0, sink);
+        put_t16_hireg(bits, in_reg2, out_reg0, sink);
+        ''')
+
+# Loads with a 12-bit positive offset or an 8-bit negative offset,
+# `ldr.w rt, [rn, #offset]` and friends.
+TIldr = EncRecipe(
+        'TIldr', Load, size=4, ins=GPR, outs=GPR,
+        instp=Or(IsUnsignedInt(Load.offset, 12), IsSignedInt(Load.offset, 8)),
+        emit='put_t32_ldst(bits, in_reg0, offset.into(), out_reg0, sink);')
+
+TSstr = EncRecipe(
+        'TSstr', Store, size=4, ins=(GPR, GPR), outs=(),
+        instp=Or(IsUnsignedInt(Store.offset, 12),
+                 IsSignedInt(Store.offset, 8)),
+        emit='put_t32_ldst(bits, in_reg1, offset.into(), in_reg0, sink);')
+
+# Spills and fills, `str.w rt, [sp, #offset]` and `ldr.w rt, [sp, #offset]`.
+TGPsp = EncRecipe(
+        'TGPsp', Unary, size=4, ins=GPR, outs=Stack(GPR),
+        emit='''
+        let offset = stack_slot_offset(func, out_ss0);
+        put_t32_ldst(bits, STACK_POINTER, offset, in_reg0, sink);
+        ''')
+
+TGPfi = EncRecipe(
+        'TGPfi', Unary, size=4, ins=Stack(GPR), outs=GPR,
+        emit='''
+        let offset = stack_slot_offset(func, in_ss0);
+        put_t32_ldst(bits, STACK_POINTER, offset, out_reg0, sink);
+        ''')
+
+# Stack pointer adjustment, `addw sp, sp, #imm` or `subw sp, sp, #-imm`.
+TIadjsp = EncRecipe(
+        'TIadjsp', UnaryImm, size=4, ins=(), outs=(),
+        instp=Or(IsUnsignedInt(UnaryImm.imm, 12),
+                 IsSignedInt(UnaryImm.imm, 12)),
+        emit='''
+        put_t32_addsub_imm(bits, STACK_POINTER, imm.into(), STACK_POINTER,
+                           sink);
+        ''')
+
+# Larger stack pointer adjustments with a 20-bit magnitude. The high bits are
+# adjusted by `add.w` or `sub.w` with a modified immediate, and the low 12
+# bits by `addw` or `subw`.
+TIadjsp2 = EncRecipe(
+        'TIadjsp2', UnaryImm, size=8, ins=(), outs=(),
+        instp=IsSignedInt(UnaryImm.imm, 20),
+        emit='''
+        let imm: i64 = imm.into();
+        let lo = if imm < 0 { -(-imm & 0xfff) } else { imm & 0xfff };
+        put_t32_addsub_modimm(STACK_POINTER, imm - lo, STACK_POINTER, sink);
+        put_t32_addsub_imm(bits, STACK_POINTER, lo, STACK_POINTER, sink);
+        ''')
+
+# Unconditional branches, `b.n offset` and `b.w offset`. The offset is
+# relative to the address of the instruction plus 4. The ranges are
+# +/- 2 KiB and +/- 16 MiB. The first halfword of `b.w` and `bl` is all
+# immediate bits, so their encbits are the second halfword instead.
+TBjump16 = EncRecipe(
+        'TBjump16', Jump, size=2, ins=(), outs=(), branch_range=(4, 12),
+        emit='''
+        let dest = func.offsets[data.destination] as i64;
+        let disp = dest - sink.offset() as i64;
+        put_t16_b(bits, disp, sink);
+        ''')
+
+TBjump = EncRecipe(
+        'TBjump', Jump, size=4, ins=(), outs=(), branch_range=(4, 25),
+        emit='''
+        let dest = func.offsets[data.destination] as i64;
+        let disp = dest - sink.offset() as i64;
+        put_t32_b(bits, disp, sink);
+        ''')
+
+# Compare a low register to zero and branch, `cmp rn, #0` followed by
+# `beq.n offset` or `bne.n offset`. The range is +/- 256 bytes relative to the
+# branch. The encbits are the condition code.
+TBz16 = EncRecipe(
+        'TBz16', Branch, size=4, ins=GPRL, outs=(), branch_range=(6, 9),
+        emit='''
+        sink.put2(T16_CMP_IMM | ((in_reg0 & 7) << 8));
+        let dest = func.offsets[data.destination] as i64;
+        let disp = dest - sink.offset() as i64;
+        put_t16_bcond(bits as u32, disp, sink);
+        ''')
+
+# Compare any register to zero and branch, `cmp.w rn, #0` followed by
+# `beq.w offset` or `bne.w offset`. The range is +/- 1 MiB.
+TBz = EncRecipe(
+        'TBz', Branch, size=8, ins=GPR, outs=(), branch_range=(8, 21),
+        emit='''
+        put_t32_dp_imm(T32_CMP_IMM, in_reg0, 0, 0xf, sink);
+        let dest = func.offsets[data.destination] as i64;
+        let disp = dest - sink.offset() as i64;
+        put_t32_bcond(bits as u32, disp, sink);
+        ''')
+
+# Compare two registers and branch, `cmp rn, rm` followed by
+# `b<cond>.n offset` or `b<cond>.w offset`. The 16-bit `cmp` instruction can
+# use all the registers. The encbits describe the `cmp` instruction.
+TBicmp16 = EncRecipe(
+        'TBicmp16', BranchIcmp, size=4, ins=(GPR, GPR), outs=(),
+        branch_range=(6, 9),
+        emit='''
+        put_t16_cmp(bits, in_reg0, in_reg1, sink);
+        let dest = func.offsets[data.destination] as i64;
+        let disp = dest - sink.offset() as i64;
+        put_t16_bcond(icc_bits(data.cond), disp, sink);
+        ''')
+
+TBicmp = EncRecipe(
+        'TBicmp', BranchIcmp, size=6, ins=(GPR, GPR), outs=(),
+        branch_range=(6, 21),
+        emit='''
+        put_t16_cmp(bits, in_reg0, in_reg1, sink);
+        let dest = func.offsets[data.destination] as i64;
+        let disp = dest - sink.offset() as i64;
+        put_t32_bcond(icc_bits(data.cond), disp, sink);
+        ''')
+
+# Unconditional trap, `udf #0`.
+TUdf = EncRecipe(
+        'TUdf', Nullary, size=2, ins=(), outs=(),
+        emit='sink.put2(T16_UDF);')
+
+# Conditional trap on a low register, `cmp rn, #0` followed by `beq.n` or
+# `bne.n` skipping over `udf #0` unless the trap condition holds. The encbits
+# are the condition code of the skipping branch.
+TBtrap16 = EncRecipe(
+        'TBtrap16', Unary, size=6, ins=GPRL, outs=(),
+        emit='''
+        sink.put2(T16_CMP_IMM | ((in_reg0 & 7) << 8));
+        put_t16_bcond(bits as u32, 4, sink);
+        sink.put2(T16_UDF);
+        ''')
+
+# Conditional trap on any register, using `cmp.w rn, #0` instead.
+TBtrap = EncRecipe(
+        'TBtrap', Unary, size=8, ins=GPR, outs=(),
+        emit='''
+        put_t32_dp_imm(T32_CMP_IMM, in_reg0, 0, 0xf, sink);
+        put_t16_bcond(bits as u32, 4, sink);
+        sink.put2(T16_UDF);
+        ''')
+
+# Return to the caller, `bx lr`.
+TBret = EncRecipe(
+        'TBret', Return, size=2, ins=(), outs=(),
+        emit='sink.put2(bits);')
+
+# Direct function call, `bl sym`, with an `R_ARM_THM_CALL` relocation. The
+# implicit addend is the PC offset of -4.
+TBcall = EncRecipe(
+        'TBcall', Call, size=4, ins=(), outs=(),
+        emit='''
+        sink.reloc_external(RelocKind::ThmCall.into(),
+                            &func.dfg.ext_funcs[data.func_ref].name);
+        put_t32_b(bits, 0, sink);
+        ''')
+
+# Absolute address of a symbol, `movw rd, #:lower16:sym` followed by
+# `movt rd, #:upper16:sym`.
+TUgsym = EncRecipe(
+        'TUgsym', UnaryGlobalVar, size=8, ins=(), outs=GPR,
+        emit='''
+        let name = globalsym_name(func, global_var);
+        sink.reloc_external(RelocKind::ThmMovwAbsNc.into(), name);
+        put_t32_movw(bits, 0, out_reg0, sink);
+        sink.reloc_external(RelocKind::ThmMovtAbs.into(), name);
+        put_t32_movw(bits | T32_MOVT, 0, out_reg0, sink);
+        ''')
+
+# Memory barrier, `dmb ish`.
+TIfence = EncRecipe(
+        'TIfence', Fence, size=4, ins=(), outs=(),
+        emit='put_t32_word(T32_DMB_ISH, sink);')
+
+# Floating point comparisons, `vcmp` and `vmrs APSR_nzcv, fpscr` followed by
+# an `ite` block like `TRicmp`.
+TRfcmps = EncRecipe(
+        'TRfcmps', FloatCompare, size=18, ins=(SPR, SPR), outs=GPR,
+        emit='''
+        put_vfp2(bits, in_reg1, in_reg0, sink);
+        put_t32_word(VMRS, sink);
+        put_t32_cset(fcc_bits(cond), out_reg0, sink);
+        ''')
+
+TRfcmpd = EncRecipe(
+        'TRfcmpd', FloatCompare, size=18, ins=(DPR, DPR), outs=GPR,
+        emit='''
+        put_vfp2(bits, in_reg1, in_reg0, sink);
+        put_t32_word(VMRS, sink);
+        put_t32_cset(fcc_bits(cond), out_reg0, sink);
+        ''')
+
+# 16-bit T32 recipes. Most of them can only use the low registers, and the
+# flag-setting forms are used since they don't need an `it` block.
+
+# Add and subtract with three low registers, `adds rd, rn, rm`.
+TRrr16 = EncRecipe(
+        'TRrr16', Binary, size=2, ins=(GPRL, GPRL), outs=GPRL,
+        emit='put_t16_rrr(bits, in_reg0, in_reg1, out_reg0, sink);')
+
+# Two-operand operations on low registers where the result overwrites the
+# first operand, `ands rdn, rm` and friends. The multiplication
+# `muls rdm, rn, rdm` is commutative, so it works the same way.
+TRtied16 = EncRecipe(
+        'TRtied16', Binary, size=2, ins=(GPRL, GPRL), outs=0,
+        emit='put_t16_rr(bits, in_reg1, in_reg0, sink);')
+
+# Add with any registers where the result overwrites the first operand,
+# `add rdn, rm`.
+TRadd16 = EncRecipe(
+        'TRadd16', Binary, size=2, ins=(GPR, GPR), outs=0,
+        emit='put_t16_hireg(bits, in_reg1, in_reg0, sink);')
+
+# Register copies with any registers, `mov rd, rm`.
+TRmov16 = EncRecipe(
+        'TRmov16', Unary, size=2, ins=GPR, outs=GPR,
+        emit='put_t16_hireg(bits, in_reg0, out_reg0, sink);')
+
+# One-operand operations on low registers, `mvns rd, rm` and the integer
+# extensions like `uxtb rd, rm`.
+TRr16 = EncRecipe(
+        'TRr16', Unary, size=2, ins=GPRL, outs=GPRL,
+        emit='put_t16_rr(bits, in_reg0, out_reg0, sink);')
+
+# Immediate shifts on low registers, `lsls rd, rm, #sh` and friends. A zero
+# shift is always encoded as `lsls rd, rm, #0`.
+TIshift16 = EncRecipe(
+        'TIshift16', BinaryImm, size=2, ins=GPRL, outs=GPRL,
+        emit='put_t16_shift_imm(bits, in_reg0, imm.into(), out_reg0, sink);')
+
+# Add an 8-bit immediate to a low register in place, `adds rdn, #imm` or
+# `subs rdn, #-imm`.
+TIaddsub16 = EncRecipe(
+        'TIaddsub16', BinaryImm, size=2, ins=GPRL, outs=0,
+        instp=Or(IsUnsignedInt(BinaryImm.imm, 8),
+                 IsSignedInt(BinaryImm.imm, 8)),
+        emit='put_t16_addsub_imm(bits, imm.into(), in_reg0, sink);')
+
+# Small constants in low registers, `movs rd, #imm8`.
+TUmov16 = EncRecipe(
+        'TUmov16', UnaryImm, size=2, ins=(), outs=GPRL,
+        instp=IsUnsignedInt(UnaryImm.imm, 8),
+        emit='''
+        let imm: i64 = imm.into();
+        sink.put2(bits | ((out_reg0 & 7) << 8) | imm as u16);
+        ''')
+
+# Loads and stores with low registers and a scaled 5-bit offset,
+# `ldr rt, [rn, #offset]` and friends. The range depends on the access size,
+# so the encodings provide the instruction predicate.
+TIldr16 = EncRecipe(
+        'TIldr16', Load, size=2, ins=GPRL, outs=GPRL,
+        emit='put_t16_ldst(bits, in_reg0, offset.into(), out_reg0, sink);')
+
+TSstr16 = EncRecipe(
+        'TSstr16', Store, size=2, ins=(GPRL, GPRL), outs=(),
+        emit='put_t16_ldst(bits, in_reg1, offset.into(), in_reg0, sink);')
+
+# VFP recipes shared by both instruction sets. There are separate recipes for
+# single and double precision registers.
+
+# Floating point arithmetic, `vadd.f32 sd, sn, sm` and friends.
+Rfs = EncRecipe(
+        'Rfs', Binary, size=4, ins=(SPR, SPR), outs=SPR,
+        emit='put_vfp3(bits, in_reg0, in_reg1, out_reg0, sink);')
+
+Rfd = EncRecipe(
+        'Rfd', Binary, size=4, ins=(DPR, DPR), outs=DPR,
+        emit='put_vfp3(bits, in_reg0, in_reg1, out_reg0, sink);')
+
+# One-operand floating point instructions, `vsqrt.f32 sd, sm` and friends.
+# This includes register copies, `vmov.f32 sd, sm`.
+Rffs = EncRecipe(
+        'Rffs', Unary, size=4, ins=SPR, outs=SPR,
+        emit='put_vfp2(bits, in_reg0, out_reg0, sink);')
+
+Rffd = EncRecipe(
+        'Rffd', Unary, size=4, ins=DPR, outs=DPR,
+        emit='put_vfp2(bits, in_reg0, out_reg0, sink);')
+
+# Conversions between single and double precision, `vcvt.f64.f32 dd, sm` and
+# `vcvt.f32.f64 sd, dm`. The `sz` bit describes the operand.
+Rfpromote = EncRecipe(
+        'Rfpromote', Unary, size=4, ins=SPR, outs=DPR,
+        emit='put_vfp_cvt(bits, in_reg0, out_reg0, sink);')
+
+Rfdemote = EncRecipe(
+        'Rfdemote', Unary, size=4, ins=DPR, outs=SPR,
+        emit='put_vfp_cvt(bits, in_reg0, out_reg0, sink);')
+
+# Conversions from integers, `vmov sd, rt` followed by
+# `vcvt.f32.s32 sd, sd`. A double precision result uses its low half as the
+# temporary register, so it must be one of `d0`-`d15`. The encbits describe
+# the `vcvt` instruction.
+Rifs = EncRecipe(
+        'Rifs', Unary, size=8, ins=GPR, outs=SPR,
+        emit='put_vfp_from_int(bits, in_reg0, out_reg0, sink);')
+
+Rifd = EncRecipe(
+        'Rifd', Unary, size=8, ins=GPR, outs=DPRL,
+        emit='put_vfp_from_int(bits, in_reg0, out_reg0, sink);')
+
+# Moves between core registers and single precision registers,
+# `vmov rt, sn` and `vmov sn, rt`.
+Rmovrs = EncRecipe(
+        'Rmovrs', Unary, size=4, ins=SPR, outs=GPR,
+        emit='put_vmov(bits, out_reg0, in_reg0, sink);')
+
+Rmovsr = EncRecipe(
+        'Rmovsr', Unary, size=4, ins=GPR, outs=SPR,
+        emit='put_vmov(bits, in_reg0, out_reg0, sink);')
+
+# Floating point loads and stores, `vldr sd, [rn, #offset]`. The magnitude of
+# the offset is an 8-bit immediate scaled by 4.
+Fldrs = EncRecipe(
+        'Fldrs', Load, size=4, ins=GPR, outs=SPR,
+        instp=Or(IsUnsignedInt(Load.offset, 10, 2),
+                 IsSignedInt(Load.offset, 10, 2)),
+        emit='put_vldst(bits, in_reg0, offset.into(), out_reg0, sink);')
+
+Fldrd = EncRecipe(
+        'Fldrd', Load, size=4, ins=GPR, outs=DPR,
+        instp=Or(IsUnsignedInt(Load.offset, 10, 2),
+                 IsSignedInt(Load.offset, 10, 2)),
+        emit='put_vldst(bits, in_reg0, offset.into(), out_reg0, sink);')
+
+Fstrs = EncRecipe(
+        'Fstrs', Store, size=4, ins=(SPR, GPR), outs=(),
+        instp=Or(IsUnsignedInt(Store.offset, 10, 2),
+                 IsSignedInt(Store.offset, 10, 2)),
+        emit='put_vldst(bits, in_reg1, offset.into(), in_reg0, sink);')
+
+Fstrd = EncRecipe(
+        'Fstrd', Store, size=4, ins=(DPR, GPR), outs=(),
+        instp=Or(IsUnsignedInt(Store.offset, 10, 2),
+                 IsSignedInt(Store.offset, 10, 2)),
+        emit='put_vldst(bits, in_reg1, offset.into(), in_reg0, sink);')
+
+# Spill and fill floating point registers, `vstr sd, [sp, #offset]` and
+# `vldr sd, [sp, #offset]`.
+FPsps = EncRecipe(
+        'FPsps', Unary, size=4, ins=SPR, outs=Stack(SPR),
+        emit='''
+        let offset = stack_slot_offset(func, out_ss0);
+        put_vldst(bits, STACK_POINTER, offset, in_reg0, sink);
+        ''')
+
+FPspd = EncRecipe(
+        'FPspd', Unary, size=4, ins=DPR, outs=Stack(DPR),
+        emit='''
+        let offset = stack_slot_offset(func, out_ss0);
+        put_vldst(bits, STACK_POINTER, offset, in_reg0, sink);
+        ''')
+
+FPfis = EncRecipe(
+        'FPfis', Unary, size=4, ins=Stack(SPR), outs=SPR,
+        emit='''
+        let offset = stack_slot_offset(func, in_ss0);
+        put_vldst(bits, STACK_POINTER, offset, out_reg0, sink);
+        ''')
+
+FPfid = EncRecipe(
+        'FPfid', Unary, size=4, ins=Stack(DPR), outs=DPR,
+        emit='''
+        let offset = stack_slot_offset(func, in_ss0);
+        put_vldst(bits, STACK_POINTER, offset, out_reg0, sink);
+        ''')
+
+# NEON recipes shared by both instruction sets.
+
+# Vector arithmetic on quad registers, `vadd.i32 qd, qn, qm` and friends.
+Rq = EncRecipe(
+        'Rq', Binary, size=4, ins=(QPR, QPR), outs=QPR,
+        emit='put_neon3(bits, in_reg0, in_reg1, out_reg0, sink);')
+
+# Quad register copies, `vorr qd, qm, qm`.
+Rqmov = EncRecipe(
+        'Rqmov', Unary, size=4, ins=QPR, outs=QPR,
+        emit='put_neon3(bits, in_reg0, in_reg0, out_reg0, sink);')
+
+# Vector loads and stores without an offset, `vld1.64 {dd, dd+1}, [rn]` and
+# `vst1.64 {dd, dd+1}, [rn]`. Zero is the only offset that fits in a 1-bit
+# signed integer. The encbits are the `L` bit.
+Qld = EncRecipe(
+        'Qld', Load, size=4, ins=GPR, outs=QPR,
+        instp=IsSignedInt(Load.offset, 1),
+        emit='put_vld1(bits, in_reg0, out_reg0, sink);')
+
+Qst = EncRecipe(
+        'Qst', Store, size=4, ins=(QPR, GPR), outs=(),
+        instp=IsSignedInt(Store.offset, 1),
+        emit='put_vld1(bits, in_reg1, in_reg0, sink);')
+
+# Spill and fill quad registers as two double precision halves,
+# `vstr dd, [sp, #offset]` followed by `vstr dd+1, [sp, #offset+8]`. The
+# encbits describe the `vstr` or `vldr` instruction.
+QPsp = EncRecipe(
+        'QPsp', Unary, size=8, ins=QPR, outs=Stack(QPR),
+        emit='''
+        let offset = stack_slot_offset(func, out_ss0);
+        put_vldst(bits, STACK_POINTER, offset, in_reg0, sink);
+        put_vldst(bits, STACK_POINTER, offset + 8, in_reg0 + 2, sink);
+        ''')
+
+QPfi = EncRecipe(
+        'QPfi', Unary, size=8, ins=Stack(QPR), outs=QPR,
+        emit='''
+        let offset = stack_slot_offset(func, in_ss0);
+        put_vldst(bits, STACK_POINTER, offset, out_reg0, sink);
+        put_vldst(bits, STACK_POINTER, offset + 8, out_reg0 + 2, sink);
+        ''')
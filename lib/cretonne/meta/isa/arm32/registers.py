@@ -0,0 +1,42 @@
+"""
+ARM32 register banks.
+"""
+from __future__ import absolute_import
+from cretonne.registers import RegBank, RegClass
+from .defs import isa
+
+
+# The stack pointer `r13`, the link register `r14`, and the program counter
+# `r15` are included in the bank so they can be encoded, but they are
+# reserved.
+IntRegs = RegBank(
+        'IntRegs', isa,
+        'General purpose registers',
+        units=16, prefix='r')
+
+# The floating point registers. Each single precision register `sN` is one
+# register unit. The double precision register `dN` overlaps `s2N` and
+# `s2N+1`, and the quad register `qN` overlaps `d2N` and `d2N+1`.
+#
+# Only `d0`-`d15` have single precision halves. The units above `s31` name
+# the halves of `d16`-`d31` which only exist with NEON.
+FloatRegs = RegBank(
+        'FloatRegs', isa,
+        'Floating point and NEON registers',
+        units=64, prefix='s')
+
+GPR = RegClass('GPR', IntRegs)
+# The low registers `r0`-`r7` which can be used by most 16-bit T32
+# instructions.
+GPRL = RegClass('GPRL', IntRegs, start=0, count=8)
+
+SPR = RegClass('SPR', FloatRegs, start=0, count=32)
+DPR = RegClass('DPR', FloatRegs, width=2)
+
+# The double precision registers `d0`-`d15` which have single precision
+# halves.
+DPRL = RegClass('DPRL', FloatRegs, width=2, count=16)
+
+# The quad registers `q0`-`q15` used by NEON. Without NEON, only `q0`-`q7`
+# exist.
+QPR = RegClass('QPR', FloatRegs, width=4)
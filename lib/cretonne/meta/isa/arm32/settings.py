@@ -0,0 +1,26 @@
+"""
+ARM32 settings.
+"""
+from __future__ import absolute_import
+from cretonne import SettingGroup, BoolSetting
+from cretonne.predicates import And
+import cretonne.settings as shared
+from .defs import isa
+
+isa.settings = SettingGroup('arm32', parent=shared.group)
+
+is_thumb = BoolSetting("Generate T32 (Thumb-2) code instead of A32")
+
+has_vfp = BoolSetting("CPU supports the VFPv3 floating point instructions")
+has_neon = BoolSetting(
+        """
+        CPU supports the NEON Advanced SIMD instructions.
+
+        NEON implies 32 double precision registers.
+        """)
+has_hwdiv = BoolSetting("CPU supports the SDIV and UDIV instructions")
+
+use_vfp = And(has_vfp, shared.enable_float)
+use_neon = And(has_neon, shared.enable_simd)
+
+isa.settings.close(globals())
@@ -0,0 +1,24 @@
+"""
+ARM64 Target
+------------
+
+ARM64 is the 64-bit execution state of the ARMv8-A architecture, also known as
+AArch64. Its A64 instruction set has fixed-width 32-bit instructions and 31
+general purpose registers. The base ISA includes integer multiplication and
+division. Optional features are:
+
+FP / Advanced SIMD
+    Floating point and vector instructions. These are present in almost all
+    implementations, but they can be disabled for kernel code.
+
+LSE
+    The Large System Extensions from ARMv8.1 with atomic read-modify-write and
+    compare-and-swap instructions.
+
+"""
+from __future__ import absolute_import
+from . import defs
+from . import encodings, settings, registers  # noqa
+
+# Re-export the primary target ISA definition.
+isa = defs.isa.finish()
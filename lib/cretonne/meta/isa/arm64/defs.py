@@ -0,0 +1,14 @@
+"""
+ARM64 definitions.
+
+Commonly used definitions.
+"""
+from __future__ import absolute_import
+from cretonne import TargetISA, CPUMode
+import cretonne.base
+
+isa = TargetISA('arm64', [cretonne.base.instructions])
+
+# The A64 instruction set is the only CPU mode. The 32-bit operations use the
+# `w` views of the 64-bit registers, and they clear the high 32 bits.
+A64 = CPUMode('A64', isa)
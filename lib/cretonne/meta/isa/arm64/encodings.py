@@ -0,0 +1,339 @@
+"""
+ARM64 Encodings.
+"""
+from __future__ import absolute_import
+from cretonne import base
+from cretonne.formats import UnaryImm, BinaryImm, Load, Store, FloatCompare
+from cretonne.formats import AtomicRmw, AtomicLoad, AtomicStore
+from cretonne.immediates import floatcc, atomicop, ordering
+from cretonne.types import i8, i16, i32, i64, f32, f64
+from cretonne.legalize import widen, expand
+from cretonne.predicates import IsEqual, IsSignedInt, IsUnsignedInt
+from cretonne.predicates import IsLogicalImm
+from .defs import A64
+from .recipes import ADDSUB, LOGIC, DP1, DP2, DP3, ADDSUBIMM, LOGIMM
+from .recipes import MOVEWIDE, BITFIELD, EXTR, CSEL, CBZ, BIMM, RET, LDST
+from .recipes import FP2, FP1, FCMP, FMADD, FCVTI, LSE, CAS, LDAR, STLR
+from .recipes import Rrr, Rr, Rctz, Rmov, Rmul, Rdiv, Rsdiv, Rrem
+from .recipes import Iaddsub, Iadjsp, Iadjsp2, Ilogic, Ibnot, Ulogic, Umov
+from .recipes import Umovk, Umovk64, Ishl, Ishr, Irotr, Irotl, Rext, Ricmp, Rsel
+from .recipes import Ildr, Ildur, Sstr, Sstur, GPsp, GPfi
+from .recipes import Rf, Rff, Rfi, Rif, Rfma, Rfcmp
+from .recipes import Fldr, Fldur, Fstr, Fstur, FPsp, FPfi
+from .recipes import Ramo, Rcas, Iaload, Sastore, Ialdar, Sastlr, Ifence
+from .recipes import Bcall, Ugsym, Bjump, CBz, CBzLong, Bicmp, BicmpLong
+from .recipes import Bret, Udf, CBtrap
+from .settings import use_fp, use_lse
+
+# Most integer instructions have 32-bit and 64-bit forms selected by the `sf`
+# bit. The 32-bit forms use the `w` registers and clear the high 32 bits of
+# the result.
+for ty,  sf, width in [
+        (i32, 0, 32),
+        (i64, 1, 64)
+        ]:
+    A64.enc(base.iadd.bind(ty), Rrr, ADDSUB(sf, 0))
+    A64.enc(base.isub.bind(ty), Rrr, ADDSUB(sf, 1))
+
+    # Logical operations have immediate forms when the constant is a
+    # repeating pattern of rotated runs of ones. Other constants are
+    # materialized in a register by the legalizer.
+    for inst,           inst_imm,      opc in [
+            (base.band, base.band_imm, 0b00),
+            (base.bor,  base.bor_imm,  0b01),
+            (base.bxor, base.bxor_imm, 0b10)
+            ]:
+        A64.enc(inst.bind(ty), Rrr, LOGIC(sf, opc))
+        A64.enc(inst_imm.bind(ty), Ilogic, LOGIMM(sf, opc),
+                instp=IsLogicalImm(BinaryImm.imm, width))
+
+    A64.enc(base.iadd_imm.bind(ty), Iaddsub, ADDSUBIMM(sf, 0))
+
+    # Register copies are `orr rd, xzr, rm`, and `bnot` is `orn rd, xzr, rm`.
+    A64.enc(base.copy.bind(ty), Rmov, LOGIC(sf, 0b01))
+    A64.enc(base.bnot.bind(ty), Rmov, LOGIC(sf, 0b01, 1))
+
+    A64.enc(base.imul.bind(ty), Rmul, DP3(sf, 0b000, 0))
+
+    # Division and remainder check for the trapping conditions before the
+    # division instruction, which produces 0 instead of trapping.
+    A64.enc(base.udiv.bind(ty), Rdiv, DP2(sf, 0b000010))
+    A64.enc(base.sdiv.bind(ty), Rsdiv, DP2(sf, 0b000011))
+    A64.enc(base.urem.bind(ty), Rrem, DP2(sf, 0b000010))
+    A64.enc(base.srem.bind(ty), Rrem, DP2(sf, 0b000011))
+
+    # Dynamic shifts have the same masking semantics as the cton base
+    # instructions. Only the low bits of the shift amount register are used,
+    # so any integer type works for the amount.
+    for inst,           opcode in [
+            (base.ishl, 0b001000),
+            (base.ushr, 0b001001),
+            (base.sshr, 0b001010),
+            (base.rotr, 0b001011)
+            ]:
+        for amt in [i8, i16, i32, i64]:
+            A64.enc(inst.bind(ty).bind(amt), Rrr, DP2(sf, opcode))
+
+    # Immediate shifts are aliases of `ubfm`, `sbfm`, and `extr`.
+    A64.enc(base.ishl_imm.bind(ty), Ishl, BITFIELD(sf, 0b10))
+    A64.enc(base.ushr_imm.bind(ty), Ishr, BITFIELD(sf, 0b10))
+    A64.enc(base.sshr_imm.bind(ty), Ishr, BITFIELD(sf, 0b00))
+    A64.enc(base.rotr_imm.bind(ty), Irotr, EXTR(sf))
+    A64.enc(base.rotl_imm.bind(ty), Irotl, EXTR(sf))
+
+    A64.enc(base.clz.bind(ty), Rr, DP1(sf, 0b000100))
+    A64.enc(base.cls.bind(ty), Rr, DP1(sf, 0b000101))
+    A64.enc(base.ctz.bind(ty), Rctz, DP1(sf, 0b000100))
+
+    # Integer constants. The general `movz` + `movk` sequence is listed last,
+    # so it is picked before register allocation. Branch relaxation switches
+    # to a single instruction when the constant allows it.
+    A64.enc(base.iconst.bind(ty), Umov, MOVEWIDE(sf, 0b10))
+    A64.enc(base.iconst.bind(ty), Ulogic, LOGIMM(sf, 0b01),
+            instp=IsLogicalImm(UnaryImm.imm, width))
+    A64.enc(base.iconst.bind(ty), Umovk if sf == 0 else Umovk64,
+            MOVEWIDE(sf, 0b10))
+
+    # All the integer condition codes are supported by `cset` and `b.cond`.
+    A64.enc(base.icmp.bind(ty), Ricmp, ADDSUB(sf, 1, 1))
+    A64.enc(base.select.bind(ty).b1, Rsel, CSEL(sf, 0, 0))
+
+    # Control flow. Branch relaxation picks the short encodings when the
+    # destination is in range.
+    for recipe in [CBz, CBzLong]:
+        A64.enc(base.brz.bind(ty), recipe, CBZ(sf, 0))
+        A64.enc(base.brnz.bind(ty), recipe, CBZ(sf, 1))
+    for recipe in [Bicmp, BicmpLong]:
+        A64.enc(base.br_icmp.bind(ty), recipe, ADDSUB(sf, 1, 1))
+
+    # Conditional traps skip over a `udf` with `cbnz` or `cbz`.
+    A64.enc(base.trapz.bind(ty), CBtrap, CBZ(sf, 1))
+    A64.enc(base.trapnz.bind(ty), CBtrap, CBZ(sf, 0))
+
+# Integer conversions. The small integer types only appear as the inputs and
+# outputs of these instructions. All other operations on `i8` and `i16` values
+# are widened.
+A64.legalize_type(i8=widen, i16=widen)
+
+# Truncation copies the low 32 bits with `mov wd, wn`, clearing the high bits.
+for inst in [base.ireduce.i8, base.ireduce.i16]:
+    A64.enc(inst.i32, Rmov, LOGIC(0, 0b01))
+    A64.enc(inst.i64, Rmov, LOGIC(0, 0b01))
+A64.enc(base.ireduce.i32.i64, Rmov, LOGIC(0, 0b01))
+
+# The 32-bit bitfield instructions clear the high bits, so zero extension to
+# 64 bits can use them too, but sign extension needs the 64-bit form.
+for inst,         opc in [
+        (base.uextend, 0b10),
+        (base.sextend, 0b00)
+        ]:
+    A64.enc(inst.i32.i8, Rext, BITFIELD(0, opc))
+    A64.enc(inst.i32.i16, Rext, BITFIELD(0, opc))
+    A64.enc(inst.i64.i8, Rext, BITFIELD(1, opc))
+    A64.enc(inst.i64.i16, Rext, BITFIELD(1, opc))
+    A64.enc(inst.i64.i32, Rext, BITFIELD(1, opc))
+
+# Loads and stores. The `size` field is the log2 of the access size, and the
+# `opc` field selects a load, a store, or a sign-extending load to 32 or 64
+# bits. Addresses are always 64-bit registers, so both pointer types work. The
+# `i32` pointers used in the ILP32 data model are zero-extended in their
+# registers.
+#
+# The unscaled `ldur` form is listed first, so the scaled `ldr` form is
+# picked when the offset allows both.
+for ptr in [i32, i64]:
+    for inst,           ty,  size, opc in [
+            (base.load,    i32, 0b10, 0b01),
+            (base.load,    i64, 0b11, 0b01),
+            (base.uload8,  i32, 0b00, 0b01),
+            (base.uload8,  i64, 0b00, 0b01),
+            (base.sload8,  i32, 0b00, 0b11),
+            (base.sload8,  i64, 0b00, 0b10),
+            (base.uload16, i32, 0b01, 0b01),
+            (base.uload16, i64, 0b01, 0b01),
+            (base.sload16, i32, 0b01, 0b11),
+            (base.sload16, i64, 0b01, 0b10),
+            (base.uload32, i64, 0b10, 0b01),
+            (base.sload32, i64, 0b10, 0b10)
+            ]:
+        A64.enc(inst.bind(ty, ptr), Ildur, LDST(size, 0, opc))
+        A64.enc(inst.bind(ty, ptr), Ildr, LDST(size, 0, opc),
+                instp=IsUnsignedInt(Load.offset, 12 + size, size))
+
+    for inst,            ty,  size in [
+            (base.store,    i32, 0b10),
+            (base.store,    i64, 0b11),
+            (base.istore8,  i32, 0b00),
+            (base.istore8,  i64, 0b00),
+            (base.istore16, i32, 0b01),
+            (base.istore16, i64, 0b01),
+            (base.istore32, i64, 0b10)
+            ]:
+        A64.enc(inst.bind(ty, ptr), Sstur, LDST(size, 0, 0b00))
+        A64.enc(inst.bind(ty, ptr), Sstr, LDST(size, 0, 0b00),
+                instp=IsUnsignedInt(Store.offset, 12 + size, size))
+
+# Spills and fills use stack pointer relative loads and stores.
+A64.enc(base.spill.i32, GPsp, LDST(0b10, 0, 0b00))
+A64.enc(base.spill.i64, GPsp, LDST(0b11, 0, 0b00))
+A64.enc(base.fill.i32, GPfi, LDST(0b10, 0, 0b01))
+A64.enc(base.fill.i64, GPfi, LDST(0b11, 0, 0b01))
+
+# The stack frame is allocated and deallocated by adjusting the stack pointer.
+A64.enc(base.adjust_sp_imm, Iadjsp, ADDSUBIMM(1, 0))
+A64.enc(base.adjust_sp_imm, Iadjsp2, ADDSUBIMM(1, 0))
+
+A64.enc(base.jump, Bjump, BIMM(0))
+A64.enc(base.x_return, Bret, RET())
+A64.enc(base.trap, Udf, 0)
+
+# Direct calls and symbol addresses need relocations.
+A64.enc(base.call, Bcall, BIMM(1))
+A64.enc(base.globalsym_addr.i64, Ugsym, ADDSUBIMM(1, 0))
+
+# Boolean operations on `b1` values, like the results of comparisons.
+for inst,           opc in [
+        (base.band, 0b00),
+        (base.bor,  0b01),
+        (base.bxor, 0b10)
+        ]:
+    A64.enc(inst.b1, Rrr, LOGIC(0, opc))
+A64.enc(base.bnot.b1, Ibnot, LOGIMM(0, 0b10))
+for recipe in [CBz, CBzLong]:
+    A64.enc(base.brz.b1, recipe, CBZ(0, 0))
+    A64.enc(base.brnz.b1, recipe, CBZ(0, 1))
+A64.enc(base.trapz.b1, CBtrap, CBZ(0, 1))
+A64.enc(base.trapnz.b1, CBtrap, CBZ(0, 0))
+
+# Floating point and Advanced SIMD. Gated by the `use_fp` flag. The `type`
+# field selects single or double precision.
+for ty,  ftype, size in [
+        (f32, 0b00, 0b10),
+        (f64, 0b01, 0b11)
+        ]:
+    for inst,              opcode in [
+            (base.fmul,    0b0000),
+            (base.fdiv,    0b0001),
+            (base.fadd,    0b0010),
+            (base.fsub,    0b0011),
+            (base.fmax,    0b0100),
+            (base.fmin,    0b0101),
+            (base.fmaxnum, 0b0110),
+            (base.fminnum, 0b0111)
+            ]:
+        A64.enc(inst.bind(ty), Rf, FP2(ftype, opcode), isap=use_fp)
+
+    for inst,            opcode in [
+            (base.copy,    0b000000),
+            (base.fabs,    0b000001),
+            (base.fneg,    0b000010),
+            (base.sqrt,    0b000011),
+            (base.nearest, 0b001000),
+            (base.ceil,    0b001001),
+            (base.floor,   0b001010),
+            (base.trunc,   0b001011)
+            ]:
+        A64.enc(inst.bind(ty), Rff, FP1(ftype, opcode), isap=use_fp)
+
+    A64.enc(base.fma.bind(ty), Rfma, FMADD(ftype), isap=use_fp)
+
+    # The flags set by `fcmp` can express all the condition codes except
+    # `one` and `ueq`, which are expanded by the legalizer.
+    for cond in ['ord', 'uno', 'eq', 'ne', 'lt', 'le', 'gt', 'ge',
+                 'ult', 'ule', 'ugt', 'uge']:
+        A64.enc(base.fcmp.bind(ty), Rfcmp, FCMP(ftype),
+                instp=IsEqual(FloatCompare.cond, getattr(floatcc, cond)),
+                isap=use_fp)
+
+    for ptr in [i32, i64]:
+        A64.enc(base.load.bind(ty, ptr), Fldur, LDST(size, 1, 0b01),
+                isap=use_fp)
+        A64.enc(base.load.bind(ty, ptr), Fldr, LDST(size, 1, 0b01),
+                instp=IsUnsignedInt(Load.offset, 12 + size, size),
+                isap=use_fp)
+        A64.enc(base.store.bind(ty, ptr), Fstur, LDST(size, 1, 0b00),
+                isap=use_fp)
+        A64.enc(base.store.bind(ty, ptr), Fstr, LDST(size, 1, 0b00),
+                instp=IsUnsignedInt(Store.offset, 12 + size, size),
+                isap=use_fp)
+    A64.enc(base.spill.bind(ty), FPsp, LDST(size, 1, 0b00), isap=use_fp)
+    A64.enc(base.fill.bind(ty), FPfi, LDST(size, 1, 0b01), isap=use_fp)
+
+    # Conversions to and from integers. Conversions to integers round towards
+    # zero. Note that the A64 instructions saturate instead of trapping when
+    # the result is out of range.
+    for ity, sf in [(i32, 0), (i64, 1)]:
+        for inst,                 rmode, opcode in [
+                (base.fcvt_to_sint, 0b11, 0b000),
+                (base.fcvt_to_uint, 0b11, 0b001)
+                ]:
+            A64.enc(inst.bind(ity, ty), Rfi, FCVTI(sf, ftype, rmode, opcode),
+                    isap=use_fp)
+        for inst,                   rmode, opcode in [
+                (base.fcvt_from_sint, 0b00, 0b010),
+                (base.fcvt_from_uint, 0b00, 0b011)
+                ]:
+            A64.enc(inst.bind(ty, ity), Rif, FCVTI(sf, ftype, rmode, opcode),
+                    isap=use_fp)
+
+# Conversions between single and double precision.
+A64.enc(base.fpromote.f64.f32, Rff, FP1(0b00, 0b000101), isap=use_fp)
+A64.enc(base.fdemote.f32.f64, Rff, FP1(0b01, 0b000100), isap=use_fp)
+
+# Moves between integer and floating point registers with `fmov`.
+A64.enc(base.bitcast.i32.f32, Rfi, FCVTI(0, 0b00, 0b00, 0b110), isap=use_fp)
+A64.enc(base.bitcast.f32.i32, Rif, FCVTI(0, 0b00, 0b00, 0b111), isap=use_fp)
+A64.enc(base.bitcast.i64.f64, Rfi, FCVTI(1, 0b01, 0b00, 0b110), isap=use_fp)
+A64.enc(base.bitcast.f64.i64, Rif, FCVTI(1, 0b01, 0b00, 0b111), isap=use_fp)
+
+# Atomic memory operations from the ARMv8.1 Large System Extensions. There is
+# no atomic `and` instruction, only `ldclr` which clears the bits of its
+# operand, so `and` becomes a library call.
+#
+# Aligned loads and stores are always atomic, and the load-acquire and
+# store-release instructions are part of the base ISA, but they must agree
+# with the library calls used for read-modify-write operations when LSE isn't
+# available. All the atomic encodings are therefore predicated on `use_lse`.
+for ty,  size in [
+        (i8,  0b00),
+        (i16, 0b01),
+        (i32, 0b10),
+        (i64, 0b11)
+        ]:
+    for ptr in [i32, i64]:
+        # The `or` enumerator is a Python keyword, so look it up by name.
+        for op,     o3, opc in [
+                ('add',  0, 0b000),
+                ('xchg', 1, 0b000),
+                ('xor',  0, 0b010),
+                ('or',   0, 0b011),
+                ('smax', 0, 0b100),
+                ('smin', 0, 0b101),
+                ('umax', 0, 0b110),
+                ('umin', 0, 0b111)
+                ]:
+            A64.enc(base.atomic_rmw.bind(ty, ptr), Ramo, LSE(size, o3, opc),
+                    instp=IsEqual(AtomicRmw.op, getattr(atomicop, op)),
+                    isap=use_lse)
+        A64.enc(base.atomic_cas.bind(ty, ptr), Rcas, CAS(size), isap=use_lse)
+
+        A64.enc(base.atomic_load.bind(ty, ptr), Iaload, LDST(size, 0, 0b01),
+                instp=IsEqual(AtomicLoad.ordering, ordering.relaxed),
+                isap=use_lse)
+        for order in [ordering.acquire, ordering.seq_cst]:
+            A64.enc(base.atomic_load.bind(ty, ptr), Ialdar, LDAR(size),
+                    instp=IsEqual(AtomicLoad.ordering, order), isap=use_lse)
+
+        A64.enc(base.atomic_store.bind(ty, ptr), Sastore, LDST(size, 0, 0b00),
+                instp=IsEqual(AtomicStore.ordering, ordering.relaxed),
+                isap=use_lse)
+        for order in [ordering.release, ordering.seq_cst]:
+            A64.enc(base.atomic_store.bind(ty, ptr), Sastlr, STLR(size),
+                    instp=IsEqual(AtomicStore.ordering, order), isap=use_lse)
+
+A64.enc(base.fence, Ifence, 0, isap=use_lse)
+
+# Floating point values are always supported. When `use_fp` is off, floating
+# point operations have no encodings, and they are expanded into soft-float
+# library calls.
+A64.legalize_type(f32=expand, f64=expand)
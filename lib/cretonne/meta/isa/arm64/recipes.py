@@ -0,0 +1,702 @@
+"""
+ARM64 Encoding recipes.
+
+The encoding recipes defined here correspond to the A64 instruction encoding
+classes described in the reference:
+
+    ARM Architecture Reference Manual
+    ARMv8, for ARMv8-A architecture profile
+"""
+from __future__ import absolute_import
+from cretonne import EncRecipe
+from cretonne.formats import Nullary, Unary, UnaryImm, Binary, BinaryImm
+from cretonne.formats import Ternary
+from cretonne.formats import Load, Store, IntCompare, FloatCompare
+from cretonne.formats import UnaryGlobalVar, Call, Jump, Branch
+from cretonne.formats import BranchIcmp, Return
+from cretonne.formats import AtomicRmw, AtomicCas, AtomicLoad, AtomicStore
+from cretonne.formats import Fence
+from cretonne.predicates import IsSignedInt, IsUnsignedInt, Or
+from cretonne.registers import Stack, EarlyClobber
+from .registers import GPR, FPR
+
+# All A64 instructions are 32 bits wide. The top 11 bits, 31:21, identify the
+# encoding class and most of the opcode. A few classes have more opcode bits
+# lower down in the instruction.
+#
+# Encbits for all the recipes are `bits[31:21] | (op << 11)`, where `op` is a
+# 5-bit field that is placed by the recipe. The register and immediate fields
+# in bits 31:21 are left clear. The `sf` bit 31 selects 64-bit operation in
+# most integer instructions.
+#
+# The functions below encode the encbits.
+
+
+def A64(hi, op=0):
+    # type: (int, int) -> int
+    assert hi <= 0x7ff
+    assert op <= 0x1f
+    return hi | (op << 11)
+
+
+def ADDSUB(sf, op, s=0):
+    # type: (int, int, int) -> int
+    """Add/subtract (shifted register): `sf op S 01011 shift 0 Rm`."""
+    assert sf <= 1 and op <= 1 and s <= 1
+    return A64((sf << 10) | (op << 9) | (s << 8) | (0b01011 << 3))
+
+
+def LOGIC(sf, opc, n=0):
+    # type: (int, int, int) -> int
+    """Logical (shifted register): `sf opc 01010 shift N Rm`."""
+    assert sf <= 1 and opc <= 0b11 and n <= 1
+    return A64((sf << 10) | (opc << 8) | (0b01010 << 3) | n)
+
+
+def DP1(sf, opcode):
+    # type: (int, int) -> int
+    """
+    Data-processing (1 source): `sf 1 0 11010110 00000 opcode`. The opcode is
+    in bits 15:10.
+    """
+    assert sf <= 1
+    return A64((sf << 10) | (1 << 9) | 0b11010110, opcode)
+
+
+def DP2(sf, opcode):
+    # type: (int, int) -> int
+    """
+    Data-processing (2 source): `sf 0 0 11010110 Rm opcode`. The opcode is in
+    bits 15:10.
+    """
+    assert sf <= 1
+    return A64((sf << 10) | 0b11010110, opcode)
+
+
+def DP3(sf, op31, o0):
+    # type: (int, int, int) -> int
+    """
+    Data-processing (3 source): `sf 00 11011 op31 Rm o0 Ra`. The `o0` bit is
+    bit 15.
+    """
+    assert sf <= 1 and op31 <= 0b111 and o0 <= 1
+    return A64((sf << 10) | (0b11011 << 3) | op31, o0)
+
+
+def ADDSUBIMM(sf, op, s=0):
+    # type: (int, int, int) -> int
+    """Add/subtract (immediate): `sf op S 100010 sh imm12`."""
+    assert sf <= 1 and op <= 1 and s <= 1
+    return A64((sf << 10) | (op << 9) | (s << 8) | (0b100010 << 2))
+
+
+def LOGIMM(sf, opc):
+    # type: (int, int) -> int
+    """Logical (immediate): `sf opc 100100 N immr imms`."""
+    assert sf <= 1 and opc <= 0b11
+    return A64((sf << 10) | (opc << 8) | (0b100100 << 2))
+
+
+def MOVEWIDE(sf, opc):
+    # type: (int, int) -> int
+    """Move wide (immediate): `sf opc 100101 hw imm16`."""
+    assert sf <= 1 and opc <= 0b11
+    return A64((sf << 10) | (opc << 8) | (0b100101 << 2))
+
+
+def BITFIELD(sf, opc):
+    # type: (int, int) -> int
+    """Bitfield: `sf opc 100110 N immr imms`. The `N` bit must match `sf`."""
+    assert sf <= 1 and opc <= 0b11
+    return A64((sf << 10) | (opc << 8) | (0b100110 << 2) | (sf << 1))
+
+
+def EXTR(sf):
+    # type: (int) -> int
+    """Extract: `sf 00 100111 N 0 Rm imms`. The `N` bit must match `sf`."""
+    assert sf <= 1
+    return A64((sf << 10) | (0b100111 << 2) | (sf << 1))
+
+
+def CSEL(sf, op, o2):
+    # type: (int, int, int) -> int
+    """
+    Conditional select: `sf op 0 11010100 Rm cond 0 o2`. The `o2` bit is bit
+    10.
+    """
+    assert sf <= 1 and op <= 1 and o2 <= 1
+    return A64((sf << 10) | (op << 9) | 0b11010100, o2)
+
+
+def CBZ(sf, op):
+    # type: (int, int) -> int
+    """Compare and branch: `sf 011010 op imm19`."""
+    assert sf <= 1 and op <= 1
+    return A64((sf << 10) | (0b011010 << 4) | (op << 3))
+
+
+def BIMM(op):
+    # type: (int) -> int
+    """Unconditional branch (immediate): `op 00101 imm26`."""
+    assert op <= 1
+    return A64((op << 10) | (0b00101 << 5))
+
+
+def RET():
+    # type: () -> int
+    """
+    Return to the address in a register: `1101011 0 0 10 11111 0000 0 0 Rn`.
+    The all-ones field in bits 20:16 is placed by the recipe.
+    """
+    return A64(0b11010110010)
+
+
+def LDST(size, v, opc):
+    # type: (int, int, int) -> int
+    """
+    Load/store register (unsigned immediate): `size 111 V 01 opc imm12`.
+
+    The same encbits are used for the unscaled `ldur` and `stur` forms which
+    have bit 24 clear. The `size` field determines the scale of the offset.
+    """
+    assert size <= 0b11 and v <= 1 and opc <= 0b11
+    return A64(
+            (size << 9) | (0b111 << 6) | (v << 5) | (0b01 << 3) | (opc << 1))
+
+
+def FP2(ftype, opcode):
+    # type: (int, int) -> int
+    """
+    Floating-point data-processing (2 source): `000 11110 type 1 Rm opcode 10`.
+    """
+    assert ftype <= 0b11 and opcode <= 0b0111
+    return A64((0b11110 << 3) | (ftype << 1) | 1, (opcode << 2) | 0b10)
+
+
+def FP1(ftype, opcode):
+    # type: (int, int) -> int
+    """
+    Floating-point data-processing (1 source): `000 11110 type 1 opcode 10000`.
+    The opcode is in bits 20:15.
+    """
+    assert ftype <= 0b11 and opcode <= 0b11111
+    return A64((0b11110 << 3) | (ftype << 1) | 1, opcode)
+
+
+def FCMP(ftype):
+    # type: (int) -> int
+    """Floating-point compare: `000 11110 type 1 Rm 00 1000 Rn 00000`."""
+    assert ftype <= 0b11
+    return A64((0b11110 << 3) | (ftype << 1) | 1, 0b01000)
+
+
+def FMADD(ftype, o1=0, o0=0):
+    # type: (int, int, int) -> int
+    """
+    Floating-point data-processing (3 source): `000 11111 type o1 Rm o0 Ra`.
+    """
+    assert ftype <= 0b11 and o1 <= 1 and o0 <= 1
+    return A64((0b11111 << 3) | (ftype << 1) | o1, o0)
+
+
+def FCVTI(sf, ftype, rmode, opcode):
+    # type: (int, int, int, int) -> int
+    """
+    Conversion between floating-point and integer:
+    `sf 0 0 11110 type 1 rmode opcode 000000`. The `rmode` and `opcode`
+    fields are bits 20:16.
+    """
+    assert sf <= 1 and ftype <= 0b11 and rmode <= 0b11 and opcode <= 0b111
+    return A64(
+            (sf << 10) | (0b11110 << 3) | (ftype << 1) | 1,
+            (rmode << 3) | opcode)
+
+
+def LSE(size, o3, opc):
+    # type: (int, int, int) -> int
+    """
+    Atomic memory operations: `size 111 0 00 A R 1 Rs o3 opc 00`. The `A` and
+    `R` bits are left clear. They are set from the memory ordering of the
+    instruction when it is emitted. The `o3` and `opc` fields are bits 15:12.
+    """
+    assert size <= 0b11 and o3 <= 1 and opc <= 0b111
+    return A64((size << 9) | (0b111 << 6) | 1, (o3 << 3) | opc)
+
+
+def CAS(size):
+    # type: (int) -> int
+    """
+    Compare and swap: `size 001000 1 L 1 Rs o0 11111`. The `L` and `o0` bits
+    are set from the memory ordering.
+    """
+    assert size <= 0b11
+    return A64((size << 9) | (0b001000 << 3) | 0b101)
+
+
+def LDAR(size):
+    # type: (int) -> int
+    """Load-acquire register: `size 001000 1 1 0 11111 1 11111`."""
+    assert size <= 0b11
+    return A64((size << 9) | (0b001000 << 3) | 0b110)
+
+
+def STLR(size):
+    # type: (int) -> int
+    """Store-release register: `size 001000 1 0 0 11111 1 11111`."""
+    assert size <= 0b11
+    return A64((size << 9) | (0b001000 << 3) | 0b100)
+
+
+# Three-register instructions, `add rd, rn, rm` and friends. The `op` bits
+# are placed in bits 15:10. This covers the shifted register forms with a
+# zero shift, the 2-source data-processing instructions like `udiv` and
+# `lslv`, and the 2-source floating point instructions.
+Rrr = EncRecipe(
+        'Rrr', Binary, size=4, ins=(GPR, GPR), outs=GPR,
+        emit='put_rrr(bits, in_reg0, in_reg1, out_reg0, sink);')
+
+# One-source data-processing instructions, `clz rd, rn` and `cls rd, rn`.
+Rr = EncRecipe(
+        'Rr', Unary, size=4, ins=GPR, outs=GPR,
+        emit='put_rrr(bits, in_reg0, 0, out_reg0, sink);')
+
+# Count trailing zeros, `rbit rd, rn` followed by `clz rd, rd`. The encbits
+# describe the `clz` instruction, and `rbit` is the 1-source opcode 0.
+Rctz = EncRecipe(
+        'Rctz', Unary, size=8, ins=GPR, outs=GPR,
+        emit='''
+        put_rrr(bits & 0x7ff, in_reg0, 0, out_reg0, sink);
+        put_rrr(bits, out_reg0, 0, out_reg0, sink);
+        ''')
+
+# Shifted register logical operations with the zero register as the first
+# operand. This implements register copies as `orr rd, xzr, rm` and `bnot` as
+# `orn rd, xzr, rm`.
+Rmov = EncRecipe(
+        'Rmov', Unary, size=4, ins=GPR, outs=GPR,
+        emit='put_rrr(bits, ZERO_REGISTER, in_reg0, out_reg0, sink);')
+
+# Multiplication, `madd rd, rn, rm, xzr`.
+Rmul = EncRecipe(
+        'Rmul', Binary, size=4, ins=(GPR, GPR), outs=GPR,
+        emit='''
+        put_rrrr(bits, in_reg0, in_reg1, ZERO_REGISTER, out_reg0, sink);
+        ''')
+
+# Unsigned division which traps on a zero divisor:
+#
+#     cbnz rm, 1f
+#     udf #0
+#  1: udiv rd, rn, rm
+#
+# The encbits describe the `udiv` instruction.
+Rdiv = EncRecipe(
+        'Rdiv', Binary, size=12, ins=(GPR, GPR), outs=GPR,
+        emit='''
+        put_trapz(bits, in_reg1, sink);
+        put_rrr(bits, in_reg0, in_reg1, out_reg0, sink);
+        ''')
+
+# Signed division which also traps on overflow when `rn` is the smallest
+# integer and `rm` is -1. See `put_trap_sdiv_overflow()`.
+Rsdiv = EncRecipe(
+        'Rsdiv', Binary, size=28, ins=(GPR, GPR), outs=GPR,
+        emit='''
+        put_trapz(bits, in_reg1, sink);
+        put_trap_sdiv_overflow(bits, in_reg0, in_reg1, sink);
+        put_rrr(bits, in_reg0, in_reg1, out_reg0, sink);
+        ''')
+
+# Remainder, computed from the quotient with `msub rd, rd, rm, rn`. The
+# result register is written before the operands are read for the last time,
+# so it must be distinct from them. The signed remainder doesn't overflow.
+# The encbits describe the `udiv` or `sdiv` instruction.
+Rrem = EncRecipe(
+        'Rrem', Binary, size=16, ins=(GPR, GPR), outs=EarlyClobber(GPR),
+        emit='''
+        put_trapz(bits, in_reg1, sink);
+        put_rrr(bits, in_reg0, in_reg1, out_reg0, sink);
+        put_rrrr(msub(bits), out_reg0, in_reg1, in_reg0, out_reg0, sink);
+        ''')
+
+# Add or subtract immediate, `add rd, rn, #imm` or `sub rd, rn, #-imm`. The
+# 12-bit immediate can be shifted left by 12 bits.
+Iaddsub = EncRecipe(
+        'Iaddsub', BinaryImm, size=4, ins=GPR, outs=GPR,
+        instp=Or(IsSignedInt(BinaryImm.imm, 13),
+                 IsSignedInt(BinaryImm.imm, 24, 12)),
+        emit='put_addsub_imm(bits, in_reg0, imm.into(), out_reg0, sink);')
+
+# Stack pointer adjustment, `add sp, sp, #imm` or `sub sp, sp, #-imm`.
+Iadjsp = EncRecipe(
+        'Iadjsp', UnaryImm, size=4, ins=(), outs=(),
+        instp=Or(IsSignedInt(UnaryImm.imm, 13),
+                 IsSignedInt(UnaryImm.imm, 24, 12)),
+        emit='''
+        put_addsub_imm(bits, STACK_POINTER, imm.into(), STACK_POINTER, sink);
+        ''')
+
+# Larger stack pointer adjustments, split into two instructions that adjust
+# by the high and low 12 bits of the immediate.
+Iadjsp2 = EncRecipe(
+        'Iadjsp2', UnaryImm, size=8, ins=(), outs=(),
+        instp=IsSignedInt(UnaryImm.imm, 24),
+        emit='''
+        let imm: i64 = imm.into();
+        let lo = if imm < 0 { -(-imm & 0xfff) } else { imm & 0xfff };
+        put_addsub_imm(bits, STACK_POINTER, imm - lo, STACK_POINTER, sink);
+        put_addsub_imm(bits, STACK_POINTER, lo, STACK_POINTER, sink);
+        ''')
+
+# Logical immediate operations, `and rd, rn, #imm` and friends. The
+# encodings provide the instruction predicate which depends on the operation
+# width.
+Ilogic = EncRecipe(
+        'Ilogic', BinaryImm, size=4, ins=GPR, outs=GPR,
+        emit='put_logical_imm(bits, in_reg0, imm.into(), out_reg0, sink);')
+
+# Boolean negation, `eor wd, wn, #1`. The `b1` values are represented as 0 or
+# 1 in integer registers.
+Ibnot = EncRecipe(
+        'Ibnot', Unary, size=4, ins=GPR, outs=GPR,
+        emit='put_logical_imm(bits, in_reg0, 1, out_reg0, sink);')
+
+# Constants that are logical immediates, `orr rd, xzr, #imm`.
+Ulogic = EncRecipe(
+        'Ulogic', UnaryImm, size=4, ins=(), outs=GPR,
+        emit='''
+        put_logical_imm(bits, ZERO_REGISTER, imm.into(), out_reg0, sink);
+        ''')
+
+# Constants with a single non-zero 16-bit chunk in the low 32 bits,
+# `movz rd, #imm16, lsl #shift`, or small negative constants,
+# `movn rd, #imm16`. The encbits describe the `movz` instruction.
+Umov = EncRecipe(
+        'Umov', UnaryImm, size=4, ins=(), outs=GPR,
+        instp=Or(IsUnsignedInt(UnaryImm.imm, 16),
+                 IsUnsignedInt(UnaryImm.imm, 32, 16),
+                 IsSignedInt(UnaryImm.imm, 17)),
+        emit='put_mov_imm(bits, imm.into(), out_reg0, sink);')
+
+# Arbitrary 32-bit constants, `movz wd, #lo16` followed by
+# `movk wd, #hi16, lsl #16`. The encbits describe the `movz` instruction.
+Umovk = EncRecipe(
+        'Umovk', UnaryImm, size=8, ins=(), outs=GPR,
+        emit='put_movk_seq(bits, imm.into(), 2, out_reg0, sink);')
+
+# Arbitrary 64-bit constants, a `movz` followed by three `movk` instructions.
+Umovk64 = EncRecipe(
+        'Umovk64', UnaryImm, size=16, ins=(), outs=GPR,
+        emit='put_movk_seq(bits, imm.into(), 4, out_reg0, sink);')
+
+# Immediate shifts are aliases of the bitfield and extract instructions. The
+# shift amount is masked to the operation width like the dynamic shifts.
+#
+# Left shift, `ubfm rd, rn, #(-sh % w), #(w - 1 - sh)`.
+Ishl = EncRecipe(
+        'Ishl', BinaryImm, size=4, ins=GPR, outs=GPR,
+        emit='''
+        let (w, sh) = shift_amount(bits, imm.into());
+        put_bitfield(bits, in_reg0, (w - sh) & (w - 1), w - 1 - sh, out_reg0,
+                     sink);
+        ''')
+
+# Right shift, `ubfm rd, rn, #sh, #(w - 1)` or `sbfm rd, rn, #sh, #(w - 1)`.
+Ishr = EncRecipe(
+        'Ishr', BinaryImm, size=4, ins=GPR, outs=GPR,
+        emit='''
+        let (w, sh) = shift_amount(bits, imm.into());
+        put_bitfield(bits, in_reg0, sh, w - 1, out_reg0, sink);
+        ''')
+
+# Rotate right, `extr rd, rn, rn, #sh`.
+Irotr = EncRecipe(
+        'Irotr', BinaryImm, size=4, ins=GPR, outs=GPR,
+        emit='''
+        let (_, sh) = shift_amount(bits, imm.into());
+        put_extr(bits, in_reg0, in_reg0, sh, out_reg0, sink);
+        ''')
+
+# Rotate left, `extr rd, rn, rn, #(-sh % w)`.
+Irotl = EncRecipe(
+        'Irotl', BinaryImm, size=4, ins=GPR, outs=GPR,
+        emit='''
+        let (w, sh) = shift_amount(bits, imm.into());
+        put_extr(bits, in_reg0, in_reg0, (w - sh) & (w - 1), out_reg0, sink);
+        ''')
+
+# Integer extension, `ubfm rd, rn, #0, #(n - 1)` or `sbfm rd, rn, #0,
+# #(n - 1)` where `n` is the width of the input type.
+Rext = EncRecipe(
+        'Rext', Unary, size=4, ins=GPR, outs=GPR,
+        emit='''
+        let n = func.dfg.value_type(arg).bits() as u32;
+        put_bitfield(bits, in_reg0, 0, n - 1, out_reg0, sink);
+        ''')
+
+# Integer comparison, `cmp rn, rm` followed by `cset wd, cond`. The encbits
+# describe the `subs` instruction.
+Ricmp = EncRecipe(
+        'Ricmp', IntCompare, size=8, ins=(GPR, GPR), outs=GPR,
+        emit='''
+        put_rrr(bits, in_reg0, in_reg1, ZERO_REGISTER, sink);
+        put_cset(icc_bits(cond), out_reg0, sink);
+        ''')
+
+# Select, `cmp wc, #0` followed by `csel rd, rn, rm, ne`.
+Rsel = EncRecipe(
+        'Rsel', Ternary, size=8, ins=(GPR, GPR, GPR), outs=GPR,
+        emit='''
+        put_cmp_zero(in_reg0, sink);
+        put_csel(bits, in_reg1, in_reg2, COND_NE, out_reg0, sink);
+        ''')
+
+# Load with a scaled 12-bit unsigned offset, `ldr rt, [rn, #offset]`. The
+# range depends on the access size, so the encodings provide the instruction
+# predicate.
+Ildr = EncRecipe(
+        'Ildr', Load, size=4, ins=GPR, outs=GPR,
+        emit='put_ldst(bits, in_reg0, offset.into(), out_reg0, sink);')
+
+# Load with an unscaled 9-bit signed offset, `ldur rt, [rn, #offset]`.
+Ildur = EncRecipe(
+        'Ildur', Load, size=4, ins=GPR, outs=GPR,
+        instp=IsSignedInt(Load.offset, 9),
+        emit='put_ldur(bits, in_reg0, offset.into(), out_reg0, sink);')
+
+# Store with a scaled 12-bit unsigned offset, `str rt, [rn, #offset]`.
+Sstr = EncRecipe(
+        'Sstr', Store, size=4, ins=(GPR, GPR), outs=(),
+        emit='put_ldst(bits, in_reg1, offset.into(), in_reg0, sink);')
+
+# Store with an unscaled 9-bit signed offset, `stur rt, [rn, #offset]`.
+Sstur = EncRecipe(
+        'Sstur', Store, size=4, ins=(GPR, GPR), outs=(),
+        instp=IsSignedInt(Store.offset, 9),
+        emit='put_ldur(bits, in_reg1, offset.into(), in_reg0, sink);')
+
+# Spill a register to a spill slot, `str rt, [sp, #offset]` or
+# `stur rt, [sp, #offset]`. The stack slot offset is relative to the stack
+# pointer on function entry.
+GPsp = EncRecipe(
+        'GPsp', Unary, size=4, ins=GPR, outs=Stack(GPR),
+        emit='''
+        let offset = stack_slot_offset(func, out_ss0);
+        put_ldst_sp(bits, offset, in_reg0, sink);
+        ''')
+
+# Fill a register from a spill slot, `ldr rt, [sp, #offset]` or
+# `ldur rt, [sp, #offset]`.
+GPfi = EncRecipe(
+        'GPfi', Unary, size=4, ins=Stack(GPR), outs=GPR,
+        emit='''
+        let offset = stack_slot_offset(func, in_ss0);
+        put_ldst_sp(bits, offset, out_reg0, sink);
+        ''')
+
+# Floating point arithmetic, `fadd sd, sn, sm` and friends.
+Rf = EncRecipe(
+        'Rf', Binary, size=4, ins=(FPR, FPR), outs=FPR,
+        emit='put_rrr(bits, in_reg0, in_reg1, out_reg0, sink);')
+
+# One-source floating point instructions, `fsqrt sd, sn` and friends. This
+# includes register copies, `fmov sd, sn`, and the conversions between single
+# and double precision.
+Rff = EncRecipe(
+        'Rff', Unary, size=4, ins=FPR, outs=FPR,
+        emit='put_fp1(bits, in_reg0, out_reg0, sink);')
+
+# Conversions from floating point to integer registers, `fcvtzs wd, sn` and
+# `fmov wd, sn`.
+Rfi = EncRecipe(
+        'Rfi', Unary, size=4, ins=FPR, outs=GPR,
+        emit='put_fcvti(bits, in_reg0, out_reg0, sink);')
+
+# Conversions from integer to floating point registers, `scvtf sd, wn` and
+# `fmov sd, wn`.
+Rif = EncRecipe(
+        'Rif', Unary, size=4, ins=GPR, outs=FPR,
+        emit='put_fcvti(bits, in_reg0, out_reg0, sink);')
+
+# Fused multiply-add, `fmadd sd, sn, sm, sa`.
+Rfma = EncRecipe(
+        'Rfma', Ternary, size=4, ins=(FPR, FPR, FPR), outs=FPR,
+        emit='put_rrrr(bits, in_reg0, in_reg1, in_reg2, out_reg0, sink);')
+
+# Floating point comparison, `fcmp sn, sm` followed by `cset wd, cond`. The
+# encbits describe the `fcmp` instruction.
+Rfcmp = EncRecipe(
+        'Rfcmp', FloatCompare, size=8, ins=(FPR, FPR), outs=GPR,
+        emit='''
+        put_rrr(bits, in_reg0, in_reg1, 0, sink);
+        put_cset(fcc_bits(cond), out_reg0, sink);
+        ''')
+
+# Floating point loads and stores use the same addressing modes as the
+# integer instructions.
+Fldr = EncRecipe(
+        'Fldr', Load, size=4, ins=GPR, outs=FPR,
+        emit='put_ldst(bits, in_reg0, offset.into(), out_reg0, sink);')
+
+Fldur = EncRecipe(
+        'Fldur', Load, size=4, ins=GPR, outs=FPR,
+        instp=IsSignedInt(Load.offset, 9),
+        emit='put_ldur(bits, in_reg0, offset.into(), out_reg0, sink);')
+
+Fstr = EncRecipe(
+        'Fstr', Store, size=4, ins=(FPR, GPR), outs=(),
+        emit='put_ldst(bits, in_reg1, offset.into(), in_reg0, sink);')
+
+Fstur = EncRecipe(
+        'Fstur', Store, size=4, ins=(FPR, GPR), outs=(),
+        instp=IsSignedInt(Store.offset, 9),
+        emit='put_ldur(bits, in_reg1, offset.into(), in_reg0, sink);')
+
+# Spill a floating point register, `str dt, [sp, #offset]`.
+FPsp = EncRecipe(
+        'FPsp', Unary, size=4, ins=FPR, outs=Stack(FPR),
+        emit='''
+        let offset = stack_slot_offset(func, out_ss0);
+        put_ldst_sp(bits, offset, in_reg0, sink);
+        ''')
+
+# Fill a floating point register, `ldr dt, [sp, #offset]`.
+FPfi = EncRecipe(
+        'FPfi', Unary, size=4, ins=Stack(FPR), outs=FPR,
+        emit='''
+        let offset = stack_slot_offset(func, in_ss0);
+        put_ldst_sp(bits, offset, out_reg0, sink);
+        ''')
+
+# Atomic read-modify-write from the LSE extension, `ldadd rs, rt, [rn]` and
+# friends. The `A` and `R` bits are derived from the memory ordering.
+Ramo = EncRecipe(
+        'Ramo', AtomicRmw, size=4, ins=(GPR, GPR), outs=GPR,
+        emit='put_lse(bits, ordering, in_reg1, in_reg0, out_reg0, sink);')
+
+# Compare-and-swap, `cas rs, rt, [rn]`. The previous value in memory is
+# written to `rs` which holds the expected value, so the result is tied to
+# that operand.
+Rcas = EncRecipe(
+        'Rcas', AtomicCas, size=4, ins=(GPR, GPR, GPR), outs=1,
+        emit='put_cas(bits, ordering, in_reg1, in_reg0, in_reg2, sink);')
+
+# Relaxed atomic loads and stores use plain `ldr` and `str` instructions.
+Iaload = EncRecipe(
+        'Iaload', AtomicLoad, size=4, ins=GPR, outs=GPR,
+        emit='put_ldst(bits, in_reg0, 0, out_reg0, sink);')
+
+Sastore = EncRecipe(
+        'Sastore', AtomicStore, size=4, ins=(GPR, GPR), outs=(),
+        emit='put_ldst(bits, in_reg1, 0, in_reg0, sink);')
+
+# Load-acquire, `ldar rt, [rn]`, and store-release, `stlr rt, [rn]`. These
+# are also sequentially consistent with respect to each other.
+Ialdar = EncRecipe(
+        'Ialdar', AtomicLoad, size=4, ins=GPR, outs=GPR,
+        emit='put_ldar(bits, in_reg0, out_reg0, sink);')
+
+Sastlr = EncRecipe(
+        'Sastlr', AtomicStore, size=4, ins=(GPR, GPR), outs=(),
+        emit='put_ldar(bits, in_reg1, in_reg0, sink);')
+
+# Memory barrier, `dmb ishld` for acquire fences and `dmb ish` otherwise.
+Ifence = EncRecipe(
+        'Ifence', Fence, size=4, ins=(), outs=(),
+        emit='sink.put4(dmb_bits(ordering));')
+
+# Direct function call, `bl sym`, with an `R_AARCH64_CALL26` relocation.
+Bcall = EncRecipe(
+        'Bcall', Call, size=4, ins=(), outs=(),
+        emit='''
+        sink.reloc_external(RelocKind::Call26.into(),
+                            &func.dfg.ext_funcs[data.func_ref].name);
+        put_b(bits, 0, sink);
+        ''')
+
+# Address of a symbol within 4 GiB of the PC, `adrp xd, sym` followed by
+# `add xd, xd, #:lo12:sym`. The encbits describe the `add` instruction.
+Ugsym = EncRecipe(
+        'Ugsym', UnaryGlobalVar, size=8, ins=(), outs=GPR,
+        emit='''
+        let name = globalsym_name(func, global_var);
+        sink.reloc_external(RelocKind::AdrPrelPgHi21.into(), name);
+        sink.put4(ADRP | out_reg0 as u32);
+        sink.reloc_external(RelocKind::AddAbsLo12Nc.into(), name);
+        put_addsub_imm(bits, out_reg0, 0, out_reg0, sink);
+        ''')
+
+# Unconditional branch, `b offset`. The range is +/- 128 MiB.
+Bjump = EncRecipe(
+        'Bjump', Jump, size=4, ins=(), outs=(), branch_range=(0, 28),
+        emit='''
+        let dest = func.offsets[data.destination] as i64;
+        let disp = dest - sink.offset() as i64;
+        put_b(bits, disp, sink);
+        ''')
+
+# Compare a register to zero and branch, `cbz rt, offset` or
+# `cbnz rt, offset`. The range is +/- 1 MiB.
+CBz = EncRecipe(
+        'CBz', Branch, size=4, ins=GPR, outs=(), branch_range=(0, 21),
+        emit='''
+        let dest = func.offsets[data.destination] as i64;
+        let disp = dest - sink.offset() as i64;
+        put_cbz(bits, disp, in_reg0, sink);
+        ''')
+
+# Long-range version of `CBz`: The inverted branch skips over a `b` to the
+# destination EBB. The range is +/- 128 MiB relative to the `b`.
+CBzLong = EncRecipe(
+        'CBzLong', Branch, size=8, ins=GPR, outs=(), branch_range=(4, 28),
+        emit='''
+        put_cbz(invert_cbz(bits), 8, in_reg0, sink);
+        let dest = func.offsets[data.destination] as i64;
+        let disp = dest - sink.offset() as i64;
+        put_b(BIMM_B, disp, sink);
+        ''')
+
+# Compare two registers and branch, `cmp rn, rm` followed by
+# `b.cond offset`. The range is +/- 1 MiB relative to the `b.cond`. The
+# encbits describe the `subs` instruction.
+Bicmp = EncRecipe(
+        'Bicmp', BranchIcmp, size=8, ins=(GPR, GPR), outs=(),
+        branch_range=(4, 21),
+        emit='''
+        put_rrr(bits, in_reg0, in_reg1, ZERO_REGISTER, sink);
+        let dest = func.offsets[data.destination] as i64;
+        let disp = dest - sink.offset() as i64;
+        put_bcond(icc_bits(data.cond), disp, sink);
+        ''')
+
+# Long-range version of `Bicmp`: The inverted `b.cond` skips over a `b` to
+# the destination EBB. The range is +/- 128 MiB relative to the `b`.
+BicmpLong = EncRecipe(
+        'BicmpLong', BranchIcmp, size=12, ins=(GPR, GPR), outs=(),
+        branch_range=(8, 28),
+        emit='''
+        put_rrr(bits, in_reg0, in_reg1, ZERO_REGISTER, sink);
+        put_bcond(icc_bits(data.cond) ^ 1, 8, sink);
+        let dest = func.offsets[data.destination] as i64;
+        let disp = dest - sink.offset() as i64;
+        put_b(BIMM_B, disp, sink);
+        ''')
+
+# Unconditional trap, `udf #0`.
+Udf = EncRecipe(
+        'Udf', Nullary, size=4, ins=(), outs=(),
+        emit='sink.put4(UDF);')
+
+# Conditional trap, `cbz rt, 8` or `cbnz rt, 8` skipping over `udf #0` unless
+# the trap condition holds. The encbits describe the skipping branch.
+CBtrap = EncRecipe(
+        'CBtrap', Unary, size=8, ins=GPR, outs=(),
+        emit='''
+        put_cbz(bits, 8, in_reg0, sink);
+        sink.put4(UDF);
+        ''')
+
+# Return to the caller, `ret x30`.
+Bret = EncRecipe(
+        'Bret', Return, size=4, ins=(), outs=(),
+        emit='put_rrr(bits, LINK_REGISTER, 0b11111, 0, sink);')
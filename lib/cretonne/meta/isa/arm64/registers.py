@@ -0,0 +1,25 @@
+"""
+ARM64 register banks.
+"""
+from __future__ import absolute_import
+from cretonne.registers import RegBank, RegClass
+from .defs import isa
+
+
+# Register number 31 is the stack pointer or the zero register depending on
+# the instruction. It is included in the bank so it can be encoded, but it is
+# reserved.
+IntRegs = RegBank(
+        'IntRegs', isa,
+        'General purpose registers',
+        units=32, prefix='x')
+
+# The floating point and Advanced SIMD registers `v0`-`v31`. Scalar floating
+# point values use the low bits.
+FloatRegs = RegBank(
+        'FloatRegs', isa,
+        'Floating point and SIMD registers',
+        units=32, prefix='v')
+
+GPR = RegClass('GPR', IntRegs)
+FPR = RegClass('FPR', FloatRegs)
@@ -0,0 +1,20 @@
+"""
+ARM64 settings.
+"""
+from __future__ import absolute_import
+from cretonne import SettingGroup, BoolSetting
+from cretonne.predicates import And
+import cretonne.settings as shared
+from .defs import isa
+
+isa.settings = SettingGroup('arm64', parent=shared.group)
+
+has_fp = BoolSetting(
+        "CPU supports floating point and Advanced SIMD",
+        default=True)
+has_lse = BoolSetting("CPU supports the ARMv8.1 Large System Extensions")
+
+use_fp = And(has_fp, shared.enable_float)
+use_lse = And(has_lse, shared.enable_atomics)
+
+isa.settings.close(globals())
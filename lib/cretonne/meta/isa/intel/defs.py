@@ -0,0 +1,15 @@
+"""
+Intel definitions.
+
+Commonly used definitions.
+"""
+from __future__ import absolute_import
+from cretonne import TargetISA, CPUMode
+import cretonne.base
+from . import instructions as x86
+
+isa = TargetISA('intel', [cretonne.base.instructions, x86.GROUP])
+
+# CPU modes for 32-bit and 64-bit operation.
+I32 = CPUMode('I32', isa)
+I64 = CPUMode('I64', isa)
@@ -0,0 +1,440 @@
+"""
+Intel Encodings.
+"""
+from __future__ import absolute_import
+from cretonne import base
+from cretonne.formats import UnaryImm, FloatCompare
+from cretonne.immediates import floatcc
+from cretonne.types import i8, i16, i32, i64, f32, f64, b1
+from cretonne.legalize import widen, expand
+from cretonne.predicates import IsEqual, IsUnsignedInt
+from .defs import I32, I64
+from . import recipes as r
+from . import settings as cfg
+from . import instructions as x86
+from .legalize import intel_expand
+
+try:
+    from typing import Any  # noqa
+    from cretonne import MaybeBoundInst  # noqa
+except ImportError:
+    pass
+
+
+I32.legalize_type(
+        i8=widen, i16=widen, i32=intel_expand,
+        f32=expand, f64=expand)
+
+I64.legalize_type(
+        i8=widen, i16=widen, i32=intel_expand, i64=intel_expand,
+        f32=expand, f64=expand)
+
+
+def enc_i32_i64(inst, recipe, *args, **kwargs):
+    # type: (MaybeBoundInst, r.TailRecipe, *int, **Any) -> None
+    """
+    Add encodings for `inst.i32` to I32.
+    Add encodings for `inst.i32` to I64 with and without REX.
+    Add encodings for `inst.i64` to I64 with a REX.W prefix.
+
+    The encodings without a REX prefix come first, so the REX encodings are
+    picked before register allocation. Branch relaxation switches to the
+    shorter encodings when the allocated registers allow it.
+    """
+    isap = kwargs.pop('isap', None)
+    I32.enc(inst.i32, *recipe(*args, **kwargs), isap=isap)
+    I64.enc(inst.i32, *recipe(*args, **kwargs), isap=isap)
+    I64.enc(inst.i32, *recipe.rex(*args, **kwargs), isap=isap)
+    I64.enc(inst.i64, *recipe.rex(*args, w=1, **kwargs), isap=isap)
+
+
+def enc_both(inst, recipe, *args, **kwargs):
+    # type: (MaybeBoundInst, r.TailRecipe, *int, **Any) -> None
+    """
+    Add encodings for `inst` to I32, and to I64 with and without REX.
+
+    The encodings are predicated on SSE2 unless another ISA predicate is
+    given.
+    """
+    isap = kwargs.pop('isap', cfg.use_sse2)
+    instp = kwargs.pop('instp', None)
+    I32.enc(inst, *recipe(*args, **kwargs), instp=instp, isap=isap)
+    I64.enc(inst, *recipe(*args, **kwargs), instp=instp, isap=isap)
+    I64.enc(inst, *recipe.rex(*args, **kwargs), instp=instp, isap=isap)
+
+
+# Integer arithmetic.
+for inst,           opc in [
+        (base.iadd, 0x01),
+        (base.isub, 0x29),
+        (base.band, 0x21),
+        (base.bor,  0x09),
+        (base.bxor, 0x31)
+        ]:
+    enc_i32_i64(inst, r.rr, opc)
+
+enc_i32_i64(base.imul, r.rrx, 0x0f, 0xaf)
+enc_i32_i64(base.bnot, r.ur, 0xf7, rrr=2)
+
+# Division. The `intel_expand` group legalizes the base division instructions
+# into these.
+enc_i32_i64(x86.udivmodx, r.div, 0xf7, rrr=6)
+enc_i32_i64(x86.sdivmodx, r.div, 0xf7, rrr=7)
+
+enc_i32_i64(base.copy, r.umr, 0x89)
+
+# Immediate instructions with sign-extended 8-bit and 32-bit immediate.
+for inst,               rrr in [
+        (base.iadd_imm, 0),
+        (base.band_imm, 4),
+        (base.bor_imm,  1),
+        (base.bxor_imm, 6)
+        ]:
+    enc_i32_i64(inst, r.rib, 0x83, rrr=rrr)
+    enc_i32_i64(inst, r.rid, 0x81, rrr=rrr)
+
+# Immediate constants. The 32-bit `mov r, imm` also zero-extends to 64 bits,
+# `mov r/m64, imm32` sign-extends, and only the last resort has a full 64-bit
+# immediate.
+I32.enc(base.iconst.i32, *r.pu_id(0xb8))
+I64.enc(base.iconst.i32, *r.pu_id(0xb8))
+I64.enc(base.iconst.i32, *r.pu_id.rex(0xb8))
+I64.enc(base.iconst.i64, *r.pu_id(0xb8),
+        instp=IsUnsignedInt(UnaryImm.imm, 32))
+I64.enc(base.iconst.i64, *r.pu_id.rex(0xb8),
+        instp=IsUnsignedInt(UnaryImm.imm, 32))
+I64.enc(base.iconst.i64, *r.u_id.rex(0xc7, w=1))
+I64.enc(base.iconst.i64, *r.pu_iq.rex(0xb8, w=1))
+
+# Shifts and rotates.
+# The dynamic shift amount must be in `%cl`, and only its low 5 or 6 bits are
+# used, which matches the masking semantics of the Cretonne instructions.
+for inst,           rrr in [
+        (base.rotl, 0),
+        (base.rotr, 1),
+        (base.ishl, 4),
+        (base.ushr, 5),
+        (base.sshr, 7)
+        ]:
+    # Small shift amounts are produced when `i8` and `i16` shifts are widened.
+    for amt in [i8, i16, i32]:
+        I32.enc(inst.i32.bind(amt), *r.rc(0xd3, rrr=rrr))
+    for amt in [i8, i16, i32, i64]:
+        I64.enc(inst.i32.bind(amt), *r.rc(0xd3, rrr=rrr))
+        I64.enc(inst.i32.bind(amt), *r.rc.rex(0xd3, rrr=rrr))
+        I64.enc(inst.i64.bind(amt), *r.rc.rex(0xd3, rrr=rrr, w=1))
+
+for inst,               rrr in [
+        (base.rotl_imm, 0),
+        (base.rotr_imm, 1),
+        (base.ishl_imm, 4),
+        (base.ushr_imm, 5),
+        (base.sshr_imm, 7)
+        ]:
+    enc_i32_i64(inst, r.rshib, 0xc1, rrr=rrr)
+
+# Bit counting. These instructions are only available on newer CPUs.
+enc_i32_i64(base.popcnt, r.urm, 0xf3, 0x0f, 0xb8, isap=cfg.use_popcnt)
+enc_i32_i64(base.clz, r.urm, 0xf3, 0x0f, 0xbd, isap=cfg.has_lzcnt)
+enc_i32_i64(base.ctz, r.urm, 0xf3, 0x0f, 0xbc, isap=cfg.has_bmi1)
+
+# Integer comparisons produce a `b1` value in the low byte of an ABCD
+# register. All the condition codes are supported directly.
+enc_i32_i64(base.icmp, r.icscc, 0x39)
+
+# Boolean operations on `b1` values.
+for inst,           opc in [
+        (base.band, 0x21),
+        (base.bor,  0x09),
+        (base.bxor, 0x31)
+        ]:
+    I32.enc(inst.b1, *r.rr(opc))
+    I64.enc(inst.b1, *r.rr(opc))
+    I64.enc(inst.b1, *r.rr.rex(opc))
+
+# Register copies of booleans and small integers copy the whole register.
+for ty in [b1, i8, i16]:
+    I32.enc(base.copy.bind(ty), *r.umr(0x89))
+    I64.enc(base.copy.bind(ty), *r.umr(0x89))
+    I64.enc(base.copy.bind(ty), *r.umr.rex(0x89))
+
+# Integer conversions. The small integer types only appear as the inputs and
+# outputs of these instructions. All other operations on `i8` and `i16` values
+# are widened. Truncation doesn't need to change any bits since the high bits
+# of narrow integer types are ignored.
+for inst in [base.ireduce.i8, base.ireduce.i16]:
+    I32.enc(inst.i32, r.null, 0)
+    I64.enc(inst.i32, r.null, 0)
+    I64.enc(inst.i64, r.null, 0)
+I64.enc(base.ireduce.i32.i64, r.null, 0)
+
+# The 8-bit sources must be in ABCD without a REX prefix. With a REX prefix,
+# the low byte of any register can be used.
+for inst,           opc in [
+        (base.uextend, 0xb6),
+        (base.sextend, 0xbe)
+        ]:
+    I32.enc(inst.i32.i8, *r.urm_abcd(0x0f, opc))
+    I64.enc(inst.i32.i8, *r.urm_abcd(0x0f, opc))
+    I64.enc(inst.i32.i8, *r.urm.rex(0x0f, opc))
+    I64.enc(inst.i64.i8, *r.urm.rex(0x0f, opc, w=1))
+
+# The 16-bit extensions `movzwl` and `movswl`.
+for inst,           opc in [
+        (base.uextend, 0xb7),
+        (base.sextend, 0xbf)
+        ]:
+    I32.enc(inst.i32.i16, *r.urm(0x0f, opc))
+    I64.enc(inst.i32.i16, *r.urm(0x0f, opc))
+    I64.enc(inst.i32.i16, *r.urm.rex(0x0f, opc))
+    I64.enc(inst.i64.i16, *r.urm.rex(0x0f, opc, w=1))
+
+# A 32-bit register copy zero-extends to 64 bits, and `movsxd` sign-extends.
+I64.enc(base.uextend.i64.i32, *r.urm(0x8b))
+I64.enc(base.uextend.i64.i32, *r.urm.rex(0x8b))
+I64.enc(base.sextend.i64.i32, *r.urm.rex(0x63, w=1))
+
+# Loads and stores. The address operand has the native pointer type. Each
+# instruction has encodings with 8-bit and 32-bit displacements.
+for recipe in [r.st, r.stDisp32]:
+    I32.enc(base.store.i32.i32, *recipe(0x89))
+    I64.enc(base.store.i32.i64, *recipe(0x89))
+    I64.enc(base.store.i32.i64, *recipe.rex(0x89))
+    I64.enc(base.store.i64.i64, *recipe.rex(0x89, w=1))
+
+    I64.enc(base.istore32.i64.i64, *recipe(0x89))
+    I64.enc(base.istore32.i64.i64, *recipe.rex(0x89))
+
+    I32.enc(base.istore16.i32.i32, *recipe(0x66, 0x89))
+    I64.enc(base.istore16.i32.i64, *recipe(0x66, 0x89))
+    I64.enc(base.istore16.i32.i64, *recipe.rex(0x66, 0x89))
+    I64.enc(base.istore16.i64.i64, *recipe(0x66, 0x89))
+    I64.enc(base.istore16.i64.i64, *recipe.rex(0x66, 0x89))
+
+# Byte stores need the ABCD registers without a REX prefix, so I64 always uses
+# the REX encodings.
+for recipe in [r.st_abcd, r.stDisp32_abcd]:
+    I32.enc(base.istore8.i32.i32, *recipe(0x88))
+for recipe in [r.st, r.stDisp32]:
+    I64.enc(base.istore8.i32.i64, *recipe.rex(0x88))
+    I64.enc(base.istore8.i64.i64, *recipe.rex(0x88))
+
+for recipe in [r.ld, r.ldDisp32]:
+    for inst,           opc in [
+            (base.load,    (0x8b,)),
+            (base.uload16, (0x0f, 0xb7)),
+            (base.sload16, (0x0f, 0xbf)),
+            (base.uload8,  (0x0f, 0xb6)),
+            (base.sload8,  (0x0f, 0xbe))
+            ]:
+        I32.enc(inst.i32.i32, *recipe(*opc))
+        I64.enc(inst.i32.i64, *recipe(*opc))
+        I64.enc(inst.i32.i64, *recipe.rex(*opc))
+        I64.enc(inst.i64.i64, *recipe.rex(*opc, w=1))
+
+    I64.enc(base.uload32.i64.i64, *recipe(0x8b))
+    I64.enc(base.uload32.i64.i64, *recipe.rex(0x8b))
+    I64.enc(base.sload32.i64.i64, *recipe.rex(0x63, w=1))
+
+# Spills and fills use stack pointer relative loads and stores.
+I32.enc(base.spill.i32, *r.spill(0x89))
+I64.enc(base.spill.i32, *r.spill(0x89))
+I64.enc(base.spill.i32, *r.spill.rex(0x89))
+I64.enc(base.spill.i64, *r.spill.rex(0x89, w=1))
+I32.enc(base.fill.i32, *r.fill(0x8b))
+I64.enc(base.fill.i32, *r.fill(0x8b))
+I64.enc(base.fill.i32, *r.fill.rex(0x8b))
+I64.enc(base.fill.i64, *r.fill.rex(0x8b, w=1))
+
+# The stack frame is allocated and deallocated by adjusting the stack pointer.
+I32.enc(base.adjust_sp_imm, *r.adjustsp_ib(0x83))
+I32.enc(base.adjust_sp_imm, *r.adjustsp_id(0x81))
+I64.enc(base.adjust_sp_imm, *r.adjustsp_ib.rex(0x83, w=1))
+I64.enc(base.adjust_sp_imm, *r.adjustsp_id.rex(0x81, w=1))
+
+# Control flow. The general encodings with 32-bit displacements are listed
+# last, and branch relaxation picks the short 8-bit displacements when the
+# destination is in range.
+for recipe, opc in [
+        (r.jmpb, 0xeb),
+        (r.jmpd, 0xe9)
+        ]:
+    I32.enc(base.jump, *recipe(opc))
+    I64.enc(base.jump, *recipe(opc))
+
+for recipe,   brz,  brnz in [
+        (r.tjccb, 0x74, 0x75),
+        (r.tjccd, 0x84, 0x85)
+        ]:
+    enc_i32_i64(base.brz, recipe, brz)
+    enc_i32_i64(base.brnz, recipe, brnz)
+    for inst,       opc in [
+            (base.brz,  brz),
+            (base.brnz, brnz)
+            ]:
+        I32.enc(inst.b1, *recipe(opc))
+        I64.enc(inst.b1, *recipe(opc))
+        I64.enc(inst.b1, *recipe.rex(opc))
+
+for recipe in [r.icbrb, r.icbrd]:
+    enc_i32_i64(base.br_icmp, recipe, 0x39)
+
+# Traps use the `ud2` instruction. The conditional traps skip over it with a
+# `jne` or `je` instruction.
+I32.enc(base.trap, *r.trap(0x0f, 0x0b))
+I64.enc(base.trap, *r.trap(0x0f, 0x0b))
+for inst,         skip in [
+        (base.trapz,  0x75),
+        (base.trapnz, 0x74)
+        ]:
+    enc_i32_i64(inst, r.ttrap, skip)
+    I32.enc(inst.b1, *r.ttrap(skip))
+    I64.enc(inst.b1, *r.ttrap(skip))
+    I64.enc(inst.b1, *r.ttrap.rex(skip))
+
+I32.enc(base.x_return, *r.ret(0xc3))
+I64.enc(base.x_return, *r.ret(0xc3))
+
+# Direct calls and symbol addresses need relocations.
+I32.enc(base.call, *r.call_id(0xe8))
+I64.enc(base.call, *r.call_id(0xe8))
+I32.enc(base.globalsym_addr.i32, *r.gsym_id(0xb8))
+I64.enc(base.globalsym_addr.i64, *r.gsym_iq.rex(0xb8, w=1))
+
+# Scalar floating point with SSE2. The `ss` and `sd` instructions are
+# selected by the F3 and F2 mandatory prefixes.
+for ty,  pfx in [
+        (f32, 0xf3),
+        (f64, 0xf2)
+        ]:
+    for inst,           opc in [
+            (base.fadd, 0x58),
+            (base.fsub, 0x5c),
+            (base.fmul, 0x59),
+            (base.fdiv, 0x5e)
+            ]:
+        enc_both(inst.bind(ty), r.fa, pfx, 0x0f, opc)
+
+    enc_both(base.sqrt.bind(ty), r.furm, pfx, 0x0f, 0x51)
+
+    # Bitwise operations and copies use the packed single instructions which
+    # have the shortest encodings.
+    for inst,           opc in [
+            (base.band, 0x54),
+            (base.bor,  0x56),
+            (base.bxor, 0x57)
+            ]:
+        enc_both(inst.bind(ty), r.fa, 0x0f, opc)
+    enc_both(base.copy.bind(ty), r.furm, 0x0f, 0x28)
+
+    # Loads, stores, spills, and fills with `movss` and `movsd`.
+    for recipe in [r.fld, r.fldDisp32]:
+        I32.enc(base.load.bind(ty, i32), *recipe(pfx, 0x0f, 0x10),
+                isap=cfg.use_sse2)
+        I64.enc(base.load.bind(ty, i64), *recipe(pfx, 0x0f, 0x10),
+                isap=cfg.use_sse2)
+        I64.enc(base.load.bind(ty, i64), *recipe.rex(pfx, 0x0f, 0x10),
+                isap=cfg.use_sse2)
+    for recipe in [r.fst, r.fstDisp32]:
+        I32.enc(base.store.bind(ty, i32), *recipe(pfx, 0x0f, 0x11),
+                isap=cfg.use_sse2)
+        I64.enc(base.store.bind(ty, i64), *recipe(pfx, 0x0f, 0x11),
+                isap=cfg.use_sse2)
+        I64.enc(base.store.bind(ty, i64), *recipe.rex(pfx, 0x0f, 0x11),
+                isap=cfg.use_sse2)
+    enc_both(base.spill.bind(ty), r.fspill, pfx, 0x0f, 0x11)
+    enc_both(base.fill.bind(ty), r.ffill, pfx, 0x0f, 0x10)
+
+    # Conversions to and from integers. Conversions to integers truncate
+    # towards zero. Note that out of range conversions produce the "integer
+    # indefinite" value 0x80000000 instead of trapping.
+    enc_both(base.fcvt_from_sint.bind(ty, i32), r.frurm, pfx, 0x0f, 0x2a)
+    I64.enc(base.fcvt_from_sint.bind(ty, i64),
+            *r.frurm.rex(pfx, 0x0f, 0x2a, w=1), isap=cfg.use_sse2)
+    enc_both(base.fcvt_to_sint.bind(i32, ty), r.rfurm, pfx, 0x0f, 0x2c)
+    I64.enc(base.fcvt_to_sint.bind(i64, ty),
+            *r.rfurm.rex(pfx, 0x0f, 0x2c, w=1), isap=cfg.use_sse2)
+
+# Conversions between single and double precision.
+enc_both(base.fpromote.f64.f32, r.furm, 0xf3, 0x0f, 0x5a)
+enc_both(base.fdemote.f32.f64, r.furm, 0xf2, 0x0f, 0x5a)
+
+# Moves between integer and floating point registers with `movd` and `movq`.
+# The 64-bit moves are only available in I64.
+enc_both(base.bitcast.f32.i32, r.frurm, 0x66, 0x0f, 0x6e)
+enc_both(base.bitcast.i32.f32, r.rfumr, 0x66, 0x0f, 0x7e)
+I64.enc(base.bitcast.f64.i64, *r.frurm.rex(0x66, 0x0f, 0x6e, w=1),
+        isap=cfg.use_sse2)
+I64.enc(base.bitcast.i64.f64, *r.rfumr.rex(0x66, 0x0f, 0x7e, w=1),
+        isap=cfg.use_sse2)
+
+# Rounding with `roundss` and `roundsd` from SSE 4.1. The rounding mode
+# immediate is derived from the opcode.
+for inst in [base.nearest, base.floor, base.ceil, base.trunc]:
+    enc_both(inst.f32, r.furmi_rnd, 0x66, 0x0f, 0x3a, 0x0a,
+             isap=cfg.use_sse41)
+    enc_both(inst.f64, r.furmi_rnd, 0x66, 0x0f, 0x3a, 0x0b,
+             isap=cfg.use_sse41)
+
+# Floating point comparisons with `ucomiss` and `ucomisd`. The flags can only
+# express these condition codes with a single `setCC` instruction. The
+# remaining condition codes are expanded by the legalizer.
+for cond in ['ord', 'uno', 'gt', 'ge', 'ult', 'ule', 'ueq', 'one']:
+    instp = IsEqual(FloatCompare.cond, getattr(floatcc, cond))
+    enc_both(base.fcmp.f32, r.fcscc, 0x0f, 0x2e, instp=instp)
+    enc_both(base.fcmp.f64, r.fcscc, 0x66, 0x0f, 0x2e, instp=instp)
+
+# SIMD operations on 128-bit vectors with SSE2.
+for ty in [i8.by(16), i16.by(8), i32.by(4), i64.by(2),
+           f32.by(4), f64.by(2)]:
+    for inst,           opc in [
+            (base.band, 0xdb),
+            (base.bor,  0xeb),
+            (base.bxor, 0xef)
+            ]:
+        enc_both(inst.bind(ty), r.fa, 0x66, 0x0f, opc, isap=cfg.use_simd)
+    enc_both(base.copy.bind(ty), r.furm, 0x0f, 0x28, isap=cfg.use_simd)
+
+    # Unaligned loads and stores with `movups`.
+    for recipe in [r.fld, r.fldDisp32]:
+        I32.enc(base.load.bind(ty, i32), *recipe(0x0f, 0x10),
+                isap=cfg.use_simd)
+        I64.enc(base.load.bind(ty, i64), *recipe(0x0f, 0x10),
+                isap=cfg.use_simd)
+        I64.enc(base.load.bind(ty, i64), *recipe.rex(0x0f, 0x10),
+                isap=cfg.use_simd)
+    for recipe in [r.fst, r.fstDisp32]:
+        I32.enc(base.store.bind(ty, i32), *recipe(0x0f, 0x11),
+                isap=cfg.use_simd)
+        I64.enc(base.store.bind(ty, i64), *recipe(0x0f, 0x11),
+                isap=cfg.use_simd)
+        I64.enc(base.store.bind(ty, i64), *recipe.rex(0x0f, 0x11),
+                isap=cfg.use_simd)
+    enc_both(base.spill.bind(ty), r.fspill, 0x0f, 0x11, isap=cfg.use_simd)
+    enc_both(base.fill.bind(ty), r.ffill, 0x0f, 0x10, isap=cfg.use_simd)
+
+# Integer vector arithmetic, `padd*` and `psub*`.
+for ty,         add,  sub in [
+        (i8.by(16), 0xfc, 0xf8),
+        (i16.by(8), 0xfd, 0xf9),
+        (i32.by(4), 0xfe, 0xfa),
+        (i64.by(2), 0xd4, 0xfb)
+        ]:
+    enc_both(base.iadd.bind(ty), r.fa, 0x66, 0x0f, add, isap=cfg.use_simd)
+    enc_both(base.isub.bind(ty), r.fa, 0x66, 0x0f, sub, isap=cfg.use_simd)
+
+# Floating point vector arithmetic, `addps`, `addpd`, and friends.
+for ty,         pfx in [
+        (f32.by(4), ()),
+        (f64.by(2), (0x66,))
+        ]:
+    for inst,           opc in [
+            (base.fadd, 0x58),
+            (base.fsub, 0x5c),
+            (base.fmul, 0x59),
+            (base.fdiv, 0x5e),
+            ]:
+        enc_both(inst.bind(ty), r.fa, *(pfx + (0x0f, opc)),
+                 isap=cfg.use_simd)
+    enc_both(base.sqrt.bind(ty), r.furm, *(pfx + (0x0f, 0x51)),
+             isap=cfg.use_simd)
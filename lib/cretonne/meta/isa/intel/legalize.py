@@ -0,0 +1,61 @@
+"""
+Custom legalization patterns for Intel.
+"""
+from __future__ import absolute_import
+from cretonne.ast import Var
+from cretonne.xform import Rtl, XFormGroup
+from cretonne.base import udiv, sdiv, urem, srem, iconst, sshr_imm, copy
+from cretonne.types import i32, i64
+from . import instructions as x86
+
+intel_expand = XFormGroup('intel_expand', """
+        Legalize instructions by expansion.
+
+        Use Intel-specific instructions if needed. Instructions that don't
+        match any of these patterns are expanded by the shared `expand` group.
+        """)
+
+a = Var('a')
+q = Var('q')
+r = Var('r')
+x = Var('x')
+xhi = Var('xhi')
+y = Var('y')
+
+# The Intel division instructions divide a double-width numerator in
+# `%rdx:%rax` by a single-width denominator, and produce both the quotient
+# and the remainder. The unsigned numerator is zero-extended, and the signed
+# numerator is sign-extended into the high half.
+#
+# Note that `srem` traps when the quotient overflows, so `INT_MIN % -1` traps
+# instead of producing 0.
+for ty in [i32, i64]:
+    intel_expand.legalize(
+            a << udiv.bind(ty)(x, y),
+            Rtl(
+                xhi << iconst.bind(ty)(0),
+                (a, r) << x86.udivmodx(x, xhi, y)
+            ))
+
+    intel_expand.legalize(
+            a << urem.bind(ty)(x, y),
+            Rtl(
+                xhi << iconst.bind(ty)(0),
+                (q, r) << x86.udivmodx(x, xhi, y),
+                a << copy(r)
+            ))
+
+    intel_expand.legalize(
+            a << sdiv.bind(ty)(x, y),
+            Rtl(
+                xhi << sshr_imm.bind(ty)(x, ty.bits - 1),
+                (a, r) << x86.sdivmodx(x, xhi, y)
+            ))
+
+    intel_expand.legalize(
+            a << srem.bind(ty)(x, y),
+            Rtl(
+                xhi << sshr_imm.bind(ty)(x, ty.bits - 1),
+                (q, r) << x86.sdivmodx(x, xhi, y),
+                a << copy(r)
+            ))
@@ -0,0 +1,781 @@
+"""
+Intel Encoding recipes.
+"""
+from __future__ import absolute_import
+from cretonne import EncRecipe
+from cretonne.formats import Nullary, Unary, UnaryImm, Binary, BinaryImm
+from cretonne.formats import TernaryOverflow, IntCompare, FloatCompare
+from cretonne.formats import Load, Store, UnaryGlobalVar, Call, Jump, Branch
+from cretonne.formats import BranchIcmp, Return
+from cretonne.predicates import IsSignedInt
+from cretonne.registers import RegClass, Stack
+from .registers import GPR, GPR8, ABCD, FPR, FPR8
+
+try:
+    from typing import Tuple, Dict  # noqa
+    from cretonne import InstructionFormat  # noqa
+    from cretonne import OperandConstraint, ConstraintSeq, AnyPredicate  # noqa
+except ImportError:
+    pass
+
+
+# Opcode representation.
+#
+# Cretonne requires each recipe to have a single encoding size in bytes, and
+# Intel opcodes are variable length, so we use separate recipes for different
+# styles of opcodes and prefixes. The opcode format is indicated by the recipe
+# name prefix:
+
+OPCODE_PREFIX = {
+        # Prefix bytes       Name     mmpp
+        ():                 ('Op1', 0b0000),
+        (0x66,):            ('Mp1', 0b0001),
+        (0xf3,):            ('Mp1', 0b0010),
+        (0xf2,):            ('Mp1', 0b0011),
+        (0x0f,):            ('Op2', 0b0100),
+        (0x66, 0x0f):       ('Mp2', 0b0101),
+        (0xf3, 0x0f):       ('Mp2', 0b0110),
+        (0xf2, 0x0f):       ('Mp2', 0b0111),
+        (0x0f, 0x38):       ('Op3', 0b1000),
+        (0x66, 0x0f, 0x38): ('Mp3', 0b1001),
+        (0xf3, 0x0f, 0x38): ('Mp3', 0b1010),
+        (0xf2, 0x0f, 0x38): ('Mp3', 0b1011),
+        (0x0f, 0x3a):       ('Op3', 0b1100),
+        (0x66, 0x0f, 0x3a): ('Mp3', 0b1101),
+        (0xf3, 0x0f, 0x3a): ('Mp3', 0b1110),
+        (0xf2, 0x0f, 0x3a): ('Mp3', 0b1111)
+        }
+
+# The table above does not include the REX prefix which goes after the
+# mandatory prefix. VEX/XOP and EVEX prefixes are not yet supported. Encodings
+# using any of these prefixes are represented by separate recipes.
+#
+# The encoding bits are:
+#
+# 0-7:   The opcode byte <op>.
+# 8-9:   pp, mandatory prefix:
+#        00 none (Op*)
+#        01 66   (Mp*)
+#        10 F3   (Mp*)
+#        11 F2   (Mp*)
+# 10-11: mm, opcode map:
+#        00 <op>        (Op1/Mp1)
+#        01 0F <op>     (Op2/Mp2)
+#        10 0F 38 <op>  (Op3/Mp3)
+#        11 0F 3A <op>  (Op3/Mp3)
+# 12-14  rrr, opcode bits for the ModR/M byte for certain opcodes.
+# 15:    REX.W bit (or VEX.W/E)
+#
+# There is some redundancy between bits 8-11 and the recipe names, but we have
+# enough bits, and the pp+mm format is ready for supporting VEX prefixes.
+
+
+def decode_ops(ops, rrr=0, w=0):
+    # type: (Tuple[int, ...], int, int) -> Tuple[str, int]
+    """
+    Given a sequence of opcode bytes, compute the recipe name prefix and
+    encoding bits.
+    """
+    assert rrr <= 0b111
+    assert w <= 1
+    name, mmpp = OPCODE_PREFIX[ops[:-1]]
+    op = ops[-1]
+    assert op < 256
+    return (name, op | (mmpp << 8) | (rrr << 12) | (w << 15))
+
+
+def replace_put_op(emit, prefix):
+    # type: (str, str) -> str
+    """
+    Given a snippet of Rust code, replace the `PUT_OP` macro with the
+    corresponding `put_*` function from the `binemit.rs` module.
+    """
+    return emit.replace('PUT_OP', 'put_' + prefix.lower())
+
+
+# Register class mapping for no-REX instructions.
+NOREX_MAP = {
+        GPR: GPR8,
+        FPR: FPR8
+    }
+
+
+def map_regs_norex(regs):
+    # type: (ConstraintSeq) -> Tuple[OperandConstraint, ...]
+    """
+    Replace the register classes in `regs` with the smaller classes that can
+    be encoded without a REX prefix.
+    """
+    def map_norex(rc):
+        # type: (OperandConstraint) -> OperandConstraint
+        if isinstance(rc, RegClass):
+            return NOREX_MAP.get(rc, rc)
+        if isinstance(rc, Stack):
+            return Stack(NOREX_MAP.get(rc.regclass, rc.regclass))
+        return rc
+    if not isinstance(regs, tuple):
+        regs = (regs,)
+    return tuple(map_norex(rc) for rc in regs)
+
+
+class TailRecipe(object):
+    """
+    Generate encoding recipes on demand.
+
+    Intel encodings are somewhat orthogonal with the opcode representation on
+    one side and the ModR/M, SIB and immediate fields on the other side.
+
+    A `TailRecipe` represents the part of an encoding that follow the opcode.
+    It is used to generate full encoding recipes on demand when combined with
+    an opcode.
+
+    The arguments are the same as for an `EncRecipe`, except for `size` which
+    does not include the size of the opcode, and `branch_range` which is just
+    the number of bits in the displacement. Intel branch displacements are
+    relative to the end of the instruction.
+
+    The `emit` parameter contains Rust code to actually emit an encoding, like
+    `EncRecipe` does it. Additionally, the text `PUT_OP` is substituted with
+    the proper `put_*` function from the `intel/binemit.rs` module.
+
+    The register constraints are given for the REX-prefixed recipes. The
+    recipes without a REX prefix can only use the low 8 registers, so `GPR`
+    and `FPR` operands are replaced by `GPR8` and `FPR8`.
+    """
+
+    def __init__(
+            self, name, format, size, ins, outs,
+            branch_range=None, instp=None, isap=None, emit=None):
+        # type: (str, InstructionFormat, int, ConstraintSeq, ConstraintSeq, int, AnyPredicate, AnyPredicate, str) -> None # noqa
+        self.name = name
+        self.format = format
+        self.size = size
+        self.ins = ins
+        self.outs = outs
+        self.branch_range = branch_range
+        self.instp = instp
+        self.isap = isap
+        self.emit = emit
+
+        # Cached recipes, keyed by name prefix.
+        self.recipes = dict()  # type: Dict[str, EncRecipe]
+
+    def make_recipe(self, prefix, size, ins, outs):
+        # type: (str, int, ConstraintSeq, ConstraintSeq) -> EncRecipe
+        """
+        Get the recipe for the opcode format `prefix`, creating it on first
+        use.
+        """
+        if prefix not in self.recipes:
+            branch_range = None  # type: Tuple[int, int]
+            if self.branch_range is not None:
+                branch_range = (size, self.branch_range)
+            self.recipes[prefix] = EncRecipe(
+                prefix + self.name,
+                self.format,
+                size,
+                ins=ins,
+                outs=outs,
+                branch_range=branch_range,
+                instp=self.instp,
+                isap=self.isap,
+                emit=replace_put_op(self.emit, prefix))
+        return self.recipes[prefix]
+
+    def __call__(self, *ops, **kwargs):
+        # type: (*int, **int) -> Tuple[EncRecipe, int]
+        """
+        Create an encoding recipe and encoding bits for the opcode bytes in
+        `ops`.
+        """
+        rrr = kwargs.get('rrr', 0)
+        w = kwargs.get('w', 0)
+        assert w == 0, "REX.W requires a REX prefix"
+        name, bits = decode_ops(ops, rrr, w)
+        recipe = self.make_recipe(
+                name, len(ops) + self.size,
+                map_regs_norex(self.ins), map_regs_norex(self.outs))
+        return (recipe, bits)
+
+    def rex(self, *ops, **kwargs):
+        # type: (*int, **int) -> Tuple[EncRecipe, int]
+        """
+        Create a REX encoding recipe and encoding bits for the opcode bytes in
+        `ops`.
+
+        The recipe will always generate a REX prefix, whether it is required or
+        not. For instructions that don't require a REX prefix, two encodings
+        should be added: One with REX and one without.
+        """
+        rrr = kwargs.get('rrr', 0)
+        w = kwargs.get('w', 0)
+        name, bits = decode_ops(ops, rrr, w)
+        recipe = self.make_recipe(
+                'Rex' + name, 1 + len(ops) + self.size, self.ins, self.outs)
+        return (recipe, bits)
+
+
+# A null unary instruction that takes a GPR register. Can be used for identity
+# copies and no-op conversions.
+null = EncRecipe('null', Unary, size=0, ins=GPR, outs=0, emit='')
+
+# XX /r
+rr = TailRecipe(
+        'rr', Binary, size=1, ins=(GPR, GPR), outs=0,
+        emit='''
+        PUT_OP(bits, rex2(in_reg0, in_reg1), sink);
+        modrm_rr(in_reg0, in_reg1, sink);
+        ''')
+
+# XX /r with operands swapped. (RM form).
+rrx = TailRecipe(
+        'rrx', Binary, size=1, ins=(GPR, GPR), outs=0,
+        emit='''
+        PUT_OP(bits, rex2(in_reg1, in_reg0), sink);
+        modrm_rr(in_reg1, in_reg0, sink);
+        ''')
+
+# XX /r with FPR ins and outs. RM form.
+fa = TailRecipe(
+        'fa', Binary, size=1, ins=(FPR, FPR), outs=0,
+        emit='''
+        PUT_OP(bits, rex2(in_reg1, in_reg0), sink);
+        modrm_rr(in_reg1, in_reg0, sink);
+        ''')
+
+# XX /n with one arg in %rcx, for shifts.
+rc = TailRecipe(
+        'rc', Binary, size=1, ins=(GPR, GPR[1]), outs=0,
+        emit='''
+        PUT_OP(bits, rex1(in_reg0), sink);
+        modrm_r_bits(in_reg0, bits, sink);
+        ''')
+
+# XX /n for division: inputs in %rax, %rdx, r. Outputs in %rax, %rdx.
+div = TailRecipe(
+        'div', TernaryOverflow, size=1,
+        ins=(GPR[0], GPR[2], GPR), outs=(GPR[0], GPR[2]),
+        emit='''
+        PUT_OP(bits, rex1(in_reg2), sink);
+        modrm_r_bits(in_reg2, bits, sink);
+        ''')
+
+# XX /n with one operand, like `not r`.
+ur = TailRecipe(
+        'ur', Unary, size=1, ins=GPR, outs=0,
+        emit='''
+        PUT_OP(bits, rex1(in_reg0), sink);
+        modrm_r_bits(in_reg0, bits, sink);
+        ''')
+
+# XX /r, but for a unary operator with separate input/output register, like
+# copies. MR form.
+umr = TailRecipe(
+        'umr', Unary, size=1, ins=GPR, outs=GPR,
+        emit='''
+        PUT_OP(bits, rex2(out_reg0, in_reg0), sink);
+        modrm_rr(out_reg0, in_reg0, sink);
+        ''')
+
+# XX /r, but for a unary operator with separate input/output register.
+# RM form.
+urm = TailRecipe(
+        'urm', Unary, size=1, ins=GPR, outs=GPR,
+        emit='''
+        PUT_OP(bits, rex2(in_reg0, out_reg0), sink);
+        modrm_rr(in_reg0, out_reg0, sink);
+        ''')
+
+# XX /r. Same as urm, but input limited to ABCD. Used for instructions that
+# read the low byte of the input register.
+urm_abcd = TailRecipe(
+        'urm_abcd', Unary, size=1, ins=ABCD, outs=GPR,
+        emit='''
+        PUT_OP(bits, rex2(in_reg0, out_reg0), sink);
+        modrm_rr(in_reg0, out_reg0, sink);
+        ''')
+
+# XX /r, RM form, FPR -> FPR.
+furm = TailRecipe(
+        'furm', Unary, size=1, ins=FPR, outs=FPR,
+        emit='''
+        PUT_OP(bits, rex2(in_reg0, out_reg0), sink);
+        modrm_rr(in_reg0, out_reg0, sink);
+        ''')
+
+# XX /r, RM form, GPR -> FPR.
+frurm = TailRecipe(
+        'frurm', Unary, size=1, ins=GPR, outs=FPR,
+        emit='''
+        PUT_OP(bits, rex2(in_reg0, out_reg0), sink);
+        modrm_rr(in_reg0, out_reg0, sink);
+        ''')
+
+# XX /r, MR form, FPR -> GPR.
+rfumr = TailRecipe(
+        'rfumr', Unary, size=1, ins=FPR, outs=GPR,
+        emit='''
+        PUT_OP(bits, rex2(out_reg0, in_reg0), sink);
+        modrm_rr(out_reg0, in_reg0, sink);
+        ''')
+
+# XX /r, RM form, FPR -> GPR.
+rfurm = TailRecipe(
+        'rfurm', Unary, size=1, ins=FPR, outs=GPR,
+        emit='''
+        PUT_OP(bits, rex2(in_reg0, out_reg0), sink);
+        modrm_rr(in_reg0, out_reg0, sink);
+        ''')
+
+# XX /r ib with the rounding mode immediate derived from the opcode, like
+# `roundss`. RM form, FPR -> FPR.
+furmi_rnd = TailRecipe(
+        'furmi_rnd', Unary, size=2, ins=FPR, outs=FPR,
+        emit='''
+        PUT_OP(bits, rex2(in_reg0, out_reg0), sink);
+        modrm_rr(in_reg0, out_reg0, sink);
+        sink.put1(match opcode {
+            Opcode::Nearest => 0b00,
+            Opcode::Floor => 0b01,
+            Opcode::Ceil => 0b10,
+            Opcode::Trunc => 0b11,
+            x => panic!("{} unexpected for furmi_rnd", x),
+        });
+        ''')
+
+# XX /n ib with 8-bit immediate sign-extended.
+rib = TailRecipe(
+        'rib', BinaryImm, size=2, ins=GPR, outs=0,
+        instp=IsSignedInt(BinaryImm.imm, 8),
+        emit='''
+        PUT_OP(bits, rex1(in_reg0), sink);
+        modrm_r_bits(in_reg0, bits, sink);
+        let imm: i64 = imm.into();
+        sink.put1(imm as u8);
+        ''')
+
+# XX /n id with 32-bit immediate sign-extended.
+rid = TailRecipe(
+        'rid', BinaryImm, size=5, ins=GPR, outs=0,
+        instp=IsSignedInt(BinaryImm.imm, 32),
+        emit='''
+        PUT_OP(bits, rex1(in_reg0), sink);
+        modrm_r_bits(in_reg0, bits, sink);
+        let imm: i64 = imm.into();
+        sink.put4(imm as u32);
+        ''')
+
+# XX /n ib with an 8-bit shift amount. Only the low bits of the shift amount
+# are used, so there is no instruction predicate.
+rshib = TailRecipe(
+        'rshib', BinaryImm, size=2, ins=GPR, outs=0,
+        emit='''
+        PUT_OP(bits, rex1(in_reg0), sink);
+        modrm_r_bits(in_reg0, bits, sink);
+        let imm: i64 = imm.into();
+        sink.put1(imm as u8);
+        ''')
+
+# XX+rd id unary with 32-bit immediate. Note no recipe predicate.
+pu_id = TailRecipe(
+        'pu_id', UnaryImm, size=4, ins=(), outs=GPR,
+        emit='''
+        // The destination register is added to the `B8+rd` op byte, and there
+        // is no ModR/M byte.
+        PUT_OP(bits | (out_reg0 & 7), rex1(out_reg0), sink);
+        let imm: i64 = imm.into();
+        sink.put4(imm as u32);
+        ''')
+
+# XX /n id with 32-bit immediate sign-extended. UnaryImm version.
+u_id = TailRecipe(
+        'u_id', UnaryImm, size=5, ins=(), outs=GPR,
+        instp=IsSignedInt(UnaryImm.imm, 32),
+        emit='''
+        PUT_OP(bits, rex1(out_reg0), sink);
+        modrm_r_bits(out_reg0, bits, sink);
+        let imm: i64 = imm.into();
+        sink.put4(imm as u32);
+        ''')
+
+# XX+rd iq unary with 64-bit immediate.
+pu_iq = TailRecipe(
+        'pu_iq', UnaryImm, size=8, ins=(), outs=GPR,
+        emit='''
+        PUT_OP(bits | (out_reg0 & 7), rex1(out_reg0), sink);
+        let imm: i64 = imm.into();
+        sink.put8(imm as u64);
+        ''')
+
+# XX+rd id with an absolute 32-bit address of a global symbol.
+gsym_id = TailRecipe(
+        'gsym_id', UnaryGlobalVar, size=4, ins=(), outs=GPR,
+        emit='''
+        PUT_OP(bits | (out_reg0 & 7), rex1(out_reg0), sink);
+        sink.reloc_external(RelocKind::Abs4.into(),
+                            globalsym_name(func, global_var));
+        sink.put4(0);
+        ''')
+
+# XX+rd iq with an absolute 64-bit address of a global symbol.
+gsym_iq = TailRecipe(
+        'gsym_iq', UnaryGlobalVar, size=8, ins=(), outs=GPR,
+        emit='''
+        PUT_OP(bits | (out_reg0 & 7), rex1(out_reg0), sink);
+        sink.reloc_external(RelocKind::Abs8.into(),
+                            globalsym_name(func, global_var));
+        sink.put8(0);
+        ''')
+
+# Stack pointer adjustment with an 8-bit immediate, `add rsp, ib`.
+adjustsp_ib = TailRecipe(
+        'adjustsp_ib', UnaryImm, size=2, ins=(), outs=(),
+        instp=IsSignedInt(UnaryImm.imm, 8),
+        emit='''
+        PUT_OP(bits, rex1(RSP), sink);
+        modrm_r_bits(RSP, bits, sink);
+        let imm: i64 = imm.into();
+        sink.put1(imm as u8);
+        ''')
+
+# Stack pointer adjustment with a 32-bit immediate, `add rsp, id`.
+adjustsp_id = TailRecipe(
+        'adjustsp_id', UnaryImm, size=5, ins=(), outs=(),
+        instp=IsSignedInt(UnaryImm.imm, 32),
+        emit='''
+        PUT_OP(bits, rex1(RSP), sink);
+        modrm_r_bits(RSP, bits, sink);
+        let imm: i64 = imm.into();
+        sink.put4(imm as u32);
+        ''')
+
+#
+# Store recipes.
+#
+# All memory operands use a SIB byte without an index register, so any base
+# register can be used, and they always have a displacement.
+#
+
+# XX /r register-indirect store with 8-bit offset.
+st = TailRecipe(
+        'st', Store, size=3, ins=(GPR, GPR), outs=(),
+        instp=IsSignedInt(Store.offset, 8),
+        emit='''
+        PUT_OP(bits, rex2(in_reg1, in_reg0), sink);
+        modrm_sib_disp8(in_reg0, sink);
+        sib_noindex(in_reg1, sink);
+        let offset: i32 = offset.into();
+        sink.put1(offset as u8);
+        ''')
+
+# XX /r register-indirect store with 32-bit offset.
+stDisp32 = TailRecipe(
+        'stDisp32', Store, size=6, ins=(GPR, GPR), outs=(),
+        emit='''
+        PUT_OP(bits, rex2(in_reg1, in_reg0), sink);
+        modrm_sib_disp32(in_reg0, sink);
+        sib_noindex(in_reg1, sink);
+        let offset: i32 = offset.into();
+        sink.put4(offset as u32);
+        ''')
+
+# Byte stores need the low byte of the stored register, which is only
+# addressable in ABCD without a REX prefix.
+st_abcd = TailRecipe(
+        'st_abcd', Store, size=3, ins=(ABCD, GPR), outs=(),
+        instp=IsSignedInt(Store.offset, 8),
+        emit='''
+        PUT_OP(bits, rex2(in_reg1, in_reg0), sink);
+        modrm_sib_disp8(in_reg0, sink);
+        sib_noindex(in_reg1, sink);
+        let offset: i32 = offset.into();
+        sink.put1(offset as u8);
+        ''')
+
+stDisp32_abcd = TailRecipe(
+        'stDisp32_abcd', Store, size=6, ins=(ABCD, GPR), outs=(),
+        emit='''
+        PUT_OP(bits, rex2(in_reg1, in_reg0), sink);
+        modrm_sib_disp32(in_reg0, sink);
+        sib_noindex(in_reg1, sink);
+        let offset: i32 = offset.into();
+        sink.put4(offset as u32);
+        ''')
+
+# XX /r floating point store with 8-bit offset.
+fst = TailRecipe(
+        'fst', Store, size=3, ins=(FPR, GPR), outs=(),
+        instp=IsSignedInt(Store.offset, 8),
+        emit='''
+        PUT_OP(bits, rex2(in_reg1, in_reg0), sink);
+        modrm_sib_disp8(in_reg0, sink);
+        sib_noindex(in_reg1, sink);
+        let offset: i32 = offset.into();
+        sink.put1(offset as u8);
+        ''')
+
+# XX /r floating point store with 32-bit offset.
+fstDisp32 = TailRecipe(
+        'fstDisp32', Store, size=6, ins=(FPR, GPR), outs=(),
+        emit='''
+        PUT_OP(bits, rex2(in_reg1, in_reg0), sink);
+        modrm_sib_disp32(in_reg0, sink);
+        sib_noindex(in_reg1, sink);
+        let offset: i32 = offset.into();
+        sink.put4(offset as u32);
+        ''')
+
+# Spill a register to a spill slot, `mov [rsp+disp32], r`.
+spill = TailRecipe(
+        'spill', Unary, size=6, ins=GPR, outs=Stack(GPR),
+        emit='''
+        let offset = stack_slot_offset(func, out_ss0);
+        PUT_OP(bits, rex2(RSP, in_reg0), sink);
+        modrm_sib_disp32(in_reg0, sink);
+        sib_noindex(RSP, sink);
+        sink.put4(offset as u32);
+        ''')
+
+# Spill a floating point register.
+fspill = TailRecipe(
+        'fspill', Unary, size=6, ins=FPR, outs=Stack(FPR),
+        emit='''
+        let offset = stack_slot_offset(func, out_ss0);
+        PUT_OP(bits, rex2(RSP, in_reg0), sink);
+        modrm_sib_disp32(in_reg0, sink);
+        sib_noindex(RSP, sink);
+        sink.put4(offset as u32);
+        ''')
+
+#
+# Load recipes
+#
+
+# XX /r load with 8-bit offset.
+ld = TailRecipe(
+        'ld', Load, size=3, ins=GPR, outs=GPR,
+        instp=IsSignedInt(Load.offset, 8),
+        emit='''
+        PUT_OP(bits, rex2(in_reg0, out_reg0), sink);
+        modrm_sib_disp8(out_reg0, sink);
+        sib_noindex(in_reg0, sink);
+        let offset: i32 = offset.into();
+        sink.put1(offset as u8);
+        ''')
+
+# XX /r load with 32-bit offset.
+ldDisp32 = TailRecipe(
+        'ldDisp32', Load, size=6, ins=GPR, outs=GPR,
+        emit='''
+        PUT_OP(bits, rex2(in_reg0, out_reg0), sink);
+        modrm_sib_disp32(out_reg0, sink);
+        sib_noindex(in_reg0, sink);
+        let offset: i32 = offset.into();
+        sink.put4(offset as u32);
+        ''')
+
+# XX /r float load with 8-bit offset.
+fld = TailRecipe(
+        'fld', Load, size=3, ins=GPR, outs=FPR,
+        instp=IsSignedInt(Load.offset, 8),
+        emit='''
+        PUT_OP(bits, rex2(in_reg0, out_reg0), sink);
+        modrm_sib_disp8(out_reg0, sink);
+        sib_noindex(in_reg0, sink);
+        let offset: i32 = offset.into();
+        sink.put1(offset as u8);
+        ''')
+
+# XX /r float load with 32-bit offset.
+fldDisp32 = TailRecipe(
+        'fldDisp32', Load, size=6, ins=GPR, outs=FPR,
+        emit='''
+        PUT_OP(bits, rex2(in_reg0, out_reg0), sink);
+        modrm_sib_disp32(out_reg0, sink);
+        sib_noindex(in_reg0, sink);
+        let offset: i32 = offset.into();
+        sink.put4(offset as u32);
+        ''')
+
+# Fill a register from a spill slot, `mov r, [rsp+disp32]`.
+fill = TailRecipe(
+        'fill', Unary, size=6, ins=Stack(GPR), outs=GPR,
+        emit='''
+        let offset = stack_slot_offset(func, in_ss0);
+        PUT_OP(bits, rex2(RSP, out_reg0), sink);
+        modrm_sib_disp32(out_reg0, sink);
+        sib_noindex(RSP, sink);
+        sink.put4(offset as u32);
+        ''')
+
+# Fill a floating point register.
+ffill = TailRecipe(
+        'ffill', Unary, size=6, ins=Stack(FPR), outs=FPR,
+        emit='''
+        let offset = stack_slot_offset(func, in_ss0);
+        PUT_OP(bits, rex2(RSP, out_reg0), sink);
+        modrm_sib_disp32(out_reg0, sink);
+        sib_noindex(RSP, sink);
+        sink.put4(offset as u32);
+        ''')
+
+#
+# Call/return
+#
+
+# Direct call with a 32-bit PC-relative displacement, `call rel32`.
+call_id = TailRecipe(
+        'call_id', Call, size=4, ins=(), outs=(),
+        emit='''
+        PUT_OP(bits, BASE_REX, sink);
+        sink.reloc_external(RelocKind::PCRel4.into(),
+                            &func.dfg.ext_funcs[data.func_ref].name);
+        sink.put4(0);
+        ''')
+
+ret = TailRecipe(
+        'ret', Return, size=0, ins=(), outs=(),
+        emit='PUT_OP(bits, BASE_REX, sink);')
+
+#
+# Branches
+#
+
+# Unconditional jump with an 8-bit displacement, `jmp rel8`.
+jmpb = TailRecipe(
+        'jmpb', Jump, size=1, ins=(), outs=(), branch_range=8,
+        emit='''
+        PUT_OP(bits, BASE_REX, sink);
+        disp1(data.destination, func, sink);
+        ''')
+
+# Unconditional jump with a 32-bit displacement, `jmp rel32`.
+jmpd = TailRecipe(
+        'jmpd', Jump, size=4, ins=(), outs=(), branch_range=32,
+        emit='''
+        PUT_OP(bits, BASE_REX, sink);
+        disp4(data.destination, func, sink);
+        ''')
+
+# Test a register against zero and branch, `test r, r` followed by a `jcc` with
+# an 8-bit displacement. The encoding bits describe the `test` instruction,
+# and the low byte is the `jcc` opcode.
+tjccb = TailRecipe(
+        'tjccb', Branch, size=1 + 2, ins=GPR, outs=(), branch_range=8,
+        emit='''
+        // test r, r.
+        PUT_OP((bits & 0xff00) | 0x85, rex2(in_reg0, in_reg0), sink);
+        modrm_rr(in_reg0, in_reg0, sink);
+        // Jcc instruction.
+        sink.put1(bits as u8);
+        disp1(data.destination, func, sink);
+        ''')
+
+# Same as `tjccb` with a 32-bit displacement, `jcc rel32`.
+tjccd = TailRecipe(
+        'tjccd', Branch, size=1 + 6, ins=GPR, outs=(), branch_range=32,
+        emit='''
+        // test r, r.
+        PUT_OP((bits & 0xff00) | 0x85, rex2(in_reg0, in_reg0), sink);
+        modrm_rr(in_reg0, in_reg0, sink);
+        // Jcc instruction.
+        sink.put1(0x0f);
+        sink.put1(bits as u8);
+        disp4(data.destination, func, sink);
+        ''')
+
+# Compare two registers and branch, `cmp r, r` followed by a `jcc` with an
+# 8-bit displacement. The encoding bits describe the `cmp` instruction, and
+# the condition code is taken from the instruction.
+icbrb = TailRecipe(
+        'icbrb', BranchIcmp, size=1 + 2, ins=(GPR, GPR), outs=(),
+        branch_range=8,
+        emit='''
+        // cmp r1, r2.
+        PUT_OP(bits, rex2(in_reg0, in_reg1), sink);
+        modrm_rr(in_reg0, in_reg1, sink);
+        // Jcc instruction.
+        sink.put1(0x70 | icc2opc(data.cond));
+        disp1(data.destination, func, sink);
+        ''')
+
+# Same as `icbrb` with a 32-bit displacement, `jcc rel32`.
+icbrd = TailRecipe(
+        'icbrd', BranchIcmp, size=1 + 6, ins=(GPR, GPR), outs=(),
+        branch_range=32,
+        emit='''
+        // cmp r1, r2.
+        PUT_OP(bits, rex2(in_reg0, in_reg1), sink);
+        modrm_rr(in_reg0, in_reg1, sink);
+        // Jcc instruction.
+        sink.put1(0x0f);
+        sink.put1(0x80 | icc2opc(data.cond));
+        disp4(data.destination, func, sink);
+        ''')
+
+#
+# Traps
+#
+
+# Unconditional trap, `ud2`.
+trap = TailRecipe(
+        'trap', Nullary, size=0, ins=(), outs=(),
+        emit='PUT_OP(bits, BASE_REX, sink);')
+
+# Test a register against zero and trap, `test r, r` followed by a `jcc` that
+# skips over a `ud2` instruction unless the trap condition holds. The encoding
+# bits describe the `test` instruction, and the low byte is the `jcc` opcode.
+ttrap = TailRecipe(
+        'ttrap', Unary, size=1 + 2 + 2, ins=GPR, outs=(),
+        emit='''
+        // test r, r.
+        PUT_OP((bits & 0xff00) | 0x85, rex2(in_reg0, in_reg0), sink);
+        modrm_rr(in_reg0, in_reg0, sink);
+        // Jcc over the `ud2` instruction.
+        sink.put1(bits as u8);
+        sink.put1(2);
+        // ud2.
+        sink.put1(0x0f);
+        sink.put1(0x0b);
+        ''')
+
+#
+# Comparisons
+#
+
+# XX /r, setCC, movzx. The result is a `b1` value in the low bits of an ABCD
+# register, so the `setCC` and `movzx` instructions never need a REX prefix.
+icscc = TailRecipe(
+        'icscc', IntCompare, size=1 + 3 + 3, ins=(GPR, GPR), outs=ABCD,
+        emit='''
+        // Comparison instruction.
+        PUT_OP(bits, rex2(in_reg0, in_reg1), sink);
+        modrm_rr(in_reg0, in_reg1, sink);
+        // `setCC` instruction, no REX.
+        sink.put1(0x0f);
+        sink.put1(0x90 | icc2opc(cond));
+        modrm_rr(out_reg0, 0, sink);
+        // `movzbl` instruction, no REX.
+        sink.put1(0x0f);
+        sink.put1(0xb6);
+        modrm_rr(out_reg0, out_reg0, sink);
+        ''')
+
+# `ucomiss` or `ucomisd`, setCC, movzx. The first operand goes in the ModR/M
+# reg field, so the flags describe the comparison of the first operand with
+# the second.
+fcscc = TailRecipe(
+        'fcscc', FloatCompare, size=1 + 3 + 3, ins=(FPR, FPR), outs=ABCD,
+        emit='''
+        // Comparison instruction.
+        PUT_OP(bits, rex2(in_reg1, in_reg0), sink);
+        modrm_rr(in_reg1, in_reg0, sink);
+        // `setCC` instruction, no REX.
+        sink.put1(0x0f);
+        sink.put1(0x90 | fcc2opc(cond));
+        modrm_rr(out_reg0, 0, sink);
+        // `movzbl` instruction, no REX.
+        sink.put1(0x0f);
+        sink.put1(0xb6);
+        modrm_rr(out_reg0, out_reg0, sink);
+        ''')
@@ -0,0 +1,34 @@
+"""
+Intel register banks.
+
+The `IntRegs` bank uses the 64-bit register names, even in 32-bit mode where
+only the low 8 registers are available, and only their low 32 bits are used.
+
+Many instruction encodings can only address the low 8 registers in a bank
+without a REX prefix. The `GPR8` and `FPR8` register classes are used for
+those encodings.
+"""
+from __future__ import absolute_import
+from cretonne.registers import RegBank, RegClass
+from .defs import isa
+
+
+# The integer registers are numbered in ModR/M order, so the register unit
+# number is the hardware register encoding.
+IntRegs = RegBank(
+        'IntRegs', isa,
+        'General purpose registers',
+        units=16, prefix='r',
+        names='rax rcx rdx rbx rsp rbp rsi rdi'.split())
+
+FloatRegs = RegBank(
+        'FloatRegs', isa,
+        'SSE floating point registers',
+        units=16, prefix='xmm')
+
+GPR = RegClass('GPR', IntRegs)
+GPR8 = RegClass('GPR8', IntRegs, count=8)
+# Registers whose low byte can be addressed without a REX prefix.
+ABCD = RegClass('ABCD', IntRegs, count=4)
+FPR = RegClass('FPR', FloatRegs)
+FPR8 = RegClass('FPR8', FloatRegs, count=8)
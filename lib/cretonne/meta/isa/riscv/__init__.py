@@ -19,6 +19,9 @@ RV32F / RV64F
 RV32D / RV64D
     Double-precision IEEE floating point.
 
+RV32C / RV64C
+    Compressed 16-bit encodings of common instructions.
+
 RV32G / RV64G
     General purpose instruction sets. This represents the union of the I, M, A,
     F, and D instruction sets listed above.
@@ -26,7 +29,7 @@ RV32G / RV64G
 """
 from __future__ import absolute_import
 from . import defs
-from . import encodings, settings # noqa
+from . import encodings, settings, registers  # noqa
 
 # Re-export the primary target ISA definition.
 isa = defs.isa.finish()
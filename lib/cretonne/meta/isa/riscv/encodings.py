@@ -3,9 +3,95 @@ RISC-V Encodings.
 """
 from __future__ import absolute_import
 from cretonne import base
+from cretonne.formats import IntCompare, FloatCompare, BranchIcmp
+from cretonne.formats import BinaryImm, Load, Store
+from cretonne.formats import AtomicRmw, AtomicLoad, AtomicStore
+from cretonne.immediates import intcc, floatcc, atomicop, ordering
+from cretonne.types import i8, i16, i32, i64, f32, f64
+from cretonne.legalize import widen, expand
+from cretonne.predicates import IsEqual, IsSignedInt, IsUnsignedInt
 from .defs import RV32, RV64
-from .recipes import OPIMM, OPIMM32, OP, OP32, R, Rshamt, I
-from .settings import use_m
+from .recipes import LOAD, STORE, BRANCH, OPIMM, OPIMM32, OP, OP32, JALR
+from .recipes import JAL, LUI, LOADFP, STOREFP, OPFP, MADD, FCVT, MISCMEM, AMO
+from .recipes import RVC, CARITH
+from .recipes import R, Rshamt, Ricmp, Ricmpinv, Ricmpeq, Ricmpne
+from .recipes import I, Iz, U, Iadjsp, Icopy, Iext, Iload, S, GPsp, GPfi
+from .recipes import Ucall, Ugsym, Ugsym64, UJ, SBzero, SBzeroLong, SB, SBlong
+from .recipes import Radjsp, Itrap, SBtrap, Rdiv, Rsdiv, Rsdiv64
+from .recipes import Iret, Ibnot, Rf, Rfsgnj, Rfcmp, R4, Rff, Rfi, Rif
+from .recipes import Fload, Fstore, FPsp, FPfi
+from .recipes import Ramo, Rcas, Iaload, Ialoadacq, Ialoadsc, Sastore
+from .recipes import Sastorerel, Ifence
+from .recipes import CR, CRmv, CA, CI, CIshamt, CIz, CIadjsp, CB, CL, CS
+from .recipes import CJ, CBz, CJr
+from .settings import use_m, use_a, use_f, use_d, supports_c
+
+# "C" Standard Extension for compressed instructions. Gated by the
+# `supports_c` flag.
+#
+# The 16-bit encodings have tighter register and immediate constraints than
+# the 32-bit instructions, and many of them overwrite their first operand.
+# They are listed first since they are the most specific encodings, so the
+# general 32-bit encodings are picked before register allocation. Branch
+# relaxation switches every instruction to its smallest encoding with register
+# constraints that are satisfied by the allocated registers.
+#
+# Spills and fills could use `c.swsp` and `c.lwsp`, but the stack slot offsets
+# are not available to instruction predicates.
+for cpu,  ty,  shamt in [
+        (RV32, i32, 5),
+        (RV64, i64, 6)
+        ]:
+    cpu.enc(base.iadd.bind(ty), CR, RVC(0b10, 0b100, 1 << 12),
+            isap=supports_c)
+    cpu.enc(base.copy.bind(ty), CRmv, RVC(0b10, 0b100), isap=supports_c)
+    for inst,           f2 in [
+            (base.isub, 0b00),
+            (base.bxor, 0b01),
+            (base.bor,  0b10),
+            (base.band, 0b11)
+            ]:
+        cpu.enc(inst.bind(ty), CA, CARITH(0b100011, f2), isap=supports_c)
+
+    cpu.enc(base.iadd_imm.bind(ty), CI, RVC(0b01, 0b000), isap=supports_c)
+    cpu.enc(base.iconst.bind(ty), CIz, RVC(0b01, 0b010), isap=supports_c)
+    cpu.enc(base.band_imm.bind(ty), CB, RVC(0b01, 0b100, 0b10 << 10),
+            instp=IsSignedInt(BinaryImm.imm, 6), isap=supports_c)
+    cpu.enc(base.ishl_imm.bind(ty), CIshamt, RVC(0b10, 0b000),
+            instp=IsUnsignedInt(BinaryImm.imm, shamt), isap=supports_c)
+    for inst,               f2 in [
+            (base.ushr_imm, 0b00),
+            (base.sshr_imm, 0b01)
+            ]:
+        cpu.enc(inst.bind(ty), CB, RVC(0b01, 0b100, f2 << 10),
+                instp=IsUnsignedInt(BinaryImm.imm, shamt), isap=supports_c)
+
+    # The word loads and stores have a 5-bit offset scaled by 4.
+    cpu.enc(base.load.bind(i32, ty), CL, RVC(0b00, 0b010),
+            instp=IsUnsignedInt(Load.offset, 7, 2), isap=supports_c)
+    cpu.enc(base.store.bind(i32, ty), CS, RVC(0b00, 0b110),
+            instp=IsUnsignedInt(Store.offset, 7, 2), isap=supports_c)
+
+    cpu.enc(base.adjust_sp_imm, CIadjsp, RVC(0b01, 0b011), isap=supports_c)
+    cpu.enc(base.jump, CJ, RVC(0b01, 0b101), isap=supports_c)
+    cpu.enc(base.brz.bind(ty), CBz, RVC(0b01, 0b110), isap=supports_c)
+    cpu.enc(base.brnz.bind(ty), CBz, RVC(0b01, 0b111), isap=supports_c)
+    cpu.enc(base.x_return, CJr, RVC(0b10, 0b100), isap=supports_c)
+
+# The 32-bit operations in RV64 have their own `c.addw`, `c.subw`, and
+# `c.addiw` instructions. The doubleword loads and stores have a 5-bit offset
+# scaled by 8.
+RV64.enc(base.iadd.i32, CA, CARITH(0b100111, 0b01), isap=supports_c)
+RV64.enc(base.isub.i32, CA, CARITH(0b100111, 0b00), isap=supports_c)
+RV64.enc(base.iadd_imm.i32, CI, RVC(0b01, 0b001), isap=supports_c)
+RV64.enc(base.iconst.i32, CIz, RVC(0b01, 0b010), isap=supports_c)
+RV64.enc(base.copy.i32, CRmv, RVC(0b10, 0b100), isap=supports_c)
+RV64.enc(base.brz.i32, CBz, RVC(0b01, 0b110), isap=supports_c)
+RV64.enc(base.brnz.i32, CBz, RVC(0b01, 0b111), isap=supports_c)
+RV64.enc(base.load.i64.i64, CL, RVC(0b00, 0b011),
+         instp=IsUnsignedInt(Load.offset, 8, 3), isap=supports_c)
+RV64.enc(base.store.i64.i64, CS, RVC(0b00, 0b111),
+         instp=IsUnsignedInt(Store.offset, 8, 3), isap=supports_c)
 
 # Basic arithmetic binary instructions are encoded in an R-type instruction.
 for inst,           inst_imm,      f3,    f7 in [
@@ -41,14 +127,353 @@ for inst,           inst_imm,      f3,    f7 in [
     # Allow i32 shift amounts in 64-bit shifts.
     RV64.enc(inst.i64.i32, R, OP(f3, f7))
     RV64.enc(inst.i32.i64, R, OP32(f3, f7))
+    # Small shift amounts are produced when `i8` and `i16` shifts are widened.
+    # Only the low bits of the shift amount register are used.
+    for amt in [i8, i16]:
+        RV32.enc(inst.i32.bind(amt), R, OP(f3, f7))
+        RV64.enc(inst.i64.bind(amt), R, OP(f3, f7))
+        RV64.enc(inst.i32.bind(amt), R, OP32(f3, f7))
 
     # Immediate shifts.
     RV32.enc(inst_imm.i32, Rshamt, OPIMM(f3, f7))
     RV64.enc(inst_imm.i64, Rshamt, OPIMM(f3, f7))
     RV64.enc(inst_imm.i32, Rshamt, OPIMM32(f3, f7))
 
+# Integer constants with the low 12 bits clear are materialized with `lui`,
+# and small constants with `addi rd, x0, imm`. Other constants are split
+# into smaller pieces by the legalizer.
+RV32.enc(base.iconst.i32, Iz, OPIMM(0b000))
+RV64.enc(base.iconst.i32, Iz, OPIMM(0b000))
+RV64.enc(base.iconst.i64, Iz, OPIMM(0b000))
+RV32.enc(base.iconst.i32, U, LUI())
+RV64.enc(base.iconst.i32, U, LUI())
+RV64.enc(base.iconst.i64, U, LUI())
+
+# Integer comparisons. The `slt` and `sltu` instructions compute `<` directly,
+# and `>=` is computed by inverting the result with `xori`. Equality is tested
+# by comparing `x ^ y` to zero. The remaining condition codes are legalized
+# by swapping the operands.
+for cond,       recipe,   bits in [
+        (intcc.slt, Ricmp,    OP(0b010, 0b0000000)),
+        (intcc.ult, Ricmp,    OP(0b011, 0b0000000)),
+        (intcc.sge, Ricmpinv, OP(0b010, 0b0000000)),
+        (intcc.uge, Ricmpinv, OP(0b011, 0b0000000)),
+        (intcc.eq,  Ricmpeq,  OPIMM(0b011)),
+        (intcc.ne,  Ricmpne,  OP(0b011, 0b0000000))
+        ]:
+    instp = IsEqual(IntCompare.cond, cond)
+    RV32.enc(base.icmp.i32, recipe, bits, instp=instp)
+    RV64.enc(base.icmp.i64, recipe, bits, instp=instp)
+    # The 32-bit values are sign-extended in RV64 registers, so the 64-bit
+    # comparisons work for them too.
+    RV64.enc(base.icmp.i32, recipe, bits, instp=instp)
+
 # "M" Standard Extension for Integer Multiplication and Division.
 # Gated by the `use_m` flag.
 RV32.enc(base.imul.i32, R, OP(0b000, 0b0000001), isap=use_m)
 RV64.enc(base.imul.i64, R, OP(0b000, 0b0000001), isap=use_m)
 RV64.enc(base.imul.i32, R, OP32(0b000, 0b0000001), isap=use_m)
+
+# Division and remainder check for the trapping conditions before the
+# division instruction, which produces a result instead of trapping.
+RV32.enc(base.sdiv.i32, Rsdiv, OP(0b100, 0b0000001), isap=use_m)
+RV64.enc(base.sdiv.i64, Rsdiv64, OP(0b100, 0b0000001), isap=use_m)
+RV64.enc(base.sdiv.i32, Rsdiv, OP32(0b100, 0b0000001), isap=use_m)
+for inst,     f3 in [
+        (base.udiv, 0b101),
+        (base.srem, 0b110),
+        (base.urem, 0b111),
+        ]:
+    RV32.enc(inst.i32, Rdiv, OP(f3, 0b0000001), isap=use_m)
+    RV64.enc(inst.i64, Rdiv, OP(f3, 0b0000001), isap=use_m)
+    RV64.enc(inst.i32, Rdiv, OP32(f3, 0b0000001), isap=use_m)
+
+# Integer conversions. The small integer types only appear as the inputs and
+# outputs of these instructions. All other operations on `i8` and `i16` values
+# are widened.
+RV32.legalize_type(i8=widen, i16=widen)
+RV64.legalize_type(i8=widen, i16=widen)
+
+for inst in [base.ireduce.i8, base.ireduce.i16]:
+    RV32.enc(inst.i32, Icopy, OPIMM(0b000))
+    RV64.enc(inst.i32, Icopy, OPIMM(0b000))
+    RV64.enc(inst.i64, Icopy, OPIMM(0b000))
+RV64.enc(base.ireduce.i32.i64, Icopy, OPIMM(0b000))
+
+# Register copies.
+RV32.enc(base.copy.i32, Icopy, OPIMM(0b000))
+RV64.enc(base.copy.i32, Icopy, OPIMM(0b000))
+RV64.enc(base.copy.i64, Icopy, OPIMM(0b000))
+
+for inst,           f7 in [
+        (base.uextend, 0b0000000),
+        (base.sextend, 0b0100000)
+        ]:
+    RV32.enc(inst.i32.i8, Iext, OPIMM(0b101, f7))
+    RV32.enc(inst.i32.i16, Iext, OPIMM(0b101, f7))
+    # The 32-bit shifts sign-extend their result, as required for `i32`
+    # values in RV64 registers.
+    RV64.enc(inst.i32.i8, Iext, OPIMM32(0b101, f7))
+    RV64.enc(inst.i32.i16, Iext, OPIMM32(0b101, f7))
+    RV64.enc(inst.i64.i8, Iext, OPIMM(0b101, f7))
+    RV64.enc(inst.i64.i16, Iext, OPIMM(0b101, f7))
+    RV64.enc(inst.i64.i32, Iext, OPIMM(0b101, f7))
+
+# Loads and stores. The funct3 field encodes the access size and signedness.
+# The address operand has the native pointer type.
+RV32.enc(base.load.i32.i32, Iload, LOAD(0b010))
+RV32.enc(base.store.i32.i32, S, STORE(0b010))
+RV64.enc(base.load.i32.i64, Iload, LOAD(0b010))
+RV64.enc(base.store.i32.i64, S, STORE(0b010))
+RV64.enc(base.load.i64.i64, Iload, LOAD(0b011))
+RV64.enc(base.store.i64.i64, S, STORE(0b011))
+
+for inst,           f3 in [
+        (base.sload8,   0b000),
+        (base.sload16,  0b001),
+        (base.uload8,   0b100),
+        (base.uload16,  0b101)
+        ]:
+    RV32.enc(inst.i32.i32, Iload, LOAD(f3))
+    RV64.enc(inst.i32.i64, Iload, LOAD(f3))
+    RV64.enc(inst.i64.i64, Iload, LOAD(f3))
+RV64.enc(base.sload32.i64.i64, Iload, LOAD(0b010))
+RV64.enc(base.uload32.i64.i64, Iload, LOAD(0b110))
+
+for inst,           f3 in [
+        (base.istore8,  0b000),
+        (base.istore16, 0b001)
+        ]:
+    RV32.enc(inst.i32.i32, S, STORE(f3))
+    RV64.enc(inst.i32.i64, S, STORE(f3))
+    RV64.enc(inst.i64.i64, S, STORE(f3))
+RV64.enc(base.istore32.i64.i64, S, STORE(0b010))
+
+# Spills and fills use stack pointer relative loads and stores.
+RV32.enc(base.spill.i32, GPsp, STORE(0b010))
+RV64.enc(base.spill.i32, GPsp, STORE(0b010))
+RV64.enc(base.spill.i64, GPsp, STORE(0b011))
+RV32.enc(base.fill.i32, GPfi, LOAD(0b010))
+RV64.enc(base.fill.i32, GPfi, LOAD(0b010))
+RV64.enc(base.fill.i64, GPfi, LOAD(0b011))
+
+# The stack frame is allocated and deallocated by adjusting the stack pointer.
+RV32.enc(base.adjust_sp_imm, Iadjsp, OPIMM(0b000))
+RV64.enc(base.adjust_sp_imm, Iadjsp, OPIMM(0b000))
+RV32.enc(base.adjust_sp_imm, Radjsp, OP(0b000, 0b0000000))
+RV64.enc(base.adjust_sp_imm, Radjsp, OP(0b000, 0b0000000))
+
+# Control flow. Branch relaxation picks the short `SBzero` encoding when the
+# destination is in range, and falls back to `SBzeroLong` otherwise.
+RV32.enc(base.jump, UJ, JAL())
+RV64.enc(base.jump, UJ, JAL())
+for inst,           f3 in [
+        (base.brz,  0b000),
+        (base.brnz, 0b001)
+        ]:
+    for recipe in [SBzero, SBzeroLong]:
+        RV32.enc(inst.i32, recipe, BRANCH(f3))
+        RV64.enc(inst.i64, recipe, BRANCH(f3))
+        RV64.enc(inst.i32, recipe, BRANCH(f3))
+
+# Compare and branch. The SB-type branches implement `==`, `!=`, `<`, and `>=`
+# for signed and unsigned operands.
+for cond,       f3 in [
+        (intcc.eq,  0b000),
+        (intcc.ne,  0b001),
+        (intcc.slt, 0b100),
+        (intcc.sge, 0b101),
+        (intcc.ult, 0b110),
+        (intcc.uge, 0b111)
+        ]:
+    instp = IsEqual(BranchIcmp.cond, cond)
+    for recipe in [SB, SBlong]:
+        RV32.enc(base.br_icmp.i32, recipe, BRANCH(f3), instp=instp)
+        RV64.enc(base.br_icmp.i64, recipe, BRANCH(f3), instp=instp)
+        RV64.enc(base.br_icmp.i32, recipe, BRANCH(f3), instp=instp)
+
+RV32.enc(base.x_return, Iret, JALR())
+RV64.enc(base.x_return, Iret, JALR())
+
+# Traps execute an illegal instruction. The conditional traps skip over it
+# with a `bne` or `beq` branch. Comparisons produce `b1` values that are 0 or
+# 1, so they can be tested like integers.
+RV32.enc(base.trap, Itrap, 0)
+RV64.enc(base.trap, Itrap, 0)
+for inst,         f3 in [
+        (base.trapz,  0b001),
+        (base.trapnz, 0b000)
+        ]:
+    RV32.enc(inst.i32, SBtrap, BRANCH(f3))
+    RV32.enc(inst.b1, SBtrap, BRANCH(f3))
+    RV64.enc(inst.i64, SBtrap, BRANCH(f3))
+    RV64.enc(inst.i32, SBtrap, BRANCH(f3))
+    RV64.enc(inst.b1, SBtrap, BRANCH(f3))
+
+# Direct calls and symbol addresses need relocations.
+RV32.enc(base.call, Ucall, JALR())
+RV64.enc(base.call, Ucall, JALR())
+RV32.enc(base.globalsym_addr.i32, Ugsym, OPIMM(0b000))
+RV64.enc(base.globalsym_addr.i64, Ugsym64, LOAD(0b011))
+
+# Boolean operations on `b1` values, like the results of comparisons.
+for inst,           f3 in [
+        (base.bxor, 0b100),
+        (base.bor,  0b110),
+        (base.band, 0b111)
+        ]:
+    RV32.enc(inst.b1, R, OP(f3, 0b0000000))
+    RV64.enc(inst.b1, R, OP(f3, 0b0000000))
+RV32.enc(base.bnot.b1, Ibnot, OPIMM(0b100))
+RV64.enc(base.bnot.b1, Ibnot, OPIMM(0b100))
+
+# "F" and "D" Standard Extensions for floating point. Gated by the `use_f` and
+# `use_d` flags. The low bits of funct7 are the `fmt` field which selects
+# single or double precision. Arithmetic uses the dynamic rounding mode.
+for ty,  fmt,  isap, width in [
+        (f32, 0b00, use_f, 0b010),
+        (f64, 0b01, use_d, 0b011)
+        ]:
+    for cpu, ptr in [(RV32, i32), (RV64, i64)]:
+        for inst,                f3rm,  f5 in [
+                (base.fadd,      0b111, 0b00000),
+                (base.fsub,      0b111, 0b00001),
+                (base.fmul,      0b111, 0b00010),
+                (base.fdiv,      0b111, 0b00011),
+                (base.fcopysign, 0b000, 0b00100),
+                (base.fminnum,   0b000, 0b00101),
+                (base.fmaxnum,   0b001, 0b00101)
+                ]:
+            cpu.enc(inst.bind(ty), Rf, OPFP(f3rm, (f5 << 2) | fmt), isap=isap)
+
+        # Sign injection with two copies of the same register.
+        for inst,            f3rm in [
+                (base.copy,  0b000),
+                (base.fneg,  0b001),
+                (base.fabs,  0b010)
+                ]:
+            cpu.enc(inst.bind(ty), Rfsgnj, OPFP(f3rm, (0b00100 << 2) | fmt),
+                    isap=isap)
+
+        cpu.enc(base.sqrt.bind(ty), Rff, FCVT((0b01011 << 2) | fmt, 0),
+                isap=isap)
+        cpu.enc(base.fma.bind(ty), R4, MADD(fmt), isap=isap)
+
+        # The remaining condition codes are expanded by the legalizer.
+        for cond,         f3cmp in [
+                (floatcc.eq, 0b010),
+                (floatcc.lt, 0b001),
+                (floatcc.le, 0b000)
+                ]:
+            cpu.enc(base.fcmp.bind(ty), Rfcmp,
+                    OPFP(f3cmp, (0b10100 << 2) | fmt),
+                    instp=IsEqual(FloatCompare.cond, cond), isap=isap)
+
+        # The funct3 field of loads and stores encodes the access width.
+        cpu.enc(base.load.bind(ty, ptr), Fload, LOADFP(width), isap=isap)
+        cpu.enc(base.store.bind(ty, ptr), Fstore, STOREFP(width), isap=isap)
+        cpu.enc(base.spill.bind(ty), FPsp, STOREFP(width), isap=isap)
+        cpu.enc(base.fill.bind(ty), FPfi, LOADFP(width), isap=isap)
+
+    # Conversions to and from integers. The `rs2` field selects the integer
+    # type. Conversions to integers round towards zero. Note that the RISC-V
+    # instructions saturate instead of trapping when the result is out of
+    # range.
+    for cpu,  ity, rs2 in [
+            (RV32, i32, 0b00),
+            (RV64, i32, 0b00),
+            (RV64, i64, 0b10)
+            ]:
+        for inst,                 unsigned in [
+                (base.fcvt_to_sint, 0),
+                (base.fcvt_to_uint, 1)
+                ]:
+            cpu.enc(inst.bind(ity, ty), Rfi,
+                    FCVT((0b11000 << 2) | fmt, rs2 | unsigned, 0b001),
+                    isap=isap)
+        for inst,                   unsigned in [
+                (base.fcvt_from_sint, 0),
+                (base.fcvt_from_uint, 1)
+                ]:
+            cpu.enc(inst.bind(ty, ity), Rif,
+                    FCVT((0b11010 << 2) | fmt, rs2 | unsigned),
+                    isap=isap)
+
+# Conversions between single and double precision.
+RV32.enc(base.fpromote.f64.f32, Rff, FCVT(0b0100001, 0b00000), isap=use_d)
+RV64.enc(base.fpromote.f64.f32, Rff, FCVT(0b0100001, 0b00000), isap=use_d)
+RV32.enc(base.fdemote.f32.f64, Rff, FCVT(0b0100000, 0b00001), isap=use_d)
+RV64.enc(base.fdemote.f32.f64, Rff, FCVT(0b0100000, 0b00001), isap=use_d)
+
+# Moves between integer and floating point registers with `fmv.x.w` and
+# `fmv.w.x`. The 64-bit moves are only available on RV64.
+for cpu in [RV32, RV64]:
+    cpu.enc(base.bitcast.i32.f32, Rfi, FCVT(0b1110000, 0, 0b000), isap=use_f)
+    cpu.enc(base.bitcast.f32.i32, Rif, FCVT(0b1111000, 0, 0b000), isap=use_f)
+RV64.enc(base.bitcast.i64.f64, Rfi, FCVT(0b1110001, 0, 0b000), isap=use_d)
+RV64.enc(base.bitcast.f64.i64, Rif, FCVT(0b1111001, 0, 0b000), isap=use_d)
+
+# Atomic memory operations from the A extension. The `amo*` and `lr`/`sc`
+# instructions only exist for 32-bit and 64-bit accesses, so narrower atomic
+# read-modify-write operations become library calls.
+#
+# Aligned loads and stores are always atomic, and `fence` is part of the base
+# ISA, but they must agree with the library calls used for read-modify-write
+# operations when the A extension isn't available. All the atomic encodings
+# are therefore predicated on `use_a`.
+for cpu,  ty,  ptr, f3 in [
+        (RV32, i32, i32, 0b010),
+        (RV64, i32, i64, 0b010),
+        (RV64, i64, i64, 0b011)
+        ]:
+    # The `and` and `or` enumerators are Python keywords, so look them up by
+    # name.
+    for op,     f5 in [
+            ('add',  0b00000),
+            ('xchg', 0b00001),
+            ('xor',  0b00100),
+            ('or',   0b01000),
+            ('and',  0b01100),
+            ('smin', 0b10000),
+            ('smax', 0b10100),
+            ('umin', 0b11000),
+            ('umax', 0b11100)
+            ]:
+        cpu.enc(base.atomic_rmw.bind(ty, ptr), Ramo, AMO(f3, f5),
+                instp=IsEqual(AtomicRmw.op, getattr(atomicop, op)),
+                isap=use_a)
+    cpu.enc(base.atomic_cas.bind(ty, ptr), Rcas, AMO(f3, 0b00010),
+            isap=use_a)
+
+for cpu,  ty,  ptr, f3 in [
+        (RV32, i8,  i32, 0b000),
+        (RV32, i16, i32, 0b001),
+        (RV32, i32, i32, 0b010),
+        (RV64, i8,  i64, 0b000),
+        (RV64, i16, i64, 0b001),
+        (RV64, i32, i64, 0b010),
+        (RV64, i64, i64, 0b011)
+        ]:
+    for recipe,     order in [
+            (Iaload,    ordering.relaxed),
+            (Ialoadacq, ordering.acquire),
+            (Ialoadsc,  ordering.seq_cst)
+            ]:
+        cpu.enc(base.atomic_load.bind(ty, ptr), recipe, LOAD(f3),
+                instp=IsEqual(AtomicLoad.ordering, order), isap=use_a)
+    for recipe,      order in [
+            (Sastore,    ordering.relaxed),
+            (Sastorerel, ordering.release),
+            (Sastorerel, ordering.seq_cst)
+            ]:
+        cpu.enc(base.atomic_store.bind(ty, ptr), recipe, STORE(f3),
+                instp=IsEqual(AtomicStore.ordering, order), isap=use_a)
+
+RV32.enc(base.fence, Ifence, MISCMEM(0b000), isap=use_a)
+RV64.enc(base.fence, Ifence, MISCMEM(0b000), isap=use_a)
+
+# Floating point values are always supported. Without the F and D extensions,
+# or when `enable_float` is off, floating point operations have no encodings,
+# and they are expanded into soft-float library calls.
+RV32.legalize_type(f32=expand, f64=expand)
+RV64.legalize_type(f32=expand, f64=expand)
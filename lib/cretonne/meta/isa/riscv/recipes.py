@@ -10,8 +10,16 @@ instruction formats described in the reference:
 """
 from __future__ import absolute_import
 from cretonne import EncRecipe
-from cretonne.formats import Binary, BinaryImm
-from cretonne.predicates import IsSignedInt
+from cretonne.formats import Nullary, Unary, UnaryImm, Binary, BinaryImm
+from cretonne.formats import Ternary
+from cretonne.formats import Load, Store, IntCompare, FloatCompare
+from cretonne.formats import UnaryGlobalVar, Call, Jump, Branch
+from cretonne.formats import BranchIcmp, Return
+from cretonne.formats import AtomicRmw, AtomicCas, AtomicLoad, AtomicStore
+from cretonne.formats import Fence
+from cretonne.predicates import IsSignedInt, And, Not
+from cretonne.registers import Stack, EarlyClobber
+from .registers import GPR, GPRC, FPR
 
 # The low 7 bits of a RISC-V instruction is the base opcode. All 32-bit
 # instructions have 11 as the two low bits, with bits 6:2 determining the base
@@ -51,6 +59,22 @@ def OPIMM32(funct3, funct7=0):
     return 0b00110 | (funct3 << 5) | (funct7 << 8)
 
 
+def JALR(funct3=0):
+    # type: (int) -> int
+    assert funct3 <= 0b111
+    return 0b11001 | (funct3 << 5)
+
+
+def JAL():
+    # type: () -> int
+    return 0b11011
+
+
+def LUI():
+    # type: () -> int
+    return 0b01101
+
+
 def OP(funct3, funct7):
     # type: (int, int) -> int
     assert funct3 <= 0b111
@@ -65,11 +89,592 @@ def OP32(funct3, funct7):
     return 0b01110 | (funct3 << 5) | (funct7 << 8)
 
 
+def LOADFP(funct3):
+    # type: (int) -> int
+    assert funct3 <= 0b111
+    return 0b00001 | (funct3 << 5)
+
+
+def STOREFP(funct3):
+    # type: (int) -> int
+    assert funct3 <= 0b111
+    return 0b01001 | (funct3 << 5)
+
+
+def OPFP(funct3, funct7):
+    # type: (int, int) -> int
+    assert funct3 <= 0b111
+    assert funct7 <= 0b1111111
+    return 0b10100 | (funct3 << 5) | (funct7 << 8)
+
+
+def MADD(fmt):
+    # type: (int) -> int
+    """
+    Fused multiply-add with the dynamic rounding mode in funct3. The `fmt`
+    field selects the precision.
+    """
+    assert fmt <= 0b11
+    return 0b10000 | (0b111 << 5) | (fmt << 8)
+
+
+def MISCMEM(funct3):
+    # type: (int) -> int
+    assert funct3 <= 0b111
+    return 0b00011 | (funct3 << 5)
+
+
+def AMO(funct3, funct5):
+    # type: (int, int) -> int
+    """
+    Atomic memory operations. The `aq` and `rl` bits at the bottom of funct7
+    are left clear in the encbits. They are set from the memory ordering of
+    the instruction when it is emitted.
+    """
+    assert funct3 <= 0b111
+    assert funct5 <= 0b11111
+    return 0b01011 | (funct3 << 5) | (funct5 << 10)
+
+
+def FCVT(funct7, rs2, rm=0b111):
+    # type: (int, int, int) -> int
+    """
+    Unary OP-FP instructions with a fixed `rs2` field that selects the
+    operation. The OP-FP opcode is implied, so its place in the encbits is
+    used for `rs2` instead: `rs2 | (rm << 5) | (funct7 << 8)`.
+
+    The rounding mode defaults to the dynamic rounding mode.
+    """
+    assert rs2 <= 0b11111
+    assert rm <= 0b111
+    assert funct7 <= 0b1111111
+    return rs2 | (rm << 5) | (funct7 << 8)
+
+
+# Compressed 16-bit instructions from the C extension. The two low bits select
+# one of three quadrants, and bits 15:13 are the funct3 field.
+#
+# Encbits for the compressed recipes are the whole 16-bit instruction with the
+# register and immediate fields cleared.
+
+
+def RVC(quadrant, funct3, funct=0):
+    # type: (int, int, int) -> int
+    """
+    Compressed instructions. The `funct` argument contains any other fixed
+    bits of the instruction, in place.
+    """
+    assert quadrant <= 0b10
+    assert funct3 <= 0b111
+    assert funct & ~0x1ffc == 0
+    return quadrant | (funct3 << 13) | funct
+
+
+def CARITH(funct6, funct2):
+    # type: (int, int) -> int
+    """
+    CA-type register-register arithmetic like `c.sub rd', rs2'`. The funct6
+    field is in bits 15:10, and funct2 is in bits 6:5.
+    """
+    assert funct6 <= 0b111111
+    assert funct2 <= 0b11
+    return 0b01 | (funct6 << 10) | (funct2 << 5)
+
+
 # R-type 32-bit instructions: These are mostly binary arithmetic instructions.
 # The encbits are `opcode[6:2] | (funct3 << 5) | (funct7 << 8)
-R = EncRecipe('R', Binary)
+R = EncRecipe(
+        'R', Binary, size=4, ins=(GPR, GPR), outs=GPR,
+        emit='put_r(bits, in_reg0, in_reg1, out_reg0, sink);')
 
 # R-type with an immediate shift amount instead of rs2.
-Rshamt = EncRecipe('Rshamt', BinaryImm)
+Rshamt = EncRecipe(
+        'Rshamt', BinaryImm, size=4, ins=GPR, outs=GPR,
+        emit='put_rshamt(bits, in_reg0, imm.into(), out_reg0, sink);')
+
+I = EncRecipe(
+        'I', BinaryImm, size=4, ins=GPR, outs=GPR,
+        instp=IsSignedInt(BinaryImm.imm, 12),
+        emit='put_i(bits, in_reg0, imm.into(), out_reg0, sink);')
+
+# Integer comparisons. The encbits select `slt` or `sltu`.
+Ricmp = EncRecipe(
+        'Ricmp', IntCompare, size=4, ins=(GPR, GPR), outs=GPR,
+        emit='put_r(bits, in_reg0, in_reg1, out_reg0, sink);')
+
+# Inverted integer comparison, `slt rd, rs1, rs2` followed by
+# `xori rd, rd, 1`. The encbits describe the `slt` or `sltu` instruction.
+Ricmpinv = EncRecipe(
+        'Ricmpinv', IntCompare, size=8, ins=(GPR, GPR), outs=GPR,
+        emit='''
+        put_r(bits, in_reg0, in_reg1, out_reg0, sink);
+        put_i(XORI, out_reg0, 1, out_reg0, sink);
+        ''')
+
+# Integer equality, `xor rd, rs1, rs2` followed by `sltiu rd, rd, 1`.
+# The encbits describe the `sltiu` instruction.
+Ricmpeq = EncRecipe(
+        'Ricmpeq', IntCompare, size=8, ins=(GPR, GPR), outs=GPR,
+        emit='''
+        put_r(XOR, in_reg0, in_reg1, out_reg0, sink);
+        put_i(bits, out_reg0, 1, out_reg0, sink);
+        ''')
+
+# Integer inequality, `xor rd, rs1, rs2` followed by `sltu rd, x0, rd`.
+# The encbits describe the `sltu` instruction.
+Ricmpne = EncRecipe(
+        'Ricmpne', IntCompare, size=8, ins=(GPR, GPR), outs=GPR,
+        emit='''
+        put_r(XOR, in_reg0, in_reg1, out_reg0, sink);
+        put_r(bits, 0, out_reg0, out_reg0, sink);
+        ''')
+
+# Small constants, `addi rd, x0, imm`.
+Iz = EncRecipe(
+        'Iz', UnaryImm, size=4, ins=(), outs=GPR,
+        instp=IsSignedInt(UnaryImm.imm, 12),
+        emit='put_i(bits, 0, imm.into(), out_reg0, sink);')
+
+# U-type instructions have a 20-bit immediate that targets bits 12-31.
+# The constant is sign-extended to 64 bits on RV64.
+U = EncRecipe(
+        'U', UnaryImm, size=4, ins=(), outs=GPR,
+        instp=IsSignedInt(UnaryImm.imm, 32, 12),
+        emit='put_u(bits as u32, imm.into(), out_reg0, sink);')
+
+# Stack pointer adjustment, `addi sp, sp, imm`.
+Iadjsp = EncRecipe(
+        'Iadjsp', UnaryImm, size=4, ins=(), outs=(),
+        instp=IsSignedInt(UnaryImm.imm, 12),
+        emit='put_i(bits, STACK_POINTER, imm.into(), STACK_POINTER, sink);')
+
+# Larger stack pointer adjustments, `lui t0, %hi(imm)` and `addi t0, t0,
+# %lo(imm)` followed by `add sp, sp, t0`. The encbits describe the `add`
+# instruction. The range is limited so the `lui` immediate can't overflow.
+Radjsp = EncRecipe(
+        'Radjsp', UnaryImm, size=12, ins=(), outs=(),
+        instp=IsSignedInt(UnaryImm.imm, 31),
+        emit='''
+        let imm: i64 = imm.into();
+        let lo = (imm << 52) >> 52;
+        put_u(LUI, imm - lo, FRAME_SCRATCH, sink);
+        put_i(ADDI, FRAME_SCRATCH, lo, FRAME_SCRATCH, sink);
+        put_r(bits, STACK_POINTER, FRAME_SCRATCH, STACK_POINTER, sink);
+        ''')
+
+# Register copy, `addi rd, rs, 0`.
+# Used for integer truncation which doesn't need to change any bits since the
+# high bits of narrow integer types are ignored.
+Icopy = EncRecipe(
+        'Icopy', Unary, size=4, ins=GPR, outs=GPR,
+        emit='put_i(bits, in_reg0, 0, out_reg0, sink);')
+
+# Integer extension with a pair of immediate shifts: `slli rd, rs, n` followed
+# by a right shift `srli rd, rd, n` or `srai rd, rd, n`, where `n` is computed
+# from the input and output types. The encbits describe the right shift.
+Iext = EncRecipe(
+        'Iext', Unary, size=8, ins=GPR, outs=GPR,
+        emit='''
+        let shift = ty.bits() - func.dfg.value_type(arg).bits();
+        put_iext(bits, in_reg0, shift as i64, out_reg0, sink);
+        ''')
+
+# I-type load from memory, `lw rd, offset(rs1)`.
+Iload = EncRecipe(
+        'Iload', Load, size=4, ins=GPR, outs=GPR,
+        instp=IsSignedInt(Load.offset, 12),
+        emit='put_i(bits, in_reg0, offset.into(), out_reg0, sink);')
+
+# S-type store to memory, `sw rs2, offset(rs1)`.
+S = EncRecipe(
+        'S', Store, size=4, ins=(GPR, GPR), outs=(),
+        instp=IsSignedInt(Store.offset, 12),
+        emit='put_s(bits, in_reg1, in_reg0, offset.into(), sink);')
+
+# Spill a register to a spill slot, `sw rs2, offset(sp)`.
+# The stack slot offset is relative to the stack pointer on function entry.
+GPsp = EncRecipe(
+        'GPsp', Unary, size=4, ins=GPR, outs=Stack(GPR),
+        emit='''
+        let offset = stack_slot_offset(func, out_ss0);
+        put_s(bits, STACK_POINTER, in_reg0, offset, sink);
+        ''')
+
+# Fill a register from a spill slot, `lw rd, offset(sp)`.
+GPfi = EncRecipe(
+        'GPfi', Unary, size=4, ins=Stack(GPR), outs=GPR,
+        emit='''
+        let offset = stack_slot_offset(func, in_ss0);
+        put_i(bits, STACK_POINTER, offset, out_reg0, sink);
+        ''')
+
+# Boolean negation, `xori rd, rs, 1`. The `b1` values are represented as 0 or
+# 1 in integer registers.
+Ibnot = EncRecipe(
+        'Ibnot', Unary, size=4, ins=GPR, outs=GPR,
+        emit='put_i(bits, in_reg0, 1, out_reg0, sink);')
+
+# Floating point R-type instructions, `fadd.s rd, rs1, rs2` and friends.
+Rf = EncRecipe(
+        'Rf', Binary, size=4, ins=(FPR, FPR), outs=FPR,
+        emit='put_r(bits, in_reg0, in_reg1, out_reg0, sink);')
+
+# Sign injection with the same register as both operands. This implements
+# register copies, `fneg`, and `fabs` as `fsgnj.s rd, rs, rs`,
+# `fsgnjn.s rd, rs, rs`, and `fsgnjx.s rd, rs, rs`.
+Rfsgnj = EncRecipe(
+        'Rfsgnj', Unary, size=4, ins=FPR, outs=FPR,
+        emit='put_r(bits, in_reg0, in_reg0, out_reg0, sink);')
+
+# Floating point comparison with an integer result, `feq.s rd, rs1, rs2`.
+Rfcmp = EncRecipe(
+        'Rfcmp', FloatCompare, size=4, ins=(FPR, FPR), outs=GPR,
+        emit='put_r(bits, in_reg0, in_reg1, out_reg0, sink);')
+
+# R4-type fused multiply-add, `fmadd.s rd, rs1, rs2, rs3`.
+R4 = EncRecipe(
+        'R4', Ternary, size=4, ins=(FPR, FPR, FPR), outs=FPR,
+        emit='put_r4(bits, in_reg0, in_reg1, in_reg2, out_reg0, sink);')
+
+# Unary OP-FP instructions with a fixed `rs2` field, like `fsqrt.s rd, rs1`
+# and the conversions `fcvt.*`. There is a recipe for each combination of
+# register classes. The encbits are computed by `FCVT()`.
+Rff = EncRecipe(
+        'Rff', Unary, size=4, ins=FPR, outs=FPR,
+        emit='put_fcvt(bits, in_reg0, out_reg0, sink);')
+
+Rfi = EncRecipe(
+        'Rfi', Unary, size=4, ins=FPR, outs=GPR,
+        emit='put_fcvt(bits, in_reg0, out_reg0, sink);')
+
+Rif = EncRecipe(
+        'Rif', Unary, size=4, ins=GPR, outs=FPR,
+        emit='put_fcvt(bits, in_reg0, out_reg0, sink);')
+
+# Floating point load from memory, `flw rd, offset(rs1)`.
+Fload = EncRecipe(
+        'Fload', Load, size=4, ins=GPR, outs=FPR,
+        instp=IsSignedInt(Load.offset, 12),
+        emit='put_i(bits, in_reg0, offset.into(), out_reg0, sink);')
+
+# Floating point store to memory, `fsw rs2, offset(rs1)`.
+Fstore = EncRecipe(
+        'Fstore', Store, size=4, ins=(FPR, GPR), outs=(),
+        instp=IsSignedInt(Store.offset, 12),
+        emit='put_s(bits, in_reg1, in_reg0, offset.into(), sink);')
+
+# Spill a floating point register, `fsw rs2, offset(sp)`.
+FPsp = EncRecipe(
+        'FPsp', Unary, size=4, ins=FPR, outs=Stack(FPR),
+        emit='''
+        let offset = stack_slot_offset(func, out_ss0);
+        put_s(bits, STACK_POINTER, in_reg0, offset, sink);
+        ''')
+
+# Fill a floating point register, `flw rd, offset(sp)`.
+FPfi = EncRecipe(
+        'FPfi', Unary, size=4, ins=Stack(FPR), outs=FPR,
+        emit='''
+        let offset = stack_slot_offset(func, in_ss0);
+        put_i(bits, STACK_POINTER, offset, out_reg0, sink);
+        ''')
+
+# Atomic read-modify-write, `amoadd.w rd, rs2, (rs1)` and friends.
+# The `aq` and `rl` bits are derived from the memory ordering.
+Ramo = EncRecipe(
+        'Ramo', AtomicRmw, size=4, ins=(GPR, GPR), outs=GPR,
+        emit='''
+        let aq = ordering.is_acquire();
+        let rl = ordering.is_release();
+        put_r(amo_ordering(bits, aq, rl), in_reg0, in_reg1, out_reg0, sink);
+        ''')
+
+# Compare-and-swap loop with `lr.w` and `sc.w`, see `put_cas()`. The result
+# register is written before the operands are read for the last time.
+# The encbits describe the `lr` instruction.
+Rcas = EncRecipe(
+        'Rcas', AtomicCas, size=20, ins=(GPR, GPR, GPR),
+        outs=EarlyClobber(GPR),
+        emit='''
+        put_cas(bits, ordering, in_reg0, in_reg1, in_reg2, out_reg0, sink);
+        ''')
+
+# Atomic loads use a plain load instruction. Acquire semantics are provided
+# by a trailing `fence r, rw`, and sequentially consistent loads also have a
+# leading `fence rw, rw`.
+Iaload = EncRecipe(
+        'Iaload', AtomicLoad, size=4, ins=GPR, outs=GPR,
+        emit='put_i(bits, in_reg0, 0, out_reg0, sink);')
+
+Ialoadacq = EncRecipe(
+        'Ialoadacq', AtomicLoad, size=8, ins=GPR, outs=GPR,
+        emit='''
+        put_i(bits, in_reg0, 0, out_reg0, sink);
+        put_i(FENCE, 0, FENCE_R_RW, 0, sink);
+        ''')
+
+Ialoadsc = EncRecipe(
+        'Ialoadsc', AtomicLoad, size=12, ins=GPR, outs=GPR,
+        emit='''
+        put_i(FENCE, 0, FENCE_RW_RW, 0, sink);
+        put_i(bits, in_reg0, 0, out_reg0, sink);
+        put_i(FENCE, 0, FENCE_R_RW, 0, sink);
+        ''')
+
+# Atomic stores use a plain store instruction, preceded by `fence rw, w` for
+# release semantics.
+Sastore = EncRecipe(
+        'Sastore', AtomicStore, size=4, ins=(GPR, GPR), outs=(),
+        emit='put_s(bits, in_reg1, in_reg0, 0, sink);')
+
+Sastorerel = EncRecipe(
+        'Sastorerel', AtomicStore, size=8, ins=(GPR, GPR), outs=(),
+        emit='''
+        put_i(FENCE, 0, FENCE_RW_W, 0, sink);
+        put_s(bits, in_reg1, in_reg0, 0, sink);
+        ''')
+
+# Memory fence, `fence pred, succ`. The predecessor and successor sets are
+# derived from the memory ordering.
+Ifence = EncRecipe(
+        'Ifence', Fence, size=4, ins=(), outs=(),
+        emit='put_i(bits, 0, fence_sets(ordering), 0, sink);')
+
+# Direct function call, `auipc ra, 0` followed by `jalr ra, 0(ra)`.
+# The `R_RISCV_CALL` relocation on the pair is resolved by the linker.
+# The encbits describe the `jalr` instruction.
+Ucall = EncRecipe(
+        'Ucall', Call, size=8, ins=(), outs=(),
+        emit='''
+        sink.reloc_external(RelocKind::Call.into(),
+                            &func.dfg.ext_funcs[data.func_ref].name);
+        put_u(AUIPC, 0, RETURN_ADDRESS, sink);
+        put_i(bits, RETURN_ADDRESS, 0, RETURN_ADDRESS, sink);
+        ''')
+
+# Absolute address of a symbol on RV32, `lui rd, %hi(sym)` followed by
+# `addi rd, rd, %lo(sym)`. The encbits describe the `addi` instruction.
+Ugsym = EncRecipe(
+        'Ugsym', UnaryGlobalVar, size=8, ins=(), outs=GPR,
+        emit='''
+        let name = globalsym_name(func, global_var);
+        sink.reloc_external(RelocKind::Hi20.into(), name);
+        put_u(LUI, 0, out_reg0, sink);
+        sink.reloc_external(RelocKind::Lo12I.into(), name);
+        put_i(bits, out_reg0, 0, out_reg0, sink);
+        ''')
+
+# Absolute address of a symbol on RV64. The 64-bit address is stored in the
+# instruction stream and loaded PC-relative:
+#
+#     auipc rd, 0
+#     ld rd, 12(rd)
+#     jal x0, 12
+#     .dword sym
+#
+# The encbits describe the `ld` instruction.
+Ugsym64 = EncRecipe(
+        'Ugsym64', UnaryGlobalVar, size=20, ins=(), outs=GPR,
+        emit='''
+        put_u(AUIPC, 0, out_reg0, sink);
+        put_i(bits, out_reg0, 12, out_reg0, sink);
+        put_uj(12, 0, sink);
+        sink.reloc_external(RelocKind::Abs64.into(),
+                            globalsym_name(func, global_var));
+        sink.put8(0);
+        ''')
+
+# Unconditional jump, `jal x0, offset`. The range is +/- 1 MiB.
+UJ = EncRecipe(
+        'UJ', Jump, size=4, ins=(), outs=(), branch_range=(0, 21),
+        emit='''
+        let dest = func.offsets[data.destination] as i64;
+        let disp = dest - sink.offset() as i64;
+        put_uj(disp, 0, sink);
+        ''')
+
+# SB-type branch comparing a register to zero, `beq rs1, x0, offset` or
+# `bne rs1, x0, offset`. The range is +/- 4 KiB.
+SBzero = EncRecipe(
+        'SBzero', Branch, size=4, ins=GPR, outs=(), branch_range=(0, 13),
+        emit='''
+        let dest = func.offsets[data.destination] as i64;
+        let disp = dest - sink.offset() as i64;
+        put_sb(bits, disp, in_reg0, 0, sink);
+        ''')
+
+# Long-range version of `SBzero`: The inverted branch skips over a `jal` to
+# the destination EBB. The range is +/- 1 MiB relative to the `jal`.
+SBzeroLong = EncRecipe(
+        'SBzeroLong', Branch, size=8, ins=GPR, outs=(), branch_range=(4, 21),
+        emit='''
+        put_sb(invert_branch(bits), 8, in_reg0, 0, sink);
+        let dest = func.offsets[data.destination] as i64;
+        let disp = dest - sink.offset() as i64;
+        put_uj(disp, 0, sink);
+        ''')
+
+# SB-type branch comparing two registers, `beq rs1, rs2, offset` and friends.
+# The range is +/- 4 KiB.
+SB = EncRecipe(
+        'SB', BranchIcmp, size=4, ins=(GPR, GPR), outs=(),
+        branch_range=(0, 13),
+        emit='''
+        let dest = func.offsets[data.destination] as i64;
+        let disp = dest - sink.offset() as i64;
+        put_sb(bits, disp, in_reg0, in_reg1, sink);
+        ''')
+
+# Long-range version of `SB`: The inverted branch skips over a `jal` to the
+# destination EBB. The range is +/- 1 MiB relative to the `jal`.
+SBlong = EncRecipe(
+        'SBlong', BranchIcmp, size=8, ins=(GPR, GPR), outs=(),
+        branch_range=(4, 21),
+        emit='''
+        put_sb(invert_branch(bits), 8, in_reg0, in_reg1, sink);
+        let dest = func.offsets[data.destination] as i64;
+        let disp = dest - sink.offset() as i64;
+        put_uj(disp, 0, sink);
+        ''')
+
+# Unconditional trap, an illegal instruction with all bits zero.
+Itrap = EncRecipe(
+        'Itrap', Nullary, size=4, ins=(), outs=(),
+        emit='sink.put4(ILLEGAL_INSTRUCTION);')
+
+# Conditional trap, `beqz rs, 8` or `bnez rs, 8` skipping over an illegal
+# instruction unless the trap condition holds. The encbits describe the
+# skipping branch.
+SBtrap = EncRecipe(
+        'SBtrap', Unary, size=8, ins=GPR, outs=(),
+        emit='''
+        put_sb(bits, 8, in_reg0, 0, sink);
+        sink.put4(ILLEGAL_INSTRUCTION);
+        ''')
+
+# Division and remainder which trap on a zero divisor. The M extension
+# instructions produce a result instead of trapping:
+#
+#     bnez rs2, 1f
+#     <illegal>
+#  1: div rd, rs1, rs2
+#
+# The signed remainder doesn't overflow.
+Rdiv = EncRecipe(
+        'Rdiv', Binary, size=12, ins=(GPR, GPR), outs=GPR,
+        emit='''
+        put_trapz(in_reg1, sink);
+        put_r(bits, in_reg0, in_reg1, out_reg0, sink);
+        ''')
+
+# Signed division which also traps on overflow when `rs1` is the smallest
+# integer and `rs2` is -1. See `put_trap_sdiv_overflow()`. The result
+# register is used as a temporary before the operands are read for the last
+# time, so it must be distinct from them. `Rsdiv` is used for `i32` operands
+# and `Rsdiv64` for `i64` operands.
+Rsdiv = EncRecipe(
+        'Rsdiv', Binary, size=32, ins=(GPR, GPR), outs=EarlyClobber(GPR),
+        emit='''
+        put_trapz(in_reg1, sink);
+        put_trap_sdiv_overflow(in_reg0, in_reg1, 31, out_reg0, sink);
+        put_r(bits, in_reg0, in_reg1, out_reg0, sink);
+        ''')
+
+Rsdiv64 = EncRecipe(
+        'Rsdiv64', Binary, size=32, ins=(GPR, GPR), outs=EarlyClobber(GPR),
+        emit='''
+        put_trapz(in_reg1, sink);
+        put_trap_sdiv_overflow(in_reg0, in_reg1, 63, out_reg0, sink);
+        put_r(bits, in_reg0, in_reg1, out_reg0, sink);
+        ''')
+
+# Return to the caller, `jalr x0, 0(ra)`.
+Iret = EncRecipe(
+        'Iret', Return, size=4, ins=(), outs=(),
+        emit='put_i(bits, RETURN_ADDRESS, 0, 0, sink);')
+
+# Compressed register-register operations overwrite their first operand,
+# `c.add rd, rs2`.
+CR = EncRecipe(
+        'CR', Binary, size=2, ins=(GPR, GPR), outs=0,
+        emit='put_cr(bits, in_reg0, in_reg1, sink);')
+
+# Compressed register copy, `c.mv rd, rs2`.
+CRmv = EncRecipe(
+        'CRmv', Unary, size=2, ins=GPR, outs=GPR,
+        emit='put_cr(bits, out_reg0, in_reg0, sink);')
+
+# CA-type arithmetic on the `x8`-`x15` registers, `c.sub rd', rs2'`.
+CA = EncRecipe(
+        'CA', Binary, size=2, ins=(GPRC, GPRC), outs=0,
+        emit='put_ca(bits, in_reg0, in_reg1, sink);')
+
+# CI-type operations with a 6-bit immediate that overwrite their register
+# operand, `c.addi rd, imm`.
+CI = EncRecipe(
+        'CI', BinaryImm, size=2, ins=GPR, outs=0,
+        instp=IsSignedInt(BinaryImm.imm, 6),
+        emit='put_ci(bits, in_reg0, imm.into(), sink);')
+
+# Compressed left shift, `c.slli rd, shamt`. The shift amount range depends on
+# the base ISA, so the encodings provide the instruction predicate.
+CIshamt = EncRecipe(
+        'CIshamt', BinaryImm, size=2, ins=GPR, outs=0,
+        emit='put_ci(bits, in_reg0, imm.into(), sink);')
+
+# Small constants, `c.li rd, imm`.
+CIz = EncRecipe(
+        'CIz', UnaryImm, size=2, ins=(), outs=GPR,
+        instp=IsSignedInt(UnaryImm.imm, 6),
+        emit='put_ci(bits, out_reg0, imm.into(), sink);')
+
+# Stack pointer adjustment, `c.addi16sp sp, imm`. The immediate must be a
+# nonzero multiple of 16. Zero is the only such multiple that fits in a 1-bit
+# signed integer.
+CIadjsp = EncRecipe(
+        'CIadjsp', UnaryImm, size=2, ins=(), outs=(),
+        instp=And(IsSignedInt(UnaryImm.imm, 10, 4),
+                  Not(IsSignedInt(UnaryImm.imm, 1))),
+        emit='put_ci16sp(bits, imm.into(), sink);')
+
+# CB-type immediate operations on the `x8`-`x15` registers, `c.andi rd', imm`
+# and the right shifts `c.srli rd', shamt` and `c.srai rd', shamt`. The
+# encodings provide the instruction predicate.
+CB = EncRecipe(
+        'CB', BinaryImm, size=2, ins=GPRC, outs=0,
+        emit='put_cbi(bits, in_reg0, imm.into(), sink);')
+
+# Compressed load, `c.lw rd', offset(rs1')`. The offset is an unsigned,
+# scaled immediate, so the encodings provide the instruction predicate.
+CL = EncRecipe(
+        'CL', Load, size=2, ins=GPRC, outs=GPRC,
+        emit='put_cls(bits, in_reg0, out_reg0, offset.into(), sink);')
+
+# Compressed store, `c.sw rs2', offset(rs1')`.
+CS = EncRecipe(
+        'CS', Store, size=2, ins=(GPRC, GPRC), outs=(),
+        emit='put_cls(bits, in_reg1, in_reg0, offset.into(), sink);')
+
+# Compressed unconditional jump, `c.j offset`. The range is +/- 2 KiB.
+CJ = EncRecipe(
+        'CJ', Jump, size=2, ins=(), outs=(), branch_range=(0, 12),
+        emit='''
+        let dest = func.offsets[data.destination] as i64;
+        let disp = dest - sink.offset() as i64;
+        put_cj(bits, disp, sink);
+        ''')
+
+# Compressed branch comparing a register to zero, `c.beqz rs1', offset` or
+# `c.bnez rs1', offset`. The range is +/- 256 bytes.
+CBz = EncRecipe(
+        'CBz', Branch, size=2, ins=GPRC, outs=(), branch_range=(0, 9),
+        emit='''
+        let dest = func.offsets[data.destination] as i64;
+        let disp = dest - sink.offset() as i64;
+        put_cb(bits, disp, in_reg0, sink);
+        ''')
 
-I = EncRecipe('I', BinaryImm, instp=IsSignedInt(BinaryImm.imm, 12))
+# Compressed return to the caller, `c.jr ra`.
+CJr = EncRecipe(
+        'CJr', Return, size=2, ins=(), outs=(),
+        emit='put_cr(bits, RETURN_ADDRESS, 0, sink);')
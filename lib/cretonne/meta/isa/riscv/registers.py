@@ -0,0 +1,23 @@
+"""
+RISC-V register banks.
+"""
+from __future__ import absolute_import
+from cretonne.registers import RegBank, RegClass
+from .defs import isa
+
+
+# We include `x0`, a.k.a `zero` in the register bank. It will be reserved.
+IntRegs = RegBank(
+        'IntRegs', isa,
+        'General purpose registers',
+        units=32, prefix='x')
+
+FloatRegs = RegBank(
+        'FloatRegs', isa,
+        'Floating point registers',
+        units=32, prefix='f')
+
+GPR = RegClass('GPR', IntRegs)
+# The registers `x8`-`x15` that can be used by most compressed instructions.
+GPRC = RegClass('GPRC', IntRegs, start=8, count=8)
+FPR = RegClass('FPR', FloatRegs)
@@ -0,0 +1,141 @@
+//! Common helper code for ABI lowering.
+//!
+//! This module provides functions and data structures that are useful for implementing the
+//! `TargetIsa::legalize_signature()` method.
+
+use ir::{ArgumentLoc, ArgumentType, Type};
+use ir::types;
+
+/// Legalization action to perform on a single argument or return value.
+///
+/// An argument may go through a sequence of legalization steps before it reaches the final
+/// `Assign` action.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ArgAction {
+    /// Assign the argument to the given location.
+    Assign(ArgumentLoc),
+
+    /// Convert the argument, then call again.
+    ///
+    /// This action can split an integer type into two smaller integer arguments, or it can split a
+    /// SIMD vector into halves.
+    Convert(ValueConversion),
+}
+
+impl From<ArgumentLoc> for ArgAction {
+    fn from(x: ArgumentLoc) -> ArgAction {
+        ArgAction::Assign(x)
+    }
+}
+
+impl From<ValueConversion> for ArgAction {
+    fn from(x: ValueConversion) -> ArgAction {
+        ArgAction::Convert(x)
+    }
+}
+
+/// Legalization action to be applied to a value that is being passed to or from a legalized ABI.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ValueConversion {
+    /// Split an integer type into low and high parts, using `isplit_lohi`.
+    IntSplit,
+
+    /// Split a vector type into halves with identical lane types.
+    VectorSplit,
+
+    /// Bit-cast a floating point type to an integer type of the same size.
+    IntBits,
+
+    /// Sign-extend integer value to the required type.
+    Sext(Type),
+
+    /// Unsigned zero-extend value to the required type.
+    Uext(Type),
+}
+
+impl ValueConversion {
+    /// Apply this conversion to a type, return the converted type.
+    pub fn apply(self, ty: Type) -> Type {
+        match self {
+            ValueConversion::IntSplit => ty.half_width().expect("Integer type too small to split"),
+            ValueConversion::VectorSplit => ty.half_vector().expect("Not a vector"),
+            ValueConversion::IntBits => {
+                match ty {
+                    types::F32 => types::I32,
+                    types::F64 => types::I64,
+                    _ => panic!("Not a float type: {}", ty),
+                }
+            }
+            ValueConversion::Sext(nty) => nty,
+            ValueConversion::Uext(nty) => nty,
+        }
+    }
+
+    /// Is this a split conversion that results in two arguments?
+    pub fn is_split(self) -> bool {
+        match self {
+            ValueConversion::IntSplit => true,
+            ValueConversion::VectorSplit => true,
+            _ => false,
+        }
+    }
+}
+
+/// Common trait for assigning arguments to registers or stack locations.
+///
+/// This will be implemented by individual ISAs.
+pub trait ArgAssigner {
+    /// Pick an assignment action for function argument (or return value) `arg`.
+    fn assign(&mut self, arg: &ArgumentType) -> ArgAction;
+}
+
+/// Legalize the arguments in `args` using the given argument assigner.
+///
+/// This function can be used for both arguments and return values.
+pub fn legalize_args<AA: ArgAssigner>(args: &mut Vec<ArgumentType>, aa: &mut AA) {
+    // Iterate over the arguments.
+    // We may need to mutate the vector in place, so don't use a normal iterator, and clone the
+    // argument to avoid holding a reference.
+    let mut argno = 0;
+    while let Some(arg) = args.get(argno).cloned() {
+        // Leave the pre-assigned arguments alone.
+        // We'll assume that they don't interfere with our assignments.
+        if arg.location.is_assigned() {
+            argno += 1;
+            continue;
+        }
+
+        match aa.assign(&arg) {
+            // Assign argument to a location and move on to the next one.
+            ArgAction::Assign(loc) => {
+                args[argno].location = loc;
+                argno += 1;
+            }
+            // Convert this argument, possibly splitting it in two. Then revisit the new arguments.
+            ArgAction::Convert(conv) => {
+                let new_arg = ArgumentType { value_type: conv.apply(arg.value_type), ..arg };
+                args[argno].value_type = new_arg.value_type;
+                if conv.is_split() {
+                    args.insert(argno + 1, new_arg);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ValueConversion::*;
+    use ir::types::{I8, I32, I64, F32, F64};
+
+    #[test]
+    fn conversion() {
+        assert_eq!(IntSplit.apply(I64), I32);
+        assert_eq!(VectorSplit.apply(I32.by(4).unwrap()), I32.by(2).unwrap());
+        assert_eq!(IntBits.apply(F32), I32);
+        assert_eq!(IntBits.apply(F64), I64);
+        assert_eq!(Sext(I32).apply(I8), I32);
+        assert!(IntSplit.is_split());
+        assert!(!Uext(I64).is_split());
+    }
+}
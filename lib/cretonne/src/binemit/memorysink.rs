@@ -0,0 +1,129 @@
+//! Code sink that writes binary machine code into contiguous memory.
+//!
+//! The `CodeSink` trait is the most general way of extracting binary machine code from Cretonne,
+//! and it is implemented by things like the `test binemit` file test driver to generate
+//! hexadecimal machine code. The `MemoryCodeSink` type is the code sink used when compiling a
+//! function for execution: It writes the machine code into a byte slice and forwards any
+//! relocations to a `RelocSink` trait object provided by the embedder.
+
+use ir::{Ebb, FunctionName, JumpTable};
+use super::{CodeSink, CodeOffset, Reloc};
+
+/// A `CodeSink` that writes binary machine code directly into a byte slice.
+///
+/// Multi-byte values are written in little-endian byte order, which is the only byte order used
+/// by the supported ISAs.
+pub struct MemoryCodeSink<'a> {
+    data: &'a mut [u8],
+    offset: usize,
+    relocs: &'a mut RelocSink,
+}
+
+impl<'a> MemoryCodeSink<'a> {
+    /// Create a new memory code sink that writes a function to the memory in `data`.
+    ///
+    /// The slice must be large enough to hold the whole function. This will panic when writing
+    /// past the end of `data`.
+    pub fn new(data: &'a mut [u8], relocs: &'a mut RelocSink) -> MemoryCodeSink<'a> {
+        MemoryCodeSink {
+            data: data,
+            offset: 0,
+            relocs: relocs,
+        }
+    }
+}
+
+/// A trait for receiving relocations for code that is emitted directly into memory.
+///
+/// The relocation offsets are relative to the beginning of the function.
+pub trait RelocSink {
+    /// Add a relocation referencing an EBB at the current offset.
+    fn reloc_ebb(&mut self, offset: CodeOffset, reloc: Reloc, ebb: Ebb);
+
+    /// Add a relocation referencing an external symbol at the current offset.
+    fn reloc_external(&mut self, offset: CodeOffset, reloc: Reloc, name: &FunctionName);
+
+    /// Add a relocation referencing a jump table.
+    fn reloc_jt(&mut self, offset: CodeOffset, reloc: Reloc, jt: JumpTable);
+}
+
+impl<'a> CodeSink for MemoryCodeSink<'a> {
+    fn offset(&self) -> CodeOffset {
+        self.offset as CodeOffset
+    }
+
+    fn put1(&mut self, x: u8) {
+        self.data[self.offset] = x;
+        self.offset += 1;
+    }
+
+    fn put2(&mut self, x: u16) {
+        self.put1(x as u8);
+        self.put1((x >> 8) as u8);
+    }
+
+    fn put4(&mut self, x: u32) {
+        self.put2(x as u16);
+        self.put2((x >> 16) as u16);
+    }
+
+    fn put8(&mut self, x: u64) {
+        self.put4(x as u32);
+        self.put4((x >> 32) as u32);
+    }
+
+    fn reloc_ebb(&mut self, reloc: Reloc, ebb: Ebb) {
+        let ofs = self.offset();
+        self.relocs.reloc_ebb(ofs, reloc, ebb);
+    }
+
+    fn reloc_external(&mut self, reloc: Reloc, name: &FunctionName) {
+        let ofs = self.offset();
+        self.relocs.reloc_external(ofs, reloc, name);
+    }
+
+    fn reloc_jt(&mut self, reloc: Reloc, jt: JumpTable) {
+        let ofs = self.offset();
+        self.relocs.reloc_jt(ofs, reloc, jt);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use binemit::{CodeSink, CodeOffset, Reloc};
+    use ir::{Ebb, FunctionName, JumpTable};
+    use super::{MemoryCodeSink, RelocSink};
+
+    struct Relocs(Vec<(CodeOffset, Reloc, String)>);
+
+    impl RelocSink for Relocs {
+        fn reloc_ebb(&mut self, offset: CodeOffset, reloc: Reloc, ebb: Ebb) {
+            self.0.push((offset, reloc, ebb.to_string()));
+        }
+
+        fn reloc_external(&mut self, offset: CodeOffset, reloc: Reloc, name: &FunctionName) {
+            self.0.push((offset, reloc, name.to_string()));
+        }
+
+        fn reloc_jt(&mut self, offset: CodeOffset, reloc: Reloc, jt: JumpTable) {
+            self.0.push((offset, reloc, jt.to_string()));
+        }
+    }
+
+    #[test]
+    fn little_endian() {
+        let mut mem = [0u8; 16];
+        let mut relocs = Relocs(Vec::new());
+        {
+            let mut sink = MemoryCodeSink::new(&mut mem, &mut relocs);
+            sink.put1(0x01);
+            sink.put2(0x0302);
+            sink.reloc_external(Reloc(7), &FunctionName::new("foo"));
+            sink.put4(0x07060504);
+            sink.put8(0x0f0e0d0c0b0a0908);
+            assert_eq!(sink.offset(), 15);
+        }
+        assert_eq!(mem, [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 0]);
+        assert_eq!(relocs.0, [(3, Reloc(7), "foo".to_string())]);
+    }
+}
@@ -0,0 +1,103 @@
+//! Binary machine code emission.
+//!
+//! The `binemit` module contains code for translating Cretonne's intermediate representation into
+//! binary machine code.
+
+mod memorysink;
+mod relaxation;
+
+pub use self::memorysink::{MemoryCodeSink, RelocSink};
+pub use self::relaxation::relax_branches;
+
+use ir::{Ebb, JumpTable, Function, FunctionName, Inst, StackSlot, GlobalVar, GlobalVarData};
+use isa::TargetIsa;
+
+/// Offset in bytes from the beginning of the function.
+///
+/// Cretonne can be used as a cross compiler, so we don't want to use a type like `usize` which
+/// depends on the *host* platform, not the *target* platform.
+pub type CodeOffset = u32;
+
+/// Relocation kinds depend on the current ISA.
+///
+/// The relocation kind is an index into the table of relocation names returned by
+/// `TargetIsa::reloc_names()`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Reloc(pub u16);
+
+/// Abstract interface for adding bytes to the code segment.
+///
+/// A `CodeSink` will receive all of the machine code for a function. It also accepts relocations
+/// which are locations in the code section that need to be fixed up when linking.
+///
+/// A relocation always applies to the bytes emitted immediately after it, so the offset of a
+/// relocation is the number of bytes emitted into the sink before the `reloc_*` call.
+pub trait CodeSink {
+    /// Get the current position, the number of bytes emitted so far.
+    fn offset(&self) -> CodeOffset;
+
+    /// Add 1 byte to the code section.
+    fn put1(&mut self, x: u8);
+
+    /// Add 2 bytes to the code section.
+    fn put2(&mut self, x: u16);
+
+    /// Add 4 bytes to the code section.
+    fn put4(&mut self, x: u32);
+
+    /// Add 8 bytes to the code section.
+    fn put8(&mut self, x: u64);
+
+    /// Add a relocation referencing an EBB at the current offset.
+    fn reloc_ebb(&mut self, reloc: Reloc, ebb: Ebb);
+
+    /// Add a relocation referencing an external symbol at the current offset.
+    ///
+    /// External symbols are the names of functions declared in the preamble and the names of
+    /// `globalsym` global variables.
+    fn reloc_external(&mut self, reloc: Reloc, name: &FunctionName);
+
+    /// Add a relocation referencing a jump table at the current offset.
+    fn reloc_jt(&mut self, reloc: Reloc, jt: JumpTable);
+}
+
+/// Report a bad encoding error.
+#[inline(never)]
+pub fn bad_encoding(func: &Function, inst: Inst) -> ! {
+    panic!("Bad encoding {} for {}: {:?}",
+           func.encodings.get(inst).cloned().unwrap_or_default(),
+           inst,
+           func.dfg[inst]);
+}
+
+/// Get the offset of the stack slot `ss` relative to the stack pointer.
+///
+/// Stack slot offsets are relative to the stack pointer on entry to the function, so the size of
+/// the stack frame allocated by the prologue must be added.
+pub fn stack_slot_offset(func: &Function, ss: StackSlot) -> i64 {
+    match func.stack_slots[ss].offset {
+        Some(offset) => offset as i64 + func.frame_size.unwrap_or(0) as i64,
+        None => panic!("No offset assigned to {}", ss),
+    }
+}
+
+/// Get the symbol name of the `globalsym` global variable `gv`.
+pub fn globalsym_name(func: &Function, gv: GlobalVar) -> &FunctionName {
+    match func.global_vars[gv] {
+        GlobalVarData::Sym { ref name } => name,
+        ref data => panic!("{} is not a globalsym: {}", gv, data),
+    }
+}
+
+/// Emit binary machine code for all the instructions in `func` to `sink`.
+///
+/// The function must have been through `relax_branches()` so all instructions have their final
+/// encodings, and the EBB offsets are known.
+pub fn emit_function(func: &Function, isa: &TargetIsa, sink: &mut CodeSink) {
+    for ebb in func.layout.ebbs() {
+        debug_assert_eq!(func.offsets[ebb], sink.offset());
+        for inst in func.layout.ebb_insts(ebb) {
+            isa.emit_inst(func, inst, sink);
+        }
+    }
+}
@@ -0,0 +1,283 @@
+//! Branch relaxation and offset computation.
+//!
+//! # EBB header offsets
+//!
+//! Before we can generate binary machine code for branch instructions, we need to know the final
+//! offsets of all the EBB headers in the function. This information is encoded in the
+//! `func.offsets` table.
+//!
+//! # Branch relaxation
+//!
+//! Branch relaxation is the process of ensuring that all branches in the function have enough
+//! range to encode their destination. It is common to have multiple branch encodings in an ISA.
+//! For example, RISC-V has conditional branches with a ±4 KiB range and unconditional `jal`
+//! jumps with a ±1 MiB range. A conditional branch that can't reach its destination is encoded as
+//! an inverted branch over a `jal`.
+//!
+//! Branches are first given their smallest encoding. The EBB offsets are then computed
+//! iteratively, and any branch that turns out to be out of range is switched to a larger encoding
+//! with more range. Encodings only ever grow, so the offsets only increase and the iteration
+//! reaches a fixed point.
+//!
+//! # Instruction shrinking
+//!
+//! Some ISAs have compact encodings that can only be used with certain registers, like the
+//! RISC-V compressed instructions. The register allocator doesn't know about them, so before the
+//! branches are relaxed, every instruction is given its smallest legal encoding whose operand
+//! constraints are satisfied by the value locations. This means that instruction sizes can be any
+//! mix of 2-byte and 4-byte encodings, and a relaxed branch only ever uses encodings whose
+//! constraints are satisfied.
+
+use binemit::{CodeOffset, bad_encoding};
+use ir::{Function, Inst};
+use ir::instructions::BranchInfo;
+use isa::{TargetIsa, Encoding};
+
+/// Relax branches and compute the final layout of EBB headers in `func`.
+///
+/// Every instruction must have a legal encoding. It is first given its smallest encoding that is
+/// compatible with the value locations, so the function should be register allocated.
+///
+/// Fill in the `func.offsets` table so the function is ready for binary emission.
+/// Return the total size of the function in bytes.
+pub fn relax_branches(func: &mut Function, isa: &TargetIsa) -> CodeOffset {
+    let sizing = isa.recipe_sizing();
+    let constraints = isa.recipe_constraints();
+
+    // All EBB offsets start out as 0 and only grow as branches are relaxed.
+    func.offsets.clear();
+    func.offsets.resize(func.dfg.num_ebbs());
+
+    // Start with the smallest encoding of every instruction, including the branches. The current
+    // encoding is kept unless a strictly smaller one is available.
+    for ebb in func.layout.ebbs() {
+        for inst in func.layout.ebb_insts(ebb) {
+            let enc = encoding(func, inst);
+            if !enc.is_legal() {
+                bad_encoding(func, inst);
+            }
+            let smallest = isa.legal_encodings(&func.dfg, &func.dfg[inst])
+                .into_iter()
+                .filter(|e| constraints[e.recipe()].satisfied(inst, func))
+                .min_by_key(|e| sizing[e.recipe()].bytes);
+            if let Some(smallest) = smallest {
+                if sizing[smallest.recipe()].bytes < sizing[enc.recipe()].bytes {
+                    *func.encodings.ensure(inst) = smallest;
+                }
+            }
+        }
+    }
+
+    // Compute EBB offsets, relaxing any branches that are out of range. A forward branch may be
+    // checked against a stale destination offset, but then the destination EBB moves and forces
+    // another pass.
+    let mut offset = 0;
+    let mut go_again = true;
+    while go_again {
+        go_again = false;
+        offset = 0;
+
+        for ebb in func.layout.ebbs() {
+            if func.offsets[ebb] != offset {
+                func.offsets[ebb] = offset;
+                go_again = true;
+            }
+
+            for inst in func.layout.ebb_insts(ebb) {
+                let enc = encoding(func, inst);
+                let mut size = sizing[enc.recipe()].bytes;
+                if let (Some(range), BranchInfo::SingleDest(dest, _)) =
+                    (sizing[enc.recipe()].branch_range, func.dfg[inst].analyze_branch()) {
+                    let dest_offset = func.offsets[dest];
+                    if !range.contains(offset, dest_offset) {
+                        let relaxed = relax_branch(func, inst, enc, offset, dest_offset, isa);
+                        *func.encodings.ensure(inst) = relaxed;
+                        size = sizing[relaxed.recipe()].bytes;
+                        go_again = true;
+                    }
+                }
+                offset += size as CodeOffset;
+            }
+        }
+    }
+
+    offset
+}
+
+/// Get the current encoding of `inst`, or the default illegal encoding.
+fn encoding(func: &Function, inst: Inst) -> Encoding {
+    func.encodings.get(inst).cloned().unwrap_or_default()
+}
+
+/// Find a larger encoding for the branch `inst` at `offset` which can reach `dest`.
+///
+/// Only encodings that are at least as large as the current encoding `enc` are considered. This
+/// guarantees that branch relaxation terminates. The operand constraints of the new encoding must
+/// be satisfied by the current value locations.
+fn relax_branch(func: &Function,
+                inst: Inst,
+                enc: Encoding,
+                offset: CodeOffset,
+                dest: CodeOffset,
+                isa: &TargetIsa)
+                -> Encoding {
+    let sizing = isa.recipe_sizing();
+    let constraints = isa.recipe_constraints();
+    let min_size = sizing[enc.recipe()].bytes;
+    isa.legal_encodings(&func.dfg, &func.dfg[inst])
+        .into_iter()
+        .filter(|e| {
+            let s = &sizing[e.recipe()];
+            s.bytes >= min_size && s.branch_range.map_or(false, |r| r.contains(offset, dest)) &&
+            constraints[e.recipe()].satisfied(inst, func)
+        })
+        .min_by_key(|e| sizing[e.recipe()].bytes)
+        .unwrap_or_else(|| {
+            panic!("Branch {} at offset {:#x} can't reach offset {:#x}",
+                   inst,
+                   offset,
+                   dest)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use ir::{Function, Ebb, Inst, Cursor, InstBuilder, ValueLoc, types};
+    use ir::instructions::VariableArgs;
+    use isa::{self, TargetIsa};
+    use settings::{self, Configurable};
+    use super::relax_branches;
+
+    // Build a function with a forward and a backward branch around `padding` instructions:
+    //
+    //     ebb0(v0: i32):
+    //         brz v0, ebb2
+    //         jump ebb1
+    //     ebb1:
+    //         v1 = iadd_imm v0, 1 ; repeated `padding` times
+    //         jump ebb2
+    //     ebb2:
+    //         brz v0, ebb0
+    //         return
+    //
+    // The argument `v0` is in `%x10`, and the `iadd_imm` results are in `%x11`.
+    //
+    // Return the function and the two `brz` instructions.
+    fn branchy(isa: &TargetIsa, padding: usize) -> (Function, Inst, Inst) {
+        let mut func = Function::new();
+        let ebb0 = func.dfg.make_ebb();
+        let ebb1 = func.dfg.make_ebb();
+        let ebb2 = func.dfg.make_ebb();
+        let v0 = func.dfg.append_ebb_arg(ebb0, types::I32);
+        let (fwd, back);
+        {
+            let dfg = &mut func.dfg;
+            let cur = &mut Cursor::new(&mut func.layout);
+
+            cur.insert_ebb(ebb0);
+            fwd = dfg.ins(cur).brz(v0, ebb2, VariableArgs::new());
+            dfg.ins(cur).jump(ebb1, VariableArgs::new());
+
+            cur.insert_ebb(ebb1);
+            for _ in 0..padding {
+                let v1 = dfg.ins(cur).iadd_imm(v0, 1);
+                *func.locations.ensure(v1) = ValueLoc::Reg(11);
+            }
+            dfg.ins(cur).jump(ebb2, VariableArgs::new());
+
+            cur.insert_ebb(ebb2);
+            back = dfg.ins(cur).brz(v0, ebb0, VariableArgs::new());
+            dfg.ins(cur).return_(VariableArgs::new());
+        }
+
+        *func.locations.ensure(v0) = ValueLoc::Reg(10);
+
+        let ebbs: Vec<Ebb> = func.layout.ebbs().collect();
+        for ebb in ebbs {
+            let insts: Vec<Inst> = func.layout.ebb_insts(ebb).collect();
+            for inst in insts {
+                if let Ok(enc) = isa.encode(&func.dfg, &func.dfg[inst]) {
+                    *func.encodings.ensure(inst) = enc;
+                }
+            }
+        }
+        (func, fwd, back)
+    }
+
+    fn riscv() -> Box<TargetIsa> {
+        isa::lookup("riscv").unwrap().finish(settings::Flags::new(&settings::builder()))
+    }
+
+    fn riscv_c() -> Box<TargetIsa> {
+        let mut isa_builder = isa::lookup("riscv").unwrap();
+        isa_builder.set_bool("supports_c", true).unwrap();
+        isa_builder.finish(settings::Flags::new(&settings::builder()))
+    }
+
+    #[test]
+    fn short_branches() {
+        let isa = riscv();
+        let (mut func, fwd, back) = branchy(&*isa, 1000);
+
+        // 1000 instructions are 4000 bytes, so everything is in range.
+        assert_eq!(relax_branches(&mut func, &*isa), 4020);
+        assert_eq!(isa.display_enc(func.encodings[fwd]).to_string(), "SBzero#18");
+        assert_eq!(isa.display_enc(func.encodings[back]).to_string(), "SBzero#18");
+        let offsets: Vec<_> = func.layout.ebbs().map(|ebb| func.offsets[ebb]).collect();
+        assert_eq!(offsets, [0, 8, 4012]);
+    }
+
+    #[test]
+    fn long_branches() {
+        let isa = riscv();
+        let (mut func, fwd, back) = branchy(&*isa, 1024);
+
+        // Both branches need the inverted branch over a `jal`.
+        assert_eq!(relax_branches(&mut func, &*isa), 4124);
+        assert_eq!(isa.display_enc(func.encodings[fwd]).to_string(), "SBzeroLong#18");
+        assert_eq!(isa.display_enc(func.encodings[back]).to_string(), "SBzeroLong#18");
+        let offsets: Vec<_> = func.layout.ebbs().map(|ebb| func.offsets[ebb]).collect();
+        assert_eq!(offsets, [0, 12, 4112]);
+    }
+
+    #[test]
+    fn compressed_branches() {
+        let isa = riscv_c();
+        let (mut func, fwd, back) = branchy(&*isa, 50);
+
+        // The branches, jumps, and return are compressed. The `iadd_imm` instructions can't use
+        // `c.addi` since the result isn't in the same register as the argument.
+        assert_eq!(relax_branches(&mut func, &*isa), 210);
+        assert_eq!(isa.display_enc(func.encodings[fwd]).to_string(), "CBz#c001");
+        assert_eq!(isa.display_enc(func.encodings[back]).to_string(), "CBz#c001");
+        let offsets: Vec<_> = func.layout.ebbs().map(|ebb| func.offsets[ebb]).collect();
+        assert_eq!(offsets, [0, 4, 206]);
+    }
+
+    #[test]
+    fn mixed_branches() {
+        let isa = riscv_c();
+        let (mut func, fwd, back) = branchy(&*isa, 100);
+
+        // The `c.beqz` range is only +/- 256 bytes, so both branches are relaxed to a 4-byte
+        // `beq`. The jumps and return stay compressed.
+        assert_eq!(relax_branches(&mut func, &*isa), 414);
+        assert_eq!(isa.display_enc(func.encodings[fwd]).to_string(), "SBzero#18");
+        assert_eq!(isa.display_enc(func.encodings[back]).to_string(), "SBzero#18");
+        let offsets: Vec<_> = func.layout.ebbs().map(|ebb| func.offsets[ebb]).collect();
+        assert_eq!(offsets, [0, 6, 408]);
+    }
+
+    #[test]
+    fn compressed_registers() {
+        let isa = riscv_c();
+        let (mut func, fwd, back) = branchy(&*isa, 50);
+
+        // `c.beqz` can only test the registers `%x8`-`%x15`.
+        let v0 = func.dfg.ebb_args(func.layout.entry_block().unwrap()).next().unwrap();
+        *func.locations.ensure(v0) = ValueLoc::Reg(20);
+        assert_eq!(relax_branches(&mut func, &*isa), 214);
+        assert_eq!(isa.display_enc(func.encodings[fwd]).to_string(), "SBzero#18");
+        assert_eq!(isa.display_enc(func.encodings[back]).to_string(), "SBzero#18");
+    }
+}
@@ -52,28 +52,38 @@ impl ControlFlowGraph {
     /// During initialization mappings will be generated for any existing
     /// blocks within the CFG's associated function.
     pub fn new(func: &Function) -> ControlFlowGraph {
-
         let mut cfg = ControlFlowGraph {
-            data: EntityMap::with_capacity(func.dfg.num_ebbs()),
-            entry_block: func.layout.entry_block(),
+            data: EntityMap::new(),
+            entry_block: None,
         };
+        cfg.compute(func);
+        cfg
+    }
+
+    /// Compute the control flow graph of `func`.
+    ///
+    /// This will clear and overwrite any information already stored in this data structure, but
+    /// it reuses the allocated memory.
+    pub fn compute(&mut self, func: &Function) {
+        self.entry_block = func.layout.entry_block();
+        self.data.clear();
+        self.data.resize(func.dfg.num_ebbs());
 
         for ebb in &func.layout {
             for inst in func.layout.ebb_insts(ebb) {
                 match func.dfg[inst].analyze_branch() {
                     BranchInfo::SingleDest(dest, _) => {
-                        cfg.add_edge((ebb, inst), dest);
+                        self.add_edge((ebb, inst), dest);
                     }
                     BranchInfo::Table(jt) => {
                         for (_, dest) in func.jump_tables[jt].entries() {
-                            cfg.add_edge((ebb, inst), dest);
+                            self.add_edge((ebb, inst), dest);
                         }
                     }
                     BranchInfo::NotABranch => {}
                 }
             }
         }
-        cfg
     }
 
     fn add_edge(&mut self, from: BasicBlock, to: Ebb) {
@@ -0,0 +1,323 @@
+//! Cretonne compilation context and main entry point.
+//!
+//! When compiling many small functions, it is important to avoid repeatedly allocating and
+//! deallocating the data structures needed for compilation. The `Context` struct is used to hold
+//! on to memory allocations between function compilations.
+//!
+//! The context does not hold a `TargetIsa` instance which has to be provided as an argument
+//! instead. This is because an ISA instance is immutable and can be used by multiple compilation
+//! contexts concurrently. Typically, you would have one context per compilation thread and only a
+//! single ISA instance.
+
+use binemit::{self, CodeOffset, MemoryCodeSink, RelocSink};
+use cfg::ControlFlowGraph;
+use dominator_tree::DominatorTree;
+use ir::Function;
+use isa::TargetIsa;
+use legalize_function;
+use regalloc;
+use frame::insert_prologue_epilogue;
+use verifier::{verify_function, verify_encodings, Result};
+
+/// Persistent data structures and compilation pipeline.
+pub struct Context {
+    /// The function we're compiling.
+    pub func: Function,
+
+    /// The control flow graph of `func`.
+    pub cfg: ControlFlowGraph,
+
+    /// Dominator tree for `func`.
+    pub domtree: DominatorTree,
+
+    /// Register allocation context.
+    pub regalloc: regalloc::Context,
+}
+
+/// Information about the machine code produced by `Context::compile()`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CodeInfo {
+    /// Size of the function's machine code in bytes.
+    pub code_size: CodeOffset,
+}
+
+impl Context {
+    /// Allocate a new compilation context.
+    ///
+    /// The returned instance should be reused for compiling multiple functions in order to avoid
+    /// needless allocator thrashing.
+    pub fn new() -> Context {
+        let func = Function::new();
+        let cfg = ControlFlowGraph::new(&func);
+        let domtree = DominatorTree::new(&cfg);
+        Context {
+            func: func,
+            cfg: cfg,
+            domtree: domtree,
+            regalloc: regalloc::Context::new(),
+        }
+    }
+
+    /// Compile the function.
+    ///
+    /// Run the function through all the passes necessary to generate binary machine code for the
+    /// target ISA represented by `isa`. This does not include the final step of emitting machine
+    /// code into a code sink; use `emit_to_memory()` for that.
+    ///
+    /// When the `enable_verifier` setting is on, the function is verified before compilation and
+    /// after each pass that modifies it. Compilation fails if the target ISA can't encode all the
+    /// instructions after legalization or after inserting the prologue and epilogues.
+    ///
+    /// Returns information about the function's code, including its size in bytes.
+    pub fn compile(&mut self, isa: &TargetIsa) -> Result<CodeInfo> {
+        try!(self.verify_if(isa));
+
+        self.legalize(isa);
+        try!(self.verify_if(isa));
+        try!(self.verify_encodings());
+
+        self.flowgraph();
+        self.regalloc(isa);
+        try!(self.verify_if(isa));
+
+        self.prologue_epilogue(isa);
+        try!(self.verify_if(isa));
+        try!(self.verify_encodings());
+
+        let code_size = self.relax_branches(isa);
+        Ok(CodeInfo { code_size: code_size })
+    }
+
+    /// Emit machine code directly into a byte slice.
+    ///
+    /// Write all of the function's machine code to `mem`, which must be at least as large as the
+    /// code size returned by `compile()`. Relocations are reported to `relocs` with offsets
+    /// relative to the beginning of `mem`.
+    ///
+    /// The machine code is generated by `isa` which must be the same target ISA that was passed to
+    /// `compile()`. Since the context doesn't hold on to an ISA instance, it has to be provided
+    /// again here.
+    pub fn emit_to_memory(&self, mem: &mut [u8], relocs: &mut RelocSink, isa: &TargetIsa) {
+        let mut sink = MemoryCodeSink::new(mem, relocs);
+        binemit::emit_function(&self.func, isa, &mut sink);
+    }
+
+    /// Run the verifier on the function.
+    pub fn verify(&self) -> Result<()> {
+        verify_function(&self.func)
+    }
+
+    /// Check that every instruction in the function has a legal encoding.
+    pub fn verify_encodings(&self) -> Result<()> {
+        verify_encodings(&self.func)
+    }
+
+    /// Run the verifier only if the `enable_verifier` setting is true.
+    fn verify_if(&self, isa: &TargetIsa) -> Result<()> {
+        if isa.flags().enable_verifier() {
+            self.verify()
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Run the legalizer for `isa` on the function.
+    pub fn legalize(&mut self, isa: &TargetIsa) {
+        legalize_function(&mut self.func, isa);
+    }
+
+    /// Recompute the control flow graph and dominator tree.
+    pub fn flowgraph(&mut self) {
+        self.cfg.compute(&self.func);
+        self.domtree.compute(&self.cfg);
+    }
+
+    /// Run the register allocator on the function.
+    ///
+    /// The control flow graph and dominator tree must be up to date. The register allocator can
+    /// split edges, so they are no longer valid afterwards.
+    pub fn regalloc(&mut self, isa: &TargetIsa) {
+        self.regalloc.run(&mut self.func, &self.cfg, &self.domtree, isa);
+    }
+
+    /// Lay out the stack frame and insert the prologue and epilogues.
+    pub fn prologue_epilogue(&mut self, isa: &TargetIsa) {
+        insert_prologue_epilogue(&mut self.func, isa);
+    }
+
+    /// Run the branch relaxation pass and return the final code size.
+    pub fn relax_branches(&mut self, isa: &TargetIsa) -> CodeOffset {
+        binemit::relax_branches(&mut self.func, isa)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use binemit::{CodeOffset, Reloc, RelocSink};
+    use ir::{Function, FunctionName, Signature, ArgumentType, Ebb, JumpTable, Cursor,
+             InstBuilder, ValueDef, Opcode, types};
+    use ir::instructions::VariableArgs;
+    use ir::entities::AnyEntity;
+    use isa::{self, TargetIsa};
+    use settings;
+    use super::{Context, CodeInfo};
+
+    struct NoRelocs;
+
+    impl RelocSink for NoRelocs {
+        fn reloc_ebb(&mut self, _: CodeOffset, _: Reloc, _: Ebb) {
+            panic!("Unexpected relocation");
+        }
+
+        fn reloc_external(&mut self, _: CodeOffset, _: Reloc, _: &FunctionName) {
+            panic!("Unexpected relocation");
+        }
+
+        fn reloc_jt(&mut self, _: CodeOffset, _: Reloc, _: JumpTable) {
+            panic!("Unexpected relocation");
+        }
+    }
+
+    fn riscv() -> Box<TargetIsa> {
+        isa::lookup("riscv").unwrap().finish(settings::Flags::new(&settings::builder()))
+    }
+
+    // Build the function:
+    //
+    //     function add(i32, i32) -> i32 {
+    //     ebb0(v0: i32, v1: i32):
+    //         v2 = iadd v0, v1
+    //         return v2
+    //     }
+    fn add() -> Function {
+        let mut sig = Signature::new();
+        sig.argument_types.push(ArgumentType::new(types::I32));
+        sig.argument_types.push(ArgumentType::new(types::I32));
+        sig.return_types.push(ArgumentType::new(types::I32));
+        let mut func = Function::with_name_signature(FunctionName::new("add"), sig);
+
+        let ebb0 = func.dfg.make_ebb();
+        let v0 = func.dfg.append_ebb_arg(ebb0, types::I32);
+        let v1 = func.dfg.append_ebb_arg(ebb0, types::I32);
+        {
+            let dfg = &mut func.dfg;
+            let cur = &mut Cursor::new(&mut func.layout);
+            cur.insert_ebb(ebb0);
+            let v2 = dfg.ins(cur).iadd(v0, v1);
+            let mut rets = VariableArgs::new();
+            rets.push(v2);
+            dfg.ins(cur).return_(rets);
+        }
+        func
+    }
+
+    #[test]
+    fn compile_and_emit() {
+        let isa = riscv();
+        let mut ctx = Context::new();
+
+        // The context can be reused for multiple functions.
+        for _ in 0..2 {
+            ctx.func = add();
+            assert_eq!(ctx.compile(&*isa), Ok(CodeInfo { code_size: 12 }));
+
+            let mut mem = [0u8; 12];
+            ctx.emit_to_memory(&mut mem, &mut NoRelocs, &*isa);
+            assert_eq!(mem,
+                       [
+                           0xb3, 0x02, 0xb5, 0x00, // add x5, x10, x11
+                           0x13, 0x85, 0x02, 0x00, // addi x10, x5, 0
+                           0x67, 0x80, 0x00, 0x00, // jalr x0, 0(x1)
+                       ]);
+        }
+    }
+
+    #[test]
+    fn split_vectors() {
+        let isa = riscv();
+        let mut ctx = Context::new();
+        ctx.func = add();
+
+        // Compute the sum in lane 3 of a vector. RISC-V has no SIMD instructions, so the vectors
+        // are split into scalars.
+        let ebb0 = ctx.func.layout.entry_block().unwrap();
+        let args: Vec<_> = ctx.func.dfg.ebb_args(ebb0).collect();
+        {
+            let dfg = &mut ctx.func.dfg;
+            let cur = &mut Cursor::new(&mut ctx.func.layout);
+            cur.goto_top(ebb0);
+            cur.next_inst();
+            let x = dfg.ins(cur).splat(types::I32X4, args[0]);
+            let y = dfg.ins(cur).splat(types::I32X4, args[1]);
+            let sum = dfg.ins(cur).iadd(x, y);
+            let lane = dfg.ins(cur).extractlane(sum, 3);
+            let ret = cur.next_inst().unwrap();
+            let mut rets = VariableArgs::new();
+            rets.push(lane);
+            dfg.replace(ret).return_(rets);
+        }
+        assert!(ctx.compile(&*isa).is_ok());
+
+        // The `vsplit` and `vconcat` instructions inserted by the legalizer are all gone.
+        for ebb in ctx.func.layout.ebbs() {
+            for inst in ctx.func.layout.ebb_insts(ebb) {
+                let opcode = ctx.func.dfg[inst].opcode();
+                assert!(opcode != Opcode::Vsplit && opcode != Opcode::Vconcat);
+            }
+        }
+    }
+
+    #[test]
+    fn too_many_return_values() {
+        let isa = riscv();
+        let mut ctx = Context::new();
+
+        // RISC-V returns at most two values in registers.
+        let mut sig = Signature::new();
+        sig.argument_types.push(ArgumentType::new(types::I32));
+        for _ in 0..3 {
+            sig.return_types.push(ArgumentType::new(types::I32));
+        }
+        ctx.func = Function::with_name_signature(FunctionName::new("rets"), sig);
+        let ebb0 = ctx.func.dfg.make_ebb();
+        let v0 = ctx.func.dfg.append_ebb_arg(ebb0, types::I32);
+        {
+            let dfg = &mut ctx.func.dfg;
+            let cur = &mut Cursor::new(&mut ctx.func.layout);
+            cur.insert_ebb(ebb0);
+            let mut rets = VariableArgs::new();
+            for _ in 0..3 {
+                rets.push(v0);
+            }
+            dfg.ins(cur).return_(rets);
+        }
+        let err = ctx.compile(&*isa).unwrap_err();
+        assert_eq!(err.location, AnyEntity::Function);
+        assert!(err.message.contains("calling convention"));
+    }
+
+    #[test]
+    fn unencodable() {
+        let isa = riscv();
+        let mut ctx = Context::new();
+        ctx.func = add();
+
+        // RISC-V doesn't have a population count instruction.
+        let ebb0 = ctx.func.layout.entry_block().unwrap();
+        let v0 = ctx.func.dfg.ebb_args(ebb0).next().unwrap();
+        let popcnt = {
+            let dfg = &mut ctx.func.dfg;
+            let cur = &mut Cursor::new(&mut ctx.func.layout);
+            cur.goto_top(ebb0);
+            cur.next_inst();
+            let v = dfg.ins(cur).popcnt(v0);
+            match dfg.value_def(v) {
+                ValueDef::Res(inst, _) => inst,
+                ValueDef::Arg(..) => panic!("{} is not an instruction result", v),
+            }
+        };
+        let err = ctx.compile(&*isa).unwrap_err();
+        assert_eq!(err.location, popcnt.into());
+        assert!(err.message.contains("no legal encoding"));
+    }
+}
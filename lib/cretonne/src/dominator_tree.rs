@@ -14,13 +14,25 @@ impl DominatorTree {
     /// Build a dominator tree from a control flow graph using Keith D. Cooper's
     /// "Simple, Fast Dominator Algorithm."
     pub fn new(cfg: &ControlFlowGraph) -> DominatorTree {
+        let mut domtree = DominatorTree { data: EntityMap::new() };
+        domtree.compute(cfg);
+        domtree
+    }
+
+    /// Compute the dominator tree of the control flow graph `cfg`.
+    ///
+    /// This will clear and overwrite any information already stored in this data structure, but
+    /// it reuses the allocated memory.
+    pub fn compute(&mut self, cfg: &ControlFlowGraph) {
         let mut ebbs = cfg.postorder_ebbs();
         ebbs.reverse();
 
         let len = ebbs.len();
 
         // The mappings which designate the dominator tree.
-        let mut data = EntityMap::with_capacity(len);
+        let data = &mut self.data;
+        data.clear();
+        data.resize(cfg.ebbs().count());
 
         let mut postorder_map = EntityMap::with_capacity(len);
         for (i, ebb) in ebbs.iter().enumerate() {
@@ -51,7 +63,7 @@ impl DominatorTree {
                     if let Some(_) = data[pred.0] {
                         new_idom = match new_idom {
                             Some(cur_idom) => {
-                                Some((DominatorTree::intersect(&mut data,
+                                Some((DominatorTree::intersect(data,
                                                                &postorder_map,
                                                                *pred,
                                                                cur_idom)))
@@ -75,8 +87,6 @@ impl DominatorTree {
                 }
             }
         }
-
-        DominatorTree { data: data }
     }
 
     /// Find the common dominator of two ebbs.
@@ -0,0 +1,290 @@
+//! Stack frame layout and prologue/epilogue insertion.
+//!
+//! After register allocation, all the stack slots in a function are known, and so are the
+//! registers it uses. The stack frame can then be laid out, and the prologue and epilogue code
+//! that allocates the frame and preserves registers for the caller is inserted.
+//!
+//! The frame layout is shared by all ISAs. The ISA-specific parts are provided by `TargetIsa`
+//! hooks:
+//!
+//! - `TargetIsa::stack_alignment()` is the required alignment of the stack pointer.
+//! - `TargetIsa::return_address_size()` is the size of the return address pushed by calls.
+//! - `TargetIsa::saved_registers()` lists the registers that the prologue must save.
+//!
+//! The stack frame looks like this, with the stack growing downwards:
+//!
+//! ```text
+//!    incoming arguments       offset >= 0
+//!    return address           (only when pushed by the call)
+//!    ---------------------    <- stack pointer on entry
+//!    local and spill slots    offset < 0
+//!    padding
+//!    outgoing arguments
+//!    ---------------------    <- stack pointer after the prologue
+//! ```
+//!
+//! All stack slot offsets are relative to the stack pointer on entry to the function. The
+//! `func.frame_size` field records how far the prologue moves the stack pointer, so binary
+//! emission can compute the offset of a slot relative to the current stack pointer.
+//!
+//! The stack pointer is aligned before the return address is pushed, so the stack pointer on entry
+//! is misaligned by the size of the return address. The frame size and slot offsets account for
+//! that, so the stack pointer is aligned again after the prologue.
+//!
+//! The register allocator creates outgoing argument slots with the offsets assigned by the callee
+//! signature, which are relative to the stack pointer on entry to the callee. They are converted
+//! to offsets relative to the current function's stack pointer on entry when the frame is laid
+//! out.
+
+use ir::{Function, Cursor, InstBuilder, InstructionData, Opcode, Inst, ValueLoc, ArgumentType,
+         ArgumentLoc, StackSlot, StackSlotData, StackSlotKind};
+use ir::instructions::CallInfo;
+use isa::{TargetIsa, RegUnit};
+use regalloc::{encode_inst, encode_def};
+
+/// Lay out the stack frame of `func` and insert the prologue and epilogue code.
+///
+/// The function must be register allocated. The prologue is inserted before the first instruction
+/// in the entry EBB, and an epilogue is inserted before every `return` instruction:
+///
+/// 1. The prologue allocates the stack frame with an `adjust_sp_imm` instruction and then spills
+///    the registers returned by `TargetIsa::saved_registers()`. The saved registers are added to
+///    the function signature and the entry EBB as special arguments.
+/// 2. Each epilogue fills the saved registers, deallocates the stack frame, and passes the saved
+///    registers as extra return values.
+pub fn insert_prologue_epilogue(func: &mut Function, isa: &TargetIsa) {
+    let first = match func.layout.entry_block().and_then(|ebb| func.layout.ebb_insts(ebb).next()) {
+        Some(inst) => inst,
+        None => return,
+    };
+    let entry = func.layout.entry_block().unwrap();
+
+    // The saved registers get spill slots in the frame like any other value.
+    let saved = isa.saved_registers(func);
+    let slots: Vec<StackSlot> = saved.iter()
+        .map(|arg| {
+            let size = arg.value_type.bytes();
+            func.stack_slots.push(StackSlotData::new(StackSlotKind::SpillSlot, size))
+        })
+        .collect();
+
+    let frame_size = layout_stack(func, isa.stack_alignment(), isa.return_address_size());
+    func.frame_size = Some(frame_size);
+
+    // The prologue allocates the stack frame and then spills the saved registers.
+    if frame_size > 0 {
+        let adjust = {
+            let mut pos = Cursor::new(&mut func.layout);
+            pos.goto_inst(first);
+            func.dfg.ins(&mut pos).adjust_sp_imm(-(frame_size as i64))
+        };
+        encode_inst(func, isa, adjust);
+    }
+    let mut spilled = Vec::with_capacity(saved.len());
+    for (arg, &ss) in saved.iter().zip(&slots) {
+        let value = func.dfg.append_ebb_arg(entry, arg.value_type);
+        *func.locations.ensure(value) = ValueLoc::Reg(saved_register(arg));
+        let spill = {
+            let mut pos = Cursor::new(&mut func.layout);
+            pos.goto_inst(first);
+            func.dfg.ins(&mut pos).spill(value)
+        };
+        *func.locations.ensure(spill) = ValueLoc::Stack(ss);
+        encode_def(func, isa, spill);
+        spilled.push(spill);
+    }
+    func.own_signature_mut().argument_types.extend(saved.iter().cloned());
+
+    // Each epilogue fills the saved registers and then deallocates the stack frame.
+    let returns: Vec<Inst> = func.layout
+        .ebbs()
+        .flat_map(|ebb| func.layout.ebb_insts(ebb))
+        .filter(|&inst| func.dfg[inst].opcode() == Opcode::Return)
+        .collect();
+    for inst in returns {
+        for (arg, &spill) in saved.iter().zip(&spilled) {
+            let fill = {
+                let mut pos = Cursor::new(&mut func.layout);
+                pos.goto_inst(inst);
+                func.dfg.ins(&mut pos).fill(spill)
+            };
+            *func.locations.ensure(fill) = ValueLoc::Reg(saved_register(arg));
+            encode_def(func, isa, fill);
+            match func.dfg[inst] {
+                InstructionData::Return { ref mut data, .. } => data.varargs.push(fill),
+                _ => unreachable!(),
+            }
+        }
+        if frame_size > 0 {
+            let adjust = {
+                let mut pos = Cursor::new(&mut func.layout);
+                pos.goto_inst(inst);
+                func.dfg.ins(&mut pos).adjust_sp_imm(frame_size as i64)
+            };
+            encode_inst(func, isa, adjust);
+        }
+    }
+    func.own_signature_mut().return_types.extend(saved.iter().cloned());
+}
+
+/// Compute the stack frame layout of `func`.
+///
+/// Assign offsets to all the local variable and spill slots below the stack pointer on entry.
+/// Slots are aligned to their natural alignment, but never more than `alignment`, unless the slot
+/// specifies its own alignment. Incoming argument slots keep their assigned offsets. Outgoing
+/// argument slots are placed at the bottom of the frame, so they are at their callee offsets
+/// when the callee is entered.
+///
+/// The stack pointer on entry is `return_address_size` bytes below an `alignment` boundary. Return
+/// the size of the stack frame that makes the stack pointer aligned again. A function without any
+/// stack slots or calls doesn't need a frame.
+pub fn layout_stack(func: &mut Function, alignment: u32, return_address_size: u32) -> u32 {
+    assert!(alignment.is_power_of_two(), "Bad stack alignment {}", alignment);
+
+    // Place the slots with the largest alignment first to minimize padding. The sort is stable,
+    // so slots with the same alignment are placed in order.
+    let mut slots: Vec<(StackSlot, u32)> = func.stack_slots
+        .keys()
+        .filter(|&ss| match func.stack_slots[ss].kind {
+            StackSlotKind::Local | StackSlotKind::SpillSlot => true,
+            StackSlotKind::IncomingArg | StackSlotKind::OutgoingArg => false,
+        })
+        .map(|ss| (ss, slot_alignment(&func.stack_slots[ss], alignment)))
+        .collect();
+    slots.sort_by(|a, b| b.1.cmp(&a.1));
+
+    // Number of bytes allocated below the aligned stack pointer before the call, including the
+    // return address.
+    let mut size = return_address_size;
+    for (ss, align) in slots {
+        let data = &mut func.stack_slots[ss];
+        size = align_up(size + data.size, align);
+        data.offset = Some(return_address_size as i32 - size as i32);
+    }
+
+    // The callee offsets of the outgoing arguments include the return address pushed by the call.
+    let outgoing: Vec<StackSlot> = func.stack_slots
+        .keys()
+        .filter(|&ss| func.stack_slots[ss].kind == StackSlotKind::OutgoingArg)
+        .collect();
+    let outgoing_size = outgoing.iter()
+        .map(|&ss| {
+            let data = &func.stack_slots[ss];
+            let offset = data.offset.expect("Outgoing argument needs an offset");
+            (offset + data.size as i32 - return_address_size as i32) as u32
+        })
+        .max()
+        .unwrap_or(0);
+    if size == return_address_size && outgoing_size == 0 && !has_calls(func) {
+        return 0;
+    }
+
+    let frame_size = align_up(size + outgoing_size, alignment) - return_address_size;
+    for ss in outgoing {
+        let data = &mut func.stack_slots[ss];
+        data.offset = data.offset.map(|offset| offset - (return_address_size + frame_size) as i32);
+    }
+    frame_size
+}
+
+/// Does `func` contain any calls?
+fn has_calls(func: &Function) -> bool {
+    func.layout
+        .ebbs()
+        .flat_map(|ebb| func.layout.ebb_insts(ebb))
+        .any(|inst| match func.dfg[inst].analyze_call() {
+            CallInfo::NotACall => false,
+            _ => true,
+        })
+}
+
+/// Get the alignment of a stack slot.
+fn slot_alignment(data: &StackSlotData, alignment: u32) -> u32 {
+    data.align.unwrap_or_else(|| {
+        // Use the natural alignment of the slot size, but don't exceed the stack alignment.
+        let natural = data.size.next_power_of_two();
+        if natural < alignment { natural } else { alignment }
+    })
+}
+
+/// Round `x` up to a multiple of the power of two `align`.
+fn align_up(x: u32, align: u32) -> u32 {
+    (x + align - 1) & !(align - 1)
+}
+
+/// Get the register that holds the saved register argument `arg`.
+fn saved_register(arg: &ArgumentType) -> RegUnit {
+    match arg.location {
+        ArgumentLoc::Reg(reg) => reg,
+        _ => panic!("Saved register needs a register location: {}", arg),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ir::{Function, StackSlotData, StackSlotKind};
+    use super::layout_stack;
+
+    #[test]
+    fn layout() {
+        let mut func = Function::new();
+        assert_eq!(layout_stack(&mut func, 16, 0), 0);
+
+        let mut incoming = StackSlotData::new(StackSlotKind::IncomingArg, 8);
+        incoming.offset = Some(0);
+        let ss0 = func.stack_slots.push(incoming);
+        let ss1 = func.stack_slots.push(StackSlotData::new(StackSlotKind::SpillSlot, 4));
+        let ss2 = func.stack_slots.push(StackSlotData::new(StackSlotKind::Local, 1));
+        let ss3 = func.stack_slots.push(StackSlotData::new(StackSlotKind::SpillSlot, 8));
+        let mut big = StackSlotData::new(StackSlotKind::Local, 40);
+        big.align = Some(4);
+        let ss4 = func.stack_slots.push(big);
+
+        // Slots are placed in order of decreasing alignment: ss3, ss1, ss4, ss2.
+        assert_eq!(layout_stack(&mut func, 16, 0), 64);
+        assert_eq!(func.stack_slots[ss0].offset, Some(0));
+        assert_eq!(func.stack_slots[ss3].offset, Some(-8));
+        assert_eq!(func.stack_slots[ss1].offset, Some(-12));
+        assert_eq!(func.stack_slots[ss4].offset, Some(-52));
+        assert_eq!(func.stack_slots[ss2].offset, Some(-53));
+    }
+
+    #[test]
+    fn return_address() {
+        let mut func = Function::new();
+        assert_eq!(layout_stack(&mut func, 16, 8), 0);
+
+        let ss0 = func.stack_slots.push(StackSlotData::new(StackSlotKind::Local, 4));
+        let ss1 = func.stack_slots.push(StackSlotData::new(StackSlotKind::SpillSlot, 16));
+
+        // The stack pointer on entry is 8 bytes below a 16-byte boundary, so the 16-byte slot
+        // needs 8 bytes of padding.
+        assert_eq!(layout_stack(&mut func, 16, 8), 40);
+        assert_eq!(func.stack_slots[ss1].offset, Some(-24));
+        assert_eq!(func.stack_slots[ss0].offset, Some(-28));
+    }
+
+    #[test]
+    fn outgoing_args() {
+        let mut func = Function::new();
+        let ss0 = func.stack_slots.push(StackSlotData::new(StackSlotKind::SpillSlot, 4));
+        let mut outgoing = StackSlotData::new(StackSlotKind::OutgoingArg, 4);
+        outgoing.offset = Some(12);
+        let ss1 = func.stack_slots.push(outgoing);
+
+        // The outgoing arguments are at the bottom of the frame, so the 12-byte offset in the
+        // callee's frame is 12 bytes above the stack pointer after the prologue.
+        assert_eq!(layout_stack(&mut func, 16, 0), 32);
+        assert_eq!(func.stack_slots[ss0].offset, Some(-4));
+        assert_eq!(func.stack_slots[ss1].offset, Some(-20));
+
+        // When the call pushes an 8-byte return address, the callee offset 8 is right at the
+        // stack pointer after the prologue.
+        let mut func = Function::new();
+        let mut outgoing = StackSlotData::new(StackSlotKind::OutgoingArg, 8);
+        outgoing.offset = Some(8);
+        let ss0 = func.stack_slots.push(outgoing);
+        assert_eq!(layout_stack(&mut func, 16, 8), 8);
+        assert_eq!(func.stack_slots[ss0].offset, Some(-8));
+    }
+}
@@ -0,0 +1,207 @@
+//! Immediate operands for atomic memory operations.
+//!
+//! The atomic instructions take enumerated immediate operands that select the read-modify-write
+//! operation to perform and the memory ordering constraints of the access.
+
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+/// The operation performed by an `atomic_rmw` instruction.
+///
+/// The operation computes the new value to store in memory from the old value in memory and the
+/// instruction's value operand.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AtomicRmwOp {
+    /// `old + x`.
+    Add,
+    /// `old & x`.
+    And,
+    /// `old | x`.
+    Or,
+    /// `old ^ x`.
+    Xor,
+    /// `x`, exchanging the old value with `x`.
+    Xchg,
+    /// The signed minimum of `old` and `x`.
+    Smin,
+    /// The signed maximum of `old` and `x`.
+    Smax,
+    /// The unsigned minimum of `old` and `x`.
+    Umin,
+    /// The unsigned maximum of `old` and `x`.
+    Umax,
+}
+
+impl Display for AtomicRmwOp {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        use self::AtomicRmwOp::*;
+        f.write_str(match self {
+            &Add => "add",
+            &And => "and",
+            &Or => "or",
+            &Xor => "xor",
+            &Xchg => "xchg",
+            &Smin => "smin",
+            &Smax => "smax",
+            &Umin => "umin",
+            &Umax => "umax",
+        })
+    }
+}
+
+impl FromStr for AtomicRmwOp {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        use self::AtomicRmwOp::*;
+        match s {
+            "add" => Ok(Add),
+            "and" => Ok(And),
+            "or" => Ok(Or),
+            "xor" => Ok(Xor),
+            "xchg" => Ok(Xchg),
+            "smin" => Ok(Smin),
+            "smax" => Ok(Smax),
+            "umin" => Ok(Umin),
+            "umax" => Ok(Umax),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Memory ordering constraints of an atomic memory operation.
+///
+/// These are the orderings from the C11 and C++11 memory models, except for `consume` which is
+/// treated as `acquire` by most compilers anyway.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AtomicOrdering {
+    /// No ordering constraints. Only the atomicity of the access itself is guaranteed.
+    Relaxed,
+    /// Memory accesses after this operation can't be moved before it.
+    Acquire,
+    /// Memory accesses before this operation can't be moved after it.
+    Release,
+    /// Both `Acquire` and `Release`.
+    AcqRel,
+    /// Like `AcqRel`, and all sequentially consistent operations appear in a single total order.
+    SeqCst,
+}
+
+impl AtomicOrdering {
+    /// Does this ordering have acquire semantics?
+    pub fn is_acquire(self) -> bool {
+        match self {
+            AtomicOrdering::Acquire |
+            AtomicOrdering::AcqRel |
+            AtomicOrdering::SeqCst => true,
+            AtomicOrdering::Relaxed |
+            AtomicOrdering::Release => false,
+        }
+    }
+
+    /// Does this ordering have release semantics?
+    pub fn is_release(self) -> bool {
+        match self {
+            AtomicOrdering::Release |
+            AtomicOrdering::AcqRel |
+            AtomicOrdering::SeqCst => true,
+            AtomicOrdering::Relaxed |
+            AtomicOrdering::Acquire => false,
+        }
+    }
+
+    /// Get the number representing this ordering in the C11 `memory_order` enumeration.
+    ///
+    /// This is the `memorder` argument of the `__atomic_*` runtime library functions.
+    pub fn c11_number(self) -> i64 {
+        match self {
+            AtomicOrdering::Relaxed => 0,
+            AtomicOrdering::Acquire => 2,
+            AtomicOrdering::Release => 3,
+            AtomicOrdering::AcqRel => 4,
+            AtomicOrdering::SeqCst => 5,
+        }
+    }
+}
+
+impl Display for AtomicOrdering {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        use self::AtomicOrdering::*;
+        f.write_str(match self {
+            &Relaxed => "relaxed",
+            &Acquire => "acquire",
+            &Release => "release",
+            &AcqRel => "acq_rel",
+            &SeqCst => "seq_cst",
+        })
+    }
+}
+
+impl FromStr for AtomicOrdering {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        use self::AtomicOrdering::*;
+        match s {
+            "relaxed" => Ok(Relaxed),
+            "acquire" => Ok(Acquire),
+            "release" => Ok(Release),
+            "acq_rel" => Ok(AcqRel),
+            "seq_cst" => Ok(SeqCst),
+            _ => Err(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static OP_ALL: [AtomicRmwOp; 9] = [AtomicRmwOp::Add,
+                                       AtomicRmwOp::And,
+                                       AtomicRmwOp::Or,
+                                       AtomicRmwOp::Xor,
+                                       AtomicRmwOp::Xchg,
+                                       AtomicRmwOp::Smin,
+                                       AtomicRmwOp::Smax,
+                                       AtomicRmwOp::Umin,
+                                       AtomicRmwOp::Umax];
+
+    static ORDERING_ALL: [AtomicOrdering; 5] = [AtomicOrdering::Relaxed,
+                                                AtomicOrdering::Acquire,
+                                                AtomicOrdering::Release,
+                                                AtomicOrdering::AcqRel,
+                                                AtomicOrdering::SeqCst];
+
+    #[test]
+    fn op_display() {
+        for r in &OP_ALL {
+            let op = *r;
+            assert_eq!(op.to_string().parse(), Ok(op));
+        }
+        assert_eq!("sub".parse::<AtomicRmwOp>(), Err(()));
+    }
+
+    #[test]
+    fn ordering_display() {
+        for r in &ORDERING_ALL {
+            let ord = *r;
+            assert_eq!(ord.to_string().parse(), Ok(ord));
+        }
+        assert_eq!("consume".parse::<AtomicOrdering>(), Err(()));
+    }
+
+    #[test]
+    fn ordering_semantics() {
+        assert!(!AtomicOrdering::Relaxed.is_acquire());
+        assert!(!AtomicOrdering::Relaxed.is_release());
+        assert!(AtomicOrdering::Acquire.is_acquire());
+        assert!(!AtomicOrdering::Acquire.is_release());
+        assert!(!AtomicOrdering::Release.is_acquire());
+        assert!(AtomicOrdering::Release.is_release());
+        assert!(AtomicOrdering::AcqRel.is_acquire());
+        assert!(AtomicOrdering::AcqRel.is_release());
+        assert!(AtomicOrdering::SeqCst.is_acquire());
+        assert!(AtomicOrdering::SeqCst.is_release());
+    }
+}
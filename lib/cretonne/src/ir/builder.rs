@@ -5,9 +5,11 @@
 
 use ir::{types, instructions};
 use ir::{InstructionData, DataFlowGraph, Cursor};
-use ir::{Opcode, Type, Inst, Value, Ebb, JumpTable, VariableArgs, SigRef, FuncRef};
-use ir::immediates::{Imm64, Uimm8, Ieee32, Ieee64, ImmVector};
+use ir::{Opcode, Type, Inst, Value, Ebb, JumpTable, VariableArgs, SigRef, FuncRef, StackSlot,
+         GlobalVar, Heap, MemFlags};
+use ir::immediates::{Imm64, Uimm8, Uimm32, Offset32, Ieee32, Ieee64, ImmVector};
 use ir::condcodes::{IntCC, FloatCC};
+use ir::atomics::{AtomicRmwOp, AtomicOrdering};
 
 /// Base trait for instruction builders.
 ///
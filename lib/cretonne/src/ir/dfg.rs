@@ -80,6 +80,29 @@ impl DataFlowGraph {
     }
 }
 
+// Find the original value that `value` aliases, using the `extended_values` table.
+fn resolve_aliases(values: &[ValueData], value: Value) -> Value {
+    use ir::entities::ExpandedValue::Table;
+    let mut v = value;
+
+    // Each iteration follows one alias, and there can't be more aliases than table values.
+    for _ in 0..values.len() + 1 {
+        v = match v.expand() {
+            Table(idx) => {
+                match values[idx] {
+                    ValueData::Alias { original, .. } => {
+                        // Follow alias values.
+                        original
+                    }
+                    _ => return v,
+                }
+            }
+            _ => return v,
+        };
+    }
+    panic!("Value alias loop detected for {}", value);
+}
+
 /// Handling values.
 ///
 /// Values are either EBB arguments or instruction results.
@@ -134,24 +157,16 @@ impl DataFlowGraph {
     ///
     /// Find the original SSA value that `value` aliases.
     pub fn resolve_aliases(&self, value: Value) -> Value {
-        use ir::entities::ExpandedValue::Table;
-        let mut v = value;
-
-        for _ in 0..self.extended_values.len() {
-            v = match v.expand() {
-                Table(idx) => {
-                    match self.extended_values[idx] {
-                        ValueData::Alias { original, .. } => {
-                            // Follow alias values.
-                            original
-                        }
-                        _ => return v,
-                    }
-                }
-                _ => return v,
-            };
-        }
-        panic!("Value alias loop detected for {}", value);
+        resolve_aliases(&self.extended_values, value)
+    }
+
+    /// Resolve value aliases in the arguments to `inst`.
+    ///
+    /// Rewrite the value operands of `inst` so they refer directly to the original values instead
+    /// of going through aliases.
+    pub fn resolve_aliases_in_arguments(&mut self, inst: Inst) {
+        let values = &self.extended_values;
+        self.insts[inst].map_arguments(|v| resolve_aliases(values, v));
     }
 
     /// Resolve value copies.
@@ -596,6 +611,9 @@ mod tests {
 
         assert_eq!(dfg.value_def(val), ValueDef::Res(inst, 0));
         assert_eq!(dfg.value_type(val), types::I32);
+
+        // Direct values are never aliases, even when there are no table values.
+        assert_eq!(dfg.resolve_aliases(val), val);
     }
 
     #[test]
@@ -688,5 +706,13 @@ mod tests {
         assert_eq!(dfg.resolve_aliases(c3), c3);
         // But this goes through both copies and aliases.
         assert_eq!(dfg.resolve_copies(c3), c2);
+
+        // Rewrite the copy to use the original value instead of the alias.
+        let copy = match c3.expand() {
+            Direct(i) => i,
+            _ => panic!(),
+        };
+        dfg.resolve_aliases_in_arguments(copy);
+        assert_eq!(dfg[copy].typevar_operand(), Some(c2));
     }
 }
@@ -95,9 +95,24 @@ impl Default for Inst {
 
 
 /// An opaque reference to an SSA value.
-#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, PartialOrd, Ord)]
 pub struct Value(u32);
 
+/// Values can be used as keys in a secondary `EntityMap`.
+///
+/// The index of a value is its internal representation where direct and table values are
+/// interleaved, so a map keyed by values has about twice as many entries as there are values.
+impl EntityRef for Value {
+    fn new(index: usize) -> Self {
+        assert!(index < (u32::MAX as usize));
+        Value(index as u32)
+    }
+
+    fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
 /// Value references can either reference an instruction directly, or they can refer to the
 /// extended value table.
 pub enum ExpandedValue {
@@ -311,6 +326,68 @@ impl Default for SigRef {
     }
 }
 
+/// An opaque reference to a global variable.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct GlobalVar(u32);
+
+impl EntityRef for GlobalVar {
+    fn new(index: usize) -> GlobalVar {
+        assert!(index < (u32::MAX as usize));
+        GlobalVar(index as u32)
+    }
+
+    fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// Display a `GlobalVar` reference as "gv12".
+impl Display for GlobalVar {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        write!(fmt, "gv{}", self.0)
+    }
+}
+
+/// A guaranteed invalid global variable reference.
+pub const NO_GLOBAL_VAR: GlobalVar = GlobalVar(u32::MAX);
+
+impl Default for GlobalVar {
+    fn default() -> GlobalVar {
+        NO_GLOBAL_VAR
+    }
+}
+
+/// An opaque reference to a heap.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Heap(u32);
+
+impl EntityRef for Heap {
+    fn new(index: usize) -> Heap {
+        assert!(index < (u32::MAX as usize));
+        Heap(index as u32)
+    }
+
+    fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// Display a `Heap` reference as "heap12".
+impl Display for Heap {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        write!(fmt, "heap{}", self.0)
+    }
+}
+
+/// A guaranteed invalid heap reference.
+pub const NO_HEAP: Heap = Heap(u32::MAX);
+
+impl Default for Heap {
+    fn default() -> Heap {
+        NO_HEAP
+    }
+}
+
 /// A reference to any of the entities defined in this module.
 #[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
 pub enum AnyEntity {
@@ -330,6 +407,10 @@ pub enum AnyEntity {
     FuncRef(FuncRef),
     /// A function call signature.
     SigRef(SigRef),
+    /// A global variable.
+    GlobalVar(GlobalVar),
+    /// A heap.
+    Heap(Heap),
 }
 
 impl Display for AnyEntity {
@@ -343,6 +424,8 @@ impl Display for AnyEntity {
             AnyEntity::JumpTable(r) => r.fmt(fmt),
             AnyEntity::FuncRef(r) => r.fmt(fmt),
             AnyEntity::SigRef(r) => r.fmt(fmt),
+            AnyEntity::GlobalVar(r) => r.fmt(fmt),
+            AnyEntity::Heap(r) => r.fmt(fmt),
         }
     }
 }
@@ -389,6 +472,18 @@ impl From<SigRef> for AnyEntity {
     }
 }
 
+impl From<GlobalVar> for AnyEntity {
+    fn from(r: GlobalVar) -> AnyEntity {
+        AnyEntity::GlobalVar(r)
+    }
+}
+
+impl From<Heap> for AnyEntity {
+    fn from(r: Heap) -> AnyEntity {
+        AnyEntity::Heap(r)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
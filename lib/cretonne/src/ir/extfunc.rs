@@ -6,7 +6,9 @@
 //! This module declares the data types used to represent external functions and call signatures.
 
 use std::fmt::{self, Display, Formatter};
-use ir::{Type, FunctionName, SigRef};
+use std::str::FromStr;
+use ir::{Type, FunctionName, SigRef, ArgumentLoc};
+use isa::RegInfo;
 
 /// Function signature.
 ///
@@ -18,6 +20,8 @@ pub struct Signature {
     pub argument_types: Vec<ArgumentType>,
     /// Types returned from the function.
     pub return_types: Vec<ArgumentType>,
+    /// Calling convention.
+    pub call_conv: CallConv,
 }
 
 impl Signature {
@@ -26,36 +30,54 @@ impl Signature {
         Signature {
             argument_types: Vec::new(),
             return_types: Vec::new(),
+            call_conv: CallConv::Native,
         }
     }
+
+    /// Return an object that can display `self` with correct register names.
+    pub fn display<'a, R: Into<Option<&'a RegInfo>>>(&'a self, regs: R) -> DisplaySignature<'a> {
+        DisplaySignature(self, regs.into())
+    }
 }
 
-fn write_list(f: &mut Formatter, args: &Vec<ArgumentType>) -> fmt::Result {
+/// Wrapper type capable of displaying a `Signature` with correct register names.
+pub struct DisplaySignature<'a>(&'a Signature, Option<&'a RegInfo>);
+
+fn write_list(f: &mut Formatter, args: &[ArgumentType], regs: Option<&RegInfo>) -> fmt::Result {
     match args.split_first() {
         None => {}
         Some((first, rest)) => {
-            try!(write!(f, "{}", first));
+            try!(write!(f, "{}", first.display(regs)));
             for arg in rest {
-                try!(write!(f, ", {}", arg));
+                try!(write!(f, ", {}", arg.display(regs)));
             }
         }
     }
     Ok(())
 }
 
-impl Display for Signature {
+impl<'a> Display for DisplaySignature<'a> {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         try!(write!(f, "("));
-        try!(write_list(f, &self.argument_types));
+        try!(write_list(f, &self.0.argument_types, self.1));
         try!(write!(f, ")"));
-        if !self.return_types.is_empty() {
+        if !self.0.return_types.is_empty() {
             try!(write!(f, " -> "));
-            try!(write_list(f, &self.return_types));
+            try!(write_list(f, &self.0.return_types, self.1));
+        }
+        if self.0.call_conv != CallConv::Native {
+            try!(write!(f, " {}", self.0.call_conv));
         }
         Ok(())
     }
 }
 
+impl Display for Signature {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        self.display(None).fmt(f)
+    }
+}
+
 /// Function argument or return value type.
 ///
 /// This describes the value type being passed to or from a function along with flags that affect
@@ -68,6 +90,11 @@ pub struct ArgumentType {
     pub extension: ArgumentExtension,
     /// Place this argument in a register if possible.
     pub inreg: bool,
+    /// Special purpose of argument, or `Normal`.
+    pub purpose: ArgumentPurpose,
+    /// ABI-specific location of this argument, or `Unassigned` for arguments that have not yet
+    /// been legalized.
+    pub location: ArgumentLoc,
 }
 
 impl ArgumentType {
@@ -77,25 +104,50 @@ impl ArgumentType {
             value_type: vt,
             extension: ArgumentExtension::None,
             inreg: false,
+            purpose: ArgumentPurpose::Normal,
+            location: ArgumentLoc::Unassigned,
         }
     }
+
+    /// Return an object that can display `self` with correct register names.
+    pub fn display<'a, R: Into<Option<&'a RegInfo>>>(&'a self, regs: R) -> DisplayArgumentType<'a> {
+        DisplayArgumentType(self, regs.into())
+    }
 }
 
-impl Display for ArgumentType {
+/// Wrapper type capable of displaying an `ArgumentType` with correct register names.
+pub struct DisplayArgumentType<'a>(&'a ArgumentType, Option<&'a RegInfo>);
+
+impl<'a> Display for DisplayArgumentType<'a> {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
-        try!(write!(f, "{}", self.value_type));
-        match self.extension {
+        try!(write!(f, "{}", self.0.value_type));
+        match self.0.extension {
             ArgumentExtension::None => {}
             ArgumentExtension::Uext => try!(write!(f, " uext")),
             ArgumentExtension::Sext => try!(write!(f, " sext")),
         }
-        if self.inreg {
+        if self.0.inreg {
             try!(write!(f, " inreg"));
         }
+        match self.0.purpose {
+            ArgumentPurpose::Normal => {}
+            ArgumentPurpose::VMContext => try!(write!(f, " vmctx")),
+            ArgumentPurpose::Link => try!(write!(f, " link")),
+            ArgumentPurpose::CalleeSaved => try!(write!(f, " csr")),
+        }
+        if self.0.location.is_assigned() {
+            try!(write!(f, " [{}]", self.0.location.display(self.1)));
+        }
         Ok(())
     }
 }
 
+impl Display for ArgumentType {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        self.display(None).fmt(f)
+    }
+}
+
 /// Function argument extension options.
 ///
 /// On some architectures, small integer function arguments are extended to the width of a
@@ -110,6 +162,78 @@ pub enum ArgumentExtension {
     Sext,
 }
 
+/// Calling convention identifiers.
+///
+/// The calling convention determines how arguments and return values are passed. The ISA maps
+/// each calling convention to concrete `ArgumentLoc` assignments when legalizing a signature.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum CallConv {
+    /// The default calling convention for the target ISA.
+    Native,
+    /// A calling convention optimized for speed, for calls between Cretonne functions.
+    Fast,
+    /// A calling convention optimized for rarely called functions such as error paths.
+    Cold,
+    /// The System V ABI calling convention.
+    SystemV,
+}
+
+impl Display for CallConv {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        use self::CallConv::*;
+        f.write_str(match *self {
+            Native => "native",
+            Fast => "fast",
+            Cold => "cold",
+            SystemV => "system_v",
+        })
+    }
+}
+
+impl FromStr for CallConv {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<CallConv, ()> {
+        use self::CallConv::*;
+        match s {
+            "native" => Ok(Native),
+            "fast" => Ok(Fast),
+            "cold" => Ok(Cold),
+            "system_v" => Ok(SystemV),
+            _ => Err(()),
+        }
+    }
+}
+
+/// The special purpose of a function argument.
+///
+/// Function arguments and return values are used to pass user program values between functions,
+/// but they are also used to represent special registers with significance to the ABI.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum ArgumentPurpose {
+    /// A normal user program value passed to or from a function.
+    Normal,
+    /// A VM context pointer.
+    ///
+    /// This is a pointer to a context struct containing details about the current sandbox. It is
+    /// used as a base pointer for `vmctx` global variables.
+    VMContext,
+
+    /// The return address of the function.
+    ///
+    /// On ISAs where a call instruction leaves the return address in a register, the register must
+    /// be saved by the prologue of a function that makes calls of its own. It is represented as an
+    /// extra argument and return value of the function.
+    Link,
+
+    /// A callee-saved register.
+    ///
+    /// Callee-saved registers that are used by a function are saved by the prologue and restored
+    /// by the epilogue. They are represented as extra arguments and return values of the function
+    /// so the saved value flows from the entry EBB to the `return` instructions.
+    CalleeSaved,
+}
+
 /// An external function.
 ///
 /// Information about a function that can be called directly with a direct `call` instruction.
@@ -130,6 +254,7 @@ impl Display for ExtFuncData {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use ir::ArgumentLoc;
     use ir::types::{I32, F32, B8};
 
     #[test]
@@ -140,6 +265,14 @@ mod tests {
         assert_eq!(t.to_string(), "i32 uext");
         t.inreg = true;
         assert_eq!(t.to_string(), "i32 uext inreg");
+        t.purpose = ArgumentPurpose::VMContext;
+        assert_eq!(t.to_string(), "i32 uext inreg vmctx");
+        t.location = ArgumentLoc::Reg(10);
+        assert_eq!(t.to_string(), "i32 uext inreg vmctx [%10]");
+        t.location = ArgumentLoc::Stack(-8);
+        assert_eq!(t.to_string(), "i32 uext inreg vmctx [-8]");
+        t.purpose = ArgumentPurpose::CalleeSaved;
+        assert_eq!(t.to_string(), "i32 uext inreg csr [-8]");
     }
 
     #[test]
@@ -154,5 +287,9 @@ mod tests {
         assert_eq!(sig.to_string(), "(i32, i32x4) -> f32");
         sig.return_types.push(ArgumentType::new(B8));
         assert_eq!(sig.to_string(), "(i32, i32x4) -> f32, b8");
+        sig.call_conv = CallConv::SystemV;
+        assert_eq!(sig.to_string(), "(i32, i32x4) -> f32, b8 system_v");
+        assert_eq!("system_v".parse(), Ok(CallConv::SystemV));
+        assert_eq!("fastest".parse::<CallConv>(), Err(()));
     }
 }
@@ -4,9 +4,11 @@
 //! instructions.
 
 use std::fmt::{self, Display, Debug, Formatter};
-use ir::{FunctionName, Signature, Inst, StackSlot, StackSlotData, JumpTable, JumpTableData,
-         DataFlowGraph, Layout};
+use ir::{FunctionName, Signature, ArgumentPurpose, Inst, Value, StackSlot, StackSlotData,
+         JumpTable, JumpTableData, GlobalVar, GlobalVarData, Heap, HeapData, DataFlowGraph,
+         Layout, ValueLoc, Ebb};
 use isa::Encoding;
+use binemit::CodeOffset;
 use entity_map::{EntityMap, PrimaryEntityData};
 use write::write_function;
 
@@ -25,6 +27,12 @@ pub struct Function {
     /// Stack slots allocated in this function.
     pub stack_slots: EntityMap<StackSlot, StackSlotData>,
 
+    /// Global variables referenced.
+    pub global_vars: EntityMap<GlobalVar, GlobalVarData>,
+
+    /// Heaps referenced.
+    pub heaps: EntityMap<Heap, HeapData>,
+
     /// Jump tables used in this function.
     pub jump_tables: EntityMap<JumpTable, JumpTableData>,
 
@@ -37,10 +45,30 @@ pub struct Function {
     /// Encoding recipe and bits for the legal instructions.
     /// Illegal instructions have the `Encoding::default()` value.
     pub encodings: EntityMap<Inst, Encoding>,
+
+    /// Location assigned to every value by the register allocator.
+    /// Values that have not been assigned a location have the `ValueLoc::Unassigned` value.
+    pub locations: EntityMap<Value, ValueLoc>,
+
+    /// Code offsets of the EBB headers.
+    ///
+    /// This information is only transiently available after the `binemit::relax_branches` function
+    /// computes it, and it can easily be recomputed by calling that function. It is not included
+    /// in the textual IL format.
+    pub offsets: EntityMap<Ebb, CodeOffset>,
+
+    /// Size of the stack frame allocated by the prologue, in bytes.
+    ///
+    /// This is computed by `insert_prologue_epilogue()` when the stack slots have been laid out.
+    /// Binary emission uses it to compute stack slot offsets relative to the stack pointer. It is
+    /// not included in the textual IL format.
+    pub frame_size: Option<u32>,
 }
 
 impl PrimaryEntityData for StackSlotData {}
 impl PrimaryEntityData for JumpTableData {}
+impl PrimaryEntityData for GlobalVarData {}
+impl PrimaryEntityData for HeapData {}
 
 impl Function {
     /// Create a function with the given name and signature.
@@ -49,10 +77,15 @@ impl Function {
             name: name,
             signature: sig,
             stack_slots: EntityMap::new(),
+            global_vars: EntityMap::new(),
+            heaps: EntityMap::new(),
             jump_tables: EntityMap::new(),
             dfg: DataFlowGraph::new(),
             layout: Layout::new(),
             encodings: EntityMap::new(),
+            locations: EntityMap::new(),
+            offsets: EntityMap::new(),
+            frame_size: None,
         }
     }
 
@@ -65,6 +98,23 @@ impl Function {
     pub fn own_signature(&self) -> &Signature {
         &self.signature
     }
+
+    /// Get a mutable reference to the signature of this function.
+    pub fn own_signature_mut(&mut self) -> &mut Signature {
+        &mut self.signature
+    }
+
+    /// Find the entry block argument with the special `purpose`, if any.
+    ///
+    /// Return `None` if the signature has no such argument or the function has no entry block.
+    pub fn special_arg(&self, purpose: ArgumentPurpose) -> Option<Value> {
+        let args = &self.signature.argument_types;
+        let idx = match args.iter().position(|arg| arg.purpose == purpose) {
+            Some(idx) => idx,
+            None => return None,
+        };
+        self.layout.entry_block().and_then(|entry| self.dfg.ebb_args(entry).nth(idx))
+    }
 }
 
 impl Display for Function {
@@ -0,0 +1,69 @@
+//! Global variables.
+//!
+//! A global variable is declared in the function preamble and assigned an
+//! `ir::entities::GlobalVar` reference. Its value is an address that can be computed with the
+//! `global_addr` instruction.
+
+use ir::immediates::Offset32;
+use ir::{GlobalVar, FunctionName};
+use std::fmt::{self, Display, Formatter};
+
+/// Information about a global variable declaration.
+#[derive(Clone, Debug)]
+pub enum GlobalVarData {
+    /// Variable is part of the VM context struct, its address is a constant offset from the VM
+    /// context pointer.
+    VmCtx {
+        /// Offset from the `vmctx` pointer to this global.
+        offset: Offset32,
+    },
+
+    /// Variable is part of a struct pointed to by another global variable.
+    ///
+    /// The `base` global variable is assumed to contain a pointer to a struct. This global
+    /// variable lives at an offset into the struct.
+    Deref {
+        /// The base pointer global variable.
+        base: GlobalVar,
+
+        /// Byte offset to be added to the pointer loaded from `base`.
+        offset: Offset32,
+    },
+
+    /// Variable is at an address identified by a symbolic name. Cretonne itself does not interpret
+    /// this name; it's used by embedders to link with other data structures.
+    Sym {
+        /// The symbolic name.
+        name: FunctionName,
+    },
+}
+
+impl Display for GlobalVarData {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match *self {
+            GlobalVarData::VmCtx { offset } => write!(f, "vmctx{}", offset),
+            GlobalVarData::Deref { base, offset } => write!(f, "deref({}){}", base, offset),
+            GlobalVarData::Sym { ref name } => write!(f, "globalsym {}", name),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::GlobalVarData;
+    use ir::{Function, FunctionName};
+
+    #[test]
+    fn display() {
+        let mut func = Function::new();
+        let gv0 = func.global_vars.push(GlobalVarData::VmCtx { offset: 0.into() });
+        let gv1 = func.global_vars.push(GlobalVarData::Deref {
+            base: gv0,
+            offset: (-8).into(),
+        });
+        let gv2 = func.global_vars.push(GlobalVarData::Sym { name: FunctionName::new("foo") });
+        assert_eq!(func.global_vars[gv0].to_string(), "vmctx");
+        assert_eq!(func.global_vars[gv1].to_string(), "deref(gv0)-8");
+        assert_eq!(func.global_vars[gv2].to_string(), "globalsym foo");
+    }
+}
@@ -0,0 +1,99 @@
+//! Heaps.
+//!
+//! A heap is a linear memory region declared in the function preamble and assigned an
+//! `ir::entities::Heap` reference. Heaps are accessed through addresses computed by the
+//! `heap_addr` instruction which performs the bounds checking required by the heap style.
+
+use ir::immediates::Imm64;
+use ir::GlobalVar;
+use std::fmt::{self, Display, Formatter};
+
+/// Information about a heap declaration.
+#[derive(Clone, Debug)]
+pub struct HeapData {
+    /// Method for determining the heap base address.
+    pub base: HeapBase,
+
+    /// Size in bytes of the guard pages following the heap. Accessing the guard pages traps.
+    pub guard_size: Imm64,
+
+    /// Heap style, with additional style-specific info.
+    pub style: HeapStyle,
+}
+
+/// Method for determining the base address of a heap.
+#[derive(Clone, Copy, Debug)]
+pub enum HeapBase {
+    /// The heap base address is stored in a global variable.
+    GlobalVar(GlobalVar),
+}
+
+/// Style of heap including style-specific information.
+#[derive(Clone, Copy, Debug)]
+pub enum HeapStyle {
+    /// A dynamic heap can be relocated to a different base address when it is grown. Accesses are
+    /// checked against a bound that is loaded from a global variable.
+    Dynamic {
+        /// Global variable holding the current bound of the heap in bytes.
+        bound_gv: GlobalVar,
+    },
+
+    /// A static heap has a fixed base address and a number of not-yet-allocated pages before the
+    /// guard pages.
+    Static {
+        /// Heap bound in bytes. The guard pages are allocated after the bound.
+        bound: Imm64,
+    },
+}
+
+impl Display for HeapData {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        try!(write!(f,
+                    "{}",
+                    match self.style {
+                        HeapStyle::Dynamic { .. } => "dynamic",
+                        HeapStyle::Static { .. } => "static",
+                    }));
+
+        match self.base {
+            HeapBase::GlobalVar(gv) => try!(write!(f, " {}", gv)),
+        }
+
+        match self.style {
+            HeapStyle::Dynamic { bound_gv } => try!(write!(f, ", bound {}", bound_gv)),
+            HeapStyle::Static { bound } => try!(write!(f, ", bound {}", bound)),
+        }
+
+        write!(f, ", guard {}", self.guard_size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{HeapData, HeapBase, HeapStyle};
+    use ir::{Function, GlobalVarData};
+    use ir::immediates::Imm64;
+
+    #[test]
+    fn display() {
+        let mut func = Function::new();
+        let gv0 = func.global_vars.push(GlobalVarData::VmCtx { offset: 0.into() });
+        let gv1 = func.global_vars.push(GlobalVarData::VmCtx { offset: 8.into() });
+
+        let heap = HeapData {
+            base: HeapBase::GlobalVar(gv0),
+            guard_size: Imm64::new(0x8000_0000),
+            style: HeapStyle::Static { bound: Imm64::new(0x1_0000_0000) },
+        };
+        assert_eq!(heap.to_string(),
+                   "static gv0, bound 0x0001_0000_0000, guard 0x8000_0000");
+
+        let heap = HeapData {
+            base: HeapBase::GlobalVar(gv0),
+            guard_size: Imm64::new(0x1000),
+            style: HeapStyle::Dynamic { bound_gv: gv1 },
+        };
+        assert_eq!(heap.to_string(), "dynamic gv0, bound gv1, guard 4096");
+        assert_eq!(func.global_vars[gv1].to_string(), "vmctx+8");
+    }
+}
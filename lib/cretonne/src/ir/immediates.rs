@@ -135,6 +135,78 @@ impl FromStr for Imm64 {
 /// This is used to indicate lane indexes typically.
 pub type Uimm8 = u8;
 
+/// 32-bit unsigned integer immediate operand.
+///
+/// This is used to represent sizes of memory objects.
+pub type Uimm32 = u32;
+
+/// 32-bit signed immediate offset.
+///
+/// This is used to encode an immediate offset for load/store instructions. All supported ISAs have
+/// a maximum load/store offset that fits in an `i32`.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub struct Offset32(i32);
+
+impl Offset32 {
+    /// Create a new `Offset32` representing the signed number `x`.
+    pub fn new(x: i32) -> Offset32 {
+        Offset32(x)
+    }
+}
+
+impl Into<i32> for Offset32 {
+    fn into(self) -> i32 {
+        self.0
+    }
+}
+
+impl Into<i64> for Offset32 {
+    fn into(self) -> i64 {
+        self.0 as i64
+    }
+}
+
+impl From<i32> for Offset32 {
+    fn from(x: i32) -> Self {
+        Offset32(x)
+    }
+}
+
+impl Display for Offset32 {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        // 0 displays as an empty offset.
+        if self.0 == 0 {
+            return Ok(());
+        }
+
+        // Always include a sign.
+        try!(write!(f, "{}", if self.0 < 0 { '-' } else { '+' }));
+
+        // Use the same formatting as `Imm64` for the magnitude.
+        write!(f, "{}", Imm64(self.0.wrapping_abs() as u32 as i64))
+    }
+}
+
+impl FromStr for Offset32 {
+    type Err = &'static str;
+
+    // Parse a decimal or hexadecimal `Offset32`, formatted as above.
+    fn from_str(s: &str) -> Result<Offset32, &'static str> {
+        if !(s.starts_with('-') || s.starts_with('+')) {
+            return Err("Offset must begin with sign");
+        }
+        let magnitude: i64 = try!(Imm64::from_str(&s[1..])).into();
+        if magnitude < 0 {
+            return Err("Invalid offset");
+        }
+        let value = if s.starts_with('-') { -magnitude } else { magnitude };
+        if value < i32::min_value() as i64 || value > i32::max_value() as i64 {
+            return Err("Offset out of range");
+        }
+        Ok(Offset32(value as i32))
+    }
+}
+
 /// An IEEE binary32 immediate floating point value.
 ///
 /// All bit patterns are allowed.
@@ -526,6 +598,36 @@ mod tests {
                            "Too many hexadecimal digits in Imm64");
     }
 
+    #[test]
+    fn format_offset32() {
+        assert_eq!(Offset32(0).to_string(), "");
+        assert_eq!(Offset32(1).to_string(), "+1");
+        assert_eq!(Offset32(-1).to_string(), "-1");
+        assert_eq!(Offset32(9999).to_string(), "+9999");
+        assert_eq!(Offset32(10000).to_string(), "+0x2710");
+        assert_eq!(Offset32(-9999).to_string(), "-9999");
+        assert_eq!(Offset32(-10000).to_string(), "-0x2710");
+        assert_eq!(Offset32(0xffff).to_string(), "+0xffff");
+        assert_eq!(Offset32(0x10000).to_string(), "+0x0001_0000");
+        assert_eq!(Offset32(i32::min_value()).to_string(), "-0x8000_0000");
+    }
+
+    #[test]
+    fn parse_offset32() {
+        parse_ok::<Offset32>("+0", "");
+        parse_ok::<Offset32>("+1", "+1");
+        parse_ok::<Offset32>("-0", "");
+        parse_ok::<Offset32>("-1", "-1");
+        parse_ok::<Offset32>("+0x0", "");
+        parse_ok::<Offset32>("+0xf", "+15");
+        parse_ok::<Offset32>("-0x9", "-9");
+        parse_ok::<Offset32>("-0x8000_0000", "-0x8000_0000");
+
+        parse_err::<Offset32>("+0x8000_0000", "Offset out of range");
+        parse_err::<Offset32>("1", "Offset must begin with sign");
+        parse_err::<Offset32>("+-1", "Invalid offset");
+    }
+
     #[test]
     fn format_ieee32() {
         assert_eq!(Ieee32::new(0.0).to_string(), "0.0");
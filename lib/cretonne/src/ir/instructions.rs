@@ -10,9 +10,11 @@ use std::fmt::{self, Display, Formatter};
 use std::str::FromStr;
 use std::ops::{Deref, DerefMut};
 
-use ir::{Value, Type, Ebb, JumpTable, SigRef, FuncRef};
-use ir::immediates::{Imm64, Uimm8, Ieee32, Ieee64, ImmVector};
+use ir::{Value, Type, Ebb, JumpTable, SigRef, FuncRef, StackSlot, GlobalVar, Heap, MemFlags,
+         DataFlowGraph};
+use ir::immediates::{Imm64, Uimm8, Uimm32, Offset32, Ieee32, Ieee64, ImmVector};
 use ir::condcodes::*;
+use ir::atomics::*;
 use ir::types;
 
 // Include code generated by `lib/cretonne/meta/gen_instr.py`. This file contains:
@@ -186,6 +188,75 @@ pub enum InstructionData {
         cond: FloatCC,
         args: [Value; 2],
     },
+    Load {
+        opcode: Opcode,
+        ty: Type,
+        flags: MemFlags,
+        arg: Value,
+        offset: Offset32,
+    },
+    Store {
+        opcode: Opcode,
+        ty: Type,
+        flags: MemFlags,
+        args: [Value; 2],
+        offset: Offset32,
+    },
+    AtomicRmw {
+        opcode: Opcode,
+        ty: Type,
+        op: AtomicRmwOp,
+        ordering: AtomicOrdering,
+        args: [Value; 2],
+    },
+    AtomicCas {
+        opcode: Opcode,
+        ty: Type,
+        ordering: AtomicOrdering,
+        args: [Value; 3],
+    },
+    AtomicLoad {
+        opcode: Opcode,
+        ty: Type,
+        ordering: AtomicOrdering,
+        arg: Value,
+    },
+    AtomicStore {
+        opcode: Opcode,
+        ty: Type,
+        ordering: AtomicOrdering,
+        args: [Value; 2],
+    },
+    Fence {
+        opcode: Opcode,
+        ty: Type,
+        ordering: AtomicOrdering,
+    },
+    StackLoad {
+        opcode: Opcode,
+        ty: Type,
+        stack_slot: StackSlot,
+        offset: Offset32,
+    },
+    StackStore {
+        opcode: Opcode,
+        ty: Type,
+        arg: Value,
+        stack_slot: StackSlot,
+        offset: Offset32,
+    },
+    UnaryGlobalVar {
+        opcode: Opcode,
+        ty: Type,
+        global_var: GlobalVar,
+    },
+    HeapAddr {
+        opcode: Opcode,
+        ty: Type,
+        heap: Heap,
+        arg: Value,
+        imm: Uimm32,
+    },
     Jump {
         opcode: Opcode,
         ty: Type,
@@ -196,6 +267,11 @@ pub enum InstructionData {
         ty: Type,
         data: Box<BranchData>,
     },
+    BranchIcmp {
+        opcode: Opcode,
+        ty: Type,
+        data: Box<BranchIcmpData>,
+    },
     BranchTable {
         opcode: Opcode,
         ty: Type,
@@ -349,6 +425,35 @@ impl Display for BranchData {
     }
 }
 
+/// Payload data for branch instructions that compare two integer values. These need to carry
+/// lists of EBB arguments that won't fit in the allowed InstructionData size.
+#[derive(Clone, Debug)]
+pub struct BranchIcmpData {
+    /// Condition code for the comparison.
+    pub cond: IntCC,
+    /// Value arguments compared by the branch.
+    pub args: [Value; 2],
+    /// Branch destination EBB.
+    pub destination: Ebb,
+    /// Arguments passed to destination EBB.
+    pub varargs: VariableArgs,
+}
+
+impl Display for BranchIcmpData {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        try!(write!(f,
+                    "{}, {}, {}, {}",
+                    self.cond,
+                    self.args[0],
+                    self.args[1],
+                    self.destination));
+        if !self.varargs.is_empty() {
+            try!(write!(f, "({})", self.varargs));
+        }
+        Ok(())
+    }
+}
+
 /// Payload of a call instruction.
 #[derive(Clone, Debug)]
 pub struct CallData {
@@ -396,6 +501,9 @@ impl InstructionData {
             &InstructionData::Branch { ref data, .. } => {
                 BranchInfo::SingleDest(data.destination, &data.varargs)
             }
+            &InstructionData::BranchIcmp { ref data, .. } => {
+                BranchInfo::SingleDest(data.destination, &data.varargs)
+            }
             &InstructionData::BranchTable { table, .. } => BranchInfo::Table(table),
             _ => BranchInfo::NotABranch,
         }
@@ -425,6 +533,87 @@ impl InstructionData {
             _ => false,
         }
     }
+
+    /// Get the controlling type variable, or `VOID` if this instruction isn't polymorphic.
+    ///
+    /// Most polymorphic instructions produce a result of the controlling type, but some, like
+    /// `icmp`, infer the controlling type variable from the type of an input operand in `dfg`.
+    pub fn ctrl_typevar(&self, dfg: &DataFlowGraph) -> Type {
+        let constraints = self.opcode().constraints();
+        if !constraints.is_polymorphic() {
+            types::VOID
+        } else if constraints.use_typevar_operand() {
+            let arg = self.typevar_operand().expect("Instruction format has no typevar operand");
+            dfg.value_type(arg)
+        } else {
+            self.first_type()
+        }
+    }
+
+    /// Replace every value operand of this instruction with `f(operand)`.
+    ///
+    /// This visits both the fixed value operands and any variable arguments.
+    pub fn map_arguments<F>(&mut self, mut f: F)
+        where F: FnMut(Value) -> Value
+    {
+        fn map_all<F: FnMut(Value) -> Value>(args: &mut [Value], f: &mut F) {
+            for arg in args {
+                *arg = f(*arg);
+            }
+        }
+
+        match *self {
+            InstructionData::Nullary { .. } |
+            InstructionData::UnaryImm { .. } |
+            InstructionData::UnaryIeee32 { .. } |
+            InstructionData::UnaryIeee64 { .. } |
+            InstructionData::UnaryImmVector { .. } |
+            InstructionData::StackLoad { .. } |
+            InstructionData::Fence { .. } |
+            InstructionData::UnaryGlobalVar { .. } => {}
+
+            InstructionData::Unary { ref mut arg, .. } |
+            InstructionData::UnarySplit { ref mut arg, .. } |
+            InstructionData::BinaryImm { ref mut arg, .. } |
+            InstructionData::BinaryImmRev { ref mut arg, .. } |
+            InstructionData::ExtractLane { ref mut arg, .. } |
+            InstructionData::Load { ref mut arg, .. } |
+            InstructionData::AtomicLoad { ref mut arg, .. } |
+            InstructionData::StackStore { ref mut arg, .. } |
+            InstructionData::HeapAddr { ref mut arg, .. } |
+            InstructionData::BranchTable { ref mut arg, .. } => *arg = f(*arg),
+
+            InstructionData::Binary { ref mut args, .. } |
+            InstructionData::BinaryOverflow { ref mut args, .. } |
+            InstructionData::InsertLane { ref mut args, .. } |
+            InstructionData::IntCompare { ref mut args, .. } |
+            InstructionData::FloatCompare { ref mut args, .. } |
+            InstructionData::Store { ref mut args, .. } |
+            InstructionData::AtomicRmw { ref mut args, .. } |
+            InstructionData::AtomicStore { ref mut args, .. } => map_all(args, &mut f),
+
+            InstructionData::Ternary { ref mut args, .. } |
+            InstructionData::AtomicCas { ref mut args, .. } => map_all(args, &mut f),
+            InstructionData::TernaryOverflow { ref mut data, .. } => {
+                map_all(&mut data.args, &mut f)
+            }
+            InstructionData::Jump { ref mut data, .. } => map_all(&mut data.varargs, &mut f),
+            InstructionData::Branch { ref mut data, .. } => {
+                data.arg = f(data.arg);
+                map_all(&mut data.varargs, &mut f);
+            }
+            InstructionData::BranchIcmp { ref mut data, .. } => {
+                map_all(&mut data.args, &mut f);
+                map_all(&mut data.varargs, &mut f);
+            }
+            InstructionData::Call { ref mut data, .. } => map_all(&mut data.varargs, &mut f),
+            InstructionData::IndirectCall { ref mut data, .. } => {
+                data.arg = f(data.arg);
+                map_all(&mut data.varargs, &mut f);
+            }
+            InstructionData::Return { ref mut data, .. } => map_all(&mut data.varargs, &mut f),
+        }
+    }
 }
 
 /// Information about branch and jump instructions.
@@ -611,6 +800,12 @@ enum OperandConstraint {
 
     /// This operand is `ctrlType.double_width()`.
     DoubleWidth,
+
+    /// This operand is `ctrlType.half_vector()`.
+    HalfVector,
+
+    /// This operand is `ctrlType.double_vector()`.
+    DoubleVector,
 }
 
 impl OperandConstraint {
@@ -628,6 +823,10 @@ impl OperandConstraint {
             AsBool => Some(ctrl_type.as_bool()),
             HalfWidth => Some(ctrl_type.half_width().expect("invalid type for half_width")),
             DoubleWidth => Some(ctrl_type.double_width().expect("invalid type for double_width")),
+            HalfVector => Some(ctrl_type.half_vector().expect("invalid type for half_vector")),
+            DoubleVector => {
+                Some(ctrl_type.double_vector().expect("invalid type for double_vector"))
+            }
         }
     }
 }
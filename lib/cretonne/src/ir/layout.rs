@@ -234,6 +234,33 @@ impl Layout {
         }
     }
 
+    /// Remove `inst` from the layout.
+    pub fn remove_inst(&mut self, inst: Inst) {
+        let ebb = self.inst_ebb(inst).expect("Instruction already removed.");
+        // Clear the `inst` node and extract links.
+        let prev;
+        let next;
+        {
+            let n = &mut self.insts[inst];
+            prev = n.prev;
+            next = n.next;
+            n.ebb = NO_EBB;
+            n.prev = NO_INST;
+            n.next = NO_INST;
+        }
+        // Fix up links to `inst`.
+        if prev == NO_INST {
+            self.ebbs[ebb].first_inst = next;
+        } else {
+            self.insts[prev].next = next;
+        }
+        if next == NO_INST {
+            self.ebbs[ebb].last_inst = prev;
+        } else {
+            self.insts[next].prev = prev;
+        }
+    }
+
     /// Iterate over the instructions in `ebb` in layout order.
     pub fn ebb_insts<'f>(&'f self, ebb: Ebb) -> Insts<'f> {
         Insts {
@@ -381,6 +408,13 @@ impl<'f> Cursor<'f> {
         self.pos
     }
 
+    /// Move the cursor to a new position.
+    ///
+    /// This is typically used to return to a position previously saved with `position()`.
+    pub fn set_position(&mut self, pos: CursorPosition) {
+        self.pos = pos;
+    }
+
     /// Get the EBB corresponding to the current position.
     pub fn current_ebb(&self) -> Option<Ebb> {
         use self::CursorPosition::*;
@@ -909,6 +943,17 @@ mod tests {
 
         layout.insert_inst(i0, i1);
         verify(&mut layout, &[(e1, &[i2, i0, i1])]);
+
+        layout.remove_inst(i0);
+        assert_eq!(layout.inst_ebb(i0), None);
+        verify(&mut layout, &[(e1, &[i2, i1])]);
+
+        layout.remove_inst(i1);
+        verify(&mut layout, &[(e1, &[i2])]);
+
+        // A removed instruction can be inserted again.
+        layout.append_inst(i1, e1);
+        verify(&mut layout, &[(e1, &[i2, i1])]);
     }
 
     #[test]
@@ -3,25 +3,35 @@
 pub mod types;
 pub mod entities;
 pub mod condcodes;
+pub mod atomics;
 pub mod immediates;
 pub mod instructions;
 pub mod stackslot;
 pub mod jumptable;
+pub mod globalvar;
+pub mod heap;
 pub mod dfg;
 pub mod layout;
 pub mod function;
+pub mod valueloc;
 mod funcname;
 mod extfunc;
+mod memflags;
 mod builder;
 
 pub use ir::funcname::FunctionName;
-pub use ir::extfunc::{Signature, ArgumentType, ArgumentExtension, ExtFuncData};
+pub use ir::extfunc::{Signature, CallConv, ArgumentType, ArgumentExtension, ArgumentPurpose,
+                      ExtFuncData};
+pub use ir::memflags::MemFlags;
 pub use ir::types::Type;
-pub use ir::entities::{Ebb, Inst, Value, StackSlot, JumpTable, FuncRef, SigRef};
+pub use ir::entities::{Ebb, Inst, Value, StackSlot, JumpTable, FuncRef, SigRef, GlobalVar, Heap};
 pub use ir::instructions::{Opcode, InstructionData, VariableArgs};
-pub use ir::stackslot::StackSlotData;
+pub use ir::stackslot::{StackSlotData, StackSlotKind};
 pub use ir::jumptable::JumpTableData;
+pub use ir::globalvar::GlobalVarData;
+pub use ir::heap::{HeapData, HeapBase, HeapStyle};
 pub use ir::dfg::{DataFlowGraph, ValueDef};
 pub use ir::layout::{Layout, Cursor};
 pub use ir::function::Function;
+pub use ir::valueloc::{ArgumentLoc, ValueLoc};
 pub use ir::builder::InstBuilder;
@@ -4,42 +4,141 @@
 //!
 
 use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+/// The kind of a stack slot.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StackSlotKind {
+    /// A local variable in the function's stack frame.
+    Local,
+
+    /// A spill slot for a value that couldn't be kept in a register. Spill slots are created by
+    /// the register allocator.
+    SpillSlot,
+
+    /// An incoming function argument passed on the stack by the caller.
+    ///
+    /// If the current function has more arguments than fit in registers, the remaining arguments
+    /// are passed on the stack by the caller. These incoming arguments are represented as stack
+    /// slots with assigned offsets relative to the stack pointer on entry.
+    IncomingArg,
+
+    /// An outgoing function argument.
+    ///
+    /// When preparing to call a function whose arguments don't fit in registers, outgoing argument
+    /// stack slots are used to represent individual arguments in the outgoing call frame. Their
+    /// offsets are relative to the stack pointer on entry to the callee until the stack frame is
+    /// laid out.
+    OutgoingArg,
+}
+
+impl FromStr for StackSlotKind {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<StackSlotKind, ()> {
+        use self::StackSlotKind::*;
+        match s {
+            "local" => Ok(Local),
+            "spill" => Ok(SpillSlot),
+            "incoming_arg" => Ok(IncomingArg),
+            "outgoing_arg" => Ok(OutgoingArg),
+            _ => Err(()),
+        }
+    }
+}
+
+impl Display for StackSlotKind {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        use self::StackSlotKind::*;
+        f.write_str(match *self {
+            Local => "local",
+            SpillSlot => "spill",
+            IncomingArg => "incoming_arg",
+            OutgoingArg => "outgoing_arg",
+        })
+    }
+}
 
 /// Contents of a stack slot.
 #[derive(Clone, Debug)]
 pub struct StackSlotData {
+    /// The kind of stack slot.
+    pub kind: StackSlotKind,
+
     /// Size of stack slot in bytes.
     pub size: u32,
+
+    /// Required alignment of the stack slot in bytes, a power of two.
+    ///
+    /// When `None`, an appropriate alignment is picked based on the size of the stack slot.
+    pub align: Option<u32>,
+
+    /// Offset of stack slot relative to the stack pointer on entry to the function, once it has
+    /// been assigned.
+    ///
+    /// Incoming arguments are at non-negative offsets, while the function's own slots are at
+    /// negative offsets below the return address.
+    pub offset: Option<i32>,
 }
 
 impl StackSlotData {
-    /// Create a stack slot with the specified byte size.
-    pub fn new(size: u32) -> StackSlotData {
-        StackSlotData { size: size }
+    /// Create a stack slot with the specified kind and byte size.
+    pub fn new(kind: StackSlotKind, size: u32) -> StackSlotData {
+        StackSlotData {
+            kind: kind,
+            size: size,
+            align: None,
+            offset: None,
+        }
     }
 }
 
 impl Display for StackSlotData {
     fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
-        write!(fmt, "stack_slot {}", self.size)
+        try!(write!(fmt, "{} {}", self.kind, self.size));
+        if let Some(align) = self.align {
+            try!(write!(fmt, ", align {}", align));
+        }
+        if let Some(offset) = self.offset {
+            try!(write!(fmt, ", offset {}", offset));
+        }
+        Ok(())
     }
 }
 
 #[cfg(test)]
 mod tests {
     use ir::Function;
-    use super::StackSlotData;
+    use super::{StackSlotData, StackSlotKind};
 
     #[test]
     fn stack_slot() {
         let mut func = Function::new();
 
-        let ss0 = func.stack_slots.push(StackSlotData::new(4));
-        let ss1 = func.stack_slots.push(StackSlotData::new(8));
+        let ss0 = func.stack_slots.push(StackSlotData::new(StackSlotKind::IncomingArg, 4));
+        let ss1 = func.stack_slots.push(StackSlotData::new(StackSlotKind::SpillSlot, 8));
         assert_eq!(ss0.to_string(), "ss0");
         assert_eq!(ss1.to_string(), "ss1");
 
         assert_eq!(func.stack_slots[ss0].size, 4);
         assert_eq!(func.stack_slots[ss1].size, 8);
+
+        assert_eq!(func.stack_slots[ss0].to_string(), "incoming_arg 4");
+        assert_eq!(func.stack_slots[ss1].to_string(), "spill 8");
+
+        func.stack_slots[ss1].align = Some(8);
+        func.stack_slots[ss1].offset = Some(-32);
+        assert_eq!(func.stack_slots[ss1].to_string(), "spill 8, align 8, offset -32");
+    }
+
+    #[test]
+    fn kinds() {
+        for &kind in &[StackSlotKind::Local,
+                       StackSlotKind::SpillSlot,
+                       StackSlotKind::IncomingArg,
+                       StackSlotKind::OutgoingArg] {
+            assert_eq!(kind.to_string().parse(), Ok(kind));
+        }
+        assert_eq!("stack_slot".parse::<StackSlotKind>(), Err(()));
     }
 }
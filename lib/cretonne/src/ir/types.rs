@@ -186,6 +186,11 @@ impl Type {
         self.lane_bits() as u16 * self.lane_count()
     }
 
+    /// Get the number of bytes used to store this type in memory.
+    pub fn bytes(self) -> u32 {
+        (self.bits() as u32 + 7) / 8
+    }
+
     /// Get a SIMD vector type with `n` times more lanes than this one.
     ///
     /// If this is a scalar type, this produces a SIMD type with this as a lane type and `n` lanes.
@@ -214,6 +219,13 @@ impl Type {
         }
     }
 
+    /// Get a SIMD vector with twice the number of lanes.
+    ///
+    /// Scalar types are treated as vectors with one lane.
+    pub fn double_vector(self) -> Option<Type> {
+        self.by(2)
+    }
+
     /// Index of this type, for use with hash tables etc.
     pub fn index(self) -> usize {
         self.0 as usize
@@ -270,6 +282,10 @@ mod tests {
     fn basic_scalars() {
         assert_eq!(VOID, VOID.lane_type());
         assert_eq!(0, VOID.bits());
+        assert_eq!(0, VOID.bytes());
+        assert_eq!(1, B1.bytes());
+        assert_eq!(4, F32.bytes());
+        assert_eq!(16, I32.by(4).unwrap().bytes());
         assert_eq!(B1, B1.lane_type());
         assert_eq!(B8, B8.lane_type());
         assert_eq!(B16, B16.lane_type());
@@ -339,6 +355,11 @@ mod tests {
         assert_eq!(I32.half_vector(), None);
         assert_eq!(VOID.half_vector(), None);
 
+        assert_eq!(I32.double_vector(), Some(I32.by(2).unwrap()));
+        assert_eq!(I32X4.double_vector(), Some(I32.by(8).unwrap()));
+        assert_eq!(big.double_vector(), None);
+        assert_eq!(VOID.double_vector(), None);
+
         // Check that the generated constants match the computed vector types.
         assert_eq!(I32.by(4), Some(I32X4));
         assert_eq!(F64.by(8), Some(F64X8));
@@ -0,0 +1,250 @@
+//! ARM32 ABI implementation.
+//!
+//! This module implements the AAPCS procedure call standard through the primary
+//! `legalize_signature()` entry point.
+//!
+//! When VFP is in use, the hard-float variant of the AAPCS is used, and floating point arguments
+//! are passed in the VFP registers. Otherwise, floating point arguments are passed according to
+//! the integer calling convention.
+
+use abi::{ArgAction, ArgAssigner, ValueConversion, legalize_args};
+use ir::{Function, Signature, ArgumentType, ArgumentLoc, ArgumentExtension, ArgumentPurpose,
+         Type, ValueLoc};
+use ir::instructions::CallInfo;
+use isa::RegClass;
+use regalloc::AllocatableSet;
+use ir::types;
+use super::registers::{GPR, SPR, DPR, QPR};
+use super::settings;
+
+/// The stack pointer is always 8-byte aligned at public interfaces.
+pub const STACK_ALIGNMENT: u32 = 8;
+
+/// The return address is passed in the link register `r14`.
+const LINK_REG: usize = 14;
+
+/// The callee-saved integer registers are `r4`-`r8`, `r10`, and `r11`. The platform register `r9`
+/// is reserved.
+const CALLEE_SAVED_GPRS: [usize; 7] = [4, 5, 6, 7, 8, 10, 11];
+
+/// The double precision registers `d8`-`d15` are callee-saved. They overlap `s16`-`s31`.
+const CALLEE_SAVED_DPRS: [usize; 8] = [8, 9, 10, 11, 12, 13, 14, 15];
+
+struct Args {
+    use_vfp: bool,
+    use_neon: bool,
+    regs: u32,
+    // Bit mask of the allocated single precision registers `s0`-`s15`.
+    fregs: u16,
+    // Once a floating point argument has gone on the stack, the remaining ones do too.
+    fstack: bool,
+    // The next argument is the low half of a split 64-bit integer.
+    align_next: bool,
+    offset: u32,
+}
+
+impl Args {
+    fn new(isa_flags: &settings::Flags) -> Args {
+        Args {
+            use_vfp: isa_flags.use_vfp(),
+            use_neon: isa_flags.use_neon(),
+            regs: 0,
+            fregs: 0,
+            fstack: false,
+            align_next: false,
+            offset: 0,
+        }
+    }
+
+    /// Can a value of type `ty` be passed in the VFP registers?
+    fn has_vfp_reg(&self, ty: Type) -> bool {
+        if ty.is_scalar() {
+            ty.is_float() && self.use_vfp
+        } else {
+            ty.bits() == 128 && self.use_neon
+        }
+    }
+
+    /// Allocate the first free VFP register for `ty`, back-filling single precision registers
+    /// left over from double precision alignment.
+    fn vfp_reg(&mut self, ty: Type) -> ArgAction {
+        // The number of single precision registers in `ty`.
+        let count = ty.bits() / 32;
+        let mask = (1u16 << count) - 1;
+        let mut sreg = 0;
+        while !self.fstack && sreg + count <= 16 {
+            if self.fregs & (mask << sreg) == 0 {
+                self.fregs |= mask << sreg;
+                let reg = match count {
+                    1 => SPR.unit(sreg as usize),
+                    2 => DPR.unit(sreg as usize / 2),
+                    _ => QPR.unit(sreg as usize / 4),
+                };
+                return ArgumentLoc::Reg(reg).into();
+            }
+            sreg += count;
+        }
+        self.fstack = true;
+        self.stack_slot(ty.bytes())
+    }
+
+    /// Assign a stack location aligned to the size of the value, up to 8 bytes.
+    fn stack_slot(&mut self, bytes: u32) -> ArgAction {
+        let align = if bytes >= 8 { 8 } else { 4 };
+        self.offset = (self.offset + align - 1) & !(align - 1);
+        let loc = ArgumentLoc::Stack(self.offset as i32);
+        self.offset += bytes;
+        loc.into()
+    }
+}
+
+impl ArgAssigner for Args {
+    fn assign(&mut self, arg: &ArgumentType) -> ArgAction {
+        let ty = arg.value_type;
+
+        if self.has_vfp_reg(ty) {
+            return self.vfp_reg(ty);
+        }
+
+        // Other vectors are broken down until they fit in a register.
+        if !ty.is_scalar() {
+            return ValueConversion::VectorSplit.into();
+        }
+
+        if ty.is_float() && ty.bits() > 32 {
+            return ValueConversion::IntBits.into();
+        }
+
+        if ty.bits() > 32 {
+            // Large integers and booleans are broken down to fit in a register. The low half is
+            // passed first, and it goes in an even-numbered register or an 8-byte aligned stack
+            // slot.
+            self.align_next = true;
+            return ValueConversion::IntSplit.into();
+        }
+
+        // Small integers are extended to the size of a register.
+        if ty.is_int() && ty.bits() < 32 {
+            match arg.extension {
+                ArgumentExtension::None => {}
+                ArgumentExtension::Uext => return ValueConversion::Uext(types::I32).into(),
+                ArgumentExtension::Sext => return ValueConversion::Sext(types::I32).into(),
+            }
+        }
+
+        if self.align_next {
+            self.align_next = false;
+            self.regs += self.regs & 1;
+            if self.regs >= 4 {
+                self.offset = (self.offset + 7) & !7;
+            }
+        }
+
+        if self.regs < 4 {
+            // Assign to one of `r0`-`r3`.
+            let reg = GPR.unit(self.regs as usize);
+            self.regs += 1;
+            ArgumentLoc::Reg(reg).into()
+        } else {
+            self.stack_slot(4)
+        }
+    }
+}
+
+/// Legalize `sig` for ARM32.
+///
+/// Up to four integer arguments are passed in `r0`-`r3`. With VFP, floating point arguments are
+/// passed in `s0`-`s15` and `d0`-`d7`, and with NEON, 128-bit vectors are passed in `q0`-`q3`. The
+/// remaining arguments are passed on the stack. Return values use the same registers.
+pub fn legalize_signature(sig: &mut Signature, isa_flags: &settings::Flags) {
+    let mut args = Args::new(isa_flags);
+    legalize_args(&mut sig.argument_types, &mut args);
+
+    let mut rets = Args::new(isa_flags);
+    legalize_args(&mut sig.return_types, &mut rets);
+}
+
+/// Get register class for a type appearing in a legalized signature.
+pub fn regclass_for_abi_type(ty: Type, isa_flags: &settings::Flags) -> RegClass {
+    if !ty.is_scalar() && isa_flags.use_neon() {
+        QPR
+    } else if ty == types::F32 && isa_flags.use_vfp() {
+        SPR
+    } else if ty == types::F64 && isa_flags.use_vfp() {
+        DPR
+    } else {
+        GPR
+    }
+}
+
+/// Get the set of allocatable registers for `func`.
+pub fn allocatable_registers(_func: &Function, isa_flags: &settings::Flags) -> AllocatableSet {
+    let mut regs = AllocatableSet::new();
+    // The platform register `r9` and the intra-procedure-call scratch register `r12` are
+    // reserved. So are the stack pointer `r13`, the link register `r14`, and the program counter
+    // `r15`.
+    for &unit in &[9, 12, 13, 14, 15] {
+        regs.take(GPR, GPR.unit(unit));
+    }
+    // The double precision registers `d16`-`d31` only exist with NEON.
+    if !isa_flags.use_neon() {
+        for unit in 16..32 {
+            regs.take(DPR, DPR.unit(unit));
+        }
+    }
+    regs
+}
+
+/// Get the registers that must be saved by the prologue of `func`.
+///
+/// These are the callee-saved integer and double precision registers used by `func`, and the link
+/// register if `func` contains any calls. A double precision register must be saved when any of
+/// the single precision or quad registers overlapping it are used.
+pub fn saved_registers(func: &Function, isa_flags: &settings::Flags) -> Vec<ArgumentType> {
+    let mut saved = Vec::new();
+
+    let has_calls = func.layout
+        .ebbs()
+        .flat_map(|ebb| func.layout.ebb_insts(ebb))
+        .any(|inst| match func.dfg[inst].analyze_call() {
+            CallInfo::NotACall => false,
+            _ => true,
+        });
+    if has_calls {
+        saved.push(saved_register(types::I32, ArgumentPurpose::Link, GPR, LINK_REG));
+    }
+
+    for &unit in &CALLEE_SAVED_GPRS {
+        let loc = ValueLoc::Reg(GPR.unit(unit));
+        if func.locations.keys().any(|v| func.locations[v] == loc) {
+            saved.push(saved_register(types::I32, ArgumentPurpose::CalleeSaved, GPR, unit));
+        }
+    }
+
+    if isa_flags.use_vfp() {
+        for &unit in &CALLEE_SAVED_DPRS {
+            let first = DPR.unit(unit);
+            let used = func.locations.keys().any(|v| match func.locations[v] {
+                ValueLoc::Reg(reg) => {
+                    // The register units of the value, which may be wider than a single unit.
+                    let width = func.dfg.value_type(v).bits() / 32;
+                    reg < first + 2 && reg + width.max(1) > first
+                }
+                _ => false,
+            });
+            if used {
+                saved.push(saved_register(types::F64, ArgumentPurpose::CalleeSaved, DPR, unit));
+            }
+        }
+    }
+
+    saved
+}
+
+/// Make a special-purpose argument for the saved register `unit` in `rc`.
+fn saved_register(ty: Type, purpose: ArgumentPurpose, rc: RegClass, unit: usize) -> ArgumentType {
+    let mut arg = ArgumentType::new(ty);
+    arg.purpose = purpose;
+    arg.location = ArgumentLoc::Reg(rc.unit(unit));
+    arg
+}
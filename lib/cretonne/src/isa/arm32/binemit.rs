@@ -0,0 +1,1116 @@
+//! Emitting binary ARM32 machine code.
+//!
+//! A32 instructions are emitted as 32-bit little-endian words. T32 instructions are emitted as
+//! one or two 16-bit little-endian halfwords, the most significant halfword first.
+
+use binemit::{CodeSink, Reloc, bad_encoding, stack_slot_offset, globalsym_name};
+use ir::{Function, Inst, InstructionData};
+use ir::condcodes::{IntCC, FloatCC};
+use isa::RegUnit;
+
+include!(concat!(env!("OUT_DIR"), "/binemit-arm32.rs"));
+
+/// ARM32 relocation kinds.
+pub enum RelocKind {
+    /// A 24-bit PC-relative branch offset in an A32 `bl` instruction.
+    Call,
+    /// The low 16 bits of an absolute address in an A32 `movw` instruction.
+    MovwAbsNc,
+    /// The high 16 bits of an absolute address in an A32 `movt` instruction.
+    MovtAbs,
+    /// A PC-relative branch offset in a T32 `bl` instruction.
+    ThmCall,
+    /// The low 16 bits of an absolute address in a T32 `movw` instruction.
+    ThmMovwAbsNc,
+    /// The high 16 bits of an absolute address in a T32 `movt` instruction.
+    ThmMovtAbs,
+}
+
+/// The names of the ARM32 relocation kinds, using the ELF names.
+pub static RELOC_NAMES: [&'static str; 6] = ["R_ARM_CALL",
+                                             "R_ARM_MOVW_ABS_NC",
+                                             "R_ARM_MOVT_ABS",
+                                             "R_ARM_THM_CALL",
+                                             "R_ARM_THM_MOVW_ABS_NC",
+                                             "R_ARM_THM_MOVT_ABS"];
+
+impl Into<Reloc> for RelocKind {
+    fn into(self) -> Reloc {
+        Reloc(self as u16)
+    }
+}
+
+/// The stack pointer `r13`.
+const STACK_POINTER: RegUnit = 13;
+
+/// The first register unit of the `FloatRegs` bank.
+const FIRST_FLOAT_UNIT: RegUnit = 16;
+
+/// Encoding bit 15 selects T32 emission in the recipes shared by both instruction sets.
+const THUMB: u16 = 1 << 15;
+
+/// The `eq` condition code.
+const COND_EQ: u32 = 0b0000;
+
+/// The `ne` condition code.
+const COND_NE: u32 = 0b0001;
+
+/// The `al` condition code which always holds.
+const COND_AL: u32 = 0b1110;
+
+/// Encoding bits of the A32 `and` instruction with an immediate.
+const A32_AND_IMM: u16 = 0x20;
+
+/// Encoding bits of the A32 `cmp` instruction with an immediate.
+const A32_CMP_IMM: u16 = 0x35;
+
+/// Encoding bits of the A32 `mov` instruction with an immediate.
+const A32_MOV_IMM: u16 = 0x3a;
+
+/// Encoding bits of the A32 `mvn` instruction with an immediate.
+const A32_MVN_IMM: u16 = 0x3e;
+
+/// The bits that turn an A32 `add` into `sub`, in the encoding bits of either.
+const A32_ADD_SUB: u16 = 0x0c;
+
+/// Encoding bits of the A32 `rbit` instruction.
+const A32_RBIT: u16 = 0x36f;
+
+/// The bit that turns A32 `movw` into `movt`.
+const A32_MOVT: u16 = 0x04;
+
+/// Encoding bits of the A32 `b` instruction.
+const A32_B: u16 = 0xa0;
+
+/// The A32 `mls` instruction.
+const A32_MLS: u32 = 0xe060_0090;
+
+/// The A32 `bne` instruction with a zero offset, which skips the following instruction.
+const A32_BNE_SKIP: u32 = 0x1a00_0000;
+
+/// The A32 permanently undefined instruction `udf #0`, which raises an exception.
+const A32_UDF: u32 = 0xe7f0_00f0;
+
+/// The A32 `cmn rn, #1` instruction.
+const A32_CMN_ONE: u32 = 0xe370_0001;
+
+/// The A32 `cmpeq rn, #0x80000000` instruction.
+const A32_CMPEQ_MIN: u32 = 0x0350_0102;
+
+/// The A32 `bx lr` instruction.
+const A32_BX_LR: u32 = 0xe12f_ff1e;
+
+/// The A32 `dmb ish` full memory barrier.
+const A32_DMB_ISH: u32 = 0xf57f_f05b;
+
+/// Encoding bits of the T32 `and.w` instruction with an immediate.
+const T32_AND_IMM: u16 = 0xf000;
+
+/// Encoding bits of the T32 `cmp.w` instruction with an immediate.
+const T32_CMP_IMM: u16 = 0xf1b0;
+
+/// Encoding bits of the T32 `cmn.w` instruction with an immediate.
+const T32_CMN_IMM: u16 = 0xf110;
+
+/// Encoding bits of the T32 `mov.w` instruction with an immediate.
+const T32_MOV_IMM: u16 = 0xf04f;
+
+/// Encoding bits of the T32 `mvn` instruction with an immediate.
+const T32_MVN_IMM: u16 = 0xf06f;
+
+/// Encoding bits of the T32 `add.w` instruction with a modified immediate.
+const T32_ADD_MODIMM: u16 = 0xf100;
+
+/// The bits that turn T32 `add.w` into `sub.w`, and `addw` into `subw`.
+const T32_ADD_SUB: u16 = 0x00a0;
+
+/// Encoding bits of the T32 `mov.w` instruction with a shifted register.
+const T32_MOV_REG: u16 = 0xea4f;
+
+/// Encoding bits of the T32 `rbit` instruction.
+const T32_RBIT: u16 = 0xfa90;
+
+/// The bit that turns T32 `movw` into `movt`.
+const T32_MOVT: u16 = 0x0080;
+
+/// The first halfword of the T32 `mls` instruction.
+const T32_MLS: u16 = 0xfb00;
+
+/// The T32 `dmb ish` full memory barrier.
+const T32_DMB_ISH: u32 = 0xf3bf_8f5b;
+
+/// The 16-bit T32 `cmp rn, #imm8` instruction.
+const T16_CMP_IMM: u16 = 0x2800;
+
+/// The 16-bit T32 `cmp rn, rm` instruction with high registers.
+const T16_CMP_HI: u16 = 0x4500;
+
+/// The bit that turns a 16-bit T32 `adds rdn, #imm8` into `subs`.
+const T16_ADD_SUB: u16 = 0x0800;
+
+/// The 16-bit T32 `bne.n` instruction with a zero offset, which skips the following instruction.
+const T16_BNE_SKIP: u16 = 0xd100;
+
+/// The 16-bit T32 permanently undefined instruction `udf #0`.
+const T16_UDF: u16 = 0xde00;
+
+/// The 16-bit T32 `it eq` instruction.
+const T16_IT_EQ: u16 = 0xbf08;
+
+/// The `vmrs APSR_nzcv, fpscr` instruction which copies the VFP flags to the core flags.
+const VMRS: u32 = 0xeef1_fa10;
+
+/// Get the A32 instruction word from the encoding bits.
+///
+/// Encoding bits: `bits[27:20] | (bits[7:4] << 8) | ((cond ^ 0xe) << 12)`.
+fn a32_word(bits: u16) -> u32 {
+    let bits = bits as u32;
+    let cond = (bits >> 12) ^ COND_AL;
+    (cond << 28) | ((bits & 0xff) << 20) | (((bits >> 8) & 0xf) << 4)
+}
+
+/// Replace the condition code in the encoding bits of an A32 instruction.
+fn cond_bits(bits: u16, cond: u32) -> u16 {
+    (bits & 0x0fff) | (((cond ^ COND_AL) as u16) << 12)
+}
+
+/// Get the A32 modified immediate encoding of `imm`, an 8-bit value rotated right by an even
+/// amount.
+fn a32_modimm(imm: u32) -> u32 {
+    for rot in 0..16 {
+        let imm8 = imm.rotate_left(2 * rot);
+        if imm8 <= 0xff {
+            return (rot << 8) | imm8;
+        }
+    }
+    panic!("{:#x} is not an A32 modified immediate", imm)
+}
+
+/// A32 data-processing instructions with register operands.
+///
+///   31   27  24     20 19 15 11    6    4 3
+///   cond 000 opcode S  Rn Rd imm5 type 0 Rm
+///   28   25  21     20 16 12 7    5    4 0
+///
+/// The shift amount is zero.
+fn put_a32_dp<CS: CodeSink + ?Sized>(bits: u16,
+                                     rn: RegUnit,
+                                     rm: RegUnit,
+                                     rd: RegUnit,
+                                     sink: &mut CS) {
+    let mut i = a32_word(bits);
+    i |= rm as u32 & 0xf;
+    i |= (rd as u32 & 0xf) << 12;
+    i |= (rn as u32 & 0xf) << 16;
+    sink.put4(i);
+}
+
+/// A32 data-processing instructions with a modified immediate operand.
+///
+///   31   27  24     20 19 15 11
+///   cond 001 opcode S  Rn Rd imm12
+///   28   25  21     20 16 12 0
+fn put_a32_dp_imm<CS: CodeSink + ?Sized>(bits: u16,
+                                         rn: RegUnit,
+                                         imm: i64,
+                                         rd: RegUnit,
+                                         sink: &mut CS) {
+    let mut i = a32_word(bits);
+    i |= a32_modimm(imm as u32);
+    i |= (rd as u32 & 0xf) << 12;
+    i |= (rn as u32 & 0xf) << 16;
+    sink.put4(i);
+}
+
+/// Add an immediate, `add rd, rn, #imm`. A negative immediate becomes `sub rd, rn, #-imm`.
+fn put_a32_addsub_imm<CS: CodeSink + ?Sized>(bits: u16,
+                                             rn: RegUnit,
+                                             imm: i64,
+                                             rd: RegUnit,
+                                             sink: &mut CS) {
+    if imm < 0 {
+        put_a32_dp_imm(bits ^ A32_ADD_SUB, rn, -imm, rd, sink);
+    } else {
+        put_a32_dp_imm(bits, rn, imm, rd, sink);
+    }
+}
+
+/// Multiplication and division, `mul rd, rn, rm` and `udiv rd, rn, rm`.
+///
+///   31   27       19 15 11 7  3
+///   cond op       Rd Ra Rm op Rn
+///   28   20       16 12 8  4  0
+///
+/// The `Ra` field is zero for `mul` and all ones for the divisions.
+fn put_a32_mul<CS: CodeSink + ?Sized>(bits: u16,
+                                      rn: RegUnit,
+                                      rm: RegUnit,
+                                      rd: RegUnit,
+                                      sink: &mut CS) {
+    let mut i = a32_word(bits);
+    i |= rn as u32 & 0xf;
+    i |= (rm as u32 & 0xf) << 8;
+    i |= (rd as u32 & 0xf) << 16;
+    sink.put4(i);
+}
+
+/// Division, `udiv rd, rn, rm` or `sdiv rd, rn, rm`.
+fn put_a32_div<CS: CodeSink + ?Sized>(bits: u16,
+                                      rn: RegUnit,
+                                      rm: RegUnit,
+                                      rd: RegUnit,
+                                      sink: &mut CS) {
+    let mut i = a32_word(bits);
+    i |= rn as u32 & 0xf;
+    i |= (rm as u32 & 0xf) << 8;
+    i |= 0xf << 12;
+    i |= (rd as u32 & 0xf) << 16;
+    sink.put4(i);
+}
+
+/// Multiply and subtract, `mls rd, rn, rm, ra` which computes `ra - rn * rm`.
+fn put_a32_mls<CS: CodeSink + ?Sized>(rn: RegUnit,
+                                      rm: RegUnit,
+                                      ra: RegUnit,
+                                      rd: RegUnit,
+                                      sink: &mut CS) {
+    let mut i = A32_MLS;
+    i |= rn as u32 & 0xf;
+    i |= (rm as u32 & 0xf) << 8;
+    i |= (ra as u32 & 0xf) << 12;
+    i |= (rd as u32 & 0xf) << 16;
+    sink.put4(i);
+}
+
+/// Trap if `rm` is zero:
+///
+///   0: cmp rm, #0
+///   4: bne 12
+///   8: udf #0
+fn put_a32_trapz<CS: CodeSink + ?Sized>(rm: RegUnit, sink: &mut CS) {
+    put_a32_dp_imm(A32_CMP_IMM, rm, 0, 0, sink);
+    sink.put4(A32_BNE_SKIP);
+    sink.put4(A32_UDF);
+}
+
+/// Trap if a signed division of `rn` by `rm` overflows:
+///
+///   0:  cmn rm, #1
+///   4:  cmpeq rn, #0x80000000
+///   8:  bne 16
+///   12: udf #0
+///
+/// The second comparison is only made if `rm` is -1.
+fn put_a32_trap_sdiv_overflow<CS: CodeSink + ?Sized>(rn: RegUnit, rm: RegUnit, sink: &mut CS) {
+    sink.put4(A32_CMN_ONE | ((rm as u32 & 0xf) << 16));
+    sink.put4(A32_CMPEQ_MIN | ((rn as u32 & 0xf) << 16));
+    sink.put4(A32_BNE_SKIP);
+    sink.put4(A32_UDF);
+}
+
+/// Shift by a register, `mov rd, rm, lsl rs` and friends.
+///
+///   31   27        15 11 7 6    4 3
+///   cond 0001101 S Rd Rs 0 type 1 Rm
+///   28   21        12 8  7 5    4 0
+fn put_a32_shift_reg<CS: CodeSink + ?Sized>(bits: u16,
+                                            rm: RegUnit,
+                                            rs: RegUnit,
+                                            rd: RegUnit,
+                                            sink: &mut CS) {
+    let mut i = a32_word(bits);
+    i |= rm as u32 & 0xf;
+    i |= (rs as u32 & 0xf) << 8;
+    i |= (rd as u32 & 0xf) << 12;
+    sink.put4(i);
+}
+
+/// Shift by an immediate, `mov rd, rm, lsl #imm` and friends.
+///
+///   31   27        15 11   6    4 3
+///   cond 0001101 S Rd imm5 type 0 Rm
+///   28   21        12 7    5    4 0
+///
+/// The shift amount is taken modulo 32. A zero shift amount has special meanings for the other
+/// shift types, so it is always encoded as `lsl #0`.
+fn put_a32_shift_imm<CS: CodeSink + ?Sized>(bits: u16,
+                                            rm: RegUnit,
+                                            imm: i64,
+                                            rd: RegUnit,
+                                            sink: &mut CS) {
+    let sh = imm as u32 & 0x1f;
+    let mut i = a32_word(bits);
+    if sh == 0 {
+        i &= !(0b11 << 5);
+    }
+    i |= rm as u32 & 0xf;
+    i |= sh << 7;
+    i |= (rd as u32 & 0xf) << 12;
+    sink.put4(i);
+}
+
+/// One-register instructions with all-ones `Rn` fields, `clz rd, rm` and `rbit rd, rm`.
+///
+///   31   27       19   15 11   7  3
+///   cond op       1111 Rd 1111 op Rm
+///   28   20       16   12 8    4  0
+fn put_a32_r1<CS: CodeSink + ?Sized>(bits: u16, rm: RegUnit, rd: RegUnit, sink: &mut CS) {
+    let mut i = a32_word(bits);
+    i |= rm as u32 & 0xf;
+    i |= 0xf << 8;
+    i |= (rd as u32 & 0xf) << 12;
+    i |= 0xf << 16;
+    sink.put4(i);
+}
+
+/// Integer extension, `uxtb rd, rm` and friends.
+///
+///   31   27       19   15 11     9  7  3
+///   cond op       1111 Rd rotate 00 op Rm
+///   28   20       16   12 10     8  4  0
+fn put_a32_ext<CS: CodeSink + ?Sized>(bits: u16, rm: RegUnit, rd: RegUnit, sink: &mut CS) {
+    let mut i = a32_word(bits);
+    i |= rm as u32 & 0xf;
+    i |= (rd as u32 & 0xf) << 12;
+    i |= 0xf << 16;
+    sink.put4(i);
+}
+
+/// Move a 16-bit immediate, `movw rd, #imm16` or `movt rd, #imm16`.
+///
+///   31   27       19   15 11
+///   cond 00110H00 imm4 Rd imm12
+///   28   20       16   12 0
+fn put_a32_movw<CS: CodeSink + ?Sized>(bits: u16, imm: u32, rd: RegUnit, sink: &mut CS) {
+    let mut i = a32_word(bits);
+    i |= imm & 0xfff;
+    i |= (rd as u32 & 0xf) << 12;
+    i |= ((imm >> 12) & 0xf) << 16;
+    sink.put4(i);
+}
+
+/// Materialize a constant in one instruction, `movw rd, #imm16` or `mvn rd, #~imm` for small
+/// negative constants.
+fn put_a32_mov_imm<CS: CodeSink + ?Sized>(bits: u16, imm: i64, rd: RegUnit, sink: &mut CS) {
+    if imm < 0 {
+        put_a32_dp_imm(cond_bits(A32_MVN_IMM, (bits >> 12) as u32 ^ COND_AL),
+                       0,
+                       !imm,
+                       rd,
+                       sink);
+    } else {
+        put_a32_movw(bits, imm as u32, rd, sink);
+    }
+}
+
+/// Get the ARM condition code that is true after `cmp x, y` when `x cond y`.
+fn icc_bits(cond: IntCC) -> u32 {
+    use ir::condcodes::IntCC::*;
+    match cond {
+        Equal => 0b0000,
+        NotEqual => 0b0001,
+        UnsignedGreaterThanOrEqual => 0b0010,
+        UnsignedLessThan => 0b0011,
+        UnsignedGreaterThan => 0b1000,
+        UnsignedLessThanOrEqual => 0b1001,
+        SignedGreaterThanOrEqual => 0b1010,
+        SignedLessThan => 0b1011,
+        SignedGreaterThan => 0b1100,
+        SignedLessThanOrEqual => 0b1101,
+    }
+}
+
+/// Get the ARM condition code that is true after `vcmp x, y` and `vmrs` when `x cond y`.
+///
+/// An unordered comparison sets the C and V flags. There are no condition codes for `one` and
+/// `ueq`.
+fn fcc_bits(cond: FloatCC) -> u32 {
+    use ir::condcodes::FloatCC::*;
+    match cond {
+        Ordered => 0b0111,
+        Unordered => 0b0110,
+        Equal => 0b0000,
+        NotEqual => 0b0001,
+        LessThan => 0b0100,
+        LessThanOrEqual => 0b1001,
+        GreaterThan => 0b1100,
+        GreaterThanOrEqual => 0b1010,
+        UnorderedOrLessThan => 0b1011,
+        UnorderedOrLessThanOrEqual => 0b1101,
+        UnorderedOrGreaterThan => 0b1000,
+        UnorderedOrGreaterThanOrEqual => 0b0010,
+        OrderedNotEqual | UnorderedOrEqual => panic!("No ARM condition code for {}", cond),
+    }
+}
+
+/// Set `rd` to 1 if the condition `cond` holds, 0 otherwise:
+///
+///   mov<!cond> rd, #0
+///   mov<cond> rd, #1
+///
+/// The inverse condition code flips the low bit.
+fn put_a32_cset<CS: CodeSink + ?Sized>(cond: u32, rd: RegUnit, sink: &mut CS) {
+    put_a32_dp_imm(cond_bits(A32_MOV_IMM, cond ^ 1), 0, 0, rd, sink);
+    put_a32_dp_imm(cond_bits(A32_MOV_IMM, cond), 0, 1, rd, sink);
+}
+
+/// Load and store words and unsigned bytes with an immediate offset.
+///
+///   31   27  24 23 22 21 20 19 15 11
+///   cond 010 P  U  B  W  L  Rn Rt imm12
+///   28   25  24 23 22 21 20 16 12 0
+///
+/// The `U` bit is cleared for negative offsets.
+fn put_a32_ldst<CS: CodeSink + ?Sized>(bits: u16,
+                                       rn: RegUnit,
+                                       offset: i64,
+                                       rt: RegUnit,
+                                       sink: &mut CS) {
+    let mut i = a32_word(bits);
+    if offset < 0 {
+        i &= !(1 << 23);
+    }
+    let imm = offset.abs() as u32;
+    assert!(imm < 0x1000, "Offset {} out of range", offset);
+    i |= imm;
+    i |= (rt as u32 & 0xf) << 12;
+    i |= (rn as u32 & 0xf) << 16;
+    sink.put4(i);
+}
+
+/// Load and store halfwords and signed bytes with an immediate offset.
+///
+///   31   27  24 23 22 21 20 19 15 11    7 6   4 3
+///   cond 000 P  U  1  W  L  Rn Rt imm4H 1 op2 1 imm4L
+///   28   25  24 23 22 21 20 16 12 8     7 5   4 0
+///
+/// The `U` bit is cleared for negative offsets.
+fn put_a32_ldsth<CS: CodeSink + ?Sized>(bits: u16,
+                                        rn: RegUnit,
+                                        offset: i64,
+                                        rt: RegUnit,
+                                        sink: &mut CS) {
+    let mut i = a32_word(bits);
+    if offset < 0 {
+        i &= !(1 << 23);
+    }
+    let imm = offset.abs() as u32;
+    assert!(imm < 0x100, "Offset {} out of range", offset);
+    i |= imm & 0xf;
+    i |= (imm >> 4) << 8;
+    i |= (rt as u32 & 0xf) << 12;
+    i |= (rn as u32 & 0xf) << 16;
+    sink.put4(i);
+}
+
+/// Branch, `b offset` or `bl offset`, where `offset` is relative to the address of the
+/// instruction.
+///
+///   31   27  24 23
+///   cond 101 L  imm24
+///   28   25  24 0
+///
+/// The branch target is relative to the address of the instruction plus 8.
+fn put_a32_b<CS: CodeSink + ?Sized>(bits: u16, offset: i64, sink: &mut CS) {
+    let imm24 = ((offset - 8) >> 2) as u32 & 0xff_ffff;
+    sink.put4(a32_word(bits) | imm24);
+}
+
+/// Put a 32-bit T32 instruction as two halfwords.
+fn put_t32<CS: CodeSink + ?Sized>(hw1: u32, hw2: u32, sink: &mut CS) {
+    sink.put2(hw1 as u16);
+    sink.put2(hw2 as u16);
+}
+
+/// Put a 32-bit instruction word in the T32 byte order, most significant halfword first.
+fn put_t32_word<CS: CodeSink + ?Sized>(word: u32, sink: &mut CS) {
+    put_t32(word >> 16, word & 0xffff, sink);
+}
+
+/// Get the T32 modified immediate encoding of `imm` as the 12-bit `i:imm3:imm8` field.
+///
+/// This is an 8-bit value, a repeating pattern of an 8-bit value, or an 8-bit value with the top
+/// bit set rotated right by 8 to 31 bits.
+fn t32_modimm(imm: u32) -> u32 {
+    let b = imm & 0xff;
+    if imm == b {
+        return b;
+    }
+    if imm == b * 0x0001_0001 {
+        return 0x100 | b;
+    }
+    let b1 = (imm >> 8) & 0xff;
+    if imm == b1 * 0x0100_0100 {
+        return 0x200 | b1;
+    }
+    if imm == b * 0x0101_0101 {
+        return 0x300 | b;
+    }
+    for rot in 8..32 {
+        let imm8 = imm.rotate_left(rot);
+        if imm8 <= 0xff && imm8 & 0x80 != 0 {
+            return (rot << 7) | (imm8 & 0x7f);
+        }
+    }
+    panic!("{:#x} is not a T32 modified immediate", imm)
+}
+
+/// T32 data-processing instructions with register operands.
+///
+///   15          3      15 14   11 7    5    3
+///   op        S Rn     0  imm3 Rd imm2 type Rm
+///   4         4 0      15 12   8  6    4    0
+///
+/// The shift amount is zero.
+fn put_t32_dp<CS: CodeSink + ?Sized>(bits: u16,
+                                     rn: RegUnit,
+                                     rm: RegUnit,
+                                     rd: RegUnit,
+                                     sink: &mut CS) {
+    let hw1 = bits as u32 | (rn as u32 & 0xf);
+    let hw2 = ((rd as u32 & 0xf) << 8) | (rm as u32 & 0xf);
+    put_t32(hw1, hw2, sink);
+}
+
+/// T32 data-processing instructions with a modified immediate.
+///
+///   15    10 9        3      15 14   11 7
+///   11110 i  0 op   S Rn     0  imm3 Rd imm8
+///   11    10 9      4 0      15 12   8  0
+fn put_t32_dp_imm<CS: CodeSink + ?Sized>(bits: u16,
+                                         rn: RegUnit,
+                                         imm: i64,
+                                         rd: RegUnit,
+                                         sink: &mut CS) {
+    let imm12 = t32_modimm(imm as u32);
+    let hw1 = bits as u32 | ((imm12 >> 11) << 10) | (rn as u32 & 0xf);
+    let hw2 = (((imm12 >> 8) & 7) << 12) | ((rd as u32 & 0xf) << 8) | (imm12 & 0xff);
+    put_t32(hw1, hw2, sink);
+}
+
+/// Add or subtract a modified immediate, `add.w rd, rn, #imm` or `sub.w rd, rn, #-imm`.
+fn put_t32_addsub_modimm<CS: CodeSink + ?Sized>(rn: RegUnit,
+                                                imm: i64,
+                                                rd: RegUnit,
+                                                sink: &mut CS) {
+    if imm < 0 {
+        put_t32_dp_imm(T32_ADD_MODIMM ^ T32_ADD_SUB, rn, -imm, rd, sink);
+    } else {
+        put_t32_dp_imm(T32_ADD_MODIMM, rn, imm, rd, sink);
+    }
+}
+
+/// Add a 12-bit immediate, `addw rd, rn, #imm`. A negative immediate becomes
+/// `subw rd, rn, #-imm`.
+///
+///   15    10 9      3      15 14   11 7
+///   11110 i  op     Rn     0  imm3 Rd imm8
+///   11    10 4      0      15 12   8  0
+fn put_t32_addsub_imm<CS: CodeSink + ?Sized>(bits: u16,
+                                             rn: RegUnit,
+                                             imm: i64,
+                                             rd: RegUnit,
+                                             sink: &mut CS) {
+    let (bits, imm) = if imm < 0 {
+        (bits ^ T32_ADD_SUB, (-imm) as u32)
+    } else {
+        (bits, imm as u32)
+    };
+    let hw1 = bits as u32 | (((imm >> 11) & 1) << 10) | (rn as u32 & 0xf);
+    let hw2 = (((imm >> 8) & 7) << 12) | ((rd as u32 & 0xf) << 8) | (imm & 0xff);
+    put_t32(hw1, hw2, sink);
+}
+
+/// T32 register instructions with all-ones in the top of the second halfword, like
+/// `mul rd, rn, rm`, `lsl.w rd, rn, rm`, and `udiv rd, rn, rm`.
+///
+///   15          3      15   11 7  3
+///   op          Rn     1111 Rd op Rm
+///   4           0      12   8  4  0
+fn put_t32_reg<CS: CodeSink + ?Sized>(bits: u16,
+                                      rn: RegUnit,
+                                      op: u32,
+                                      rm: RegUnit,
+                                      rd: RegUnit,
+                                      sink: &mut CS) {
+    let hw1 = bits as u32 | (rn as u32 & 0xf);
+    let hw2 = 0xf000 | ((rd as u32 & 0xf) << 8) | (op << 4) | (rm as u32 & 0xf);
+    put_t32(hw1, hw2, sink);
+}
+
+/// Multiply and subtract, `mls rd, rn, rm, ra` which computes `ra - rn * rm`.
+fn put_t32_mls<CS: CodeSink + ?Sized>(rn: RegUnit,
+                                      rm: RegUnit,
+                                      ra: RegUnit,
+                                      rd: RegUnit,
+                                      sink: &mut CS) {
+    let hw1 = T32_MLS as u32 | (rn as u32 & 0xf);
+    let hw2 = ((ra as u32 & 0xf) << 12) | ((rd as u32 & 0xf) << 8) | 0x10 | (rm as u32 & 0xf);
+    put_t32(hw1, hw2, sink);
+}
+
+/// Trap if `rm` is zero:
+///
+///   0: cmp.w rm, #0
+///   4: bne.n 8
+///   6: udf #0
+fn put_t32_trapz<CS: CodeSink + ?Sized>(rm: RegUnit, sink: &mut CS) {
+    put_t32_dp_imm(T32_CMP_IMM, rm, 0, 0xf, sink);
+    sink.put2(T16_BNE_SKIP);
+    sink.put2(T16_UDF);
+}
+
+/// Trap if a signed division of `rn` by `rm` overflows:
+///
+///   0:  cmn.w rm, #1
+///   4:  it eq
+///   6:  cmpeq.w rn, #0x80000000
+///   10: bne.n 14
+///   12: udf #0
+fn put_t32_trap_sdiv_overflow<CS: CodeSink + ?Sized>(rn: RegUnit, rm: RegUnit, sink: &mut CS) {
+    put_t32_dp_imm(T32_CMN_IMM, rm, 1, 0xf, sink);
+    sink.put2(T16_IT_EQ);
+    put_t32_dp_imm(T32_CMP_IMM, rn, 0x8000_0000, 0xf, sink);
+    sink.put2(T16_BNE_SKIP);
+    sink.put2(T16_UDF);
+}
+
+/// Shift by an immediate, `mov.w rd, rm, lsl #imm` and friends.
+///
+///   15          3      15 14   11 7    5    3
+///   11101010010 S 1111 0  imm3 Rd imm2 type Rm
+///   4           4 0    15 12   8  6    4    0
+///
+/// Encoding bits: The shift type.
+///
+/// The shift amount is taken modulo 32. A zero shift amount is always encoded as `lsl #0`.
+fn put_t32_shift_imm<CS: CodeSink + ?Sized>(bits: u16,
+                                            rm: RegUnit,
+                                            imm: i64,
+                                            rd: RegUnit,
+                                            sink: &mut CS) {
+    let sh = imm as u32 & 0x1f;
+    let shtype = if sh == 0 { 0 } else { bits as u32 & 3 };
+    let hw2 = ((sh >> 2) << 12) | ((rd as u32 & 0xf) << 8) | ((sh & 3) << 6) | (shtype << 4) |
+              (rm as u32 & 0xf);
+    put_t32(T32_MOV_REG as u32, hw2, sink);
+}
+
+/// Move a 16-bit immediate, `movw rd, #imm16` or `movt rd, #imm16`.
+///
+///   15    10 9      3      15 14   11 7
+///   11110 i  1001H0 imm4   0  imm3 Rd imm8
+///   11    10 4      0      15 12   8  0
+fn put_t32_movw<CS: CodeSink + ?Sized>(bits: u16, imm: u32, rd: RegUnit, sink: &mut CS) {
+    let hw1 = bits as u32 | (((imm >> 11) & 1) << 10) | ((imm >> 12) & 0xf);
+    let hw2 = (((imm >> 8) & 7) << 12) | ((rd as u32 & 0xf) << 8) | (imm & 0xff);
+    put_t32(hw1, hw2, sink);
+}
+
+/// Materialize a constant in one instruction, `movw rd, #imm16` or `mvn rd, #~imm` for small
+/// negative constants.
+fn put_t32_mov_imm<CS: CodeSink + ?Sized>(bits: u16, imm: i64, rd: RegUnit, sink: &mut CS) {
+    if imm < 0 {
+        put_t32_dp_imm(T32_MVN_IMM, 0, !imm, rd, sink);
+    } else {
+        put_t32_movw(bits, imm as u32, rd, sink);
+    }
+}
+
+/// Get the `ite cond` instruction which makes the next instruction conditional on `cond`, and the
+/// one after that on the inverse condition.
+fn ite_bits(cond: u32) -> u16 {
+    let firstcond = cond as u16;
+    let mask = (((firstcond & 1) ^ 1) << 3) | 0b0100;
+    0xbf00 | (firstcond << 4) | mask
+}
+
+/// Set `rd` to 1 if the condition `cond` holds, 0 otherwise:
+///
+///   ite <cond>
+///   mov<cond>.w rd, #1
+///   mov<!cond>.w rd, #0
+fn put_t32_cset<CS: CodeSink + ?Sized>(cond: u32, rd: RegUnit, sink: &mut CS) {
+    sink.put2(ite_bits(cond));
+    put_t32_dp_imm(T32_MOV_IMM, 0, 1, rd, sink);
+    put_t32_dp_imm(T32_MOV_IMM, 0, 0, rd, sink);
+}
+
+/// Load and store with an immediate offset.
+///
+///   15         3      15 11     7
+///   11111000 op Rn     Rt imm12
+///   4          0      12 0
+///
+/// Negative offsets use the form with an 8-bit offset, which has bit 7 of the first halfword
+/// clear:
+///
+///   15         3      15 11 10 9 8 7
+///   11111000 op Rn     Rt 1  P U W imm8
+///   4          0      12 11 10 9 8 0
+fn put_t32_ldst<CS: CodeSink + ?Sized>(bits: u16,
+                                       rn: RegUnit,
+                                       offset: i64,
+                                       rt: RegUnit,
+                                       sink: &mut CS) {
+    let rn = rn as u32 & 0xf;
+    let rt = (rt as u32 & 0xf) << 12;
+    if offset >= 0 {
+        assert!(offset < 0x1000, "Offset {} out of range", offset);
+        put_t32(bits as u32 | rn, rt | offset as u32, sink);
+    } else {
+        assert!(offset > -0x100, "Offset {} out of range", offset);
+        put_t32((bits as u32 & !0x80) | rn, rt | 0xc00 | (-offset) as u32, sink);
+    }
+}
+
+/// Branch, `b.w offset` or `bl offset`, where `offset` is relative to the address of the
+/// instruction.
+///
+///   15    10 9         15 14 13 12 11 10
+///   11110 S  imm10     1  op J1 1  J2 imm11
+///   11    10 0         15 14 13 12 11 0
+///
+/// Encoding bits: The second halfword.
+///
+/// The branch target is relative to the address of the instruction plus 4. The `J1` and `J2`
+/// bits are `!(I1 ^ S)` and `!(I2 ^ S)` where `S:I1:I2:imm10:imm11:0` is the offset.
+fn put_t32_b<CS: CodeSink + ?Sized>(bits: u16, offset: i64, sink: &mut CS) {
+    let imm = (offset - 4) as u32;
+    let s = (imm >> 24) & 1;
+    let j1 = !((imm >> 23) ^ s) & 1;
+    let j2 = !((imm >> 22) ^ s) & 1;
+    let hw1 = 0xf000 | (s << 10) | ((imm >> 12) & 0x3ff);
+    let hw2 = bits as u32 | (j1 << 13) | (j2 << 11) | ((imm >> 1) & 0x7ff);
+    put_t32(hw1, hw2, sink);
+}
+
+/// Conditional branch, `b<cond>.w offset`, where `offset` is relative to the address of the
+/// instruction.
+///
+///   15    10 9    5        15 14 13 12 11 10
+///   11110 S  cond imm6     1  0  J1 0  J2 imm11
+///   11    10 6    0        15 14 13 12 11 0
+///
+/// The branch target is relative to the address of the instruction plus 4, and it is
+/// `S:J2:J1:imm6:imm11:0`.
+fn put_t32_bcond<CS: CodeSink + ?Sized>(cond: u32, offset: i64, sink: &mut CS) {
+    let imm = (offset - 4) as u32;
+    let hw1 = 0xf000 | (((imm >> 20) & 1) << 10) | (cond << 6) | ((imm >> 12) & 0x3f);
+    let hw2 = 0x8000 | (((imm >> 18) & 1) << 13) | (((imm >> 19) & 1) << 11) |
+              ((imm >> 1) & 0x7ff);
+    put_t32(hw1, hw2, sink);
+}
+
+/// 16-bit instructions with three low registers, `adds rd, rn, rm`.
+///
+///   15      8  5  2
+///   op      Rm Rn Rd
+///   9       6  3  0
+fn put_t16_rrr<CS: CodeSink + ?Sized>(bits: u16,
+                                      rn: RegUnit,
+                                      rm: RegUnit,
+                                      rd: RegUnit,
+                                      sink: &mut CS) {
+    sink.put2(bits | ((rm & 7) << 6) | ((rn & 7) << 3) | (rd & 7));
+}
+
+/// 16-bit instructions with two low registers, `ands rdn, rm` and `uxtb rd, rm`.
+///
+///   15         5  2
+///   op         Rm Rd
+///   6          3  0
+fn put_t16_rr<CS: CodeSink + ?Sized>(bits: u16, rm: RegUnit, rd: RegUnit, sink: &mut CS) {
+    sink.put2(bits | ((rm & 7) << 3) | (rd & 7));
+}
+
+/// 16-bit instructions with any registers, `mov rd, rm` and `add rdn, rm`.
+///
+///   15       7  6  2
+///   op       D  Rm Rd
+///   8        7  3  0
+///
+/// The `D` bit is the high bit of the `rd` register number.
+fn put_t16_hireg<CS: CodeSink + ?Sized>(bits: u16, rm: RegUnit, rd: RegUnit, sink: &mut CS) {
+    sink.put2(bits | ((rd & 8) << 4) | ((rm & 0xf) << 3) | (rd & 7));
+}
+
+/// Shift a low register by an immediate, `lsls rd, rm, #imm` and friends.
+///
+///   15   10   5  2
+///   op   imm5 Rm Rd
+///   11   6    3  0
+///
+/// The shift amount is taken modulo 32. A zero shift amount is always encoded as `lsls #0`.
+fn put_t16_shift_imm<CS: CodeSink + ?Sized>(bits: u16,
+                                            rm: RegUnit,
+                                            imm: i64,
+                                            rd: RegUnit,
+                                            sink: &mut CS) {
+    let sh = imm as u16 & 0x1f;
+    let bits = if sh == 0 { 0 } else { bits };
+    sink.put2(bits | (sh << 6) | ((rm & 7) << 3) | (rd & 7));
+}
+
+/// Add an 8-bit immediate to a low register, `adds rdn, #imm`. A negative immediate becomes
+/// `subs rdn, #-imm`.
+///
+///   15    10  7
+///   op    Rdn imm8
+///   11    8   0
+fn put_t16_addsub_imm<CS: CodeSink + ?Sized>(bits: u16, imm: i64, rdn: RegUnit, sink: &mut CS) {
+    let (bits, imm) = if imm < 0 {
+        (bits ^ T16_ADD_SUB, -imm)
+    } else {
+        (bits, imm)
+    };
+    sink.put2(bits | ((rdn & 7) << 8) | imm as u16);
+}
+
+/// Load and store with low registers and a scaled 5-bit offset, `ldr rt, [rn, #offset]` and
+/// friends.
+///
+///   15    10   5  2
+///   op    imm5 Rn Rt
+///   11    6    3  0
+///
+/// The offset is scaled by the access size: Words are `0b0110`, bytes are `0b0111`, and halfwords
+/// are `0b1000` in the top four bits of the encoding bits.
+fn put_t16_ldst<CS: CodeSink + ?Sized>(bits: u16,
+                                       rn: RegUnit,
+                                       offset: i64,
+                                       rt: RegUnit,
+                                       sink: &mut CS) {
+    let scale = match bits >> 12 {
+        0b0110 => 2,
+        0b0111 => 0,
+        _ => 1,
+    };
+    let imm5 = (offset >> scale) as u16 & 0x1f;
+    sink.put2(bits | (imm5 << 6) | ((rn & 7) << 3) | (rt & 7));
+}
+
+/// Compare two registers, `cmp rn, rm`. There are separate encodings for two low registers and
+/// for any other registers.
+///
+/// Encoding bits: The low register form.
+fn put_t16_cmp<CS: CodeSink + ?Sized>(bits: u16, rn: RegUnit, rm: RegUnit, sink: &mut CS) {
+    if rn < 8 && rm < 8 {
+        sink.put2(bits | (rm << 3) | rn);
+    } else {
+        put_t16_hireg(T16_CMP_HI, rm, rn, sink);
+    }
+}
+
+/// Branch, `b.n offset`, where `offset` is relative to the address of the instruction.
+///
+///   15    10
+///   11100 imm11
+///   11    0
+fn put_t16_b<CS: CodeSink + ?Sized>(bits: u16, offset: i64, sink: &mut CS) {
+    let imm11 = ((offset - 4) >> 1) as u16 & 0x7ff;
+    sink.put2(bits | imm11);
+}
+
+/// Conditional branch, `b<cond>.n offset`, where `offset` is relative to the address of the
+/// instruction.
+///
+///   15   11   7
+///   1101 cond imm8
+///   12   8    0
+fn put_t16_bcond<CS: CodeSink + ?Sized>(cond: u32, offset: i64, sink: &mut CS) {
+    let imm8 = ((offset - 4) >> 1) as u16 & 0xff;
+    sink.put2(0xd000 | ((cond as u16) << 8) | imm8);
+}
+
+/// Put a VFP or NEON instruction word in the instruction set selected by the `THUMB` encoding bit.
+fn put_vfp_word<CS: CodeSink + ?Sized>(bits: u16, word: u32, sink: &mut CS) {
+    if bits & THUMB != 0 {
+        put_t32_word(word, sink);
+    } else {
+        sink.put4(word);
+    }
+}
+
+/// Is the `sz` bit set in the encoding bits of a VFP instruction, selecting double precision?
+fn vfp_double(bits: u16) -> bool {
+    bits & (1 << 12) != 0
+}
+
+/// Get the VFP instruction word from the encoding bits.
+///
+/// Encoding bits: `opc1 | (opc2 << 4) | (op4 << 8) | (sz << 12)`.
+fn vfp_word(bits: u16) -> u32 {
+    let bits = bits as u32;
+    0xee00_0a00 | ((bits & 0xf) << 20) | (((bits >> 4) & 0xf) << 16) | (((bits >> 12) & 1) << 8) |
+    (((bits >> 8) & 0xf) << 4)
+}
+
+/// Get the 4-bit register field and the extra register bit of a VFP register.
+///
+/// A single precision register `sN` is encoded as `N >> 1` and `N & 1`. A double precision
+/// register `dN` is encoded as `N & 15` and `N >> 4`. Quad registers are encoded as their first
+/// double precision register.
+fn vfp_reg(reg: RegUnit, double: bool) -> (u32, u32) {
+    let s = (reg - FIRST_FLOAT_UNIT) as u32;
+    if double {
+        let d = s / 2;
+        (d & 0xf, d >> 4)
+    } else {
+        (s >> 1, s & 1)
+    }
+}
+
+/// Put the `vd` register in bits 15:12 and bit 22 of a VFP instruction word.
+fn vfp_vd(reg: RegUnit, double: bool) -> u32 {
+    let (v, x) = vfp_reg(reg, double);
+    (v << 12) | (x << 22)
+}
+
+/// Put the `vn` register in bits 19:16 and bit 7 of a VFP instruction word.
+fn vfp_vn(reg: RegUnit, double: bool) -> u32 {
+    let (v, x) = vfp_reg(reg, double);
+    (v << 16) | (x << 7)
+}
+
+/// Put the `vm` register in bits 3:0 and bit 5 of a VFP instruction word.
+fn vfp_vm(reg: RegUnit, double: bool) -> u32 {
+    let (v, x) = vfp_reg(reg, double);
+    v | (x << 5)
+}
+
+/// VFP data-processing instructions with three registers, `vadd.f32 sd, sn, sm` and friends.
+///
+///   31   27   23 22 21 19 15 11  8  7 6 5 4 3
+///   cond 1110 op D  op Vn Vd 101 sz N op M 0 Vm
+///   28   24   23 22 20 16 12 9   8  7 6 5 4 0
+fn put_vfp3<CS: CodeSink + ?Sized>(bits: u16,
+                                   vn: RegUnit,
+                                   vm: RegUnit,
+                                   vd: RegUnit,
+                                   sink: &mut CS) {
+    let double = vfp_double(bits);
+    let i = vfp_word(bits) | vfp_vn(vn, double) | vfp_vm(vm, double) | vfp_vd(vd, double);
+    put_vfp_word(bits, i, sink);
+}
+
+/// VFP data-processing instructions with two registers, `vsqrt.f32 sd, sm` and friends. The
+/// `opc2` field takes the place of the `Vn` field.
+fn put_vfp2<CS: CodeSink + ?Sized>(bits: u16, vm: RegUnit, vd: RegUnit, sink: &mut CS) {
+    let double = vfp_double(bits);
+    let i = vfp_word(bits) | vfp_vm(vm, double) | vfp_vd(vd, double);
+    put_vfp_word(bits, i, sink);
+}
+
+/// Conversions between single and double precision. The `sz` bit describes the `vm` operand,
+/// and `vd` has the other precision.
+fn put_vfp_cvt<CS: CodeSink + ?Sized>(bits: u16, vm: RegUnit, vd: RegUnit, sink: &mut CS) {
+    let double = vfp_double(bits);
+    let i = vfp_word(bits) | vfp_vm(vm, double) | vfp_vd(vd, !double);
+    put_vfp_word(bits, i, sink);
+}
+
+/// Move between a core register and a single precision register, `vmov rt, sn` or `vmov sn, rt`.
+///
+///   31   27      20 19 15 11   7 6  4 3
+///   cond 1110000 op Vn Rt 1010 N 00 1 0000
+///   28   21      20 16 12 8    7 5  4 0
+fn put_vmov<CS: CodeSink + ?Sized>(bits: u16, rt: RegUnit, sn: RegUnit, sink: &mut CS) {
+    let i = vfp_word(bits) | ((rt as u32 & 0xf) << 12) | vfp_vn(sn, false);
+    put_vfp_word(bits, i, sink);
+}
+
+/// Convert an integer in the core register `rt` to floating point:
+///
+///   vmov sd, rt
+///   vcvt.f32.s32 vd, sd
+///
+/// The single precision register `sd` is `vd` itself or its low half.
+///
+/// Encoding bits: The `vcvt` instruction.
+fn put_vfp_from_int<CS: CodeSink + ?Sized>(bits: u16, rt: RegUnit, vd: RegUnit, sink: &mut CS) {
+    put_vmov(bits & THUMB | VMOV_TO_SREG, rt, vd, sink);
+    let double = vfp_double(bits);
+    let i = vfp_word(bits) | vfp_vm(vd, false) | vfp_vd(vd, double);
+    put_vfp_word(bits, i, sink);
+}
+
+/// Encoding bits of the `vmov sn, rt` instruction.
+const VMOV_TO_SREG: u16 = 0b0001 << 8;
+
+/// VFP loads and stores, `vldr sd, [rn, #offset]` and `vstr sd, [rn, #offset]`.
+///
+///   31   27   23 22 21 20 19 15 11  8  7
+///   cond 1101 U  D  0  L  Rn Vd 101 sz imm8
+///   28   24   23 22 21 20 16 12 9   8  0
+///
+/// The offset is a multiple of 4, and the `U` bit is cleared for negative offsets.
+///
+/// Encoding bits: `l | (sz << 12)`.
+fn put_vldst<CS: CodeSink + ?Sized>(bits: u16,
+                                    rn: RegUnit,
+                                    offset: i64,
+                                    vd: RegUnit,
+                                    sink: &mut CS) {
+    let double = vfp_double(bits);
+    let imm8 = (offset.abs() >> 2) as u32;
+    assert!(imm8 < 0x100, "Offset {} out of range", offset);
+    let mut i = 0xed00_0a00 | imm8;
+    if offset >= 0 {
+        i |= 1 << 23;
+    }
+    i |= (bits as u32 & 1) << 20;
+    i |= (rn as u32 & 0xf) << 16;
+    i |= (double as u32) << 8;
+    i |= vfp_vd(vd, double);
+    put_vfp_word(bits, i, sink);
+}
+
+/// Put a NEON instruction word in the instruction set selected by the `THUMB` encoding bit.
+///
+/// The T32 encodings have a different top byte, and the `U` bit moves from bit 24 to bit 28.
+fn put_neon_word<CS: CodeSink + ?Sized>(bits: u16, word: u32, top: u32, sink: &mut CS) {
+    if bits & THUMB != 0 {
+        let u = (word >> 24) & 1;
+        put_t32_word((word & 0x00ff_ffff) | top | (u << 28), sink);
+    } else {
+        sink.put4(word);
+    }
+}
+
+/// NEON instructions with three quad registers, `vadd.i32 qd, qn, qm` and friends.
+///
+///   31      24 23 22 21   19 15 11  7 6 5 4  3
+///   1111001 U  0  D  size Vn Vd opc N 1 M op Vm
+///   25      24 23 22 20   16 12 8   7 6 5 4  0
+///
+/// Encoding bits: `u | (size << 1) | (opc << 3) | (op << 7)`.
+fn put_neon3<CS: CodeSink + ?Sized>(bits: u16,
+                                    qn: RegUnit,
+                                    qm: RegUnit,
+                                    qd: RegUnit,
+                                    sink: &mut CS) {
+    let b = bits as u32;
+    let mut i = 0xf200_0040;
+    i |= (b & 1) << 24;
+    i |= ((b >> 1) & 3) << 20;
+    i |= ((b >> 3) & 0xf) << 8;
+    i |= ((b >> 7) & 1) << 4;
+    i |= vfp_vn(qn, true) | vfp_vm(qm, true) | vfp_vd(qd, true);
+    put_neon_word(bits, i, 0xef00_0000, sink);
+}
+
+/// Load or store a quad register, `vld1.64 {dd, dd+1}, [rn]` or `vst1.64 {dd, dd+1}, [rn]`.
+///
+///   31       23 22 21 20 19 15 11   7    5     3
+///   11110100 0  D  L  0  Rn Vd 1010 size align 1111
+///   24       23 22 21 20 16 12 8    6    4     0
+///
+/// Encoding bits: The `L` bit.
+fn put_vld1<CS: CodeSink + ?Sized>(bits: u16, rn: RegUnit, qd: RegUnit, sink: &mut CS) {
+    let mut i = 0xf400_0acf;
+    i |= (bits as u32 & 1) << 21;
+    i |= (rn as u32 & 0xf) << 16;
+    i |= vfp_vd(qd, true);
+    put_neon_word(bits, i, 0xf900_0000, sink);
+}
@@ -0,0 +1,322 @@
+//! ARM32 Instruction Set Architecture.
+
+pub mod settings;
+mod abi;
+mod binemit;
+mod enc_tables;
+mod registers;
+
+use super::super::settings as shared_settings;
+use binemit::CodeSink;
+use isa::enc_tables::{self as shared_enc_tables, lookup_enclist, general_encoding,
+                      visit_encodings};
+use isa::Builder as IsaBuilder;
+use isa::{TargetIsa, RegInfo, RegClass, Encoding, Legalize, RecipeConstraints, RecipeSizing};
+use ir::{Function, Inst, InstructionData, DataFlowGraph, Signature, ArgumentType, Type, types};
+use regalloc::AllocatableSet;
+
+#[allow(dead_code)]
+struct Isa {
+    shared_flags: shared_settings::Flags,
+    isa_flags: settings::Flags,
+    cpumode: &'static [shared_enc_tables::Level1Entry<u16>],
+}
+
+/// Get an ISA builder for creating ARM32 targets.
+pub fn isa_builder() -> IsaBuilder {
+    IsaBuilder {
+        setup: settings::builder(),
+        constructor: isa_constructor,
+    }
+}
+
+fn isa_constructor(shared_flags: shared_settings::Flags,
+                   builder: &shared_settings::Builder)
+                   -> Box<TargetIsa> {
+    let isa_flags = settings::Flags::new(&shared_flags, builder);
+    let level1 = if isa_flags.is_thumb() {
+        &enc_tables::LEVEL1_T32[..]
+    } else {
+        &enc_tables::LEVEL1_A32[..]
+    };
+    Box::new(Isa {
+        isa_flags: isa_flags,
+        shared_flags: shared_flags,
+        cpumode: level1,
+    })
+}
+
+impl TargetIsa for Isa {
+    fn name(&self) -> &'static str {
+        "arm32"
+    }
+
+    fn flags(&self) -> &shared_settings::Flags {
+        &self.shared_flags
+    }
+
+    fn register_info(&self) -> RegInfo {
+        registers::INFO.clone()
+    }
+
+    fn regclass_for_abi_type(&self, ty: Type) -> RegClass {
+        abi::regclass_for_abi_type(ty, &self.isa_flags)
+    }
+
+    fn allocatable_registers(&self, func: &Function) -> AllocatableSet {
+        abi::allocatable_registers(func, &self.isa_flags)
+    }
+
+    fn stack_alignment(&self) -> u32 {
+        abi::STACK_ALIGNMENT
+    }
+
+    fn saved_registers(&self, func: &Function) -> Vec<ArgumentType> {
+        abi::saved_registers(func, &self.isa_flags)
+    }
+
+    /// ARM32 is always a 32-bit ISA, regardless of the `is_64bit` setting.
+    fn pointer_type(&self) -> Type {
+        types::I32
+    }
+
+    fn encode(&self, dfg: &DataFlowGraph, inst: &InstructionData) -> Result<Encoding, Legalize> {
+        let ctrl_typevar = inst.ctrl_typevar(dfg);
+        lookup_enclist(ctrl_typevar,
+                       inst.opcode(),
+                       self.cpumode,
+                       &enc_tables::LEVEL2[..])
+            .and_then(|enclist_offset| {
+                general_encoding(enclist_offset,
+                                 &enc_tables::ENCLISTS[..],
+                                 |instp| enc_tables::check_instp(inst, instp, dfg),
+                                 |isap| self.isa_flags.numbered_predicate(isap as usize))
+            })
+            .map_err(|action| {
+                // The vector types are supported by the CPU modes when NEON is available. Vector
+                // instructions without a NEON encoding are split into halves, all the way down
+                // to scalars if necessary.
+                if ctrl_typevar.is_scalar() {
+                    action
+                } else {
+                    Legalize::Narrow
+                }
+            })
+    }
+
+    fn legal_encodings(&self, dfg: &DataFlowGraph, inst: &InstructionData) -> Vec<Encoding> {
+        let mut encodings = Vec::new();
+        if let Ok(enclist_offset) = lookup_enclist(inst.ctrl_typevar(dfg),
+                                                   inst.opcode(),
+                                                   self.cpumode,
+                                                   &enc_tables::LEVEL2[..]) {
+            visit_encodings(enclist_offset,
+                            &enc_tables::ENCLISTS[..],
+                            |instp| enc_tables::check_instp(inst, instp, dfg),
+                            |isap| self.isa_flags.numbered_predicate(isap as usize),
+                            |enc| encodings.push(enc));
+        }
+        encodings
+    }
+
+    fn recipe_names(&self) -> &'static [&'static str] {
+        &enc_tables::RECIPE_NAMES[..]
+    }
+
+    fn recipe_constraints(&self) -> &'static [RecipeConstraints] {
+        &enc_tables::RECIPE_CONSTRAINTS
+    }
+
+    fn recipe_sizing(&self) -> &'static [RecipeSizing] {
+        &enc_tables::RECIPE_SIZING
+    }
+
+    fn legalize_signature(&self, sig: &mut Signature) {
+        abi::legalize_signature(sig, &self.isa_flags)
+    }
+
+    fn emit_inst(&self, func: &Function, inst: Inst, sink: &mut CodeSink) {
+        binemit::emit_inst(func, inst, sink)
+    }
+
+    fn reloc_names(&self) -> &'static [&'static str] {
+        &binemit::RELOC_NAMES
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use settings::{self, Configurable};
+    use isa::{self, Legalize, OperandConstraint, ConstraintKind};
+    use ir::{DataFlowGraph, InstructionData, Opcode, Value};
+    use ir::{types, immediates};
+
+    fn encstr(isa: &isa::TargetIsa, enc: isa::Encoding) -> String {
+        isa.display_enc(enc).to_string()
+    }
+
+    fn isa_with(flags: &[&str]) -> Box<isa::TargetIsa> {
+        let mut isa_builder = isa::lookup("arm32").unwrap();
+        for flag in flags {
+            isa_builder.set_bool(flag, true).unwrap();
+        }
+        isa_builder.finish(settings::Flags::new(&settings::builder()))
+    }
+
+    fn binimm(opcode: Opcode, arg: Value, imm: i64) -> InstructionData {
+        InstructionData::BinaryImm {
+            opcode: opcode,
+            ty: types::I32,
+            arg: arg,
+            imm: immediates::Imm64::new(imm),
+        }
+    }
+
+    #[test]
+    fn test_immediates() {
+        let a32 = isa_with(&[]);
+        let t32 = isa_with(&["is_thumb"]);
+
+        let mut dfg = DataFlowGraph::new();
+        let ebb = dfg.make_ebb();
+        let arg32 = dfg.append_ebb_arg(ebb, types::I32);
+
+        // Negative immediates are emitted as `sub`.
+        let add = binimm(Opcode::IaddImm, arg32, -100);
+        assert_eq!(encstr(&*a32, a32.encode(&dfg, &add).unwrap()), "Iaddsub#28");
+        assert_eq!(encstr(&*t32, t32.encode(&dfg, &add).unwrap()), "TIaddsub#f200");
+
+        // T32 has a 12-bit immediate form, A32 only has modified immediates.
+        let add_large = binimm(Opcode::IaddImm, arg32, 0xfff);
+        assert_eq!(a32.encode(&dfg, &add_large), Err(Legalize::Expand));
+        assert_eq!(encstr(&*t32, t32.encode(&dfg, &add_large).unwrap()), "TIaddsub#f200");
+        let add_huge = binimm(Opcode::IaddImm, arg32, 0x1000);
+        assert_eq!(t32.encode(&dfg, &add_huge), Err(Legalize::Expand));
+
+        // Logical immediates are 8 bits.
+        let and = binimm(Opcode::BandImm, arg32, 0xff);
+        assert_eq!(encstr(&*a32, a32.encode(&dfg, &and).unwrap()), "Ilogic#20");
+        assert_eq!(encstr(&*t32, t32.encode(&dfg, &and).unwrap()), "TIlogic#f000");
+        let and_large = binimm(Opcode::BandImm, arg32, 0x100);
+        assert_eq!(a32.encode(&dfg, &and_large), Err(Legalize::Expand));
+        assert_eq!(t32.encode(&dfg, &and_large), Err(Legalize::Expand));
+    }
+
+    #[test]
+    fn test_narrow() {
+        let isa = isa_with(&[]);
+
+        let mut dfg = DataFlowGraph::new();
+        let ebb = dfg.make_ebb();
+        let arg64 = dfg.append_ebb_arg(ebb, types::I64);
+        let arg8 = dfg.append_ebb_arg(ebb, types::I8);
+
+        // There is no i64 arithmetic on a 32-bit ISA.
+        let add64 = InstructionData::Binary {
+            opcode: Opcode::Iadd,
+            ty: types::I64,
+            args: [arg64, arg64],
+        };
+        assert_eq!(isa.encode(&dfg, &add64), Err(Legalize::Narrow));
+
+        // There is no i8 arithmetic either.
+        let add8 = InstructionData::Binary {
+            opcode: Opcode::Iadd,
+            ty: types::I8,
+            args: [arg8, arg8],
+        };
+        assert_eq!(isa.encode(&dfg, &add8), Err(Legalize::Widen));
+    }
+
+    #[test]
+    fn test_hwdiv() {
+        let mut dfg = DataFlowGraph::new();
+        let ebb = dfg.make_ebb();
+        let arg32 = dfg.append_ebb_arg(ebb, types::I32);
+        let udiv = InstructionData::Binary {
+            opcode: Opcode::Udiv,
+            ty: types::I32,
+            args: [arg32, arg32],
+        };
+
+        // The division instructions are optional.
+        let isa = isa_with(&[]);
+        assert_eq!(isa.encode(&dfg, &udiv), Err(Legalize::Expand));
+
+        let isa = isa_with(&["has_hwdiv"]);
+        assert_eq!(encstr(&*isa, isa.encode(&dfg, &udiv).unwrap()), "Rdiv#173");
+        let isa = isa_with(&["is_thumb", "has_hwdiv"]);
+        assert_eq!(encstr(&*isa, isa.encode(&dfg, &udiv).unwrap()), "TRdiv#fbb0");
+    }
+
+    #[test]
+    fn test_vectors() {
+        let mut dfg = DataFlowGraph::new();
+        let ebb = dfg.make_ebb();
+        let x32 = dfg.append_ebb_arg(ebb, types::I32.by(4).unwrap());
+        let x64 = dfg.append_ebb_arg(ebb, types::I64.by(2).unwrap());
+        let add = InstructionData::Binary {
+            opcode: Opcode::Iadd,
+            ty: types::I32.by(4).unwrap(),
+            args: [x32, x32],
+        };
+        let mul = InstructionData::Binary {
+            opcode: Opcode::Imul,
+            ty: types::I64.by(2).unwrap(),
+            args: [x64, x64],
+        };
+
+        // Without NEON, vector instructions are split.
+        let isa = isa_with(&[]);
+        assert_eq!(isa.encode(&dfg, &add), Err(Legalize::Narrow));
+
+        let isa = isa_with(&["has_neon"]);
+        assert_eq!(encstr(&*isa, isa.encode(&dfg, &add).unwrap()), "Rq#44");
+        // There is no 64-bit lane multiplication.
+        assert_eq!(isa.encode(&dfg, &mul), Err(Legalize::Narrow));
+    }
+
+    #[test]
+    fn recipe_constraints() {
+        let isa = isa_with(&["is_thumb"]);
+        let regs = isa.register_info();
+        let gpr = &regs.classes[0];
+        let gprl = &regs.classes[1];
+        assert_eq!(gpr.name, "GPR");
+        assert_eq!(gprl.name, "GPRL");
+
+        let constraints = |name| {
+            let recipe = isa.recipe_names().iter().position(|&n| n == name).unwrap();
+            isa.recipe_constraints()[recipe]
+        };
+        let reg = |rc| {
+            OperandConstraint {
+                kind: ConstraintKind::Reg,
+                regclass: rc,
+            }
+        };
+
+        let trrr = constraints("TRrr");
+        assert_eq!(trrr.ins, &[reg(gpr), reg(gpr)]);
+        assert_eq!(trrr.outs, &[reg(gpr)]);
+
+        // Most 16-bit instructions can only use the low registers, and the first operand is also
+        // the output.
+        let trtied16 = constraints("TRtied16");
+        assert_eq!(trtied16.ins, &[reg(gprl), reg(gprl)]);
+        assert_eq!(trtied16.outs,
+                   &[OperandConstraint {
+                         kind: ConstraintKind::Tied(0),
+                         regclass: gprl,
+                     }]);
+
+        // The remainder is computed after the quotient, so the output can't overwrite an input.
+        let trrem = constraints("TRrem");
+        assert_eq!(trrem.ins, &[reg(gpr), reg(gpr)]);
+        assert_eq!(trrem.outs,
+                   &[OperandConstraint {
+                         kind: ConstraintKind::EarlyClobber,
+                         regclass: gpr,
+                     }]);
+    }
+}
@@ -0,0 +1,56 @@
+//! ARM32 register descriptions.
+
+use isa::registers::{RegBank, RegClass, RegClassData, RegInfo};
+
+include!(concat!(env!("OUT_DIR"), "/registers-arm32.rs"));
+
+#[cfg(test)]
+mod tests {
+    use super::{INFO, GPR, GPRL, SPR, DPR, DPRL, QPR};
+    use isa::RegUnit;
+
+    #[test]
+    fn unit_encodings() {
+        assert_eq!(INFO.parse_regunit("r0"), Some(0));
+        assert_eq!(INFO.parse_regunit("r15"), Some(15));
+        assert_eq!(INFO.parse_regunit("s0"), Some(16));
+        assert_eq!(INFO.parse_regunit("s63"), Some(79));
+
+        assert_eq!(INFO.parse_regunit("r16"), None);
+        assert_eq!(INFO.parse_regunit("s64"), None);
+        assert_eq!(INFO.parse_regunit("d0"), None);
+    }
+
+    #[test]
+    fn unit_names() {
+        fn uname(ru: RegUnit) -> String {
+            INFO.display_regunit(ru).to_string()
+        }
+
+        assert_eq!(uname(0), "%r0");
+        assert_eq!(uname(13), "%r13");
+        assert_eq!(uname(16), "%s0");
+        assert_eq!(uname(79), "%s63");
+        assert_eq!(uname(80), "%INVALID80");
+    }
+
+    #[test]
+    fn classes() {
+        assert!(GPR.contains(GPR.unit(15)));
+        assert!(GPRL.contains(GPRL.unit(7)));
+        assert!(!GPRL.contains(GPR.unit(8)));
+        assert!(!SPR.contains(GPR.unit(0)));
+
+        // The double and quad registers overlap the single precision registers.
+        assert_eq!(DPR.unit(1), SPR.unit(2));
+        assert_eq!(QPR.unit(1), DPR.unit(2));
+        assert!(DPR.contains(SPR.unit(30)));
+        assert!(!DPR.contains(SPR.unit(31)));
+
+        // Only `d0`-`d15` have single precision halves.
+        assert!(DPR.contains(DPR.unit(31)));
+        assert!(DPRL.contains(DPR.unit(15)));
+        assert!(!DPRL.contains(DPR.unit(16)));
+        assert!(!SPR.contains(DPR.unit(16)));
+    }
+}
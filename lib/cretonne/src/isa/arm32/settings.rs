@@ -0,0 +1,49 @@
+//! ARM32 Settings.
+
+use settings::{self, detail, Builder};
+use std::fmt;
+
+// Include code generated by `lib/cretonne/meta/gen_settings.py`. This file contains a public
+// `Flags` struct with an impl for all of the settings defined in
+// `lib/cretonne/meta/isa/arm32/settings.py`.
+include!(concat!(env!("OUT_DIR"), "/settings-arm32.rs"));
+
+#[cfg(test)]
+mod tests {
+    use super::{builder, Flags};
+    use settings::{self, Configurable};
+
+    #[test]
+    fn display_default() {
+        let shared = settings::Flags::new(&settings::builder());
+        let b = builder();
+        let f = Flags::new(&shared, &b);
+        assert_eq!(f.to_string(),
+                   "[arm32]\n\
+                    is_thumb = false\n\
+                    has_vfp = false\n\
+                    has_neon = false\n\
+                    has_hwdiv = false\n");
+        assert_eq!(f.use_vfp(), false);
+        assert_eq!(f.use_neon(), false);
+    }
+
+    #[test]
+    fn predicates() {
+        let shared = settings::Flags::new(&settings::builder());
+        let mut b = builder();
+        b.set_bool("has_vfp", true).unwrap();
+        b.set_bool("has_neon", true).unwrap();
+        let f = Flags::new(&shared, &b);
+        assert_eq!(f.use_vfp(), true);
+        assert_eq!(f.use_neon(), true);
+
+        let mut sb = settings::builder();
+        sb.set_bool("enable_float", false).unwrap();
+        sb.set_bool("enable_simd", false).unwrap();
+        let shared = settings::Flags::new(&sb);
+        let f = Flags::new(&shared, &b);
+        assert_eq!(f.use_vfp(), false);
+        assert_eq!(f.use_neon(), false);
+    }
+}
@@ -0,0 +1,196 @@
+//! ARM64 ABI implementation.
+//!
+//! This module implements the AAPCS64 procedure call standard through the primary
+//! `legalize_signature()` entry point.
+//!
+//! When floating point is disabled, floating point arguments are passed according to the integer
+//! calling convention.
+
+use abi::{ArgAction, ArgAssigner, ValueConversion, legalize_args};
+use ir::{Function, Signature, ArgumentType, ArgumentLoc, ArgumentExtension, ArgumentPurpose,
+         Type, ValueLoc};
+use ir::instructions::CallInfo;
+use isa::RegClass;
+use regalloc::AllocatableSet;
+use ir::types;
+use settings as shared_settings;
+use super::registers::{GPR, FPR};
+use super::settings;
+
+/// The stack pointer is always 16-byte aligned.
+pub const STACK_ALIGNMENT: u32 = 16;
+
+/// The return address is passed in the link register `x30`.
+const LINK_REG: usize = 30;
+
+/// The callee-saved integer registers are `x19`-`x28`. The frame pointer `x29` is reserved.
+const CALLEE_SAVED_GPRS: [usize; 10] = [19, 20, 21, 22, 23, 24, 25, 26, 27, 28];
+
+/// The low 64 bits of `v8`-`v15` are callee-saved.
+const CALLEE_SAVED_FPRS: [usize; 8] = [8, 9, 10, 11, 12, 13, 14, 15];
+
+struct Args {
+    pointer_bits: u16,
+    pointer_bytes: u32,
+    pointer_type: Type,
+    use_fp: bool,
+    regs: u32,
+    fregs: u32,
+    offset: u32,
+}
+
+impl Args {
+    fn new(pointer_type: Type, isa_flags: &settings::Flags) -> Args {
+        Args {
+            pointer_bits: pointer_type.bits(),
+            pointer_bytes: pointer_type.bytes(),
+            pointer_type: pointer_type,
+            use_fp: isa_flags.use_fp(),
+            regs: 0,
+            fregs: 0,
+            offset: 0,
+        }
+    }
+}
+
+impl ArgAssigner for Args {
+    fn assign(&mut self, arg: &ArgumentType) -> ArgAction {
+        let ty = arg.value_type;
+
+        // Vectors are broken down until they fit in a register.
+        if !ty.is_scalar() {
+            return ValueConversion::VectorSplit.into();
+        }
+
+        if ty.is_float() && self.use_fp {
+            if self.fregs < 8 {
+                let reg = FPR.unit(self.fregs as usize);
+                self.fregs += 1;
+                return ArgumentLoc::Reg(reg).into();
+            }
+            // Floating point arguments that don't fit in `v0`-`v7` go on the stack. They are
+            // never passed in integer registers.
+            return self.stack_slot();
+        }
+
+        if ty.bits() > self.pointer_bits {
+            // Large integers and booleans are broken down to fit in a register. The low half is
+            // passed first.
+            return ValueConversion::IntSplit.into();
+        }
+
+        // Small integers are extended to the size of a pointer register.
+        if ty.is_int() && ty.bits() < self.pointer_bits {
+            match arg.extension {
+                ArgumentExtension::None => {}
+                ArgumentExtension::Uext => return ValueConversion::Uext(self.pointer_type).into(),
+                ArgumentExtension::Sext => return ValueConversion::Sext(self.pointer_type).into(),
+            }
+        }
+
+        if self.regs < 8 {
+            // Assign to one of `x0`-`x7`.
+            let reg = GPR.unit(self.regs as usize);
+            self.regs += 1;
+            ArgumentLoc::Reg(reg).into()
+        } else {
+            self.stack_slot()
+        }
+    }
+}
+
+impl Args {
+    /// Assign a stack location. Every argument on the stack occupies a pointer-sized slot.
+    fn stack_slot(&mut self) -> ArgAction {
+        let loc = ArgumentLoc::Stack(self.offset as i32);
+        self.offset += self.pointer_bytes;
+        loc.into()
+    }
+}
+
+/// Legalize `sig` for ARM64.
+///
+/// Up to eight integer arguments are passed in `x0`-`x7`, and up to eight floating point
+/// arguments in `v0`-`v7`. The remaining arguments are passed on the stack. Return values use the
+/// same registers.
+pub fn legalize_signature(sig: &mut Signature,
+                          flags: &shared_settings::Flags,
+                          isa_flags: &settings::Flags) {
+    let pointer_type = if flags.is_64bit() {
+        types::I64
+    } else {
+        types::I32
+    };
+
+    let mut args = Args::new(pointer_type, isa_flags);
+    legalize_args(&mut sig.argument_types, &mut args);
+
+    let mut rets = Args::new(pointer_type, isa_flags);
+    legalize_args(&mut sig.return_types, &mut rets);
+}
+
+/// Get register class for a type appearing in a legalized signature.
+pub fn regclass_for_abi_type(ty: Type, isa_flags: &settings::Flags) -> RegClass {
+    if ty.is_float() && isa_flags.use_fp() {
+        FPR
+    } else {
+        GPR
+    }
+}
+
+/// Get the set of allocatable registers for `func`.
+pub fn allocatable_registers(_func: &Function) -> AllocatableSet {
+    let mut regs = AllocatableSet::new();
+    // The intra-procedure-call scratch registers `x16` and `x17` are clobbered by linker veneers,
+    // and the platform register `x18` is reserved. The frame pointer `x29`, the link register
+    // `x30`, and the stack pointer or zero register `x31` are also reserved.
+    for &unit in &[16, 17, 18, 29, 30, 31] {
+        regs.take(GPR, GPR.unit(unit));
+    }
+    regs
+}
+
+/// Get the registers that must be saved by the prologue of `func`.
+///
+/// These are the callee-saved integer and floating point registers used by `func`, and the link
+/// register if `func` contains any calls. Only the low 64 bits of the floating point registers
+/// are callee-saved.
+pub fn saved_registers(func: &Function,
+                       pointer_type: Type,
+                       isa_flags: &settings::Flags)
+                       -> Vec<ArgumentType> {
+    let mut saved = Vec::new();
+
+    let has_calls = func.layout
+        .ebbs()
+        .flat_map(|ebb| func.layout.ebb_insts(ebb))
+        .any(|inst| match func.dfg[inst].analyze_call() {
+            CallInfo::NotACall => false,
+            _ => true,
+        });
+    if has_calls {
+        saved.push(saved_register(pointer_type, ArgumentPurpose::Link, GPR, LINK_REG));
+    }
+
+    let mut candidates: Vec<(RegClass, usize, Type)> =
+        CALLEE_SAVED_GPRS.iter().map(|&unit| (GPR, unit, pointer_type)).collect();
+    if isa_flags.use_fp() {
+        candidates.extend(CALLEE_SAVED_FPRS.iter().map(|&unit| (FPR, unit, types::F64)));
+    }
+    for (rc, unit, ty) in candidates {
+        let loc = ValueLoc::Reg(rc.unit(unit));
+        if func.locations.keys().any(|v| func.locations[v] == loc) {
+            saved.push(saved_register(ty, ArgumentPurpose::CalleeSaved, rc, unit));
+        }
+    }
+
+    saved
+}
+
+/// Make a special-purpose argument for the saved register `unit` in `rc`.
+fn saved_register(ty: Type, purpose: ArgumentPurpose, rc: RegClass, unit: usize) -> ArgumentType {
+    let mut arg = ArgumentType::new(ty);
+    arg.purpose = purpose;
+    arg.location = ArgumentLoc::Reg(rc.unit(unit));
+    arg
+}
@@ -0,0 +1,727 @@
+//! Emitting binary ARM64 machine code.
+
+use binemit::{CodeSink, Reloc, bad_encoding, stack_slot_offset, globalsym_name};
+use ir::{Function, Inst, InstructionData};
+use ir::atomics::AtomicOrdering;
+use ir::condcodes::{IntCC, FloatCC};
+use isa::RegUnit;
+
+include!(concat!(env!("OUT_DIR"), "/binemit-arm64.rs"));
+
+/// ARM64 relocation kinds.
+pub enum RelocKind {
+    /// A 26-bit PC-relative branch offset in a `bl` instruction.
+    Call26,
+    /// The page of an address relative to the page of the PC in an `adrp` instruction.
+    AdrPrelPgHi21,
+    /// The low 12 bits of an absolute address in an `add` instruction.
+    AddAbsLo12Nc,
+}
+
+/// The names of the ARM64 relocation kinds, using the ELF names.
+pub static RELOC_NAMES: [&'static str; 3] = ["R_AARCH64_CALL26",
+                                             "R_AARCH64_ADR_PREL_PG_HI21",
+                                             "R_AARCH64_ADD_ABS_LO12_NC"];
+
+impl Into<Reloc> for RelocKind {
+    fn into(self) -> Reloc {
+        Reloc(self as u16)
+    }
+}
+
+/// The link register `x30` holding the return address.
+const LINK_REGISTER: RegUnit = 30;
+
+/// Register number 31 is the stack pointer when it is the base register of a load or store, and
+/// in the `add` and `sub` immediate instructions.
+const STACK_POINTER: RegUnit = 31;
+
+/// Register number 31 is the zero register in most other instructions.
+const ZERO_REGISTER: RegUnit = 31;
+
+/// The `sf` bit in the encoding bits which selects a 64-bit operation.
+const SF: u16 = 1 << 10;
+
+/// The `op` bit in the encoding bits of `add` and `sub` which selects subtraction.
+const ADDSUB_OP: u16 = 1 << 9;
+
+/// The `opc` bits in the encoding bits of the move wide instructions, `movz` = 0b10.
+const MOVEWIDE_OPC: u16 = 0b11 << 8;
+
+/// Encoding bits of the `movn` instruction, relative to `movz`.
+const MOVN: u16 = 0b00 << 8;
+
+/// Encoding bits of the `movk` instruction, relative to `movz`.
+const MOVK: u16 = 0b11 << 8;
+
+/// The `op` bit in the encoding bits of `cbz` which selects `cbnz`.
+const CBZ_OP: u16 = 1 << 3;
+
+/// Encoding bits of the `b` instruction.
+const BIMM_B: u16 = 0b00101 << 5;
+
+/// The `adrp` instruction.
+const ADRP: u32 = 0x9000_0000;
+
+/// The permanently undefined instruction `udf #0`, which raises an exception.
+const UDF: u32 = 0x0000_0000;
+
+/// The `b.cond` instruction.
+const BCOND: u32 = 0x5400_0000;
+
+/// The `cset wd, cond` instruction is `csinc wd, wzr, wzr, invert(cond)`.
+const CSET: u32 = 0x1a9f_07e0;
+
+/// The `cmp wn, #0` instruction which is `subs wzr, wn, #0`.
+const CMP_W_ZERO: u32 = 0x7100_001f;
+
+/// The `cmn wn, #1` instruction which is `adds wzr, wn, #1`.
+const CMN_W_ONE: u32 = 0x3100_041f;
+
+/// The `ccmp wn, #1, #0, eq` instruction.
+const CCMP_W_ONE_EQ: u32 = 0x7a41_0800;
+
+/// The `dmb ish` full memory barrier.
+const DMB_ISH: u32 = 0xd503_3bbf;
+
+/// The `dmb ishld` barrier which orders loads against later loads and stores.
+const DMB_ISHLD: u32 = 0xd503_39bf;
+
+/// The `ne` condition code.
+const COND_NE: u32 = 0b0001;
+
+/// The `vc` condition code.
+const COND_VC: u32 = 0b0111;
+
+/// Get the instruction word with the fixed bits 31:21 from the encoding bits.
+fn hi(bits: u16) -> u32 {
+    (bits as u32 & 0x7ff) << 21
+}
+
+/// Get the 5-bit `op` field from the encoding bits.
+fn op(bits: u16) -> u32 {
+    bits as u32 >> 11
+}
+
+/// Get the `sf` bit from the encoding bits, positioned at bit 31 of an instruction.
+fn sf(bits: u16) -> u32 {
+    ((bits & SF) as u32) << 21
+}
+
+/// Get the width of the operation in bits, as selected by the `sf` bit.
+fn width(bits: u16) -> u32 {
+    if bits & SF != 0 { 64 } else { 32 }
+}
+
+/// Three-register instructions.
+///
+///   31      20 15 9  4
+///   hi      Rm op Rn Rd
+///   21      16 10 5  0
+///
+/// This also covers the 1-source data-processing instructions with a zero `Rm` field, and the
+/// floating point comparisons with a zero `Rd` field.
+///
+/// Encoding bits: `bits[31:21] | (op << 11)` where `op` is bits 14:10.
+fn put_rrr<CS: CodeSink + ?Sized>(bits: u16,
+                                  rn: RegUnit,
+                                  rm: RegUnit,
+                                  rd: RegUnit,
+                                  sink: &mut CS) {
+    let rn = rn as u32 & 0x1f;
+    let rm = rm as u32 & 0x1f;
+    let rd = rd as u32 & 0x1f;
+
+    let mut i = hi(bits);
+    i |= rd;
+    i |= rn << 5;
+    i |= op(bits) << 10;
+    i |= rm << 16;
+
+    sink.put4(i);
+}
+
+/// Four-register instructions, `madd rd, rn, rm, ra` and `fmadd`.
+///
+///   31      20 15 14 9  4
+///   hi      Rm o0 Ra Rn Rd
+///   21      16 15 10 5  0
+///
+/// Encoding bits: `bits[31:21] | (o0 << 11)`.
+fn put_rrrr<CS: CodeSink + ?Sized>(bits: u16,
+                                   rn: RegUnit,
+                                   rm: RegUnit,
+                                   ra: RegUnit,
+                                   rd: RegUnit,
+                                   sink: &mut CS) {
+    let rn = rn as u32 & 0x1f;
+    let rm = rm as u32 & 0x1f;
+    let ra = ra as u32 & 0x1f;
+    let rd = rd as u32 & 0x1f;
+
+    let mut i = hi(bits);
+    i |= rd;
+    i |= rn << 5;
+    i |= ra << 10;
+    i |= op(bits) << 15;
+    i |= rm << 16;
+
+    sink.put4(i);
+}
+
+/// Get the encoding bits of the `msub` instruction matching the width of a division.
+fn msub(bits: u16) -> u16 {
+    (bits & SF) | (0b11011 << 3) | (1 << 11)
+}
+
+/// One-source floating point instructions.
+///
+///   31      20     14    9  4
+///   hi      opcode 10000 Rn Rd
+///   21      15     10    5  0
+///
+/// Encoding bits: `bits[31:21] | (opcode << 11)`.
+fn put_fp1<CS: CodeSink + ?Sized>(bits: u16, rn: RegUnit, rd: RegUnit, sink: &mut CS) {
+    let rn = rn as u32 & 0x1f;
+    let rd = rd as u32 & 0x1f;
+
+    let mut i = hi(bits);
+    i |= rd;
+    i |= rn << 5;
+    i |= 0b10000 << 10;
+    i |= op(bits) << 15;
+
+    sink.put4(i);
+}
+
+/// Conversions between floating point and integer registers.
+///
+///   31      20           15     9  4
+///   hi      rmode:opcode 000000 Rn Rd
+///   21      16           10     5  0
+///
+/// Encoding bits: `bits[31:21] | ((rmode << 3 | opcode) << 11)`.
+fn put_fcvti<CS: CodeSink + ?Sized>(bits: u16, rn: RegUnit, rd: RegUnit, sink: &mut CS) {
+    let rn = rn as u32 & 0x1f;
+    let rd = rd as u32 & 0x1f;
+
+    let mut i = hi(bits);
+    i |= rd;
+    i |= rn << 5;
+    i |= op(bits) << 16;
+
+    sink.put4(i);
+}
+
+/// Add or subtract an immediate.
+///
+///   31 30 29 28     22 21    9  4
+///   sf op S  100010 sh imm12 Rn Rd
+///   31 30 29 23     22 10    5  0
+///
+/// Negative immediates are emitted as the opposite operation. Immediates with the low 12 bits
+/// clear use the shifted form.
+///
+/// Encoding bits: `bits[31:21]` of the `add` or `adds` instruction.
+fn put_addsub_imm<CS: CodeSink + ?Sized>(bits: u16,
+                                         rn: RegUnit,
+                                         imm: i64,
+                                         rd: RegUnit,
+                                         sink: &mut CS) {
+    let (bits, imm) = if imm < 0 {
+        (bits ^ ADDSUB_OP, imm.wrapping_neg() as u64)
+    } else {
+        (bits, imm as u64)
+    };
+    let (sh, imm12) = if imm < 0x1000 {
+        (0, imm as u32)
+    } else {
+        (1, (imm >> 12) as u32)
+    };
+    debug_assert!(imm12 < 0x1000, "add immediate out of range");
+    let rn = rn as u32 & 0x1f;
+    let rd = rd as u32 & 0x1f;
+
+    let mut i = hi(bits);
+    i |= rd;
+    i |= rn << 5;
+    i |= imm12 << 10;
+    i |= sh << 22;
+
+    sink.put4(i);
+}
+
+/// Logical operations with an immediate.
+///
+///   31 30  28     22 21   15   9  4
+///   sf opc 100100 N  immr imms Rn Rd
+///   31 29  23     22 16   10   5  0
+///
+/// The immediate must satisfy the `is_logical_imm` predicate for the operation width.
+///
+/// Encoding bits: `bits[31:21]`.
+fn put_logical_imm<CS: CodeSink + ?Sized>(bits: u16,
+                                          rn: RegUnit,
+                                          imm: i64,
+                                          rd: RegUnit,
+                                          sink: &mut CS) {
+    let (n, immr, imms) = logical_imm_fields(imm, width(bits));
+    let rn = rn as u32 & 0x1f;
+    let rd = rd as u32 & 0x1f;
+
+    let mut i = hi(bits);
+    i |= rd;
+    i |= rn << 5;
+    i |= imms << 10;
+    i |= immr << 16;
+    i |= n << 22;
+
+    sink.put4(i);
+}
+
+/// Compute the `(N, immr, imms)` fields encoding the logical immediate `imm` for an operation
+/// with `width` bits.
+///
+/// The immediate is an element of `size` bits replicated to fill `width` bits. The element is a
+/// run of ones rotated right by `immr` bits, and `imms` encodes both the element size and the
+/// number of ones.
+fn logical_imm_fields(imm: i64, width: u32) -> (u32, u32, u32) {
+    let mask = |size: u32| if size < 64 { (1u64 << size) - 1 } else { !0 };
+    let v = imm as u64 & mask(width);
+
+    // Find the smallest element that is replicated to produce `v`.
+    let mut size = width;
+    while size > 2 {
+        let half = size / 2;
+        if v & mask(half) != (v >> half) & mask(half) {
+            break;
+        }
+        size = half;
+    }
+
+    let elem = v & mask(size);
+    let ones = elem.count_ones();
+    assert!(ones > 0 && ones < size,
+            "{:#x} is not a logical immediate",
+            imm);
+    let run = mask(ones);
+    let rotr = |r: u32| if r == 0 {
+        run
+    } else {
+        ((run >> r) | (run << (size - r))) & mask(size)
+    };
+    let immr = (0..size)
+        .find(|&r| rotr(r) == elem)
+        .unwrap_or_else(|| panic!("{:#x} is not a logical immediate", imm));
+
+    let n = (size == 64) as u32;
+    let imms = ((!(size - 1) << 1) & 0x3f) | (ones - 1);
+    (n, immr, imms)
+}
+
+/// Move wide immediate instructions, `movz`, `movn`, and `movk`.
+///
+///   31 30  28     22 20    4
+///   sf opc 100101 hw imm16 Rd
+///   31 29  23     21 5     0
+///
+/// Encoding bits: `bits[31:21]` of the `movz` instruction. The `opc` field is replaced with
+/// `opc`.
+fn put_movewide<CS: CodeSink + ?Sized>(bits: u16,
+                                       opc: u16,
+                                       imm16: u32,
+                                       hw: u32,
+                                       rd: RegUnit,
+                                       sink: &mut CS) {
+    let rd = rd as u32 & 0x1f;
+
+    let mut i = hi((bits & !MOVEWIDE_OPC) | opc);
+    i |= rd;
+    i |= (imm16 & 0xffff) << 5;
+    i |= hw << 21;
+
+    sink.put4(i);
+}
+
+/// Materialize a constant with a single `movz` or `movn` instruction.
+///
+/// The constant is either a 16-bit chunk in the low 32 bits or a small negative number.
+fn put_mov_imm<CS: CodeSink + ?Sized>(bits: u16, imm: i64, rd: RegUnit, sink: &mut CS) {
+    if imm < 0 {
+        put_movewide(bits, MOVN, !imm as u32, 0, rd, sink);
+    } else if imm < 0x1_0000 {
+        put_movewide(bits, bits & MOVEWIDE_OPC, imm as u32, 0, rd, sink);
+    } else {
+        put_movewide(bits, bits & MOVEWIDE_OPC, (imm >> 16) as u32, 1, rd, sink);
+    }
+}
+
+/// Materialize an arbitrary constant with `movz` for the low 16 bits followed by `movk` for each
+/// of the remaining `chunks - 1` 16-bit chunks.
+fn put_movk_seq<CS: CodeSink + ?Sized>(bits: u16,
+                                       imm: i64,
+                                       chunks: u32,
+                                       rd: RegUnit,
+                                       sink: &mut CS) {
+    let imm = imm as u64;
+    put_movewide(bits, bits & MOVEWIDE_OPC, imm as u32, 0, rd, sink);
+    for hw in 1..chunks {
+        put_movewide(bits, MOVK, (imm >> (16 * hw)) as u32, hw, rd, sink);
+    }
+}
+
+/// Get the operation width and the shift amount `imm` masked to the width.
+fn shift_amount(bits: u16, imm: i64) -> (u32, u32) {
+    let w = width(bits);
+    (w, imm as u32 & (w - 1))
+}
+
+/// Bitfield instructions, `ubfm` and `sbfm`.
+///
+///   31 30  28     22 21   15   9  4
+///   sf opc 100110 N  immr imms Rn Rd
+///   31 29  23     22 16   10   5  0
+///
+/// Encoding bits: `bits[31:21]` including the `N` bit.
+fn put_bitfield<CS: CodeSink + ?Sized>(bits: u16,
+                                       rn: RegUnit,
+                                       immr: u32,
+                                       imms: u32,
+                                       rd: RegUnit,
+                                       sink: &mut CS) {
+    let rn = rn as u32 & 0x1f;
+    let rd = rd as u32 & 0x1f;
+
+    let mut i = hi(bits);
+    i |= rd;
+    i |= rn << 5;
+    i |= (imms & 0x3f) << 10;
+    i |= (immr & 0x3f) << 16;
+
+    sink.put4(i);
+}
+
+/// Extract a register from a pair of registers, `extr rd, rn, rm, #lsb`.
+///
+///   31 30 28     22 21 20 15  9  4
+///   sf 00 100111 N  0  Rm lsb Rn Rd
+///   31 29 23     22 21 16 10  5  0
+///
+/// Encoding bits: `bits[31:21]` including the `N` bit.
+fn put_extr<CS: CodeSink + ?Sized>(bits: u16,
+                                   rn: RegUnit,
+                                   rm: RegUnit,
+                                   lsb: u32,
+                                   rd: RegUnit,
+                                   sink: &mut CS) {
+    let rn = rn as u32 & 0x1f;
+    let rm = rm as u32 & 0x1f;
+    let rd = rd as u32 & 0x1f;
+
+    let mut i = hi(bits);
+    i |= rd;
+    i |= rn << 5;
+    i |= (lsb & 0x3f) << 10;
+    i |= rm << 16;
+
+    sink.put4(i);
+}
+
+/// Get the A64 condition code that is true after `cmp x, y` when `x cond y`.
+fn icc_bits(cond: IntCC) -> u32 {
+    use ir::condcodes::IntCC::*;
+    match cond {
+        Equal => 0b0000,
+        NotEqual => 0b0001,
+        UnsignedGreaterThanOrEqual => 0b0010,
+        UnsignedLessThan => 0b0011,
+        UnsignedGreaterThan => 0b1000,
+        UnsignedLessThanOrEqual => 0b1001,
+        SignedGreaterThanOrEqual => 0b1010,
+        SignedLessThan => 0b1011,
+        SignedGreaterThan => 0b1100,
+        SignedLessThanOrEqual => 0b1101,
+    }
+}
+
+/// Get the A64 condition code that is true after `fcmp x, y` when `x cond y`.
+///
+/// An unordered comparison sets the C and V flags. There are no condition codes for `one` and
+/// `ueq`.
+fn fcc_bits(cond: FloatCC) -> u32 {
+    use ir::condcodes::FloatCC::*;
+    match cond {
+        Ordered => 0b0111,
+        Unordered => 0b0110,
+        Equal => 0b0000,
+        NotEqual => 0b0001,
+        LessThan => 0b0100,
+        LessThanOrEqual => 0b1001,
+        GreaterThan => 0b1100,
+        GreaterThanOrEqual => 0b1010,
+        UnorderedOrLessThan => 0b1011,
+        UnorderedOrLessThanOrEqual => 0b1101,
+        UnorderedOrGreaterThan => 0b1000,
+        UnorderedOrGreaterThanOrEqual => 0b0010,
+        OrderedNotEqual | UnorderedOrEqual => panic!("No A64 condition code for {}", cond),
+    }
+}
+
+/// Set `rd` to 1 if the condition `cond` holds, 0 otherwise.
+///
+/// The `cset wd, cond` instruction is an alias of `csinc wd, wzr, wzr, invert(cond)`, and the
+/// inverse condition code flips the low bit.
+fn put_cset<CS: CodeSink + ?Sized>(cond: u32, rd: RegUnit, sink: &mut CS) {
+    sink.put4(CSET | ((cond ^ 1) << 12) | (rd as u32 & 0x1f));
+}
+
+/// Compare the low 32 bits of `rn` to zero, `cmp wn, #0`.
+fn put_cmp_zero<CS: CodeSink + ?Sized>(rn: RegUnit, sink: &mut CS) {
+    sink.put4(CMP_W_ZERO | ((rn as u32 & 0x1f) << 5));
+}
+
+/// Conditional select.
+///
+///   31      20 15   11 9  4
+///   hi      Rm cond o2 Rn Rd
+///   21      16 12   10 5  0
+///
+/// Encoding bits: `bits[31:21] | (o2 << 11)`.
+fn put_csel<CS: CodeSink + ?Sized>(bits: u16,
+                                   rn: RegUnit,
+                                   rm: RegUnit,
+                                   cond: u32,
+                                   rd: RegUnit,
+                                   sink: &mut CS) {
+    let rn = rn as u32 & 0x1f;
+    let rm = rm as u32 & 0x1f;
+    let rd = rd as u32 & 0x1f;
+
+    let mut i = hi(bits);
+    i |= rd;
+    i |= rn << 5;
+    i |= op(bits) << 10;
+    i |= cond << 12;
+    i |= rm << 16;
+
+    sink.put4(i);
+}
+
+/// Unconditional branch, `b offset` or `bl offset`.
+///
+///   31 30    25
+///   op 00101 imm26
+///   31 26    0
+///
+/// Encoding bits: `bits[31:21]`.
+fn put_b<CS: CodeSink + ?Sized>(bits: u16, offset: i64, sink: &mut CS) {
+    let imm26 = (offset >> 2) as u32 & 0x3ff_ffff;
+    sink.put4(hi(bits) | imm26);
+}
+
+/// Conditional branch, `b.cond offset`.
+///
+///   31       23    4 3
+///   01010100 imm19 0 cond
+///   24       5     4 0
+fn put_bcond<CS: CodeSink + ?Sized>(cond: u32, offset: i64, sink: &mut CS) {
+    let imm19 = (offset >> 2) as u32 & 0x7ffff;
+    sink.put4(BCOND | (imm19 << 5) | cond);
+}
+
+/// Compare a register to zero and branch, `cbz rt, offset` or `cbnz rt, offset`.
+///
+///   31 30     24 23    4
+///   sf 011010 op imm19 Rt
+///   31 25     24 5     0
+///
+/// Encoding bits: `bits[31:21]`.
+fn put_cbz<CS: CodeSink + ?Sized>(bits: u16, offset: i64, rt: RegUnit, sink: &mut CS) {
+    let imm19 = (offset >> 2) as u32 & 0x7ffff;
+    sink.put4(hi(bits) | (imm19 << 5) | (rt as u32 & 0x1f));
+}
+
+/// Invert a `cbz` instruction to `cbnz` and vice versa.
+fn invert_cbz(bits: u16) -> u16 {
+    bits ^ CBZ_OP
+}
+
+/// Trap if `rm` is zero: `cbnz rm, 8` followed by `udf #0`.
+///
+/// Encoding bits: The `sf` bit selects the width of the comparison.
+fn put_trapz<CS: CodeSink + ?Sized>(bits: u16, rm: RegUnit, sink: &mut CS) {
+    let cbnz = (bits & SF) | (0b011010 << 4) | CBZ_OP;
+    put_cbz(cbnz, 8, rm, sink);
+    sink.put4(UDF);
+}
+
+/// Trap if a signed division of `rn` by `rm` overflows:
+///
+///   0:  cmn rm, #1
+///   4:  ccmp rn, #1, #0, eq
+///   8:  b.vc 16
+///   12: udf #0
+///
+/// If `rm` is -1, the `ccmp` computes `rn - 1` which only overflows when `rn` is the smallest
+/// integer. Otherwise, it clears all the flags.
+///
+/// Encoding bits: The `sf` bit selects the width of the comparisons.
+fn put_trap_sdiv_overflow<CS: CodeSink + ?Sized>(bits: u16,
+                                                 rn: RegUnit,
+                                                 rm: RegUnit,
+                                                 sink: &mut CS) {
+    sink.put4(sf(bits) | CMN_W_ONE | ((rm as u32 & 0x1f) << 5));
+    sink.put4(sf(bits) | CCMP_W_ONE_EQ | ((rn as u32 & 0x1f) << 5));
+    put_bcond(COND_VC, 8, sink);
+    sink.put4(UDF);
+}
+
+/// Loads and stores with a scaled 12-bit unsigned offset.
+///
+///   31   29  26 25 23  21    9  4
+///   size 111 V  01 opc imm12 Rn Rt
+///   30   27  26 24 22  10    5  0
+///
+/// The offset is scaled by the access size, `1 << size`.
+///
+/// Encoding bits: `bits[31:21]`.
+fn put_ldst<CS: CodeSink + ?Sized>(bits: u16,
+                                   rn: RegUnit,
+                                   offset: i64,
+                                   rt: RegUnit,
+                                   sink: &mut CS) {
+    let scale = (bits as u32 >> 9) & 3;
+    let imm12 = (offset >> scale) as u32 & 0xfff;
+    let rn = rn as u32 & 0x1f;
+    let rt = rt as u32 & 0x1f;
+
+    let mut i = hi(bits);
+    i |= rt;
+    i |= rn << 5;
+    i |= imm12 << 10;
+
+    sink.put4(i);
+}
+
+/// Loads and stores with an unscaled 9-bit signed offset, `ldur` and `stur`.
+///
+///   31   29  26 25 23  21 20   11 9  4
+///   size 111 V  00 opc 0  imm9 00 Rn Rt
+///   30   27  26 24 22  21 12   10 5  0
+///
+/// Encoding bits: `bits[31:21]` of the scaled form, which has bit 24 set.
+fn put_ldur<CS: CodeSink + ?Sized>(bits: u16,
+                                   rn: RegUnit,
+                                   offset: i64,
+                                   rt: RegUnit,
+                                   sink: &mut CS) {
+    let imm9 = offset as u32 & 0x1ff;
+    let rn = rn as u32 & 0x1f;
+    let rt = rt as u32 & 0x1f;
+
+    let mut i = hi(bits) & !(1 << 24);
+    i |= rt;
+    i |= rn << 5;
+    i |= imm9 << 12;
+
+    sink.put4(i);
+}
+
+/// Load or store `rt` at `offset` from the stack pointer, using the scaled form when possible.
+fn put_ldst_sp<CS: CodeSink + ?Sized>(bits: u16, offset: i64, rt: RegUnit, sink: &mut CS) {
+    let scale = (bits >> 9) & 3;
+    if offset >= 0 && offset % (1 << scale) == 0 && offset < (0x1000 << scale) {
+        put_ldst(bits, STACK_POINTER, offset, rt, sink);
+    } else if offset >= -0x100 && offset < 0x100 {
+        put_ldur(bits, STACK_POINTER, offset, rt, sink);
+    } else {
+        panic!("Stack slot offset {} out of range", offset);
+    }
+}
+
+/// Atomic memory operations from the LSE extension, `ldadd rs, rt, [rn]` and friends.
+///
+///   31   29  26 25 23 22 21 20 15 11 9  4
+///   size 111 0  00 A  R  1  Rs op 00 Rn Rt
+///   30   27  26 24 23 22 21 16 12 10 5  0
+///
+/// Encoding bits: `bits[31:21] | (op << 11)` where the `A` and `R` bits are clear.
+fn put_lse<CS: CodeSink + ?Sized>(bits: u16,
+                                  ordering: AtomicOrdering,
+                                  rs: RegUnit,
+                                  rn: RegUnit,
+                                  rt: RegUnit,
+                                  sink: &mut CS) {
+    let rs = rs as u32 & 0x1f;
+    let rn = rn as u32 & 0x1f;
+    let rt = rt as u32 & 0x1f;
+
+    let mut i = hi(bits);
+    i |= rt;
+    i |= rn << 5;
+    i |= op(bits) << 12;
+    i |= rs << 16;
+    i |= (ordering.is_release() as u32) << 22;
+    i |= (ordering.is_acquire() as u32) << 23;
+
+    sink.put4(i);
+}
+
+/// Compare and swap, `cas rs, rt, [rn]`.
+///
+///   31   29     23 22 21 20 15 14    9  4
+///   size 001000 1  L  1  Rs o0 11111 Rn Rt
+///   30   24     23 22 21 16 15 10    5  0
+///
+/// The `L` bit gives acquire semantics, and the `o0` bit gives release semantics.
+///
+/// Encoding bits: `bits[31:21]` where the `L` bit is clear.
+fn put_cas<CS: CodeSink + ?Sized>(bits: u16,
+                                  ordering: AtomicOrdering,
+                                  rs: RegUnit,
+                                  rn: RegUnit,
+                                  rt: RegUnit,
+                                  sink: &mut CS) {
+    let rs = rs as u32 & 0x1f;
+    let rn = rn as u32 & 0x1f;
+    let rt = rt as u32 & 0x1f;
+
+    let mut i = hi(bits);
+    i |= rt;
+    i |= rn << 5;
+    i |= 0b11111 << 10;
+    i |= (ordering.is_release() as u32) << 15;
+    i |= rs << 16;
+    i |= (ordering.is_acquire() as u32) << 22;
+
+    sink.put4(i);
+}
+
+/// Load-acquire and store-release, `ldar rt, [rn]` and `stlr rt, [rn]`.
+///
+///   31   29     23 22 21 20    15 14    9  4
+///   size 001000 1  L  0  11111 1  11111 Rn Rt
+///   30   24     23 22 21 16    15 10    5  0
+///
+/// Encoding bits: `bits[31:21]`.
+fn put_ldar<CS: CodeSink + ?Sized>(bits: u16, rn: RegUnit, rt: RegUnit, sink: &mut CS) {
+    let rn = rn as u32 & 0x1f;
+    let rt = rt as u32 & 0x1f;
+
+    let mut i = hi(bits);
+    i |= rt;
+    i |= rn << 5;
+    i |= 0b11111 << 10;
+    i |= 1 << 15;
+    i |= 0b11111 << 16;
+
+    sink.put4(i);
+}
+
+/// Get the `dmb` barrier instruction for a fence with the given `ordering`.
+fn dmb_bits(ordering: AtomicOrdering) -> u32 {
+    match ordering {
+        AtomicOrdering::Acquire => DMB_ISHLD,
+        _ => DMB_ISH,
+    }
+}
@@ -0,0 +1,263 @@
+//! ARM64 Instruction Set Architecture.
+
+pub mod settings;
+mod abi;
+mod binemit;
+mod enc_tables;
+mod registers;
+
+use super::super::settings as shared_settings;
+use binemit::CodeSink;
+use isa::enc_tables::{self as shared_enc_tables, lookup_enclist, general_encoding,
+                      visit_encodings};
+use isa::Builder as IsaBuilder;
+use isa::{TargetIsa, RegInfo, RegClass, Encoding, Legalize, RecipeConstraints, RecipeSizing};
+use ir::{Function, Inst, InstructionData, DataFlowGraph, Signature, ArgumentType, Type};
+use regalloc::AllocatableSet;
+
+#[allow(dead_code)]
+struct Isa {
+    shared_flags: shared_settings::Flags,
+    isa_flags: settings::Flags,
+    cpumode: &'static [shared_enc_tables::Level1Entry<u16>],
+}
+
+/// Get an ISA builder for creating ARM64 targets.
+pub fn isa_builder() -> IsaBuilder {
+    IsaBuilder {
+        setup: settings::builder(),
+        constructor: isa_constructor,
+    }
+}
+
+fn isa_constructor(shared_flags: shared_settings::Flags,
+                   builder: &shared_settings::Builder)
+                   -> Box<TargetIsa> {
+    Box::new(Isa {
+        isa_flags: settings::Flags::new(&shared_flags, builder),
+        shared_flags: shared_flags,
+        cpumode: &enc_tables::LEVEL1_A64[..],
+    })
+}
+
+impl TargetIsa for Isa {
+    fn name(&self) -> &'static str {
+        "arm64"
+    }
+
+    fn flags(&self) -> &shared_settings::Flags {
+        &self.shared_flags
+    }
+
+    fn register_info(&self) -> RegInfo {
+        registers::INFO.clone()
+    }
+
+    fn regclass_for_abi_type(&self, ty: Type) -> RegClass {
+        abi::regclass_for_abi_type(ty, &self.isa_flags)
+    }
+
+    fn allocatable_registers(&self, func: &Function) -> AllocatableSet {
+        abi::allocatable_registers(func)
+    }
+
+    fn stack_alignment(&self) -> u32 {
+        abi::STACK_ALIGNMENT
+    }
+
+    fn saved_registers(&self, func: &Function) -> Vec<ArgumentType> {
+        abi::saved_registers(func, self.pointer_type(), &self.isa_flags)
+    }
+
+    fn encode(&self, dfg: &DataFlowGraph, inst: &InstructionData) -> Result<Encoding, Legalize> {
+        lookup_enclist(inst.ctrl_typevar(dfg),
+                       inst.opcode(),
+                       self.cpumode,
+                       &enc_tables::LEVEL2[..])
+            .and_then(|enclist_offset| {
+                general_encoding(enclist_offset,
+                                 &enc_tables::ENCLISTS[..],
+                                 |instp| enc_tables::check_instp(inst, instp, dfg),
+                                 |isap| self.isa_flags.numbered_predicate(isap as usize))
+            })
+    }
+
+    fn legal_encodings(&self, dfg: &DataFlowGraph, inst: &InstructionData) -> Vec<Encoding> {
+        let mut encodings = Vec::new();
+        if let Ok(enclist_offset) = lookup_enclist(inst.ctrl_typevar(dfg),
+                                                   inst.opcode(),
+                                                   self.cpumode,
+                                                   &enc_tables::LEVEL2[..]) {
+            visit_encodings(enclist_offset,
+                            &enc_tables::ENCLISTS[..],
+                            |instp| enc_tables::check_instp(inst, instp, dfg),
+                            |isap| self.isa_flags.numbered_predicate(isap as usize),
+                            |enc| encodings.push(enc));
+        }
+        encodings
+    }
+
+    fn recipe_names(&self) -> &'static [&'static str] {
+        &enc_tables::RECIPE_NAMES[..]
+    }
+
+    fn recipe_constraints(&self) -> &'static [RecipeConstraints] {
+        &enc_tables::RECIPE_CONSTRAINTS
+    }
+
+    fn recipe_sizing(&self) -> &'static [RecipeSizing] {
+        &enc_tables::RECIPE_SIZING
+    }
+
+    fn legalize_signature(&self, sig: &mut Signature) {
+        abi::legalize_signature(sig, &self.shared_flags, &self.isa_flags)
+    }
+
+    fn emit_inst(&self, func: &Function, inst: Inst, sink: &mut CodeSink) {
+        binemit::emit_inst(func, inst, sink)
+    }
+
+    fn reloc_names(&self) -> &'static [&'static str] {
+        &binemit::RELOC_NAMES
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use settings::{self, Configurable};
+    use isa::{self, Legalize, OperandConstraint, ConstraintKind};
+    use ir::{DataFlowGraph, InstructionData, Opcode};
+    use ir::{types, immediates};
+    use ir::atomics::{AtomicRmwOp, AtomicOrdering};
+
+    fn encstr(isa: &isa::TargetIsa, enc: isa::Encoding) -> String {
+        isa.display_enc(enc).to_string()
+    }
+
+    fn shared_flags() -> settings::Flags {
+        let mut shared_builder = settings::builder();
+        shared_builder.set_bool("is_64bit", true).unwrap();
+        settings::Flags::new(&shared_builder)
+    }
+
+    #[test]
+    fn test_immediates() {
+        let isa = isa::lookup("arm64").unwrap().finish(shared_flags());
+
+        let mut dfg = DataFlowGraph::new();
+        let ebb = dfg.make_ebb();
+        let arg64 = dfg.append_ebb_arg(ebb, types::I64);
+        let arg32 = dfg.append_ebb_arg(ebb, types::I32);
+        let arg8 = dfg.append_ebb_arg(ebb, types::I8);
+
+        let binimm = |opcode, ty, arg, imm| {
+            InstructionData::BinaryImm {
+                opcode: opcode,
+                ty: ty,
+                arg: arg,
+                imm: immediates::Imm64::new(imm),
+            }
+        };
+
+        // Negative immediates are emitted as `sub`.
+        let add64 = binimm(Opcode::IaddImm, types::I64, arg64, -10);
+        assert_eq!(encstr(&*isa, isa.encode(&dfg, &add64).unwrap()), "Iaddsub#488");
+
+        // A 12-bit immediate shifted left by 12.
+        let add32 = binimm(Opcode::IaddImm, types::I32, arg32, 0x5000);
+        assert_eq!(encstr(&*isa, isa.encode(&dfg, &add32).unwrap()), "Iaddsub#88");
+
+        // Out of range for the shifted or unshifted forms.
+        let add64_large = binimm(Opcode::IaddImm, types::I64, arg64, 0x1001);
+        assert_eq!(isa.encode(&dfg, &add64_large), Err(Legalize::Expand));
+
+        // A repeating pattern is a logical immediate.
+        let and64 = binimm(Opcode::BandImm, types::I64, arg64, 0x00ff_00ff_00ff_00ff);
+        assert_eq!(encstr(&*isa, isa.encode(&dfg, &and64).unwrap()), "Ilogic#490");
+
+        // 5 is not a run of ones.
+        let and32 = binimm(Opcode::BandImm, types::I32, arg32, 5);
+        assert_eq!(isa.encode(&dfg, &and32), Err(Legalize::Expand));
+
+        // All ones is not a logical immediate, but the high bits are ignored by 32-bit
+        // operations.
+        let or32 = binimm(Opcode::BorImm, types::I32, arg32, 0x1_0000_ff00);
+        assert_eq!(encstr(&*isa, isa.encode(&dfg, &or32).unwrap()), "Ilogic#190");
+        let or64 = binimm(Opcode::BorImm, types::I64, arg64, -1);
+        assert_eq!(isa.encode(&dfg, &or64), Err(Legalize::Expand));
+
+        // There is no i8 arithmetic.
+        let add8 = InstructionData::Binary {
+            opcode: Opcode::Iadd,
+            ty: types::I8,
+            args: [arg8, arg8],
+        };
+        assert_eq!(isa.encode(&dfg, &add8), Err(Legalize::Widen));
+    }
+
+    #[test]
+    fn test_lse() {
+        let mut dfg = DataFlowGraph::new();
+        let ebb = dfg.make_ebb();
+        let p = dfg.append_ebb_arg(ebb, types::I64);
+        let x = dfg.append_ebb_arg(ebb, types::I32);
+
+        let rmw = InstructionData::AtomicRmw {
+            opcode: Opcode::AtomicRmw,
+            ty: types::I32,
+            op: AtomicRmwOp::Add,
+            ordering: AtomicOrdering::SeqCst,
+            args: [p, x],
+        };
+
+        // The atomic instructions need the Large System Extensions.
+        let isa = isa::lookup("arm64").unwrap().finish(shared_flags());
+        assert_eq!(isa.encode(&dfg, &rmw), Err(Legalize::Expand));
+
+        let mut isa_builder = isa::lookup("arm64").unwrap();
+        isa_builder.set_bool("has_lse", true).unwrap();
+        let isa = isa_builder.finish(shared_flags());
+        assert_eq!(encstr(&*isa, isa.encode(&dfg, &rmw).unwrap()), "Ramo#5c1");
+    }
+
+    #[test]
+    fn recipe_constraints() {
+        let mut isa_builder = isa::lookup("arm64").unwrap();
+        isa_builder.set_bool("has_lse", true).unwrap();
+        let isa = isa_builder.finish(shared_flags());
+        let regs = isa.register_info();
+        let gpr = &regs.classes[0];
+        assert_eq!(gpr.name, "GPR");
+
+        let constraints = |name| {
+            let recipe = isa.recipe_names().iter().position(|&n| n == name).unwrap();
+            isa.recipe_constraints()[recipe]
+        };
+        let reg = OperandConstraint {
+            kind: ConstraintKind::Reg,
+            regclass: gpr,
+        };
+
+        let rrr = constraints("Rrr");
+        assert_eq!(rrr.ins, &[reg, reg]);
+        assert_eq!(rrr.outs, &[reg]);
+
+        // The remainder is computed after the quotient, so the output can't overwrite an input.
+        let rrem = constraints("Rrem");
+        assert_eq!(rrem.ins, &[reg, reg]);
+        assert_eq!(rrem.outs,
+                   &[OperandConstraint {
+                         kind: ConstraintKind::EarlyClobber,
+                         regclass: gpr,
+                     }]);
+
+        // The `cas` instruction overwrites the expected value with the old value.
+        let rcas = constraints("Rcas");
+        assert_eq!(rcas.ins, &[reg, reg, reg]);
+        assert_eq!(rcas.outs,
+                   &[OperandConstraint {
+                         kind: ConstraintKind::Tied(1),
+                         regclass: gpr,
+                     }]);
+    }
+}
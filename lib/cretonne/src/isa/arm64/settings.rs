@@ -0,0 +1,40 @@
+//! ARM64 Settings.
+
+use settings::{self, detail, Builder};
+use std::fmt;
+
+// Include code generated by `lib/cretonne/meta/gen_settings.py`. This file contains a public
+// `Flags` struct with an impl for all of the settings defined in
+// `lib/cretonne/meta/isa/arm64/settings.py`.
+include!(concat!(env!("OUT_DIR"), "/settings-arm64.rs"));
+
+#[cfg(test)]
+mod tests {
+    use super::{builder, Flags};
+    use settings::{self, Configurable};
+
+    #[test]
+    fn display_default() {
+        let shared = settings::Flags::new(&settings::builder());
+        let b = builder();
+        let f = Flags::new(&shared, &b);
+        assert_eq!(f.to_string(),
+                   "[arm64]\n\
+                    has_fp = true\n\
+                    has_lse = false\n");
+        assert_eq!(f.use_fp(), true);
+        assert_eq!(f.use_lse(), false);
+    }
+
+    #[test]
+    fn predicates() {
+        let mut sb = settings::builder();
+        sb.set_bool("enable_float", false).unwrap();
+        let shared = settings::Flags::new(&sb);
+        let mut b = builder();
+        b.set_bool("has_lse", true).unwrap();
+        let f = Flags::new(&shared, &b);
+        assert_eq!(f.use_fp(), false);
+        assert_eq!(f.use_lse(), true);
+    }
+}
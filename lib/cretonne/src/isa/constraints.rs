@@ -0,0 +1,120 @@
+//! Register constraints for instruction operands.
+//!
+//! An encoding recipe specifies how an instruction is encoded as binary machine code, but it only
+//! works if the operands and results satisfy certain constraints. Constraints on immediate
+//! operands are checked by instruction predicates when the recipe is chosen.
+//!
+//! It is the register allocator's job to make sure that the register constraints on value operands
+//! are satisfied. After register allocation, the constraints of alternative encodings can be
+//! checked against the value locations to find encodings that can be used instead.
+
+use ir::{Function, Inst, ValueLoc};
+use isa::{RegClass, RegUnit};
+
+/// Register constraint for a single value operand or instruction result.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct OperandConstraint {
+    /// The kind of constraint.
+    pub kind: ConstraintKind,
+
+    /// The register class of the operand.
+    ///
+    /// This applies to all kinds of constraints, but with slightly different meaning.
+    pub regclass: RegClass,
+}
+
+/// The different kinds of operand constraints.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ConstraintKind {
+    /// This operand or result must be a register from the given register class.
+    Reg,
+
+    /// This operand or result must be a fixed register.
+    ///
+    /// The constraint's `regclass` field is the top-level register class containing the fixed
+    /// register.
+    FixedReg(RegUnit),
+
+    /// This result value must use the same register as an input value operand. Input operands
+    /// can't be tied.
+    ///
+    /// The associated number is the index of the input value operand this result is tied to.
+    ///
+    /// The constraint's `regclass` field is the register class that may be used for both the
+    /// input operand and the result.
+    Tied(u8),
+
+    /// This result must be a register from the given register class that is distinct from the
+    /// registers of all the input value operands.
+    ///
+    /// An early clobber result is written by the instruction before it has finished reading its
+    /// inputs, so it can't reuse the register of an input operand that is killed by the
+    /// instruction.
+    EarlyClobber,
+
+    /// This operand must be a value in a stack slot.
+    ///
+    /// The constraint's `regclass` field is the register class that would normally be used to load
+    /// and store values of this type.
+    Stack,
+}
+
+impl OperandConstraint {
+    /// Is the value location `loc` permitted by this constraint?
+    ///
+    /// Tied and early clobber constraints also depend on the other operands, so this only checks
+    /// that `loc` is a register in the right class.
+    pub fn satisfied(&self, loc: ValueLoc) -> bool {
+        match (self.kind, loc) {
+            (ConstraintKind::FixedReg(unit), ValueLoc::Reg(reg)) => reg == unit,
+            (ConstraintKind::Stack, ValueLoc::Stack(_)) => true,
+            (ConstraintKind::Stack, _) => false,
+            (_, ValueLoc::Reg(reg)) => self.regclass.contains(reg),
+            _ => false,
+        }
+    }
+}
+
+/// Constraints for an encoding recipe.
+#[derive(Clone, Copy)]
+pub struct RecipeConstraints {
+    /// Constraints for the instruction's fixed value operands.
+    ///
+    /// If the instruction takes a variable number of operands, the register constraints for those
+    /// operands must be computed dynamically.
+    pub ins: &'static [OperandConstraint],
+
+    /// Constraints for the instruction's fixed results.
+    ///
+    /// If the instruction produces a variable number of results, it's probably a call and the
+    /// constraints must be derived from the calling convention ABI.
+    pub outs: &'static [OperandConstraint],
+}
+
+impl RecipeConstraints {
+    /// Are these constraints satisfied by the current value locations of `inst` in `func`?
+    ///
+    /// Only the fixed value operands and results are checked. A value that hasn't been assigned a
+    /// location doesn't satisfy any constraint.
+    pub fn satisfied(&self, inst: Inst, func: &Function) -> bool {
+        let mut args = Vec::new();
+        func.dfg[inst].clone().map_arguments(|arg| {
+            args.push(func.locations.get(arg).cloned().unwrap_or_default());
+            arg
+        });
+        if !self.ins.iter().zip(&args).all(|(cons, &loc)| cons.satisfied(loc)) {
+            return false;
+        }
+
+        self.outs.iter().zip(func.dfg.inst_results(inst)).all(|(cons, res)| {
+            let loc = func.locations.get(res).cloned().unwrap_or_default();
+            match cons.kind {
+                ConstraintKind::Tied(num) => {
+                    cons.satisfied(loc) && args.get(num as usize) == Some(&loc)
+                }
+                ConstraintKind::EarlyClobber => cons.satisfied(loc) && !args.contains(&loc),
+                _ => cons.satisfied(loc),
+            }
+        })
+    }
+}
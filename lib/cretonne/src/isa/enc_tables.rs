@@ -3,7 +3,7 @@
 //! This module contains types and functions for working with the encoding tables generated by
 //! `lib/cretonne/meta/gen_encoding.py`.
 use ir::{Type, Opcode};
-use isa::Encoding;
+use isa::{Encoding, Legalize};
 use constant_hash::{Table, probe};
 
 /// Level 1 hash table entry.
@@ -19,11 +19,15 @@ use constant_hash::{Table, probe};
 /// size of the `LEVEL2` table. A `u16` offset allows entries to shrink to 32 bits each, but some
 /// ISAs may have tables so large that `u32` offsets are needed.
 ///
+/// Each entry also records the legalization action to use for instructions with the controlling
+/// type variable when their opcode is missing from the level 2 table.
+///
 /// Empty entries are encoded with a 0 `log2len`. This is on the assumption that no level 2 tables
 /// have only a single entry.
 pub struct Level1Entry<OffT: Into<u32> + Copy> {
     pub ty: Type,
     pub log2len: u8,
+    pub legalize: Legalize,
     pub offset: OffT,
 }
 
@@ -77,22 +81,32 @@ impl<OffT: Into<u32> + Copy> Table<Opcode> for [Level2Entry<OffT>] {
 /// Given the controlling type variable and instruction opcode, find the corresponding encoding
 /// list.
 ///
-/// Returns an offset into the ISA's `ENCLIST` table, or `None` if the opcode/type combination is
-/// not legal.
+/// Returns an offset into the ISA's `ENCLIST` table, or a `Legalize` action if the opcode/type
+/// combination is not legal:
+///
+/// - When the controlling type variable has no level 2 table at all, the type is not supported by
+///   the CPU mode, and the instruction should be narrowed.
+/// - When the type is supported, but the opcode is missing from its level 2 table, the
+///   instruction should be legalized with the action recorded in the level 1 entry. This is
+///   usually `Legalize::Expand`.
 pub fn lookup_enclist<OffT1, OffT2>(ctrl_typevar: Type,
                                     opcode: Opcode,
                                     level1_table: &[Level1Entry<OffT1>],
                                     level2_table: &[Level2Entry<OffT2>])
-                                    -> Option<usize>
+                                    -> Result<usize, Legalize>
     where OffT1: Into<u32> + Copy,
           OffT2: Into<u32> + Copy
 {
-    probe(level1_table, ctrl_typevar, ctrl_typevar.index()).and_then(|l1idx| {
-        let l1ent = &level1_table[l1idx];
-        let l2off = l1ent.offset.into() as usize;
-        let l2tab = &level2_table[l2off..l2off + (1 << l1ent.log2len)];
-        probe(l2tab, opcode, opcode as usize).map(|l2idx| l2tab[l2idx].offset.into() as usize)
-    })
+    probe(level1_table, ctrl_typevar, ctrl_typevar.index())
+        .ok_or(Legalize::Narrow)
+        .and_then(|l1idx| {
+            let l1ent = &level1_table[l1idx];
+            let l2off = l1ent.offset.into() as usize;
+            let l2tab = &level2_table[l2off..l2off + (1 << l1ent.log2len)];
+            probe(l2tab, opcode, opcode as usize)
+                .map(|l2idx| l2tab[l2idx].offset.into() as usize)
+                .ok_or(l1ent.legalize)
+        })
 }
 
 /// Encoding list entry.
@@ -120,23 +134,43 @@ const CODE_FAIL: EncListEntry = 0xffff;
 /// - `instp` is passed an instruction predicate number to be evaluated on the current instruction.
 /// - `isap` is passed an ISA predicate number to evaluate.
 ///
-/// Returns the corresponding encoding, or `None` if no list entries are satisfied by `inst`.
+/// Returns the corresponding encoding, or `Legalize::Expand` if no list entries are satisfied by
+/// `inst`.
 pub fn general_encoding<InstP, IsaP>(offset: usize,
                                      enclist: &[EncListEntry],
                                      instp: InstP,
                                      isap: IsaP)
-                                     -> Option<Encoding>
+                                     -> Result<Encoding, Legalize>
     where InstP: Fn(EncListEntry) -> bool,
           IsaP: Fn(EncListEntry) -> bool
 {
     let mut found = None;
+    visit_encodings(offset, enclist, instp, isap, |enc| found = Some(enc));
+    found.ok_or(Legalize::Expand)
+}
+
+/// Visit all the encodings of `inst` in an encoding list.
+///
+/// Given an encoding list offset as returned by `lookup_enclist` above, call `visit` with every
+/// encoding whose predicates are satisfied, ordered from the most specific to the most general.
+///
+/// The `instp` and `isap` closures are used to evaluate predicates as in `general_encoding()`.
+pub fn visit_encodings<InstP, IsaP, Visit>(offset: usize,
+                                           enclist: &[EncListEntry],
+                                           instp: InstP,
+                                           isap: IsaP,
+                                           mut visit: Visit)
+    where InstP: Fn(EncListEntry) -> bool,
+          IsaP: Fn(EncListEntry) -> bool,
+          Visit: FnMut(Encoding)
+{
     let mut pos = offset;
     while enclist[pos] != CODE_FAIL {
         let pred = enclist[pos];
         if pred <= CODE_ALWAYS {
             // This is an instruction predicate followed by recipe and encbits entries.
             if pred == CODE_ALWAYS || instp(pred) {
-                found = Some(Encoding::new(enclist[pos + 1], enclist[pos + 2]))
+                visit(Encoding::new(enclist[pos + 1], enclist[pos + 2]))
             }
             pos += 3;
         } else {
@@ -148,5 +182,4 @@ pub fn general_encoding<InstP, IsaP>(offset: usize,
             }
         }
     }
-    found
 }
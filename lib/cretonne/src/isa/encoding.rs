@@ -1,6 +1,7 @@
 //! The `Encoding` struct.
 
 use std::fmt;
+use binemit::CodeOffset;
 
 /// Bits needed to encode an instruction as binary machine code.
 ///
@@ -76,3 +77,67 @@ impl fmt::Display for DisplayEncoding {
         }
     }
 }
+
+/// Code size information for an encoding recipe.
+///
+/// All encoding recipes correspond to an exact instruction size.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RecipeSizing {
+    /// Size in bytes of instructions encoded with this recipe.
+    pub bytes: u8,
+
+    /// Allowed branch range in this recipe, if any.
+    ///
+    /// All encoding recipes for branches have exact branch range information.
+    pub branch_range: Option<BranchRange>,
+}
+
+/// The maximum range of a branch instruction.
+///
+/// The branch displacement is measured in bytes from an origin inside the instruction to the
+/// destination EBB. The displacement must be representable as a signed integer with `bits` bits.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BranchRange {
+    /// Offset in bytes from the address of the branch instruction to the origin used for computing
+    /// the branch displacement. This is the location of the branching instruction for encodings
+    /// that expand into multiple native instructions.
+    pub origin: u8,
+
+    /// Number of bits in the signed byte displacement encoded in the instruction. This does not
+    /// account for branches that can only target aligned addresses.
+    pub bits: u8,
+}
+
+impl BranchRange {
+    /// Determine if this branch range can represent the range from `branch` to `dest`, where
+    /// `branch` is the code offset of the branch instruction itself and `dest` is the code offset
+    /// of the destination EBB header.
+    pub fn contains(self, branch: CodeOffset, dest: CodeOffset) -> bool {
+        let d = dest.wrapping_sub(branch + self.origin as CodeOffset) as i32;
+        let s = 32 - self.bits as u32;
+        d == d << s >> s
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BranchRange;
+
+    #[test]
+    fn branch_range() {
+        // A 13-bit signed displacement like RISC-V conditional branches: [-4096, 4095].
+        let r = BranchRange { origin: 0, bits: 13 };
+        assert!(r.contains(1000, 1000));
+        assert!(r.contains(0, 4094));
+        assert!(!r.contains(0, 4096));
+        assert!(r.contains(4096, 0));
+        assert!(!r.contains(4098, 0));
+
+        // The displacement is measured from the origin.
+        let r = BranchRange { origin: 4, bits: 13 };
+        assert!(r.contains(0, 4098));
+        assert!(!r.contains(0, 4100));
+        assert!(r.contains(4092, 0));
+        assert!(!r.contains(4094, 0));
+    }
+}
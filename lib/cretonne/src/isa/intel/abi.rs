@@ -0,0 +1,180 @@
+//! Intel ABI implementation.
+//!
+//! This module implements the System V AMD64 calling convention for 64-bit code, and the
+//! traditional `cdecl` convention for 32-bit code where all arguments are passed on the stack.
+//!
+//! The `call` instruction pushes the return address, so the incoming stack arguments start one
+//! pointer size above the stack pointer on entry.
+
+use abi::{ArgAction, ArgAssigner, ValueConversion, legalize_args};
+use ir::{Function, Signature, ArgumentType, ArgumentLoc, ArgumentExtension, ArgumentPurpose,
+         Type, ValueLoc};
+use isa::RegClass;
+use regalloc::AllocatableSet;
+use ir::types;
+use settings as shared_settings;
+use super::registers::{GPR, FPR};
+use super::settings;
+
+/// Integer arguments are passed in `%rdi`, `%rsi`, `%rdx`, `%rcx`, `%r8`, and `%r9`.
+const ARG_GPRS: [usize; 6] = [7, 6, 2, 1, 8, 9];
+
+/// Integer return values are passed in `%rax` and `%rdx`.
+const RET_GPRS: [usize; 2] = [0, 2];
+
+/// The stack pointer is always 16-byte aligned at a call site.
+pub const STACK_ALIGNMENT: u32 = 16;
+
+/// The callee-saved registers in 64-bit mode are `%rbx`, and `%r12`-`%r15`. All the XMM registers
+/// are caller-saved.
+const CALLEE_SAVED_GPRS_64: [usize; 5] = [3, 12, 13, 14, 15];
+
+/// The callee-saved registers in 32-bit mode are `%ebx`, `%esi`, and `%edi`.
+const CALLEE_SAVED_GPRS_32: [usize; 3] = [3, 6, 7];
+
+struct Args {
+    pointer_bits: u16,
+    pointer_bytes: u32,
+    pointer_type: Type,
+    gprs: &'static [usize],
+    gpr_used: usize,
+    fpr_limit: usize,
+    fpr_used: usize,
+    offset: u32,
+}
+
+impl Args {
+    fn new(pointer_type: Type, gprs: &'static [usize], fpr_limit: usize) -> Args {
+        Args {
+            pointer_bits: pointer_type.bits(),
+            pointer_bytes: pointer_type.bytes(),
+            pointer_type: pointer_type,
+            gprs: gprs,
+            gpr_used: 0,
+            fpr_limit: fpr_limit,
+            fpr_used: 0,
+            // Skip the return address pushed by the `call` instruction.
+            offset: pointer_type.bytes(),
+        }
+    }
+}
+
+impl ArgAssigner for Args {
+    fn assign(&mut self, arg: &ArgumentType) -> ArgAction {
+        let ty = arg.value_type;
+
+        // Vectors larger than an XMM register are broken down.
+        if !ty.is_scalar() && ty.bits() > 128 {
+            return ValueConversion::VectorSplit.into();
+        }
+
+        // Large integers and booleans are broken down to fit in a register.
+        if !ty.is_float() && ty.is_scalar() && ty.bits() > self.pointer_bits {
+            return ValueConversion::IntSplit.into();
+        }
+
+        // Small integers are extended to the size of a pointer register.
+        if ty.is_int() && ty.bits() < self.pointer_bits {
+            match arg.extension {
+                ArgumentExtension::None => {}
+                ArgumentExtension::Uext => return ValueConversion::Uext(self.pointer_type).into(),
+                ArgumentExtension::Sext => return ValueConversion::Sext(self.pointer_type).into(),
+            }
+        }
+
+        if ty.is_float() || !ty.is_scalar() {
+            if self.fpr_used < self.fpr_limit {
+                let reg = FPR.unit(self.fpr_used);
+                self.fpr_used += 1;
+                return ArgumentLoc::Reg(reg).into();
+            }
+        } else if self.gpr_used < self.gprs.len() {
+            let reg = GPR.unit(self.gprs[self.gpr_used]);
+            self.gpr_used += 1;
+            return ArgumentLoc::Reg(reg).into();
+        }
+
+        // Assign a stack location. Every argument on the stack occupies a pointer-sized slot, or
+        // more for large floats and vectors.
+        let size = ty.bytes();
+        let slot = (size + self.pointer_bytes - 1) / self.pointer_bytes * self.pointer_bytes;
+        let loc = ArgumentLoc::Stack(self.offset as i32);
+        self.offset += slot;
+        loc.into()
+    }
+}
+
+/// Legalize `sig` for Intel.
+///
+/// In 64-bit mode, up to six integer arguments and eight floating point or vector arguments are
+/// passed in registers. In 32-bit mode, all arguments are passed on the stack. Integer return
+/// values use `%rax` and `%rdx`, and floating point return values use `%xmm0` and `%xmm1`.
+pub fn legalize_signature(sig: &mut Signature,
+                          flags: &shared_settings::Flags,
+                          _isa_flags: &settings::Flags) {
+    let mut args = if flags.is_64bit() {
+        Args::new(types::I64, &ARG_GPRS, 8)
+    } else {
+        Args::new(types::I32, &[], 0)
+    };
+    legalize_args(&mut sig.argument_types, &mut args);
+
+    let pointer_type = if flags.is_64bit() {
+        types::I64
+    } else {
+        types::I32
+    };
+    let mut rets = Args::new(pointer_type, &RET_GPRS, 2);
+    legalize_args(&mut sig.return_types, &mut rets);
+}
+
+/// Get register class for a type appearing in a legalized signature.
+pub fn regclass_for_abi_type(ty: Type) -> RegClass {
+    if ty.is_float() || !ty.is_scalar() {
+        FPR
+    } else {
+        GPR
+    }
+}
+
+/// Get the set of allocatable registers for `func`.
+pub fn allocatable_registers(_func: &Function, flags: &shared_settings::Flags) -> AllocatableSet {
+    let mut regs = AllocatableSet::new();
+    // The stack pointer `%rsp` and the frame pointer `%rbp` are reserved.
+    regs.take(GPR, GPR.unit(4));
+    regs.take(GPR, GPR.unit(5));
+
+    // 32-bit code can only use the first eight registers of each bank.
+    if !flags.is_64bit() {
+        for i in 8..16 {
+            regs.take(GPR, GPR.unit(i));
+            regs.take(FPR, FPR.unit(i));
+        }
+    }
+
+    regs
+}
+
+/// Get the registers that must be saved by the prologue of `func`.
+///
+/// These are the callee-saved integer registers used by `func`. The return address is pushed on
+/// the stack by the `call` instruction, so it doesn't need to be saved.
+pub fn saved_registers(func: &Function, flags: &shared_settings::Flags) -> Vec<ArgumentType> {
+    let (pointer_type, callee_saved) = if flags.is_64bit() {
+        (types::I64, &CALLEE_SAVED_GPRS_64[..])
+    } else {
+        (types::I32, &CALLEE_SAVED_GPRS_32[..])
+    };
+
+    let mut saved = Vec::new();
+    for &unit in callee_saved {
+        let loc = ValueLoc::Reg(GPR.unit(unit));
+        if func.locations.keys().any(|v| func.locations[v] == loc) {
+            let mut arg = ArgumentType::new(pointer_type);
+            arg.purpose = ArgumentPurpose::CalleeSaved;
+            arg.location = ArgumentLoc::Reg(GPR.unit(unit));
+            saved.push(arg);
+        }
+    }
+    saved
+}
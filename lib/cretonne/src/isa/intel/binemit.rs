@@ -0,0 +1,280 @@
+//! Emitting binary Intel machine code.
+
+use binemit::{CodeSink, Reloc, bad_encoding, stack_slot_offset, globalsym_name};
+use ir::{Function, Inst, InstructionData, Opcode, Ebb};
+use ir::condcodes::{IntCC, FloatCC};
+use isa::RegUnit;
+
+include!(concat!(env!("OUT_DIR"), "/binemit-intel.rs"));
+
+/// Intel relocation kinds.
+pub enum RelocKind {
+    /// A 4-byte relative function reference. Based from relocation + 4 bytes.
+    PCRel4,
+    /// A 4-byte absolute address.
+    Abs4,
+    /// An 8-byte absolute address.
+    Abs8,
+}
+
+/// The names of the Intel relocation kinds, using the ELF x86-64 psABI names.
+pub static RELOC_NAMES: [&'static str; 3] = ["R_X86_64_PC32", "R_X86_64_32", "R_X86_64_64"];
+
+impl Into<Reloc> for RelocKind {
+    fn into(self) -> Reloc {
+        Reloc(self as u16)
+    }
+}
+
+/// The stack pointer register `%rsp`.
+const RSP: RegUnit = 4;
+
+/// The REX prefix with no bits set. The `W`, `R`, and `B` bits are filled in from the encoding
+/// bits and the register operands.
+const BASE_REX: u8 = 0b0100_0000;
+
+/// Mandatory prefixes indexed by the `pp` field of the encoding bits.
+const PREFIX: [u8; 3] = [0x66, 0xf3, 0xf2];
+
+/// Get the REX prefix for an instruction with a single register operand in the ModR/M `rm` field
+/// or in the low bits of the opcode.
+fn rex1(reg_b: RegUnit) -> u8 {
+    let b = ((reg_b >> 3) & 1) as u8;
+    BASE_REX | b
+}
+
+/// Get the REX prefix for an instruction with register operands in the ModR/M `rm` and `reg`
+/// fields.
+fn rex2(rm: RegUnit, reg: RegUnit) -> u8 {
+    let b = ((rm >> 3) & 1) as u8;
+    let r = ((reg >> 3) & 1) as u8;
+    BASE_REX | b | (r << 2)
+}
+
+/// Add the `W` bit from the encoding bits to a REX prefix.
+fn rex_w(bits: u16, rex: u8) -> u8 {
+    let w = ((bits >> 15) & 1) as u8;
+    rex | (w << 3)
+}
+
+/// Emit the mandatory prefix encoded in the `pp` field of `bits`.
+fn put_pp<CS: CodeSink + ?Sized>(bits: u16, sink: &mut CS) {
+    let pp = ((bits >> 8) & 3) as usize;
+    debug_assert!(pp != 0, "Mandatory prefix expected");
+    sink.put1(PREFIX[pp - 1]);
+}
+
+/// Emit the escape bytes for the opcode map encoded in the `mm` field of `bits`.
+fn put_mm<CS: CodeSink + ?Sized>(bits: u16, sink: &mut CS) {
+    sink.put1(0x0f);
+    match (bits >> 10) & 3 {
+        1 => {}
+        2 => sink.put1(0x38),
+        3 => sink.put1(0x3a),
+        _ => panic!("Invalid opcode map for {:#06x}", bits),
+    }
+}
+
+// Emit single-byte opcode with no REX prefix.
+fn put_op1<CS: CodeSink + ?Sized>(bits: u16, rex: u8, sink: &mut CS) {
+    debug_assert_eq!(bits & 0x8f00, 0, "Invalid encoding bits for Op1*");
+    debug_assert_eq!(rex, BASE_REX, "Invalid registers for REX-less Op1 encoding");
+    sink.put1(bits as u8);
+}
+
+// Emit single-byte opcode with REX prefix.
+fn put_rexop1<CS: CodeSink + ?Sized>(bits: u16, rex: u8, sink: &mut CS) {
+    debug_assert_eq!(bits & 0x0f00, 0, "Invalid encoding bits for Op1*");
+    sink.put1(rex_w(bits, rex));
+    sink.put1(bits as u8);
+}
+
+// Emit single-byte opcode with mandatory prefix and no REX prefix.
+fn put_mp1<CS: CodeSink + ?Sized>(bits: u16, rex: u8, sink: &mut CS) {
+    debug_assert_eq!(bits & 0x8c00, 0, "Invalid encoding bits for Mp1*");
+    debug_assert_eq!(rex, BASE_REX, "Invalid registers for REX-less Mp1 encoding");
+    put_pp(bits, sink);
+    sink.put1(bits as u8);
+}
+
+// Emit single-byte opcode with mandatory prefix and REX prefix.
+fn put_rexmp1<CS: CodeSink + ?Sized>(bits: u16, rex: u8, sink: &mut CS) {
+    debug_assert_eq!(bits & 0x0c00, 0, "Invalid encoding bits for Mp1*");
+    put_pp(bits, sink);
+    sink.put1(rex_w(bits, rex));
+    sink.put1(bits as u8);
+}
+
+// Emit two-byte opcode: 0F XX with no REX prefix.
+fn put_op2<CS: CodeSink + ?Sized>(bits: u16, rex: u8, sink: &mut CS) {
+    debug_assert_eq!(bits & 0x8f00, 0x0400, "Invalid encoding bits for Op2*");
+    debug_assert_eq!(rex, BASE_REX, "Invalid registers for REX-less Op2 encoding");
+    put_mm(bits, sink);
+    sink.put1(bits as u8);
+}
+
+// Emit two-byte opcode: 0F XX with REX prefix.
+fn put_rexop2<CS: CodeSink + ?Sized>(bits: u16, rex: u8, sink: &mut CS) {
+    debug_assert_eq!(bits & 0x0f00, 0x0400, "Invalid encoding bits for RexOp2*");
+    sink.put1(rex_w(bits, rex));
+    put_mm(bits, sink);
+    sink.put1(bits as u8);
+}
+
+// Emit two-byte opcode: 0F XX with mandatory prefix and no REX prefix.
+fn put_mp2<CS: CodeSink + ?Sized>(bits: u16, rex: u8, sink: &mut CS) {
+    debug_assert_eq!(bits & 0x8c00, 0x0400, "Invalid encoding bits for Mp2*");
+    debug_assert_eq!(rex, BASE_REX, "Invalid registers for REX-less Mp2 encoding");
+    put_pp(bits, sink);
+    put_mm(bits, sink);
+    sink.put1(bits as u8);
+}
+
+// Emit two-byte opcode: 0F XX with mandatory prefix and REX prefix.
+fn put_rexmp2<CS: CodeSink + ?Sized>(bits: u16, rex: u8, sink: &mut CS) {
+    debug_assert_eq!(bits & 0x0c00, 0x0400, "Invalid encoding bits for Mp2*");
+    put_pp(bits, sink);
+    sink.put1(rex_w(bits, rex));
+    put_mm(bits, sink);
+    sink.put1(bits as u8);
+}
+
+// Emit three-byte opcode: 0F 38 XX or 0F 3A XX with mandatory prefix and no REX prefix.
+fn put_mp3<CS: CodeSink + ?Sized>(bits: u16, rex: u8, sink: &mut CS) {
+    debug_assert_eq!(bits & 0x8800, 0x0800, "Invalid encoding bits for Mp3*");
+    debug_assert_eq!(rex, BASE_REX, "Invalid registers for REX-less Mp3 encoding");
+    put_pp(bits, sink);
+    put_mm(bits, sink);
+    sink.put1(bits as u8);
+}
+
+// Emit three-byte opcode: 0F 38 XX or 0F 3A XX with mandatory prefix and REX prefix.
+fn put_rexmp3<CS: CodeSink + ?Sized>(bits: u16, rex: u8, sink: &mut CS) {
+    debug_assert_eq!(bits & 0x0800, 0x0800, "Invalid encoding bits for Mp3*");
+    put_pp(bits, sink);
+    sink.put1(rex_w(bits, rex));
+    put_mm(bits, sink);
+    sink.put1(bits as u8);
+}
+
+/// Emit a ModR/M byte for reg-reg operands.
+fn modrm_rr<CS: CodeSink + ?Sized>(rm: RegUnit, reg: RegUnit, sink: &mut CS) {
+    let reg = reg as u8 & 7;
+    let rm = rm as u8 & 7;
+    let mut b = 0b11000000;
+    b |= reg << 3;
+    b |= rm;
+    sink.put1(b);
+}
+
+/// Emit a ModR/M byte where the reg bits are part of the opcode.
+fn modrm_r_bits<CS: CodeSink + ?Sized>(rm: RegUnit, bits: u16, sink: &mut CS) {
+    let reg = (bits >> 12) as u8 & 7;
+    let rm = rm as u8 & 7;
+    let mut b = 0b11000000;
+    b |= reg << 3;
+    b |= rm;
+    sink.put1(b);
+}
+
+/// Emit a mode 01 ModR/M byte with a SIB byte and an 8-bit displacement to follow.
+fn modrm_sib_disp8<CS: CodeSink + ?Sized>(reg: RegUnit, sink: &mut CS) {
+    let reg = reg as u8 & 7;
+    let mut b = 0b01000100;
+    b |= reg << 3;
+    sink.put1(b);
+}
+
+/// Emit a mode 10 ModR/M byte with a SIB byte and a 32-bit displacement to follow.
+fn modrm_sib_disp32<CS: CodeSink + ?Sized>(reg: RegUnit, sink: &mut CS) {
+    let reg = reg as u8 & 7;
+    let mut b = 0b10000100;
+    b |= reg << 3;
+    sink.put1(b);
+}
+
+/// Emit a SIB byte with a base register and no index register.
+///
+/// This form can address any base register, including `%rsp` and `%r12` which can't be encoded
+/// in the ModR/M `rm` field.
+fn sib_noindex<CS: CodeSink + ?Sized>(base: RegUnit, sink: &mut CS) {
+    let base = base as u8 & 7;
+    // SIB        SS_III_BBB.
+    let mut b = 0b00_100_000;
+    b |= base;
+    sink.put1(b);
+}
+
+/// Get the low 4 bits of an opcode for an integer condition code.
+///
+/// Add this offset to a base opcode for:
+///
+/// ---- 0x70: Short conditional branch.
+/// 0x0f 0x80: Long conditional branch.
+/// 0x0f 0x90: SetCC.
+///
+fn icc2opc(cond: IntCC) -> u8 {
+    use ir::condcodes::IntCC::*;
+    match cond {
+        // 0x0 = Overflow.
+        // 0x1 = !Overflow.
+        UnsignedLessThan => 0x2,
+        UnsignedGreaterThanOrEqual => 0x3,
+        Equal => 0x4,
+        NotEqual => 0x5,
+        UnsignedLessThanOrEqual => 0x6,
+        UnsignedGreaterThan => 0x7,
+        // 0x8 = Sign.
+        // 0x9 = !Sign.
+        // 0xa = Parity even.
+        // 0xb = Parity odd.
+        SignedLessThan => 0xc,
+        SignedGreaterThanOrEqual => 0xd,
+        SignedLessThanOrEqual => 0xe,
+        SignedGreaterThan => 0xf,
+    }
+}
+
+/// Get the low 4 bits of an opcode for a floating point condition code.
+///
+/// The `ucomiss` and `ucomisd` instructions set the `ZF`, `PF`, and `CF` flags:
+///
+/// - Unordered: ZF,PF,CF = 111.
+/// - Greater than: ZF,PF,CF = 000.
+/// - Less than: ZF,PF,CF = 001.
+/// - Equal: ZF,PF,CF = 100.
+///
+/// Conditions that require testing more than one flag, like `eq` and `ne`, don't have a single
+/// opcode. The encoding tables only contain the supported conditions.
+fn fcc2opc(cond: FloatCC) -> u8 {
+    use ir::condcodes::FloatCC::*;
+    match cond {
+        Ordered => 0xb, // PF = 0.
+        Unordered => 0xa, // PF = 1.
+        OrderedNotEqual => 0x5, // ZF = 0.
+        UnorderedOrEqual => 0x4, // ZF = 1.
+        GreaterThan => 0x7, // CF = 0 and ZF = 0.
+        GreaterThanOrEqual => 0x3, // CF = 0.
+        UnorderedOrLessThan => 0x2, // CF = 1.
+        UnorderedOrLessThanOrEqual => 0x6, // CF = 1 or ZF = 1.
+        _ => panic!("{} not supported by fcc2opc", cond),
+    }
+}
+
+/// Emit an 8-bit branch displacement to `destination`.
+///
+/// The displacement is relative to the end of the instruction, which is right after the
+/// displacement byte.
+fn disp1<CS: CodeSink + ?Sized>(destination: Ebb, func: &Function, sink: &mut CS) {
+    let delta = func.offsets[destination].wrapping_sub(sink.offset() + 1);
+    sink.put1(delta as u8);
+}
+
+/// Emit a 32-bit branch displacement to `destination`.
+///
+/// The displacement is relative to the end of the instruction, which is right after the
+/// displacement.
+fn disp4<CS: CodeSink + ?Sized>(destination: Ebb, func: &Function, sink: &mut CS) {
+    let delta = func.offsets[destination].wrapping_sub(sink.offset() + 4);
+    sink.put4(delta);
+}
@@ -0,0 +1,22 @@
+//! Encoding tables for Intel ISAs.
+
+use ir::{Opcode, InstructionData, DataFlowGraph};
+use ir::condcodes::FloatCC;
+use ir::instructions::InstructionFormat;
+use ir::types;
+use predicates;
+use isa::Legalize;
+use isa::enc_tables::{Level1Entry, Level2Entry};
+use isa::constraints::{RecipeConstraints, OperandConstraint, ConstraintKind};
+use isa::encoding::{RecipeSizing, BranchRange};
+use super::registers::*;
+
+// Include the generated encoding tables:
+// - `LEVEL1_I32`
+// - `LEVEL1_I64`
+// - `LEVEL2`
+// - `ENCLIST`
+// - `RECIPE_NAMES`
+// - `RECIPE_CONSTRAINTS`
+// - `RECIPE_SIZING`
+include!(concat!(env!("OUT_DIR"), "/encoding-intel.rs"));
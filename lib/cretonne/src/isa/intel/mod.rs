@@ -0,0 +1,325 @@
+//! Intel Instruction Set Architectures.
+
+pub mod settings;
+mod abi;
+mod binemit;
+mod enc_tables;
+mod registers;
+
+use super::super::settings as shared_settings;
+use binemit::CodeSink;
+use isa::enc_tables::{self as shared_enc_tables, lookup_enclist, general_encoding,
+                      visit_encodings};
+use isa::Builder as IsaBuilder;
+use isa::{TargetIsa, RegInfo, RegClass, Encoding, Legalize, RecipeConstraints, RecipeSizing};
+use ir::{Function, Inst, InstructionData, DataFlowGraph, Signature, ArgumentType, Type};
+use regalloc::AllocatableSet;
+
+#[allow(dead_code)]
+struct Isa {
+    shared_flags: shared_settings::Flags,
+    isa_flags: settings::Flags,
+    cpumode: &'static [shared_enc_tables::Level1Entry<u16>],
+}
+
+/// Get an ISA builder for creating Intel targets.
+pub fn isa_builder() -> IsaBuilder {
+    IsaBuilder {
+        setup: settings::builder(),
+        constructor: isa_constructor,
+    }
+}
+
+fn isa_constructor(shared_flags: shared_settings::Flags,
+                   builder: &shared_settings::Builder)
+                   -> Box<TargetIsa> {
+    let level1 = if shared_flags.is_64bit() {
+        &enc_tables::LEVEL1_I64[..]
+    } else {
+        &enc_tables::LEVEL1_I32[..]
+    };
+    Box::new(Isa {
+        isa_flags: settings::Flags::new(&shared_flags, builder),
+        shared_flags: shared_flags,
+        cpumode: level1,
+    })
+}
+
+impl TargetIsa for Isa {
+    fn name(&self) -> &'static str {
+        "intel"
+    }
+
+    fn flags(&self) -> &shared_settings::Flags {
+        &self.shared_flags
+    }
+
+    fn register_info(&self) -> RegInfo {
+        registers::INFO.clone()
+    }
+
+    fn regclass_for_abi_type(&self, ty: Type) -> RegClass {
+        abi::regclass_for_abi_type(ty)
+    }
+
+    fn allocatable_registers(&self, func: &Function) -> AllocatableSet {
+        abi::allocatable_registers(func, &self.shared_flags)
+    }
+
+    fn stack_alignment(&self) -> u32 {
+        abi::STACK_ALIGNMENT
+    }
+
+    fn return_address_size(&self) -> u32 {
+        self.pointer_type().bytes()
+    }
+
+    fn saved_registers(&self, func: &Function) -> Vec<ArgumentType> {
+        abi::saved_registers(func, &self.shared_flags)
+    }
+
+    fn encode(&self, dfg: &DataFlowGraph, inst: &InstructionData) -> Result<Encoding, Legalize> {
+        lookup_enclist(inst.ctrl_typevar(dfg),
+                       inst.opcode(),
+                       self.cpumode,
+                       &enc_tables::LEVEL2[..])
+            .and_then(|enclist_offset| {
+                general_encoding(enclist_offset,
+                                 &enc_tables::ENCLISTS[..],
+                                 |instp| enc_tables::check_instp(inst, instp, dfg),
+                                 |isap| self.isa_flags.numbered_predicate(isap as usize))
+            })
+    }
+
+    fn legal_encodings(&self, dfg: &DataFlowGraph, inst: &InstructionData) -> Vec<Encoding> {
+        let mut encodings = Vec::new();
+        if let Ok(enclist_offset) = lookup_enclist(inst.ctrl_typevar(dfg),
+                                                   inst.opcode(),
+                                                   self.cpumode,
+                                                   &enc_tables::LEVEL2[..]) {
+            visit_encodings(enclist_offset,
+                            &enc_tables::ENCLISTS[..],
+                            |instp| enc_tables::check_instp(inst, instp, dfg),
+                            |isap| self.isa_flags.numbered_predicate(isap as usize),
+                            |enc| encodings.push(enc));
+        }
+        encodings
+    }
+
+    fn recipe_names(&self) -> &'static [&'static str] {
+        &enc_tables::RECIPE_NAMES[..]
+    }
+
+    fn recipe_constraints(&self) -> &'static [RecipeConstraints] {
+        &enc_tables::RECIPE_CONSTRAINTS
+    }
+
+    fn recipe_sizing(&self) -> &'static [RecipeSizing] {
+        &enc_tables::RECIPE_SIZING
+    }
+
+    fn legalize_signature(&self, sig: &mut Signature) {
+        abi::legalize_signature(sig, &self.shared_flags, &self.isa_flags)
+    }
+
+    fn emit_inst(&self, func: &Function, inst: Inst, sink: &mut CodeSink) {
+        binemit::emit_inst(func, inst, sink)
+    }
+
+    fn reloc_names(&self) -> &'static [&'static str] {
+        &binemit::RELOC_NAMES
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use settings::{self, Configurable};
+    use isa::{self, Legalize, OperandConstraint, ConstraintKind};
+    use ir::{DataFlowGraph, InstructionData, Opcode};
+    use ir::{types, immediates};
+
+    fn encstr(isa: &isa::TargetIsa, enc: isa::Encoding) -> String {
+        isa.display_enc(enc).to_string()
+    }
+
+    #[test]
+    fn test_64bitenc() {
+        let mut shared_builder = settings::builder();
+        shared_builder.set_bool("is_64bit", true).unwrap();
+        let shared_flags = settings::Flags::new(&shared_builder);
+        let isa = isa::lookup("intel").unwrap().finish(shared_flags);
+
+        let mut dfg = DataFlowGraph::new();
+        let ebb = dfg.make_ebb();
+        let arg64 = dfg.append_ebb_arg(ebb, types::I64);
+        let arg32 = dfg.append_ebb_arg(ebb, types::I32);
+
+        // Try to encode iadd_imm.i64 vx1, -10.
+        let inst64 = InstructionData::BinaryImm {
+            opcode: Opcode::IaddImm,
+            ty: types::I64,
+            arg: arg64,
+            imm: immediates::Imm64::new(-10),
+        };
+
+        // The general encoding is REX.W 81 /0 id.
+        assert_eq!(encstr(&*isa, isa.encode(&dfg, &inst64).unwrap()),
+                   "RexOp1rid#8081");
+
+        // Create an iadd.i32 which is encodable with or without a REX prefix.
+        let inst32 = InstructionData::Binary {
+            opcode: Opcode::Iadd,
+            ty: types::I32,
+            args: [arg32, arg32],
+        };
+
+        assert_eq!(encstr(&*isa, isa.encode(&dfg, &inst32).unwrap()), "RexOp1rr#01");
+
+        // Division is expanded into the Intel-specific instructions.
+        let div64 = InstructionData::Binary {
+            opcode: Opcode::Udiv,
+            ty: types::I64,
+            args: [arg64, arg64],
+        };
+
+        assert_eq!(isa.encode(&dfg, &div64), Err(Legalize::IntelExpand));
+    }
+
+    // Same as above, but for 32-bit code.
+    #[test]
+    fn test_32bitenc() {
+        let mut shared_builder = settings::builder();
+        shared_builder.set_bool("is_64bit", false).unwrap();
+        let shared_flags = settings::Flags::new(&shared_builder);
+        let isa = isa::lookup("intel").unwrap().finish(shared_flags);
+
+        let mut dfg = DataFlowGraph::new();
+        let ebb = dfg.make_ebb();
+        let arg64 = dfg.append_ebb_arg(ebb, types::I64);
+        let arg32 = dfg.append_ebb_arg(ebb, types::I32);
+        let arg8 = dfg.append_ebb_arg(ebb, types::I8);
+
+        // There are no i64 encodings in 32-bit mode.
+        let inst64 = InstructionData::BinaryImm {
+            opcode: Opcode::IaddImm,
+            ty: types::I64,
+            arg: arg64,
+            imm: immediates::Imm64::new(-10),
+        };
+
+        assert_eq!(isa.encode(&dfg, &inst64), Err(Legalize::Narrow));
+
+        // Small immediates are picked by branch relaxation, so the general encoding is first.
+        let inst32 = InstructionData::BinaryImm {
+            opcode: Opcode::IaddImm,
+            ty: types::I32,
+            arg: arg32,
+            imm: immediates::Imm64::new(10),
+        };
+
+        assert_eq!(encstr(&*isa, isa.encode(&dfg, &inst32).unwrap()), "Op1rid#81");
+
+        // The `tzcnt` instruction is only available with BMI1.
+        let ctz32 = InstructionData::Unary {
+            opcode: Opcode::Ctz,
+            ty: types::I32,
+            arg: arg32,
+        };
+
+        assert_eq!(isa.encode(&dfg, &ctz32), Err(Legalize::Expand));
+
+        // There are no i8 arithmetic instructions, but some conversions use i8.
+        let add8 = InstructionData::Binary {
+            opcode: Opcode::Iadd,
+            ty: types::I8,
+            args: [arg8, arg8],
+        };
+
+        assert_eq!(isa.encode(&dfg, &add8), Err(Legalize::Widen));
+
+        // An ireduce.i8 doesn't need any code.
+        let reduce8 = InstructionData::Unary {
+            opcode: Opcode::Ireduce,
+            ty: types::I8,
+            arg: arg32,
+        };
+
+        assert_eq!(encstr(&*isa, isa.encode(&dfg, &reduce8).unwrap()), "null#00");
+    }
+
+    #[test]
+    fn test_bmi1() {
+        let shared_flags = settings::Flags::new(&settings::builder());
+
+        // Set the has_bmi1 setting which unlocks the `tzcnt` encoding for ctz.
+        let mut isa_builder = isa::lookup("intel").unwrap();
+        isa_builder.set_bool("has_bmi1", true).unwrap();
+
+        let isa = isa_builder.finish(shared_flags);
+
+        let mut dfg = DataFlowGraph::new();
+        let ebb = dfg.make_ebb();
+        let arg32 = dfg.append_ebb_arg(ebb, types::I32);
+
+        let ctz32 = InstructionData::Unary {
+            opcode: Opcode::Ctz,
+            ty: types::I32,
+            arg: arg32,
+        };
+        assert_eq!(encstr(&*isa, isa.encode(&dfg, &ctz32).unwrap()), "Mp2urm#6bc");
+    }
+
+    #[test]
+    fn recipe_constraints() {
+        let shared_flags = settings::Flags::new(&settings::builder());
+        let isa = isa::lookup("intel").unwrap().finish(shared_flags);
+        let regs = isa.register_info();
+        let gpr = &regs.classes[0];
+        assert_eq!(gpr.name, "GPR");
+
+        let constraints = |name| {
+            let recipe = isa.recipe_names().iter().position(|&n| n == name).unwrap();
+            isa.recipe_constraints()[recipe]
+        };
+        let reg = OperandConstraint {
+            kind: ConstraintKind::Reg,
+            regclass: gpr,
+        };
+        let tied = OperandConstraint {
+            kind: ConstraintKind::Tied(0),
+            regclass: gpr,
+        };
+        let fixed = |unit| {
+            OperandConstraint {
+                kind: ConstraintKind::FixedReg(unit),
+                regclass: gpr,
+            }
+        };
+
+        // Two-address instructions overwrite their first operand.
+        let rr = constraints("RexOp1rr");
+        assert_eq!(rr.ins, &[reg, reg]);
+        assert_eq!(rr.outs, &[tied]);
+
+        // Without a REX prefix, only the low 8 registers can be used.
+        let gpr8 = &regs.classes[1];
+        assert_eq!(gpr8.name, "GPR8");
+        let reg8 = OperandConstraint {
+            kind: ConstraintKind::Reg,
+            regclass: gpr8,
+        };
+        let rr8 = constraints("Op1rr");
+        assert_eq!(rr8.ins, &[reg8, reg8]);
+
+        // Shift amounts must be in `%rcx`.
+        let rc = constraints("RexOp1rc");
+        assert_eq!(rc.ins, &[reg, fixed(1)]);
+        assert_eq!(rc.outs, &[tied]);
+
+        // Division uses `%rdx:%rax`.
+        let div = constraints("RexOp1div");
+        assert_eq!(div.ins, &[fixed(0), fixed(2), reg]);
+        assert_eq!(div.outs, &[fixed(0), fixed(2)]);
+    }
+}
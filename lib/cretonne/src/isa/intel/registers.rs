@@ -0,0 +1,66 @@
+//! Intel register descriptions.
+
+use isa::registers::{RegBank, RegClass, RegClassData, RegInfo};
+
+include!(concat!(env!("OUT_DIR"), "/registers-intel.rs"));
+
+#[cfg(test)]
+mod tests {
+    use super::{INFO, GPR, GPR8, ABCD, FPR, FPR8};
+    use isa::RegUnit;
+
+    #[test]
+    fn unit_encodings() {
+        // The encoding of integer registers is not alphabetical.
+        assert_eq!(INFO.parse_regunit("rax"), Some(0));
+        assert_eq!(INFO.parse_regunit("rbx"), Some(3));
+        assert_eq!(INFO.parse_regunit("rcx"), Some(1));
+        assert_eq!(INFO.parse_regunit("rdx"), Some(2));
+        assert_eq!(INFO.parse_regunit("rsi"), Some(6));
+        assert_eq!(INFO.parse_regunit("rdi"), Some(7));
+        assert_eq!(INFO.parse_regunit("rsp"), Some(4));
+        assert_eq!(INFO.parse_regunit("rbp"), Some(5));
+        assert_eq!(INFO.parse_regunit("r8"), Some(8));
+        assert_eq!(INFO.parse_regunit("r15"), Some(15));
+
+        assert_eq!(INFO.parse_regunit("xmm0"), Some(16));
+        assert_eq!(INFO.parse_regunit("xmm15"), Some(31));
+
+        assert_eq!(INFO.parse_regunit("r16"), None);
+        assert_eq!(INFO.parse_regunit("xmm16"), None);
+    }
+
+    #[test]
+    fn unit_names() {
+        fn uname(ru: RegUnit) -> String {
+            INFO.display_regunit(ru).to_string()
+        }
+
+        assert_eq!(uname(0), "%rax");
+        assert_eq!(uname(3), "%rbx");
+        assert_eq!(uname(7), "%rdi");
+        assert_eq!(uname(8), "%r8");
+        assert_eq!(uname(15), "%r15");
+        assert_eq!(uname(16), "%xmm0");
+        assert_eq!(uname(31), "%xmm15");
+    }
+
+    #[test]
+    fn classes() {
+        assert!(GPR.contains(GPR.unit(0)));
+        assert!(GPR.contains(GPR.unit(15)));
+        assert!(!FPR.contains(GPR.unit(0)));
+        assert!(FPR.contains(FPR.unit(15)));
+        assert_eq!(FPR.unit(10), 26);
+
+        // The registers that can be encoded without a REX prefix.
+        assert!(GPR8.contains(GPR.unit(7)));
+        assert!(!GPR8.contains(GPR.unit(8)));
+        assert!(FPR8.contains(FPR.unit(7)));
+        assert!(!FPR8.contains(FPR.unit(8)));
+
+        // The registers with an addressable low byte without a REX prefix.
+        assert!(ABCD.contains(GPR.unit(3)));
+        assert!(!ABCD.contains(GPR.unit(6)));
+    }
+}
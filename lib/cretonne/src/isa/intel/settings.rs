@@ -0,0 +1,54 @@
+//! Intel Settings.
+
+use settings::{self, detail, Builder};
+use std::fmt;
+
+// Include code generated by `lib/cretonne/meta/gen_settings.py`. This file contains a public
+// `Flags` struct with an impl for all of the settings defined in
+// `lib/cretonne/meta/isa/intel/settings.py`.
+include!(concat!(env!("OUT_DIR"), "/settings-intel.rs"));
+
+#[cfg(test)]
+mod tests {
+    use super::{builder, Flags};
+    use settings::{self, Configurable};
+
+    #[test]
+    fn display_default() {
+        let shared = settings::Flags::new(&settings::builder());
+        let b = builder();
+        let f = Flags::new(&shared, &b);
+        assert_eq!(f.to_string(),
+                   "[intel]\n\
+                    has_sse2 = true\n\
+                    has_sse3 = false\n\
+                    has_ssse3 = false\n\
+                    has_sse41 = false\n\
+                    has_sse42 = false\n\
+                    has_popcnt = false\n\
+                    has_bmi1 = false\n\
+                    has_lzcnt = false\n");
+        // Predicates are not part of the Display output.
+        assert_eq!(f.use_sse2(), true);
+        assert_eq!(f.use_sse41(), false);
+    }
+
+    #[test]
+    fn predicates() {
+        let shared = settings::Flags::new(&settings::builder());
+        let mut b = builder();
+        b.set_bool("has_popcnt", true).unwrap();
+        let f = Flags::new(&shared, &b);
+        assert_eq!(f.use_popcnt(), false);
+
+        b.set_bool("has_sse42", true).unwrap();
+        let f = Flags::new(&shared, &b);
+        assert_eq!(f.use_popcnt(), true);
+
+        let mut sb = settings::builder();
+        sb.set_bool("enable_float", false).unwrap();
+        let shared = settings::Flags::new(&sb);
+        let f = Flags::new(&shared, &builder());
+        assert_eq!(f.use_sse2(), false);
+    }
+}
@@ -40,19 +40,31 @@
 //! The configured target ISA trait object is a `Box<TargetIsa>` which can be used for multiple
 //! concurrent function compilations.
 
-pub use isa::encoding::Encoding;
+pub use isa::encoding::{Encoding, RecipeSizing, BranchRange};
+pub use isa::constraints::{RecipeConstraints, OperandConstraint, ConstraintKind};
+pub use isa::registers::{RegInfo, RegUnit, RegClass, RegClassData, RegBank};
 use settings;
-use ir::{InstructionData, DataFlowGraph};
+use binemit::CodeSink;
+use ir::{Function, Inst, InstructionData, DataFlowGraph, Signature, ArgumentType, Type, types};
+use regalloc::AllocatableSet;
 
 pub mod riscv;
+pub mod intel;
+pub mod arm64;
+pub mod arm32;
+mod constraints;
 mod encoding;
 mod enc_tables;
+pub mod registers;
 
 /// Look for a supported ISA with the given `name`.
 /// Return a builder that can create a corresponding `TargetIsa`.
 pub fn lookup(name: &str) -> Option<Builder> {
     match name {
         "riscv" => riscv_builder(),
+        "intel" => intel_builder(),
+        "arm64" => arm64_builder(),
+        "arm32" => arm32_builder(),
         _ => None,
     }
 }
@@ -62,6 +74,21 @@ fn riscv_builder() -> Option<Builder> {
     Some(riscv::isa_builder())
 }
 
+// Make a builder for Intel x86 and x86-64.
+fn intel_builder() -> Option<Builder> {
+    Some(intel::isa_builder())
+}
+
+// Make a builder for ARM64.
+fn arm64_builder() -> Option<Builder> {
+    Some(arm64::isa_builder())
+}
+
+// Make a builder for ARM32.
+fn arm32_builder() -> Option<Builder> {
+    Some(arm32::isa_builder())
+}
+
 /// Builder for a `TargetIsa`.
 /// Modify the ISA-specific settings before creating the `TargetIsa` trait object with `finish`.
 pub struct Builder {
@@ -87,6 +114,37 @@ impl settings::Configurable for Builder {
     }
 }
 
+/// Legalization action to perform when an instruction has no legal encoding.
+///
+/// This is returned by `TargetIsa::encode()` to guide the legalizer. Each action corresponds to an
+/// `XFormGroup` of transformations defined in `lib/cretonne/meta/cretonne/legalize.py`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Legalize {
+    /// Legalize in terms of narrower types.
+    ///
+    /// The controlling type variable is not supported by the ISA. Integer types are split into
+    /// high and low parts, and vector types are split into halves.
+    Narrow,
+
+    /// Legalize in terms of wider types.
+    ///
+    /// The controlling type variable is too small to be used directly by most instructions. For
+    /// example, `i8` and `i16` arithmetic is performed with `i32` instructions on RISC targets.
+    Widen,
+
+    /// Expand into a sequence of other instructions operating on the same types.
+    ///
+    /// The controlling type variable is supported, but the opcode or its operands are not.
+    Expand,
+
+    /// Expand using Intel-specific instructions.
+    ///
+    /// Some operations like integer division are expanded into Intel-specific instructions with
+    /// fixed register operands. Patterns that don't apply fall back to `Expand`. The patterns are
+    /// defined in `lib/cretonne/meta/isa/intel/legalize.py`.
+    IntelExpand,
+}
+
 /// Methods that are specialized to a target ISA.
 pub trait TargetIsa {
     /// Get the name of this ISA.
@@ -95,13 +153,89 @@ pub trait TargetIsa {
     /// Get the ISA-independent flags that were used to make this trait object.
     fn flags(&self) -> &settings::Flags;
 
+    /// Get a data structure describing the registers in this ISA.
+    fn register_info(&self) -> RegInfo;
+
+    /// Get the register class that should be used to represent an ABI argument or return value of
+    /// type `ty`. This should be the top-level register class that contains the argument
+    /// registers.
+    ///
+    /// This is also the register class the register allocator uses for values that aren't
+    /// constrained by an encoding recipe.
+    fn regclass_for_abi_type(&self, ty: Type) -> RegClass;
+
+    /// Get the set of allocatable registers that can be used when compiling `func`.
+    ///
+    /// This set excludes reserved registers like the stack pointer and other special-purpose
+    /// registers.
+    fn allocatable_registers(&self, func: &Function) -> AllocatableSet;
+
+    /// Get the required alignment of the stack pointer in bytes.
+    ///
+    /// The stack frame allocated by the prologue is a multiple of this size.
+    fn stack_alignment(&self) -> u32;
+
+    /// Get the number of bytes pushed on the stack by a call instruction.
+    ///
+    /// When calls push the return address, the stack pointer on entry to a function is misaligned
+    /// by this amount. ISAs that pass the return address in a register don't need to override
+    /// this.
+    fn return_address_size(&self) -> u32 {
+        0
+    }
+
+    /// Get the registers that the prologue of the register allocated function `func` must save.
+    ///
+    /// This includes the callee-saved registers used by `func`, and the link register if it is
+    /// clobbered by calls. Each register is described as a special-purpose argument with an
+    /// `ArgumentLoc::Reg` location.
+    fn saved_registers(&self, func: &Function) -> Vec<ArgumentType>;
+
+    /// Get the pointer type of this ISA.
+    fn pointer_type(&self) -> Type {
+        if self.flags().is_64bit() {
+            types::I64
+        } else {
+            types::I32
+        }
+    }
+
     /// Encode an instruction after determining it is legal.
     ///
     /// If `inst` can legally be encoded in this ISA, produce the corresponding `Encoding` object.
-    /// Otherwise, return `None`.
+    /// Otherwise, return a `Legalize` action describing how the instruction should be transformed
+    /// into legal instructions.
     ///
     /// This is also the main entry point for determining if an instruction is legal.
-    fn encode(&self, dfg: &DataFlowGraph, inst: &InstructionData) -> Option<Encoding>;
+    fn encode(&self, dfg: &DataFlowGraph, inst: &InstructionData) -> Result<Encoding, Legalize>;
+
+    /// Get all the legal encodings of `inst`.
+    ///
+    /// The encodings are ordered from the most specific to the most general, so the last one is
+    /// the encoding returned by `encode()`. An instruction can have multiple encodings with
+    /// different code sizes. Branch relaxation uses this to find a branch encoding with a
+    /// sufficient range.
+    fn legal_encodings(&self, dfg: &DataFlowGraph, inst: &InstructionData) -> Vec<Encoding>;
+
+    /// Legalize a function signature.
+    ///
+    /// This is used to legalize both the signature of the function being compiled and any called
+    /// functions. The signature should be modified by adding `ArgumentLoc` annotations to all
+    /// arguments and return values.
+    ///
+    /// Arguments with types that are not supported by the ABI can be expanded into multiple
+    /// arguments:
+    ///
+    /// - Integer types that are too large to fit in a register can be broken into multiple
+    ///   arguments of a smaller integer type.
+    /// - Floating point types can be bit-cast to an integer type of the same size, and possibly
+    ///   broken into smaller integer types.
+    /// - Vector types can be bit-cast and broken down into smaller vectors or scalars.
+    ///
+    /// Values passed across the ABI boundaries must be converted to match the legalized signature.
+    ///
+    /// The default implementation leaves the signature unchanged.
+    fn legalize_signature(&self, _sig: &mut Signature) {}
 
     /// Get a static array of names associated with encoding recipes in this ISA. Encoding recipes
     /// are numbered starting from 0, corresponding to indexes into th name array.
@@ -109,6 +243,29 @@ pub trait TargetIsa {
     /// This is just used for printing and parsing encodings in the textual IL format.
     fn recipe_names(&self) -> &'static [&'static str];
 
+    /// Get a static array of value operand constraints associated with encoding recipes in this
+    /// ISA.
+    ///
+    /// The constraints describe which registers can be used with an encoding recipe.
+    fn recipe_constraints(&self) -> &'static [RecipeConstraints];
+
+    /// Get a static array of code size information associated with encoding recipes in this ISA.
+    ///
+    /// This is used to compute code offsets before emitting binary machine code.
+    fn recipe_sizing(&self) -> &'static [RecipeSizing];
+
+    /// Get a static array of names associated with relocations in this ISA.
+    ///
+    /// This array can be indexed by the contents of `binemit::Reloc` objects passed to a
+    /// `CodeSink`.
+    fn reloc_names(&self) -> &'static [&'static str];
+
+    /// Emit binary machine code for a single instruction into the `sink` trait object.
+    ///
+    /// Note that this will call `put*` methods on the trait object via its vtable which is not the
+    /// fastest way of emitting code.
+    fn emit_inst(&self, func: &Function, inst: Inst, sink: &mut CodeSink);
+
     /// Create an object that can display an ISA-dependent encoding properly.
     fn display_enc(&self, enc: Encoding) -> encoding::DisplayEncoding {
         encoding::DisplayEncoding {
@@ -0,0 +1,230 @@
+//! RISC-V ABI implementation.
+//!
+//! This module implements the RISC-V calling convention through the primary `legalize_signature()`
+//! entry point.
+//!
+//! This doesn't support the soft-float ABI variants where floating point arguments are passed in
+//! integer registers while the hardware has floating point registers. When the `F` or `D`
+//! extensions are not in use, floating point arguments are passed according to the integer
+//! calling convention.
+
+use abi::{ArgAction, ArgAssigner, ValueConversion, legalize_args};
+use ir::{Function, Signature, ArgumentType, ArgumentLoc, ArgumentExtension, ArgumentPurpose,
+         Type, ValueLoc};
+use ir::instructions::CallInfo;
+use isa::RegClass;
+use regalloc::AllocatableSet;
+use ir::types;
+use settings as shared_settings;
+use super::registers::{GPR, FPR};
+use super::settings;
+
+/// Arguments are passed in `a0`-`a7` which are `x10`-`x17`, and in `fa0`-`fa7` which are
+/// `f10`-`f17`.
+const FIRST_ARG_REG: usize = 10;
+
+/// The stack pointer is always 16-byte aligned in the standard calling convention.
+pub const STACK_ALIGNMENT: u32 = 16;
+
+/// The return address is passed in `ra` which is `x1`.
+const LINK_REG: usize = 1;
+
+/// The callee-saved integer registers `s0`-`s11` are `x8`, `x9`, and `x18`-`x27`.
+const CALLEE_SAVED_GPRS: [usize; 12] = [8, 9, 18, 19, 20, 21, 22, 23, 24, 25, 26, 27];
+
+/// The callee-saved floating point registers `fs0`-`fs11` are `f8`, `f9`, and `f18`-`f27`.
+const CALLEE_SAVED_FPRS: [usize; 12] = [8, 9, 18, 19, 20, 21, 22, 23, 24, 25, 26, 27];
+
+struct Args {
+    pointer_bits: u16,
+    pointer_bytes: u32,
+    pointer_type: Type,
+    use_f: bool,
+    use_d: bool,
+    reg_limit: u32,
+    use_stack: bool,
+    regs: u32,
+    fregs: u32,
+    offset: u32,
+}
+
+impl Args {
+    fn new(pointer_type: Type,
+           reg_limit: u32,
+           use_stack: bool,
+           isa_flags: &settings::Flags)
+           -> Args {
+        Args {
+            pointer_bits: pointer_type.bits(),
+            pointer_bytes: pointer_type.bytes(),
+            pointer_type: pointer_type,
+            use_f: isa_flags.use_f(),
+            use_d: isa_flags.use_d(),
+            reg_limit: reg_limit,
+            use_stack: use_stack,
+            regs: 0,
+            fregs: 0,
+            offset: 0,
+        }
+    }
+
+    /// Can a value of the floating point type `ty` be passed in a floating point register?
+    fn has_fpr(&self, ty: Type) -> bool {
+        match ty {
+            types::F32 => self.use_f,
+            types::F64 => self.use_d,
+            _ => false,
+        }
+    }
+}
+
+impl ArgAssigner for Args {
+    fn assign(&mut self, arg: &ArgumentType) -> ArgAction {
+        let ty = arg.value_type;
+
+        // RISC-V doesn't have SIMD at all, so break all vectors down.
+        if !ty.is_scalar() {
+            return ValueConversion::VectorSplit.into();
+        }
+
+        if ty.is_float() {
+            if self.has_fpr(ty) && self.fregs < self.reg_limit {
+                let reg = FPR.unit(FIRST_ARG_REG + self.fregs as usize);
+                self.fregs += 1;
+                return ArgumentLoc::Reg(reg).into();
+            }
+            // Without a free floating point register, the value is passed according to the
+            // integer calling convention. Floats that don't fit in an integer register are
+            // converted to an integer type so they can be split below.
+            if ty.bits() > self.pointer_bits {
+                return ValueConversion::IntBits.into();
+            }
+        } else if ty.bits() > self.pointer_bits {
+            // Large integers and booleans are broken down to fit in a register. The low half is
+            // passed first.
+            return ValueConversion::IntSplit.into();
+        }
+
+        // Small integers are extended to the size of a pointer register.
+        if ty.is_int() && ty.bits() < self.pointer_bits {
+            match arg.extension {
+                ArgumentExtension::None => {}
+                ArgumentExtension::Uext => return ValueConversion::Uext(self.pointer_type).into(),
+                ArgumentExtension::Sext => return ValueConversion::Sext(self.pointer_type).into(),
+            }
+        }
+
+        if self.regs < self.reg_limit {
+            // Assign to a register.
+            let reg = GPR.unit(FIRST_ARG_REG + self.regs as usize);
+            self.regs += 1;
+            ArgumentLoc::Reg(reg).into()
+        } else if !self.use_stack {
+            // Return values that don't fit in registers would be returned in memory through a
+            // hidden pointer argument. That isn't supported, so leave the location unassigned.
+            ArgumentLoc::Unassigned.into()
+        } else {
+            // Assign a stack location. Every argument on the stack occupies a pointer-sized slot.
+            let loc = ArgumentLoc::Stack(self.offset as i32);
+            self.offset += self.pointer_bytes;
+            loc.into()
+        }
+    }
+}
+
+/// Legalize `sig` for RISC-V.
+///
+/// All the calling conventions map to the standard RISC-V psABI. Up to eight arguments are passed
+/// in registers, and up to two return values. The remaining arguments are passed on the stack.
+///
+/// Returning more values requires the psABI's hidden pointer to memory in the caller's frame,
+/// which isn't implemented. The extra return values are left without a location, so
+/// `verifier::verify_encodings()` rejects the signature.
+pub fn legalize_signature(sig: &mut Signature,
+                          flags: &shared_settings::Flags,
+                          isa_flags: &settings::Flags) {
+    let pointer_type = if flags.is_64bit() {
+        types::I64
+    } else {
+        types::I32
+    };
+
+    let mut args = Args::new(pointer_type, 8, true, isa_flags);
+    legalize_args(&mut sig.argument_types, &mut args);
+
+    let mut rets = Args::new(pointer_type, 2, false, isa_flags);
+    legalize_args(&mut sig.return_types, &mut rets);
+}
+
+/// Get register class for a type appearing in a legalized signature.
+pub fn regclass_for_abi_type(ty: Type, isa_flags: &settings::Flags) -> RegClass {
+    let use_fpr = match ty {
+        types::F32 => isa_flags.use_f(),
+        types::F64 => isa_flags.use_d(),
+        _ => false,
+    };
+    if use_fpr { FPR } else { GPR }
+}
+
+/// Get the set of allocatable registers for `func`.
+pub fn allocatable_registers(_func: &Function) -> AllocatableSet {
+    let mut regs = AllocatableSet::new();
+    // The zero register `x0` is hard-wired to 0. The return address `x1`, stack pointer `x2`,
+    // global pointer `x3`, and thread pointer `x4` are reserved.
+    for unit in 0..5 {
+        regs.take(GPR, GPR.unit(unit));
+    }
+    regs
+}
+
+/// Get the registers that must be saved by the prologue of `func`.
+///
+/// These are the callee-saved integer and floating point registers used by `func`, and the return
+/// address register if `func` contains any calls. The floating point registers are saved with
+/// their full width, which is 64 bits when the `D` extension is in use.
+pub fn saved_registers(func: &Function,
+                       pointer_type: Type,
+                       isa_flags: &settings::Flags)
+                       -> Vec<ArgumentType> {
+    let mut saved = Vec::new();
+
+    let has_calls = func.layout
+        .ebbs()
+        .flat_map(|ebb| func.layout.ebb_insts(ebb))
+        .any(|inst| match func.dfg[inst].analyze_call() {
+            CallInfo::NotACall => false,
+            _ => true,
+        });
+    if has_calls {
+        saved.push(saved_register(pointer_type, ArgumentPurpose::Link, GPR, LINK_REG));
+    }
+
+    let float_type = if isa_flags.use_d() {
+        Some(types::F64)
+    } else if isa_flags.use_f() {
+        Some(types::F32)
+    } else {
+        None
+    };
+    let mut candidates: Vec<(RegClass, usize, Type)> =
+        CALLEE_SAVED_GPRS.iter().map(|&unit| (GPR, unit, pointer_type)).collect();
+    if let Some(ty) = float_type {
+        candidates.extend(CALLEE_SAVED_FPRS.iter().map(|&unit| (FPR, unit, ty)));
+    }
+    for (rc, unit, ty) in candidates {
+        let loc = ValueLoc::Reg(rc.unit(unit));
+        if func.locations.keys().any(|v| func.locations[v] == loc) {
+            saved.push(saved_register(ty, ArgumentPurpose::CalleeSaved, rc, unit));
+        }
+    }
+
+    saved
+}
+
+/// Make a special-purpose argument for the saved register `unit` in `rc`.
+fn saved_register(ty: Type, purpose: ArgumentPurpose, rc: RegClass, unit: usize) -> ArgumentType {
+    let mut arg = ArgumentType::new(ty);
+    arg.purpose = purpose;
+    arg.location = ArgumentLoc::Reg(rc.unit(unit));
+    arg
+}
@@ -0,0 +1,562 @@
+//! Emitting binary RISC-V machine code.
+
+use binemit::{CodeSink, Reloc, bad_encoding, stack_slot_offset, globalsym_name};
+use ir::{Function, Inst, InstructionData};
+use ir::atomics::AtomicOrdering;
+use isa::RegUnit;
+
+include!(concat!(env!("OUT_DIR"), "/binemit-riscv.rs"));
+
+/// RISC-V relocation kinds.
+pub enum RelocKind {
+    /// A function call through an `auipc` + `jalr` instruction pair.
+    Call,
+    /// The high 20 bits of an absolute address in a U-type instruction like `lui`.
+    Hi20,
+    /// The low 12 bits of an absolute address in an I-type instruction like `addi`.
+    Lo12I,
+    /// A 64-bit absolute address.
+    Abs64,
+}
+
+/// The names of the RISC-V relocation kinds, using the ELF psABI names.
+pub static RELOC_NAMES: [&'static str; 4] = ["R_RISCV_CALL",
+                                             "R_RISCV_HI20",
+                                             "R_RISCV_LO12_I",
+                                             "R_RISCV_64"];
+
+impl Into<Reloc> for RelocKind {
+    fn into(self) -> Reloc {
+        Reloc(self as u16)
+    }
+}
+
+/// The return address register `x1`.
+const RETURN_ADDRESS: RegUnit = 1;
+
+/// The stack pointer register `x2`.
+const STACK_POINTER: RegUnit = 2;
+
+/// The temporary register `t0` used for large stack pointer adjustments. It never holds a live
+/// value in the prologue and epilogue, where the stack pointer is adjusted.
+const FRAME_SCRATCH: RegUnit = 5;
+
+/// Base opcode of the `lui` instruction.
+const LUI: u32 = 0b01101;
+
+/// Base opcode of the `auipc` instruction.
+const AUIPC: u32 = 0b00101;
+
+/// Base opcode of the `jal` instruction.
+const JAL: u32 = 0b11011;
+
+/// Base opcode of the OP-FP floating point instructions.
+const OP_FP: u16 = 0b10100;
+
+/// Encoding bits of the `xor` instruction.
+const XOR: u16 = 0b01100 | (0b100 << 5);
+
+/// Encoding bits of the `addi` instruction.
+const ADDI: u16 = 0b00100;
+
+/// Encoding bits of the `xori` instruction.
+const XORI: u16 = 0b00100 | (0b100 << 5);
+
+/// Encoding bits of the `slli` instruction.
+const SLLI: u16 = 0b00100 | (0b001 << 5);
+
+/// Encoding bits of the `bne` instruction.
+const BNE: u16 = 0b11000 | (0b001 << 5);
+
+/// Encoding bits of the `fence` instruction.
+const FENCE: u16 = 0b00011;
+
+/// Predecessor and successor sets for `fence r, rw`, used after an acquire operation.
+const FENCE_R_RW: i64 = 0b0010_0011;
+
+/// Predecessor and successor sets for `fence rw, w`, used before a release operation.
+const FENCE_RW_W: i64 = 0b0011_0001;
+
+/// Predecessor and successor sets for `fence rw, rw`, a full memory barrier.
+const FENCE_RW_RW: i64 = 0b0011_0011;
+
+/// The funct5 field of the `sc` store-conditional instruction.
+const SC_FUNCT5: u16 = 0b00011;
+
+/// An instruction with all bits zero is guaranteed to be illegal.
+const ILLEGAL_INSTRUCTION: u32 = 0;
+
+/// R-type instructions.
+///
+///   31     24  19  14     11 6
+///   funct7 rs2 rs1 funct3 rd opcode
+///       25  20  15     12  7      0
+///
+/// Encoding bits: `opcode[6:2] | (funct3 << 5) | (funct7 << 8)`.
+fn put_r<CS: CodeSink + ?Sized>(bits: u16,
+                                rs1: RegUnit,
+                                rs2: RegUnit,
+                                rd: RegUnit,
+                                sink: &mut CS) {
+    let bits = bits as u32;
+    let opcode5 = bits & 0x1f;
+    let funct3 = (bits >> 5) & 0x7;
+    let funct7 = (bits >> 8) & 0x7f;
+    let rs1 = rs1 as u32 & 0x1f;
+    let rs2 = rs2 as u32 & 0x1f;
+    let rd = rd as u32 & 0x1f;
+
+    // 0-6: opcode
+    let mut i = 0x3;
+    i |= opcode5 << 2;
+    i |= rd << 7;
+    i |= funct3 << 12;
+    i |= rs1 << 15;
+    i |= rs2 << 20;
+    i |= funct7 << 25;
+
+    sink.put4(i);
+}
+
+/// R-type instructions with a shift amount instead of rs2.
+///
+///   31     25    19  14     11 6
+///   funct7 shamt rs1 funct3 rd opcode
+///       25    20  15     12  7      0
+///
+/// Both funct7 and shamt contribute to bit 25. In RV64, shamt uses it for shifts > 31.
+///
+/// Encoding bits: `opcode[6:2] | (funct3 << 5) | (funct7 << 8)`.
+fn put_rshamt<CS: CodeSink + ?Sized>(bits: u16,
+                                     rs1: RegUnit,
+                                     shamt: i64,
+                                     rd: RegUnit,
+                                     sink: &mut CS) {
+    let bits = bits as u32;
+    let funct7 = (bits >> 8) & 0x7f;
+    let shamt = shamt as u32 & 0x3f;
+
+    // The shift amount goes in the immediate field of an I-type instruction, with funct7 above it.
+    let imm = (funct7 << 5) | shamt;
+    put_i(bits as u16 & 0xff, rs1, imm as i64, rd, sink);
+}
+
+/// I-type instructions.
+///
+///   31  19  14     11 6
+///   imm rs1 funct3 rd opcode
+///    20  15     12  7      0
+///
+/// Encoding bits: `opcode[6:2] | (funct3 << 5)`
+fn put_i<CS: CodeSink + ?Sized>(bits: u16, rs1: RegUnit, imm: i64, rd: RegUnit, sink: &mut CS) {
+    let bits = bits as u32;
+    let opcode5 = bits & 0x1f;
+    let funct3 = (bits >> 5) & 0x7;
+    let rs1 = rs1 as u32 & 0x1f;
+    let rd = rd as u32 & 0x1f;
+
+    // 0-6: opcode
+    let mut i = 0x3;
+    i |= opcode5 << 2;
+    i |= rd << 7;
+    i |= funct3 << 12;
+    i |= rs1 << 15;
+    i |= (imm << 20) as u32;
+
+    sink.put4(i);
+}
+
+/// S-type instructions.
+///
+///   31       24  19  14     11       6
+///   imm[11:5] rs2 rs1 funct3 imm[4:0] opcode
+///         25  20  15     12        7      0
+///
+/// Encoding bits: `opcode[6:2] | (funct3 << 5)`
+fn put_s<CS: CodeSink + ?Sized>(bits: u16, rs1: RegUnit, rs2: RegUnit, imm: i64, sink: &mut CS) {
+    let bits = bits as u32;
+    let opcode5 = bits & 0x1f;
+    let funct3 = (bits >> 5) & 0x7;
+    let rs1 = rs1 as u32 & 0x1f;
+    let rs2 = rs2 as u32 & 0x1f;
+    let imm = imm as u32;
+
+    // 0-6: opcode
+    let mut i = 0x3;
+    i |= opcode5 << 2;
+    i |= (imm & 0x1f) << 7;
+    i |= funct3 << 12;
+    i |= rs1 << 15;
+    i |= rs2 << 20;
+    i |= ((imm >> 5) & 0x7f) << 25;
+
+    sink.put4(i);
+}
+
+/// SB-type branch instructions.
+///
+///   31          24  19  14     11          6
+///   imm[12|10:5] rs2 rs1 funct3 imm[4:1|11] opcode
+///             25  20  15     12          7      0
+///
+/// The offset is a signed multiple of 2 bytes relative to the branch instruction.
+///
+/// Encoding bits: `opcode[6:2] | (funct3 << 5)`
+fn put_sb<CS: CodeSink + ?Sized>(bits: u16,
+                                 offset: i64,
+                                 rs1: RegUnit,
+                                 rs2: RegUnit,
+                                 sink: &mut CS) {
+    let bits = bits as u32;
+    let opcode5 = bits & 0x1f;
+    let funct3 = (bits >> 5) & 0x7;
+    let rs1 = rs1 as u32 & 0x1f;
+    let rs2 = rs2 as u32 & 0x1f;
+    let imm = offset as u32;
+
+    // 0-6: opcode
+    let mut i = 0x3;
+    i |= opcode5 << 2;
+    i |= ((imm >> 11) & 0x1) << 7;
+    i |= ((imm >> 1) & 0xf) << 8;
+    i |= funct3 << 12;
+    i |= rs1 << 15;
+    i |= rs2 << 20;
+    i |= ((imm >> 5) & 0x3f) << 25;
+    i |= ((imm >> 12) & 0x1) << 31;
+
+    sink.put4(i);
+}
+
+/// Invert the condition of the branch in the encoding bits `bits`.
+///
+/// The conditional branches come in pairs that differ in the low bit of funct3: `beq`/`bne`,
+/// `blt`/`bge`, and `bltu`/`bgeu`.
+fn invert_branch(bits: u16) -> u16 {
+    bits ^ (1 << 5)
+}
+
+/// Trap if `rs` is zero:
+///
+///   0: bne rs, x0, 8
+///   4: <illegal>
+fn put_trapz<CS: CodeSink + ?Sized>(rs: RegUnit, sink: &mut CS) {
+    put_sb(BNE, 8, rs, 0, sink);
+    sink.put4(ILLEGAL_INSTRUCTION);
+}
+
+/// Trap if a signed division of `rs1` by `rs2` overflows, using `tmp` as a temporary:
+///
+///   0:  addi tmp, x0, -1
+///   4:  bne rs2, tmp, 16
+///   8:  slli tmp, tmp, shamt
+///   12: bne rs1, tmp, 8
+///   16: <illegal>
+///
+/// Shifting -1 left by `shamt`, one less than the number of bits in the operands, produces the
+/// smallest integer. The 32-bit smallest integer is sign-extended on RV64 like all `i32` values.
+fn put_trap_sdiv_overflow<CS: CodeSink + ?Sized>(rs1: RegUnit,
+                                                 rs2: RegUnit,
+                                                 shamt: i64,
+                                                 tmp: RegUnit,
+                                                 sink: &mut CS) {
+    put_i(ADDI, 0, -1, tmp, sink);
+    put_sb(BNE, 16, rs2, tmp, sink);
+    put_rshamt(SLLI, tmp, shamt, tmp, sink);
+    put_sb(BNE, 8, rs1, tmp, sink);
+    sink.put4(ILLEGAL_INSTRUCTION);
+}
+
+/// R4-type fused multiply-add instructions.
+///
+///   31  26  24  19  14 11 6
+///   rs3 fmt rs2 rs1 rm rd opcode
+///    27  25  20  15 12  7      0
+///
+/// Encoding bits: `opcode[6:2] | (rm << 5) | (fmt << 8)`.
+fn put_r4<CS: CodeSink + ?Sized>(bits: u16,
+                                 rs1: RegUnit,
+                                 rs2: RegUnit,
+                                 rs3: RegUnit,
+                                 rd: RegUnit,
+                                 sink: &mut CS) {
+    let bits = bits as u32;
+    let opcode5 = bits & 0x1f;
+    let rm = (bits >> 5) & 0x7;
+    let fmt = (bits >> 8) & 0x3;
+    let rs1 = rs1 as u32 & 0x1f;
+    let rs2 = rs2 as u32 & 0x1f;
+    let rs3 = rs3 as u32 & 0x1f;
+    let rd = rd as u32 & 0x1f;
+
+    // 0-6: opcode
+    let mut i = 0x3;
+    i |= opcode5 << 2;
+    i |= rd << 7;
+    i |= rm << 12;
+    i |= rs1 << 15;
+    i |= rs2 << 20;
+    i |= fmt << 25;
+    i |= rs3 << 27;
+
+    sink.put4(i);
+}
+
+/// Unary OP-FP instructions with a fixed `rs2` field.
+///
+/// These are R-type instructions where the `rs2` field selects the operation instead of a
+/// register.
+///
+/// Encoding bits: `rs2 | (rm << 5) | (funct7 << 8)`.
+fn put_fcvt<CS: CodeSink + ?Sized>(bits: u16, rs1: RegUnit, rd: RegUnit, sink: &mut CS) {
+    let rs2 = (bits & 0x1f) as RegUnit;
+    put_r(OP_FP | (bits & !0x1f), rs1, rs2, rd, sink);
+}
+
+/// U-type instructions.
+///
+///   31  11 6
+///   imm rd opcode
+///    12  7      0
+///
+/// The immediate provides the high 20 bits of a 32-bit value.
+fn put_u<CS: CodeSink + ?Sized>(opcode5: u32, imm: i64, rd: RegUnit, sink: &mut CS) {
+    let rd = rd as u32 & 0x1f;
+
+    // 0-6: opcode
+    let mut i = 0x3;
+    i |= opcode5 << 2;
+    i |= rd << 7;
+    i |= imm as u32 & 0xfffff000;
+
+    sink.put4(i);
+}
+
+/// UJ-type instructions, `jal rd, offset`.
+///
+///   31        30        20      19         11 6
+///   imm[20] imm[10:1] imm[11] imm[19:12] rd opcode
+///        31        21      20         12  7      0
+///
+/// The offset is a signed multiple of 2 bytes relative to the `jal` instruction.
+fn put_uj<CS: CodeSink + ?Sized>(offset: i64, rd: RegUnit, sink: &mut CS) {
+    let rd = rd as u32 & 0x1f;
+    let imm = offset as u32;
+
+    // 0-6: opcode
+    let mut i = 0x3;
+    i |= JAL << 2;
+    i |= rd << 7;
+    i |= imm & 0xff000;
+    i |= ((imm >> 11) & 0x1) << 20;
+    i |= ((imm >> 1) & 0x3ff) << 21;
+    i |= ((imm >> 20) & 0x1) << 31;
+
+    sink.put4(i);
+}
+
+/// Integer extension with a pair of immediate shifts.
+///
+/// Emit `slli rd, rs1, shamt` followed by the right shift given by the encoding bits, `srli rd,
+/// rd, shamt` or `srai rd, rd, shamt`. The left shift uses the same opcode as the right shift so
+/// the 32-bit `*w` variants are used together.
+fn put_iext<CS: CodeSink + ?Sized>(bits: u16,
+                                   rs1: RegUnit,
+                                   shamt: i64,
+                                   rd: RegUnit,
+                                   sink: &mut CS) {
+    // `slli` is funct3 = 0b001 with a zero funct7.
+    let slli = (bits & 0x1f) | (0b001 << 5);
+    put_rshamt(slli, rs1, shamt, rd, sink);
+    put_rshamt(bits, rd, shamt, rd, sink);
+}
+
+/// Set the `aq` and `rl` bits in the encoding bits of an atomic memory operation.
+///
+/// Encoding bits: `opcode[6:2] | (funct3 << 5) | (funct7 << 8)` where the `aq` and `rl` bits are
+/// the two low bits of funct7.
+fn amo_ordering(bits: u16, aq: bool, rl: bool) -> u16 {
+    bits | ((aq as u16) << 9) | ((rl as u16) << 8)
+}
+
+/// Compare-and-swap loop using load-reserved and store-conditional instructions.
+///
+///   0:  lr rd, (p)
+///   4:  bne rd, e, 20
+///   8:  sc rd, r, (p)
+///   12: bnez rd, 0
+///   16: addi rd, e, 0
+///
+/// The `sc` instruction writes 0 to `rd` when it succeeds, so the previous value in memory is
+/// restored from `e` after the loop. The `rd` register is written before the operands are read
+/// for the last time, so it must be distinct from all of them.
+///
+/// Encoding bits: The `lr` instruction, `AMO | (funct3 << 5) | (funct5 << 10)`.
+fn put_cas<CS: CodeSink + ?Sized>(bits: u16,
+                                  ordering: AtomicOrdering,
+                                  p: RegUnit,
+                                  e: RegUnit,
+                                  r: RegUnit,
+                                  rd: RegUnit,
+                                  sink: &mut CS) {
+    // A sequentially consistent `lr` must also be ordered after earlier release operations.
+    let lr = amo_ordering(bits,
+                          ordering.is_acquire(),
+                          ordering == AtomicOrdering::SeqCst);
+    let sc = amo_ordering((bits & 0xff) | (SC_FUNCT5 << 10), false, ordering.is_release());
+    put_r(lr, p, 0, rd, sink);
+    put_sb(BNE, 16, rd, e, sink);
+    put_r(sc, p, r, rd, sink);
+    put_sb(BNE, -12, rd, 0, sink);
+    put_i(ADDI, e, 0, rd, sink);
+}
+
+/// Get the predecessor and successor sets of a `fence` instruction with the given `ordering`.
+fn fence_sets(ordering: AtomicOrdering) -> i64 {
+    match ordering {
+        AtomicOrdering::Acquire => FENCE_R_RW,
+        AtomicOrdering::Release => FENCE_RW_W,
+        _ => FENCE_RW_RW,
+    }
+}
+
+/// CR-type compressed instructions, `c.add rd, rs2` and `c.mv rd, rs2`.
+///
+///   15     11 6   1
+///   funct4 rd rs2 op
+///       12  7   2  0
+///
+/// Encoding bits: The 16-bit instruction with the register fields cleared.
+fn put_cr<CS: CodeSink + ?Sized>(bits: u16, rd: RegUnit, rs2: RegUnit, sink: &mut CS) {
+    let rd = rd & 0x1f;
+    let rs2 = rs2 & 0x1f;
+    sink.put2(bits | rd << 7 | rs2 << 2);
+}
+
+/// CA-type compressed instructions on the `x8`-`x15` registers, `c.sub rd', rs2'`.
+///
+///   15     9   6      4    1
+///   funct6 rd' funct2 rs2' op
+///       10   7      5    2  0
+///
+/// Encoding bits: The 16-bit instruction with the register fields cleared.
+fn put_ca<CS: CodeSink + ?Sized>(bits: u16, rd: RegUnit, rs2: RegUnit, sink: &mut CS) {
+    let rd = rd & 0x7;
+    let rs2 = rs2 & 0x7;
+    sink.put2(bits | rd << 7 | rs2 << 2);
+}
+
+/// CI-type compressed instructions with a 6-bit immediate, `c.addi rd, imm`.
+///
+///   15     12     11 6        1
+///   funct3 imm[5] rd imm[4:0] op
+///       13     12  7        2  0
+///
+/// Encoding bits: The 16-bit instruction with the register and immediate fields cleared.
+fn put_ci<CS: CodeSink + ?Sized>(bits: u16, rd: RegUnit, imm: i64, sink: &mut CS) {
+    let rd = rd & 0x1f;
+    let imm = imm as u16;
+    sink.put2(bits | ((imm >> 5) & 0x1) << 12 | rd << 7 | (imm & 0x1f) << 2);
+}
+
+/// The `c.addi16sp` instruction, a CI-type instruction with a scrambled immediate.
+///
+///   15     12     11 6              1
+///   funct3 imm[9] 2  imm[4|6|8:7|5] op
+///       13     12  7              2  0
+///
+/// The immediate is a nonzero multiple of 16.
+fn put_ci16sp<CS: CodeSink + ?Sized>(bits: u16, imm: i64, sink: &mut CS) {
+    let imm = imm as u16;
+    let mut i = bits | STACK_POINTER << 7;
+    i |= ((imm >> 9) & 0x1) << 12;
+    i |= ((imm >> 4) & 0x1) << 6;
+    i |= ((imm >> 6) & 0x1) << 5;
+    i |= ((imm >> 7) & 0x3) << 3;
+    i |= ((imm >> 5) & 0x1) << 2;
+    sink.put2(i);
+}
+
+/// CB-type compressed immediate instructions on the `x8`-`x15` registers, `c.andi rd', imm`,
+/// `c.srli rd', shamt`, and `c.srai rd', shamt`.
+///
+///   15     12     11     9   6        1
+///   funct3 imm[5] funct2 rd' imm[4:0] op
+///       13     12     10   7        2  0
+///
+/// Encoding bits: The 16-bit instruction with the register and immediate fields cleared.
+fn put_cbi<CS: CodeSink + ?Sized>(bits: u16, rd: RegUnit, imm: i64, sink: &mut CS) {
+    let rd = rd & 0x7;
+    let imm = imm as u16;
+    sink.put2(bits | ((imm >> 5) & 0x1) << 12 | rd << 7 | (imm & 0x1f) << 2);
+}
+
+/// CL-type and CS-type compressed loads and stores, `c.lw rd', offset(rs1')` and
+/// `c.sw rs2', offset(rs1')`.
+///
+///   15     12          9    6          4       1
+///   funct3 offset[5:3] rs1' offset[2|6] rd'/rs2' op
+///       13          10    7          5       2  0
+///
+/// The doubleword loads and stores have `offset[7:6]` in bits 6:5 instead. They are identified by
+/// the low bit of funct3.
+///
+/// Encoding bits: The 16-bit instruction with the register and offset fields cleared.
+fn put_cls<CS: CodeSink + ?Sized>(bits: u16,
+                                  rs1: RegUnit,
+                                  r2: RegUnit,
+                                  offset: i64,
+                                  sink: &mut CS) {
+    let rs1 = rs1 & 0x7;
+    let r2 = r2 & 0x7;
+    let offset = offset as u16;
+    let mut i = bits | rs1 << 7 | r2 << 2;
+    i |= ((offset >> 3) & 0x7) << 10;
+    if bits & (1 << 13) != 0 {
+        i |= ((offset >> 6) & 0x3) << 5;
+    } else {
+        i |= ((offset >> 2) & 0x1) << 6;
+        i |= ((offset >> 6) & 0x1) << 5;
+    }
+    sink.put2(i);
+}
+
+/// CJ-type compressed jump, `c.j offset`.
+///
+///   15     12                         1
+///   funct3 offset[11|4|9:8|10|6|7|3:1|5] op
+///       13                          2  0
+///
+/// The offset is a signed multiple of 2 bytes relative to the jump instruction.
+fn put_cj<CS: CodeSink + ?Sized>(bits: u16, offset: i64, sink: &mut CS) {
+    let imm = offset as u16;
+    let mut i = bits;
+    i |= ((imm >> 11) & 0x1) << 12;
+    i |= ((imm >> 4) & 0x1) << 11;
+    i |= ((imm >> 8) & 0x3) << 9;
+    i |= ((imm >> 10) & 0x1) << 8;
+    i |= ((imm >> 6) & 0x1) << 7;
+    i |= ((imm >> 7) & 0x1) << 6;
+    i |= ((imm >> 1) & 0x7) << 3;
+    i |= ((imm >> 5) & 0x1) << 2;
+    sink.put2(i);
+}
+
+/// CB-type compressed branches, `c.beqz rs1', offset` and `c.bnez rs1', offset`.
+///
+///   15     12           9    6                1
+///   funct3 offset[8|4:3] rs1' offset[7:6|2:1|5] op
+///       13           10    7                2  0
+///
+/// The offset is a signed multiple of 2 bytes relative to the branch instruction.
+fn put_cb<CS: CodeSink + ?Sized>(bits: u16, offset: i64, rs1: RegUnit, sink: &mut CS) {
+    let rs1 = rs1 & 0x7;
+    let imm = offset as u16;
+    let mut i = bits | rs1 << 7;
+    i |= ((imm >> 8) & 0x1) << 12;
+    i |= ((imm >> 3) & 0x3) << 10;
+    i |= ((imm >> 6) & 0x3) << 5;
+    i |= ((imm >> 1) & 0x3) << 3;
+    i |= ((imm >> 5) & 0x1) << 2;
+    sink.put2(i);
+}
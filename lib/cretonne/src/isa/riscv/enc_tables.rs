@@ -1,14 +1,23 @@
 //! Encoding tables for RISC-V.
 
-use ir::{Opcode, InstructionData};
+use ir::{Opcode, InstructionData, DataFlowGraph};
+use ir::condcodes::{IntCC, FloatCC};
+use ir::atomics::{AtomicRmwOp, AtomicOrdering};
 use ir::instructions::InstructionFormat;
 use ir::types;
 use predicates;
+use isa::Legalize;
 use isa::enc_tables::{Level1Entry, Level2Entry};
+use isa::constraints::{RecipeConstraints, OperandConstraint, ConstraintKind};
+use isa::encoding::{RecipeSizing, BranchRange};
+use super::registers::*;
 
 // Include the generated encoding tables:
 // - `LEVEL1_RV32`
 // - `LEVEL1_RV64`
 // - `LEVEL2`
 // - `ENCLIST`
+// - `RECIPE_NAMES`
+// - `RECIPE_CONSTRAINTS`
+// - `RECIPE_SIZING`
 include!(concat!(env!("OUT_DIR"), "/encoding-riscv.rs"));
@@ -1,13 +1,19 @@
 //! RISC-V Instruction Set Architecture.
 
 pub mod settings;
+mod abi;
+mod binemit;
 mod enc_tables;
+mod registers;
 
 use super::super::settings as shared_settings;
-use isa::enc_tables::{self as shared_enc_tables, lookup_enclist, general_encoding};
+use binemit::CodeSink;
+use isa::enc_tables::{self as shared_enc_tables, lookup_enclist, general_encoding,
+                      visit_encodings};
 use isa::Builder as IsaBuilder;
-use isa::{TargetIsa, Encoding};
-use ir::{InstructionData, DataFlowGraph};
+use isa::{TargetIsa, RegInfo, RegClass, Encoding, Legalize, RecipeConstraints, RecipeSizing};
+use ir::{Function, Inst, InstructionData, DataFlowGraph, Signature, ArgumentType, Type};
+use regalloc::AllocatableSet;
 
 #[allow(dead_code)]
 struct Isa {
@@ -48,28 +54,83 @@ impl TargetIsa for Isa {
         &self.shared_flags
     }
 
-    fn encode(&self, _: &DataFlowGraph, inst: &InstructionData) -> Option<Encoding> {
-        lookup_enclist(inst.first_type(),
+    fn register_info(&self) -> RegInfo {
+        registers::INFO.clone()
+    }
+
+    fn regclass_for_abi_type(&self, ty: Type) -> RegClass {
+        abi::regclass_for_abi_type(ty, &self.isa_flags)
+    }
+
+    fn allocatable_registers(&self, func: &Function) -> AllocatableSet {
+        abi::allocatable_registers(func)
+    }
+
+    fn stack_alignment(&self) -> u32 {
+        abi::STACK_ALIGNMENT
+    }
+
+    fn saved_registers(&self, func: &Function) -> Vec<ArgumentType> {
+        abi::saved_registers(func, self.pointer_type(), &self.isa_flags)
+    }
+
+    fn encode(&self, dfg: &DataFlowGraph, inst: &InstructionData) -> Result<Encoding, Legalize> {
+        lookup_enclist(inst.ctrl_typevar(dfg),
                        inst.opcode(),
                        self.cpumode,
                        &enc_tables::LEVEL2[..])
             .and_then(|enclist_offset| {
                 general_encoding(enclist_offset,
                                  &enc_tables::ENCLISTS[..],
-                                 |instp| enc_tables::check_instp(inst, instp),
+                                 |instp| enc_tables::check_instp(inst, instp, dfg),
                                  |isap| self.isa_flags.numbered_predicate(isap as usize))
             })
     }
 
+    fn legal_encodings(&self, dfg: &DataFlowGraph, inst: &InstructionData) -> Vec<Encoding> {
+        let mut encodings = Vec::new();
+        if let Ok(enclist_offset) = lookup_enclist(inst.ctrl_typevar(dfg),
+                                                   inst.opcode(),
+                                                   self.cpumode,
+                                                   &enc_tables::LEVEL2[..]) {
+            visit_encodings(enclist_offset,
+                            &enc_tables::ENCLISTS[..],
+                            |instp| enc_tables::check_instp(inst, instp, dfg),
+                            |isap| self.isa_flags.numbered_predicate(isap as usize),
+                            |enc| encodings.push(enc));
+        }
+        encodings
+    }
+
     fn recipe_names(&self) -> &'static [&'static str] {
         &enc_tables::RECIPE_NAMES[..]
     }
+
+    fn recipe_constraints(&self) -> &'static [RecipeConstraints] {
+        &enc_tables::RECIPE_CONSTRAINTS
+    }
+
+    fn recipe_sizing(&self) -> &'static [RecipeSizing] {
+        &enc_tables::RECIPE_SIZING
+    }
+
+    fn legalize_signature(&self, sig: &mut Signature) {
+        abi::legalize_signature(sig, &self.shared_flags, &self.isa_flags)
+    }
+
+    fn emit_inst(&self, func: &Function, inst: Inst, sink: &mut CodeSink) {
+        binemit::emit_inst(func, inst, sink)
+    }
+
+    fn reloc_names(&self) -> &'static [&'static str] {
+        &binemit::RELOC_NAMES
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use settings::{self, Configurable};
-    use isa;
+    use isa::{self, Legalize, OperandConstraint, ConstraintKind};
     use ir::{DataFlowGraph, InstructionData, Opcode};
     use ir::{types, immediates};
 
@@ -109,7 +170,7 @@ mod tests {
         };
 
         // Immediate is out of range for ADDI.
-        assert_eq!(isa.encode(&dfg, &inst64_large), None);
+        assert_eq!(isa.encode(&dfg, &inst64_large), Err(Legalize::Expand));
 
         // Create an iadd_imm.i32 which is encodable in RV64.
         let inst32 = InstructionData::BinaryImm {
@@ -135,6 +196,7 @@ mod tests {
         let ebb = dfg.make_ebb();
         let arg64 = dfg.append_ebb_arg(ebb, types::I64);
         let arg32 = dfg.append_ebb_arg(ebb, types::I32);
+        let arg8 = dfg.append_ebb_arg(ebb, types::I8);
 
         // Try to encode iadd_imm.i64 vx1, -10.
         let inst64 = InstructionData::BinaryImm {
@@ -144,8 +206,8 @@ mod tests {
             imm: immediates::Imm64::new(-10),
         };
 
-        // ADDI is I/0b00100
-        assert_eq!(isa.encode(&dfg, &inst64), None);
+        // There are no i64 encodings in RV32.
+        assert_eq!(isa.encode(&dfg, &inst64), Err(Legalize::Narrow));
 
         // Try to encode iadd_imm.i64 vx1, -10000.
         let inst64_large = InstructionData::BinaryImm {
@@ -155,8 +217,7 @@ mod tests {
             imm: immediates::Imm64::new(-10000),
         };
 
-        // Immediate is out of range for ADDI.
-        assert_eq!(isa.encode(&dfg, &inst64_large), None);
+        assert_eq!(isa.encode(&dfg, &inst64_large), Err(Legalize::Narrow));
 
         // Create an iadd_imm.i32 which is encodable in RV32.
         let inst32 = InstructionData::BinaryImm {
@@ -176,7 +237,26 @@ mod tests {
             args: [arg32, arg32],
         };
 
-        assert_eq!(isa.encode(&dfg, &mul32), None);
+        assert_eq!(isa.encode(&dfg, &mul32), Err(Legalize::Expand));
+
+        // There are no i8 arithmetic instructions, but some conversions use i8.
+        let add8 = InstructionData::Binary {
+            opcode: Opcode::Iadd,
+            ty: types::I8,
+            args: [arg8, arg8],
+        };
+
+        assert_eq!(isa.encode(&dfg, &add8), Err(Legalize::Widen));
+
+        // Create an ireduce.i8 which is encodable as a register copy.
+        let reduce8 = InstructionData::Unary {
+            opcode: Opcode::Ireduce,
+            ty: types::I8,
+            arg: arg32,
+        };
+
+        // ADDI is I/0b00100
+        assert_eq!(encstr(&*isa, isa.encode(&dfg, &reduce8).unwrap()), "Icopy#04");
     }
 
     #[test]
@@ -204,4 +284,50 @@ mod tests {
         };
         assert_eq!(encstr(&*isa, isa.encode(&dfg, &mul32).unwrap()), "R#10c");
     }
+
+    #[test]
+    fn recipe_constraints() {
+        let shared_flags = settings::Flags::new(&settings::builder());
+        let isa = isa::lookup("riscv").unwrap().finish(shared_flags);
+        let regs = isa.register_info();
+        let gpr = &regs.classes[0];
+        assert_eq!(gpr.name, "GPR");
+
+        let constraints = |name| {
+            let recipe = isa.recipe_names().iter().position(|&n| n == name).unwrap();
+            isa.recipe_constraints()[recipe]
+        };
+        let reg = OperandConstraint {
+            kind: ConstraintKind::Reg,
+            regclass: gpr,
+        };
+
+        let r = constraints("R");
+        assert_eq!(r.ins, &[reg, reg]);
+        assert_eq!(r.outs, &[reg]);
+
+        let i = constraints("I");
+        assert_eq!(i.ins, &[reg]);
+        assert_eq!(i.outs, &[reg]);
+
+        let rshamt = constraints("Rshamt");
+        assert_eq!(rshamt.ins, &[reg]);
+        assert_eq!(rshamt.outs, &[reg]);
+
+        // The compressed CA-type instructions only use `%x8`-`%x15` and overwrite their first
+        // operand.
+        let gprc = &regs.classes[1];
+        assert_eq!(gprc.name, "GPRC");
+        let regc = OperandConstraint {
+            kind: ConstraintKind::Reg,
+            regclass: gprc,
+        };
+        let tied = OperandConstraint {
+            kind: ConstraintKind::Tied(0),
+            regclass: gprc,
+        };
+        let ca = constraints("CA");
+        assert_eq!(ca.ins, &[regc, regc]);
+        assert_eq!(ca.outs, &[tied]);
+    }
 }
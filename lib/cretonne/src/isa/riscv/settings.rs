@@ -24,6 +24,7 @@ mod tests {
                     supports_a = false\n\
                     supports_f = false\n\
                     supports_d = false\n\
+                    supports_c = false\n\
                     enable_m = true\n");
         // Predicates are not part of the Display output.
         assert_eq!(f.full_float(), false);
@@ -0,0 +1,153 @@
+//! Materializing integer constants.
+//!
+//! RISC ISAs can only encode small immediate operands in their instructions. Larger constants are
+//! built from smaller pieces by a sequence of instructions:
+//!
+//! - An `iconst` that can't be encoded directly is split into a high part and a sign-extended
+//!   12-bit low part which is added with an `iadd_imm` instruction. The high part of a 32-bit
+//!   value is a multiple of 4096 which can be materialized by a single `lui`-style instruction.
+//! - A 64-bit constant with a high part that doesn't fit in 32 bits is built from a smaller
+//!   constant which is shifted into place with an `ishl_imm` instruction before the low part is
+//!   added.
+//! - An `iconst.i64` on a 32-bit ISA is split into two 32-bit constants that are concatenated.
+//! - Binary instructions with an immediate operand that is out of range, like `iadd_imm`, are
+//!   rewritten to use a separate `iconst` instruction for the immediate.
+//!
+//! The expansions produce new `iconst` instructions that are legalized again, so a constant of any
+//! size is eventually reduced to pieces that the ISA can encode.
+
+use ir::{Cursor, DataFlowGraph, InstBuilder, InstructionData, Opcode, Type, Value};
+use ir::types::{I32, I64};
+
+/// Expand the constant in the instruction pointed to by `pos`.
+///
+/// This handles `iconst` instructions and binary instructions with an immediate operand. Return
+/// `true` if the instruction was rewritten.
+pub fn expand_constant(pos: &mut Cursor, dfg: &mut DataFlowGraph) -> bool {
+    let inst = pos.current_inst().expect("need instruction");
+    let ty = dfg[inst].ctrl_typevar(dfg);
+    match dfg[inst] {
+        InstructionData::UnaryImm { opcode: Opcode::Iconst, imm, .. } => {
+            expand_iconst(pos, dfg, ty, imm.into())
+        }
+        InstructionData::BinaryImm { opcode, arg, imm, .. } => {
+            expand_binary_imm(pos, dfg, opcode, ty, arg, imm.into())
+        }
+        InstructionData::BinaryImmRev { opcode: Opcode::IsubImm, imm, arg, .. } => {
+            // `isub_imm` computes `imm - arg`.
+            let c = dfg.ins(pos).iconst(ty, imm);
+            dfg.replace(inst).isub(c, arg);
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Split the `iconst.i64` instruction pointed to by `pos` into two 32-bit constants.
+///
+/// Return `true` if the instruction was rewritten.
+pub fn narrow_iconst(pos: &mut Cursor, dfg: &mut DataFlowGraph) -> bool {
+    let inst = pos.current_inst().expect("need instruction");
+    let imm: i64 = match dfg[inst] {
+        InstructionData::UnaryImm { opcode: Opcode::Iconst, imm, .. } => imm.into(),
+        _ => return false,
+    };
+    if dfg[inst].ctrl_typevar(dfg) != I64 {
+        return false;
+    }
+    let lo = dfg.ins(pos).iconst(I32, imm as i32 as i64);
+    let hi = dfg.ins(pos).iconst(I32, (imm >> 32) as i32 as i64);
+    dfg.replace(inst).iconcat_lohi(lo, hi);
+    true
+}
+
+/// Expand `iconst.ty imm` into a sequence of smaller constants.
+fn expand_iconst(pos: &mut Cursor, dfg: &mut DataFlowGraph, ty: Type, imm: i64) -> bool {
+    let inst = pos.current_inst().expect("need instruction");
+
+    // The immediate of an `iconst.i32` can be given as either a signed or an unsigned 32-bit
+    // value. Canonicalize it to the sign-extended form first.
+    let ext = match ty {
+        I32 => imm as i32 as i64,
+        I64 => imm,
+        _ => return false,
+    };
+    if ext != imm {
+        dfg.replace(inst).iconst(ty, ext);
+        return true;
+    }
+
+    let lo = low12(imm);
+    let hi = imm.wrapping_sub(lo);
+
+    // The high part of a 32-bit value fits in a `lui` instruction. 32-bit additions wrap around,
+    // so `i32` values close to the upper limit work too.
+    if ty == I32 || hi == hi as i32 as i64 {
+        if hi == 0 || lo == 0 {
+            // This constant can't be split any further.
+            return false;
+        }
+        let x = dfg.ins(pos).iconst(ty, hi as i32 as i64);
+        dfg.replace(inst).iadd_imm(x, lo);
+        return true;
+    }
+
+    // Shift a smaller constant into place. Using all the trailing zeros in the shift amount keeps
+    // the constant to be shifted as small as possible.
+    let shift = hi.trailing_zeros() as i64;
+    let x = dfg.ins(pos).iconst(ty, hi >> shift);
+    if lo == 0 {
+        dfg.replace(inst).ishl_imm(x, shift);
+    } else {
+        let y = dfg.ins(pos).ishl_imm(x, shift);
+        dfg.replace(inst).iadd_imm(y, lo);
+    }
+    true
+}
+
+/// Expand a binary instruction with an immediate operand into an `iconst` and the corresponding
+/// binary instruction with two register operands.
+fn expand_binary_imm(pos: &mut Cursor,
+                     dfg: &mut DataFlowGraph,
+                     opcode: Opcode,
+                     ty: Type,
+                     arg: Value,
+                     imm: i64)
+                     -> bool {
+    let inst = pos.current_inst().expect("need instruction");
+    match opcode {
+        Opcode::IaddImm | Opcode::ImulImm | Opcode::BandImm | Opcode::BorImm |
+        Opcode::BxorImm => {}
+        _ => return false,
+    }
+    let c = dfg.ins(pos).iconst(ty, imm);
+    match opcode {
+        Opcode::IaddImm => dfg.replace(inst).iadd(arg, c),
+        Opcode::ImulImm => dfg.replace(inst).imul(arg, c),
+        Opcode::BandImm => dfg.replace(inst).band(arg, c),
+        Opcode::BorImm => dfg.replace(inst).bor(arg, c),
+        Opcode::BxorImm => dfg.replace(inst).bxor(arg, c),
+        _ => unreachable!(),
+    };
+    true
+}
+
+/// Get the sign-extended low 12 bits of `x`.
+fn low12(x: i64) -> i64 {
+    (x << 52) >> 52
+}
+
+#[cfg(test)]
+mod tests {
+    use super::low12;
+
+    #[test]
+    fn low_bits() {
+        assert_eq!(low12(0), 0);
+        assert_eq!(low12(2047), 2047);
+        assert_eq!(low12(2048), -2048);
+        assert_eq!(low12(-2049), 2047);
+        assert_eq!(low12(0x8000_0000), 0);
+        assert_eq!(low12(0x1234_5fff), -1);
+    }
+}
@@ -0,0 +1,86 @@
+//! Expanding floating point comparisons.
+//!
+//! Most ISAs only implement a few of the 14 `FloatCC` condition codes directly. The `fcmp`
+//! instructions with other condition codes are expanded in terms of the supported ones:
+//!
+//! - Swapping the operands turns `gt` into `lt`, `uge` into `ule`, and so on.
+//! - The inverse condition code produces the opposite result, so `ne` is `bnot` of `eq`.
+//! - `ord` is computed as `x == x && y == y`, and `one` as `x < y || y < x`.
+//! - `eq` is computed as `ord && ueq`, and `ne` as `uno || one`. This is needed on Intel where
+//!   the flags set by `ucomiss` can't express `eq` and `ne` with a single condition.
+//!
+//! The target ISA is asked which condition codes it can encode. When it doesn't support the
+//! condition codes needed, the comparison is left alone so it can be turned into a library call.
+
+use ir::{Cursor, DataFlowGraph, InstBuilder, InstructionData};
+use ir::condcodes::{CondCode, FloatCC};
+use isa::TargetIsa;
+
+/// Expand the `fcmp` instruction pointed to by `pos` using the condition codes supported by `isa`.
+///
+/// Return `true` if the instruction was rewritten.
+pub fn expand_fcmp(pos: &mut Cursor, dfg: &mut DataFlowGraph, isa: &TargetIsa) -> bool {
+    let inst = pos.current_inst().expect("need instruction");
+    let (cond, x, y) = match dfg[inst] {
+        InstructionData::FloatCompare { cond, args, .. } => (cond, args[0], args[1]),
+        _ => return false,
+    };
+
+    // Can `isa` encode this comparison with the condition code `cc`?
+    let legal = |dfg: &DataFlowGraph, cc: FloatCC| {
+        let mut data = dfg[inst].clone();
+        if let InstructionData::FloatCompare { ref mut cond, .. } = data {
+            *cond = cc;
+        }
+        isa.encode(dfg, &data).is_ok()
+    };
+
+    let rev = cond.reverse();
+    let inv = cond.inverse();
+    if legal(dfg, rev) {
+        dfg.replace(inst).fcmp(rev, y, x);
+    } else if legal(dfg, inv) {
+        let a = dfg.ins(pos).fcmp(inv, x, y);
+        dfg.replace(inst).bnot(a);
+    } else if legal(dfg, inv.reverse()) {
+        let a = dfg.ins(pos).fcmp(inv.reverse(), y, x);
+        dfg.replace(inst).bnot(a);
+    } else {
+        match cond {
+            FloatCC::Ordered if legal(dfg, FloatCC::Equal) => {
+                // A NaN is the only value that isn't equal to itself.
+                let a = dfg.ins(pos).fcmp(FloatCC::Equal, x, x);
+                let b = dfg.ins(pos).fcmp(FloatCC::Equal, y, y);
+                dfg.replace(inst).band(a, b);
+            }
+            FloatCC::OrderedNotEqual if legal(dfg, FloatCC::LessThan) => {
+                let a = dfg.ins(pos).fcmp(FloatCC::LessThan, x, y);
+                let b = dfg.ins(pos).fcmp(FloatCC::LessThan, y, x);
+                dfg.replace(inst).bor(a, b);
+            }
+            // The inverse comparisons are expanded further when the legalizer revisits them.
+            FloatCC::Unordered if legal(dfg, FloatCC::Equal) => {
+                let a = dfg.ins(pos).fcmp(FloatCC::Ordered, x, y);
+                dfg.replace(inst).bnot(a);
+            }
+            FloatCC::UnorderedOrEqual if legal(dfg, FloatCC::LessThan) => {
+                let a = dfg.ins(pos).fcmp(FloatCC::OrderedNotEqual, x, y);
+                dfg.replace(inst).bnot(a);
+            }
+            FloatCC::Equal if legal(dfg, FloatCC::Ordered) &&
+                              legal(dfg, FloatCC::UnorderedOrEqual) => {
+                let a = dfg.ins(pos).fcmp(FloatCC::Ordered, x, y);
+                let b = dfg.ins(pos).fcmp(FloatCC::UnorderedOrEqual, x, y);
+                dfg.replace(inst).band(a, b);
+            }
+            FloatCC::NotEqual if legal(dfg, FloatCC::Unordered) &&
+                                 legal(dfg, FloatCC::OrderedNotEqual) => {
+                let a = dfg.ins(pos).fcmp(FloatCC::Unordered, x, y);
+                let b = dfg.ins(pos).fcmp(FloatCC::OrderedNotEqual, x, y);
+                dfg.replace(inst).bor(a, b);
+            }
+            _ => return false,
+        }
+    }
+    true
+}
@@ -0,0 +1,56 @@
+//! Expanding `global_addr`.
+//!
+//! The `global_addr` instruction is expanded according to the kind of global variable it
+//! references:
+//!
+//! - `vmctx` globals are at a constant offset from the VM context pointer which is passed as a
+//!   special function argument.
+//! - `deref` globals load a pointer from their base global variable and add an offset.
+//! - `globalsym` globals become `globalsym_addr` instructions which are left for the code generator
+//!   to materialize.
+
+use entity_map::EntityMap;
+use ir::{Cursor, DataFlowGraph, InstBuilder, InstructionData, MemFlags, Value, GlobalVar,
+         GlobalVarData};
+
+/// Expand the `global_addr` instruction pointed to by `pos`.
+///
+/// The `vmctx` argument is the function's VM context pointer, if it has one. Return `true` if the
+/// instruction was rewritten.
+pub fn expand_global_addr(pos: &mut Cursor,
+                          dfg: &mut DataFlowGraph,
+                          global_vars: &EntityMap<GlobalVar, GlobalVarData>,
+                          vmctx: Option<Value>)
+                          -> bool {
+    let inst = pos.current_inst().expect("need instruction");
+    let gv = match dfg[inst] {
+        InstructionData::UnaryGlobalVar { global_var, .. } => global_var,
+        _ => return false,
+    };
+    let addr_ty = dfg[inst].first_type();
+
+    match global_vars[gv] {
+        GlobalVarData::VmCtx { offset } => {
+            // Without a VM context pointer, there is nothing to compute the address from.
+            let vmctx = match vmctx {
+                Some(vmctx) => vmctx,
+                None => return false,
+            };
+            let offset: i64 = offset.into();
+            dfg.replace(inst).iadd_imm(vmctx, offset);
+        }
+        GlobalVarData::Deref { base, offset } => {
+            let mut flags = MemFlags::new();
+            flags.set_notrap();
+            flags.set_aligned();
+            let base_addr = dfg.ins(pos).global_addr(addr_ty, base);
+            let base_ptr = dfg.ins(pos).load(addr_ty, flags, base_addr, 0);
+            let offset: i64 = offset.into();
+            dfg.replace(inst).iadd_imm(base_ptr, offset);
+        }
+        GlobalVarData::Sym { .. } => {
+            dfg.replace(inst).globalsym_addr(addr_ty, gv);
+        }
+    }
+    true
+}
@@ -0,0 +1,141 @@
+//! Expanding `heap_addr`.
+//!
+//! The `heap_addr` instruction is expanded into an explicit bounds check followed by an addition
+//! of the heap base address. The bounds check depends on the heap style:
+//!
+//! - Dynamic heaps load the current bound from a global variable, compare the offset against it
+//!   and trap with a `trapnz` instruction if the access is out of bounds.
+//! - Static heaps have a bound that is known at compile time. When the offset is a 32-bit value
+//!   and the guard pages following the heap cover the whole range of possible offsets, no check is
+//!   needed at all. Out-of-bounds accesses will then trap when they hit the guard pages.
+
+use entity_map::EntityMap;
+use ir::{Cursor, DataFlowGraph, InstBuilder, InstructionData, MemFlags, Type, Value, GlobalVar,
+         Heap, HeapData, HeapBase, HeapStyle};
+use ir::condcodes::IntCC;
+use ir::types::I32;
+use ir::immediates::Uimm32;
+
+/// Expand the `heap_addr` instruction pointed to by `pos`.
+///
+/// Return `true` if the instruction was rewritten. The address computation replaces the
+/// `heap_addr` instruction, and the bounds check is inserted before it.
+pub fn expand_heap_addr(pos: &mut Cursor,
+                        dfg: &mut DataFlowGraph,
+                        heaps: &EntityMap<Heap, HeapData>)
+                        -> bool {
+    let inst = pos.current_inst().expect("need instruction");
+    let (heap, offset, size) = match dfg[inst] {
+        InstructionData::HeapAddr { heap, arg, imm, .. } => (heap, arg, imm),
+        _ => return false,
+    };
+    let addr_ty = dfg[inst].first_type();
+    let data = &heaps[heap];
+
+    match data.style {
+        HeapStyle::Dynamic { bound_gv } => {
+            dynamic_bounds_check(pos, dfg, addr_ty, offset, size, bound_gv)
+        }
+        HeapStyle::Static { bound } => {
+            static_bounds_check(pos,
+                                dfg,
+                                offset,
+                                size,
+                                bound.into(),
+                                data.guard_size.into())
+        }
+    }
+
+    // The offset is an unsigned value, so it is zero-extended to the address type.
+    let offset = if dfg.value_type(offset) == addr_ty {
+        offset
+    } else {
+        dfg.ins(pos).uextend(addr_ty, offset)
+    };
+    let base = match data.base {
+        HeapBase::GlobalVar(gv) => load_global(pos, dfg, addr_ty, addr_ty, gv),
+    };
+    dfg.replace(inst).iadd(base, offset);
+    true
+}
+
+/// Load a value of type `ty` from the global variable `gv`.
+///
+/// Global variables are assumed to be accessible and properly aligned.
+fn load_global(pos: &mut Cursor,
+               dfg: &mut DataFlowGraph,
+               addr_ty: Type,
+               ty: Type,
+               gv: GlobalVar)
+               -> Value {
+    let mut flags = MemFlags::new();
+    flags.set_notrap();
+    flags.set_aligned();
+    let addr = dfg.ins(pos).global_addr(addr_ty, gv);
+    dfg.ins(pos).load(ty, flags, addr, 0)
+}
+
+/// Insert a bounds check of the range `offset .. offset + size` against the bound stored in
+/// `bound_gv`.
+fn dynamic_bounds_check(pos: &mut Cursor,
+                        dfg: &mut DataFlowGraph,
+                        addr_ty: Type,
+                        offset: Value,
+                        size: Uimm32,
+                        bound_gv: GlobalVar) {
+    let offset_ty = dfg.value_type(offset);
+    let bound = load_global(pos, dfg, addr_ty, offset_ty, bound_gv);
+    let oob = if size == 1 {
+        dfg.ins(pos).icmp(IntCC::UnsignedGreaterThanOrEqual, offset, bound)
+    } else {
+        // The last byte accessed is at `offset + size - 1`, which must be less than `bound`.
+        // Computing `bound - size` wraps around when the heap is smaller than the access, so that
+        // case is checked separately.
+        let size_val = dfg.ins(pos).iconst(offset_ty, size as i64);
+        let too_small = dfg.ins(pos).icmp(IntCC::UnsignedLessThan, bound, size_val);
+        let limit = dfg.ins(pos).isub(bound, size_val);
+        let past_limit = dfg.ins(pos).icmp(IntCC::UnsignedGreaterThan, offset, limit);
+        dfg.ins(pos).bor(too_small, past_limit)
+    };
+    dfg.ins(pos).trapnz(oob);
+}
+
+/// Insert a bounds check of the range `offset .. offset + size` against the static `bound`, unless
+/// the guard pages make it unnecessary.
+fn static_bounds_check(pos: &mut Cursor,
+                       dfg: &mut DataFlowGraph,
+                       offset: Value,
+                       size: Uimm32,
+                       bound: i64,
+                       guard_size: i64) {
+    let offset_ty = dfg.value_type(offset);
+    let size = size as u64;
+    let bound = bound as u64;
+
+    // An access that is larger than the whole heap always traps.
+    if size > bound {
+        let one = dfg.ins(pos).iconst(I32, 1);
+        dfg.ins(pos).trapnz(one);
+        return;
+    }
+
+    // The largest offset that keeps the access in bounds.
+    let limit = bound - size;
+
+    // No check is needed when the largest 32-bit offset still keeps the whole access inside the
+    // heap and its guard pages. An overflowing sum covers any offset.
+    if offset_ty == I32 &&
+       limit.checked_add(guard_size as u64).map_or(true, |end| end >= 0xffff_ffff) {
+        return;
+    }
+
+    // The immediate of an `iconst.i32` is sign-extended.
+    let limit = if offset_ty == I32 {
+        limit as i32 as i64
+    } else {
+        limit as i64
+    };
+    let limit = dfg.ins(pos).iconst(offset_ty, limit);
+    let oob = dfg.ins(pos).icmp(IntCC::UnsignedGreaterThan, offset, limit);
+    dfg.ins(pos).trapnz(oob);
+}
@@ -0,0 +1,407 @@
+//! Expanding instructions as runtime library calls.
+//!
+//! Instructions that can't be legalized any other way are replaced with calls to runtime library
+//! functions as a last resort. This is mostly used for floating point arithmetic on targets
+//! without a floating point unit, or when the `enable_float` setting is off.
+//!
+//! The library functions use the soft-float names and calling conventions from libgcc and
+//! compiler-rt, so `fadd.f32` becomes a call to `__addsf3`. Rounding functions like `ceil` and
+//! `sqrt` call the corresponding C math library functions instead. The functions are declared in
+//! the function preamble as needed.
+//!
+//! Atomic memory operations call the libatomic functions that GCC and Clang use for targets
+//! without native atomic instructions, like `__atomic_fetch_add_4`. There are no library functions
+//! for the atomic minimum and maximum operations, so they can't be expanded.
+//!
+//! Integer division and remainder call the libgcc functions like `__udivsi3` on targets without a
+//! divide instruction, and for `i64` operands on 32-bit targets. These functions don't trap, so the
+//! trapping conditions are checked before the call.
+
+use ir::{DataFlowGraph, Cursor, Inst, InstBuilder, InstructionData, Opcode, Type, Value,
+         VariableArgs, FuncRef, FunctionName, Signature, ArgumentType, ExtFuncData};
+use ir::types::{I8, I16, I32, I64, F32, F64, VOID};
+use ir::condcodes::{FloatCC, IntCC};
+use ir::atomics::AtomicRmwOp;
+
+/// Try to replace the instruction pointed to by `pos` with a call to a runtime library function.
+///
+/// Return `true` if the instruction was replaced, or `false` if there is no library function that
+/// implements it.
+pub fn expand_as_libcall(pos: &mut Cursor, dfg: &mut DataFlowGraph) -> bool {
+    let inst = pos.current_inst().expect("need instruction");
+    if dfg[inst].second_result().is_some() {
+        return false;
+    }
+
+    match dfg[inst] {
+        InstructionData::FloatCompare { cond, args, .. } => {
+            return expand_fcmp(pos, dfg, inst, cond, args[0], args[1]);
+        }
+        InstructionData::AtomicRmw { .. } |
+        InstructionData::AtomicCas { .. } |
+        InstructionData::AtomicLoad { .. } |
+        InstructionData::AtomicStore { .. } |
+        InstructionData::Fence { .. } => return expand_atomic(pos, dfg, inst),
+        _ => {}
+    }
+
+    let mut args = VariableArgs::new();
+    let mut data = dfg[inst].clone();
+    data.map_arguments(|arg| {
+        args.push(arg);
+        arg
+    });
+    let arg_type = match args.first() {
+        Some(&arg) => dfg.value_type(arg),
+        None => return false,
+    };
+    let result_type = dfg[inst].first_type();
+    let name = match libcall_name(data.opcode(), arg_type, result_type) {
+        Some(name) => name,
+        None => return false,
+    };
+
+    match data.opcode() {
+        Opcode::Udiv | Opcode::Sdiv | Opcode::Urem | Opcode::Srem => {
+            insert_div_checks(pos, dfg, data.opcode(), args[0], args[1])
+        }
+        _ => {}
+    }
+
+    let arg_types: Vec<Type> = args.iter().map(|&arg| dfg.value_type(arg)).collect();
+    let fref = import_function(dfg, name, &arg_types, result_type);
+    dfg.replace(inst).call(fref, args);
+    true
+}
+
+/// Insert the checks that make the integer division or remainder `opcode` of `x` by `y` trap.
+///
+/// All of them trap on a zero divisor. `sdiv` also traps when the quotient overflows, which only
+/// happens when dividing the smallest integer by -1.
+fn insert_div_checks(pos: &mut Cursor,
+                     dfg: &mut DataFlowGraph,
+                     opcode: Opcode,
+                     x: Value,
+                     y: Value) {
+    dfg.ins(pos).trapz(y);
+    if opcode == Opcode::Sdiv {
+        let ty = dfg.value_type(x);
+        let min = dfg.ins(pos).iconst(ty, i64::min_value() >> (64 - ty.bits()));
+        let neg_one = dfg.ins(pos).iconst(ty, -1);
+        let x_min = dfg.ins(pos).icmp(IntCC::Equal, x, min);
+        let y_neg_one = dfg.ins(pos).icmp(IntCC::Equal, y, neg_one);
+        let overflow = dfg.ins(pos).band(x_min, y_neg_one);
+        dfg.ins(pos).trapnz(overflow);
+    }
+}
+
+/// Expand a floating point comparison into a call to a soft-float comparison function followed by
+/// an integer comparison of the returned value against 0.
+///
+/// The `one` and `ueq` conditions don't have a single comparison function, so they are composed
+/// of two calls: `one` is computed as `ord && ne`, and `ueq` as `uno || eq`.
+fn expand_fcmp(pos: &mut Cursor,
+               dfg: &mut DataFlowGraph,
+               inst: Inst,
+               cond: FloatCC,
+               x: Value,
+               y: Value)
+               -> bool {
+    let ty = dfg.value_type(x);
+    let parts = match cond {
+        FloatCC::OrderedNotEqual => (FloatCC::Ordered, FloatCC::NotEqual),
+        FloatCC::UnorderedOrEqual => (FloatCC::Unordered, FloatCC::Equal),
+        _ => {
+            let (name, intcc) = match fcmp_libcall(cond, ty) {
+                Some(lc) => lc,
+                None => return false,
+            };
+            let res = insert_fcmp_call(pos, dfg, name, x, y);
+            let zero = dfg.ins(pos).iconst(I32, 0);
+            dfg.replace(inst).icmp(intcc, res, zero);
+            return true;
+        }
+    };
+
+    let (lc1, lc2) = match (fcmp_libcall(parts.0, ty), fcmp_libcall(parts.1, ty)) {
+        (Some(lc1), Some(lc2)) => (lc1, lc2),
+        _ => return false,
+    };
+    let res1 = insert_fcmp_call(pos, dfg, lc1.0, x, y);
+    let res2 = insert_fcmp_call(pos, dfg, lc2.0, x, y);
+    let zero = dfg.ins(pos).iconst(I32, 0);
+    let a = dfg.ins(pos).icmp(lc1.1, res1, zero);
+    let b = dfg.ins(pos).icmp(lc2.1, res2, zero);
+    if cond == FloatCC::OrderedNotEqual {
+        dfg.replace(inst).band(a, b);
+    } else {
+        dfg.replace(inst).bor(a, b);
+    }
+    true
+}
+
+/// Insert a call to the soft-float comparison function `name` with the arguments `x` and `y`.
+///
+/// Return the `i32` result of the call.
+fn insert_fcmp_call(pos: &mut Cursor,
+                    dfg: &mut DataFlowGraph,
+                    name: &str,
+                    x: Value,
+                    y: Value)
+                    -> Value {
+    let ty = dfg.value_type(x);
+    let fref = import_function(dfg, name, &[ty, ty], I32);
+    let mut args = VariableArgs::new();
+    args.push(x);
+    args.push(y);
+    let call = dfg.ins(pos).call(fref, args);
+    dfg.first_result(call)
+}
+
+/// Expand an atomic memory operation into a call to a libatomic function.
+///
+/// The `__atomic_*` functions take the memory ordering as their last argument, numbered like the
+/// C11 `memory_order` enumeration. Compare-and-swap and fences use the legacy `__sync_*`
+/// functions instead. They are always sequentially consistent, which is at least as strong as any
+/// ordering.
+fn expand_atomic(pos: &mut Cursor, dfg: &mut DataFlowGraph, inst: Inst) -> bool {
+    // The value operands in the order of the library function arguments.
+    let (op, operands, ordering) = match dfg[inst] {
+        InstructionData::AtomicRmw { op, ordering, args, .. } => {
+            (Some(op), vec![args[0], args[1]], Some(ordering))
+        }
+        InstructionData::AtomicCas { args, .. } => (None, args.to_vec(), None),
+        InstructionData::AtomicLoad { ordering, arg, .. } => (None, vec![arg], Some(ordering)),
+        InstructionData::AtomicStore { ordering, args, .. } => {
+            (None, vec![args[1], args[0]], Some(ordering))
+        }
+        InstructionData::Fence { .. } => (None, Vec::new(), None),
+        _ => return false,
+    };
+    let ty = dfg[inst].ctrl_typevar(dfg);
+    let name = match atomic_libcall_name(dfg[inst].opcode(), op, ty) {
+        Some(name) => name,
+        None => return false,
+    };
+
+    let mut args = VariableArgs::new();
+    for arg in operands {
+        args.push(arg);
+    }
+    if let Some(ordering) = ordering {
+        args.push(dfg.ins(pos).iconst(I32, ordering.c11_number()));
+    }
+    let arg_types: Vec<Type> = args.iter().map(|&arg| dfg.value_type(arg)).collect();
+    let result_type = dfg[inst].first_type();
+    let fref = import_function(dfg, &name, &arg_types, result_type);
+    dfg.replace(inst).call(fref, args);
+    true
+}
+
+/// Get the name of the libatomic function implementing the atomic instruction `opcode` on values
+/// of type `ty`. The operation `op` is only used by `atomic_rmw`.
+///
+/// The function names have a suffix with the size of the accessed memory in bytes.
+fn atomic_libcall_name(opcode: Opcode, op: Option<AtomicRmwOp>, ty: Type) -> Option<String> {
+    if opcode == Opcode::Fence {
+        return Some("__sync_synchronize".to_string());
+    }
+    let prefix = match (opcode, op) {
+        (Opcode::AtomicRmw, Some(AtomicRmwOp::Add)) => "__atomic_fetch_add",
+        (Opcode::AtomicRmw, Some(AtomicRmwOp::And)) => "__atomic_fetch_and",
+        (Opcode::AtomicRmw, Some(AtomicRmwOp::Or)) => "__atomic_fetch_or",
+        (Opcode::AtomicRmw, Some(AtomicRmwOp::Xor)) => "__atomic_fetch_xor",
+        (Opcode::AtomicRmw, Some(AtomicRmwOp::Xchg)) => "__atomic_exchange",
+        (Opcode::AtomicCas, _) => "__sync_val_compare_and_swap",
+        (Opcode::AtomicLoad, _) => "__atomic_load",
+        (Opcode::AtomicStore, _) => "__atomic_store",
+        _ => return None,
+    };
+    match ty {
+        I8 | I16 | I32 | I64 => Some(format!("{}_{}", prefix, ty.bytes())),
+        _ => None,
+    }
+}
+
+/// Get the name of the library function implementing `opcode`.
+///
+/// The function takes arguments of type `arg_type` and returns `result_type`.
+fn libcall_name(opcode: Opcode, arg_type: Type, result_type: Type) -> Option<&'static str> {
+    let name = match (opcode, arg_type, result_type) {
+        (Opcode::Fadd, F32, F32) => "__addsf3",
+        (Opcode::Fadd, F64, F64) => "__adddf3",
+        (Opcode::Fsub, F32, F32) => "__subsf3",
+        (Opcode::Fsub, F64, F64) => "__subdf3",
+        (Opcode::Fmul, F32, F32) => "__mulsf3",
+        (Opcode::Fmul, F64, F64) => "__muldf3",
+        (Opcode::Fdiv, F32, F32) => "__divsf3",
+        (Opcode::Fdiv, F64, F64) => "__divdf3",
+        (Opcode::Fneg, F32, F32) => "__negsf2",
+        (Opcode::Fneg, F64, F64) => "__negdf2",
+
+        (Opcode::Sqrt, F32, F32) => "sqrtf",
+        (Opcode::Sqrt, F64, F64) => "sqrt",
+        (Opcode::Fma, F32, F32) => "fmaf",
+        (Opcode::Fma, F64, F64) => "fma",
+        (Opcode::Fabs, F32, F32) => "fabsf",
+        (Opcode::Fabs, F64, F64) => "fabs",
+        (Opcode::Fcopysign, F32, F32) => "copysignf",
+        (Opcode::Fcopysign, F64, F64) => "copysign",
+        (Opcode::Fminnum, F32, F32) => "fminf",
+        (Opcode::Fminnum, F64, F64) => "fmin",
+        (Opcode::Fmaxnum, F32, F32) => "fmaxf",
+        (Opcode::Fmaxnum, F64, F64) => "fmax",
+        (Opcode::Ceil, F32, F32) => "ceilf",
+        (Opcode::Ceil, F64, F64) => "ceil",
+        (Opcode::Floor, F32, F32) => "floorf",
+        (Opcode::Floor, F64, F64) => "floor",
+        (Opcode::Trunc, F32, F32) => "truncf",
+        (Opcode::Trunc, F64, F64) => "trunc",
+        (Opcode::Nearest, F32, F32) => "nearbyintf",
+        (Opcode::Nearest, F64, F64) => "nearbyint",
+
+        (Opcode::Fpromote, F32, F64) => "__extendsfdf2",
+        (Opcode::Fdemote, F64, F32) => "__truncdfsf2",
+
+        (Opcode::FcvtToSint, F32, I32) => "__fixsfsi",
+        (Opcode::FcvtToSint, F32, I64) => "__fixsfdi",
+        (Opcode::FcvtToSint, F64, I32) => "__fixdfsi",
+        (Opcode::FcvtToSint, F64, I64) => "__fixdfdi",
+        (Opcode::FcvtToUint, F32, I32) => "__fixunssfsi",
+        (Opcode::FcvtToUint, F32, I64) => "__fixunssfdi",
+        (Opcode::FcvtToUint, F64, I32) => "__fixunsdfsi",
+        (Opcode::FcvtToUint, F64, I64) => "__fixunsdfdi",
+        (Opcode::FcvtFromSint, I32, F32) => "__floatsisf",
+        (Opcode::FcvtFromSint, I64, F32) => "__floatdisf",
+        (Opcode::FcvtFromSint, I32, F64) => "__floatsidf",
+        (Opcode::FcvtFromSint, I64, F64) => "__floatdidf",
+        (Opcode::FcvtFromUint, I32, F32) => "__floatunsisf",
+        (Opcode::FcvtFromUint, I64, F32) => "__floatundisf",
+        (Opcode::FcvtFromUint, I32, F64) => "__floatunsidf",
+        (Opcode::FcvtFromUint, I64, F64) => "__floatundidf",
+
+        (Opcode::Udiv, I32, I32) => "__udivsi3",
+        (Opcode::Udiv, I64, I64) => "__udivdi3",
+        (Opcode::Sdiv, I32, I32) => "__divsi3",
+        (Opcode::Sdiv, I64, I64) => "__divdi3",
+        (Opcode::Urem, I32, I32) => "__umodsi3",
+        (Opcode::Urem, I64, I64) => "__umoddi3",
+        (Opcode::Srem, I32, I32) => "__modsi3",
+        (Opcode::Srem, I64, I64) => "__moddi3",
+
+        _ => return None,
+    };
+    Some(name)
+}
+
+/// Get the soft-float comparison function implementing `fcmp cond` on `ty` operands, along with
+/// the integer condition code that compares the function's return value to 0.
+///
+/// The soft-float comparison functions return an `i32` value whose sign encodes the result of the
+/// comparison. Each function also returns a value with a well-defined sign when the operands are
+/// unordered, so most floating point condition codes can be implemented by a single call.
+/// Return `None` for the `one` and `ueq` conditions which need two calls.
+fn fcmp_libcall(cond: FloatCC, ty: Type) -> Option<(&'static str, IntCC)> {
+    use ir::condcodes::FloatCC::*;
+    let (sf, df, intcc) = match cond {
+        Ordered => ("__unordsf2", "__unorddf2", IntCC::Equal),
+        Unordered => ("__unordsf2", "__unorddf2", IntCC::NotEqual),
+        Equal => ("__eqsf2", "__eqdf2", IntCC::Equal),
+        NotEqual => ("__nesf2", "__nedf2", IntCC::NotEqual),
+        LessThan => ("__ltsf2", "__ltdf2", IntCC::SignedLessThan),
+        LessThanOrEqual => ("__lesf2", "__ledf2", IntCC::SignedLessThanOrEqual),
+        GreaterThan => ("__gtsf2", "__gtdf2", IntCC::SignedGreaterThan),
+        GreaterThanOrEqual => ("__gesf2", "__gedf2", IntCC::SignedGreaterThanOrEqual),
+        UnorderedOrLessThan => ("__gesf2", "__gedf2", IntCC::SignedLessThan),
+        UnorderedOrLessThanOrEqual => ("__gtsf2", "__gtdf2", IntCC::SignedLessThanOrEqual),
+        UnorderedOrGreaterThan => ("__lesf2", "__ledf2", IntCC::SignedGreaterThan),
+        UnorderedOrGreaterThanOrEqual => ("__ltsf2", "__ltdf2", IntCC::SignedGreaterThanOrEqual),
+        OrderedNotEqual | UnorderedOrEqual => return None,
+    };
+    match ty {
+        F32 => Some((sf, intcc)),
+        F64 => Some((df, intcc)),
+        _ => None,
+    }
+}
+
+/// Get a reference to the library function `name` with the given argument and return types.
+///
+/// Reuse an existing declaration in the function preamble if possible, otherwise declare the
+/// function along with its signature. A `VOID` result type means that the function doesn't return
+/// a value.
+fn import_function(dfg: &mut DataFlowGraph,
+                   name: &str,
+                   arg_types: &[Type],
+                   result_type: Type)
+                   -> FuncRef {
+    let mut sig = Signature::new();
+    sig.argument_types.extend(arg_types.iter().map(|&ty| ArgumentType::new(ty)));
+    if result_type != VOID {
+        sig.return_types.push(ArgumentType::new(result_type));
+    }
+    let name = FunctionName::new(name);
+
+    for fref in dfg.ext_funcs.keys() {
+        let ext = &dfg.ext_funcs[fref];
+        if ext.name == name && dfg.signatures[ext.signature] == sig {
+            return fref;
+        }
+    }
+
+    let sigref = match dfg.signatures.keys().find(|&s| dfg.signatures[s] == sig) {
+        Some(sigref) => sigref,
+        None => dfg.signatures.push(sig),
+    };
+    dfg.ext_funcs.push(ExtFuncData {
+        name: name,
+        signature: sigref,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{libcall_name, fcmp_libcall, atomic_libcall_name};
+    use ir::Opcode;
+    use ir::types::{I8, I32, I64, F32, F64, VOID};
+    use ir::condcodes::{FloatCC, IntCC};
+    use ir::atomics::AtomicRmwOp;
+
+    #[test]
+    fn names() {
+        assert_eq!(libcall_name(Opcode::Fadd, F32, F32), Some("__addsf3"));
+        assert_eq!(libcall_name(Opcode::Fdiv, F64, F64), Some("__divdf3"));
+        assert_eq!(libcall_name(Opcode::Sqrt, F32, F32), Some("sqrtf"));
+        assert_eq!(libcall_name(Opcode::FcvtToUint, F64, I64), Some("__fixunsdfdi"));
+        assert_eq!(libcall_name(Opcode::FcvtFromSint, I32, F32), Some("__floatsisf"));
+        assert_eq!(libcall_name(Opcode::Udiv, I32, I32), Some("__udivsi3"));
+        assert_eq!(libcall_name(Opcode::Srem, I64, I64), Some("__moddi3"));
+        assert_eq!(libcall_name(Opcode::Iadd, I32, I32), None);
+        assert_eq!(libcall_name(Opcode::Fadd, I32, I32), None);
+    }
+
+    #[test]
+    fn fcmp() {
+        assert_eq!(fcmp_libcall(FloatCC::Equal, F32),
+                   Some(("__eqsf2", IntCC::Equal)));
+        assert_eq!(fcmp_libcall(FloatCC::UnorderedOrLessThan, F64),
+                   Some(("__gedf2", IntCC::SignedLessThan)));
+        assert_eq!(fcmp_libcall(FloatCC::OrderedNotEqual, F32), None);
+        assert_eq!(fcmp_libcall(FloatCC::Equal, I32), None);
+    }
+
+    #[test]
+    fn atomics() {
+        let name = |opcode, op, ty| atomic_libcall_name(opcode, op, ty);
+        assert_eq!(name(Opcode::AtomicRmw, Some(AtomicRmwOp::Add), I32),
+                   Some("__atomic_fetch_add_4".to_string()));
+        assert_eq!(name(Opcode::AtomicRmw, Some(AtomicRmwOp::Xchg), I8),
+                   Some("__atomic_exchange_1".to_string()));
+        assert_eq!(name(Opcode::AtomicRmw, Some(AtomicRmwOp::Umax), I32), None);
+        assert_eq!(name(Opcode::AtomicCas, None, I64),
+                   Some("__sync_val_compare_and_swap_8".to_string()));
+        assert_eq!(name(Opcode::AtomicStore, None, F32), None);
+        assert_eq!(name(Opcode::Fence, None, VOID),
+                   Some("__sync_synchronize".to_string()));
+    }
+}
@@ -0,0 +1,168 @@
+//! Legalize instructions.
+//!
+//! A legal instruction is one that can be mapped directly to a machine code instruction for the
+//! target ISA. The `legalize_function()` function takes as input any function and transforms it
+//! into an equivalent function using only legal instructions.
+//!
+//! The characteristics of legal instructions depend on the target ISA, so any given instruction
+//! can be legal for one ISA and illegal for another.
+//!
+//! Besides transforming instructions, the legalizer also fills out the `function.encodings` map
+//! which provides a legal encoding recipe for every instruction.
+//!
+//! The legalizer does not deal with register allocation constraints. These constraints are derived
+//! from the encoding recipes, and solved later by the register allocator.
+
+use ir::{Function, Cursor, DataFlowGraph, InstructionData, Opcode, InstBuilder, ArgumentPurpose};
+use ir::types;
+use ir::condcodes::IntCC;
+use isa::{TargetIsa, Legalize};
+
+mod constants;
+mod fcmp;
+mod globalvar;
+mod heap;
+mod libcall;
+mod split;
+mod stack;
+
+/// Legalize `func` for `isa`.
+///
+/// - Transform any instructions that don't have a legal representation in `isa`.
+/// - Fill out `func.encodings`.
+/// - Assign argument locations to all signatures in `func`.
+///
+pub fn legalize_function(func: &mut Function, isa: &TargetIsa) {
+    func.encodings.resize(func.dfg.num_insts());
+    let vmctx = func.special_arg(ArgumentPurpose::VMContext);
+    let mut pos = Cursor::new(&mut func.layout);
+    while let Some(_ebb) = pos.next_ebb() {
+        // Keep track of the cursor position before the instruction being processed, so we can
+        // double back when replacing instructions.
+        let mut prev_pos = pos.position();
+
+        while let Some(inst) = pos.next_inst() {
+            match isa.encode(&func.dfg, &func.dfg[inst]) {
+                Ok(encoding) => *func.encodings.ensure(inst) = encoding,
+                Err(action) => {
+                    // We should transform the instruction into legal equivalents.
+                    // Possible strategies are:
+                    // 1. Legalize::Expand: Expand instruction into sequence of legal instructions.
+                    //    Possibly iteratively.
+                    // 2. Legalize::Narrow: Split the controlling type variable into high and low
+                    //    parts. This applies both to SIMD vector types which can be halved (see
+                    //    the `split` module) and to integer types such as `i64` used on a 32-bit
+                    //    ISA.
+                    // 3. Legalize::Widen: Promote the type of the controlling type variable to a
+                    //    larger type. This typically means expressing `i8` and `i16` arithmetic in
+                    //    terms of `i32` operations on RISC targets. (It may or may not be
+                    //    beneficial to promote small vector types versus splitting them.)
+                    // 4. As a last resort, convert to library calls. For example, floating point
+                    //    operations on an ISA with no IEEE 754 support (see the `libcall` module).
+                    let changed = match func.dfg[inst].opcode() {
+                        // These expansions depend on the entities declared in the function
+                        // preamble, so they aren't covered by the generated patterns.
+                        Opcode::HeapAddr => {
+                            heap::expand_heap_addr(&mut pos, &mut func.dfg, &func.heaps)
+                        }
+                        Opcode::GlobalAddr => {
+                            globalvar::expand_global_addr(&mut pos,
+                                                          &mut func.dfg,
+                                                          &func.global_vars,
+                                                          vmctx)
+                        }
+                        Opcode::StackLoad | Opcode::StackStore => {
+                            stack::expand_stack_access(&mut pos,
+                                                       &mut func.dfg,
+                                                       isa.pointer_type())
+                        }
+                        // Floating point comparisons are expanded in terms of the condition
+                        // codes supported by `isa`, falling back to library calls.
+                        Opcode::Fcmp => {
+                            fcmp::expand_fcmp(&mut pos, &mut func.dfg, isa) ||
+                            legalize_action(action, &mut pos, &mut func.dfg)
+                        }
+                        _ => legalize_action(action, &mut pos, &mut func.dfg),
+                    };
+                    // If the current instruction was replaced, we need to double back and revisit
+                    // the expanded sequence. This is both to assign encodings and possibly to
+                    // expand further.
+                    // There's a risk of infinite looping here if the legalization patterns are
+                    // unsound. Should we attempt to detect that?
+                    if changed {
+                        pos.set_position(prev_pos);
+                        continue;
+                    }
+                }
+            }
+
+            // Remember this position in case we need to double back.
+            prev_pos = pos.position();
+        }
+    }
+
+    // Instructions that couldn't be legalized still get a (default) entry in the encodings map.
+    func.encodings.resize(func.dfg.num_insts());
+
+    // Secondary results of legalized instructions are turned into aliases of the values computed
+    // by the expansion. Rewrite all instruction arguments to refer to the original values. This
+    // is done as a separate pass since values may be used before their definition in layout
+    // order.
+    for ebb in func.layout.ebbs() {
+        for inst in func.layout.ebb_insts(ebb) {
+            func.dfg.resolve_aliases_in_arguments(inst);
+        }
+    }
+
+    // Most of the `vsplit` and `vconcat` instructions inserted when splitting vectors are unused
+    // once all the vector operations have been split.
+    split::remove_dead_splits(func);
+
+    // The signatures are legalized last so they include the library calls inserted above.
+    legalize_signatures(func, isa);
+}
+
+/// Legalize all the function signatures in `func`.
+///
+/// This changes all signatures to be ABI-compliant with full `ArgumentLoc` annotations. It doesn't
+/// change the entry block arguments, calls, or return instructions, so this can leave the function
+/// in a state with type discrepancies when arguments are split or extended.
+fn legalize_signatures(func: &mut Function, isa: &TargetIsa) {
+    isa.legalize_signature(func.own_signature_mut());
+    for sig in func.dfg.signatures.keys() {
+        isa.legalize_signature(&mut func.dfg.signatures[sig]);
+    }
+}
+
+/// Apply the legalization `action` to the instruction pointed to by `pos`, using a library call
+/// as a last resort.
+///
+/// Constants that are too large to encode are split by the `constants` module when none of the
+/// generated patterns apply.
+///
+/// Return `true` if the instruction was rewritten.
+fn legalize_action(action: Legalize, pos: &mut Cursor, dfg: &mut DataFlowGraph) -> bool {
+    let inst = pos.current_inst().expect("need instruction");
+    let changed = match action {
+        Legalize::Expand => expand(pos, dfg) || constants::expand_constant(pos, dfg),
+        Legalize::Narrow => {
+            if dfg[inst].ctrl_typevar(dfg).is_scalar() {
+                narrow(pos, dfg) || constants::narrow_iconst(pos, dfg)
+            } else {
+                split::simd_split(pos, dfg)
+            }
+        }
+        Legalize::Widen => widen(pos, dfg),
+        Legalize::IntelExpand => {
+            intel_expand(pos, dfg) || expand(pos, dfg) || constants::expand_constant(pos, dfg)
+        }
+    };
+    changed || libcall::expand_as_libcall(pos, dfg)
+}
+
+// Include legalization patterns that were generated by `gen_legalizer.py` from the `XForms` in
+// `meta/cretonne/legalize.py` and the ISA-specific legalization groups.
+//
+// Concretely, this defines private functions `narrow()`, `widen()`, `expand()`, and
+// `intel_expand()`.
+include!(concat!(env!("OUT_DIR"), "/legalizer.rs"));
@@ -0,0 +1,249 @@
+//! Vector splitting.
+//!
+//! SIMD vector operations that can't be encoded for the target ISA are legalized by splitting them
+//! into two operations on vectors with half as many lanes. The legalizer revisits the new
+//! instructions, so splitting is repeated until the operations become legal. When the target ISA
+//! has no SIMD support at all, vectors are split all the way down to scalar lanes.
+//!
+//! Vector values are split into halves with `vsplit` instructions, and the results of the split
+//! operations are concatenated with `vconcat`. When a value being split was itself produced by a
+//! `vconcat`, its operands are used directly so no new `vsplit` is needed.
+//!
+//! Target ISAs don't have encodings for `vsplit` and `vconcat`, so once the whole function has
+//! been legalized, `remove_dead_splits()` deletes the ones whose results are no longer used. The
+//! remaining ones pass vectors across EBB or function boundaries which isn't supported yet.
+
+use std::collections::HashMap;
+use ir::{Function, Cursor, DataFlowGraph, InstBuilder, InstructionData, Opcode, Type, Value,
+         ValueDef};
+use ir::immediates::{Uimm8, Ieee32, Ieee64};
+use ir::types;
+
+/// Split the SIMD vector instruction pointed to by `pos` into two instructions operating on half
+/// vectors.
+///
+/// Return `true` if the instruction was rewritten, or `false` if it can't be split. When the
+/// instruction is rewritten, the new instructions are inserted before it, and the cursor is left
+/// pointing at the `vconcat` or lane instruction that replaced it.
+pub fn simd_split(pos: &mut Cursor, dfg: &mut DataFlowGraph) -> bool {
+    let inst = pos.current_inst().expect("need instruction");
+    if dfg[inst].ctrl_typevar(dfg).lane_count() < 2 {
+        return false;
+    }
+
+    match dfg[inst] {
+        // These are the instructions we use for splitting, so they can't be split themselves.
+        InstructionData::UnarySplit { opcode: Opcode::Vsplit, .. } |
+        InstructionData::Binary { opcode: Opcode::Vconcat, .. } => false,
+        InstructionData::ExtractLane { opcode: Opcode::Extractlane, lane, arg, .. } => {
+            let half_lanes = half_lanes(dfg, arg);
+            let (lo, hi) = split_value(pos, dfg, arg);
+            let (half, lane) = if lane < half_lanes {
+                (lo, lane)
+            } else {
+                (hi, lane - half_lanes)
+            };
+            if half_lanes == 1 {
+                dfg.replace(inst).copy(half);
+            } else {
+                dfg.replace(inst).extractlane(half, lane);
+            }
+            true
+        }
+        InstructionData::InsertLane { opcode: Opcode::Insertlane, lane, args, .. } => {
+            let half_lanes = half_lanes(dfg, args[0]);
+            let (lo, hi) = split_value(pos, dfg, args[0]);
+            let (lo, hi) = if lane < half_lanes {
+                (insert_half(pos, dfg, lo, lane, half_lanes, args[1]), hi)
+            } else {
+                (lo, insert_half(pos, dfg, hi, lane - half_lanes, half_lanes, args[1]))
+            };
+            dfg.replace(inst).vconcat(lo, hi);
+            true
+        }
+        InstructionData::UnaryImmVector { opcode: Opcode::Vconst, ref data, .. } => {
+            let imm = data.imm.clone();
+            split_vconst(pos, dfg, &imm)
+        }
+        _ => split_lanewise(pos, dfg),
+    }
+}
+
+/// Get the number of lanes in each half of the vector `value`.
+fn half_lanes(dfg: &DataFlowGraph, value: Value) -> Uimm8 {
+    (dfg.value_type(value).lane_count() / 2) as Uimm8
+}
+
+/// Insert `y` as lane `lane` of the half vector `half` which has `half_lanes` lanes.
+fn insert_half(pos: &mut Cursor,
+               dfg: &mut DataFlowGraph,
+               half: Value,
+               lane: Uimm8,
+               half_lanes: Uimm8,
+               y: Value)
+               -> Value {
+    if half_lanes == 1 {
+        y
+    } else {
+        dfg.ins(pos).insertlane(half, lane, y)
+    }
+}
+
+/// Split a lane-wise vector operation.
+///
+/// A lane-wise operation computes each lane of its result independently from the same lane of its
+/// vector operands. Scalar operands are passed to both halves.
+fn split_lanewise(pos: &mut Cursor, dfg: &mut DataFlowGraph) -> bool {
+    let inst = pos.current_inst().expect("need instruction");
+    let lanes = dfg[inst].ctrl_typevar(dfg).lane_count();
+
+    // Instructions with multiple results and instructions that change the number of lanes can't
+    // be split lane-wise.
+    let result_type = dfg[inst].first_type();
+    if dfg[inst].second_result().is_some() || result_type.lane_count() != lanes {
+        return false;
+    }
+    let mut lanewise = true;
+    let mut data = dfg[inst].clone();
+    data.map_arguments(|arg| {
+        let arg_lanes = dfg.value_type(arg).lane_count();
+        if arg_lanes != 1 && arg_lanes != lanes {
+            lanewise = false;
+        }
+        arg
+    });
+    if !lanewise {
+        return false;
+    }
+
+    // Split the vector operands, collecting the high halves for the second instruction.
+    let mut lo_data = data.clone();
+    let mut hi_args = Vec::new();
+    lo_data.map_arguments(|arg| {
+        if dfg.value_type(arg).lane_count() == lanes {
+            let (lo, hi) = split_value(pos, dfg, arg);
+            hi_args.push(hi);
+            lo
+        } else {
+            hi_args.push(arg);
+            arg
+        }
+    });
+    let mut hi_data = data;
+    let mut hi_args = hi_args.into_iter();
+    hi_data.map_arguments(|_| hi_args.next().expect("missing argument"));
+
+    let half_type = result_type.half_vector().expect("vector type");
+    let lo = insert_half_inst(pos, dfg, lo_data, half_type);
+    let hi = insert_half_inst(pos, dfg, hi_data, half_type);
+    dfg.replace(inst).vconcat(lo, hi);
+    true
+}
+
+/// Split a `vconst` instruction by dividing its immediate vector between the two halves.
+///
+/// The immediate vector holds the lanes in order starting from lane 0, each lane in little-endian
+/// byte order. Scalar halves become `iconst`, `f32const`, or `f64const` instructions.
+fn split_vconst(pos: &mut Cursor, dfg: &mut DataFlowGraph, imm: &[u8]) -> bool {
+    let inst = pos.current_inst().expect("need instruction");
+    let ty = dfg[inst].first_type();
+    if imm.len() != ty.bytes() as usize || ty.lane_type().is_bool() {
+        return false;
+    }
+
+    let half_type = ty.half_vector().expect("vector type");
+    let (lo, hi) = imm.split_at(imm.len() / 2);
+    let lo = insert_half_const(pos, dfg, half_type, lo);
+    let hi = insert_half_const(pos, dfg, half_type, hi);
+    dfg.replace(inst).vconcat(lo, hi);
+    true
+}
+
+/// Insert a constant of type `ty` with the little-endian representation `bytes`.
+fn insert_half_const(pos: &mut Cursor, dfg: &mut DataFlowGraph, ty: Type, bytes: &[u8]) -> Value {
+    if !ty.is_scalar() {
+        return dfg.ins(pos).vconst(ty, bytes.to_vec());
+    }
+    let bits = bytes.iter().rev().fold(0u64, |bits, &b| (bits << 8) | b as u64);
+    match ty {
+        types::F32 => dfg.ins(pos).f32const(Ieee32::from_bits(bits as u32)),
+        types::F64 => dfg.ins(pos).f64const(Ieee64::from_bits(bits)),
+        _ => {
+            // Sign-extend the lane to 64 bits.
+            let shift = 64 - ty.bits();
+            dfg.ins(pos).iconst(ty, ((bits << shift) as i64) >> shift)
+        }
+    }
+}
+
+/// Insert a copy of a lane-wise instruction operating on half vectors of type `ty`.
+///
+/// Return the result value. When `ty` is a scalar, some vector instructions are replaced by their
+/// scalar counterparts.
+fn insert_half_inst(pos: &mut Cursor,
+                    dfg: &mut DataFlowGraph,
+                    mut data: InstructionData,
+                    ty: Type)
+                    -> Value {
+    if ty.is_scalar() {
+        match data {
+            InstructionData::Unary { opcode: Opcode::Splat, arg, .. } => return arg,
+            InstructionData::Ternary { opcode: Opcode::Vselect, args, .. } => {
+                return dfg.ins(pos).select(args[0], args[1], args[2]);
+            }
+            _ => {}
+        }
+    }
+    *data.first_type_mut() = ty;
+    let inst = dfg.make_inst(data);
+    pos.insert_inst(inst);
+    dfg.first_result(inst)
+}
+
+/// Split the vector `value` into its low and high halves.
+///
+/// If `value` was produced by a `vconcat` instruction, return its operands. Otherwise insert a
+/// `vsplit` instruction at `pos`.
+fn split_value(pos: &mut Cursor, dfg: &mut DataFlowGraph, value: Value) -> (Value, Value) {
+    if let ValueDef::Res(inst, 0) = dfg.value_def(value) {
+        if let InstructionData::Binary { opcode: Opcode::Vconcat, args, .. } = dfg[inst] {
+            return (args[0], args[1]);
+        }
+    }
+    dfg.ins(pos).vsplit(value)
+}
+
+/// Remove the `vsplit` and `vconcat` instructions in `func` whose results are unused.
+///
+/// The instructions are visited in a single backwards pass, so a chain of splits used only by
+/// each other is removed as long as the uses appear after the definitions in the layout.
+pub fn remove_dead_splits(func: &mut Function) {
+    let mut uses = HashMap::new();
+    for ebb in func.layout.ebbs() {
+        for inst in func.layout.ebb_insts(ebb) {
+            func.dfg[inst].clone().map_arguments(|arg| {
+                *uses.entry(arg).or_insert(0) += 1;
+                arg
+            });
+        }
+    }
+
+    let ebbs: Vec<_> = func.layout.ebbs().collect();
+    for &ebb in ebbs.iter().rev() {
+        let insts: Vec<_> = func.layout.ebb_insts(ebb).collect();
+        for &inst in insts.iter().rev() {
+            match func.dfg[inst].opcode() {
+                Opcode::Vsplit | Opcode::Vconcat => {}
+                _ => continue,
+            }
+            if func.dfg.inst_results(inst).any(|v| uses.get(&v).cloned().unwrap_or(0) > 0) {
+                continue;
+            }
+            func.dfg[inst].clone().map_arguments(|arg| {
+                *uses.get_mut(&arg).expect("counted argument") -= 1;
+                arg
+            });
+            func.layout.remove_inst(inst);
+        }
+    }
+}
@@ -0,0 +1,34 @@
+//! Expanding stack slot accesses.
+//!
+//! The `stack_load` and `stack_store` instructions are expanded into a `stack_addr` instruction
+//! computing the address of the accessed bytes followed by an ordinary `load` or `store`. The
+//! verifier guarantees that stack slot accesses are in bounds, so the expanded memory accesses
+//! can't trap.
+
+use ir::{Cursor, DataFlowGraph, InstBuilder, InstructionData, MemFlags, Opcode, Type};
+
+/// Expand the `stack_load` or `stack_store` instruction pointed to by `pos`.
+///
+/// The `stack_addr` instruction computing an address of type `addr_ty` is inserted before the
+/// memory access that replaces the original instruction.
+///
+/// Return `true` if the instruction was rewritten.
+pub fn expand_stack_access(pos: &mut Cursor, dfg: &mut DataFlowGraph, addr_ty: Type) -> bool {
+    let inst = pos.current_inst().expect("need instruction");
+    let mut flags = MemFlags::new();
+    flags.set_notrap();
+
+    match dfg[inst] {
+        InstructionData::StackLoad { opcode: Opcode::StackLoad, ty, stack_slot, offset } => {
+            let addr = dfg.ins(pos).stack_addr(addr_ty, stack_slot, offset);
+            dfg.replace(inst).load(ty, flags, addr, 0);
+            true
+        }
+        InstructionData::StackStore { opcode: Opcode::StackStore, arg, stack_slot, offset, .. } => {
+            let addr = dfg.ins(pos).stack_addr(addr_ty, stack_slot, offset);
+            dfg.replace(inst).store(flags, arg, addr, 0);
+            true
+        }
+        _ => false,
+    }
+}
@@ -2,22 +2,30 @@
 
 #![deny(missing_docs)]
 
+pub use context::{Context, CodeInfo};
 pub use verifier::verify_function;
 pub use write::write_function;
 pub use legalizer::legalize_function;
+pub use regalloc::allocate_registers;
+pub use frame::insert_prologue_epilogue;
 
 /// Version number of the cretonne crate.
 pub const VERSION: &'static str = env!("CARGO_PKG_VERSION");
 
+pub mod binemit;
 pub mod ir;
 pub mod isa;
 pub mod cfg;
 pub mod dominator_tree;
 pub mod entity_map;
+pub mod regalloc;
 pub mod settings;
 pub mod verifier;
 
+mod abi;
+mod context;
 mod write;
 mod constant_hash;
 mod predicates;
 mod legalizer;
+mod frame;
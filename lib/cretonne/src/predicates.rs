@@ -4,7 +4,7 @@
 //! `lib/cretonne/meta/cretonne/predicates.py` classes.
 //!
 //! The predicates the operate on integer fields use `Into<i64>` as a shared trait bound. This
-//! bound is implemented by all the native integer types as well as `Imm64`.
+//! bound is implemented by all the native integer types as well as `Imm64` and `Offset32`.
 //!
 //! Some of these predicates may be unused in certain ISA configurations, so we suppress the
 //! dead_code warning.
@@ -25,6 +25,42 @@ pub fn is_unsigned_int<T: Into<i64>>(x: T, wd: u8, sc: u8) -> bool {
     u == (u & m)
 }
 
+/// Check that the low `wd` bits of `x` can be encoded as an ARM64 logical immediate.
+///
+/// A logical immediate is an element of 2, 4, 8, 16, 32, or 64 bits, replicated to fill `wd` bits.
+/// The element must be a rotated run of ones, so all zeros and all ones are not representable.
+#[allow(dead_code)]
+pub fn is_logical_imm<T: Into<i64>>(x: T, wd: u8) -> bool {
+    let m = if wd < 64 { (1 << wd) - 1 } else { !0 };
+    let v = x.into() as u64 & m;
+    if v == 0 || v == m {
+        return false;
+    }
+
+    // Find the smallest element that is replicated to produce `v`.
+    let mut size = wd as u32;
+    while size > 2 {
+        let half = size / 2;
+        let hm = (1 << half) - 1;
+        if v & hm != (v >> half) & hm {
+            break;
+        }
+        size = half;
+    }
+
+    // A rotated run of ones has exactly two bit transitions when rotated by one.
+    let em = if size < 64 { (1 << size) - 1 } else { !0 };
+    let e = v & em;
+    let rot = ((e >> 1) | (e << (size - 1))) & em;
+    (e ^ rot).count_ones() == 2
+}
+
+/// Check that `x` is the same as `y`.
+#[allow(dead_code)]
+pub fn is_equal<T: Eq + Copy>(x: T, y: T) -> bool {
+    x == y
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -63,4 +99,24 @@ mod tests {
         assert!(!is_signed_int(x1, 16, 4));
         assert!(!is_signed_int(x2, 16, 4));
     }
+
+    #[test]
+    fn logical_imm() {
+        assert!(is_logical_imm(1, 32));
+        assert!(is_logical_imm(0xff00, 64));
+        assert!(is_logical_imm(0x5555_5555u32, 32));
+        assert!(is_logical_imm(0x8000_0000_0000_0001u64 as i64, 64));
+        assert!(is_logical_imm(0x00ff_00ff_00ff_00ffi64, 64));
+        assert!(is_logical_imm(-2, 32));
+        assert!(is_logical_imm(-2, 64));
+
+        assert!(!is_logical_imm(0, 32));
+        assert!(!is_logical_imm(-1, 64));
+        assert!(!is_logical_imm(0xffff_ffffu32, 32));
+        assert!(!is_logical_imm(5, 32));
+        assert!(!is_logical_imm(0x1234, 64));
+        // The high 32 bits are ignored for 32-bit operations.
+        assert!(is_logical_imm(0x1234_0000_00ffi64, 32));
+        assert!(!is_logical_imm(0x1234_0000_00ffi64, 64));
+    }
 }
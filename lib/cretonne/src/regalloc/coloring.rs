@@ -0,0 +1,342 @@
+//! Register coloring pass.
+//!
+//! The coloring pass assigns a register to every value that isn't in a stack slot. The spilling
+//! pass has already made sure that there are enough registers available at every instruction,
+//! so the registers can be assigned greedily while visiting the EBBs in dominator tree preorder.
+//! Every value has a single register for its whole live range.
+//!
+//! At the top of an EBB, the registers holding live-in values are unavailable. The EBB arguments
+//! are preferably assigned the same registers as the corresponding branch arguments in an already
+//! colored predecessor, which avoids copies when passing EBB arguments later.
+//!
+//! Within an EBB, the registers of values that are killed by an instruction are made available
+//! before the instruction results are assigned registers. The operand constraints of the
+//! instruction's encoding recipe determine the register class of a result, and fixed or tied
+//! result registers. Early clobber results are the exception: they are assigned before the killed
+//! values are freed, so they never share a register with an input operand.
+//!
+//! Values in fixed registers have short live ranges after the `fixed` pass. Any other value that
+//! is live at the same time as a fixed register value avoids that register, so it is available
+//! when the fixed register value is defined. Operands that are not in the register class required
+//! by the instruction's encoding recipe are copied into a register of the right class.
+
+use std::collections::BTreeMap;
+use cfg::ControlFlowGraph;
+use ir::{Function, Ebb, Inst, Value, ValueLoc};
+use ir::instructions::BranchInfo;
+use isa::{TargetIsa, RegClass, RegUnit, ConstraintKind};
+use regalloc::AllocatableSet;
+use regalloc::fixed::fixed_values;
+use regalloc::liveness::{Liveness, LiveSet, inst_arguments};
+use super::{location, copy_operand, operand_constraint, result_constraint, value_regclass};
+
+/// Fixed register requirements in a function.
+struct FixedRegs {
+    /// The register required for each value with a fixed register constraint.
+    regs: BTreeMap<Value, RegUnit>,
+
+    /// The registers each value must avoid because they are required by another value that is
+    /// live at the same time.
+    avoid: BTreeMap<Value, Vec<RegUnit>>,
+}
+
+impl FixedRegs {
+    /// Collect the fixed register values in the EBBs in `order`, and the registers that their
+    /// interfering values must avoid.
+    fn compute(func: &Function,
+               isa: &TargetIsa,
+               order: &[Ebb],
+               liveness: &Liveness)
+               -> FixedRegs {
+        let mut fixed = FixedRegs {
+            regs: BTreeMap::new(),
+            avoid: BTreeMap::new(),
+        };
+
+        // Entry block arguments have already been assigned their ABI registers.
+        if let Some(entry) = func.layout.entry_block() {
+            for arg in func.dfg.ebb_args(entry) {
+                if let ValueLoc::Reg(reg) = location(func, arg) {
+                    fixed.regs.insert(arg, reg);
+                }
+            }
+        }
+        for &ebb in order {
+            for inst in func.layout.ebb_insts(ebb) {
+                fixed.regs.extend(fixed_values(func, isa, inst));
+            }
+        }
+
+        for &ebb in order {
+            let live = liveness.ebb_liveness(func, ebb);
+            let mut entry = live.entry.clone();
+            entry.extend(func.dfg.ebb_args(ebb));
+            fixed.add_interference(&entry);
+            for &(inst, ref live_after) in &live.insts {
+                // Unused results still need their register momentarily.
+                let mut live = live_after.clone();
+                live.extend(func.dfg.inst_results(inst));
+                fixed.add_interference(&live);
+            }
+        }
+
+        fixed
+    }
+
+    /// Record that the values in `live` are live at the same time.
+    fn add_interference(&mut self, live: &LiveSet) {
+        let fixed: Vec<(Value, RegUnit)> = live.iter()
+            .filter_map(|value| self.regs.get(value).map(|&reg| (*value, reg)))
+            .collect();
+        for &value in live {
+            for &(fixed_value, reg) in &fixed {
+                if fixed_value != value {
+                    let avoid = self.avoid.entry(value).or_insert_with(Vec::new);
+                    if !avoid.contains(&reg) {
+                        avoid.push(reg);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Get the fixed register required by `value`, if any.
+    fn reg(&self, value: Value) -> Option<RegUnit> {
+        self.regs.get(&value).cloned()
+    }
+
+    /// Get the registers that `value` should avoid.
+    fn avoid(&self, value: Value) -> &[RegUnit] {
+        self.avoid.get(&value).map_or(&[], |avoid| &avoid[..])
+    }
+}
+
+/// Assign registers to all values in the EBBs in `order`.
+pub fn color(func: &mut Function,
+             cfg: &ControlFlowGraph,
+             isa: &TargetIsa,
+             order: &[Ebb],
+             liveness: &Liveness) {
+    let allocatable = isa.allocatable_registers(func);
+    let fixed = FixedRegs::compute(func, isa, order, liveness);
+    for &ebb in order {
+        color_ebb(func, cfg, isa, &allocatable, &fixed, liveness, ebb);
+    }
+}
+
+/// Assign registers to the arguments and instruction results in `ebb`.
+fn color_ebb(func: &mut Function,
+             cfg: &ControlFlowGraph,
+             isa: &TargetIsa,
+             allocatable: &AllocatableSet,
+             fixed: &FixedRegs,
+             liveness: &Liveness,
+             ebb: Ebb) {
+    // The live-in values were defined in a dominating EBB, so they have already been colored.
+    let mut regs = allocatable.clone();
+    for &value in liveness.live_in(ebb) {
+        if let ValueLoc::Reg(reg) = location(func, value) {
+            regs.take(value_regclass(func, isa, value).expect("register value"), reg);
+        }
+    }
+
+    let live = liveness.ebb_liveness(func, ebb);
+
+    let args: Vec<Value> = func.dfg.ebb_args(ebb).collect();
+    for (num, &arg) in args.iter().enumerate() {
+        let rc = match value_regclass(func, isa, arg) {
+            Some(rc) => rc,
+            None => continue,
+        };
+        // Entry block arguments may already have been assigned ABI registers.
+        let reg = match location(func, arg) {
+            ValueLoc::Reg(reg) => reg,
+            _ => {
+                match predecessor_hint(func, cfg, ebb, num) {
+                    Some(reg) if regs.is_avail(rc, reg) && !fixed.avoid(arg).contains(&reg) => {
+                        reg
+                    }
+                    _ => first_free(&regs, rc, arg, fixed.avoid(arg)),
+                }
+            }
+        };
+        regs.take(rc, reg);
+        *func.locations.ensure(arg) = ValueLoc::Reg(reg);
+    }
+    for &arg in &args {
+        if !live.entry.contains(&arg) {
+            free_value(func, isa, &mut regs, arg);
+        }
+    }
+
+    for (idx, &(inst, ref live_after)) in live.insts.iter().enumerate() {
+        color_inst(func,
+                   isa,
+                   &mut regs,
+                   fixed,
+                   inst,
+                   live.live_before(idx),
+                   live_after);
+    }
+}
+
+/// Get the register holding EBB argument number `num` in a predecessor branch that has already
+/// been colored.
+fn predecessor_hint(func: &Function,
+                    cfg: &ControlFlowGraph,
+                    ebb: Ebb,
+                    num: usize)
+                    -> Option<RegUnit> {
+    for &(_, branch) in cfg.get_predecessors(ebb) {
+        if let BranchInfo::SingleDest(_, args) = func.dfg[branch].analyze_branch() {
+            if let Some(&arg) = args.get(num) {
+                if let ValueLoc::Reg(reg) = location(func, arg) {
+                    return Some(reg);
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Assign registers to the results of `inst`.
+fn color_inst(func: &mut Function,
+              isa: &TargetIsa,
+              regs: &mut AllocatableSet,
+              fixed: &FixedRegs,
+              inst: Inst,
+              live_before: &LiveSet,
+              live_after: &LiveSet) {
+    let results: Vec<Value> = func.dfg.inst_results(inst).collect();
+
+    // Operands that are not in the register required by the encoding recipe are copied into
+    // place. The copies are killed by the instruction.
+    let mut copies = Vec::new();
+    for (num, arg) in inst_arguments(func, inst).into_iter().enumerate() {
+        let reg = match location(func, arg) {
+            ValueLoc::Reg(reg) => reg,
+            _ => continue,
+        };
+        let (rc, fixed_reg) = match operand_constraint(func, isa, inst, num) {
+            Some(cons) => {
+                match cons.kind {
+                    ConstraintKind::FixedReg(fixed_reg) if fixed_reg != reg => {
+                        (cons.regclass, Some(fixed_reg))
+                    }
+                    ConstraintKind::Reg if !cons.regclass.contains(reg) => (cons.regclass, None),
+                    _ => continue,
+                }
+            }
+            None => continue,
+        };
+        let copy = copy_operand(func, isa, inst, num, arg);
+        let reg = match fixed_reg {
+            Some(reg) => {
+                assert!(regs.is_avail(rc, reg),
+                        "Fixed register for {} is not available",
+                        copy);
+                reg
+            }
+            None => first_free(regs, rc, copy, &[]),
+        };
+        regs.take(rc, reg);
+        *func.locations.ensure(copy) = ValueLoc::Reg(reg);
+        copies.push(copy);
+    }
+
+    // A tied result overwrites its input register, so an input that is still needed after the
+    // instruction must be copied first. The input is also copied when the result must avoid its
+    // register. The copy is killed by the instruction.
+    for (idx, &res) in results.iter().enumerate() {
+        if let Some(&ConstraintKind::Tied(num)) =
+            result_constraint(func, isa, inst, idx).map(|cons| &cons.kind) {
+            let arg = inst_arguments(func, inst)[num as usize];
+            let avoided = match location(func, arg) {
+                ValueLoc::Reg(reg) => fixed.avoid(res).contains(&reg),
+                _ => false,
+            };
+            if live_after.contains(&arg) || avoided {
+                let copy = copy_operand(func, isa, inst, num as usize, arg);
+                let rc = value_regclass(func, isa, copy).expect("register value");
+                let reg = first_free(regs, rc, copy, fixed.avoid(res));
+                regs.take(rc, reg);
+                *func.locations.ensure(copy) = ValueLoc::Reg(reg);
+                copies.push(copy);
+            }
+        }
+    }
+
+    // Early clobber results are written while the inputs are still being read, so they must be
+    // assigned before the registers of the killed inputs become available.
+    for (idx, &res) in results.iter().enumerate() {
+        if let Some(&ConstraintKind::EarlyClobber) =
+            result_constraint(func, isa, inst, idx).map(|cons| &cons.kind) {
+            let rc = value_regclass(func, isa, res).expect("register value");
+            let reg = first_free(regs, rc, res, fixed.avoid(res));
+            regs.take(rc, reg);
+            *func.locations.ensure(res) = ValueLoc::Reg(reg);
+        }
+    }
+
+    // Free the registers of the values killed by `inst`.
+    for &value in live_before.iter().filter(|v| !live_after.contains(v)) {
+        free_value(func, isa, regs, value);
+    }
+    for copy in copies {
+        free_value(func, isa, regs, copy);
+    }
+
+    for (idx, &res) in results.iter().enumerate() {
+        if location(func, res).is_assigned() {
+            continue;
+        }
+        let rc = match value_regclass(func, isa, res) {
+            Some(rc) => rc,
+            None => continue,
+        };
+        let reg = match result_constraint(func, isa, inst, idx).map(|cons| cons.kind) {
+            Some(ConstraintKind::FixedReg(reg)) => {
+                assert!(regs.is_avail(rc, reg),
+                        "Fixed register for {} is not available",
+                        res);
+                reg
+            }
+            Some(ConstraintKind::Tied(num)) => {
+                location(func, inst_arguments(func, inst)[num as usize]).unwrap_reg()
+            }
+            _ => {
+                match fixed.reg(res) {
+                    Some(reg) => {
+                        assert!(regs.is_avail(rc, reg),
+                                "Fixed register for {} is not available",
+                                res);
+                        reg
+                    }
+                    None => first_free(regs, rc, res, fixed.avoid(res)),
+                }
+            }
+        };
+        regs.take(rc, reg);
+        *func.locations.ensure(res) = ValueLoc::Reg(reg);
+    }
+
+    // Results that are never used only need a register momentarily.
+    for &res in results.iter().filter(|v| !live_after.contains(v)) {
+        free_value(func, isa, regs, res);
+    }
+}
+
+/// Get the first available register in `rc`, preferring registers that are not in `avoid`.
+fn first_free(regs: &AllocatableSet, rc: RegClass, value: Value, avoid: &[RegUnit]) -> RegUnit {
+    regs.iter(rc)
+        .find(|reg| !avoid.contains(reg))
+        .or_else(|| regs.iter(rc).next())
+        .unwrap_or_else(|| panic!("Ran out of {} registers for {}", rc, value))
+}
+
+/// Make the register holding `value` available again.
+fn free_value(func: &Function, isa: &TargetIsa, regs: &mut AllocatableSet, value: Value) {
+    if let ValueLoc::Reg(reg) = location(func, value) {
+        regs.free(value_regclass(func, isa, value).expect("register value"), reg);
+    }
+}
@@ -0,0 +1,177 @@
+//! Isolating values in fixed registers.
+//!
+//! Some encoding recipes require an operand or a result in a specific register, like the Intel
+//! division instructions which use `%rax` and `%rdx`. The coloring pass assigns every value a
+//! single register for its whole live range, so the values in fixed registers are given short
+//! live ranges before spilling:
+//!
+//! - A fixed register operand is replaced with a `copy` inserted right before the instruction.
+//! - A used result in a fixed register is copied right after the instruction, and all the other
+//!   uses are rewritten to use the copy. This includes call results, which are returned in the
+//!   registers given by the call signature.
+//! - Entry block arguments that are passed in a register used by a fixed register constraint in
+//!   the function are copied at the top of the entry block.
+//!
+//! The coloring pass then keeps the other values that are live at the same time out of the fixed
+//! registers.
+
+use ir::{Function, Ebb, Inst, Value, ValueLoc, ArgumentLoc, Cursor, InstBuilder, InstructionData,
+         Opcode};
+use isa::{TargetIsa, RegUnit, ConstraintKind};
+use regalloc::liveness::inst_arguments;
+use super::{location, encode_def, def_inst, copy_operand, operand_constraint, result_constraint};
+
+/// Insert copies in `func` so values in fixed registers have short live ranges.
+pub fn isolate_fixed_values(func: &mut Function, isa: &TargetIsa) {
+    let insts: Vec<Inst> = func.layout
+        .ebbs()
+        .flat_map(|ebb| func.layout.ebb_insts(ebb))
+        .collect();
+
+    let mut fixed_regs = Vec::new();
+    let mut fixed_results = Vec::new();
+    for &inst in &insts {
+        for (num, arg) in inst_arguments(func, inst).into_iter().enumerate() {
+            if let Some(ConstraintKind::FixedReg(reg)) =
+                operand_constraint(func, isa, inst, num).map(|cons| cons.kind) {
+                fixed_regs.push(reg);
+                if can_copy(func, isa, arg) {
+                    copy_operand(func, isa, inst, num, arg);
+                }
+            }
+        }
+        let results: Vec<Value> = func.dfg.inst_results(inst).collect();
+        for (idx, res) in results.into_iter().enumerate() {
+            if let Some(ConstraintKind::FixedReg(reg)) =
+                result_constraint(func, isa, inst, idx).map(|cons| cons.kind) {
+                fixed_regs.push(reg);
+                fixed_results.push((inst, res));
+            }
+        }
+        // Values in registers are never live across a call, so the entry block arguments don't
+        // need to make room for the call results.
+        for (res, _) in call_results(func, inst) {
+            fixed_results.push((inst, res));
+        }
+    }
+
+    for (inst, res) in fixed_results {
+        if is_used(func, res) && can_copy(func, isa, res) {
+            let copy = insert_copy_after(func, isa, inst, res);
+            replace_uses(func, res, copy);
+        }
+    }
+
+    if let Some(entry) = func.layout.entry_block() {
+        let args: Vec<Value> = func.dfg.ebb_args(entry).collect();
+        for arg in args {
+            let in_fixed_reg = match location(func, arg) {
+                ValueLoc::Reg(reg) => fixed_regs.contains(&reg),
+                _ => false,
+            };
+            if in_fixed_reg && is_used(func, arg) && can_copy(func, isa, arg) {
+                let copy = insert_copy_at_top(func, isa, entry, arg);
+                replace_uses(func, arg, copy);
+            }
+        }
+    }
+}
+
+/// Get the fixed registers required by the operand constraints of `inst`.
+///
+/// Returns a list of `(value, reg)` pairs for the value operands and results of `inst` that must
+/// be in a specific register.
+pub fn fixed_values(func: &Function, isa: &TargetIsa, inst: Inst) -> Vec<(Value, RegUnit)> {
+    let mut fixed = Vec::new();
+    for (num, arg) in inst_arguments(func, inst).into_iter().enumerate() {
+        if let Some(ConstraintKind::FixedReg(reg)) =
+            operand_constraint(func, isa, inst, num).map(|cons| cons.kind) {
+            fixed.push((arg, reg));
+        }
+    }
+    for (idx, res) in func.dfg.inst_results(inst).enumerate() {
+        if let Some(ConstraintKind::FixedReg(reg)) =
+            result_constraint(func, isa, inst, idx).map(|cons| cons.kind) {
+            fixed.push((res, reg));
+        }
+    }
+    fixed.extend(call_results(func, inst));
+    fixed
+}
+
+/// Get the results of the call instruction `inst` along with their return registers.
+///
+/// Returns an empty list if `inst` is not a call, or if its results don't match the call
+/// signature.
+fn call_results(func: &Function, inst: Inst) -> Vec<(Value, RegUnit)> {
+    let sig = match func.dfg.call_signature(inst) {
+        Some(sig) => sig,
+        None => return Vec::new(),
+    };
+    let abi_rets = &func.dfg.signatures[sig].return_types;
+    if func.dfg.inst_results(inst).count() != abi_rets.len() {
+        return Vec::new();
+    }
+    func.dfg
+        .inst_results(inst)
+        .zip(abi_rets)
+        .map(|(res, abi)| match abi.location {
+            ArgumentLoc::Reg(reg) => (res, reg),
+            _ => panic!("{}: call result {} is not returned in a register", inst, abi),
+        })
+        .collect()
+}
+
+/// Can `isa` encode a `copy` of `value`?
+fn can_copy(func: &Function, isa: &TargetIsa, value: Value) -> bool {
+    let data = InstructionData::Unary {
+        opcode: Opcode::Copy,
+        ty: func.dfg.value_type(value),
+        arg: value,
+    };
+    isa.encode(&func.dfg, &data).is_ok()
+}
+
+/// Is `value` used as an argument by any instruction in the layout?
+fn is_used(func: &Function, value: Value) -> bool {
+    func.layout
+        .ebbs()
+        .flat_map(|ebb| func.layout.ebb_insts(ebb))
+        .any(|inst| inst_arguments(func, inst).contains(&value))
+}
+
+/// Insert a copy of `value` right after `inst`, and return the copied value.
+fn insert_copy_after(func: &mut Function, isa: &TargetIsa, inst: Inst, value: Value) -> Value {
+    let copy = {
+        let mut pos = Cursor::new(&mut func.layout);
+        pos.goto_inst(inst);
+        pos.next_inst();
+        func.dfg.ins(&mut pos).copy(value)
+    };
+    encode_def(func, isa, copy);
+    copy
+}
+
+/// Insert a copy of `value` before the first instruction in `ebb`, and return the copied value.
+fn insert_copy_at_top(func: &mut Function, isa: &TargetIsa, ebb: Ebb, value: Value) -> Value {
+    let copy = {
+        let mut pos = Cursor::new(&mut func.layout);
+        pos.goto_top(ebb);
+        pos.next_inst();
+        func.dfg.ins(&mut pos).copy(value)
+    };
+    encode_def(func, isa, copy);
+    copy
+}
+
+/// Rewrite all uses of `value` to use `copy` instead, except in the instruction defining `copy`.
+fn replace_uses(func: &mut Function, value: Value, copy: Value) {
+    let def = def_inst(func, copy);
+    let insts: Vec<Inst> = func.layout
+        .ebbs()
+        .flat_map(|ebb| func.layout.ebb_insts(ebb))
+        .collect();
+    for inst in insts.into_iter().filter(|&inst| inst != def) {
+        func.dfg[inst].map_arguments(|arg| if arg == value { copy } else { arg });
+    }
+}
@@ -0,0 +1,177 @@
+//! Liveness analysis for SSA values.
+//!
+//! The register allocator needs to know which values are live at every point in the function. We
+//! compute the set of values that are live into each EBB by iterating a backwards data flow
+//! analysis to a fixpoint. The live sets inside an EBB are then recomputed on demand by scanning
+//! the EBB backwards from the live-in sets of its branch destinations.
+//!
+//! Live sets are ordered by value number so the register allocator makes deterministic decisions.
+
+use std::collections::BTreeSet;
+use entity_map::EntityMap;
+use ir::{Function, Ebb, Inst, Value};
+use ir::instructions::BranchInfo;
+
+/// Set of live values.
+pub type LiveSet = BTreeSet<Value>;
+
+/// Live-in sets for all the EBBs in a function.
+pub struct Liveness {
+    live_in: EntityMap<Ebb, LiveSet>,
+}
+
+/// Live sets inside a single EBB.
+pub struct EbbLiveness {
+    /// Values live into the first instruction. This is the live-in set of the EBB plus any EBB
+    /// arguments that are used.
+    pub entry: LiveSet,
+
+    /// Instructions in layout order, along with the set of values that are live after each
+    /// instruction.
+    pub insts: Vec<(Inst, LiveSet)>,
+}
+
+impl Liveness {
+    /// Create a new empty liveness analysis.
+    ///
+    /// The live-in sets are computed by `compute()`.
+    pub fn new() -> Liveness {
+        Liveness { live_in: EntityMap::new() }
+    }
+
+    /// Compute the live-in sets of the EBBs in `order`, discarding any previous results.
+    ///
+    /// EBBs that are not in `order` are considered unreachable, and they don't contribute to the
+    /// live sets.
+    pub fn compute(&mut self, func: &Function, order: &[Ebb]) {
+        self.live_in.clear();
+        self.live_in.resize(func.dfg.num_ebbs());
+
+        // Visiting EBBs in reverse order means that most successors are visited before their
+        // predecessors.
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for &ebb in order.iter().rev() {
+                let live = self.ebb_liveness(func, ebb).entry;
+                let live = live.into_iter()
+                    .filter(|&v| !func.dfg.ebb_args(ebb).any(|arg| arg == v))
+                    .collect();
+                if live != self.live_in[ebb] {
+                    self.live_in[ebb] = live;
+                    changed = true;
+                }
+            }
+        }
+    }
+
+    /// Get the set of values that are live into `ebb`, not counting its own arguments.
+    pub fn live_in(&self, ebb: Ebb) -> &LiveSet {
+        &self.live_in[ebb]
+    }
+
+    /// Compute the live sets inside `ebb` from the current live-in sets of its successors.
+    pub fn ebb_liveness(&self, func: &Function, ebb: Ebb) -> EbbLiveness {
+        let mut live = LiveSet::new();
+        let mut insts = Vec::new();
+
+        for inst in func.layout.ebb_insts(ebb).collect::<Vec<_>>().into_iter().rev() {
+            insts.push((inst, live.clone()));
+
+            for res in func.dfg.inst_results(inst) {
+                live.remove(&res);
+            }
+            match func.dfg[inst].analyze_branch() {
+                BranchInfo::SingleDest(dest, _) => {
+                    live.extend(self.live_in[dest].iter().cloned());
+                }
+                BranchInfo::Table(jt) => {
+                    for (_, dest) in func.jump_tables[jt].entries() {
+                        live.extend(self.live_in[dest].iter().cloned());
+                    }
+                }
+                BranchInfo::NotABranch => {}
+            }
+            live.extend(inst_arguments(func, inst));
+        }
+
+        insts.reverse();
+        EbbLiveness {
+            entry: live,
+            insts: insts,
+        }
+    }
+}
+
+impl EbbLiveness {
+    /// Get the values that are live before instruction number `idx` in the EBB.
+    pub fn live_before(&self, idx: usize) -> &LiveSet {
+        if idx == 0 {
+            &self.entry
+        } else {
+            &self.insts[idx - 1].1
+        }
+    }
+}
+
+/// Get the value arguments to `inst`, in operand order.
+pub fn inst_arguments(func: &Function, inst: Inst) -> Vec<Value> {
+    let mut args = Vec::new();
+    let mut data = func.dfg[inst].clone();
+    data.map_arguments(|arg| {
+        args.push(arg);
+        arg
+    });
+    args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Liveness;
+    use ir::{Function, InstBuilder, Cursor, VariableArgs, types};
+
+    #[test]
+    fn loop_liveness() {
+        let mut func = Function::new();
+        let ebb0 = func.dfg.make_ebb();
+        let ebb1 = func.dfg.make_ebb();
+        let v0 = func.dfg.append_ebb_arg(ebb0, types::I32);
+        let v1 = func.dfg.append_ebb_arg(ebb1, types::I32);
+
+        let (v2, v3);
+        {
+            let dfg = &mut func.dfg;
+            let cur = &mut Cursor::new(&mut func.layout);
+
+            cur.insert_ebb(ebb0);
+            v2 = dfg.ins(cur).iconst(types::I32, 1);
+            let mut args = VariableArgs::new();
+            args.push(v0);
+            dfg.ins(cur).jump(ebb1, args);
+
+            cur.insert_ebb(ebb1);
+            v3 = dfg.ins(cur).iadd(v1, v2);
+            let mut args = VariableArgs::new();
+            args.push(v3);
+            dfg.ins(cur).brnz(v3, ebb1, args);
+            let mut args = VariableArgs::new();
+            args.push(v1);
+            dfg.ins(cur).return_(args);
+        }
+
+        let mut liveness = Liveness::new();
+        liveness.compute(&func, &[ebb0, ebb1]);
+        assert!(liveness.live_in(ebb0).is_empty());
+        assert_eq!(liveness.live_in(ebb1).iter().cloned().collect::<Vec<_>>(), [v2]);
+
+        let live = liveness.ebb_liveness(&func, ebb1);
+        assert_eq!(live.entry.len(), 2);
+        assert_eq!(live.insts.len(), 3);
+        // `v2` is live around the loop, and `v1` is used by the return.
+        assert!(live.insts[0].1.contains(&v1));
+        assert!(live.insts[0].1.contains(&v2));
+        assert!(live.insts[0].1.contains(&v3));
+        assert!(!live.insts[1].1.contains(&v3));
+        assert!(live.insts[2].1.is_empty());
+    }
+}
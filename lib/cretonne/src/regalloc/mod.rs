@@ -0,0 +1,279 @@
+//! Register allocation.
+//!
+//! The register allocator assigns every SSA value in a legalized function to a register or a
+//! stack slot, recording the result in the `func.locations` map. It works in four passes over
+//! the EBBs in a dominator tree preorder, so the definition of a value is always visited before
+//! its uses:
+//!
+//! 1. The `fixed` pass copies the values that must be in a fixed register, like the operands of
+//!    Intel's division instructions, so they only need the register for a short live range.
+//! 2. The `spilling` pass computes the register pressure at every instruction. When there are
+//!    more live values than allocatable registers in a register class, a value is moved to a spill
+//!    slot with a `spill` instruction, and every use of the value reads it back with a `fill`
+//!    instruction. Values that are live across a call are always spilled, since the callee may
+//!    clobber the caller-saved registers.
+//! 3. The `coloring` pass assigns a register to every value that isn't in a stack slot, subject to
+//!    the operand constraints of the instruction encodings.
+//! 4. Finally, EBB arguments are passed in the registers that were assigned to the destination
+//!    EBB's arguments. Branches, calls, and returns are preceded by parallel copies that move
+//!    their arguments into place. Conditional branches have their edge split first, so the copies
+//!    only execute when the branch is taken.
+//!
+//! Call arguments passed on the stack are stored in outgoing argument slots. Return values on the
+//! stack are not supported, and signatures using them are rejected by `verify_encodings()`
+//! before register allocation. EBBs that are not reachable from the entry block don't get any
+//! value locations assigned.
+
+use cfg::ControlFlowGraph;
+use dominator_tree::DominatorTree;
+use ir::{Function, Ebb, Inst, Value, ValueDef, ValueLoc, ArgumentLoc, StackSlotData,
+         StackSlotKind, Cursor, InstBuilder};
+use isa::{TargetIsa, RegClass, ConstraintKind, OperandConstraint};
+use self::liveness::Liveness;
+
+pub use self::allocatable_set::AllocatableSet;
+
+mod allocatable_set;
+mod coloring;
+mod fixed;
+mod liveness;
+mod moves;
+mod spilling;
+
+/// Persistent memory allocations for register allocation.
+///
+/// A register allocation context can be reused for multiple functions to avoid reallocating the
+/// EBB order and liveness data structures every time.
+pub struct Context {
+    /// The reachable EBBs in dominator tree preorder.
+    order: Vec<Ebb>,
+
+    /// Live-in sets of the EBBs in `order`.
+    liveness: Liveness,
+}
+
+impl Context {
+    /// Create a new register allocation context.
+    pub fn new() -> Context {
+        Context {
+            order: Vec::new(),
+            liveness: Liveness::new(),
+        }
+    }
+
+    /// Allocate registers for all the values in `func`.
+    ///
+    /// The function must be legalized for `isa`, and `cfg` and `domtree` must be up to date.
+    /// Edges can be split while assigning EBB arguments, so the control flow graph and dominator
+    /// tree are no longer valid when this function returns.
+    pub fn run(&mut self,
+               func: &mut Function,
+               cfg: &ControlFlowGraph,
+               domtree: &DominatorTree,
+               isa: &TargetIsa) {
+        domtree_preorder(func, cfg, domtree, &mut self.order);
+
+        func.locations.clear();
+        func.encodings.resize(func.dfg.num_insts());
+        assign_fixed_locations(func, isa);
+        fixed::isolate_fixed_values(func, isa);
+
+        spilling::spill(func, isa, &self.order, &mut self.liveness);
+        coloring::color(func, cfg, isa, &self.order, &self.liveness);
+        moves::insert_moves(func, isa, &self.order);
+    }
+}
+
+/// Allocate registers for all the values in `func`.
+///
+/// This is a convenience function which uses a new register allocation `Context`. See
+/// `Context::run()` for details.
+pub fn allocate_registers(func: &mut Function,
+                          cfg: &ControlFlowGraph,
+                          domtree: &DominatorTree,
+                          isa: &TargetIsa) {
+    Context::new().run(func, cfg, domtree, isa);
+}
+
+/// Compute a preorder of the dominator tree for the reachable EBBs in `func` into `order`.
+///
+/// Children in the dominator tree are visited in layout order.
+fn domtree_preorder(func: &Function,
+                    cfg: &ControlFlowGraph,
+                    domtree: &DominatorTree,
+                    order: &mut Vec<Ebb>) {
+    order.clear();
+    let entry = match func.layout.entry_block() {
+        Some(ebb) => ebb,
+        None => return,
+    };
+
+    let reachable = cfg.postorder_ebbs();
+    let children: Vec<(Ebb, Ebb)> = func.layout
+        .ebbs()
+        .filter(|ebb| *ebb != entry && reachable.contains(ebb))
+        .filter_map(|ebb| domtree.idom(ebb).map(|(parent, _)| (parent, ebb)))
+        .collect();
+
+    let mut stack = vec![entry];
+    while let Some(ebb) = stack.pop() {
+        order.push(ebb);
+        // Push the children in reverse layout order, so they are popped in layout order.
+        for &(_, child) in children.iter().rev().filter(|&&(parent, _)| parent == ebb) {
+            stack.push(child);
+        }
+    }
+}
+
+/// Assign the locations that are determined before register allocation.
+///
+/// - Entry block arguments are passed in the locations given by the function signature.
+/// - Results of instructions with a stack operand constraint, like `spill`, are assigned to new
+///   spill slots.
+fn assign_fixed_locations(func: &mut Function, isa: &TargetIsa) {
+    if let Some(entry) = func.layout.entry_block() {
+        let args: Vec<Value> = func.dfg.ebb_args(entry).collect();
+        let abi_args = func.own_signature().argument_types.clone();
+        let matches = args.len() == abi_args.len() &&
+                      args.iter()
+            .zip(&abi_args)
+            .all(|(&arg, abi)| func.dfg.value_type(arg) == abi.value_type);
+        if matches {
+            for (&arg, abi) in args.iter().zip(&abi_args) {
+                let loc = match abi.location {
+                    ArgumentLoc::Reg(reg) => ValueLoc::Reg(reg),
+                    ArgumentLoc::Stack(offset) => {
+                        let size = abi.value_type.bytes();
+                        let mut data = StackSlotData::new(StackSlotKind::IncomingArg, size);
+                        data.offset = Some(offset);
+                        ValueLoc::Stack(func.stack_slots.push(data))
+                    }
+                    ArgumentLoc::Unassigned => continue,
+                };
+                *func.locations.ensure(arg) = loc;
+            }
+        }
+    }
+
+    for ebb in func.layout.ebbs().collect::<Vec<_>>() {
+        for inst in func.layout.ebb_insts(ebb).collect::<Vec<_>>() {
+            let results: Vec<Value> = func.dfg.inst_results(inst).collect();
+            for (idx, &res) in results.iter().enumerate() {
+                if let Some(cons) = result_constraint(func, isa, inst, idx) {
+                    if cons.kind == ConstraintKind::Stack {
+                        assign_spill_slot(func, res);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Assign a new spill slot to `value`.
+fn assign_spill_slot(func: &mut Function, value: Value) {
+    let size = func.dfg.value_type(value).bytes();
+    let ss = func.stack_slots.push(StackSlotData::new(StackSlotKind::SpillSlot, size));
+    *func.locations.ensure(value) = ValueLoc::Stack(ss);
+}
+
+/// Get the current location of `value`.
+fn location(func: &Function, value: Value) -> ValueLoc {
+    func.locations.get(value).cloned().unwrap_or_default()
+}
+
+/// Set the encoding of the newly inserted instruction `inst`.
+///
+/// Instructions that can't be encoded get the default encoding, just like the legalizer leaves
+/// them.
+pub fn encode_inst(func: &mut Function, isa: &TargetIsa, inst: Inst) {
+    let enc = isa.encode(&func.dfg, &func.dfg[inst]).unwrap_or_default();
+    *func.encodings.ensure(inst) = enc;
+}
+
+/// Set the encoding of the newly inserted instruction defining `value`.
+pub fn encode_def(func: &mut Function, isa: &TargetIsa, value: Value) {
+    let inst = def_inst(func, value);
+    encode_inst(func, isa, inst);
+}
+
+/// Get the instruction defining `value`.
+fn def_inst(func: &Function, value: Value) -> Inst {
+    match func.dfg.value_def(value) {
+        ValueDef::Res(inst, _) => inst,
+        ValueDef::Arg(..) => panic!("{} is not an instruction result", value),
+    }
+}
+
+/// Insert a copy of `arg` before `inst`, and use it as operand number `num`.
+///
+/// Return the copied value.
+fn copy_operand(func: &mut Function,
+                isa: &TargetIsa,
+                inst: Inst,
+                num: usize,
+                arg: Value)
+                -> Value {
+    let copy = {
+        let mut pos = Cursor::new(&mut func.layout);
+        pos.goto_inst(inst);
+        func.dfg.ins(&mut pos).copy(arg)
+    };
+    if let ValueDef::Res(copy_inst, _) = func.dfg.value_def(copy) {
+        encode_inst(func, isa, copy_inst);
+    }
+
+    let mut idx = 0;
+    func.dfg[inst].map_arguments(|a| {
+        idx += 1;
+        if idx - 1 == num { copy } else { a }
+    });
+    copy
+}
+
+/// Get the operand constraint for result number `idx` of `inst`, if it has a legal encoding.
+fn result_constraint(func: &Function,
+                     isa: &TargetIsa,
+                     inst: Inst,
+                     idx: usize)
+                     -> Option<&'static OperandConstraint> {
+    let enc = func.encodings.get(inst).cloned().unwrap_or_default();
+    if enc.is_legal() {
+        isa.recipe_constraints()[enc.recipe()].outs.get(idx)
+    } else {
+        None
+    }
+}
+
+/// Get the operand constraint for value operand number `idx` of `inst`, if it has a legal
+/// encoding.
+fn operand_constraint(func: &Function,
+                      isa: &TargetIsa,
+                      inst: Inst,
+                      idx: usize)
+                      -> Option<&'static OperandConstraint> {
+    let enc = func.encodings.get(inst).cloned().unwrap_or_default();
+    if enc.is_legal() {
+        isa.recipe_constraints()[enc.recipe()].ins.get(idx)
+    } else {
+        None
+    }
+}
+
+/// Get the register class that should hold `value`, or `None` if the value lives in a stack slot.
+///
+/// The register class is determined by the encoding recipe of the defining instruction. Values
+/// that aren't constrained by a recipe use the ABI register class for their type.
+fn value_regclass(func: &Function, isa: &TargetIsa, value: Value) -> Option<RegClass> {
+    if let ValueLoc::Stack(_) = location(func, value) {
+        return None;
+    }
+    if let ValueDef::Res(inst, idx) = func.dfg.value_def(value) {
+        if let Some(cons) = result_constraint(func, isa, inst, idx) {
+            return match cons.kind {
+                ConstraintKind::Stack => None,
+                _ => Some(cons.regclass),
+            };
+        }
+    }
+    Some(isa.regclass_for_abi_type(func.dfg.value_type(value)))
+}
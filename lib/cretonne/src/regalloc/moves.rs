@@ -0,0 +1,239 @@
+//! Moving values into place for branches and returns.
+//!
+//! After coloring, every EBB argument has been assigned a register, and the values passed by a
+//! branch must be moved into those registers before the branch. Likewise, return values must be
+//! moved into the registers given by the function signature, and call arguments into the
+//! registers given by the call signature. The spilling pass has moved all the values that are
+//! live across a call to the stack, so the argument registers can't hold any other live values.
+//! Call arguments that are passed on the stack are spilled to outgoing argument slots before the
+//! register arguments are moved into place.
+//!
+//! The moves are inserted as a parallel copy: A sequence of `copy` instructions that behaves as if
+//! all the values were copied at the same time. When the moves form a cycle, one of the values is
+//! temporarily moved to a spill slot to break the cycle.
+//!
+//! The copies must only execute when a conditional branch is taken, so conditional branches that
+//! need copies are redirected to a new EBB containing the copies and a jump to the original
+//! destination.
+
+use ir::{Function, Ebb, Inst, Value, ValueDef, ValueLoc, Cursor, InstBuilder, InstructionData,
+         Opcode, VariableArgs, ArgumentType, ArgumentLoc, SigRef, StackSlotData, StackSlotKind};
+use ir::instructions::BranchInfo;
+use isa::{TargetIsa, RegUnit};
+use super::{location, assign_spill_slot, encode_inst};
+
+/// Insert parallel copies before the branches, calls, and returns in the EBBs in `order`.
+pub fn insert_moves(func: &mut Function, isa: &TargetIsa, order: &[Ebb]) {
+    for &ebb in order {
+        for inst in func.layout.ebb_insts(ebb).collect::<Vec<_>>() {
+            let dest = match func.dfg[inst].analyze_branch() {
+                BranchInfo::SingleDest(dest, _) => dest,
+                _ => {
+                    if func.dfg[inst].opcode() == Opcode::Return {
+                        move_return_values(func, isa, inst);
+                    } else if let Some(sig) = func.dfg.call_signature(inst) {
+                        move_call_arguments(func, isa, inst, sig);
+                    }
+                    continue;
+                }
+            };
+
+            let targets: Vec<ValueLoc> =
+                func.dfg.ebb_args(dest).map(|arg| location(func, arg)).collect();
+            if !needs_moves(func, varargs(func, inst), &targets) {
+                continue;
+            }
+
+            let jump = if func.dfg[inst].opcode() == Opcode::Jump {
+                inst
+            } else {
+                split_edge(func, isa, inst)
+            };
+            insert_parallel_copy(func, isa, jump, &targets);
+        }
+    }
+}
+
+/// Move the values returned by the `return` instruction `inst` into the return registers.
+fn move_return_values(func: &mut Function, isa: &TargetIsa, inst: Inst) {
+    let targets = abi_registers(&func.own_signature().return_types);
+    if needs_moves(func, varargs(func, inst), &targets) {
+        insert_parallel_copy(func, isa, inst, &targets);
+    }
+}
+
+/// Move the arguments of the call instruction `inst` into the argument registers and outgoing
+/// argument slots of `sig`.
+fn move_call_arguments(func: &mut Function, isa: &TargetIsa, inst: Inst, sig: SigRef) {
+    let abi_args = func.dfg.signatures[sig].argument_types.clone();
+    if varargs(func, inst).len() == abi_args.len() {
+        store_stack_arguments(func, isa, inst, &abi_args);
+    }
+    let targets = abi_registers(&abi_args);
+    if needs_moves(func, varargs(func, inst), &targets) {
+        insert_parallel_copy(func, isa, inst, &targets);
+    }
+}
+
+/// Spill the arguments of the call instruction `inst` that are passed on the stack to new
+/// outgoing argument slots, and pass the spilled values to the call instead.
+///
+/// The stores must happen before the register arguments are moved, since the parallel copy can
+/// overwrite the registers holding the stack arguments.
+fn store_stack_arguments(func: &mut Function,
+                         isa: &TargetIsa,
+                         inst: Inst,
+                         abi_args: &[ArgumentType]) {
+    for (num, abi) in abi_args.iter().enumerate() {
+        if let ArgumentLoc::Stack(offset) = abi.location {
+            let arg = varargs(func, inst)[num];
+            let spill = insert_before(func, isa, inst, |pos, dfg| dfg.ins(pos).spill(arg));
+            let mut data = StackSlotData::new(StackSlotKind::OutgoingArg, abi.value_type.bytes());
+            data.offset = Some(offset);
+            *func.locations.ensure(spill) = ValueLoc::Stack(func.stack_slots.push(data));
+            varargs_mut(func, inst)[num] = spill;
+        }
+    }
+}
+
+/// Get the register locations of the ABI arguments or return values in `args`.
+fn abi_registers(args: &[ArgumentType]) -> Vec<ValueLoc> {
+    args.iter()
+        .map(|arg| match arg.location {
+            ArgumentLoc::Reg(reg) => ValueLoc::Reg(reg),
+            _ => ValueLoc::Unassigned,
+        })
+        .collect()
+}
+
+/// Get the variable arguments to the branch, call, or return instruction `inst`.
+fn varargs(func: &Function, inst: Inst) -> &[Value] {
+    match func.dfg[inst] {
+        InstructionData::Call { ref data, .. } => &data.varargs,
+        InstructionData::IndirectCall { ref data, .. } => &data.varargs,
+        InstructionData::Jump { ref data, .. } => &data.varargs,
+        InstructionData::Branch { ref data, .. } => &data.varargs,
+        InstructionData::BranchIcmp { ref data, .. } => &data.varargs,
+        InstructionData::Return { ref data, .. } => &data.varargs,
+        _ => &[],
+    }
+}
+
+/// Get a mutable reference to the variable arguments of the branch, call, or return instruction
+/// `inst`.
+fn varargs_mut(func: &mut Function, inst: Inst) -> &mut VariableArgs {
+    match func.dfg[inst] {
+        InstructionData::Call { ref mut data, .. } => &mut data.varargs,
+        InstructionData::IndirectCall { ref mut data, .. } => &mut data.varargs,
+        InstructionData::Jump { ref mut data, .. } => &mut data.varargs,
+        InstructionData::Branch { ref mut data, .. } => &mut data.varargs,
+        InstructionData::BranchIcmp { ref mut data, .. } => &mut data.varargs,
+        InstructionData::Return { ref mut data, .. } => &mut data.varargs,
+        _ => panic!("{} has no variable arguments", inst),
+    }
+}
+
+/// Do any of the values in `args` need to be moved into the register given by `targets`?
+///
+/// Targets that are not registers are ignored. If the number of values doesn't match the number
+/// of targets, the instruction doesn't agree with its destination, and no moves are inserted.
+fn needs_moves(func: &Function, args: &[Value], targets: &[ValueLoc]) -> bool {
+    args.len() == targets.len() &&
+    args.iter().zip(targets).any(|(&arg, &target)| match target {
+        ValueLoc::Reg(_) => location(func, arg) != target,
+        _ => false,
+    })
+}
+
+/// Redirect the conditional branch `inst` to a new EBB which jumps to the original destination.
+///
+/// Return the new jump instruction.
+fn split_edge(func: &mut Function, isa: &TargetIsa, inst: Inst) -> Inst {
+    let new_ebb = func.dfg.make_ebb();
+    func.layout.append_ebb(new_ebb);
+
+    let (dest, args) = match func.dfg[inst] {
+        InstructionData::Branch { ref mut data, .. } => {
+            let dest = data.destination;
+            data.destination = new_ebb;
+            (dest, ::std::mem::replace(&mut data.varargs, VariableArgs::new()))
+        }
+        InstructionData::BranchIcmp { ref mut data, .. } => {
+            let dest = data.destination;
+            data.destination = new_ebb;
+            (dest, ::std::mem::replace(&mut data.varargs, VariableArgs::new()))
+        }
+        _ => panic!("{} is not a conditional branch", inst),
+    };
+
+    let jump = {
+        let mut pos = Cursor::new(&mut func.layout);
+        pos.goto_bottom(new_ebb);
+        func.dfg.ins(&mut pos).jump(dest, args)
+    };
+    encode_inst(func, isa, jump);
+    jump
+}
+
+/// Insert a parallel copy before `inst` which moves its variable arguments into the registers
+/// given by `targets`.
+fn insert_parallel_copy(func: &mut Function, isa: &TargetIsa, inst: Inst, targets: &[ValueLoc]) {
+    // Pending moves: (argument number, value, source register, destination register).
+    let mut pending: Vec<(usize, Value, RegUnit, RegUnit)> = Vec::new();
+    for (num, (&arg, &target)) in varargs(func, inst).iter().zip(targets).enumerate() {
+        if let (ValueLoc::Reg(src), ValueLoc::Reg(dst)) = (location(func, arg), target) {
+            if src != dst {
+                pending.push((num, arg, src, dst));
+            }
+        }
+    }
+
+    let mut moved = Vec::new();
+    let mut spilled = Vec::new();
+    while !pending.is_empty() {
+        // A move can be executed when no other pending move still needs to read its destination
+        // register. If all the pending moves form cycles, break one by moving its value to a
+        // spill slot.
+        match pending.iter().position(|&(_, _, _, dst)| !pending.iter().any(|m| m.2 == dst)) {
+            Some(idx) => {
+                let (num, arg, _, dst) = pending.remove(idx);
+                let copy = insert_before(func, isa, inst, |pos, dfg| dfg.ins(pos).copy(arg));
+                *func.locations.ensure(copy) = ValueLoc::Reg(dst);
+                moved.push((num, copy));
+            }
+            None => {
+                let (num, arg, _, dst) = pending.remove(0);
+                let spill = insert_before(func, isa, inst, |pos, dfg| dfg.ins(pos).spill(arg));
+                assign_spill_slot(func, spill);
+                spilled.push((num, spill, dst));
+            }
+        }
+    }
+    for (num, spill, dst) in spilled {
+        let fill = insert_before(func, isa, inst, |pos, dfg| dfg.ins(pos).fill(spill));
+        *func.locations.ensure(fill) = ValueLoc::Reg(dst);
+        moved.push((num, fill));
+    }
+
+    let args = varargs_mut(func, inst);
+    for (num, value) in moved {
+        args[num] = value;
+    }
+}
+
+/// Insert a single instruction before `inst` using the builder function `build`, and encode it.
+///
+/// Return the result value of the new instruction.
+fn insert_before<F>(func: &mut Function, isa: &TargetIsa, inst: Inst, build: F) -> Value
+    where F: FnOnce(&mut Cursor, &mut ::ir::DataFlowGraph) -> Value
+{
+    let value = {
+        let mut pos = Cursor::new(&mut func.layout);
+        pos.goto_inst(inst);
+        build(&mut pos, &mut func.dfg)
+    };
+    if let ValueDef::Res(new_inst, _) = func.dfg.value_def(value) {
+        encode_inst(func, isa, new_inst);
+    }
+    value
+}
@@ -0,0 +1,289 @@
+//! Spilling pass.
+//!
+//! The spilling pass makes sure that the number of values live in registers never exceeds the
+//! number of allocatable registers in their register class. This guarantees that the coloring
+//! pass will find a free register for every value.
+//!
+//! We use a simple spill-everywhere strategy: When the register pressure is too high, a live value
+//! that is not used by the current instruction is picked, preferring the value whose next use is
+//! furthest away. The value is copied to a spill slot with a `spill` instruction right after its
+//! definition, and every use of the value is rewritten to use a `fill` instruction inserted
+//! immediately before it. The values to spill are picked for the whole function before any spill
+//! code is inserted, and all the uses are rewritten in a single pass, so the liveness analysis
+//! only needs to be recomputed once per round.
+//!
+//! Values that are already in a stack slot, like incoming arguments passed on the stack, are
+//! also filled before every use that needs a register.
+//!
+//! The callee may clobber any of the caller-saved registers, so every value that is live across a
+//! call is spilled before the register pressure is considered. Only the call arguments and the
+//! fills feeding them are in registers at the call.
+
+use std::collections::BTreeMap;
+use ir::{Function, Ebb, Inst, Value, ValueDef, ValueLoc, Cursor, InstBuilder, Opcode};
+use isa::{TargetIsa, RegClass, ConstraintKind};
+use regalloc::AllocatableSet;
+use regalloc::liveness::{Liveness, LiveSet, inst_arguments};
+use super::{location, assign_spill_slot, encode_inst, operand_constraint, result_constraint,
+            value_regclass};
+
+/// Insert spills and fills in `func` so the register pressure never exceeds the available
+/// registers.
+///
+/// Leave the liveness information for the final function in `liveness`.
+pub fn spill(func: &mut Function, isa: &TargetIsa, order: &[Ebb], liveness: &mut Liveness) {
+    let regs = isa.allocatable_registers(func);
+
+    // Values that start out in a stack slot need to be filled before they are used.
+    let mut stack = BTreeMap::new();
+    for &ebb in order {
+        let mut values: Vec<Value> = func.dfg.ebb_args(ebb).collect();
+        for inst in func.layout.ebb_insts(ebb) {
+            values.extend(func.dfg.inst_results(inst));
+        }
+        for value in values {
+            if let ValueLoc::Stack(_) = location(func, value) {
+                stack.insert(value, value);
+            }
+        }
+    }
+
+    liveness.compute(func, order);
+    for value in live_across_calls(func, isa, liveness, order) {
+        let spilled = insert_spill(func, isa, value);
+        stack.insert(value, spilled);
+    }
+    insert_fills(func, isa, &stack);
+
+    // Spilling a value never increases the register pressure elsewhere, so the values to spill in
+    // all the EBBs can be picked from the same liveness information. The pressure is checked
+    // again after inserting the spills.
+    loop {
+        liveness.compute(func, order);
+        let values = spill_candidates(func, isa, &regs, liveness, order);
+        if values.is_empty() {
+            return;
+        }
+        let stack = values.into_iter()
+            .map(|value| (value, insert_spill(func, isa, value)))
+            .collect();
+        insert_fills(func, isa, &stack);
+    }
+}
+
+/// Get the values in registers that are live across a call in the EBBs in `order`.
+fn live_across_calls(func: &Function,
+                     isa: &TargetIsa,
+                     liveness: &Liveness,
+                     order: &[Ebb])
+                     -> LiveSet {
+    let mut across = LiveSet::new();
+    for &ebb in order {
+        for (inst, live_after) in liveness.ebb_liveness(func, ebb).insts {
+            if func.dfg.call_signature(inst).is_some() {
+                across.extend(live_after.into_iter().filter(|&v| {
+                    value_regclass(func, isa, v).is_some() &&
+                    !func.dfg.inst_results(inst).any(|res| res == v)
+                }));
+            }
+        }
+    }
+    across
+}
+
+/// Find the values to spill in order to reduce the register pressure in the EBBs in `order`.
+///
+/// Return an empty set if the register pressure never exceeds the available registers.
+fn spill_candidates(func: &Function,
+                    isa: &TargetIsa,
+                    regs: &AllocatableSet,
+                    liveness: &Liveness,
+                    order: &[Ebb])
+                    -> LiveSet {
+    let mut spilled = LiveSet::new();
+    for &ebb in order {
+        let live = liveness.ebb_liveness(func, ebb);
+        let insts: Vec<Inst> = live.insts.iter().map(|&(inst, _)| inst).collect();
+
+        // The EBB arguments are all defined at the top of the EBB, even if they are not used.
+        let mut pressure: LiveSet = liveness.live_in(ebb).difference(&spilled).cloned().collect();
+        pressure.extend(func.dfg.ebb_args(ebb));
+        if let Err(rc) = relieve(func, isa, regs, pressure, &insts, &[], &mut spilled) {
+            panic!("Can't reduce {} pressure at {}", rc, ebb);
+        }
+
+        for (idx, &(inst, ref live_after)) in live.insts.iter().enumerate() {
+            // The instruction results need registers even if they are not used. The values killed
+            // by the instruction have already been counted in the previous live set. A spilled
+            // value is still in a register where it is defined, and its uses read a fill.
+            let mut pressure: LiveSet = live_after.difference(&spilled).cloned().collect();
+            pressure.extend(func.dfg.inst_results(inst));
+            // Early clobber results also compete with the killed arguments.
+            if has_early_clobber(func, isa, inst) {
+                pressure.extend(inst_arguments(func, inst));
+            }
+            let mut excluded = inst_arguments(func, inst);
+            excluded.extend(func.dfg.inst_results(inst));
+            if let Err(rc) =
+                relieve(func, isa, regs, pressure, &insts[idx + 1..], &excluded, &mut spilled) {
+                panic!("Can't reduce {} pressure at {}", rc, inst);
+            }
+        }
+    }
+    spilled
+}
+
+/// Add values from `pressure` to `spilled` until the remaining values fit in `regs`.
+///
+/// The values are picked by `furthest_use()`. Return the overflowing register class if there are
+/// no more values that can be spilled.
+fn relieve(func: &Function,
+           isa: &TargetIsa,
+           regs: &AllocatableSet,
+           mut pressure: LiveSet,
+           insts: &[Inst],
+           excluded: &[Value],
+           spilled: &mut LiveSet)
+           -> Result<(), RegClass> {
+    while let Some(rc) = overflow(func, isa, regs, &pressure) {
+        let value = try!(furthest_use(func, isa, rc, &pressure, insts, excluded).ok_or(rc));
+        pressure.remove(&value);
+        spilled.insert(value);
+    }
+    Ok(())
+}
+
+/// Does `inst` have any results with an early clobber constraint?
+fn has_early_clobber(func: &Function, isa: &TargetIsa, inst: Inst) -> bool {
+    (0..func.dfg.inst_results(inst).count()).any(|idx| {
+        result_constraint(func, isa, inst, idx)
+            .map_or(false, |cons| cons.kind == ConstraintKind::EarlyClobber)
+    })
+}
+
+/// Find a register class where the values in `live` need more registers than are available.
+fn overflow(func: &Function,
+            isa: &TargetIsa,
+            regs: &AllocatableSet,
+            live: &LiveSet)
+            -> Option<RegClass> {
+    let mut counts: Vec<(RegClass, usize)> = Vec::new();
+    for &value in live {
+        if let Some(rc) = value_regclass(func, isa, value) {
+            match counts.iter().position(|&(c, _)| c == rc) {
+                Some(i) => counts[i].1 += 1,
+                None => counts.push((rc, 1)),
+            }
+        }
+    }
+    counts.into_iter().find(|&(rc, n)| n > regs.count(rc)).map(|(rc, _)| rc)
+}
+
+/// Pick the value in `live` of class `rc` whose next use in `insts` is furthest away.
+///
+/// Values in `excluded` and values defined by a `fill` instruction can't be spilled.
+fn furthest_use(func: &Function,
+                isa: &TargetIsa,
+                rc: RegClass,
+                live: &LiveSet,
+                insts: &[Inst],
+                excluded: &[Value])
+                -> Option<Value> {
+    live.iter()
+        .cloned()
+        .filter(|&v| value_regclass(func, isa, v) == Some(rc) && !excluded.contains(&v))
+        .filter(|&v| match func.dfg.value_def(v) {
+            ValueDef::Res(inst, _) => func.dfg[inst].opcode() != Opcode::Fill,
+            ValueDef::Arg(..) => true,
+        })
+        .max_by_key(|&v| {
+            insts.iter()
+                .position(|&inst| inst_arguments(func, inst).contains(&v))
+                .unwrap_or(insts.len())
+        })
+}
+
+/// Copy `value` to a new spill slot right after its definition.
+///
+/// Return the spilled value.
+fn insert_spill(func: &mut Function, isa: &TargetIsa, value: Value) -> Value {
+    let spilled = {
+        let mut pos = Cursor::new(&mut func.layout);
+        match func.dfg.value_def(value) {
+            ValueDef::Res(inst, _) => pos.goto_inst(inst),
+            ValueDef::Arg(ebb, _) => pos.goto_top(ebb),
+        }
+        pos.next_inst();
+        func.dfg.ins(&mut pos).spill(value)
+    };
+    if let ValueDef::Res(spill_inst, _) = func.dfg.value_def(spilled) {
+        encode_inst(func, isa, spill_inst);
+    }
+    assign_spill_slot(func, spilled);
+    spilled
+}
+
+/// Rewrite the uses of the values in `stack` to read the stack values they map to.
+///
+/// A `fill` instruction is inserted before every instruction that needs one of the stack values in
+/// a register. The whole function is rewritten in a single pass, so it is best to spill many
+/// values at once.
+fn insert_fills(func: &mut Function, isa: &TargetIsa, stack: &BTreeMap<Value, Value>) {
+    if stack.is_empty() {
+        return;
+    }
+    for ebb in func.layout.ebbs().collect::<Vec<_>>() {
+        for inst in func.layout.ebb_insts(ebb).collect::<Vec<_>>() {
+            let args = inst_arguments(func, inst);
+            let mut new_args = Vec::with_capacity(args.len());
+            // Fills inserted before `inst`, and the stack values they read.
+            let mut fills: Vec<(Value, Value)> = Vec::new();
+            for (idx, &arg) in args.iter().enumerate() {
+                let stack_value = match stack.get(&arg) {
+                    // The `spill` instruction itself reads the value from its register.
+                    Some(&sv) if !is_defined_by(func, sv, inst) => sv,
+                    _ => {
+                        new_args.push(arg);
+                        continue;
+                    }
+                };
+                let needs_reg = operand_constraint(func, isa, inst, idx)
+                    .map_or(true, |cons| cons.kind != ConstraintKind::Stack);
+                if !needs_reg {
+                    new_args.push(stack_value);
+                    continue;
+                }
+                if let Some(&(_, filled)) = fills.iter().find(|&&(sv, _)| sv == stack_value) {
+                    new_args.push(filled);
+                    continue;
+                }
+                let filled = {
+                    let mut pos = Cursor::new(&mut func.layout);
+                    pos.goto_inst(inst);
+                    func.dfg.ins(&mut pos).fill(stack_value)
+                };
+                if let ValueDef::Res(fill_inst, _) = func.dfg.value_def(filled) {
+                    encode_inst(func, isa, fill_inst);
+                }
+                fills.push((stack_value, filled));
+                new_args.push(filled);
+            }
+
+            if new_args != args {
+                let mut idx = 0;
+                func.dfg[inst].map_arguments(|_| {
+                    idx += 1;
+                    new_args[idx - 1]
+                });
+            }
+        }
+    }
+}
+
+/// Is `value` a result of `inst`?
+fn is_defined_by(func: &Function, value: Value, inst: Inst) -> bool {
+    match func.dfg.value_def(value) {
+        ValueDef::Res(def, _) => def == inst,
+        ValueDef::Arg(..) => false,
+    }
+}
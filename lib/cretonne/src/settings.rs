@@ -269,6 +269,7 @@ mod tests {
         assert_eq!(f.to_string(),
                    "[shared]\n\
                     opt_level = \"default\"\n\
+                    enable_verifier = true\n\
                     is_64bit = false\n\
                     enable_float = true\n\
                     enable_simd = true\n\
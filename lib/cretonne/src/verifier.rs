@@ -12,10 +12,22 @@
 //!   Instruction integrity
 //!
 //!    - The instruction format must match the opcode.
+//!    - Referenced stack slots, global variables and heaps must exist.
+//!    - Global variables can't be based on themselves through a chain of `deref` globals.
+//!    - Stack slot loads and stores must be in-bounds.
+//!    - Atomic loads can't have release semantics, atomic stores can't have acquire semantics,
+//!      and fences can't be relaxed.
 //! TODO:
 //!    - All result values must be created for multi-valued instructions.
 //!    - Instructions with no results must have a VOID first_type().
-//!    - All referenced entities must exist. (Values, EBBs, stack slots, ...)
+//!    - The other referenced entities must exist. (Values, EBBs, jump tables, functions,
+//!      signatures)
+//!
+//!   Preamble integrity
+//!
+//!    - Heap declarations must refer to existing global variables.
+//!    - The base of a `deref` global variable must exist.
+//!    - Functions using `vmctx` global variables must have a VM context argument.
 //!
 //!   SSA form
 //!
@@ -43,7 +55,6 @@
 //!
 //!   Ad hoc checking
 //!
-//!    - Stack slot loads and stores must be in-bounds.
 //!    - Immediate constraints for certain opcodes, like udiv_imm v3, 0.
 //!    - Extend / truncate instructions have more type constraints: Source type can't be
 //!      larger / smaller than result type.
@@ -52,8 +63,11 @@
 //!    - Swizzle and shuffle instructions take a variable number of lane arguments. The number
 //!      of arguments must match the destination type, and the lane indexes must be in range.
 
-use ir::{Function, ValueDef, Ebb, Inst};
-use ir::instructions::InstructionFormat;
+use ir::{Function, ValueDef, Ebb, Inst, Opcode, StackSlot, GlobalVar, GlobalVarData, Heap,
+         HeapBase, HeapStyle, ArgumentPurpose, Signature};
+use ir::atomics::AtomicOrdering;
+use ir::immediates::Offset32;
+use ir::instructions::{InstructionFormat, InstructionData};
 use ir::entities::AnyEntity;
 use std::fmt::{self, Display, Formatter};
 use std::result;
@@ -98,6 +112,43 @@ pub fn verify_function(func: &Function) -> Result<()> {
     Verifier::new(func).run()
 }
 
+/// Verify that every instruction in `func` has a legal encoding, and that the function's
+/// signatures can be represented by the target ISA's calling convention.
+///
+/// This only makes sense after legalization. Instructions without a legal encoding can't be
+/// emitted as machine code. Signature legalization leaves arguments and return values the calling
+/// convention can't represent without a location, and return values on the stack are not
+/// supported by the register allocator.
+pub fn verify_encodings(func: &Function) -> Result<()> {
+    for ebb in func.layout.ebbs() {
+        for inst in func.layout.ebb_insts(ebb) {
+            if !func.encodings.get(inst).map_or(false, |enc| enc.is_legal()) {
+                return err!(inst, "{} has no legal encoding", func.dfg[inst].opcode());
+            }
+        }
+    }
+    if let Some(msg) = signature_error(func.own_signature()) {
+        return err!(AnyEntity::Function, msg);
+    }
+    for sig in func.dfg.signatures.keys() {
+        if let Some(msg) = signature_error(&func.dfg.signatures[sig]) {
+            return err!(sig, msg);
+        }
+    }
+    Ok(())
+}
+
+/// Check the argument and return value locations in the legalized signature `sig`.
+fn signature_error(sig: &Signature) -> Option<&'static str> {
+    if !sig.argument_types.iter().chain(&sig.return_types).all(|arg| arg.location.is_assigned()) {
+        Some("signature not supported by the calling convention")
+    } else if !sig.return_types.iter().all(|ret| ret.location.is_reg()) {
+        Some("return values on the stack are not supported")
+    } else {
+        None
+    }
+}
+
 struct Verifier<'a> {
     func: &'a Function,
 }
@@ -153,10 +204,138 @@ impl<'a> Verifier<'a> {
             return err!(inst, "instruction opcode doesn't match instruction format");
         }
 
+        match *inst_data {
+            InstructionData::StackLoad { opcode, ty, stack_slot, offset } => {
+                // The `stack_addr` instruction must point at a byte inside the slot.
+                let size = if opcode == Opcode::StackAddr { 1 } else { ty.bytes() };
+                try!(self.verify_stack_access(inst, stack_slot, offset, size));
+            }
+            InstructionData::StackStore { arg, stack_slot, offset, .. } => {
+                let size = self.func.dfg.value_type(arg).bytes();
+                try!(self.verify_stack_access(inst, stack_slot, offset, size));
+            }
+            InstructionData::UnaryGlobalVar { opcode, global_var, .. } => {
+                try!(self.verify_global_var(inst, global_var));
+                if opcode == Opcode::GlobalsymAddr {
+                    match self.func.global_vars[global_var] {
+                        GlobalVarData::Sym { .. } => {}
+                        _ => return err!(inst, "globalsym_addr requires a globalsym variable"),
+                    }
+                }
+            }
+            InstructionData::HeapAddr { heap, .. } => {
+                if !self.func.heaps.is_valid(heap) {
+                    return err!(inst, "invalid heap {}", heap);
+                }
+            }
+            InstructionData::AtomicLoad { ordering, .. } => {
+                if ordering.is_release() && ordering != AtomicOrdering::SeqCst {
+                    return err!(inst, "atomic load can't have {} ordering", ordering);
+                }
+            }
+            InstructionData::AtomicStore { ordering, .. } => {
+                if ordering.is_acquire() && ordering != AtomicOrdering::SeqCst {
+                    return err!(inst, "atomic store can't have {} ordering", ordering);
+                }
+            }
+            InstructionData::Fence { ordering, .. } => {
+                if ordering == AtomicOrdering::Relaxed {
+                    return err!(inst, "fence can't be relaxed");
+                }
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    /// Check that the `size` bytes accessed at `offset` are inside the stack slot `ss`.
+    fn verify_stack_access(&self,
+                           inst: Inst,
+                           ss: StackSlot,
+                           offset: Offset32,
+                           size: u32)
+                           -> Result<()> {
+        if !self.func.stack_slots.is_valid(ss) {
+            return err!(inst, "invalid stack slot {}", ss);
+        }
+        let offset: i64 = offset.into();
+        if offset < 0 {
+            return err!(inst, "negative offset into {}", ss);
+        }
+        let slot_size = self.func.stack_slots[ss].size;
+        if offset + size as i64 > slot_size as i64 {
+            return err!(inst,
+                        "{} byte access at offset {} is out of bounds for {} bytes in {}",
+                        size,
+                        offset,
+                        slot_size,
+                        ss);
+        }
+        Ok(())
+    }
+
+    fn verify_global_var<L: Into<AnyEntity>>(&self, loc: L, gv: GlobalVar) -> Result<()> {
+        if !self.func.global_vars.is_valid(gv) {
+            return err!(loc, "invalid global variable {}", gv);
+        }
+        Ok(())
+    }
+
+    fn global_var_integrity(&self, gv: GlobalVar) -> Result<()> {
+        match self.func.global_vars[gv] {
+            GlobalVarData::VmCtx { .. } => {
+                let args = &self.func.own_signature().argument_types;
+                if !args.iter().any(|arg| arg.purpose == ArgumentPurpose::VMContext) {
+                    return err!(gv, "vmctx global variable requires a vmctx argument");
+                }
+            }
+            GlobalVarData::Deref { base, .. } => {
+                try!(self.verify_global_var(gv, base));
+                try!(self.deref_acyclic(gv));
+            }
+            GlobalVarData::Sym { .. } => {}
+        }
+        Ok(())
+    }
+
+    // Follow the chain of `deref` bases from `gv`, and make sure it doesn't lead back to `gv`.
+    // A cycle that doesn't include `gv` is reported for the global variables in the cycle.
+    fn deref_acyclic(&self, gv: GlobalVar) -> Result<()> {
+        let mut visited = Vec::new();
+        let mut cur = gv;
+        while let GlobalVarData::Deref { base, .. } = self.func.global_vars[cur] {
+            if base == gv {
+                return err!(gv, "deref cycle through {}", cur);
+            }
+            if !self.func.global_vars.is_valid(base) || visited.contains(&base) {
+                break;
+            }
+            visited.push(base);
+            cur = base;
+        }
+        Ok(())
+    }
+
+    fn heap_integrity(&self, heap: Heap) -> Result<()> {
+        let data = &self.func.heaps[heap];
+        match data.base {
+            HeapBase::GlobalVar(gv) => try!(self.verify_global_var(heap, gv)),
+        }
+        match data.style {
+            HeapStyle::Dynamic { bound_gv } => try!(self.verify_global_var(heap, bound_gv)),
+            HeapStyle::Static { .. } => {}
+        }
         Ok(())
     }
 
     pub fn run(&self) -> Result<()> {
+        for gv in self.func.global_vars.keys() {
+            try!(self.global_var_integrity(gv));
+        }
+        for heap in self.func.heaps.keys() {
+            try!(self.heap_integrity(heap));
+        }
         for ebb in self.func.layout.ebbs() {
             for inst in self.func.layout.ebb_insts(ebb) {
                 try!(self.ebb_integrity(ebb, inst));
@@ -170,7 +349,10 @@ impl<'a> Verifier<'a> {
 #[cfg(test)]
 mod tests {
     use super::{Verifier, Error};
-    use ir::Function;
+    use ir::{Function, FunctionName, GlobalVar, GlobalVarData, HeapData, HeapBase, HeapStyle,
+             StackSlotData, StackSlotKind};
+    use ir::immediates::Imm64;
+    use entity_map::EntityRef;
     use ir::instructions::{InstructionData, Opcode};
     use ir::types;
 
@@ -207,4 +389,73 @@ mod tests {
         let verifier = Verifier::new(&func);
         assert_err_with_msg!(verifier.run(), "instruction format");
     }
+
+    #[test]
+    fn bad_heap() {
+        let mut func = Function::new();
+        let gv0 = func.global_vars.push(GlobalVarData::Sym { name: FunctionName::new("base") });
+        func.heaps.push(HeapData {
+            base: HeapBase::GlobalVar(gv0),
+            guard_size: Imm64::new(0),
+            style: HeapStyle::Dynamic { bound_gv: GlobalVar::new(1) },
+        });
+        let verifier = Verifier::new(&func);
+        assert_err_with_msg!(verifier.run(), "invalid global variable gv1");
+    }
+
+    #[test]
+    fn missing_vmctx() {
+        let mut func = Function::new();
+        func.global_vars.push(GlobalVarData::VmCtx { offset: 0.into() });
+        let verifier = Verifier::new(&func);
+        assert_err_with_msg!(verifier.run(), "requires a vmctx argument");
+    }
+
+    #[test]
+    fn deref_cycle() {
+        let mut func = Function::new();
+        let gv0 = func.global_vars.push(GlobalVarData::Sym { name: FunctionName::new("base") });
+        let gv1 = func.global_vars.push(GlobalVarData::Deref {
+            base: gv0,
+            offset: 0.into(),
+        });
+        assert_eq!(Verifier::new(&func).run(), Ok(()));
+
+        func.global_vars[gv0] = GlobalVarData::Deref {
+            base: gv1,
+            offset: 8.into(),
+        };
+        assert_err_with_msg!(Verifier::new(&func).run(), "deref cycle");
+        func.global_vars[gv0] = GlobalVarData::Deref {
+            base: gv0,
+            offset: 8.into(),
+        };
+        assert_err_with_msg!(Verifier::new(&func).run(), "deref cycle");
+    }
+
+    #[test]
+    fn stack_bounds() {
+        let mut func = Function::new();
+        let ss0 = func.stack_slots.push(StackSlotData::new(StackSlotKind::Local, 8));
+        let ebb0 = func.dfg.make_ebb();
+        func.layout.append_ebb(ebb0);
+        let load = func.dfg.make_inst(InstructionData::StackLoad {
+            opcode: Opcode::StackLoad,
+            ty: types::I32,
+            stack_slot: ss0,
+            offset: 4.into(),
+        });
+        func.layout.append_inst(load, ebb0);
+        let trap = func.dfg.make_inst(InstructionData::Nullary {
+            opcode: Opcode::Trap,
+            ty: types::VOID,
+        });
+        func.layout.append_inst(trap, ebb0);
+        assert_eq!(Verifier::new(&func).run(), Ok(()));
+
+        if let InstructionData::StackLoad { ref mut offset, .. } = func.dfg[load] {
+            *offset = 6.into();
+        }
+        assert_err_with_msg!(Verifier::new(&func).run(), "out of bounds");
+    }
 }
@@ -5,16 +5,19 @@
 //! `cretonne-reader` crate.
 
 use ir::{Function, Ebb, Inst, Value, Type};
-use isa::TargetIsa;
+use isa::{TargetIsa, RegInfo};
 use std::fmt::{Result, Error, Write};
 use std::result;
 
 /// Write `func` to `w` as equivalent text.
 /// Use `isa` to emit ISA-dependent annotations.
 pub fn write_function(w: &mut Write, func: &Function, isa: Option<&TargetIsa>) -> Result {
-    try!(write_spec(w, func));
+    let regs = isa.map(TargetIsa::register_info);
+    let regs = regs.as_ref();
+
+    try!(write_spec(w, func, regs));
     try!(writeln!(w, " {{"));
-    let mut any = try!(write_preamble(w, func));
+    let mut any = try!(write_preamble(w, func, regs));
     for ebb in &func.layout {
         if any {
             try!(writeln!(w, ""));
@@ -31,11 +34,14 @@ pub fn write_function(w: &mut Write, func: &Function, isa: Option<&TargetIsa>) -
 //
 // ====--------------------------------------------------------------------------------------====//
 
-fn write_spec(w: &mut Write, func: &Function) -> Result {
-    write!(w, "function {}{}", func.name, func.own_signature())
+fn write_spec(w: &mut Write, func: &Function, regs: Option<&RegInfo>) -> Result {
+    write!(w, "function {}{}", func.name, func.own_signature().display(regs))
 }
 
-fn write_preamble(w: &mut Write, func: &Function) -> result::Result<bool, Error> {
+fn write_preamble(w: &mut Write,
+                  func: &Function,
+                  regs: Option<&RegInfo>)
+                  -> result::Result<bool, Error> {
     let mut any = false;
 
     for ss in func.stack_slots.keys() {
@@ -46,7 +52,10 @@ fn write_preamble(w: &mut Write, func: &Function) -> result::Result<bool, Error>
     // Write out all signatures before functions since function decls can refer to signatures.
     for sig in func.dfg.signatures.keys() {
         any = true;
-        try!(writeln!(w, "    {} = signature{}", sig, func.dfg.signatures[sig]));
+        try!(writeln!(w,
+                      "    {} = signature{}",
+                      sig,
+                      func.dfg.signatures[sig].display(regs)));
     }
 
     for fnref in func.dfg.ext_funcs.keys() {
@@ -54,6 +63,16 @@ fn write_preamble(w: &mut Write, func: &Function) -> result::Result<bool, Error>
         try!(writeln!(w, "    {} = {}", fnref, func.dfg.ext_funcs[fnref]));
     }
 
+    for gv in func.global_vars.keys() {
+        any = true;
+        try!(writeln!(w, "    {} = {}", gv, func.global_vars[gv]));
+    }
+
+    for heap in func.heaps.keys() {
+        any = true;
+        try!(writeln!(w, "    {} = {}", heap, func.heaps[heap]));
+    }
+
     for jt in func.jump_tables.keys() {
         any = true;
         try!(writeln!(w, "    {} = {}", jt, func.jump_tables[jt]));
@@ -68,11 +87,19 @@ fn write_preamble(w: &mut Write, func: &Function) -> result::Result<bool, Error>
 //
 // ====--------------------------------------------------------------------------------------====//
 
-pub fn write_arg(w: &mut Write, func: &Function, arg: Value) -> Result {
-    write!(w, "{}: {}", arg, func.dfg.value_type(arg))
+pub fn write_arg(w: &mut Write, func: &Function, regs: Option<&RegInfo>, arg: Value) -> Result {
+    try!(write!(w, "{}: {}", arg, func.dfg.value_type(arg)));
+    match func.locations.get(arg) {
+        Some(&loc) if loc.is_assigned() => write!(w, " [{}]", loc.display(regs)),
+        _ => Ok(()),
+    }
 }
 
-pub fn write_ebb_header(w: &mut Write, func: &Function, ebb: Ebb) -> Result {
+pub fn write_ebb_header(w: &mut Write,
+                        func: &Function,
+                        isa: Option<&TargetIsa>,
+                        ebb: Ebb)
+                        -> Result {
     // Write out the basic block header, outdented:
     //
     //    ebb1:
@@ -85,24 +112,27 @@ pub fn write_ebb_header(w: &mut Write, func: &Function, ebb: Ebb) -> Result {
         try!(write!(w, "                    "));
     }
 
+    let regs = isa.map(TargetIsa::register_info);
+    let regs = regs.as_ref();
+
     let mut args = func.dfg.ebb_args(ebb);
     match args.next() {
         None => return writeln!(w, "{}:", ebb),
         Some(arg) => {
             try!(write!(w, "{}(", ebb));
-            try!(write_arg(w, func, arg));
+            try!(write_arg(w, func, regs, arg));
         }
     }
     // Remaining args.
     for arg in args {
         try!(write!(w, ", "));
-        try!(write_arg(w, func, arg));
+        try!(write_arg(w, func, regs, arg));
     }
     writeln!(w, "):")
 }
 
 pub fn write_ebb(w: &mut Write, func: &Function, isa: Option<&TargetIsa>, ebb: Ebb) -> Result {
-    try!(write_ebb_header(w, func, ebb));
+    try!(write_ebb_header(w, func, isa, ebb));
     for inst in func.layout.ebb_insts(ebb) {
         try!(write_instruction(w, func, isa, inst));
     }
@@ -154,10 +184,19 @@ fn write_instruction(w: &mut Write,
     if let Some(enc) = func.encodings.get(inst).cloned() {
         let mut s = String::with_capacity(16);
         if let Some(isa) = isa {
-            try!(write!(s, "[{}]", isa.display_enc(enc)));
+            try!(write!(s, "[{}", isa.display_enc(enc)));
         } else {
-            try!(write!(s, "[{}]", enc));
+            try!(write!(s, "[{}", enc));
+        }
+        // Write value locations, if we have them.
+        if !func.locations.is_empty() {
+            let regs = isa.map(TargetIsa::register_info);
+            for r in func.dfg.inst_results(inst) {
+                let loc = func.locations.get(r).cloned().unwrap_or_default();
+                try!(write!(s, ",{}", loc.display(regs.as_ref())));
+            }
         }
+        try!(write!(s, "]"));
         // Align instruction following ISA annotation to col 24.
         try!(write!(w, "{:23} ", s));
     } else {
@@ -207,8 +246,30 @@ fn write_instruction(w: &mut Write,
         ExtractLane { lane, arg, .. } => writeln!(w, " {}, {}", arg, lane),
         IntCompare { cond, args, .. } => writeln!(w, " {}, {}, {}", cond, args[0], args[1]),
         FloatCompare { cond, args, .. } => writeln!(w, " {}, {}, {}", cond, args[0], args[1]),
+        Load { flags, arg, offset, .. } => writeln!(w, "{} {}{}", flags, arg, offset),
+        Store { flags, args, offset, .. } => {
+            writeln!(w, "{} {}, {}{}", flags, args[0], args[1], offset)
+        }
+        AtomicRmw { op, ordering, args, .. } => {
+            writeln!(w, " {} {} {}, {}", op, ordering, args[0], args[1])
+        }
+        AtomicCas { ordering, args, .. } => {
+            writeln!(w, " {} {}, {}, {}", ordering, args[0], args[1], args[2])
+        }
+        AtomicLoad { ordering, arg, .. } => writeln!(w, " {} {}", ordering, arg),
+        AtomicStore { ordering, args, .. } => {
+            writeln!(w, " {} {}, {}", ordering, args[0], args[1])
+        }
+        Fence { ordering, .. } => writeln!(w, " {}", ordering),
+        StackLoad { stack_slot, offset, .. } => writeln!(w, " {}{}", stack_slot, offset),
+        StackStore { arg, stack_slot, offset, .. } => {
+            writeln!(w, " {}, {}{}", arg, stack_slot, offset)
+        }
+        UnaryGlobalVar { global_var, .. } => writeln!(w, " {}", global_var),
+        HeapAddr { heap, arg, imm, .. } => writeln!(w, " {}, {}, {}", heap, arg, imm),
         Jump { ref data, .. } => writeln!(w, " {}", data),
         Branch { ref data, .. } => writeln!(w, " {}", data),
+        BranchIcmp { ref data, .. } => writeln!(w, " {}", data),
         BranchTable { arg, table, .. } => writeln!(w, " {}, {}", arg, table),
         Call { ref data, .. } => writeln!(w, " {}({})", data.func_ref, data.varargs),
         IndirectCall { ref data, .. } => {
@@ -226,7 +287,7 @@ fn write_instruction(w: &mut Write,
 
 #[cfg(test)]
 mod tests {
-    use ir::{Function, FunctionName, StackSlotData};
+    use ir::{Function, FunctionName, StackSlotData, StackSlotKind};
     use ir::types;
 
     #[test]
@@ -237,21 +298,21 @@ mod tests {
         f.name = FunctionName::new("foo".to_string());
         assert_eq!(f.to_string(), "function foo() {\n}\n");
 
-        f.stack_slots.push(StackSlotData::new(4));
+        f.stack_slots.push(StackSlotData::new(StackSlotKind::Local, 4));
         assert_eq!(f.to_string(),
-                   "function foo() {\n    ss0 = stack_slot 4\n}\n");
+                   "function foo() {\n    ss0 = local 4\n}\n");
 
         let ebb = f.dfg.make_ebb();
         f.layout.append_ebb(ebb);
         assert_eq!(f.to_string(),
-                   "function foo() {\n    ss0 = stack_slot 4\n\nebb0:\n}\n");
+                   "function foo() {\n    ss0 = local 4\n\nebb0:\n}\n");
 
         f.dfg.append_ebb_arg(ebb, types::I8);
         assert_eq!(f.to_string(),
-                   "function foo() {\n    ss0 = stack_slot 4\n\nebb0(vx0: i8):\n}\n");
+                   "function foo() {\n    ss0 = local 4\n\nebb0(vx0: i8):\n}\n");
 
         f.dfg.append_ebb_arg(ebb, types::F32.by(4).unwrap());
         assert_eq!(f.to_string(),
-                   "function foo() {\n    ss0 = stack_slot 4\n\nebb0(vx0: i8, vx1: f32x4):\n}\n");
+                   "function foo() {\n    ss0 = local 4\n\nebb0(vx0: i8, vx1: f32x4):\n}\n");
     }
 }
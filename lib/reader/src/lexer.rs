@@ -22,10 +22,13 @@ pub enum Token<'a> {
     RPar, // ')'
     LBrace, // '{'
     RBrace, // '}'
+    LBracket, // '['
+    RBracket, // ']'
     Comma, // ','
     Dot, // '.'
     Colon, // ':'
     Equal, // '='
+    Minus, // '-'
     Arrow, // '->'
     Float(&'a str), // Floating point immediate
     Integer(&'a str), // Integer immediate
@@ -33,9 +36,13 @@ pub enum Token<'a> {
     Value(Value), // v12, vx7
     Ebb(Ebb), // ebb3
     StackSlot(u32), // ss3
+    GlobalVar(u32), // gv3
+    Heap(u32), // heap2
     JumpTable(u32), // jt2
     FuncRef(u32), // fn2
     SigRef(u32), // sig2
+    Name(&'a str), // "name" or %name
+    HexSequence(&'a str), // #89AF
     Identifier(&'a str), // Unrecognized identifier (opcode, enumerator, ...)
 }
 
@@ -199,12 +206,70 @@ impl<'a> Lexer<'a> {
         return token(Token::Comment(text), loc);
     }
 
+    // Scan a quoted name token.
+    //
+    // The quotes are not included in the token text. Names can't contain quotes or span multiple
+    // lines.
+    fn scan_name(&mut self) -> Result<LocatedToken<'a>, LocatedError> {
+        let loc = self.loc();
+        let begin = self.pos + 1;
+        loop {
+            match self.next_ch() {
+                Some('"') => break,
+                None | Some('\n') => return error(Error::InvalidChar, loc),
+                _ => {}
+            }
+        }
+        let end = self.pos;
+        self.next_ch();
+        token(Token::Name(&self.source[begin..end]), loc)
+    }
+
+    // Scan a name token prefixed with '%', like a register name.
+    //
+    // The '%' is not included in the token text. The name consists of alphanumeric characters and
+    // underscores.
+    fn scan_percent_name(&mut self) -> Result<LocatedToken<'a>, LocatedError> {
+        let loc = self.loc();
+        let begin = self.pos + 1;
+        loop {
+            match self.next_ch() {
+                Some(ch) if ch.is_alphanumeric() || ch == '_' => {}
+                _ => break,
+            }
+        }
+        let end = self.pos;
+        if begin == end {
+            return error(Error::InvalidChar, loc);
+        }
+        token(Token::Name(&self.source[begin..end]), loc)
+    }
+
+    // Scan a hexadecimal sequence like `#80a9`.
+    //
+    // The '#' is not included in the token text. At least one hexadecimal digit is required.
+    fn scan_hex_sequence(&mut self) -> Result<LocatedToken<'a>, LocatedError> {
+        let loc = self.loc();
+        let begin = self.pos + 1;
+        while let Some(ch) = self.next_ch() {
+            if !ch.is_ascii_hexdigit() {
+                break;
+            }
+        }
+        let end = self.pos;
+        if begin == end {
+            return error(Error::InvalidChar, loc);
+        }
+        token(Token::HexSequence(&self.source[begin..end]), loc)
+    }
+
     // Scan a number token which can represent either an integer or floating point number.
     //
     // Accept the following forms:
     //
     // - `10`: Integer
     // - `-10`: Integer
+    // - `+10`: Integer
     // - `0xff_00`: Integer
     // - `0.0`: Float
     // - `0x1.f`: Float
@@ -220,7 +285,7 @@ impl<'a> Lexer<'a> {
         let mut is_float = false;
 
         // Skip a leading sign.
-        if self.lookahead == Some('-') {
+        if self.lookahead == Some('-') || self.lookahead == Some('+') {
             self.next_ch();
         }
 
@@ -286,6 +351,8 @@ impl<'a> Lexer<'a> {
             "vx" => Value::table_with_number(number).map(|v| Token::Value(v)),
             "ebb" => Ebb::with_number(number).map(|ebb| Token::Ebb(ebb)),
             "ss" => Some(Token::StackSlot(number)),
+            "gv" => Some(Token::GlobalVar(number)),
+            "heap" => Some(Token::Heap(number)),
             "jt" => Some(Token::JumpTable(number)),
             "fn" => Some(Token::FuncRef(number)),
             "sig" => Some(Token::SigRef(number)),
@@ -339,6 +406,8 @@ impl<'a> Lexer<'a> {
                 Some(')') => Some(self.scan_char(Token::RPar)),
                 Some('{') => Some(self.scan_char(Token::LBrace)),
                 Some('}') => Some(self.scan_char(Token::RBrace)),
+                Some('[') => Some(self.scan_char(Token::LBracket)),
+                Some(']') => Some(self.scan_char(Token::RBracket)),
                 Some(',') => Some(self.scan_char(Token::Comma)),
                 Some('.') => Some(self.scan_char(Token::Dot)),
                 Some(':') => Some(self.scan_char(Token::Colon)),
@@ -346,10 +415,16 @@ impl<'a> Lexer<'a> {
                 Some('-') => {
                     if self.looking_at("->") {
                         Some(self.scan_chars(2, Token::Arrow))
-                    } else {
+                    } else if self.source[self.pos + 1..].starts_with(char::is_alphanumeric) {
                         Some(self.scan_number())
+                    } else {
+                        Some(self.scan_char(Token::Minus))
                     }
                 }
+                Some('+') => Some(self.scan_number()),
+                Some('"') => Some(self.scan_name()),
+                Some('%') => Some(self.scan_percent_name()),
+                Some('#') => Some(self.scan_hex_sequence()),
                 Some(ch) if ch.is_digit(10) => Some(self.scan_number()),
                 Some(ch) if ch.is_alphabetic() => Some(self.scan_word()),
                 Some(ch) if ch.is_whitespace() => {
@@ -452,7 +527,7 @@ mod tests {
 
     #[test]
     fn lex_numbers() {
-        let mut lex = Lexer::new(" 0 2_000 -1,0xf -0x0 0.0 0x0.4p-34");
+        let mut lex = Lexer::new(" 0 2_000 -1,0xf -0x0 0.0 0x0.4p-34 +5");
         assert_eq!(lex.next(), token(Token::Integer("0"), 1));
         assert_eq!(lex.next(), token(Token::Integer("2_000"), 1));
         assert_eq!(lex.next(), token(Token::Integer("-1"), 1));
@@ -461,6 +536,7 @@ mod tests {
         assert_eq!(lex.next(), token(Token::Integer("-0x0"), 1));
         assert_eq!(lex.next(), token(Token::Float("0.0"), 1));
         assert_eq!(lex.next(), token(Token::Float("0x0.4p-34"), 1));
+        assert_eq!(lex.next(), token(Token::Integer("+5"), 1));
         assert_eq!(lex.next(), None);
     }
 
@@ -486,4 +562,44 @@ mod tests {
         assert_eq!(lex.next(), token(Token::Identifier("f32x5"), 1));
         assert_eq!(lex.next(), None);
     }
+
+    #[test]
+    fn lex_names() {
+        let mut lex = Lexer::new("\"foo\" \"\" \"a b\"x \"bad\n\"");
+        assert_eq!(lex.next(), token(Token::Name("foo"), 1));
+        assert_eq!(lex.next(), token(Token::Name(""), 1));
+        assert_eq!(lex.next(), token(Token::Name("a b"), 1));
+        assert_eq!(lex.next(), token(Token::Identifier("x"), 1));
+        assert_eq!(lex.next(), error(Error::InvalidChar, 1));
+
+        let mut lex = Lexer::new("[%x10] %0,% x");
+        assert_eq!(lex.next(), token(Token::LBracket, 1));
+        assert_eq!(lex.next(), token(Token::Name("x10"), 1));
+        assert_eq!(lex.next(), token(Token::RBracket, 1));
+        assert_eq!(lex.next(), token(Token::Name("0"), 1));
+        assert_eq!(lex.next(), token(Token::Comma, 1));
+        assert_eq!(lex.next(), error(Error::InvalidChar, 1));
+        assert_eq!(lex.next(), token(Token::Identifier("x"), 1));
+    }
+
+    #[test]
+    fn lex_annotations() {
+        let mut lex = Lexer::new("[R#0c,-] [-] - -> -1 #A0f # x");
+        assert_eq!(lex.next(), token(Token::LBracket, 1));
+        assert_eq!(lex.next(), token(Token::Identifier("R"), 1));
+        assert_eq!(lex.next(), token(Token::HexSequence("0c"), 1));
+        assert_eq!(lex.next(), token(Token::Comma, 1));
+        assert_eq!(lex.next(), token(Token::Minus, 1));
+        assert_eq!(lex.next(), token(Token::RBracket, 1));
+        assert_eq!(lex.next(), token(Token::LBracket, 1));
+        assert_eq!(lex.next(), token(Token::Minus, 1));
+        assert_eq!(lex.next(), token(Token::RBracket, 1));
+        assert_eq!(lex.next(), token(Token::Minus, 1));
+        assert_eq!(lex.next(), token(Token::Arrow, 1));
+        assert_eq!(lex.next(), token(Token::Integer("-1"), 1));
+        assert_eq!(lex.next(), token(Token::HexSequence("A0f"), 1));
+        assert_eq!(lex.next(), error(Error::InvalidChar, 1));
+        assert_eq!(lex.next(), token(Token::Identifier("x"), 1));
+        assert_eq!(lex.next(), None);
+    }
 }
@@ -6,18 +6,19 @@
 // ====--------------------------------------------------------------------------------------====//
 
 use std::str::FromStr;
-use std::u32;
+use std::{i32, u32};
 use std::mem;
-use cretonne::ir::{Function, Ebb, Opcode, Value, Type, FunctionName, StackSlotData, JumpTable,
-                   JumpTableData, Signature, ArgumentType, ArgumentExtension, ExtFuncData, SigRef,
-                   FuncRef};
+use cretonne::ir::{Function, Ebb, Opcode, Value, Type, FunctionName, StackSlot, StackSlotData,
+                   JumpTable, JumpTableData, Signature, ArgumentType, ArgumentExtension,
+                   ExtFuncData, SigRef, FuncRef, GlobalVar, GlobalVarData, Heap, HeapData,
+                   HeapBase, HeapStyle, ArgumentPurpose, ArgumentLoc, ValueLoc, MemFlags};
 use cretonne::ir::types::VOID;
-use cretonne::ir::immediates::{Imm64, Ieee32, Ieee64};
+use cretonne::ir::immediates::{Imm64, Uimm32, Offset32, Ieee32, Ieee64, ImmVector};
 use cretonne::ir::entities::{AnyEntity, NO_EBB, NO_INST, NO_VALUE};
 use cretonne::ir::instructions::{InstructionFormat, InstructionData, VariableArgs,
-                                 TernaryOverflowData, JumpData, BranchData, CallData,
-                                 IndirectCallData, ReturnData};
-use cretonne::isa;
+                                 TernaryOverflowData, JumpData, BranchData, BranchIcmpData,
+                                 CallData, IndirectCallData, ReturnData, UnaryImmVectorData};
+use cretonne::isa::{self, TargetIsa, Encoding, RegUnit};
 use cretonne::settings;
 use testfile::{TestFile, Details, Comment};
 use error::{Location, Error, Result};
@@ -38,10 +39,14 @@ pub fn parse_functions(text: &str) -> Result<Vec<Function>> {
 /// The returned `TestFile` contains direct references to substrings of `text`.
 pub fn parse_test<'a>(text: &'a str) -> Result<TestFile<'a>> {
     let mut parser = Parser::new(text);
+    let commands = parser.parse_test_commands();
+    let isa_spec = try!(parser.parse_isa_specs());
+    let functions = try!(parser.parse_function_list(isa_spec.unique_isa()));
+
     Ok(TestFile {
-        commands: parser.parse_test_commands(),
-        isa_spec: try!(parser.parse_isa_specs()),
-        functions: try!(parser.parse_function_list()),
+        commands: commands,
+        isa_spec: isa_spec,
+        functions: functions,
     })
 }
 
@@ -68,16 +73,22 @@ pub struct Parser<'a> {
 //
 // Many entities like values, stack slots, and function signatures are referenced in the `.cton`
 // file by number. We need to map these numbers to real references.
-struct Context {
+struct Context<'a> {
     function: Function,
     map: SourceMap,
+
+    // The target ISA used for parsing ISA-specific details like register names. This is only
+    // `Some` if the test file contains exactly one `isa` command. A file with multiple `isa`
+    // commands is valid, but then we can't know which ISA the register names refer to.
+    unique_isa: Option<&'a TargetIsa>,
 }
 
-impl Context {
-    fn new(f: Function) -> Context {
+impl<'a> Context<'a> {
+    fn new(f: Function, unique_isa: Option<&'a TargetIsa>) -> Context<'a> {
         Context {
             function: f,
             map: SourceMap::new(),
+            unique_isa: unique_isa,
         }
     }
 
@@ -86,6 +97,40 @@ impl Context {
         self.map.def_ss(number, self.function.stack_slots.push(data), loc)
     }
 
+    // Resolve a reference to a stack slot.
+    fn get_ss(&self, number: u32, loc: &Location) -> Result<StackSlot> {
+        match self.map.get_ss(number) {
+            Some(ss) => Ok(ss),
+            None => err!(loc, "undefined stack slot ss{}", number),
+        }
+    }
+
+    // Allocate a new global variable and add a mapping number -> GlobalVar.
+    fn add_gv(&mut self, number: u32, data: GlobalVarData, loc: &Location) -> Result<()> {
+        self.map.def_gv(number, self.function.global_vars.push(data), loc)
+    }
+
+    // Resolve a reference to a global variable.
+    fn get_gv(&self, number: u32, loc: &Location) -> Result<GlobalVar> {
+        match self.map.get_gv(number) {
+            Some(gv) => Ok(gv),
+            None => err!(loc, "undefined global variable gv{}", number),
+        }
+    }
+
+    // Allocate a new heap and add a mapping number -> Heap.
+    fn add_heap(&mut self, number: u32, data: HeapData, loc: &Location) -> Result<()> {
+        self.map.def_heap(number, self.function.heaps.push(data), loc)
+    }
+
+    // Resolve a reference to a heap.
+    fn get_heap(&self, number: u32, loc: &Location) -> Result<Heap> {
+        match self.map.get_heap(number) {
+            Some(heap) => Ok(heap),
+            None => err!(loc, "undefined heap heap{}", number),
+        }
+    }
+
     // Allocate a new signature and add a mapping number -> SigRef.
     fn add_sig(&mut self, number: u32, data: Signature, loc: &Location) -> Result<()> {
         self.map.def_sig(number, self.function.dfg.signatures.push(data), loc)
@@ -144,13 +189,20 @@ impl Context {
                     InstructionData::UnaryImm { .. } |
                     InstructionData::UnaryIeee32 { .. } |
                     InstructionData::UnaryIeee64 { .. } |
-                    InstructionData::UnaryImmVector { .. } => {}
+                    InstructionData::UnaryImmVector { .. } |
+                    InstructionData::StackLoad { .. } |
+                    InstructionData::Fence { .. } |
+                    InstructionData::UnaryGlobalVar { .. } => {}
 
                     InstructionData::Unary { ref mut arg, .. } |
                     InstructionData::UnarySplit { ref mut arg, .. } |
                     InstructionData::BinaryImm { ref mut arg, .. } |
                     InstructionData::BinaryImmRev { ref mut arg, .. } |
                     InstructionData::ExtractLane { ref mut arg, .. } |
+                    InstructionData::Load { ref mut arg, .. } |
+                    InstructionData::AtomicLoad { ref mut arg, .. } |
+                    InstructionData::StackStore { ref mut arg, .. } |
+                    InstructionData::HeapAddr { ref mut arg, .. } |
                     InstructionData::BranchTable { ref mut arg, .. } => {
                         try!(self.map.rewrite_value(arg, loc));
                     }
@@ -159,11 +211,15 @@ impl Context {
                     InstructionData::BinaryOverflow { ref mut args, .. } |
                     InstructionData::InsertLane { ref mut args, .. } |
                     InstructionData::IntCompare { ref mut args, .. } |
-                    InstructionData::FloatCompare { ref mut args, .. } => {
+                    InstructionData::FloatCompare { ref mut args, .. } |
+                    InstructionData::Store { ref mut args, .. } |
+                    InstructionData::AtomicRmw { ref mut args, .. } |
+                    InstructionData::AtomicStore { ref mut args, .. } => {
                         try!(self.map.rewrite_values(args, loc));
                     }
 
-                    InstructionData::Ternary { ref mut args, .. } => {
+                    InstructionData::Ternary { ref mut args, .. } |
+                    InstructionData::AtomicCas { ref mut args, .. } => {
                         try!(self.map.rewrite_values(args, loc));
                     }
 
@@ -182,6 +238,12 @@ impl Context {
                         try!(self.map.rewrite_values(&mut data.varargs, loc));
                     }
 
+                    InstructionData::BranchIcmp { ref mut data, .. } => {
+                        try!(self.map.rewrite_values(&mut data.args, loc));
+                        try!(self.map.rewrite_ebb(&mut data.destination, loc));
+                        try!(self.map.rewrite_values(&mut data.varargs, loc));
+                    }
+
                     InstructionData::Call { ref mut data, .. } => {
                         try!(self.map.rewrite_values(&mut data.varargs, loc));
                     }
@@ -304,7 +366,7 @@ impl<'a> Parser<'a> {
     }
 
     // Match and consume a specific identifier string.
-    // Used for pseudo-keywords like "stack_slot" that only appear in certain contexts.
+    // Used for pseudo-keywords like "bound" that only appear in certain contexts.
     fn match_identifier(&mut self, want: &'static str, err_msg: &str) -> Result<Token<'a>> {
         if self.token() == Some(Token::Identifier(want)) {
             Ok(self.consume())
@@ -333,6 +395,26 @@ impl<'a> Parser<'a> {
         }
     }
 
+    // Match and consume a global variable reference.
+    fn match_gv(&mut self, err_msg: &str) -> Result<u32> {
+        if let Some(Token::GlobalVar(gv)) = self.token() {
+            self.consume();
+            Ok(gv)
+        } else {
+            err!(self.loc, err_msg)
+        }
+    }
+
+    // Match and consume a heap reference.
+    fn match_heap(&mut self, err_msg: &str) -> Result<u32> {
+        if let Some(Token::Heap(heap)) = self.token() {
+            self.consume();
+            Ok(heap)
+        } else {
+            err!(self.loc, err_msg)
+        }
+    }
+
     // Match and consume a function reference.
     fn match_fn(&mut self, err_msg: &str) -> Result<u32> {
         if let Some(Token::FuncRef(fnref)) = self.token() {
@@ -416,6 +498,35 @@ impl<'a> Parser<'a> {
         }
     }
 
+    // Match and consume a u32 immediate.
+    // This is used for memory access sizes.
+    fn match_uimm32(&mut self, err_msg: &str) -> Result<Uimm32> {
+        if let Some(Token::Integer(text)) = self.token() {
+            self.consume();
+            // Lexer just gives us raw text that looks like an integer.
+            // Parse it as a u32 to check for overflow and other issues.
+            text.parse().map_err(|_| self.error("expected u32 decimal immediate"))
+        } else {
+            err!(self.loc, err_msg)
+        }
+    }
+
+    // Match and consume an optional offset32 immediate.
+    //
+    // Note that this will match an empty string as an empty offset, and that if an offset is
+    // present, it must contain a sign.
+    fn optional_offset32(&mut self) -> Result<Offset32> {
+        if let Some(Token::Integer(text)) = self.token() {
+            self.consume();
+            // Lexer just gives us raw text that looks like an integer.
+            // Parse it as an `Offset32` to check for overflow and other issues.
+            text.parse().map_err(|e| self.error(e))
+        } else {
+            // An offset32 operand can be absent.
+            Ok(Offset32::new(0))
+        }
+    }
+
     // Match and consume an Ieee32 immediate.
     fn match_ieee32(&mut self, err_msg: &str) -> Result<Ieee32> {
         if let Some(Token::Float(text)) = self.token() {
@@ -440,6 +551,26 @@ impl<'a> Parser<'a> {
         }
     }
 
+    // Match and consume an immediate SIMD vector, written as a hexadecimal byte sequence.
+    fn match_immvector(&mut self, err_msg: &str) -> Result<ImmVector> {
+        if let Some(Token::HexSequence(text)) = self.token() {
+            self.consume();
+            if text.len() % 2 != 0 {
+                return err!(self.loc, "odd number of digits in vector: #{}", text);
+            }
+            let mut bytes = Vec::with_capacity(text.len() / 2);
+            for i in 0..text.len() / 2 {
+                match u8::from_str_radix(&text[2 * i..2 * i + 2], 16) {
+                    Ok(byte) => bytes.push(byte),
+                    Err(_) => return err!(self.loc, "invalid vector immediate: #{}", text),
+                }
+            }
+            Ok(bytes)
+        } else {
+            err!(self.loc, err_msg)
+        }
+    }
+
     // Match and consume an enumerated immediate, like one of the condition codes.
     fn match_enum<T: FromStr>(&mut self, err_msg: &str) -> Result<T> {
         if let Some(Token::Identifier(text)) = self.token() {
@@ -450,6 +581,19 @@ impl<'a> Parser<'a> {
         }
     }
 
+    // Match and consume a possibly empty sequence of memory operation flags.
+    fn optional_memflags(&mut self) -> MemFlags {
+        let mut flags = MemFlags::new();
+        while let Some(Token::Identifier(text)) = self.token() {
+            if flags.set_by_name(text) {
+                self.consume();
+            } else {
+                break;
+            }
+        }
+        flags
+    }
+
     /// Parse a list of test commands.
     pub fn parse_test_commands(&mut self) -> Vec<TestCommand<'a>> {
         let mut list = Vec::new();
@@ -519,10 +663,12 @@ impl<'a> Parser<'a> {
     /// Parse a list of function definitions.
     ///
     /// This is the top-level parse function matching the whole contents of a file.
-    pub fn parse_function_list(&mut self) -> Result<Vec<(Function, Details<'a>)>> {
+    pub fn parse_function_list(&mut self,
+                               unique_isa: Option<&TargetIsa>)
+                               -> Result<Vec<(Function, Details<'a>)>> {
         let mut list = Vec::new();
         while self.token().is_some() {
-            list.push(try!(self.parse_function()));
+            list.push(try!(self.parse_function(unique_isa)));
         }
         Ok(list)
     }
@@ -531,15 +677,15 @@ impl<'a> Parser<'a> {
     //
     // function ::= * function-spec "{" preamble function-body "}"
     //
-    fn parse_function(&mut self) -> Result<(Function, Details<'a>)> {
+    fn parse_function(&mut self, unique_isa: Option<&TargetIsa>) -> Result<(Function, Details<'a>)> {
         // Begin gathering comments.
         // Make sure we don't include any comments before the `function` keyword.
         self.token();
         self.comments.clear();
         self.gather_comments(AnyEntity::Function);
 
-        let (location, name, sig) = try!(self.parse_function_spec());
-        let mut ctx = Context::new(Function::with_name_signature(name, sig));
+        let (location, name, sig) = try!(self.parse_function_spec(unique_isa));
+        let mut ctx = Context::new(Function::with_name_signature(name, sig), unique_isa);
 
         // function ::= function-spec * "{" preamble function-body "}"
         try!(self.match_token(Token::LBrace, "expected '{' before function body"));
@@ -572,7 +718,9 @@ impl<'a> Parser<'a> {
     //
     // function-spec ::= * "function" name signature
     //
-    fn parse_function_spec(&mut self) -> Result<(Location, FunctionName, Signature)> {
+    fn parse_function_spec(&mut self,
+                           unique_isa: Option<&TargetIsa>)
+                           -> Result<(Location, FunctionName, Signature)> {
         try!(self.match_identifier("function", "expected 'function'"));
         let location = self.loc;
 
@@ -580,7 +728,7 @@ impl<'a> Parser<'a> {
         let name = try!(self.parse_function_name());
 
         // function-spec ::= "function" name * signature
-        let sig = try!(self.parse_signature());
+        let sig = try!(self.parse_signature(unique_isa));
 
         Ok((location, name, sig))
     }
@@ -591,7 +739,8 @@ impl<'a> Parser<'a> {
     //
     fn parse_function_name(&mut self) -> Result<FunctionName> {
         match self.token() {
-            Some(Token::Identifier(s)) => {
+            Some(Token::Identifier(s)) |
+            Some(Token::Name(s)) => {
                 self.consume();
                 Ok(FunctionName::new(s))
             }
@@ -603,20 +752,29 @@ impl<'a> Parser<'a> {
     //
     // signature ::=  * "(" [arglist] ")" ["->" retlist] [call_conv]
     //
-    fn parse_signature(&mut self) -> Result<Signature> {
+    fn parse_signature(&mut self, unique_isa: Option<&TargetIsa>) -> Result<Signature> {
         let mut sig = Signature::new();
 
         try!(self.match_token(Token::LPar, "expected function signature: ( args... )"));
         // signature ::=  "(" * [arglist] ")" ["->" retlist] [call_conv]
         if self.token() != Some(Token::RPar) {
-            sig.argument_types = try!(self.parse_argument_list());
+            sig.argument_types = try!(self.parse_argument_list(unique_isa));
         }
         try!(self.match_token(Token::RPar, "expected ')' after function arguments"));
         if self.optional(Token::Arrow) {
-            sig.return_types = try!(self.parse_argument_list());
+            sig.return_types = try!(self.parse_argument_list(unique_isa));
         }
 
-        // TBD: calling convention.
+        // signature ::=  "(" [arglist] ")" ["->" retlist] * [call_conv]
+        if let Some(Token::Identifier(text)) = self.token() {
+            match text.parse() {
+                Ok(cc) => {
+                    self.consume();
+                    sig.call_conv = cc;
+                }
+                _ => return err!(self.loc, "unknown calling convention: {}", text),
+            }
+        }
 
         Ok(sig)
     }
@@ -625,44 +783,83 @@ impl<'a> Parser<'a> {
     //
     // arglist ::= * arg { "," arg }
     //
-    fn parse_argument_list(&mut self) -> Result<Vec<ArgumentType>> {
+    fn parse_argument_list(&mut self, unique_isa: Option<&TargetIsa>) -> Result<Vec<ArgumentType>> {
         let mut list = Vec::new();
 
         // arglist ::= * arg { "," arg }
-        list.push(try!(self.parse_argument_type()));
+        list.push(try!(self.parse_argument_type(unique_isa)));
 
         // arglist ::= arg * { "," arg }
         while self.optional(Token::Comma) {
             // arglist ::= arg { "," * arg }
-            list.push(try!(self.parse_argument_type()));
+            list.push(try!(self.parse_argument_type(unique_isa)));
         }
 
         Ok(list)
     }
 
     // Parse a single argument type with flags.
-    fn parse_argument_type(&mut self) -> Result<ArgumentType> {
-        // arg ::= * type { flag }
+    // arg ::= * type { flag } [ argumentloc ]
+    //
+    fn parse_argument_type(&mut self, unique_isa: Option<&TargetIsa>) -> Result<ArgumentType> {
+        // arg ::= * type { flag } [ argumentloc ]
         let mut arg = ArgumentType::new(try!(self.match_type("expected argument type")));
 
-        // arg ::= type * { flag }
+        // arg ::= type * { flag } [ argumentloc ]
         while let Some(Token::Identifier(s)) = self.token() {
             match s {
                 "uext" => arg.extension = ArgumentExtension::Uext,
                 "sext" => arg.extension = ArgumentExtension::Sext,
                 "inreg" => arg.inreg = true,
+                "vmctx" => arg.purpose = ArgumentPurpose::VMContext,
+                "link" => arg.purpose = ArgumentPurpose::Link,
+                "csr" => arg.purpose = ArgumentPurpose::CalleeSaved,
                 _ => break,
             }
             self.consume();
         }
 
+        // arg ::= type { flag } * [ argumentloc ]
+        arg.location = try!(self.parse_argument_location(unique_isa));
+
         Ok(arg)
     }
 
+    // Parse an optional argument location.
+    //
+    // argumentloc ::= "[" regunit | offset "]"
+    // regunit     ::= Name(%unit)
+    // offset      ::= Integer
+    //
+    // Register units are named according to the unique target ISA, like `%x10`. Without a unique
+    // ISA, they are given as register unit numbers like `%10`.
+    fn parse_argument_location(&mut self, unique_isa: Option<&TargetIsa>) -> Result<ArgumentLoc> {
+        if !self.optional(Token::LBracket) {
+            return Ok(ArgumentLoc::Unassigned);
+        }
+        let loc = match self.token() {
+            Some(Token::Name(_)) => ArgumentLoc::Reg(try!(self.match_regunit(unique_isa))),
+            Some(Token::Integer(text)) => {
+                match text.parse() {
+                    Ok(offset) => {
+                        self.consume();
+                        ArgumentLoc::Stack(offset)
+                    }
+                    Err(_) => return err!(self.loc, "invalid stack argument offset: {}", text),
+                }
+            }
+            _ => return err!(self.loc, "expected argument location in [...]"),
+        };
+        try!(self.match_token(Token::RBracket, "expected ']' after argument location"));
+        Ok(loc)
+    }
+
     // Parse the function preamble.
     //
     // preamble      ::= * { preamble-decl }
     // preamble-decl ::= * stack-slot-decl
+    //                   * global-var-decl
+    //                   * heap-decl
     //                   * function-decl
     //                   * signature-decl
     //                   * jump-table-decl
@@ -673,12 +870,25 @@ impl<'a> Parser<'a> {
             try!(match self.token() {
                 Some(Token::StackSlot(..)) => {
                     self.gather_comments(ctx.function.stack_slots.next_key());
+                    // Report duplicates at the declaration, not at the token following its
+                    // optional flags.
+                    let loc = self.loc;
                     self.parse_stack_slot_decl()
-                        .and_then(|(num, dat)| ctx.add_ss(num, dat, &self.loc))
+                        .and_then(|(num, dat)| ctx.add_ss(num, dat, &loc))
+                }
+                Some(Token::GlobalVar(..)) => {
+                    self.gather_comments(ctx.function.global_vars.next_key());
+                    self.parse_global_var_decl(ctx)
+                        .and_then(|(num, dat)| ctx.add_gv(num, dat, &self.loc))
+                }
+                Some(Token::Heap(..)) => {
+                    self.gather_comments(ctx.function.heaps.next_key());
+                    self.parse_heap_decl(ctx)
+                        .and_then(|(num, dat)| ctx.add_heap(num, dat, &self.loc))
                 }
                 Some(Token::SigRef(..)) => {
                     self.gather_comments(ctx.function.dfg.signatures.next_key());
-                    self.parse_signature_decl()
+                    self.parse_signature_decl(ctx)
                         .and_then(|(num, dat)| ctx.add_sig(num, dat, &self.loc))
                 }
                 Some(Token::FuncRef(..)) => {
@@ -699,23 +909,156 @@ impl<'a> Parser<'a> {
 
     // Parse a stack slot decl.
     //
-    // stack-slot-decl ::= * StackSlot(ss) "=" "stack_slot" Bytes {"," stack-slot-flag}
+    // stack-slot-decl ::= * StackSlot(ss) "=" stack-slot-kind Bytes {"," stack-slot-flag}
+    // stack-slot-kind ::= "local"
+    //                   | "spill"
+    //                   | "incoming_arg"
+    //                   | "outgoing_arg"
+    // stack-slot-flag ::= "align" Imm64(bytes)
+    //                   | "offset" Imm64(bytes)
     fn parse_stack_slot_decl(&mut self) -> Result<(u32, StackSlotData)> {
         let number = try!(self.match_ss("expected stack slot number: ss«n»"));
-        try!(self.match_token(Token::Equal, "expected '=' in stack_slot decl"));
-        try!(self.match_identifier("stack_slot", "expected 'stack_slot'"));
+        try!(self.match_token(Token::Equal, "expected '=' in stack slot decl"));
+        let kind = match self.token() {
+            Some(Token::Identifier(text)) => {
+                match text.parse() {
+                    Ok(kind) => kind,
+                    Err(_) => return err!(self.loc, "unknown stack slot kind '{}'", text),
+                }
+            }
+            _ => return err!(self.loc, "expected stack slot kind"),
+        };
+        self.consume();
 
-        // stack-slot-decl ::= StackSlot(ss) "=" "stack_slot" * Bytes {"," stack-slot-flag}
-        let bytes: i64 = try!(self.match_imm64("expected byte-size in stack_slot decl")).into();
+        // stack-slot-decl ::= StackSlot(ss) "=" stack-slot-kind * Bytes {"," stack-slot-flag}
+        let bytes: i64 = try!(self.match_imm64("expected byte-size in stack slot decl")).into();
         if bytes < 0 {
             return err!(self.loc, "negative stack slot size");
         }
         if bytes > u32::MAX as i64 {
             return err!(self.loc, "stack slot too large");
         }
-        let data = StackSlotData::new(bytes as u32);
+        let mut data = StackSlotData::new(kind, bytes as u32);
+
+        // stack-slot-decl ::= StackSlot(ss) "=" stack-slot-kind Bytes * {"," stack-slot-flag}
+        while self.optional(Token::Comma) {
+            match self.token() {
+                Some(Token::Identifier("align")) => {
+                    self.consume();
+                    let align: i64 = try!(self.match_imm64("expected stack slot alignment"))
+                        .into();
+                    if align <= 0 || align > u32::MAX as i64 || align & (align - 1) != 0 {
+                        return err!(self.loc, "stack slot alignment must be a power of two");
+                    }
+                    data.align = Some(align as u32);
+                }
+                Some(Token::Identifier("offset")) => {
+                    self.consume();
+                    let offset: i64 = try!(self.match_imm64("expected stack slot offset"))
+                        .into();
+                    if offset < i32::MIN as i64 || offset > i32::MAX as i64 {
+                        return err!(self.loc, "stack slot offset out of range");
+                    }
+                    data.offset = Some(offset as i32);
+                }
+                _ => return err!(self.loc, "expected 'align' or 'offset' stack slot flag"),
+            }
+        }
 
-        // TBD: stack-slot-decl ::= StackSlot(ss) "=" "stack_slot" Bytes * {"," stack-slot-flag}
+        Ok((number, data))
+    }
+
+    // Parse a global variable decl.
+    //
+    // global-var-decl ::= * GlobalVar(gv) "=" global-var-desc
+    // global-var-desc ::= "vmctx" offset32
+    //                   | "deref" "(" GlobalVar(base) ")" offset32
+    //                   | "globalsym" name
+    //
+    // The base global variable of a `deref` must be declared first.
+    //
+    fn parse_global_var_decl(&mut self, ctx: &Context) -> Result<(u32, GlobalVarData)> {
+        let number = try!(self.match_gv("expected global variable number: gv«n»"));
+        try!(self.match_token(Token::Equal, "expected '=' in global variable decl"));
+
+        let data = match self.token() {
+            Some(Token::Identifier("vmctx")) => {
+                self.consume();
+                let offset = try!(self.optional_offset32());
+                GlobalVarData::VmCtx { offset: offset }
+            }
+            Some(Token::Identifier("deref")) => {
+                self.consume();
+                try!(self.match_token(Token::LPar, "expected '(' in 'deref' global variable decl"));
+                let base = try!(self.match_gv("expected global variable: gv«n»")
+                    .and_then(|num| ctx.get_gv(num, &self.loc)));
+                try!(self.match_token(Token::RPar, "expected ')' in 'deref' global variable decl"));
+                let offset = try!(self.optional_offset32());
+                GlobalVarData::Deref {
+                    base: base,
+                    offset: offset,
+                }
+            }
+            Some(Token::Identifier("globalsym")) => {
+                self.consume();
+                let name = try!(self.parse_function_name());
+                GlobalVarData::Sym { name: name }
+            }
+            _ => return err!(self.loc, "expected global variable kind"),
+        };
+
+        Ok((number, data))
+    }
+
+    // Parse a heap decl.
+    //
+    // heap-decl ::= * Heap(heap) "=" heap-desc
+    // heap-desc ::= heap-style heap-base "," "bound" heap-bound "," "guard" Imm64(bytes)
+    // heap-style ::= "static" | "dynamic"
+    // heap-base ::= GlobalVar(base)
+    // heap-bound ::= Imm64(bytes) | GlobalVar(bound)
+    //
+    // Static heaps have a constant bound, dynamic heaps load the bound from a global variable.
+    // The global variables must be declared before the heap.
+    //
+    fn parse_heap_decl(&mut self, ctx: &Context) -> Result<(u32, HeapData)> {
+        let number = try!(self.match_heap("expected heap number: heap«n»"));
+        try!(self.match_token(Token::Equal, "expected '=' in heap decl"));
+
+        // heap-desc ::= * heap-style heap-base "," "bound" heap-bound "," "guard" Imm64(bytes)
+        let dynamic = match self.token() {
+            Some(Token::Identifier("static")) => false,
+            Some(Token::Identifier("dynamic")) => true,
+            _ => return err!(self.loc, "expected 'static' or 'dynamic' heap style"),
+        };
+        self.consume();
+
+        // heap-desc ::= heap-style * heap-base "," "bound" heap-bound "," "guard" Imm64(bytes)
+        let base = try!(self.match_gv("expected heap base global variable: gv«n»")
+            .and_then(|num| ctx.get_gv(num, &self.loc)));
+
+        // heap-desc ::= heap-style heap-base * "," "bound" heap-bound "," "guard" Imm64(bytes)
+        try!(self.match_token(Token::Comma, "expected ',' after heap base"));
+        try!(self.match_identifier("bound", "expected 'bound'"));
+        let style = if dynamic {
+            let bound_gv = try!(self.match_gv("expected dynamic heap bound: gv«n»")
+                .and_then(|num| ctx.get_gv(num, &self.loc)));
+            HeapStyle::Dynamic { bound_gv: bound_gv }
+        } else {
+            let bound = try!(self.match_imm64("expected static heap bound in bytes"));
+            HeapStyle::Static { bound: bound }
+        };
+
+        // heap-desc ::= heap-style heap-base "," "bound" heap-bound * "," "guard" Imm64(bytes)
+        try!(self.match_token(Token::Comma, "expected ',' after heap bound"));
+        try!(self.match_identifier("guard", "expected 'guard'"));
+        let guard_size = try!(self.match_imm64("expected guard size in bytes"));
+
+        let data = HeapData {
+            base: HeapBase::GlobalVar(base),
+            guard_size: guard_size,
+            style: style,
+        };
         Ok((number, data))
     }
 
@@ -723,11 +1066,11 @@ impl<'a> Parser<'a> {
     //
     // signature-decl ::= SigRef(sigref) "=" "signature" signature
     //
-    fn parse_signature_decl(&mut self) -> Result<(u32, Signature)> {
+    fn parse_signature_decl(&mut self, ctx: &Context) -> Result<(u32, Signature)> {
         let number = try!(self.match_sig("expected signature number: sig«n»"));
         try!(self.match_token(Token::Equal, "expected '=' in signature decl"));
         try!(self.match_identifier("signature", "expected 'signature'"));
-        let data = try!(self.parse_signature());
+        let data = try!(self.parse_signature(ctx.unique_isa));
         Ok((number, data))
     }
 
@@ -747,7 +1090,7 @@ impl<'a> Parser<'a> {
 
         let data = match self.token() {
             Some(Token::Identifier("function")) => {
-                let (loc, name, sig) = try!(self.parse_function_spec());
+                let (loc, name, sig) = try!(self.parse_function_spec(ctx.unique_isa));
                 let sigref = ctx.function.dfg.signatures.push(sig);
                 ctx.map.def_entity(sigref.into(), &loc).expect("duplicate SigRef entities created");
                 ExtFuncData {
@@ -842,6 +1185,7 @@ impl<'a> Parser<'a> {
         while match self.token() {
             Some(Token::Value(_)) => true,
             Some(Token::Identifier(_)) => true,
+            Some(Token::LBracket) => true,
             _ => false,
         } {
             try!(self.parse_instruction(ctx, ebb));
@@ -878,21 +1222,131 @@ impl<'a> Parser<'a> {
     // ebb-arg ::= * Value(vx) ":" Type(t)
     //
     fn parse_ebb_arg(&mut self, ctx: &mut Context, ebb: Ebb) -> Result<()> {
-        // ebb-arg ::= * Value(vx) ":" Type(t)
+        // ebb-arg ::= * Value(vx) ":" Type(t) [value-location]
         let vx = try!(self.match_value("EBB argument must be a value"));
         let vx_location = self.loc;
-        // ebb-arg ::= Value(vx) * ":" Type(t)
+        // ebb-arg ::= Value(vx) * ":" Type(t) [value-location]
         try!(self.match_token(Token::Colon, "expected ':' after EBB argument"));
-        // ebb-arg ::= Value(vx) ":" * Type(t)
+        // ebb-arg ::= Value(vx) ":" * Type(t) [value-location]
         let t = try!(self.match_type("expected EBB argument type"));
         // Allocate the EBB argument and add the mapping.
         let value = ctx.function.dfg.append_ebb_arg(ebb, t);
-        ctx.map.def_value(vx, value, &vx_location)
+        try!(ctx.map.def_value(vx, value, &vx_location));
+
+        // ebb-arg ::= Value(vx) ":" Type(t) * [ "[" value-location "]" ]
+        if self.optional(Token::LBracket) {
+            let loc = try!(self.parse_value_location(ctx));
+            *ctx.function.locations.ensure(value) = loc;
+            try!(self.match_token(Token::RBracket, "expected ']' after value location"));
+        }
+        Ok(())
+    }
+
+    // Parse a register name like `%x10`.
+    //
+    // Register names are only recognized when there is a unique ISA. Otherwise, the register unit
+    // number is expected, like `%10`.
+    fn match_regunit(&mut self, unique_isa: Option<&TargetIsa>) -> Result<RegUnit> {
+        if let Some(Token::Name(name)) = self.token() {
+            let unit = match unique_isa {
+                Some(isa) => isa.register_info().parse_regunit(name),
+                None => name.parse().ok(),
+            };
+            match unit {
+                Some(unit) => {
+                    self.consume();
+                    Ok(unit)
+                }
+                None => err!(self.loc, "invalid register unit: %{}", name),
+            }
+        } else {
+            err!(self.loc, "expected register name")
+        }
+    }
+
+    // Parse a value location.
+    //
+    // value-location ::= * "-"
+    //                    * RegisterName(reg)
+    //                    * StackSlot(ss)
+    fn parse_value_location(&mut self, ctx: &Context) -> Result<ValueLoc> {
+        match self.token() {
+            Some(Token::Minus) => {
+                self.consume();
+                Ok(ValueLoc::Unassigned)
+            }
+            Some(Token::Name(_)) => self.match_regunit(ctx.unique_isa).map(ValueLoc::Reg),
+            Some(Token::StackSlot(src_num)) => {
+                let ss = try!(ctx.get_ss(src_num, &self.loc));
+                self.consume();
+                Ok(ValueLoc::Stack(ss))
+            }
+            _ => err!(self.loc, "expected value location"),
+        }
+    }
+
+    // Parse an encoding in an instruction annotation.
+    //
+    // encoding ::= * "-"
+    //              * Identifier(recipe) HexSequence(bits)
+    //              * Integer(recipe) HexSequence(bits)
+    //
+    // Recipe names are only recognized when there is a unique ISA.
+    fn parse_encoding(&mut self, unique_isa: Option<&TargetIsa>) -> Result<Encoding> {
+        let recipe = match self.token() {
+            Some(Token::Minus) => {
+                self.consume();
+                return Ok(Encoding::default());
+            }
+            Some(Token::Identifier(name)) => {
+                match unique_isa.and_then(|isa| {
+                    isa.recipe_names().iter().position(|&n| n == name)
+                }) {
+                    Some(recipe) => recipe,
+                    None => return err!(self.loc, "unknown encoding recipe '{}'", name),
+                }
+            }
+            Some(Token::Integer(text)) => {
+                match text.parse() {
+                    Ok(recipe) => recipe,
+                    Err(_) => return err!(self.loc, "invalid recipe number: {}", text),
+                }
+            }
+            _ => return err!(self.loc, "expected instruction encoding or '-'"),
+        };
+        self.consume();
+
+        let bits = match self.token() {
+            Some(Token::HexSequence(text)) => {
+                match u16::from_str_radix(text, 16) {
+                    Ok(bits) => bits,
+                    Err(_) => return err!(self.loc, "invalid encoding bits: #{}", text),
+                }
+            }
+            _ => return err!(self.loc, "expected '#' and encoding bits after recipe"),
+        };
+        self.consume();
+
+        Ok(Encoding::new(recipe as u16, bits))
+    }
+
+    // Parse an instruction annotation with an encoding and value locations for the results.
+    //
+    // inst-annotation ::= * "[" encoding { "," value-location } "]"
+    fn parse_inst_annotation(&mut self, ctx: &Context) -> Result<(Encoding, Vec<ValueLoc>)> {
+        try!(self.match_token(Token::LBracket, "expected '[' before instruction annotation"));
+        let encoding = try!(self.parse_encoding(ctx.unique_isa));
+        let mut locations = Vec::new();
+        while self.optional(Token::Comma) {
+            locations.push(try!(self.parse_value_location(ctx)));
+        }
+        try!(self.match_token(Token::RBracket, "expected ']' after instruction annotation"));
+        Ok((encoding, locations))
     }
 
     // Parse an instruction, append it to `ebb`.
     //
-    // instruction ::= [inst-results "="] Opcode(opc) ["." Type] ...
+    // instruction ::= [inst-annotation] [inst-results "="] Opcode(opc) ["." Type] ...
     // inst-results ::= Value(v) { "," Value(vx) }
     //
     fn parse_instruction(&mut self, ctx: &mut Context, ebb: Ebb) -> Result<()> {
@@ -900,6 +1354,14 @@ impl<'a> Parser<'a> {
         // allocate an instruction number.
         self.gather_comments(NO_INST);
 
+        // instruction ::= * [inst-annotation] [inst-results "="] Opcode(opc) ["." Type] ...
+        let annotation = if self.token() == Some(Token::LBracket) {
+            let loc = self.loc;
+            Some((try!(self.parse_inst_annotation(ctx)), loc))
+        } else {
+            None
+        };
+
         // Result value numbers.
         let mut results = Vec::new();
 
@@ -959,6 +1421,22 @@ impl<'a> Parser<'a> {
                         results.len());
         }
 
+        if let Some(((encoding, locations), loc)) = annotation {
+            *ctx.function.encodings.ensure(inst) = encoding;
+            if !locations.is_empty() {
+                if locations.len() != num_results {
+                    return err!(loc,
+                                "instruction produces {} result values, but {} locations given",
+                                num_results,
+                                locations.len());
+                }
+                let results: Vec<Value> = ctx.function.dfg.inst_results(inst).collect();
+                for (value, location) in results.into_iter().zip(locations) {
+                    *ctx.function.locations.ensure(value) = location;
+                }
+            }
+        }
+
         // If we saw any comments while parsing the instruction, they will have been recorded as
         // belonging to `NO_INST`.
         self.rewrite_last_comment_entities(NO_INST, inst);
@@ -1125,7 +1603,13 @@ impl<'a> Parser<'a> {
                 }
             }
             InstructionFormat::UnaryImmVector => {
-                unimplemented!();
+                InstructionData::UnaryImmVector {
+                    opcode: opcode,
+                    ty: VOID,
+                    data: Box::new(UnaryImmVectorData {
+                        imm: try!(self.match_immvector("expected immediate vector operand")),
+                    }),
+                }
             }
             InstructionFormat::UnarySplit => {
                 InstructionData::UnarySplit {
@@ -1234,6 +1718,26 @@ impl<'a> Parser<'a> {
                     }),
                 }
             }
+            InstructionFormat::BranchIcmp => {
+                let cond = try!(self.match_enum("expected intcc condition code"));
+                try!(self.match_token(Token::Comma, "expected ',' between operands"));
+                let lhs = try!(self.match_value("expected SSA value first operand"));
+                try!(self.match_token(Token::Comma, "expected ',' between operands"));
+                let rhs = try!(self.match_value("expected SSA value second operand"));
+                try!(self.match_token(Token::Comma, "expected ',' between operands"));
+                let ebb_num = try!(self.match_ebb("expected branch destination EBB"));
+                let args = try!(self.parse_opt_value_list());
+                InstructionData::BranchIcmp {
+                    opcode: opcode,
+                    ty: VOID,
+                    data: Box::new(BranchIcmpData {
+                        cond: cond,
+                        args: [lhs, rhs],
+                        destination: ebb_num,
+                        varargs: args,
+                    }),
+                }
+            }
             InstructionFormat::InsertLane => {
                 let lhs = try!(self.match_value("expected SSA value first operand"));
                 try!(self.match_token(Token::Comma, "expected ',' between operands"));
@@ -1284,6 +1788,139 @@ impl<'a> Parser<'a> {
                     args: [lhs, rhs],
                 }
             }
+            InstructionFormat::Load => {
+                let flags = self.optional_memflags();
+                let addr = try!(self.match_value("expected SSA value address"));
+                let offset = try!(self.optional_offset32());
+                InstructionData::Load {
+                    opcode: opcode,
+                    ty: VOID,
+                    flags: flags,
+                    arg: addr,
+                    offset: offset,
+                }
+            }
+            InstructionFormat::Store => {
+                let flags = self.optional_memflags();
+                let arg = try!(self.match_value("expected SSA value operand"));
+                try!(self.match_token(Token::Comma, "expected ',' between operands"));
+                let addr = try!(self.match_value("expected SSA value address"));
+                let offset = try!(self.optional_offset32());
+                InstructionData::Store {
+                    opcode: opcode,
+                    ty: VOID,
+                    flags: flags,
+                    args: [arg, addr],
+                    offset: offset,
+                }
+            }
+            InstructionFormat::AtomicRmw => {
+                let op = try!(self.match_enum("expected atomic operation"));
+                let ordering = try!(self.match_enum("expected memory ordering"));
+                let addr = try!(self.match_value("expected SSA value address"));
+                try!(self.match_token(Token::Comma, "expected ',' between operands"));
+                let arg = try!(self.match_value("expected SSA value operand"));
+                InstructionData::AtomicRmw {
+                    opcode: opcode,
+                    ty: VOID,
+                    op: op,
+                    ordering: ordering,
+                    args: [addr, arg],
+                }
+            }
+            InstructionFormat::AtomicCas => {
+                let ordering = try!(self.match_enum("expected memory ordering"));
+                let addr = try!(self.match_value("expected SSA value address"));
+                try!(self.match_token(Token::Comma, "expected ',' between operands"));
+                let expected = try!(self.match_value("expected SSA value expected operand"));
+                try!(self.match_token(Token::Comma, "expected ',' between operands"));
+                let replacement = try!(self.match_value("expected SSA value replacement operand"));
+                InstructionData::AtomicCas {
+                    opcode: opcode,
+                    ty: VOID,
+                    ordering: ordering,
+                    args: [addr, expected, replacement],
+                }
+            }
+            InstructionFormat::AtomicLoad => {
+                let ordering = try!(self.match_enum("expected memory ordering"));
+                let addr = try!(self.match_value("expected SSA value address"));
+                InstructionData::AtomicLoad {
+                    opcode: opcode,
+                    ty: VOID,
+                    ordering: ordering,
+                    arg: addr,
+                }
+            }
+            InstructionFormat::AtomicStore => {
+                let ordering = try!(self.match_enum("expected memory ordering"));
+                let arg = try!(self.match_value("expected SSA value operand"));
+                try!(self.match_token(Token::Comma, "expected ',' between operands"));
+                let addr = try!(self.match_value("expected SSA value address"));
+                InstructionData::AtomicStore {
+                    opcode: opcode,
+                    ty: VOID,
+                    ordering: ordering,
+                    args: [arg, addr],
+                }
+            }
+            InstructionFormat::Fence => {
+                let ordering = try!(self.match_enum("expected memory ordering"));
+                InstructionData::Fence {
+                    opcode: opcode,
+                    ty: VOID,
+                    ordering: ordering,
+                }
+            }
+            InstructionFormat::StackLoad => {
+                let ss = try!(self.match_ss("expected stack slot number: ss«n»")
+                    .and_then(|num| ctx.get_ss(num, &self.loc)));
+                let offset = try!(self.optional_offset32());
+                InstructionData::StackLoad {
+                    opcode: opcode,
+                    ty: VOID,
+                    stack_slot: ss,
+                    offset: offset,
+                }
+            }
+            InstructionFormat::StackStore => {
+                let arg = try!(self.match_value("expected SSA value operand"));
+                try!(self.match_token(Token::Comma, "expected ',' between operands"));
+                let ss = try!(self.match_ss("expected stack slot number: ss«n»")
+                    .and_then(|num| ctx.get_ss(num, &self.loc)));
+                let offset = try!(self.optional_offset32());
+                InstructionData::StackStore {
+                    opcode: opcode,
+                    ty: VOID,
+                    arg: arg,
+                    stack_slot: ss,
+                    offset: offset,
+                }
+            }
+            InstructionFormat::UnaryGlobalVar => {
+                let gv = try!(self.match_gv("expected global variable")
+                    .and_then(|num| ctx.get_gv(num, &self.loc)));
+                InstructionData::UnaryGlobalVar {
+                    opcode: opcode,
+                    ty: VOID,
+                    global_var: gv,
+                }
+            }
+            InstructionFormat::HeapAddr => {
+                let heap = try!(self.match_heap("expected heap identifier")
+                    .and_then(|num| ctx.get_heap(num, &self.loc)));
+                try!(self.match_token(Token::Comma, "expected ',' between operands"));
+                let arg = try!(self.match_value("expected SSA value heap address"));
+                try!(self.match_token(Token::Comma, "expected ',' between operands"));
+                let size = try!(self.match_uimm32("expected u32 size"));
+                InstructionData::HeapAddr {
+                    opcode: opcode,
+                    ty: VOID,
+                    heap: heap,
+                    arg: arg,
+                    imm: size,
+                }
+            }
             InstructionFormat::Call => {
                 let func_ref = try!(self.match_fn("expected function reference")
                     .and_then(|num| ctx.get_fn(num, &self.loc)));
@@ -1345,7 +1982,7 @@ impl<'a> Parser<'a> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use cretonne::ir::{ArgumentType, ArgumentExtension};
+    use cretonne::ir::{ArgumentType, ArgumentExtension, ArgumentPurpose, ArgumentLoc, ValueLoc};
     use cretonne::ir::types;
     use cretonne::ir::entities::AnyEntity;
     use testfile::{Details, Comment};
@@ -1355,46 +1992,57 @@ mod tests {
     #[test]
     fn argument_type() {
         let mut p = Parser::new("i32 sext");
-        let arg = p.parse_argument_type().unwrap();
+        let arg = p.parse_argument_type(None).unwrap();
         assert_eq!(arg,
                    ArgumentType {
                        value_type: types::I32,
                        extension: ArgumentExtension::Sext,
                        inreg: false,
+                       purpose: ArgumentPurpose::Normal,
+                       location: ArgumentLoc::Unassigned,
                    });
-        let Error { location, message } = p.parse_argument_type().unwrap_err();
+        let Error { location, message } = p.parse_argument_type(None).unwrap_err();
         assert_eq!(location.line_number, 1);
         assert_eq!(message, "expected argument type");
     }
 
     #[test]
     fn signature() {
-        let sig = Parser::new("()").parse_signature().unwrap();
+        let sig = Parser::new("()").parse_signature(None).unwrap();
         assert_eq!(sig.argument_types.len(), 0);
         assert_eq!(sig.return_types.len(), 0);
 
         let sig2 = Parser::new("(i8 inreg uext, f32, f64) -> i32 sext, f64")
-            .parse_signature()
+            .parse_signature(None)
             .unwrap();
         assert_eq!(sig2.to_string(),
                    "(i8 uext inreg, f32, f64) -> i32 sext, f64");
 
+        let sig3 = Parser::new("(i32 [%10], i64 sext [8]) -> f32 [%42] fast")
+            .parse_signature(None)
+            .unwrap();
+        assert_eq!(sig3.to_string(), "(i32 [%10], i64 sext [8]) -> f32 [%42] fast");
+
         // `void` is not recognized as a type by the lexer. It should not appear in files.
-        assert_eq!(Parser::new("() -> void").parse_signature().unwrap_err().to_string(),
+        assert_eq!(Parser::new("() -> void").parse_signature(None).unwrap_err().to_string(),
                    "1: expected argument type");
-        assert_eq!(Parser::new("i8 -> i8").parse_signature().unwrap_err().to_string(),
+        assert_eq!(Parser::new("i8 -> i8").parse_signature(None).unwrap_err().to_string(),
                    "1: expected function signature: ( args... )");
-        assert_eq!(Parser::new("(i8 -> i8").parse_signature().unwrap_err().to_string(),
+        assert_eq!(Parser::new("(i8 -> i8").parse_signature(None).unwrap_err().to_string(),
                    "1: expected ')' after function arguments");
+        assert_eq!(Parser::new("(i8 [%x10])").parse_signature(None).unwrap_err().to_string(),
+                   "1: invalid register unit: %x10");
+        assert_eq!(Parser::new("(i8) slow").parse_signature(None).unwrap_err().to_string(),
+                   "1: unknown calling convention: slow");
     }
 
     #[test]
     fn stack_slot_decl() {
         let (func, _) = Parser::new("function foo() {
-                                       ss3 = stack_slot 13
-                                       ss1 = stack_slot 1
+                                       ss3 = incoming_arg 13
+                                       ss1 = spill 1, align 8, offset -16
                                      }")
-            .parse_function()
+            .parse_function(None)
             .unwrap();
         assert_eq!(func.name.to_string(), "foo");
         let mut iter = func.stack_slots.keys();
@@ -1404,17 +2052,28 @@ mod tests {
         let ss1 = iter.next().unwrap();
         assert_eq!(ss1.to_string(), "ss1");
         assert_eq!(func.stack_slots[ss1].size, 1);
+        assert_eq!(func.stack_slots[ss1].align, Some(8));
+        assert_eq!(func.stack_slots[ss1].offset, Some(-16));
+        assert_eq!(func.stack_slots[ss1].to_string(), "spill 1, align 8, offset -16");
         assert_eq!(iter.next(), None);
 
         // Catch duplicate definitions.
         assert_eq!(Parser::new("function bar() {
-                                    ss1  = stack_slot 13
-                                    ss1  = stack_slot 1
+                                    ss1  = local 13
+                                    ss1  = local 1
                                 }")
-                       .parse_function()
+                       .parse_function(None)
                        .unwrap_err()
                        .to_string(),
                    "3: duplicate stack slot: ss1");
+
+        assert_eq!(Parser::new("function baz() {
+                                    ss0 = local 8, align 3
+                                }")
+                       .parse_function(None)
+                       .unwrap_err()
+                       .to_string(),
+                   "2: stack slot alignment must be a power of two");
     }
 
     #[test]
@@ -1423,7 +2082,7 @@ mod tests {
                                      ebb0:
                                      ebb4(vx3: i32):
                                      }")
-            .parse_function()
+            .parse_function(None)
             .unwrap();
         assert_eq!(func.name.to_string(), "ebbs");
 
@@ -1439,12 +2098,53 @@ mod tests {
         assert_eq!(ebb4_args.next(), None);
     }
 
+    #[test]
+    fn annotations() {
+        let (func, _) = Parser::new("function annot() {
+                                         ss3 = spill 4
+                                     ebb0(vx3: i32 [%10], vx4: i32 [ss3]):
+                                     [1#0c,%7] v1 = iadd vx3, vx3
+                                     [-,-]     v2 = copy v1
+                                     [-]       return
+                                     }")
+            .parse_function(None)
+            .unwrap();
+        let ebb0 = func.layout.entry_block().unwrap();
+        let args: Vec<_> = func.dfg.ebb_args(ebb0).collect();
+        assert_eq!(func.locations[args[0]], ValueLoc::Reg(10));
+        assert_eq!(func.locations[args[1]].to_string(), "ss0");
+
+        let insts: Vec<_> = func.layout.ebb_insts(ebb0).collect();
+        assert_eq!(func.encodings[insts[0]].to_string(), "1#0c");
+        assert_eq!(func.locations[func.dfg.first_result(insts[0])], ValueLoc::Reg(7));
+        assert!(!func.encodings[insts[1]].is_legal());
+        assert_eq!(func.locations[func.dfg.first_result(insts[1])], ValueLoc::Unassigned);
+        assert!(!func.encodings[insts[2]].is_legal());
+
+        assert_eq!(Parser::new("function bad() {
+                                ebb0(vx3: i32):
+                                [R#0c,%7] v1 = iadd vx3, vx3
+                                }")
+                       .parse_function(None)
+                       .unwrap_err()
+                       .to_string(),
+                   "3: unknown encoding recipe 'R'");
+        assert_eq!(Parser::new("function bad() {
+                                ebb0(vx3: i32):
+                                [1#0c,%7,%8] v1 = iadd vx3, vx3
+                                }")
+                       .parse_function(None)
+                       .unwrap_err()
+                       .to_string(),
+                   "3: instruction produces 1 result values, but 2 locations given");
+    }
+
     #[test]
     fn comments() {
         let (func, Details { comments, .. }) =
             Parser::new("; before
                          function comment() { ; decl
-                            ss10  = stack_slot 13 ; stackslot.
+                            ss10  = local 13 ; stackslot.
                             ; Still stackslot.
                             jt10 = jump_table ebb0
                             ; Jumptable
@@ -1452,7 +2152,7 @@ mod tests {
                          trap ; Instruction
                          } ; Trailing.
                          ; More trailing.")
-                .parse_function()
+                .parse_function(None)
                 .unwrap();
         assert_eq!(func.name.to_string(), "comment");
         assert_eq!(comments.len(), 8); // no 'before' comment.
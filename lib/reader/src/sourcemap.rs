@@ -8,7 +8,7 @@
 //! clients.
 
 use std::collections::HashMap;
-use cretonne::ir::{StackSlot, JumpTable, Ebb, Value, SigRef, FuncRef};
+use cretonne::ir::{StackSlot, GlobalVar, Heap, JumpTable, Ebb, Value, SigRef, FuncRef};
 use cretonne::ir::entities::AnyEntity;
 use error::{Result, Location};
 use lexer::split_entity_name;
@@ -19,6 +19,8 @@ pub struct SourceMap {
     values: HashMap<Value, Value>, // vNN, vxNN
     ebbs: HashMap<Ebb, Ebb>, // ebbNN
     stack_slots: HashMap<u32, StackSlot>, // ssNN
+    global_vars: HashMap<u32, GlobalVar>, // gvNN
+    heaps: HashMap<u32, Heap>, // heapNN
     signatures: HashMap<u32, SigRef>, // sigNN
     functions: HashMap<u32, FuncRef>, // fnNN
     jump_tables: HashMap<u32, JumpTable>, // jtNN
@@ -44,6 +46,16 @@ impl SourceMap {
         self.stack_slots.get(&src_num).cloned()
     }
 
+    /// Look up a global variable entity by its source number.
+    pub fn get_gv(&self, src_num: u32) -> Option<GlobalVar> {
+        self.global_vars.get(&src_num).cloned()
+    }
+
+    /// Look up a heap entity by its source number.
+    pub fn get_heap(&self, src_num: u32) -> Option<Heap> {
+        self.heaps.get(&src_num).cloned()
+    }
+
     /// Look up a signature entity by its source number.
     pub fn get_sig(&self, src_num: u32) -> Option<SigRef> {
         self.signatures.get(&src_num).cloned()
@@ -76,6 +88,8 @@ impl SourceMap {
                 }
                 "ebb" => Ebb::with_number(num).and_then(|e| self.get_ebb(e)).map(AnyEntity::Ebb),
                 "ss" => self.get_ss(num).map(AnyEntity::StackSlot),
+                "gv" => self.get_gv(num).map(AnyEntity::GlobalVar),
+                "heap" => self.get_heap(num).map(AnyEntity::Heap),
                 "sig" => self.get_sig(num).map(AnyEntity::SigRef),
                 "fn" => self.get_fn(num).map(AnyEntity::FuncRef),
                 "jt" => self.get_jt(num).map(AnyEntity::JumpTable),
@@ -140,6 +154,8 @@ pub trait MutableSourceMap {
     fn def_value(&mut self, src: Value, entity: Value, loc: &Location) -> Result<()>;
     fn def_ebb(&mut self, src: Ebb, entity: Ebb, loc: &Location) -> Result<()>;
     fn def_ss(&mut self, src_num: u32, entity: StackSlot, loc: &Location) -> Result<()>;
+    fn def_gv(&mut self, src_num: u32, entity: GlobalVar, loc: &Location) -> Result<()>;
+    fn def_heap(&mut self, src_num: u32, entity: Heap, loc: &Location) -> Result<()>;
     fn def_sig(&mut self, src_num: u32, entity: SigRef, loc: &Location) -> Result<()>;
     fn def_fn(&mut self, src_num: u32, entity: FuncRef, loc: &Location) -> Result<()>;
     fn def_jt(&mut self, src_num: u32, entity: JumpTable, loc: &Location) -> Result<()>;
@@ -155,6 +171,8 @@ impl MutableSourceMap for SourceMap {
             values: HashMap::new(),
             ebbs: HashMap::new(),
             stack_slots: HashMap::new(),
+            global_vars: HashMap::new(),
+            heaps: HashMap::new(),
             signatures: HashMap::new(),
             functions: HashMap::new(),
             jump_tables: HashMap::new(),
@@ -186,6 +204,22 @@ impl MutableSourceMap for SourceMap {
         }
     }
 
+    fn def_gv(&mut self, src_num: u32, entity: GlobalVar, loc: &Location) -> Result<()> {
+        if self.global_vars.insert(src_num, entity).is_some() {
+            err!(loc, "duplicate global variable: gv{}", src_num)
+        } else {
+            self.def_entity(entity.into(), loc)
+        }
+    }
+
+    fn def_heap(&mut self, src_num: u32, entity: Heap, loc: &Location) -> Result<()> {
+        if self.heaps.insert(src_num, entity).is_some() {
+            err!(loc, "duplicate heap: heap{}", src_num)
+        } else {
+            self.def_entity(entity.into(), loc)
+        }
+    }
+
     fn def_sig(&mut self, src_num: u32, entity: SigRef, loc: &Location) -> Result<()> {
         if self.signatures.insert(src_num, entity).is_some() {
             err!(loc, "duplicate signature: sig{}", src_num)
@@ -226,7 +260,9 @@ mod tests {
     #[test]
     fn details() {
         let tf = parse_test("function detail() {
-                               ss10 = stack_slot 13
+                               ss10 = local 13
+                               gv5 = vmctx+8
+                               heap3 = static gv5, bound 0x1_0000, guard 0x1000
                                jt10 = jump_table ebb0
                              ebb0(v4: i32, vx7: i32):
                                v10 = iadd v4, vx7
@@ -237,6 +273,8 @@ mod tests {
         assert_eq!(map.lookup_str("v0"), None);
         assert_eq!(map.lookup_str("ss1"), None);
         assert_eq!(map.lookup_str("ss10").unwrap().to_string(), "ss0");
+        assert_eq!(map.lookup_str("gv5").unwrap().to_string(), "gv0");
+        assert_eq!(map.lookup_str("heap3").unwrap().to_string(), "heap0");
         assert_eq!(map.lookup_str("jt10").unwrap().to_string(), "jt0");
         assert_eq!(map.lookup_str("ebb0").unwrap().to_string(), "ebb0");
         assert_eq!(map.lookup_str("v4").unwrap().to_string(), "vx0");
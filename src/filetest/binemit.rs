@@ -0,0 +1,251 @@
+//! Test command for testing the binary machine code emission.
+//!
+//! The `binemit` test command generates binary machine code for the instructions in the input
+//! functions and compares the results to the expected output in `; bin:` comments on the
+//! instructions:
+//!
+//!     [-,%x10]    v3 = iadd v1, v2    ; bin: 00b50533
+//!
+//! Instructions without an encoding annotation are encoded by the target ISA. The register
+//! operands are taken from the value location annotations.
+//!
+//! Branches are relaxed before emission, so the EBB offsets and branch encodings are final. This
+//! also switches instructions to smaller encodings when the value locations allow it, like the
+//! RISC-V compressed instructions.
+//!
+//! Relocations reported by an instruction are checked against `; reloc:` comments on the
+//! instruction, in order. Each relocation is written as the byte offset from the start of the
+//! instruction, the ISA's name for the relocation kind, and the referenced symbol:
+//!
+//!     [-,%x10]    v4 = globalsym_addr.i32 gv0
+//!                 ; bin: 00000537 00050513
+//!                 ; reloc: 0 R_RISCV_HI20 my_global
+//!                 ; reloc: 4 R_RISCV_LO12_I my_global
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt::{Display, Write};
+use cretonne::binemit;
+use cretonne::ir;
+use cretonne::ir::entities::AnyEntity;
+use cton_reader::TestCommand;
+use filetest::subtest::{SubTest, Context, Result};
+use utils::match_directive;
+
+struct TestBinEmit;
+
+pub fn subtest(parsed: &TestCommand) -> Result<Box<SubTest>> {
+    assert_eq!(parsed.command, "binemit");
+    if !parsed.options.is_empty() {
+        Err(format!("No options allowed on {}", parsed))
+    } else {
+        Ok(Box::new(TestBinEmit))
+    }
+}
+
+// Code sink that generates text.
+struct TextSink {
+    // Names of the relocation kinds for the current ISA.
+    reloc_names: &'static [&'static str],
+    // Machine code for the current instruction as hexadecimal words.
+    text: String,
+    // Offset of the current instruction in the function.
+    inst_offset: binemit::CodeOffset,
+    // Current offset in the function.
+    offset: binemit::CodeOffset,
+    // Relocations formatted as `offset name target`, with offsets relative to the instruction.
+    relocs: Vec<String>,
+}
+
+impl TextSink {
+    fn new(reloc_names: &'static [&'static str]) -> TextSink {
+        TextSink {
+            reloc_names: reloc_names,
+            text: String::new(),
+            inst_offset: 0,
+            offset: 0,
+            relocs: Vec::new(),
+        }
+    }
+
+    // Prepare for emitting a new instruction at `offset`.
+    fn begin_inst(&mut self, offset: binemit::CodeOffset) {
+        self.text.clear();
+        self.inst_offset = offset;
+        self.offset = offset;
+        self.relocs.clear();
+    }
+
+    fn add_reloc<T: Display>(&mut self, reloc: binemit::Reloc, target: T) {
+        let name = self.reloc_names.get(reloc.0 as usize).cloned().unwrap_or("<unknown>");
+        self.relocs.push(format!("{} {} {}", self.offset - self.inst_offset, name, target));
+    }
+}
+
+impl binemit::CodeSink for TextSink {
+    fn offset(&self) -> binemit::CodeOffset {
+        self.offset
+    }
+
+    fn put1(&mut self, x: u8) {
+        write!(self.text, "{:02x} ", x).unwrap();
+        self.offset += 1;
+    }
+
+    fn put2(&mut self, x: u16) {
+        write!(self.text, "{:04x} ", x).unwrap();
+        self.offset += 2;
+    }
+
+    fn put4(&mut self, x: u32) {
+        write!(self.text, "{:08x} ", x).unwrap();
+        self.offset += 4;
+    }
+
+    fn put8(&mut self, x: u64) {
+        write!(self.text, "{:016x} ", x).unwrap();
+        self.offset += 8;
+    }
+
+    fn reloc_ebb(&mut self, reloc: binemit::Reloc, ebb: ir::Ebb) {
+        self.add_reloc(reloc, ebb);
+    }
+
+    fn reloc_external(&mut self, reloc: binemit::Reloc, name: &ir::FunctionName) {
+        self.add_reloc(reloc, name);
+    }
+
+    fn reloc_jt(&mut self, reloc: binemit::Reloc, jt: ir::JumpTable) {
+        self.add_reloc(reloc, jt);
+    }
+}
+
+impl SubTest for TestBinEmit {
+    fn name(&self) -> Cow<str> {
+        Cow::from("binemit")
+    }
+
+    fn is_mutating(&self) -> bool {
+        true
+    }
+
+    fn needs_isa(&self) -> bool {
+        true
+    }
+
+    fn run(&self, func: Cow<ir::Function>, context: &Context) -> Result<()> {
+        let isa = context.isa.expect("binary emission needs an ISA");
+        let mut func = func.into_owned();
+
+        // Give an encoding to any instruction that doesn't already have one.
+        for ebb in func.layout.ebbs() {
+            for inst in func.layout.ebb_insts(ebb) {
+                if !func.encodings.get(inst).map_or(false, |e| e.is_legal()) {
+                    match isa.encode(&func.dfg, &func.dfg[inst]) {
+                        Ok(enc) => *func.encodings.ensure(inst) = enc,
+                        Err(_) => {
+                            return Err(format!("{} can't be encoded: {}",
+                                               inst,
+                                               func.dfg[inst].opcode()))
+                        }
+                    }
+                }
+            }
+        }
+
+        // Collect all of the 'bin:' and 'reloc:' directives on instructions.
+        let mut bins = HashMap::new();
+        let mut relocs = HashMap::new();
+        for comment in &context.details.comments {
+            if let Some(want) = match_directive(comment.text, "reloc:") {
+                match comment.entity {
+                    AnyEntity::Inst(inst) => {
+                        relocs.entry(inst).or_insert_with(Vec::new).push(want);
+                    }
+                    _ => {
+                        return Err(format!("'reloc:' directive on non-inst {}: {}",
+                                           comment.entity,
+                                           comment.text))
+                    }
+                }
+            }
+            if let Some(want) = match_directive(comment.text, "bin:") {
+                match comment.entity {
+                    AnyEntity::Inst(inst) => {
+                        if let Some(prev) = bins.insert(inst, want) {
+                            return Err(format!("multiple 'bin:' directives on {}: '{}' and '{}'",
+                                               inst,
+                                               prev,
+                                               want));
+                        }
+                    }
+                    _ => {
+                        return Err(format!("'bin:' directive on non-inst {}: {}",
+                                           comment.entity,
+                                           comment.text))
+                    }
+                }
+            }
+        }
+        if bins.is_empty() {
+            return Err("No 'bin:' directives found".to_string());
+        }
+
+        // Relax branches and compute EBB offsets.
+        binemit::relax_branches(&mut func, isa);
+
+        // Now emit all the annotated instructions. The instructions that aren't annotated are
+        // skipped, but they still take up space in the function.
+        let sizing = isa.recipe_sizing();
+        let mut sink = TextSink::new(isa.reloc_names());
+        for ebb in func.layout.ebbs() {
+            let mut offset = func.offsets[ebb];
+            for inst in func.layout.ebb_insts(ebb) {
+                let enc = func.encodings[inst];
+                let size = sizing[enc.recipe()].bytes as binemit::CodeOffset;
+                let inst_offset = offset;
+                offset += size;
+
+                let want = match bins.get(&inst) {
+                    Some(&want) => want,
+                    None => {
+                        if relocs.contains_key(&inst) {
+                            return Err(format!("'reloc:' directive on {} without 'bin:'", inst));
+                        }
+                        continue;
+                    }
+                };
+                sink.begin_inst(inst_offset);
+                isa.emit_inst(&func, inst, &mut sink);
+                if sink.offset != offset {
+                    return Err(format!("Emitted {} bytes for {} ({}), but the {} encoding has {}",
+                                       sink.offset - inst_offset,
+                                       inst,
+                                       func.dfg[inst].opcode(),
+                                       isa.display_enc(enc),
+                                       size));
+                }
+                let have = sink.text.trim();
+                if have != want {
+                    return Err(format!("Bad machine code for {} ({})\nWant: {}\nGot:  {}",
+                                       inst,
+                                       func.dfg[inst].opcode(),
+                                       want,
+                                       have));
+                }
+
+                // Every relocation must be matched by a 'reloc:' directive, in order.
+                let want_relocs = relocs.get(&inst).map_or(&[][..], |v| &v[..]);
+                if sink.relocs != want_relocs {
+                    return Err(format!("Bad relocations for {} ({})\nWant: {:?}\nGot:  {:?}",
+                                       inst,
+                                       func.dfg[inst].opcode(),
+                                       want_relocs,
+                                       sink.relocs));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
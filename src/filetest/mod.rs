@@ -18,6 +18,9 @@ mod concurrent;
 mod domtree;
 mod verifier;
 mod legalizer;
+mod regalloc;
+mod prologue_epilogue;
+mod binemit;
 
 /// The result of running the test in a file.
 pub type TestResult = Result<time::Duration, String>;
@@ -57,6 +60,9 @@ fn new_subtest(parsed: &TestCommand) -> subtest::Result<Box<subtest::SubTest>> {
         "domtree" => domtree::subtest(parsed),
         "verifier" => verifier::subtest(parsed),
         "legalizer" => legalizer::subtest(parsed),
+        "regalloc" => regalloc::subtest(parsed),
+        "prologue-epilogue" => prologue_epilogue::subtest(parsed),
+        "binemit" => binemit::subtest(parsed),
         _ => Err(format!("unknown test command '{}'", parsed.command)),
     }
 }
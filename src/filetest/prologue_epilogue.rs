@@ -0,0 +1,52 @@
+//! Test command for testing prologue and epilogue insertion.
+//!
+//! The `test prologue-epilogue` test command runs each function through `legalize_function()`,
+//! the register allocator, and `insert_prologue_epilogue()`, then sends the result to filecheck.
+//! The stack slot offsets assigned by the frame layout appear in the function preamble.
+
+use std::borrow::Cow;
+use cretonne::{legalize_function, allocate_registers, insert_prologue_epilogue, write_function};
+use cretonne::cfg::ControlFlowGraph;
+use cretonne::dominator_tree::DominatorTree;
+use cretonne::ir::Function;
+use cton_reader::TestCommand;
+use filetest::subtest::{SubTest, Context, Result, run_filecheck};
+
+struct TestPrologueEpilogue;
+
+pub fn subtest(parsed: &TestCommand) -> Result<Box<SubTest>> {
+    assert_eq!(parsed.command, "prologue-epilogue");
+    if !parsed.options.is_empty() {
+        Err(format!("No options allowed on {}", parsed))
+    } else {
+        Ok(Box::new(TestPrologueEpilogue))
+    }
+}
+
+impl SubTest for TestPrologueEpilogue {
+    fn name(&self) -> Cow<str> {
+        Cow::from("prologue-epilogue")
+    }
+
+    fn is_mutating(&self) -> bool {
+        true
+    }
+
+    fn needs_isa(&self) -> bool {
+        true
+    }
+
+    fn run(&self, func: Cow<Function>, context: &Context) -> Result<()> {
+        let mut func = func.into_owned();
+        let isa = context.isa.expect("prologue-epilogue needs an ISA");
+        legalize_function(&mut func, isa);
+        let cfg = ControlFlowGraph::new(&func);
+        let domtree = DominatorTree::new(&cfg);
+        allocate_registers(&mut func, &cfg, &domtree, isa);
+        insert_prologue_epilogue(&mut func, isa);
+
+        let mut text = String::new();
+        try!(write_function(&mut text, &func, Some(isa)).map_err(|e| e.to_string()));
+        run_filecheck(&text, context)
+    }
+}
@@ -0,0 +1,51 @@
+//! Test command for testing the register allocator.
+//!
+//! The `test regalloc` test command runs each function through `legalize_function()` and the
+//! register allocator, then sends the result to filecheck. The value locations assigned by the
+//! register allocator appear in the encoding annotations and EBB headers.
+
+use std::borrow::Cow;
+use cretonne::{legalize_function, allocate_registers, write_function};
+use cretonne::cfg::ControlFlowGraph;
+use cretonne::dominator_tree::DominatorTree;
+use cretonne::ir::Function;
+use cton_reader::TestCommand;
+use filetest::subtest::{SubTest, Context, Result, run_filecheck};
+
+struct TestRegalloc;
+
+pub fn subtest(parsed: &TestCommand) -> Result<Box<SubTest>> {
+    assert_eq!(parsed.command, "regalloc");
+    if !parsed.options.is_empty() {
+        Err(format!("No options allowed on {}", parsed))
+    } else {
+        Ok(Box::new(TestRegalloc))
+    }
+}
+
+impl SubTest for TestRegalloc {
+    fn name(&self) -> Cow<str> {
+        Cow::from("regalloc")
+    }
+
+    fn is_mutating(&self) -> bool {
+        true
+    }
+
+    fn needs_isa(&self) -> bool {
+        true
+    }
+
+    fn run(&self, func: Cow<Function>, context: &Context) -> Result<()> {
+        let mut func = func.into_owned();
+        let isa = context.isa.expect("register allocator needs an ISA");
+        legalize_function(&mut func, isa);
+        let cfg = ControlFlowGraph::new(&func);
+        let domtree = DominatorTree::new(&cfg);
+        allocate_registers(&mut func, &cfg, &domtree, isa);
+
+        let mut text = String::new();
+        try!(write_function(&mut text, &func, Some(isa)).map_err(|e| e.to_string()));
+        run_filecheck(&text, context)
+    }
+}